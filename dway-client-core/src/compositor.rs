@@ -1,10 +1,165 @@
-use bevy::{
+use bevy::prelude::*;
+use dway_server::{
+    wl::{compositor::SubsurfaceList, surface::WlSurface},
+    xdg::{toplevel::DWayToplevel, DWayWindow, PopupList},
+};
+use dway_util::{
+    color::{ImageDescription, OutputImageDescription},
+    output::FullscreenContent,
+    scanout::{ScanoutBuffer, ScanoutCandidate, ScanoutCandidates, ScanoutState, ScreenOverlay},
+};
+
+use crate::{
+    effect::{self, DirectScanout},
+    navigation::windowstack::WindowStack,
     prelude::*,
+    screen::{Screen, ScreenWindowList},
 };
 
+/// Offer the topmost window of each screen to the backend if it is a fullscreen window whose
+/// buffer covers the screen, without popups, subsurfaces or compositor UI which need composition.
+/// The buffer must be in the image description of the output, planes don't convert colors.
+pub fn update_scanout_candidates(
+    screen_query: Query<
+        (
+            Entity,
+            &Window,
+            &GlobalGeometry,
+            Option<&ScreenWindowList>,
+            Option<&ScanoutCandidates>,
+            Option<&OutputImageDescription>,
+            Has<ScreenOverlay>,
+        ),
+        With<Screen>,
+    >,
+    window_query: Query<
+        (
+            &GlobalGeometry,
+            &WlSurface,
+            &DWayToplevel,
+            Option<&PopupList>,
+            Option<&SubsurfaceList>,
+        ),
+        With<DWayWindow>,
+    >,
+    buffer_query: Query<&ScanoutBuffer>,
+    window_stack: Res<WindowStack>,
+    mut commands: Commands,
+) {
+    for (screen_entity, window, screen_geo, window_list, old_candidates, description, overlay) in
+        &screen_query
+    {
+        let candidate = window_list.filter(|_| !overlay).and_then(|window_list| {
+            let (window_entity, (geo, surface, toplevel, popups, subsurfaces)) = window_stack
+                .list
+                .iter()
+                .filter(|w| window_list.contains(**w))
+                .filter_map(|w| Some((*w, window_query.get(*w).ok()?)))
+                .find(|(_, (_, _, toplevel, ..))| !toplevel.min)?;
+            if !toplevel.fullscreen
+                || popups.is_some_and(|l| l.iter().next().is_some())
+                || subsurfaces.is_some_and(|l| l.iter().next().is_some())
+            {
+                return None;
+            }
+            let srgb = ImageDescription::srgb();
+            let surface_description = surface.commited.image_description.as_ref();
+            if surface_description.unwrap_or(&srgb) != description.map_or(&srgb, |d| &d.0) {
+                return None;
+            }
+            let buffer_entity = surface.commited.buffer?;
+            let buffer = buffer_query.get(buffer_entity).ok()?;
+
+            let image_rect = surface.image_rect();
+            let buffer_scale = surface.commited.scale.unwrap_or(1).max(1);
+            let scale = window.resolution.scale_factor();
+            let pos = (geo.min + image_rect.min - screen_geo.min).as_vec2() * scale;
+            let size = (image_rect.size() / buffer_scale).as_vec2() * scale;
+            let dest = IRect::from_corners(pos.round().as_ivec2(), (pos + size).round().as_ivec2());
+            // planes are not asked to scale the buffer
+            if dest.size() != buffer.size {
+                return None;
+            }
+            Some(ScanoutCandidate {
+                window: window_entity,
+                buffer_entity,
+                buffer: buffer.clone(),
+                src: Rect::from_corners(Vec2::ZERO, buffer.size.as_vec2()),
+                dest,
+            })
+        });
+
+        match candidate {
+            Some(candidate) => {
+                commands
+                    .entity(screen_entity)
+                    .insert(ScanoutCandidates(vec![candidate]));
+            }
+            None => {
+                if old_candidates.is_some_and(|c| !c.0.is_empty()) {
+                    commands
+                        .entity(screen_entity)
+                        .insert(ScanoutCandidates::default());
+                }
+            }
+        }
+    }
+}
+
+/// Mark the screens whose topmost window is fullscreen, for outputs that only enable variable
+/// refresh rate for fullscreen windows.
+pub fn update_fullscreen_content(
+    screen_query: Query<(Entity, Option<&ScreenWindowList>, Has<FullscreenContent>), With<Screen>>,
+    window_query: Query<&DWayToplevel, With<DWayWindow>>,
+    window_stack: Res<WindowStack>,
+    mut commands: Commands,
+) {
+    for (screen_entity, window_list, marked) in &screen_query {
+        let fullscreen = window_list.is_some_and(|window_list| {
+            window_stack
+                .list
+                .iter()
+                .filter(|w| window_list.contains(**w))
+                .filter_map(|w| window_query.get(*w).ok())
+                .find(|toplevel| !toplevel.min)
+                .is_some_and(|toplevel| toplevel.fullscreen)
+        });
+        if fullscreen && !marked {
+            commands.entity(screen_entity).insert(FullscreenContent);
+        } else if !fullscreen && marked {
+            commands.entity(screen_entity).remove::<FullscreenContent>();
+        }
+    }
+}
+
+pub fn update_direct_scanout(
+    screen_query: Query<&ScanoutState>,
+    window_query: Query<(Entity, Has<DirectScanout>), With<DWayWindow>>,
+    mut commands: Commands,
+) {
+    for (entity, direct_scanout) in &window_query {
+        let scanned_out = screen_query.iter().any(|state| state.contains(entity));
+        if scanned_out && !direct_scanout {
+            commands.entity(entity).insert(DirectScanout);
+        } else if !scanned_out && direct_scanout {
+            commands.entity(entity).remove::<DirectScanout>();
+        }
+    }
+}
+
 pub struct CompositorPlugin;
 impl Plugin for CompositorPlugin {
-    fn build(&self, _app: &mut App) {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ScreenOverlay>();
+        app.add_systems(
+            PostUpdate,
+            (update_scanout_candidates, update_fullscreen_content),
+        );
+        app.add_systems(
+            PreUpdate,
+            update_direct_scanout
+                .before(effect::update_window_effects)
+                .in_set(DWayClientSystem::UpdateWindow),
+        );
     }
 }
-
@@ -1,4 +1,4 @@
-use bevy::platform::collections::HashMap;
+use bevy::{platform::collections::HashMap, prelude::Resource};
 use smart_default::SmartDefault;
 
 structstruck::strike! {
@@ -10,7 +10,8 @@ structstruck::strike! {
 }
 
 structstruck::strike! {
-    #[derive(Clone, Debug, SmartDefault)]
+    /// Replacing the resource reloads the configuration.
+    #[derive(Clone, Debug, SmartDefault, Resource)]
     pub struct Config {
         pub screens: Vec< #[derive(Clone, Debug, SmartDefault)] pub struct Screen {
             pub name: String,
@@ -50,12 +51,10 @@ structstruck::strike! {
                 pub focusable: bool,
                 pub screen: Option<String>,
                 pub workspace: Option<String>,
-                #[default(true)]
-                pub blur: bool,
-                #[default(true)]
-                pub rounned_rect: bool,
-                #[default(true)]
-                pub opacity: bool,
+                /// the effects of the matching windows, `None` keeps the default
+                pub blur: Option<bool>,
+                pub rounned_rect: Option<bool>,
+                pub opacity: Option<bool>,
                 pub op_create: Option<DWayScript>,
                 pub on_destroy: Option<DWayScript>,
             },
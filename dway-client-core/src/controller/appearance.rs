@@ -0,0 +1,246 @@
+use std::{sync::Arc, time::Duration};
+
+use dbus::{
+    arg::{RefArg, Variant},
+    message::MatchRule,
+    nonblock::{Proxy, SyncConnection},
+};
+use dway_util::tokio::TokioRuntime;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use super::dbus::DBusSettings;
+use crate::prelude::*;
+
+pub const PORTAL_DBUS_DEST: &str = "org.freedesktop.portal.Desktop";
+pub const PORTAL_DBUS_PATH: &str = "/org/freedesktop/portal/desktop";
+pub const PORTAL_SETTINGS_DBUS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+pub const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+pub const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The value of the freedesktop `color-scheme` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum ColorSchemePreference {
+    #[default]
+    NoPreference,
+    PreferDark,
+    PreferLight,
+}
+
+impl ColorSchemePreference {
+    pub fn from_value(value: &dyn RefArg) -> Self {
+        match value.as_u64() {
+            Some(1) => Self::PreferDark,
+            Some(2) => Self::PreferLight,
+            _ => Self::NoPreference,
+        }
+    }
+
+    pub fn is_dark(&self) -> bool {
+        *self == Self::PreferDark
+    }
+}
+
+enum Request {
+    Updated(ColorSchemePreference),
+}
+
+async fn read_color_scheme(conn: Arc<SyncConnection>) -> Result<ColorSchemePreference> {
+    let proxy = Proxy::new(PORTAL_DBUS_DEST, PORTAL_DBUS_PATH, DBUS_TIMEOUT, conn);
+    let (value,): (Variant<Box<dyn RefArg>>,) = match proxy
+        .method_call(
+            PORTAL_SETTINGS_DBUS_INTERFACE,
+            "ReadOne",
+            (APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY),
+        )
+        .await
+    {
+        Ok(r) => r,
+        // `ReadOne` was added in version 2 of the interface, `Read` wraps the value in
+        // another variant
+        Err(_) => {
+            proxy
+                .method_call(
+                    PORTAL_SETTINGS_DBUS_INTERFACE,
+                    "Read",
+                    (APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY),
+                )
+                .await?
+        }
+    };
+    Ok(ColorSchemePreference::from_value(&value))
+}
+
+async fn create_dbus_connection(
+    tokio: tokio::runtime::Handle,
+    settings: DBusSettings,
+    tx: Sender<Request>,
+) -> Result<()> {
+    let (tokio_handle, conn) = settings.connect_sync()?;
+    let _handle = tokio.spawn(async {
+        let err = tokio_handle.await;
+        panic!("Lost connection to D-Bus: {}", err);
+    });
+
+    let tx2 = tx.clone();
+    let signals = conn
+        .add_match(MatchRule::new_signal(
+            PORTAL_SETTINGS_DBUS_INTERFACE,
+            "SettingChanged",
+        ))
+        .await?
+        .cb(
+            move |_, (namespace, key, value): (String, String, Variant<Box<dyn RefArg>>)| {
+                if namespace == APPEARANCE_NAMESPACE && key == COLOR_SCHEME_KEY {
+                    let value = ColorSchemePreference::from_value(&value);
+                    if tx2.try_send(Request::Updated(value)).is_err() {
+                        warn!("failed to send the color scheme");
+                    }
+                }
+                true
+            },
+        );
+
+    match read_color_scheme(conn.clone()).await {
+        Ok(value) => tx.send(Request::Updated(value)).await?,
+        Err(e) => debug!("failed to read the color scheme: {e}"),
+    }
+
+    tx.closed().await;
+    drop(signals);
+    Ok(())
+}
+
+/// Follows the freedesktop appearance settings through the settings portal.
+#[derive(Debug, Resource)]
+pub struct AppearanceController {
+    rx: Receiver<Request>,
+    pub color_scheme: ColorSchemePreference,
+}
+
+impl FromWorld for AppearanceController {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<DBusSettings>().cloned().unwrap_or_default();
+        let tokio = world.non_send_resource::<TokioRuntime>();
+        let (request_tx, request_rx) = channel(16);
+        let handle = tokio.handle().clone();
+        tokio.spawn(async {
+            match create_dbus_connection(handle, settings, request_tx).await {
+                Ok(()) => {
+                    info!("settings portal client exit");
+                }
+                Err(e) => {
+                    error!("settings portal client exit with an error: {e}");
+                }
+            }
+        });
+        Self {
+            rx: request_rx,
+            color_scheme: Default::default(),
+        }
+    }
+}
+
+pub fn update_appearance_controller(mut appearance_controller: ResMut<AppearanceController>) {
+    while let Ok(request) = appearance_controller.bypass_change_detection().rx.try_recv() {
+        match request {
+            Request::Updated(color_scheme) => {
+                if appearance_controller.color_scheme != color_scheme {
+                    appearance_controller.color_scheme = color_scheme;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Instant,
+    };
+
+    use dbus::{
+        channel::{MatchingReceiver, Sender as _},
+        Message,
+    };
+    use dbus_crossroads::{Crossroads, MethodErr};
+
+    use super::*;
+    use crate::controller::dbus::test::PrivateSessionBus;
+
+    #[test]
+    fn test_settings_portal_client() {
+        let bus = PrivateSessionBus::spawn();
+        let conn = bus.connect();
+        conn.request_name(PORTAL_DBUS_DEST, false, true, false)
+            .unwrap();
+        let mut cr = Crossroads::new();
+        let settings = cr.register(PORTAL_SETTINGS_DBUS_INTERFACE, |b| {
+            b.method(
+                "ReadOne",
+                ("namespace", "key"),
+                ("value",),
+                |_, _, (namespace, key): (String, String)| {
+                    if namespace == APPEARANCE_NAMESPACE && key == COLOR_SCHEME_KEY {
+                        Ok((Variant(1u32),))
+                    } else {
+                        Err(MethodErr::failed("unknown setting"))
+                    }
+                },
+            );
+        });
+        cr.insert(PORTAL_DBUS_PATH, &[settings], ());
+        conn.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |msg, conn| {
+                cr.handle_message(msg, conn).unwrap();
+                true
+            }),
+        );
+        let emit = Arc::new(AtomicBool::new(false));
+        let emit2 = emit.clone();
+        let handle = std::thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                conn.process(Duration::from_millis(50)).unwrap();
+                if emit2.swap(false, Ordering::SeqCst) {
+                    let signal = Message::new_signal(
+                        PORTAL_DBUS_PATH,
+                        PORTAL_SETTINGS_DBUS_INTERFACE,
+                        "SettingChanged",
+                    )
+                    .unwrap()
+                    .append3(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY, Variant(2u32));
+                    conn.send(signal).unwrap();
+                }
+            }
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (request_tx, mut request_rx) = channel(16);
+        runtime.spawn(create_dbus_connection(
+            runtime.handle().clone(),
+            bus.settings(),
+            request_tx,
+        ));
+
+        let Some(Request::Updated(color_scheme)) = request_rx.blocking_recv() else {
+            panic!("color scheme not received");
+        };
+        assert_eq!(color_scheme, ColorSchemePreference::PreferDark);
+
+        emit.store(true, Ordering::SeqCst);
+        let Some(Request::Updated(color_scheme)) = request_rx.blocking_recv() else {
+            panic!("color scheme change not received");
+        };
+        assert_eq!(color_scheme, ColorSchemePreference::PreferLight);
+
+        drop(request_rx);
+        handle.join().unwrap();
+    }
+}
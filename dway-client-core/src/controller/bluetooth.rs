@@ -0,0 +1,469 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use dbus::{
+    arg::PropMap,
+    message::{MatchRule, MessageType},
+    nonblock::{stdintf::org_freedesktop_dbus::Properties, Proxy, SyncConnection},
+    Path,
+};
+use dway_util::tokio::TokioRuntime;
+use tokio::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Notify,
+};
+
+use super::dbus::{props, DBusSettings};
+use crate::prelude::*;
+
+pub const BLUEZ_DBUS_DEST: &str = "org.bluez";
+pub const BLUEZ_ADAPTER_DBUS_INTERFACE: &str = "org.bluez.Adapter1";
+pub const BLUEZ_DEVICE_DBUS_INTERFACE: &str = "org.bluez.Device1";
+pub const BLUEZ_BATTERY_DBUS_INTERFACE: &str = "org.bluez.Battery1";
+
+/// Pairing waits for the user to confirm on the remote device.
+const DBUS_TIMEOUT: Duration = Duration::from_secs(30);
+const REFRESH_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct BluetoothAdapter {
+    pub path: String,
+    pub address: String,
+    pub name: String,
+    pub powered: bool,
+    pub discovering: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct BluetoothDevice {
+    pub path: String,
+    pub adapter: String,
+    pub address: String,
+    pub name: String,
+    /// the freedesktop icon name, like `audio-headset`
+    pub icon: String,
+    pub paired: bool,
+    pub trusted: bool,
+    pub connected: bool,
+    /// battery level in percent
+    pub battery: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct BluetoothState {
+    pub adapters: Vec<BluetoothAdapter>,
+    pub devices: Vec<BluetoothDevice>,
+}
+
+type ManagedObjects = HashMap<Path<'static>, HashMap<String, PropMap>>;
+
+impl BluetoothState {
+    fn from_managed_objects(objects: &ManagedObjects) -> Self {
+        let mut state = Self::default();
+        for (path, interfaces) in objects {
+            if let Some(adapter) = interfaces.get(BLUEZ_ADAPTER_DBUS_INTERFACE) {
+                state.adapters.push(BluetoothAdapter {
+                    path: path.to_string(),
+                    address: props::string(adapter, "Address"),
+                    name: props::string(adapter, "Alias"),
+                    powered: props::boolean(adapter, "Powered"),
+                    discovering: props::boolean(adapter, "Discovering"),
+                });
+            }
+            if let Some(device) = interfaces.get(BLUEZ_DEVICE_DBUS_INTERFACE) {
+                state.devices.push(BluetoothDevice {
+                    path: path.to_string(),
+                    adapter: props::string(device, "Adapter"),
+                    address: props::string(device, "Address"),
+                    name: props::string(device, "Alias"),
+                    icon: props::string(device, "Icon"),
+                    paired: props::boolean(device, "Paired"),
+                    trusted: props::boolean(device, "Trusted"),
+                    connected: props::boolean(device, "Connected"),
+                    battery: interfaces
+                        .get(BLUEZ_BATTERY_DBUS_INTERFACE)
+                        .and_then(|battery| props::number(battery, "Percentage"))
+                        .map(|p| p as u8),
+                });
+            }
+        }
+        state.adapters.sort_by(|a, b| a.path.cmp(&b.path));
+        // connected first, then paired, then the ones found by discovery
+        state.devices.sort_by(|a, b| {
+            (!a.connected, !a.paired, &a.name).cmp(&(!b.connected, !b.paired, &b.name))
+        });
+        state
+    }
+
+    pub fn adapter(&self) -> Option<&BluetoothAdapter> {
+        self.adapters.first()
+    }
+
+    pub fn paired_devices(&self) -> impl Iterator<Item = &BluetoothDevice> {
+        self.devices.iter().filter(|d| d.paired)
+    }
+}
+
+#[derive(Message, Debug, Clone, Reflect)]
+pub enum BluetoothRequest {
+    SetPowered { adapter: String, powered: bool },
+    StartDiscovery { adapter: String },
+    StopDiscovery { adapter: String },
+    Connect { device: String },
+    Disconnect { device: String },
+    Pair { device: String },
+    SetTrusted { device: String, trusted: bool },
+    /// Forget a device.
+    Remove { device: String },
+}
+
+enum Request {
+    Updated(BluetoothState),
+}
+enum Response {
+    Request(BluetoothRequest),
+}
+
+struct BluetoothWorker {
+    conn: Arc<SyncConnection>,
+    tx: Sender<Request>,
+    state: Mutex<BluetoothState>,
+}
+
+impl BluetoothWorker {
+    fn proxy<'a>(&self, path: impl Into<Path<'a>>) -> Proxy<'a, Arc<SyncConnection>> {
+        Proxy::new(BLUEZ_DBUS_DEST, path, DBUS_TIMEOUT, self.conn.clone())
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        let (objects,): (ManagedObjects,) = self
+            .proxy("/")
+            .method_call("org.freedesktop.DBus.ObjectManager", "GetManagedObjects", ())
+            .await?;
+        let state = BluetoothState::from_managed_objects(&objects);
+        let changed = {
+            let mut current = self.state.lock().unwrap();
+            let changed = *current != state;
+            *current = state.clone();
+            changed
+        };
+        if changed {
+            self.tx.send(Request::Updated(state)).await?;
+        }
+        Ok(())
+    }
+
+    async fn call(&self, path: String, interface: &str, method: &str) -> Result<()> {
+        self.proxy(path)
+            .method_call::<(), _, _, _>(interface, method, ())
+            .await?;
+        Ok(())
+    }
+
+    async fn execute(&self, request: BluetoothRequest) -> Result<()> {
+        match request {
+            BluetoothRequest::SetPowered { adapter, powered } => {
+                self.proxy(adapter)
+                    .set(BLUEZ_ADAPTER_DBUS_INTERFACE, "Powered", powered)
+                    .await?;
+            }
+            BluetoothRequest::StartDiscovery { adapter } => {
+                self.call(adapter, BLUEZ_ADAPTER_DBUS_INTERFACE, "StartDiscovery")
+                    .await?;
+            }
+            BluetoothRequest::StopDiscovery { adapter } => {
+                self.call(adapter, BLUEZ_ADAPTER_DBUS_INTERFACE, "StopDiscovery")
+                    .await?;
+            }
+            BluetoothRequest::Connect { device } => {
+                self.call(device, BLUEZ_DEVICE_DBUS_INTERFACE, "Connect")
+                    .await?;
+            }
+            BluetoothRequest::Disconnect { device } => {
+                self.call(device, BLUEZ_DEVICE_DBUS_INTERFACE, "Disconnect")
+                    .await?;
+            }
+            BluetoothRequest::Pair { device } => {
+                self.call(device.clone(), BLUEZ_DEVICE_DBUS_INTERFACE, "Pair")
+                    .await?;
+                // a paired device is trusted so that it can reconnect by itself
+                self.proxy(device)
+                    .set(BLUEZ_DEVICE_DBUS_INTERFACE, "Trusted", true)
+                    .await?;
+            }
+            BluetoothRequest::SetTrusted { device, trusted } => {
+                self.proxy(device)
+                    .set(BLUEZ_DEVICE_DBUS_INTERFACE, "Trusted", trusted)
+                    .await?;
+            }
+            BluetoothRequest::Remove { device } => {
+                let adapter = self
+                    .state
+                    .lock()
+                    .unwrap()
+                    .devices
+                    .iter()
+                    .find(|d| d.path == device)
+                    .map(|d| d.adapter.clone())
+                    .ok_or_else(|| anyhow!("unknown bluetooth device: {device}"))?;
+                self.proxy(adapter)
+                    .method_call::<(), _, _, _>(
+                        BLUEZ_ADAPTER_DBUS_INTERFACE,
+                        "RemoveDevice",
+                        (Path::new(device).map_err(|e| anyhow!("{e}"))?,),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn create_dbus_connection(
+    tokio: tokio::runtime::Handle,
+    settings: DBusSettings,
+    mut rx: Receiver<Response>,
+    tx: Sender<Request>,
+) -> Result<()> {
+    let (tokio_handle, conn) = settings.connect_system_sync()?;
+    let _handle = tokio.spawn(async {
+        let err = tokio_handle.await;
+        panic!("Lost connection to D-Bus: {}", err);
+    });
+
+    let worker = Arc::new(BluetoothWorker {
+        conn: conn.clone(),
+        tx,
+        state: Default::default(),
+    });
+
+    let changed = Arc::new(Notify::new());
+    let changed2 = changed.clone();
+    let signals = conn
+        .add_match(
+            MatchRule::new()
+                .with_type(MessageType::Signal)
+                .with_sender(BLUEZ_DBUS_DEST),
+        )
+        .await?
+        .msg_cb(move |_| {
+            changed2.notify_one();
+            true
+        });
+
+    let worker2 = worker.clone();
+    tokio.spawn(async move {
+        loop {
+            if let Err(e) = worker2.refresh().await {
+                debug!("failed to refresh bluetooth state: {e}");
+            }
+            changed.notified().await;
+            tokio::time::sleep(REFRESH_DELAY).await;
+        }
+    });
+
+    while let Some(Response::Request(request)) = rx.recv().await {
+        // pairing can take a while, do not block the other requests
+        let worker = worker.clone();
+        tokio.spawn(async move {
+            if let Err(e) = worker.execute(request.clone()).await {
+                warn!("failed to execute bluetooth request {request:?}: {e}");
+            }
+        });
+    }
+
+    drop(signals);
+    Ok(())
+}
+
+structstruck::strike! {
+    #[strikethrough[derive(Debug)]]
+    #[derive(Resource)]
+    pub struct BluetoothController {
+        rx: Receiver<Request>,
+        tx: Sender<Response>,
+        pub state: BluetoothState,
+    }
+}
+
+impl std::ops::Deref for BluetoothController {
+    type Target = BluetoothState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl FromWorld for BluetoothController {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<DBusSettings>().cloned().unwrap_or_default();
+        let tokio = world.non_send_resource::<TokioRuntime>();
+        let (request_tx, request_rx) = channel(16);
+        let (response_tx, response_rx) = channel(16);
+        let handle = tokio.handle().clone();
+        tokio.spawn(async {
+            match create_dbus_connection(handle, settings, response_rx, request_tx).await {
+                Ok(()) => {
+                    info!("bluez client exit");
+                }
+                Err(e) => {
+                    error!("bluez client exit with an error: {e}");
+                }
+            }
+        });
+        Self {
+            state: Default::default(),
+            rx: request_rx,
+            tx: response_tx,
+        }
+    }
+}
+
+pub fn update_bluetooth_controller(
+    mut events: MessageReader<BluetoothRequest>,
+    mut bluetooth_controller: ResMut<BluetoothController>,
+) {
+    while let Ok(request) = bluetooth_controller.bypass_change_detection().rx.try_recv() {
+        match request {
+            Request::Updated(state) => {
+                bluetooth_controller.state = state;
+            }
+        }
+    }
+    for event in events.read() {
+        if bluetooth_controller
+            .tx
+            .try_send(Response::Request(event.clone()))
+            .is_err()
+        {
+            warn!("failed to send bluetooth request: {event:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Instant};
+
+    use dbus::channel::MatchingReceiver;
+    use dbus_crossroads::Crossroads;
+
+    use super::*;
+    use crate::controller::dbus::test::PrivateSessionBus;
+
+    const ADAPTER_PATH: &str = "/org/bluez/hci0";
+    const HEADSET_PATH: &str = "/org/bluez/hci0/dev_00_11_22_33_44_55";
+    const MOUSE_PATH: &str = "/org/bluez/hci0/dev_66_77_88_99_AA_BB";
+
+    struct MockDevice {
+        name: &'static str,
+        paired: bool,
+        connected: bool,
+    }
+
+    #[test]
+    fn test_bluez_client() {
+        let bus = PrivateSessionBus::spawn();
+        let conn = bus.connect();
+        conn.request_name(BLUEZ_DBUS_DEST, false, true, false)
+            .unwrap();
+        let (calls_tx, calls_rx) = mpsc::channel();
+        let mut cr = Crossroads::new();
+        let adapter = cr.register(BLUEZ_ADAPTER_DBUS_INTERFACE, |b| {
+            b.property("Address")
+                .get(|_, _| Ok("AA:AA:AA:AA:AA:AA".to_string()));
+            b.property("Alias").get(|_, _| Ok("laptop".to_string()));
+            b.property("Powered").get(|_, _| Ok(true));
+            b.property("Discovering").get(|_, _| Ok(false));
+        });
+        let device = cr.register(BLUEZ_DEVICE_DBUS_INTERFACE, move |b| {
+            b.property("Adapter")
+                .get(|_, _| Ok(Path::from(ADAPTER_PATH)));
+            b.property("Alias")
+                .get(|_, device: &mut MockDevice| Ok(device.name.to_string()));
+            b.property("Paired")
+                .get(|_, device: &mut MockDevice| Ok(device.paired));
+            b.property("Connected")
+                .get(|_, device: &mut MockDevice| Ok(device.connected));
+            b.method("Connect", (), (), move |ctx, _, _: ()| {
+                calls_tx.send(ctx.path().to_string()).unwrap();
+                Ok(())
+            });
+        });
+        let battery = cr.register(BLUEZ_BATTERY_DBUS_INTERFACE, |b| {
+            b.property("Percentage").get(|_, _: &mut MockDevice| Ok(42u8));
+        });
+        let object_manager = cr.object_manager();
+        cr.insert("/", &[object_manager], ());
+        cr.insert(ADAPTER_PATH, &[adapter], ());
+        cr.insert(
+            HEADSET_PATH,
+            &[device, battery],
+            MockDevice {
+                name: "headset",
+                paired: true,
+                connected: true,
+            },
+        );
+        cr.insert(
+            MOUSE_PATH,
+            &[device],
+            MockDevice {
+                name: "mouse",
+                paired: false,
+                connected: false,
+            },
+        );
+        conn.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |msg, conn| {
+                cr.handle_message(msg, conn).unwrap();
+                true
+            }),
+        );
+        let handle = std::thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                conn.process(Duration::from_millis(50)).unwrap();
+            }
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (request_tx, mut request_rx) = channel(16);
+        let (response_tx, response_rx) = channel(16);
+        runtime.spawn(create_dbus_connection(
+            runtime.handle().clone(),
+            bus.settings(),
+            response_rx,
+            request_tx,
+        ));
+
+        let Some(Request::Updated(state)) = request_rx.blocking_recv() else {
+            panic!("bluetooth state not received");
+        };
+        let adapter = state.adapter().unwrap();
+        assert_eq!(adapter.name, "laptop");
+        assert!(adapter.powered);
+        assert_eq!(state.devices.len(), 2);
+        let headset = &state.devices[0];
+        assert_eq!(headset.name, "headset");
+        assert!(headset.connected);
+        assert_eq!(headset.battery, Some(42));
+        assert_eq!(headset.adapter, ADAPTER_PATH);
+        assert_eq!(state.paired_devices().count(), 1);
+
+        response_tx
+            .blocking_send(Response::Request(BluetoothRequest::Connect {
+                device: MOUSE_PATH.to_string(),
+            }))
+            .unwrap();
+        assert_eq!(
+            calls_rx.recv_timeout(Duration::from_secs(3)).unwrap(),
+            MOUSE_PATH
+        );
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,654 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use dbus::nonblock::{Proxy, SyncConnection};
+use dway_util::tokio::TokioRuntime;
+use smart_default::SmartDefault;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use super::dbus::DBusSettings;
+use crate::prelude::*;
+
+pub const BACKLIGHT_SUBSYSTEM: &str = "backlight";
+pub const LEDS_SUBSYSTEM: &str = "leds";
+pub const LOGIND_DBUS_DEST: &str = "org.freedesktop.login1";
+pub const LOGIND_SESSION_DBUS_PATH: &str = "/org/freedesktop/login1/session/auto";
+pub const LOGIND_SESSION_DBUS_INTERFACE: &str = "org.freedesktop.login1.Session";
+/// The VCP feature code of the luminance of a monitor.
+pub const DDC_BRIGHTNESS_VCP: &str = "10";
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Resource, Clone, Debug, Reflect, SmartDefault)]
+pub struct BrightnessSettings {
+    /// The mount point of sysfs, backlights are read from `class/backlight` and `class/leds`.
+    #[default(PathBuf::from("/sys"))]
+    pub sysfs_root: PathBuf,
+    /// Write through logind's `SetBrightness` when sysfs is not writable.
+    #[default(true)]
+    pub use_logind: bool,
+    /// Control external monitors over DDC/CI with `ddcutil`.
+    pub ddc: bool,
+    #[default(Duration::from_millis(200))]
+    pub transition: Duration,
+    #[default(0.05)]
+    pub step: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Reflect)]
+pub enum BacklightKind {
+    #[default]
+    Display,
+    Keyboard,
+    External,
+}
+
+impl BacklightKind {
+    pub fn subsystem(&self) -> Option<&'static str> {
+        match self {
+            BacklightKind::Display => Some(BACKLIGHT_SUBSYSTEM),
+            BacklightKind::Keyboard => Some(LEDS_SUBSYSTEM),
+            BacklightKind::External => None,
+        }
+    }
+
+    /// Requests for `Display` also apply to external monitors.
+    pub fn includes(&self, other: BacklightKind) -> bool {
+        *self == other || (*self == BacklightKind::Display && other == BacklightKind::External)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct Backlight {
+    pub kind: BacklightKind,
+    /// The sysfs device name, or the ddcutil display number of an external monitor.
+    pub name: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+impl Backlight {
+    pub fn value(&self) -> f32 {
+        if self.max_brightness == 0 {
+            0.0
+        } else {
+            self.brightness as f32 / self.max_brightness as f32
+        }
+    }
+
+    pub fn raw(&self, value: f32) -> u32 {
+        (value.clamp(0.0, 1.0) * self.max_brightness as f32).round() as u32
+    }
+}
+
+#[derive(Message, Debug, Clone, Reflect)]
+pub enum BrightnessRequest {
+    /// Fade every backlight of a kind to a brightness in `0.0..=1.0`.
+    Set { kind: BacklightKind, value: f32 },
+}
+
+/// Reads the backlights and keyboard backlights below a sysfs root.
+pub fn scan_sysfs(root: &Path) -> Vec<Backlight> {
+    let mut backlights = vec![];
+    for kind in [BacklightKind::Display, BacklightKind::Keyboard] {
+        let Some(subsystem) = kind.subsystem() else {
+            continue;
+        };
+        let Ok(entries) = fs::read_dir(root.join("class").join(subsystem)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if kind == BacklightKind::Keyboard && !name.contains("kbd_backlight") {
+                continue;
+            }
+            let path = entry.path();
+            let read = |file: &str| {
+                fs::read_to_string(path.join(file))
+                    .ok()
+                    .and_then(|s| s.trim().parse::<u32>().ok())
+            };
+            let Some(max_brightness) = read("max_brightness") else {
+                continue;
+            };
+            backlights.push(Backlight {
+                kind,
+                name,
+                brightness: read("actual_brightness")
+                    .or_else(|| read("brightness"))
+                    .unwrap_or_default(),
+                max_brightness,
+            });
+        }
+    }
+    backlights.sort_by(|a, b| (a.kind as u8, &a.name).cmp(&(b.kind as u8, &b.name)));
+    backlights
+}
+
+/// Parses `ddcutil detect --brief`, which lists monitors as `Display <n>`.
+pub fn parse_ddc_displays(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Display "))
+        .map(|number| number.trim().to_string())
+        .collect()
+}
+
+/// Parses `ddcutil getvcp 10 --brief`, e.g. `VCP 10 C 50 100`.
+pub fn parse_ddc_brightness(output: &str) -> Option<(u32, u32)> {
+    let fields: Vec<&str> = output.split_whitespace().collect();
+    match fields.as_slice() {
+        ["VCP", _, "C", current, max, ..] => Some((current.parse().ok()?, max.parse().ok()?)),
+        _ => None,
+    }
+}
+
+fn ddcutil(args: &[&str]) -> Result<String> {
+    let output = Command::new("ddcutil").args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "ddcutil {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+enum Request {
+    Updated(Vec<Backlight>),
+}
+enum Response {
+    Write {
+        kind: BacklightKind,
+        name: String,
+        brightness: u32,
+    },
+}
+
+struct BrightnessWorker {
+    settings: BrightnessSettings,
+    dbus_settings: DBusSettings,
+    tx: Sender<Request>,
+    logind: Mutex<Option<Arc<SyncConnection>>>,
+    external: Mutex<Vec<Backlight>>,
+    last: Mutex<Vec<Backlight>>,
+}
+
+impl BrightnessWorker {
+    async fn refresh(&self) -> Result<()> {
+        let mut backlights = scan_sysfs(&self.settings.sysfs_root);
+        backlights.extend(self.external.lock().unwrap().iter().cloned());
+        {
+            let mut last = self.last.lock().unwrap();
+            if *last == backlights {
+                return Ok(());
+            }
+            *last = backlights.clone();
+        }
+        self.tx.send(Request::Updated(backlights)).await?;
+        Ok(())
+    }
+
+    async fn detect_ddc(&self) -> Result<()> {
+        let monitors = tokio::task::spawn_blocking(|| -> Result<Vec<Backlight>> {
+            let output = ddcutil(&["detect", "--brief"])?;
+            Ok(parse_ddc_displays(&output)
+                .into_iter()
+                .filter_map(|name| {
+                    let output =
+                        ddcutil(&["getvcp", DDC_BRIGHTNESS_VCP, "--brief", "--display", &name])
+                            .ok()?;
+                    let (brightness, max_brightness) = parse_ddc_brightness(&output)?;
+                    Some(Backlight {
+                        kind: BacklightKind::External,
+                        name,
+                        brightness,
+                        max_brightness,
+                    })
+                })
+                .collect())
+        })
+        .await??;
+        *self.external.lock().unwrap() = monitors;
+        self.refresh().await
+    }
+
+    fn logind(&self) -> Result<Arc<SyncConnection>> {
+        let mut logind = self.logind.lock().unwrap();
+        if let Some(conn) = &*logind {
+            return Ok(conn.clone());
+        }
+        let (resource, conn) = self.dbus_settings.connect_system_sync()?;
+        tokio::spawn(async {
+            let err = resource.await;
+            error!("lost connection to the system bus: {err}");
+        });
+        *logind = Some(conn.clone());
+        Ok(conn)
+    }
+
+    async fn write(&self, kind: BacklightKind, name: String, brightness: u32) -> Result<()> {
+        let Some(subsystem) = kind.subsystem() else {
+            let display = name.clone();
+            tokio::task::spawn_blocking(move || {
+                ddcutil(&[
+                    "setvcp",
+                    DDC_BRIGHTNESS_VCP,
+                    &brightness.to_string(),
+                    "--display",
+                    &display,
+                ])
+            })
+            .await??;
+            if let Some(monitor) = self
+                .external
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .find(|m| m.name == name)
+            {
+                monitor.brightness = brightness;
+            }
+            return Ok(());
+        };
+
+        let path = self
+            .settings
+            .sysfs_root
+            .join("class")
+            .join(subsystem)
+            .join(&name)
+            .join("brightness");
+        match fs::write(&path, brightness.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && self.settings.use_logind => {
+                let proxy = Proxy::new(
+                    LOGIND_DBUS_DEST,
+                    LOGIND_SESSION_DBUS_PATH,
+                    DBUS_TIMEOUT,
+                    self.logind()?,
+                );
+                proxy
+                    .method_call::<(), _, _, _>(
+                        LOGIND_SESSION_DBUS_INTERFACE,
+                        "SetBrightness",
+                        (subsystem, name.as_str(), brightness),
+                    )
+                    .await?;
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+async fn run_brightness_worker(
+    tokio: tokio::runtime::Handle,
+    settings: BrightnessSettings,
+    dbus_settings: DBusSettings,
+    mut rx: Receiver<Response>,
+    tx: Sender<Request>,
+) -> Result<()> {
+    let worker = Arc::new(BrightnessWorker {
+        settings,
+        dbus_settings,
+        tx,
+        logind: Default::default(),
+        external: Default::default(),
+        last: Default::default(),
+    });
+
+    if worker.settings.ddc {
+        let worker = worker.clone();
+        tokio.spawn(async move {
+            if let Err(e) = worker.detect_ddc().await {
+                warn!("failed to detect DDC/CI monitors: {e}");
+            }
+        });
+    }
+
+    let worker2 = worker.clone();
+    tokio.spawn(async move {
+        loop {
+            if let Err(e) = worker2.refresh().await {
+                debug!("stop refreshing backlights: {e}");
+                break;
+            }
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+
+    while let Some(response) = rx.recv().await {
+        match response {
+            Response::Write {
+                kind,
+                name,
+                brightness,
+            } => {
+                if let Err(e) = worker.write(kind, name.clone(), brightness).await {
+                    warn!("failed to set the brightness of {name}: {e}");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct BrightnessTransition {
+    kind: BacklightKind,
+    name: String,
+    from: f32,
+    to: f32,
+    elapsed: Duration,
+}
+
+structstruck::strike! {
+    #[strikethrough[derive(Debug)]]
+    #[derive(Resource)]
+    pub struct BrightnessController {
+        rx: Receiver<Request>,
+        tx: Sender<Response>,
+        transitions: Vec<BrightnessTransition>,
+        pub backlights: Vec<Backlight>,
+    }
+}
+
+impl BrightnessController {
+    pub fn backlights_of(&self, kind: BacklightKind) -> impl Iterator<Item = &Backlight> {
+        self.backlights.iter().filter(move |b| kind.includes(b.kind))
+    }
+
+    /// The brightness a kind of backlight is at or is fading to.
+    pub fn target(&self, kind: BacklightKind) -> Option<f32> {
+        let backlight = self.backlights_of(kind).next()?;
+        Some(
+            self.transitions
+                .iter()
+                .find(|t| t.kind == backlight.kind && t.name == backlight.name)
+                .map(|t| t.to)
+                .unwrap_or_else(|| backlight.value()),
+        )
+    }
+
+    /// The target after a step of `delta`, which changes the backlight by at least one level.
+    pub fn increased(&self, kind: BacklightKind, delta: f32) -> Option<f32> {
+        let backlight = self.backlights_of(kind).next()?;
+        let min_step = 1.0 / backlight.max_brightness.max(1) as f32;
+        let delta = delta.signum() * delta.abs().max(min_step);
+        Some((self.target(kind)? + delta).clamp(0.0, 1.0))
+    }
+
+    fn transition_to(&mut self, kind: BacklightKind, value: f32) {
+        let value = value.clamp(0.0, 1.0);
+        for backlight in self.backlights.iter().filter(|b| kind.includes(b.kind)) {
+            self.transitions
+                .retain(|t| !(t.kind == backlight.kind && t.name == backlight.name));
+            self.transitions.push(BrightnessTransition {
+                kind: backlight.kind,
+                name: backlight.name.clone(),
+                from: backlight.value(),
+                to: value,
+                elapsed: Duration::ZERO,
+            });
+        }
+    }
+
+    fn step(&mut self, delta: Duration, duration: Duration) -> bool {
+        let Self {
+            transitions,
+            backlights,
+            tx,
+            ..
+        } = self;
+        let mut changed = false;
+        transitions.retain_mut(|transition| {
+            let Some(backlight) = backlights
+                .iter_mut()
+                .find(|b| b.kind == transition.kind && b.name == transition.name)
+            else {
+                return false;
+            };
+            transition.elapsed += delta;
+            let progress = if duration.is_zero() {
+                1.0
+            } else {
+                (transition.elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0)
+            };
+            let finished = progress >= 1.0;
+            // DDC/CI is too slow to follow the animation.
+            if backlight.kind == BacklightKind::External && !finished {
+                return true;
+            }
+            let eased = progress * progress * (3.0 - 2.0 * progress);
+            let brightness =
+                backlight.raw(transition.from + (transition.to - transition.from) * eased);
+            if brightness != backlight.brightness {
+                backlight.brightness = brightness;
+                changed = true;
+                if tx
+                    .try_send(Response::Write {
+                        kind: backlight.kind,
+                        name: backlight.name.clone(),
+                        brightness,
+                    })
+                    .is_err()
+                {
+                    warn!("failed to send the brightness of {}", backlight.name);
+                }
+            }
+            !finished
+        });
+        changed
+    }
+
+    fn merge(&mut self, backlights: Vec<Backlight>) -> bool {
+        let backlights: Vec<_> = backlights
+            .into_iter()
+            .map(|backlight| {
+                let animating = self
+                    .transitions
+                    .iter()
+                    .any(|t| t.kind == backlight.kind && t.name == backlight.name);
+                match self.backlights.iter().find(|b| {
+                    animating && b.kind == backlight.kind && b.name == backlight.name
+                }) {
+                    Some(current) => current.clone(),
+                    None => backlight,
+                }
+            })
+            .collect();
+        if self.backlights == backlights {
+            return false;
+        }
+        self.backlights = backlights;
+        true
+    }
+}
+
+impl FromWorld for BrightnessController {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world
+            .get_resource::<BrightnessSettings>()
+            .cloned()
+            .unwrap_or_default();
+        let dbus_settings = world.get_resource::<DBusSettings>().cloned().unwrap_or_default();
+        let tokio = world.non_send_resource::<TokioRuntime>();
+        let (request_tx, request_rx) = channel(16);
+        let (response_tx, response_rx) = channel(256);
+        let handle = tokio.handle().clone();
+        tokio.spawn(async {
+            match run_brightness_worker(handle, settings, dbus_settings, response_rx, request_tx)
+                .await
+            {
+                Ok(()) => {
+                    info!("brightness controller exit");
+                }
+                Err(e) => {
+                    error!("brightness controller exit with an error: {e}");
+                }
+            }
+        });
+        Self {
+            rx: request_rx,
+            tx: response_tx,
+            transitions: vec![],
+            backlights: vec![],
+        }
+    }
+}
+
+pub fn update_brightness_controller(
+    mut events: MessageReader<BrightnessRequest>,
+    mut brightness_controller: ResMut<BrightnessController>,
+    settings: Res<BrightnessSettings>,
+    time: Res<Time>,
+) {
+    let controller = brightness_controller.bypass_change_detection();
+    let mut changed = false;
+    while let Ok(request) = controller.rx.try_recv() {
+        match request {
+            Request::Updated(backlights) => {
+                changed |= controller.merge(backlights);
+            }
+        }
+    }
+    for event in events.read() {
+        match event {
+            BrightnessRequest::Set { kind, value } => controller.transition_to(*kind, *value),
+        }
+    }
+    if !controller.transitions.is_empty() {
+        changed |= controller.step(time.delta(), settings.transition);
+    }
+    if changed {
+        brightness_controller.set_changed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_device(root: &Path, subsystem: &str, name: &str, brightness: u32, max: u32) {
+        let dir = root.join("class").join(subsystem).join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("brightness"), format!("{brightness}\n")).unwrap();
+        fs::write(dir.join("max_brightness"), format!("{max}\n")).unwrap();
+    }
+
+    #[test]
+    fn test_ddcutil_output() {
+        let detect = "Display 1\n   I2C bus:  /dev/i2c-4\n\nDisplay 2\n   I2C bus:  /dev/i2c-5\n";
+        assert_eq!(parse_ddc_displays(detect), vec!["1", "2"]);
+        assert_eq!(parse_ddc_brightness("VCP 10 C 50 100\n"), Some((50, 100)));
+        assert_eq!(parse_ddc_brightness("VCP 10 ERR\n"), None);
+    }
+
+    #[test]
+    fn test_transition() {
+        let (_request_tx, request_rx) = channel(16);
+        let (response_tx, mut response_rx) = channel(16);
+        let mut controller = BrightnessController {
+            rx: request_rx,
+            tx: response_tx,
+            transitions: vec![],
+            backlights: vec![Backlight {
+                kind: BacklightKind::Display,
+                name: "intel_backlight".to_string(),
+                brightness: 0,
+                max_brightness: 100,
+            }],
+        };
+        let duration = Duration::from_millis(200);
+
+        controller.transition_to(BacklightKind::Display, 1.0);
+        assert_eq!(controller.target(BacklightKind::Display), Some(1.0));
+        assert!(controller.step(Duration::from_millis(100), duration));
+        assert_eq!(controller.backlights[0].brightness, 50);
+        assert!(matches!(
+            response_rx.try_recv(),
+            Ok(Response::Write { brightness: 50, .. })
+        ));
+
+        // the controller keeps its own value while fading
+        assert!(!controller.merge(vec![Backlight {
+            brightness: 10,
+            ..controller.backlights[0].clone()
+        }]));
+
+        assert!(controller.step(Duration::from_millis(100), duration));
+        assert_eq!(controller.backlights[0].brightness, 100);
+        assert!(controller.transitions.is_empty());
+        assert_eq!(controller.increased(BacklightKind::Display, -0.05), Some(0.95));
+    }
+
+    #[test]
+    fn test_sysfs_backlight() {
+        let root = tempfile::tempdir().unwrap();
+        write_device(root.path(), BACKLIGHT_SUBSYSTEM, "intel_backlight", 300, 1000);
+        write_device(root.path(), LEDS_SUBSYSTEM, "tpacpi::kbd_backlight", 1, 2);
+        write_device(root.path(), LEDS_SUBSYSTEM, "input0::capslock", 0, 1);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (request_tx, mut request_rx) = channel(16);
+        let (response_tx, response_rx) = channel(16);
+        let settings = BrightnessSettings {
+            sysfs_root: root.path().to_path_buf(),
+            use_logind: false,
+            ..Default::default()
+        };
+        runtime.spawn(run_brightness_worker(
+            runtime.handle().clone(),
+            settings,
+            DBusSettings::default(),
+            response_rx,
+            request_tx,
+        ));
+
+        let Some(Request::Updated(backlights)) = request_rx.blocking_recv() else {
+            panic!("the worker exited");
+        };
+        assert_eq!(
+            backlights,
+            vec![
+                Backlight {
+                    kind: BacklightKind::Display,
+                    name: "intel_backlight".to_string(),
+                    brightness: 300,
+                    max_brightness: 1000,
+                },
+                Backlight {
+                    kind: BacklightKind::Keyboard,
+                    name: "tpacpi::kbd_backlight".to_string(),
+                    brightness: 1,
+                    max_brightness: 2,
+                },
+            ]
+        );
+
+        response_tx
+            .blocking_send(Response::Write {
+                kind: BacklightKind::Display,
+                name: "intel_backlight".to_string(),
+                brightness: 800,
+            })
+            .unwrap();
+        let Some(Request::Updated(backlights)) = request_rx.blocking_recv() else {
+            panic!("the worker exited");
+        };
+        assert_eq!(backlights[0].brightness, 800);
+        assert_eq!(
+            fs::read_to_string(root.path().join("class/backlight/intel_backlight/brightness"))
+                .unwrap(),
+            "800"
+        );
+    }
+}
@@ -1,9 +1,97 @@
 use anyhow::Result;
 use dbus::{
     arg::{AppendAll, ReadAll},
+    channel::Channel,
     ffidisp::Connection,
+    nonblock::SyncConnection,
 };
-use std::time::Duration;
+use dbus_tokio::connection::{self, IOResource};
+use std::{sync::Arc, time::Duration};
+
+use crate::prelude::*;
+
+/// The buses used by the dbus based controllers.
+///
+/// The addresses override the session and system bus, which lets tests run
+/// the controllers against a private `dbus-daemon`.
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+pub struct DBusSettings {
+    pub session_address: Option<String>,
+    pub system_address: Option<String>,
+}
+
+impl DBusSettings {
+    pub fn with_address(address: impl Into<String>) -> Self {
+        let address = address.into();
+        Self {
+            session_address: Some(address.clone()),
+            system_address: Some(address),
+        }
+    }
+
+    fn connect_private(
+        address: &str,
+    ) -> Result<(IOResource<SyncConnection>, Arc<SyncConnection>)> {
+        let mut channel = Channel::open_private(address)?;
+        channel.register()?;
+        Ok(connection::from_channel(channel)?)
+    }
+
+    pub fn connect_sync(&self) -> Result<(IOResource<SyncConnection>, Arc<SyncConnection>)> {
+        match &self.session_address {
+            Some(address) => Self::connect_private(address),
+            None => Ok(connection::new_session_sync()?),
+        }
+    }
+
+    pub fn connect_system_sync(
+        &self,
+    ) -> Result<(IOResource<SyncConnection>, Arc<SyncConnection>)> {
+        match &self.system_address {
+            Some(address) => Self::connect_private(address),
+            None => Ok(connection::new_system_sync()?),
+        }
+    }
+}
+
+/// Helpers to read the loosely typed values of `GetAll` and `GetManagedObjects`.
+pub mod props {
+    use dbus::arg::{PropMap, RefArg};
+
+    pub fn string(map: &PropMap, key: &str) -> String {
+        map.get(key)
+            .and_then(|v| v.0.as_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    pub fn number(map: &PropMap, key: &str) -> Option<u64> {
+        map.get(key).and_then(|v| v.0.as_u64())
+    }
+
+    pub fn boolean(map: &PropMap, key: &str) -> bool {
+        number(map, key).unwrap_or_default() != 0
+    }
+
+    /// An object path, `None` for the empty path `/`.
+    pub fn path(map: &PropMap, key: &str) -> Option<String> {
+        Some(string(map, key)).filter(|p| !p.is_empty() && p != "/")
+    }
+
+    pub fn strings(map: &PropMap, key: &str) -> Vec<String> {
+        map.get(key)
+            .and_then(|v| v.0.as_iter())
+            .map(|iter| iter.filter_map(|a| a.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn bytes(map: &PropMap, key: &str) -> Vec<u8> {
+        map.get(key)
+            .and_then(|v| v.0.as_iter())
+            .map(|iter| iter.filter_map(|a| a.as_u64().map(|b| b as u8)).collect())
+            .unwrap_or_default()
+    }
+}
 
 pub struct DBusController {
     pub connection: Connection,
@@ -33,3 +121,56 @@ impl DBusController {
         Ok(path.method_call(interface, member, args)?)
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+
+    use dbus::{blocking, channel::Channel};
+
+    /// A `dbus-daemon` owned by a single test.
+    pub struct PrivateSessionBus {
+        child: Child,
+        pub address: String,
+    }
+
+    impl PrivateSessionBus {
+        /// Panics if `dbus-daemon` can't be started, the D-Bus tests need it installed.
+        pub fn spawn() -> Self {
+            let mut child = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("failed to start dbus-daemon, the D-Bus tests need it installed");
+            let mut address = String::new();
+            BufReader::new(child.stdout.as_mut().unwrap())
+                .read_line(&mut address)
+                .expect("failed to read the address of dbus-daemon");
+            Self {
+                child,
+                address: address.trim().to_string(),
+            }
+        }
+
+        pub fn settings(&self) -> super::DBusSettings {
+            super::DBusSettings::with_address(&self.address)
+        }
+
+        pub fn connect(&self) -> blocking::SyncConnection {
+            let mut channel = Channel::open_private(&self.address).unwrap();
+            channel.register().unwrap();
+            channel.into()
+        }
+    }
+
+    impl Drop for PrivateSessionBus {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -1,7 +1,9 @@
+pub mod appearance;
 pub mod bluetooth;
 pub mod brightness;
 pub mod dbus;
 pub mod network;
+pub mod night_light;
 pub mod notify;
 pub mod player;
 pub mod systemcontroller;
@@ -17,10 +19,17 @@ use bevy::time::{TimeSystems, common_conditions::on_timer};
 use smart_default::SmartDefault;
 
 use self::{
-    dbus::DBusController,
-    notify::{NotifyController, NotifyRequest},
+    appearance::AppearanceController,
+    bluetooth::{BluetoothController, BluetoothRequest},
+    brightness::{BrightnessController, BrightnessRequest, BrightnessSettings},
+    dbus::{DBusController, DBusSettings},
+    network::{NetworkController, NetworkRequest},
+    night_light::{NightLightController, NightLightRequest, NightLightSettings},
+    notify::{NotifyController, NotifyRequest, NotifySettings},
+    player::{PlayerController, PlayerRequest},
     systemcontroller::SystemControllRequest,
     systeminfo::SystemInfo,
+    tray::{TrayController, TrayRequest},
     userinfo::UserInfo,
 };
 use crate::controller::volume::VolumeController;
@@ -38,9 +47,29 @@ impl Plugin for ControllerPlugin {
             .init_non_send_resource::<DBusController>()
             .init_resource::<SystemInfo>()
             .init_resource::<UserInfo>()
+            .init_resource::<DBusSettings>()
+            .init_resource::<NotifySettings>()
+            .init_resource::<BrightnessSettings>()
+            .init_resource::<NightLightSettings>()
             .init_resource::<NotifyController>()
+            .init_resource::<TrayController>()
+            .init_resource::<PlayerController>()
+            .init_resource::<NetworkController>()
+            .init_resource::<BluetoothController>()
+            .init_resource::<BrightnessController>()
+            .init_resource::<AppearanceController>()
+            .init_resource::<NightLightController>()
+            .register_type::<NotifySettings>()
+            .register_type::<BrightnessSettings>()
+            .register_type::<NightLightSettings>()
             .add_event::<SystemControllRequest>()
             .add_event::<NotifyRequest>()
+            .add_event::<TrayRequest>()
+            .add_event::<PlayerRequest>()
+            .add_event::<NetworkRequest>()
+            .add_event::<BluetoothRequest>()
+            .add_event::<BrightnessRequest>()
+            .add_event::<NightLightRequest>()
             .add_systems(
                 FixedFirst,
                 (
@@ -56,7 +85,18 @@ impl Plugin for ControllerPlugin {
                 (
                     systemcontroller::receive_system_controll_request
                         .run_if(on_event::<SystemControllRequest>),
-                    notify::do_receive_notify,
+                    (notify::do_receive_notify, notify::expire_notify).chain(),
+                    tray::update_tray_controller,
+                    player::update_player_controller,
+                    network::update_network_controller,
+                    bluetooth::update_bluetooth_controller,
+                    brightness::update_brightness_controller,
+                    appearance::update_appearance_controller,
+                    (
+                        night_light::update_night_light_controller,
+                        night_light::update_output_gamma,
+                    )
+                        .chain(),
                 ),
             );
     }
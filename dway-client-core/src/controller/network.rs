@@ -0,0 +1,957 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use dbus::{
+    arg::{PropMap, RefArg, Variant},
+    message::{MatchRule, MessageType},
+    nonblock::{stdintf::org_freedesktop_dbus::Properties, Proxy, SyncConnection},
+    Path,
+};
+use dway_util::tokio::TokioRuntime;
+use tokio::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Notify,
+};
+
+use super::dbus::{props, DBusSettings};
+use crate::prelude::*;
+
+pub const NM_DBUS_DEST: &str = "org.freedesktop.NetworkManager";
+pub const NM_DBUS_PATH: &str = "/org/freedesktop/NetworkManager";
+pub const NM_DBUS_INTERFACE: &str = "org.freedesktop.NetworkManager";
+pub const NM_SETTINGS_DBUS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+pub const NM_SETTINGS_DBUS_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings";
+pub const NM_CONNECTION_DBUS_INTERFACE: &str =
+    "org.freedesktop.NetworkManager.Settings.Connection";
+pub const NM_DEVICE_DBUS_INTERFACE: &str = "org.freedesktop.NetworkManager.Device";
+pub const NM_WIRELESS_DBUS_INTERFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+pub const NM_ACCESS_POINT_DBUS_INTERFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+pub const NM_ACTIVE_CONNECTION_DBUS_INTERFACE: &str =
+    "org.freedesktop.NetworkManager.Connection.Active";
+const NM_DEVICE_DBUS_PATH_PREFIX: &str = "/org/freedesktop/NetworkManager/Devices/";
+const NM_ACCESS_POINT_DBUS_PATH_PREFIX: &str = "/org/freedesktop/NetworkManager/AccessPoint/";
+const NM_ACTIVE_CONNECTION_DBUS_PATH_PREFIX: &str =
+    "/org/freedesktop/NetworkManager/ActiveConnection/";
+const NM_CONNECTION_DBUS_PATH_PREFIX: &str = "/org/freedesktop/NetworkManager/Settings/";
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+/// Signals of NetworkManager come in bursts, wait a little before updating.
+const REFRESH_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum NetworkDeviceKind {
+    Ethernet,
+    Wifi,
+    Bluetooth,
+    Modem,
+    #[default]
+    Other,
+}
+
+impl From<u64> for NetworkDeviceKind {
+    fn from(value: u64) -> Self {
+        match value {
+            1 => Self::Ethernet,
+            2 => Self::Wifi,
+            5 => Self::Bluetooth,
+            8 => Self::Modem,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum NetworkDeviceState {
+    #[default]
+    Unavailable,
+    Disconnected,
+    Connecting,
+    Activated,
+    Deactivating,
+    Failed,
+}
+
+impl From<u64> for NetworkDeviceState {
+    fn from(value: u64) -> Self {
+        match value {
+            30 => Self::Disconnected,
+            40..=90 => Self::Connecting,
+            100 => Self::Activated,
+            110 => Self::Deactivating,
+            120 => Self::Failed,
+            _ => Self::Unavailable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct WifiAccessPoint {
+    pub path: String,
+    pub ssid: String,
+    /// signal quality in percent
+    pub strength: u8,
+    pub frequency: u32,
+    pub secured: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct NetworkDevice {
+    pub path: String,
+    pub interface: String,
+    pub kind: NetworkDeviceKind,
+    pub state: NetworkDeviceState,
+    pub active_connection: Option<String>,
+    pub access_points: Vec<WifiAccessPoint>,
+    pub active_access_point: Option<String>,
+}
+
+impl NetworkDevice {
+    pub fn active_access_point(&self) -> Option<&WifiAccessPoint> {
+        let path = self.active_access_point.as_ref()?;
+        self.access_points.iter().find(|ap| &ap.path == path)
+    }
+}
+
+/// A saved connection profile.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct NetworkConnection {
+    pub path: String,
+    pub id: String,
+    pub uuid: String,
+    /// the `connection.type` setting, like `802-11-wireless` or `vpn`
+    pub kind: String,
+    pub ssid: String,
+}
+
+impl NetworkConnection {
+    pub fn is_vpn(&self) -> bool {
+        self.kind == "vpn" || self.kind == "wireguard"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct ActiveConnection {
+    pub path: String,
+    pub id: String,
+    pub kind: String,
+    /// the path of the saved connection
+    pub connection: String,
+    pub activated: bool,
+    pub vpn: bool,
+    pub devices: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct NetworkState {
+    pub networking_enabled: bool,
+    pub wireless_enabled: bool,
+    pub primary_connection: Option<String>,
+    pub devices: Vec<NetworkDevice>,
+    pub connections: Vec<NetworkConnection>,
+    pub active_connections: Vec<ActiveConnection>,
+}
+
+impl NetworkState {
+    pub fn primary(&self) -> Option<&ActiveConnection> {
+        let path = self.primary_connection.as_ref()?;
+        self.active_connections.iter().find(|c| &c.path == path)
+    }
+
+    pub fn wifi_device(&self) -> Option<&NetworkDevice> {
+        self.devices
+            .iter()
+            .find(|d| d.kind == NetworkDeviceKind::Wifi)
+    }
+
+    pub fn active_of(&self, connection: &str) -> Option<&ActiveConnection> {
+        self.active_connections
+            .iter()
+            .find(|c| c.connection == connection)
+    }
+
+    pub fn vpns(&self) -> impl Iterator<Item = &NetworkConnection> {
+        self.connections.iter().filter(|c| c.is_vpn())
+    }
+}
+
+#[derive(Message, Debug, Clone, Reflect)]
+pub enum NetworkRequest {
+    RequestScan {
+        device: String,
+    },
+    /// Connect to an access point, a saved connection with the same ssid is reused.
+    ConnectWifi {
+        device: String,
+        access_point: String,
+        password: Option<String>,
+    },
+    ActivateConnection {
+        connection: String,
+        device: Option<String>,
+    },
+    DeactivateConnection {
+        active_connection: String,
+    },
+    DisconnectDevice {
+        device: String,
+    },
+    /// Activate a vpn connection, or deactivate it if it is active.
+    ToggleVpn {
+        connection: String,
+    },
+    SetWirelessEnabled(bool),
+}
+
+enum Request {
+    Updated(NetworkState),
+}
+
+/// The objects which sent signals since the last update, only they are fetched again.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PendingChanges {
+    /// the properties of NetworkManager, with the lists of devices and active connections
+    manager: bool,
+    /// the list of saved connections
+    settings: bool,
+    devices: HashSet<String>,
+    access_points: HashSet<String>,
+    active_connections: HashSet<String>,
+    connections: HashSet<String>,
+}
+
+impl PendingChanges {
+    fn add(&mut self, path: &str) {
+        if path == NM_SETTINGS_DBUS_PATH {
+            self.settings = true;
+        } else if path.starts_with(NM_DEVICE_DBUS_PATH_PREFIX) {
+            self.devices.insert(path.to_string());
+        } else if path.starts_with(NM_ACCESS_POINT_DBUS_PATH_PREFIX) {
+            self.access_points.insert(path.to_string());
+        } else if path.starts_with(NM_ACTIVE_CONNECTION_DBUS_PATH_PREFIX) {
+            self.active_connections.insert(path.to_string());
+        } else if path.starts_with(NM_CONNECTION_DBUS_PATH_PREFIX) {
+            self.connections.insert(path.to_string());
+        } else {
+            self.manager = true;
+        }
+    }
+}
+
+/// Replace the item with the same path, or append it if it is new.
+fn replace_or_push<T>(items: &mut Vec<T>, item: T, path: impl Fn(&T) -> &str) {
+    match items.iter_mut().find(|i| path(i) == path(&item)) {
+        Some(current) => *current = item,
+        None => items.push(item),
+    }
+}
+enum Response {
+    Request(NetworkRequest),
+}
+
+struct NetworkWorker {
+    conn: Arc<SyncConnection>,
+    tx: Sender<Request>,
+    state: Mutex<NetworkState>,
+}
+
+impl NetworkWorker {
+    fn proxy<'a>(&self, path: impl Into<Path<'a>>) -> Proxy<'a, Arc<SyncConnection>> {
+        Proxy::new(NM_DBUS_DEST, path, DBUS_TIMEOUT, self.conn.clone())
+    }
+
+    async fn get_all(&self, path: &str, interface: &str) -> Result<PropMap> {
+        Ok(self.proxy(path.to_string()).get_all(interface).await?)
+    }
+
+    async fn get_access_point(&self, path: String) -> Result<WifiAccessPoint> {
+        let properties = self.get_all(&path, NM_ACCESS_POINT_DBUS_INTERFACE).await?;
+        Ok(WifiAccessPoint {
+            ssid: String::from_utf8_lossy(&props::bytes(&properties, "Ssid")).to_string(),
+            strength: props::number(&properties, "Strength").unwrap_or_default() as u8,
+            frequency: props::number(&properties, "Frequency").unwrap_or_default() as u32,
+            secured: props::number(&properties, "Flags").unwrap_or_default() != 0
+                || props::number(&properties, "WpaFlags").unwrap_or_default() != 0
+                || props::number(&properties, "RsnFlags").unwrap_or_default() != 0,
+            path,
+        })
+    }
+
+    async fn get_device(&self, path: String) -> Result<NetworkDevice> {
+        let properties = self.get_all(&path, NM_DEVICE_DBUS_INTERFACE).await?;
+        let mut device = NetworkDevice {
+            interface: props::string(&properties, "Interface"),
+            kind: props::number(&properties, "DeviceType")
+                .unwrap_or_default()
+                .into(),
+            state: props::number(&properties, "State")
+                .unwrap_or_default()
+                .into(),
+            active_connection: props::path(&properties, "ActiveConnection"),
+            path,
+            ..Default::default()
+        };
+        if device.kind == NetworkDeviceKind::Wifi {
+            let wireless = self
+                .get_all(&device.path, NM_WIRELESS_DBUS_INTERFACE)
+                .await?;
+            device.active_access_point = props::path(&wireless, "ActiveAccessPoint");
+            for access_point in props::strings(&wireless, "AccessPoints") {
+                match self.get_access_point(access_point).await {
+                    Ok(access_point) if !access_point.ssid.is_empty() => {
+                        device.access_points.push(access_point)
+                    }
+                    Ok(_) => {}
+                    Err(e) => debug!("failed to get access point: {e}"),
+                }
+            }
+            device.access_points.sort_by(|a, b| b.strength.cmp(&a.strength));
+            // the same network is announced by several access points
+            let mut ssids = std::collections::HashSet::new();
+            let active = device.active_access_point.clone();
+            device
+                .access_points
+                .retain(|ap| Some(&ap.path) == active.as_ref() || ssids.insert(ap.ssid.clone()));
+        }
+        Ok(device)
+    }
+
+    async fn get_connection(&self, path: String) -> Result<NetworkConnection> {
+        let (settings,): (HashMap<String, PropMap>,) = self
+            .proxy(path.clone())
+            .method_call(NM_CONNECTION_DBUS_INTERFACE, "GetSettings", ())
+            .await?;
+        let connection = settings.get("connection").cloned().unwrap_or_default();
+        let ssid = settings
+            .get("802-11-wireless")
+            .map(|wireless| String::from_utf8_lossy(&props::bytes(wireless, "ssid")).to_string())
+            .unwrap_or_default();
+        Ok(NetworkConnection {
+            path,
+            id: props::string(&connection, "id"),
+            uuid: props::string(&connection, "uuid"),
+            kind: props::string(&connection, "type"),
+            ssid,
+        })
+    }
+
+    async fn get_active_connection(&self, path: String) -> Result<ActiveConnection> {
+        let properties = self
+            .get_all(&path, NM_ACTIVE_CONNECTION_DBUS_INTERFACE)
+            .await?;
+        Ok(ActiveConnection {
+            id: props::string(&properties, "Id"),
+            kind: props::string(&properties, "Type"),
+            connection: props::string(&properties, "Connection"),
+            activated: props::number(&properties, "State") == Some(2),
+            vpn: props::boolean(&properties, "Vpn"),
+            devices: props::strings(&properties, "Devices"),
+            path,
+        })
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        let properties = self.get_all(NM_DBUS_PATH, NM_DBUS_INTERFACE).await?;
+        let mut state = NetworkState {
+            networking_enabled: props::boolean(&properties, "NetworkingEnabled"),
+            wireless_enabled: props::boolean(&properties, "WirelessEnabled"),
+            primary_connection: props::path(&properties, "PrimaryConnection"),
+            ..Default::default()
+        };
+        for device in props::strings(&properties, "Devices") {
+            match self.get_device(device).await {
+                Ok(device) => state.devices.push(device),
+                Err(e) => debug!("failed to get network device: {e}"),
+            }
+        }
+        for active in props::strings(&properties, "ActiveConnections") {
+            match self.get_active_connection(active).await {
+                Ok(active) => state.active_connections.push(active),
+                Err(e) => debug!("failed to get active connection: {e}"),
+            }
+        }
+        let (connections,): (Vec<Path<'static>>,) = self
+            .proxy(NM_SETTINGS_DBUS_PATH)
+            .method_call(NM_SETTINGS_DBUS_INTERFACE, "ListConnections", ())
+            .await?;
+        for connection in connections {
+            match self.get_connection(connection.to_string()).await {
+                Ok(connection) => state.connections.push(connection),
+                Err(e) => debug!("failed to get connection settings: {e}"),
+            }
+        }
+        self.publish(state).await
+    }
+
+    /// Fetch again only the objects which changed.
+    async fn update(&self, mut changes: PendingChanges) -> Result<()> {
+        let mut state = self.state.lock().unwrap().clone();
+        if changes.manager {
+            let properties = self.get_all(NM_DBUS_PATH, NM_DBUS_INTERFACE).await?;
+            state.networking_enabled = props::boolean(&properties, "NetworkingEnabled");
+            state.wireless_enabled = props::boolean(&properties, "WirelessEnabled");
+            state.primary_connection = props::path(&properties, "PrimaryConnection");
+            let devices = props::strings(&properties, "Devices");
+            state.devices.retain(|d| devices.contains(&d.path));
+            changes.devices.extend(
+                devices
+                    .into_iter()
+                    .filter(|path| !state.devices.iter().any(|d| &d.path == path)),
+            );
+            let active_connections = props::strings(&properties, "ActiveConnections");
+            state
+                .active_connections
+                .retain(|c| active_connections.contains(&c.path));
+            changes.active_connections.extend(
+                active_connections
+                    .into_iter()
+                    .filter(|path| !state.active_connections.iter().any(|c| &c.path == path)),
+            );
+        }
+        if changes.settings {
+            let (connections,): (Vec<Path<'static>>,) = self
+                .proxy(NM_SETTINGS_DBUS_PATH)
+                .method_call(NM_SETTINGS_DBUS_INTERFACE, "ListConnections", ())
+                .await?;
+            let connections: Vec<String> = connections.iter().map(|c| c.to_string()).collect();
+            state.connections.retain(|c| connections.contains(&c.path));
+            changes.connections.extend(
+                connections
+                    .into_iter()
+                    .filter(|path| !state.connections.iter().any(|c| &c.path == path)),
+            );
+        }
+
+        for path in changes.devices {
+            match self.get_device(path.clone()).await {
+                Ok(device) => replace_or_push(&mut state.devices, device, |d| &d.path),
+                Err(e) => {
+                    debug!("failed to get network device: {e}");
+                    state.devices.retain(|d| d.path != path);
+                }
+            }
+        }
+        for path in changes.access_points {
+            let Some(device) = state
+                .devices
+                .iter_mut()
+                .find(|d| d.access_points.iter().any(|ap| ap.path == path))
+            else {
+                // new access points are announced by their device
+                continue;
+            };
+            match self.get_access_point(path.clone()).await {
+                Ok(access_point) => {
+                    replace_or_push(&mut device.access_points, access_point, |ap| &ap.path);
+                    device.access_points.sort_by(|a, b| b.strength.cmp(&a.strength));
+                }
+                Err(e) => {
+                    debug!("failed to get access point: {e}");
+                    device.access_points.retain(|ap| ap.path != path);
+                }
+            }
+        }
+        for path in changes.active_connections {
+            match self.get_active_connection(path.clone()).await {
+                Ok(active) => replace_or_push(&mut state.active_connections, active, |c| &c.path),
+                Err(e) => {
+                    debug!("failed to get active connection: {e}");
+                    state.active_connections.retain(|c| c.path != path);
+                }
+            }
+        }
+        for path in changes.connections {
+            match self.get_connection(path.clone()).await {
+                Ok(connection) => replace_or_push(&mut state.connections, connection, |c| &c.path),
+                Err(e) => {
+                    debug!("failed to get connection settings: {e}");
+                    state.connections.retain(|c| c.path != path);
+                }
+            }
+        }
+        self.publish(state).await
+    }
+
+    async fn publish(&self, state: NetworkState) -> Result<()> {
+        let changed = {
+            let mut current = self.state.lock().unwrap();
+            let changed = *current != state;
+            *current = state.clone();
+            changed
+        };
+        if changed {
+            self.tx.send(Request::Updated(state)).await?;
+        }
+        Ok(())
+    }
+
+    async fn activate(
+        &self,
+        connection: &str,
+        device: Option<&str>,
+        specific_object: Option<&str>,
+    ) -> Result<()> {
+        let (_active,): (Path<'static>,) = self
+            .proxy(NM_DBUS_PATH)
+            .method_call(
+                NM_DBUS_INTERFACE,
+                "ActivateConnection",
+                (
+                    Path::new(connection).map_err(|e| anyhow!("{e}"))?,
+                    Path::new(device.unwrap_or("/")).map_err(|e| anyhow!("{e}"))?,
+                    Path::new(specific_object.unwrap_or("/")).map_err(|e| anyhow!("{e}"))?,
+                ),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn deactivate(&self, active_connection: &str) -> Result<()> {
+        self.proxy(NM_DBUS_PATH)
+            .method_call::<(), _, _, _>(
+                NM_DBUS_INTERFACE,
+                "DeactivateConnection",
+                (Path::new(active_connection).map_err(|e| anyhow!("{e}"))?,),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn execute(&self, request: NetworkRequest) -> Result<()> {
+        match request {
+            NetworkRequest::RequestScan { device } => {
+                self.proxy(device)
+                    .method_call::<(), _, _, _>(
+                        NM_WIRELESS_DBUS_INTERFACE,
+                        "RequestScan",
+                        (PropMap::new(),),
+                    )
+                    .await?;
+            }
+            NetworkRequest::ConnectWifi {
+                device,
+                access_point,
+                password,
+            } => {
+                let saved = {
+                    let state = self.state.lock().unwrap();
+                    let ssid = state
+                        .devices
+                        .iter()
+                        .flat_map(|d| d.access_points.iter())
+                        .find(|ap| ap.path == access_point)
+                        .map(|ap| ap.ssid.clone());
+                    ssid.and_then(|ssid| {
+                        state
+                            .connections
+                            .iter()
+                            .find(|c| c.ssid == ssid)
+                            .map(|c| c.path.clone())
+                    })
+                };
+                match (saved, password) {
+                    (Some(connection), None) => {
+                        self.activate(&connection, Some(&device), Some(&access_point))
+                            .await?;
+                    }
+                    (_, password) => {
+                        let mut settings = HashMap::<String, PropMap>::new();
+                        if let Some(password) = password {
+                            settings.insert(
+                                "802-11-wireless-security".to_string(),
+                                PropMap::from([
+                                    (
+                                        "key-mgmt".to_string(),
+                                        Variant(Box::new("wpa-psk".to_string()) as Box<dyn RefArg>),
+                                    ),
+                                    (
+                                        "psk".to_string(),
+                                        Variant(Box::new(password) as Box<dyn RefArg>),
+                                    ),
+                                ]),
+                            );
+                        }
+                        let (_connection, _active): (Path<'static>, Path<'static>) = self
+                            .proxy(NM_DBUS_PATH)
+                            .method_call(
+                                NM_DBUS_INTERFACE,
+                                "AddAndActivateConnection",
+                                (
+                                    settings,
+                                    Path::new(device).map_err(|e| anyhow!("{e}"))?,
+                                    Path::new(access_point).map_err(|e| anyhow!("{e}"))?,
+                                ),
+                            )
+                            .await?;
+                    }
+                }
+            }
+            NetworkRequest::ActivateConnection { connection, device } => {
+                self.activate(&connection, device.as_deref(), None).await?;
+            }
+            NetworkRequest::DeactivateConnection { active_connection } => {
+                self.deactivate(&active_connection).await?;
+            }
+            NetworkRequest::DisconnectDevice { device } => {
+                self.proxy(device)
+                    .method_call::<(), _, _, _>(NM_DEVICE_DBUS_INTERFACE, "Disconnect", ())
+                    .await?;
+            }
+            NetworkRequest::ToggleVpn { connection } => {
+                let active = self
+                    .state
+                    .lock()
+                    .unwrap()
+                    .active_of(&connection)
+                    .map(|c| c.path.clone());
+                match active {
+                    Some(active_connection) => self.deactivate(&active_connection).await?,
+                    None => self.activate(&connection, None, None).await?,
+                }
+            }
+            NetworkRequest::SetWirelessEnabled(enabled) => {
+                self.proxy(NM_DBUS_PATH)
+                    .set(NM_DBUS_INTERFACE, "WirelessEnabled", enabled)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn create_dbus_connection(
+    tokio: tokio::runtime::Handle,
+    settings: DBusSettings,
+    mut rx: Receiver<Response>,
+    tx: Sender<Request>,
+) -> Result<()> {
+    let (tokio_handle, conn) = settings.connect_system_sync()?;
+    let _handle = tokio.spawn(async {
+        let err = tokio_handle.await;
+        panic!("Lost connection to D-Bus: {}", err);
+    });
+
+    let worker = Arc::new(NetworkWorker {
+        conn: conn.clone(),
+        tx,
+        state: Default::default(),
+    });
+
+    let changed = Arc::new(Notify::new());
+    let changed2 = changed.clone();
+    let pending = Arc::new(Mutex::new(PendingChanges::default()));
+    let pending2 = pending.clone();
+    let signals = conn
+        .add_match(
+            MatchRule::new()
+                .with_type(MessageType::Signal)
+                .with_sender(NM_DBUS_DEST),
+        )
+        .await?
+        .msg_cb(move |message| {
+            if let Some(path) = message.path() {
+                pending2.lock().unwrap().add(&path);
+            }
+            changed2.notify_one();
+            true
+        });
+
+    let worker2 = worker.clone();
+    tokio.spawn(async move {
+        if let Err(e) = worker2.refresh().await {
+            debug!("failed to refresh network state: {e}");
+        }
+        loop {
+            changed.notified().await;
+            tokio::time::sleep(REFRESH_DELAY).await;
+            let changes = std::mem::take(&mut *pending.lock().unwrap());
+            if let Err(e) = worker2.update(changes).await {
+                debug!("failed to update network state: {e}");
+            }
+        }
+    });
+
+    while let Some(Response::Request(request)) = rx.recv().await {
+        if let Err(e) = worker.execute(request.clone()).await {
+            warn!("failed to execute network request {request:?}: {e}");
+        }
+    }
+
+    drop(signals);
+    Ok(())
+}
+
+structstruck::strike! {
+    #[strikethrough[derive(Debug)]]
+    #[derive(Resource)]
+    pub struct NetworkController {
+        rx: Receiver<Request>,
+        tx: Sender<Response>,
+        pub state: NetworkState,
+    }
+}
+
+impl std::ops::Deref for NetworkController {
+    type Target = NetworkState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl FromWorld for NetworkController {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<DBusSettings>().cloned().unwrap_or_default();
+        let tokio = world.non_send_resource::<TokioRuntime>();
+        let (request_tx, request_rx) = channel(16);
+        let (response_tx, response_rx) = channel(16);
+        let handle = tokio.handle().clone();
+        tokio.spawn(async {
+            match create_dbus_connection(handle, settings, response_rx, request_tx).await {
+                Ok(()) => {
+                    info!("network manager client exit");
+                }
+                Err(e) => {
+                    error!("network manager client exit with an error: {e}");
+                }
+            }
+        });
+        Self {
+            state: Default::default(),
+            rx: request_rx,
+            tx: response_tx,
+        }
+    }
+}
+
+pub fn update_network_controller(
+    mut events: MessageReader<NetworkRequest>,
+    mut network_controller: ResMut<NetworkController>,
+) {
+    while let Ok(request) = network_controller.bypass_change_detection().rx.try_recv() {
+        match request {
+            Request::Updated(state) => {
+                network_controller.state = state;
+            }
+        }
+    }
+    for event in events.read() {
+        if network_controller
+            .tx
+            .try_send(Response::Request(event.clone()))
+            .is_err()
+        {
+            warn!("failed to send network request: {event:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Instant};
+
+    use dbus::channel::MatchingReceiver;
+    use dbus_crossroads::Crossroads;
+
+    use super::*;
+    use crate::controller::dbus::test::PrivateSessionBus;
+
+    const DEVICE_PATH: &str = "/org/freedesktop/NetworkManager/Devices/1";
+    const AP_PATH: &str = "/org/freedesktop/NetworkManager/AccessPoint/1";
+    const CONNECTION_PATH: &str = "/org/freedesktop/NetworkManager/Settings/1";
+    const VPN_PATH: &str = "/org/freedesktop/NetworkManager/Settings/2";
+    const ACTIVE_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+
+    fn connection_settings(id: &str, kind: &str, ssid: &str) -> HashMap<String, PropMap> {
+        let mut settings = HashMap::from([(
+            "connection".to_string(),
+            PropMap::from([
+                ("id".to_string(), Variant(Box::new(id.to_string()) as Box<dyn RefArg>)),
+                ("uuid".to_string(), Variant(Box::new(format!("uuid-{id}")) as Box<dyn RefArg>)),
+                ("type".to_string(), Variant(Box::new(kind.to_string()) as Box<dyn RefArg>)),
+            ]),
+        )]);
+        if !ssid.is_empty() {
+            settings.insert(
+                "802-11-wireless".to_string(),
+                PropMap::from([(
+                    "ssid".to_string(),
+                    Variant(Box::new(ssid.as_bytes().to_vec()) as Box<dyn RefArg>),
+                )]),
+            );
+        }
+        settings
+    }
+
+    /// A NetworkManager with one wifi device that is connected to `home`.
+    fn spawn_mock_network_manager(
+        bus: &PrivateSessionBus,
+        calls: mpsc::Sender<(String, String)>,
+    ) -> std::thread::JoinHandle<()> {
+        let conn = bus.connect();
+        conn.request_name(NM_DBUS_DEST, false, true, false).unwrap();
+        let mut cr = Crossroads::new();
+        let calls2 = calls.clone();
+        let nm = cr.register(NM_DBUS_INTERFACE, move |b| {
+            b.property("NetworkingEnabled").get(|_, _| Ok(true));
+            b.property("WirelessEnabled").get(|_, _| Ok(true));
+            b.property("PrimaryConnection")
+                .get(|_, _| Ok(Path::from(ACTIVE_PATH)));
+            b.property("Devices")
+                .get(|_, _| Ok(vec![Path::from(DEVICE_PATH)]));
+            b.property("ActiveConnections")
+                .get(|_, _| Ok(vec![Path::from(ACTIVE_PATH)]));
+            b.method(
+                "ActivateConnection",
+                ("connection", "device", "specific_object"),
+                ("active_connection",),
+                move |_, _, (connection, _, _): (Path<'static>, Path<'static>, Path<'static>)| {
+                    calls2
+                        .send(("ActivateConnection".to_string(), connection.to_string()))
+                        .unwrap();
+                    Ok((Path::from(ACTIVE_PATH),))
+                },
+            );
+        });
+        let device = cr.register(NM_DEVICE_DBUS_INTERFACE, |b| {
+            b.property("Interface").get(|_, _| Ok("wlan0".to_string()));
+            b.property("DeviceType").get(|_, _| Ok(2u32));
+            b.property("State").get(|_, _| Ok(100u32));
+            b.property("ActiveConnection")
+                .get(|_, _| Ok(Path::from(ACTIVE_PATH)));
+        });
+        let wireless = cr.register(NM_WIRELESS_DBUS_INTERFACE, |b| {
+            b.property("AccessPoints")
+                .get(|_, _| Ok(vec![Path::from(AP_PATH)]));
+            b.property("ActiveAccessPoint")
+                .get(|_, _| Ok(Path::from(AP_PATH)));
+        });
+        let access_point = cr.register(NM_ACCESS_POINT_DBUS_INTERFACE, |b| {
+            b.property("Ssid").get(|_, _| Ok(b"home".to_vec()));
+            b.property("Strength").get(|_, _| Ok(80u8));
+            b.property("Frequency").get(|_, _| Ok(2412u32));
+            b.property("Flags").get(|_, _| Ok(1u32));
+        });
+        let active = cr.register(NM_ACTIVE_CONNECTION_DBUS_INTERFACE, |b| {
+            b.property("Id").get(|_, _| Ok("home".to_string()));
+            b.property("Type").get(|_, _| Ok("802-11-wireless".to_string()));
+            b.property("Connection")
+                .get(|_, _| Ok(Path::from(CONNECTION_PATH)));
+            b.property("State").get(|_, _| Ok(2u32));
+            b.property("Vpn").get(|_, _| Ok(false));
+            b.property("Devices")
+                .get(|_, _| Ok(vec![Path::from(DEVICE_PATH)]));
+        });
+        let settings = cr.register(NM_SETTINGS_DBUS_INTERFACE, |b| {
+            b.method("ListConnections", (), ("connections",), |_, _, _: ()| {
+                Ok((vec![Path::from(CONNECTION_PATH), Path::from(VPN_PATH)],))
+            });
+        });
+        let connection = cr.register(NM_CONNECTION_DBUS_INTERFACE, |b| {
+            b.method("GetSettings", (), ("settings",), |ctx, _, _: ()| {
+                Ok((if &**ctx.path() == VPN_PATH {
+                    connection_settings("work", "vpn", "")
+                } else {
+                    connection_settings("home", "802-11-wireless", "home")
+                },))
+            });
+        });
+        cr.insert(NM_DBUS_PATH, &[nm], ());
+        cr.insert(DEVICE_PATH, &[device, wireless], ());
+        cr.insert(AP_PATH, &[access_point], ());
+        cr.insert(ACTIVE_PATH, &[active], ());
+        cr.insert(NM_SETTINGS_DBUS_PATH, &[settings], ());
+        cr.insert(CONNECTION_PATH, &[connection], ());
+        cr.insert(VPN_PATH, &[connection], ());
+        conn.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |msg, conn| {
+                cr.handle_message(msg, conn).unwrap();
+                true
+            }),
+        );
+        std::thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                conn.process(Duration::from_millis(50)).unwrap();
+            }
+        })
+    }
+
+    #[test]
+    fn test_pending_changes() {
+        let mut changes = PendingChanges::default();
+        changes.add(DEVICE_PATH);
+        changes.add(AP_PATH);
+        changes.add(ACTIVE_PATH);
+        changes.add(CONNECTION_PATH);
+        changes.add(NM_SETTINGS_DBUS_PATH);
+        assert_eq!(
+            changes,
+            PendingChanges {
+                manager: false,
+                settings: true,
+                devices: HashSet::from([DEVICE_PATH.to_string()]),
+                access_points: HashSet::from([AP_PATH.to_string()]),
+                active_connections: HashSet::from([ACTIVE_PATH.to_string()]),
+                connections: HashSet::from([CONNECTION_PATH.to_string()]),
+            }
+        );
+        changes.add(NM_DBUS_PATH);
+        assert!(changes.manager);
+    }
+
+    #[test]
+    fn test_network_manager_client() {
+        let bus = PrivateSessionBus::spawn();
+        let (calls_tx, calls_rx) = mpsc::channel();
+        let handle = spawn_mock_network_manager(&bus, calls_tx);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (request_tx, mut request_rx) = channel(16);
+        let (response_tx, response_rx) = channel(16);
+        runtime.spawn(create_dbus_connection(
+            runtime.handle().clone(),
+            bus.settings(),
+            response_rx,
+            request_tx,
+        ));
+
+        let Some(Request::Updated(state)) = request_rx.blocking_recv() else {
+            panic!("network state not received");
+        };
+        assert!(state.wireless_enabled);
+        assert_eq!(state.primary().unwrap().id, "home");
+        let wifi = state.wifi_device().unwrap();
+        assert_eq!(wifi.interface, "wlan0");
+        assert_eq!(wifi.state, NetworkDeviceState::Activated);
+        let access_point = wifi.active_access_point().unwrap();
+        assert_eq!(access_point.ssid, "home");
+        assert!(access_point.secured);
+        assert_eq!(state.vpns().map(|c| &*c.id).collect::<Vec<_>>(), vec!["work"]);
+
+        response_tx
+            .blocking_send(Response::Request(NetworkRequest::ConnectWifi {
+                device: DEVICE_PATH.to_string(),
+                access_point: AP_PATH.to_string(),
+                password: None,
+            }))
+            .unwrap();
+        assert_eq!(
+            calls_rx.recv_timeout(Duration::from_secs(3)).unwrap(),
+            ("ActivateConnection".to_string(), CONNECTION_PATH.to_string())
+        );
+        response_tx
+            .blocking_send(Response::Request(NetworkRequest::ToggleVpn {
+                connection: VPN_PATH.to_string(),
+            }))
+            .unwrap();
+        assert_eq!(
+            calls_rx.recv_timeout(Duration::from_secs(3)).unwrap(),
+            ("ActivateConnection".to_string(), VPN_PATH.to_string())
+        );
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,277 @@
+use std::{f32::consts::PI, time::Duration};
+
+use chrono::{Datelike, Local, Timelike, Utc};
+use dway_util::gamma::{ClientGamma, GammaLut, GammaSize, OutputGamma, NEUTRAL_TEMPERATURE};
+use smart_default::SmartDefault;
+
+use crate::prelude::*;
+
+/// The zenith of the sun at sunrise and sunset in degrees, including the refraction of the
+/// atmosphere.
+const SUNRISE_ZENITH: f32 = 90.833;
+
+#[derive(Resource, Clone, Debug, Reflect, SmartDefault)]
+pub struct NightLightSettings {
+    pub enabled: bool,
+    #[default(NEUTRAL_TEMPERATURE)]
+    pub day_temperature: u32,
+    #[default(4000)]
+    pub night_temperature: u32,
+    /// The latitude and longitude in degrees, the sun rises and sets at the fixed local hours
+    /// without it.
+    pub location: Option<Vec2>,
+    #[default(6.0)]
+    pub sunrise: f32,
+    #[default(20.0)]
+    pub sunset: f32,
+    /// The duration of the change around sunrise and sunset.
+    #[default(Duration::from_secs(30 * 60))]
+    pub transition: Duration,
+    /// The duration of the fade after the night light is toggled.
+    #[default(Duration::from_secs(2))]
+    pub fade: Duration,
+}
+
+#[derive(Message, Debug, Clone, Reflect)]
+pub enum NightLightRequest {
+    SetEnabled(bool),
+    Toggle,
+    SetNightTemperature(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum Daylight {
+    /// The hours of sunrise and sunset in UTC.
+    Normal {
+        sunrise: f32,
+        sunset: f32,
+    },
+    PolarDay,
+    PolarNight,
+}
+
+/// The times of sunrise and sunset, from the approximation of the NOAA.
+pub fn sun_times(day_of_year: u32, latitude: f32, longitude: f32) -> Daylight {
+    let year_angle = 2.0 * PI / 365.0 * (day_of_year as f32 - 0.5);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * year_angle.cos()
+            - 0.032077 * year_angle.sin()
+            - 0.014615 * (2.0 * year_angle).cos()
+            - 0.040849 * (2.0 * year_angle).sin());
+    let declination = 0.006918 - 0.399912 * year_angle.cos() + 0.070257 * year_angle.sin()
+        - 0.006758 * (2.0 * year_angle).cos()
+        + 0.000907 * (2.0 * year_angle).sin()
+        - 0.002697 * (3.0 * year_angle).cos()
+        + 0.00148 * (3.0 * year_angle).sin();
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = SUNRISE_ZENITH.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+    if cos_hour_angle > 1.0 {
+        return Daylight::PolarNight;
+    }
+    if cos_hour_angle < -1.0 {
+        return Daylight::PolarDay;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    let hours = |minutes: f32| (minutes / 60.0).rem_euclid(24.0);
+    Daylight::Normal {
+        sunrise: hours(720.0 - 4.0 * (longitude + hour_angle) - equation_of_time),
+        sunset: hours(720.0 - 4.0 * (longitude - hour_angle) - equation_of_time),
+    }
+}
+
+/// How far it is into the night at an hour of the day, from 0 at day to 1 at night. The change
+/// takes `transition` hours and is centered on sunrise and sunset.
+pub fn night_amount(now: f32, sunrise: f32, sunset: f32, transition: f32) -> f32 {
+    let offset = |hour: f32| (now - hour + 12.0).rem_euclid(24.0) - 12.0;
+    if transition > 0.0 {
+        if offset(sunset).abs() < transition / 2.0 {
+            return 0.5 + offset(sunset) / transition;
+        }
+        if offset(sunrise).abs() < transition / 2.0 {
+            return 0.5 - offset(sunrise) / transition;
+        }
+    }
+    let day = (now - sunrise).rem_euclid(24.0) < (sunset - sunrise).rem_euclid(24.0);
+    if day {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+fn hour_of_day(time: &impl Timelike) -> f32 {
+    time.hour() as f32 + time.minute() as f32 / 60.0 + time.second() as f32 / 3600.0
+}
+
+#[derive(Resource, Debug, Clone, Reflect, SmartDefault)]
+pub struct NightLightController {
+    /// The color temperature of the outputs.
+    #[default(NEUTRAL_TEMPERATURE)]
+    pub temperature: u32,
+    /// The color temperature the outputs fade to.
+    #[default(NEUTRAL_TEMPERATURE)]
+    pub target: u32,
+    #[default(NEUTRAL_TEMPERATURE as f32)]
+    current: f32,
+}
+
+impl NightLightController {
+    /// Move the temperature toward the target, a full change from day to night takes `fade`.
+    fn step(&mut self, delta: Duration, fade: Duration, range: f32) -> bool {
+        let target = self.target as f32;
+        if fade.is_zero() {
+            self.current = target;
+        } else {
+            let step = range.max(100.0) * delta.as_secs_f32() / fade.as_secs_f32();
+            self.current += (target - self.current).clamp(-step, step);
+        }
+        let temperature = self.current.round() as u32;
+        let changed = temperature != self.temperature;
+        self.temperature = temperature;
+        changed
+    }
+}
+
+pub fn update_night_light_controller(
+    mut events: MessageReader<NightLightRequest>,
+    mut settings: ResMut<NightLightSettings>,
+    mut night_light_controller: ResMut<NightLightController>,
+    time: Res<Time>,
+) {
+    for event in events.read() {
+        match event {
+            NightLightRequest::SetEnabled(enabled) => settings.enabled = *enabled,
+            NightLightRequest::Toggle => settings.enabled = !settings.enabled,
+            NightLightRequest::SetNightTemperature(temperature) => {
+                settings.night_temperature = *temperature
+            }
+        }
+    }
+
+    let night = if !settings.enabled {
+        0.0
+    } else {
+        let transition = settings.transition.as_secs_f32() / 3600.0;
+        match settings.location {
+            Some(location) => {
+                let now = Utc::now();
+                match sun_times(now.ordinal(), location.x, location.y) {
+                    Daylight::Normal { sunrise, sunset } => {
+                        night_amount(hour_of_day(&now), sunrise, sunset, transition)
+                    }
+                    Daylight::PolarDay => 0.0,
+                    Daylight::PolarNight => 1.0,
+                }
+            }
+            None => night_amount(
+                hour_of_day(&Local::now()),
+                settings.sunrise,
+                settings.sunset,
+                transition,
+            ),
+        }
+    };
+    let day_temperature = settings.day_temperature as f32;
+    let night_temperature = settings.night_temperature as f32;
+    let target = day_temperature + (night_temperature - day_temperature) * night;
+
+    let controller = night_light_controller.bypass_change_detection();
+    let mut changed = false;
+    if controller.target != target.round() as u32 {
+        controller.target = target.round() as u32;
+        changed = true;
+    }
+    changed |= controller.step(
+        time.delta(),
+        settings.fade,
+        (day_temperature - night_temperature).abs(),
+    );
+    if changed {
+        night_light_controller.set_changed();
+    }
+}
+
+/// Apply the gamma table of the client with gamma control of an output, or the color
+/// temperature of the night light.
+pub fn update_output_gamma(
+    output_query: Query<(
+        Entity,
+        Ref<GammaSize>,
+        Option<Ref<ClientGamma>>,
+        Option<&OutputGamma>,
+    )>,
+    mut removed: RemovedComponents<ClientGamma>,
+    controller: Res<NightLightController>,
+    mut commands: Commands,
+) {
+    let removed = removed.read().collect::<Vec<_>>();
+    for (entity, size, client, output_gamma) in &output_query {
+        if !controller.is_changed()
+            && !size.is_changed()
+            && !client.as_ref().is_some_and(|c| c.is_changed())
+            && !removed.contains(&entity)
+        {
+            continue;
+        }
+        let lut = match client {
+            Some(client) => Some(client.0.clone()),
+            None if controller.temperature != NEUTRAL_TEMPERATURE => {
+                Some(GammaLut::from_temperature(size.0, controller.temperature))
+            }
+            None => None,
+        };
+        match lut {
+            Some(lut) if output_gamma.map(|g| &g.0) != Some(&lut) => {
+                commands.entity(entity).insert(OutputGamma(lut));
+            }
+            None if output_gamma.is_some() => {
+                commands.entity(entity).remove::<OutputGamma>();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_times() {
+        // berlin at the march equinox, the sun rises at about 5:15 and sets at about 17:25 UTC
+        let Daylight::Normal { sunrise, sunset } = sun_times(80, 52.52, 13.40) else {
+            panic!();
+        };
+        assert!((sunrise - 5.25).abs() < 0.2, "{sunrise}");
+        assert!((sunset - 17.42).abs() < 0.2, "{sunset}");
+        assert_eq!(sun_times(172, 78.0, 15.0), Daylight::PolarDay);
+        assert_eq!(sun_times(355, 78.0, 15.0), Daylight::PolarNight);
+    }
+
+    #[test]
+    fn test_night_amount() {
+        assert_eq!(night_amount(12.0, 6.0, 20.0, 1.0), 0.0);
+        assert_eq!(night_amount(23.0, 6.0, 20.0, 1.0), 1.0);
+        assert_eq!(night_amount(2.0, 6.0, 20.0, 1.0), 1.0);
+        assert_eq!(night_amount(20.0, 6.0, 20.0, 1.0), 0.5);
+        assert_eq!(night_amount(20.25, 6.0, 20.0, 1.0), 0.75);
+        assert_eq!(night_amount(5.75, 6.0, 20.0, 1.0), 0.75);
+        // sunset after midnight in UTC
+        assert_eq!(night_amount(23.0, 15.0, 1.0, 0.0), 0.0);
+        assert_eq!(night_amount(3.0, 15.0, 1.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_fade() {
+        let mut controller = NightLightController {
+            target: 4000,
+            ..Default::default()
+        };
+        assert!(controller.step(Duration::from_secs(1), Duration::from_secs(2), 2500.0));
+        assert_eq!(controller.temperature, 5250);
+        controller.step(Duration::from_secs(2), Duration::from_secs(2), 2500.0);
+        assert_eq!(controller.temperature, 4000);
+        assert!(!controller.step(Duration::from_secs(1), Duration::from_secs(2), 2500.0));
+    }
+}
@@ -1,17 +1,17 @@
-use dbus::channel::MatchingReceiver;
+use dbus::channel::{MatchingReceiver, Sender as _};
 use dbus::{
     arg::{messageitem::MessageItem, Variant},
     message::MatchRule,
+    Message,
 };
 use dbus_crossroads::Crossroads;
-use dbus_tokio::connection;
 use derive_builder::Builder;
 use dway_util::tokio::TokioRuntime;
 use indexmap::IndexMap;
 use smart_default::SmartDefault;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
-use super::dbus::DBusController;
+use super::dbus::{DBusController, DBusSettings};
 use crate::prelude::*;
 use std::{
     collections::HashMap,
@@ -27,15 +27,46 @@ pub const NOTIFY_DBUS_PATH: &str = "/org/freedesktop/Notifications";
 pub const NOTIFY_DBUS_INTERFACE: &str = "org.freedesktop.Notifications";
 pub const NOTIFY_DBUS_MEMBER: &str = "Notify";
 
+pub const NOTIFY_CAPABILITIES: &[&str] = &[
+    "action-icons",
+    "actions",
+    "body",
+    "body-hyperlinks",
+    "body-images",
+    "body-markup",
+    "icon-static",
+    "persistence",
+];
+
 structstruck::strike! {
     #[strikethrough[derive(Debug, Clone, Reflect)]]
     #[derive(Message)]
     pub enum NotifyRequest{
-        SendNotify(NotifyData)
+        SendNotify(NotifyData),
+        /// the user clicked an action button
+        InvokeAction{
+            id: u32,
+            action: String,
+        },
+        /// the user dismissed the notification
+        Dismiss(u32),
+        /// remove a notification from the history
+        Remove(u32),
+        ClearHistory,
+        SetDoNotDisturb(bool),
     }
 }
 
-#[derive(Default, Clone, Copy)]
+/// The `reason` argument of the `NotificationClosed` signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum NotifyCloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    ClosedByCall = 3,
+    Undefined = 4,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
 pub enum NotifyUrgency {
     Low = 0,
     #[default]
@@ -43,6 +74,108 @@ pub enum NotifyUrgency {
     Critical = 2,
 }
 
+impl From<u8> for NotifyUrgency {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Low,
+            2 => Self::Critical,
+            _ => Self::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub enum NotifyImage {
+    /// decoded `image-data` hint, always converted to RGBA8
+    Data {
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+    },
+    /// `image-path` hint or a path in `app_icon`
+    Path(String),
+    /// an icon name from the freedesktop icon theme
+    Icon(String),
+}
+
+impl NotifyImage {
+    /// Parse the `(iiibiiay)` structure used by the `image-data` hint.
+    pub fn from_image_data(item: &MessageItem) -> Option<Self> {
+        let MessageItem::Struct(fields) = unwrap_variant(item) else {
+            return None;
+        };
+        let [MessageItem::Int32(width), MessageItem::Int32(height), MessageItem::Int32(rowstride), MessageItem::Bool(has_alpha), MessageItem::Int32(bits_per_sample), MessageItem::Int32(channels), MessageItem::Array(data)] =
+            &fields[..]
+        else {
+            return None;
+        };
+        if *bits_per_sample != 8 || *width <= 0 || *height <= 0 {
+            return None;
+        }
+        let data: Vec<u8> = data
+            .iter()
+            .filter_map(|b| match b {
+                MessageItem::Byte(b) => Some(*b),
+                _ => None,
+            })
+            .collect();
+        Self::from_raw(
+            *width as u32,
+            *height as u32,
+            *rowstride as usize,
+            *has_alpha,
+            *channels as usize,
+            &data,
+        )
+    }
+
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        rowstride: usize,
+        has_alpha: bool,
+        channels: usize,
+        data: &[u8],
+    ) -> Option<Self> {
+        let expect_channels = if has_alpha { 4 } else { 3 };
+        if channels != expect_channels || rowstride < width as usize * channels {
+            return None;
+        }
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height as usize {
+            let row = data.get(y * rowstride..y * rowstride + width as usize * channels)?;
+            for pixel in row.chunks_exact(channels) {
+                rgba.extend_from_slice(&pixel[..3]);
+                rgba.push(if has_alpha { pixel[3] } else { u8::MAX });
+            }
+        }
+        Some(Self::Data {
+            width,
+            height,
+            rgba,
+        })
+    }
+
+    pub fn from_path_or_icon(name: &str) -> Option<Self> {
+        if name.is_empty() {
+            return None;
+        }
+        let name = name.strip_prefix("file://").unwrap_or(name);
+        if name.starts_with('/') {
+            Some(Self::Path(name.to_string()))
+        } else {
+            Some(Self::Icon(name.to_string()))
+        }
+    }
+}
+
+fn unwrap_variant(mut item: &MessageItem) -> &MessageItem {
+    while let MessageItem::Variant(inner) = item {
+        item = inner;
+    }
+    item
+}
+
 structstruck::strike! {
     #[strikethrough[derive(Debug, Clone, Reflect)]]
     #[derive(Default, Builder)]
@@ -59,18 +192,188 @@ structstruck::strike! {
         }>,
         #[reflect(ignore)]
         pub hints: HashMap<String, Variant<MessageItem>>,
+        /// `None` lets the server choose, `Some(Duration::ZERO)` never expires
         pub expire_timeout: Option<Duration>,
     }
 }
 
-impl NotifyData {}
+impl NotifyData {
+    fn hint(&self, name: &str) -> Option<&MessageItem> {
+        self.hints.get(name).map(|v| unwrap_variant(&v.0))
+    }
+
+    fn hint_bool(&self, name: &str) -> bool {
+        match self.hint(name) {
+            Some(MessageItem::Bool(b)) => *b,
+            _ => false,
+        }
+    }
+
+    fn hint_str(&self, name: &str) -> Option<&str> {
+        match self.hint(name) {
+            Some(MessageItem::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn urgency(&self) -> NotifyUrgency {
+        match self.hint("urgency") {
+            Some(MessageItem::Byte(b)) => NotifyUrgency::from(*b),
+            Some(MessageItem::UInt32(b)) => NotifyUrgency::from(*b as u8),
+            Some(MessageItem::Int32(b)) => NotifyUrgency::from(*b as u8),
+            _ => NotifyUrgency::Normal,
+        }
+    }
+
+    /// The notification stays on screen until it is dismissed.
+    pub fn resident(&self) -> bool {
+        self.hint_bool("resident")
+    }
+
+    /// The notification is not kept in the history center.
+    pub fn transient(&self) -> bool {
+        self.hint_bool("transient")
+    }
+
+    pub fn category(&self) -> Option<&str> {
+        self.hint_str("category")
+    }
+
+    pub fn desktop_entry(&self) -> Option<&str> {
+        self.hint_str("desktop-entry")
+    }
+
+    /// Resolve the image following the priority order of the specification.
+    pub fn image(&self) -> Option<NotifyImage> {
+        ["image-data", "image_data", "icon_data"]
+            .iter()
+            .find_map(|name| self.hint(name).and_then(NotifyImage::from_image_data))
+            .or_else(|| {
+                ["image-path", "image_path"]
+                    .iter()
+                    .find_map(|name| self.hint_str(name).and_then(NotifyImage::from_path_or_icon))
+            })
+            .or_else(|| NotifyImage::from_path_or_icon(&self.app_icon))
+    }
+
+    pub fn body_spans(&self) -> Vec<NotifyBodySpan> {
+        parse_body_markup(&self.body)
+    }
+}
+
+/// A run of body text that shares the same style.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect)]
+pub struct NotifyBodySpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub link: Option<String>,
+}
+
+/// Parse the markup subset allowed by the specification: `<b>`, `<i>`, `<u>`,
+/// `<a href="...">` and `<img src="..." alt="..."/>`. Unknown tags are stripped.
+pub fn parse_body_markup(body: &str) -> Vec<NotifyBodySpan> {
+    let mut spans: Vec<NotifyBodySpan> = vec![];
+    let mut style = NotifyBodySpan::default();
+    let mut rest = body;
+
+    while !rest.is_empty() {
+        let Some(begin) = rest.find('<') else {
+            push_text(&mut spans, &style, unescape_markup(rest));
+            break;
+        };
+        push_text(&mut spans, &style, unescape_markup(&rest[..begin]));
+        let Some(end) = rest[begin..].find('>') else {
+            push_text(&mut spans, &style, unescape_markup(&rest[begin..]));
+            break;
+        };
+        let tag = rest[begin + 1..begin + end].trim().trim_end_matches('/').trim();
+        rest = &rest[begin + end + 1..];
+
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag.trim()),
+            None => (false, tag),
+        };
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        match (&*name.to_ascii_lowercase(), closing) {
+            ("b", closing) => style.bold = !closing,
+            ("i", closing) => style.italic = !closing,
+            ("u", closing) => style.underline = !closing,
+            ("a", false) => style.link = markup_attribute(attributes, "href"),
+            ("a", true) => style.link = None,
+            ("br", _) => push_text(&mut spans, &style, "\n".to_string()),
+            ("img", false) => {
+                if let Some(alt) = markup_attribute(attributes, "alt") {
+                    push_text(&mut spans, &style, alt);
+                }
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+fn push_text(spans: &mut Vec<NotifyBodySpan>, style: &NotifyBodySpan, text: String) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last)
+            if last.bold == style.bold
+                && last.italic == style.italic
+                && last.underline == style.underline
+                && last.link == style.link =>
+        {
+            last.text += &text;
+        }
+        _ => spans.push(NotifyBodySpan {
+            text,
+            ..style.clone()
+        }),
+    }
+}
+
+fn markup_attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(index) = rest.find('=') {
+        let key = rest[..index].trim();
+        let value = rest[index + 1..].trim_start();
+        let (value, remain) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                (&value[..end], &value[(end + 1).min(value.len())..])
+            }
+            _ => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            }
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some(unescape_markup(value));
+        }
+        rest = remain;
+    }
+    None
+}
+
+fn unescape_markup(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
 
 enum Request {
     ReceiveNotify(NotifyHistory),
     CloseNotify(u32),
 }
 enum Response {
-    CloseNotify(u32),
+    CloseNotify(u32, NotifyCloseReason),
+    ActionInvoked(u32, String),
 }
 
 struct DbusWorker {
@@ -80,6 +383,7 @@ struct DbusWorker {
 
 async fn create_dbus_connection(
     tokio: tokio::runtime::Handle,
+    settings: DBusSettings,
     mut rx: Receiver<Response>,
     tx: Sender<Request>,
 ) -> Result<()> {
@@ -90,7 +394,7 @@ async fn create_dbus_connection(
     let worker2 = worker.clone();
     let worker3 = worker.clone();
 
-    let (tokio_handle, conn) = connection::new_session_sync()?;
+    let (tokio_handle, conn) = settings.connect_sync()?;
     let _handle = tokio.spawn(async {
         let err = tokio_handle.await;
         panic!("Lost connection to D-Bus: {}", err);
@@ -110,7 +414,7 @@ async fn create_dbus_connection(
 
     let interface_token = cr.register(NOTIFY_DBUS_INTERFACE, |b| {
         b.method("GetCapabilities", (), ("reply",), |_ctx, _cr, _args: ()| {
-            Ok((vec!["action-icons", "actions", "body"],)) // TODO: more capabilities
+            Ok((NOTIFY_CAPABILITIES.to_vec(),))
         });
         b.method_with_cr_async(
             "CloseNotification",
@@ -119,7 +423,7 @@ async fn create_dbus_connection(
             move |mut ctx, _cr, (id,): (u32,)| {
                 let worker3 = worker3.clone();
                 async move {
-                    let _ = worker3.tx.send(Request::CloseNotify(id));
+                    let _ = worker3.tx.send(Request::CloseNotify(id)).await;
                     ctx.reply(Ok(()))
                 }
             },
@@ -166,13 +470,16 @@ async fn create_dbus_connection(
             )| {
                 let worker2 = worker2.clone();
                 async move {
-                    let id = worker2.notify_id.fetch_add(1, Ordering::AcqRel);
+                    let id = if replaces_id != 0 {
+                        replaces_id
+                    } else {
+                        worker2.notify_id.fetch_add(1, Ordering::AcqRel)
+                    };
                     let _ = worker2
                         .tx
                         .send(Request::ReceiveNotify(NotifyHistory {
                             id,
                             time: SystemTime::now(),
-                            closed: false,
                             data: NotifyData {
                                 replaces_id,
                                 app_icon,
@@ -190,12 +497,13 @@ async fn create_dbus_connection(
                                     })
                                     .collect(),
                                 hints,
-                                expire_timeout: if expire_timeout > 1 {
+                                expire_timeout: if expire_timeout >= 0 {
                                     Some(Duration::from_millis(expire_timeout as u64))
                                 } else {
                                     None
                                 },
                             },
+                            ..Default::default()
                         }))
                         .await;
                     ctx.reply(Ok((id,)))
@@ -216,8 +524,22 @@ async fn create_dbus_connection(
     );
 
     while let Some(response) = rx.recv().await {
-        match response {
-            Response::CloseNotify(_) => todo!(),
+        let message = match response {
+            Response::CloseNotify(id, reason) => Message::signal(
+                &NOTIFY_DBUS_PATH.into(),
+                &NOTIFY_DBUS_INTERFACE.into(),
+                &"NotificationClosed".into(),
+            )
+            .append2(id, reason as u32),
+            Response::ActionInvoked(id, action) => Message::signal(
+                &NOTIFY_DBUS_PATH.into(),
+                &NOTIFY_DBUS_INTERFACE.into(),
+                &"ActionInvoked".into(),
+            )
+            .append2(id, action),
+        };
+        if conn.send(message).is_err() {
+            error!("failed to send notification signal");
         }
     }
 
@@ -226,12 +548,13 @@ async fn create_dbus_connection(
 
 impl FromWorld for NotifyController {
     fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<DBusSettings>().cloned().unwrap_or_default();
         let tokio = world.non_send_resource::<TokioRuntime>();
         let (request_tx, request_rx) = channel(64);
         let (response_tx, response_rx) = channel(64);
         let handle = tokio.handle().clone();
         tokio.spawn(async {
-            match create_dbus_connection(handle, response_rx, request_tx).await {
+            match create_dbus_connection(handle, settings, response_rx, request_tx).await {
                 Ok(()) => {
                     info!("Notifications server exit");
                 }
@@ -255,17 +578,31 @@ structstruck::strike! {
         rx: Receiver<Request>,
         tx: Sender<Response>,
         pub notifys: IndexMap<u32,
-            #[derive(SmartDefault)]
+            #[derive(SmartDefault, Clone)]
             pub struct NotifyHistory{
                 pub id: u32,
+                /// the notification is no longer shown as a popup
                 pub closed: bool,
+                pub close_reason: Option<NotifyCloseReason>,
+                /// the notification was received while it should not be shown as a popup
+                pub silent: bool,
+                pub read: bool,
                 #[default(SystemTime::now())]
                 pub time: SystemTime,
+                pub expire_at: Option<SystemTime>,
                 pub data: NotifyData,
         }>,
     }
 }
 
+impl NotifyHistory {
+    /// Changes whenever the shown content of the notification does: a replaced notification has a
+    /// new receive time.
+    pub fn revision(&self) -> (u32, SystemTime, bool) {
+        (self.id, self.time, self.closed)
+    }
+}
+
 impl std::ops::Deref for NotifyController {
     type Target = IndexMap<u32, NotifyHistory>;
 
@@ -274,20 +611,142 @@ impl std::ops::Deref for NotifyController {
     }
 }
 
+impl NotifyController {
+    /// Notifications that should currently be shown as popups.
+    pub fn popups(&self) -> impl Iterator<Item = &NotifyHistory> {
+        self.notifys.values().filter(|n| !n.closed && !n.silent)
+    }
+
+    pub fn unread_count(&self) -> usize {
+        self.notifys.values().filter(|n| !n.read).count()
+    }
+
+    pub fn close(&mut self, id: u32, reason: NotifyCloseReason) {
+        let Some(notify) = self.notifys.get_mut(&id) else {
+            return;
+        };
+        if notify.closed {
+            return;
+        }
+        notify.closed = true;
+        notify.close_reason = Some(reason);
+        if notify.data.transient() {
+            self.notifys.shift_remove(&id);
+        }
+        if self.tx.try_send(Response::CloseNotify(id, reason)).is_err() {
+            warn!(id, "failed to emit NotificationClosed");
+        }
+    }
+
+    pub fn invoke_action(&mut self, id: u32, action: &str) {
+        let Some(notify) = self.notifys.get_mut(&id) else {
+            return;
+        };
+        notify.read = true;
+        let resident = notify.data.resident();
+        if self
+            .tx
+            .try_send(Response::ActionInvoked(id, action.to_string()))
+            .is_err()
+        {
+            warn!(id, action, "failed to emit ActionInvoked");
+        }
+        if !resident {
+            self.close(id, NotifyCloseReason::Dismissed);
+        }
+    }
+
+    fn receive(&mut self, mut notify: NotifyHistory, settings: &NotifySettings, now: SystemTime) {
+        let rule = settings.rule(&notify.data.app_name);
+        if rule == NotifyAppRule::Block {
+            let _ = self
+                .tx
+                .try_send(Response::CloseNotify(notify.id, NotifyCloseReason::Undefined));
+            return;
+        }
+        notify.silent = !settings.should_popup(&notify.data);
+        notify.expire_at = settings
+            .timeout(&notify.data)
+            .map(|timeout| now + timeout);
+        self.notifys.shift_remove(&notify.id);
+        self.notifys.insert(notify.id, notify);
+
+        while self.notifys.len() > settings.max_history {
+            let Some((&id, _)) = self.notifys.iter().find(|(_, n)| n.closed) else {
+                break;
+            };
+            self.notifys.shift_remove(&id);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum NotifyAppRule {
+    /// show popups for this application
+    #[default]
+    Show,
+    /// keep notifications in the history without popups
+    Silent,
+    /// drop every notification of this application
+    Block,
+}
+
+#[derive(Resource, Debug, Clone, SmartDefault, Reflect)]
+pub struct NotifySettings {
+    pub do_not_disturb: bool,
+    /// critical notifications are shown even in do-not-disturb mode
+    #[default(true)]
+    pub critical_bypass_do_not_disturb: bool,
+    #[default(Duration::from_secs(5))]
+    pub default_timeout: Duration,
+    #[default(128)]
+    pub max_history: usize,
+    pub app_rules: HashMap<String, NotifyAppRule>,
+}
+
+impl NotifySettings {
+    pub fn rule(&self, app_name: &str) -> NotifyAppRule {
+        self.app_rules.get(app_name).copied().unwrap_or_default()
+    }
+
+    pub fn should_popup(&self, data: &NotifyData) -> bool {
+        match self.rule(&data.app_name) {
+            NotifyAppRule::Show => {}
+            NotifyAppRule::Silent | NotifyAppRule::Block => return false,
+        }
+        if data.urgency() == NotifyUrgency::Critical && self.critical_bypass_do_not_disturb {
+            return true;
+        }
+        !self.do_not_disturb
+    }
+
+    /// Critical and resident notifications never expire.
+    pub fn timeout(&self, data: &NotifyData) -> Option<Duration> {
+        if data.urgency() == NotifyUrgency::Critical || data.resident() {
+            return None;
+        }
+        match data.expire_timeout {
+            None => Some(self.default_timeout),
+            Some(Duration::ZERO) => None,
+            Some(timeout) => Some(timeout),
+        }
+    }
+}
+
 pub fn do_receive_notify(
     dbus: NonSend<DBusController>,
     mut events: MessageReader<NotifyRequest>,
     mut notify_controller: ResMut<NotifyController>,
+    mut settings: ResMut<NotifySettings>,
 ) {
-    while let Ok(request) = notify_controller.rx.try_recv() {
+    let now = SystemTime::now();
+    while let Ok(request) = notify_controller.bypass_change_detection().rx.try_recv() {
         match request {
             Request::ReceiveNotify(notify) => {
-                notify_controller.notifys.insert(notify.id, notify);
+                notify_controller.receive(notify, &settings, now);
             }
             Request::CloseNotify(id) => {
-                if let Some(notify) = notify_controller.notifys.get_mut(&id) {
-                    notify.closed = true;
-                }
+                notify_controller.close(id, NotifyCloseReason::ClosedByCall);
             }
         }
     }
@@ -314,10 +773,199 @@ pub fn do_receive_notify(
                         &data.hints,
                         data.expire_timeout
                             .map(|t| t.as_millis() as i32)
-                            .unwrap_or(0),
+                            .unwrap_or(-1),
                     ),
                 );
             }
+            NotifyRequest::InvokeAction { id, action } => {
+                notify_controller.invoke_action(*id, action);
+            }
+            NotifyRequest::Dismiss(id) => {
+                if let Some(notify) = notify_controller.notifys.get_mut(id) {
+                    notify.read = true;
+                }
+                notify_controller.close(*id, NotifyCloseReason::Dismissed);
+            }
+            NotifyRequest::Remove(id) => {
+                notify_controller.close(*id, NotifyCloseReason::Dismissed);
+                notify_controller.notifys.shift_remove(id);
+            }
+            NotifyRequest::ClearHistory => {
+                let ids: Vec<u32> = notify_controller.notifys.keys().cloned().collect();
+                for id in ids {
+                    notify_controller.close(id, NotifyCloseReason::Dismissed);
+                }
+                notify_controller.notifys.clear();
+            }
+            NotifyRequest::SetDoNotDisturb(value) => {
+                settings.do_not_disturb = *value;
+            }
+        }
+    }
+}
+
+pub fn expire_notify(mut notify_controller: ResMut<NotifyController>) {
+    let now = SystemTime::now();
+    let expired: Vec<u32> = notify_controller
+        .notifys
+        .values()
+        .filter(|n| !n.closed && n.expire_at.map(|t| t <= now).unwrap_or(false))
+        .map(|n| n.id)
+        .collect();
+    for id in expired {
+        notify_controller.close(id, NotifyCloseReason::Expired);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use dbus::{arg::messageitem::MessageItemArray, Signature};
+
+    use super::*;
+    use crate::controller::dbus::test::PrivateSessionBus;
+
+    #[test]
+    fn test_parse_body_markup() {
+        let spans = parse_body_markup(
+            "plain <b>bold <u>both</u></b> <a href=\"https://dway.org\"><i>link</i></a> &amp; <img src=\"a.png\" alt=\"img\"/>",
+        );
+        assert_eq!(
+            spans,
+            vec![
+                NotifyBodySpan {
+                    text: "plain ".to_string(),
+                    ..Default::default()
+                },
+                NotifyBodySpan {
+                    text: "bold ".to_string(),
+                    bold: true,
+                    ..Default::default()
+                },
+                NotifyBodySpan {
+                    text: "both".to_string(),
+                    bold: true,
+                    underline: true,
+                    ..Default::default()
+                },
+                NotifyBodySpan {
+                    text: " ".to_string(),
+                    ..Default::default()
+                },
+                NotifyBodySpan {
+                    text: "link".to_string(),
+                    italic: true,
+                    link: Some("https://dway.org".to_string()),
+                    ..Default::default()
+                },
+                NotifyBodySpan {
+                    text: " & img".to_string(),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_hints() {
+        let image_data = MessageItem::Struct(vec![
+            MessageItem::Int32(2),
+            MessageItem::Int32(1),
+            MessageItem::Int32(8),
+            MessageItem::Bool(false),
+            MessageItem::Int32(8),
+            MessageItem::Int32(3),
+            MessageItem::Array(
+                MessageItemArray::new(
+                    [1u8, 2, 3, 4, 5, 6, 0, 0]
+                        .into_iter()
+                        .map(MessageItem::Byte)
+                        .collect(),
+                    Signature::from("ay"),
+                )
+                .unwrap(),
+            ),
+        ]);
+        let data = NotifyData {
+            hints: HashMap::from([
+                ("urgency".to_string(), Variant(MessageItem::Byte(2))),
+                ("image-data".to_string(), Variant(image_data)),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(data.urgency(), NotifyUrgency::Critical);
+        assert_eq!(
+            data.image(),
+            Some(NotifyImage::Data {
+                width: 2,
+                height: 1,
+                rgba: vec![1, 2, 3, 255, 4, 5, 6, 255],
+            })
+        );
+        assert_eq!(NotifySettings::default().timeout(&data), None);
+    }
+
+    #[test]
+    fn test_notify_server() {
+        let bus = PrivateSessionBus::spawn();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (request_tx, mut request_rx) = channel(64);
+        let (response_tx, response_rx) = channel(64);
+        runtime.spawn(create_dbus_connection(
+            runtime.handle().clone(),
+            bus.settings(),
+            response_rx,
+            request_tx,
+        ));
+
+        let client = bus.connect();
+        let proxy = client.with_proxy(NOTIFY_DBUS_DEST, NOTIFY_DBUS_PATH, Duration::from_secs(5));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let id = loop {
+            let result: Result<(u32,), _> = proxy.method_call(
+                NOTIFY_DBUS_INTERFACE,
+                NOTIFY_DBUS_MEMBER,
+                (
+                    "test",
+                    0u32,
+                    "",
+                    "summary",
+                    "body",
+                    vec!["default", "Open"],
+                    HashMap::<String, Variant<MessageItem>>::new(),
+                    -1i32,
+                ),
+            );
+            match result {
+                Ok((id,)) => break id,
+                Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+                Err(e) => panic!("failed to call Notify: {e}"),
+            }
+        };
+
+        let Some(Request::ReceiveNotify(notify)) = request_rx.blocking_recv() else {
+            panic!("notification not received");
+        };
+        assert_eq!(notify.id, id);
+        assert_eq!(notify.data.summary, "summary");
+        assert_eq!(notify.data.expire_timeout, None);
+        assert_eq!(notify.data.actions[0].text, "Open");
+
+        let closed = Arc::new(std::sync::Mutex::new(None));
+        let closed2 = closed.clone();
+        let rule = MatchRule::new_signal(NOTIFY_DBUS_INTERFACE, "NotificationClosed");
+        client.add_match(rule, move |(id, reason): (u32, u32), _, _| {
+            *closed2.lock().unwrap() = Some((id, reason));
+            true
+        })
+        .unwrap();
+        response_tx
+            .blocking_send(Response::CloseNotify(id, NotifyCloseReason::Expired))
+            .unwrap();
+        while closed.lock().unwrap().is_none() && Instant::now() < deadline {
+            client.process(Duration::from_millis(100)).unwrap();
         }
+        assert_eq!(*closed.lock().unwrap(), Some((id, NotifyCloseReason::Expired as u32)));
     }
 }
@@ -1,8 +1,659 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-pub struct PlayerController{
+use dbus::{
+    arg::{PropMap, RefArg},
+    message::MatchRule,
+    nonblock::{stdintf::org_freedesktop_dbus::Properties, Proxy, SyncConnection},
+    Path,
+};
+use dway_util::tokio::TokioRuntime;
+use indexmap::IndexMap;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use super::dbus::DBusSettings;
+use crate::prelude::*;
+
+pub const MPRIS_DBUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+pub const MPRIS_DBUS_PATH: &str = "/org/mpris/MediaPlayer2";
+pub const MPRIS_DBUS_INTERFACE: &str = "org.mpris.MediaPlayer2";
+pub const MPRIS_PLAYER_DBUS_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(2);
+const POSITION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+impl From<&str> for PlaybackStatus {
+    fn from(value: &str) -> Self {
+        match value {
+            "Playing" => Self::Playing,
+            "Paused" => Self::Paused,
+            _ => Self::Stopped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Reflect)]
+pub struct PlayerMetadata {
+    pub track_id: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub art_url: String,
+    pub length: Option<Duration>,
+}
+
+fn arg_strings(arg: &dyn RefArg) -> Vec<String> {
+    if let Some(s) = arg.as_str() {
+        return vec![s.to_string()];
+    }
+    arg.as_iter()
+        .map(|iter| iter.flat_map(arg_strings).collect())
+        .unwrap_or_default()
+}
+
+impl PlayerMetadata {
+    pub fn from_prop_map(map: &PropMap) -> Self {
+        let string = |key: &str| {
+            map.get(key)
+                .map(|v| arg_strings(&v.0).join(", "))
+                .unwrap_or_default()
+        };
+        Self {
+            track_id: string("mpris:trackid"),
+            title: string("xesam:title"),
+            artists: map
+                .get("xesam:artist")
+                .map(|v| arg_strings(&v.0))
+                .unwrap_or_default(),
+            album: string("xesam:album"),
+            art_url: string("mpris:artUrl"),
+            length: map
+                .get("mpris:length")
+                .and_then(|v| v.0.as_i64())
+                .filter(|l| *l > 0)
+                .map(|l| Duration::from_micros(l as u64)),
+        }
+    }
+
+    pub fn artist(&self) -> String {
+        self.artists.join(", ")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Reflect)]
+pub struct MediaPlayer {
+    /// the well-known bus name, `org.mpris.MediaPlayer2.*`
+    pub name: String,
+    pub identity: String,
+    pub desktop_entry: String,
+    pub status: PlaybackStatus,
+    pub metadata: PlayerMetadata,
+    pub position: Duration,
+    pub volume: f64,
+    pub rate: f64,
+    pub can_control: bool,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_seek: bool,
+}
+
+impl MediaPlayer {
+    /// The progress of the current track in `[0, 1]`.
+    pub fn progress(&self) -> f32 {
+        match self.metadata.length {
+            Some(length) if !length.is_zero() => {
+                (self.position.as_secs_f32() / length.as_secs_f32()).clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn update_player_properties(&mut self, properties: &PropMap) {
+        for (key, value) in properties {
+            let value = &value.0;
+            match &**key {
+                "PlaybackStatus" => {
+                    self.status = value.as_str().map(PlaybackStatus::from).unwrap_or_default()
+                }
+                "Metadata" => {
+                    if let Some(metadata) = dbus::arg::cast::<PropMap>(&**value) {
+                        self.metadata = PlayerMetadata::from_prop_map(metadata);
+                    } else if let Some(mut iter) = value.as_iter() {
+                        // metadata read from the bus is a generic dict
+                        let mut map = PropMap::new();
+                        while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+                            if let Some(k) = k.as_str() {
+                                map.insert(k.to_string(), dbus::arg::Variant(v.box_clone()));
+                            }
+                        }
+                        self.metadata = PlayerMetadata::from_prop_map(&map);
+                    }
+                }
+                "Position" => {
+                    self.position =
+                        Duration::from_micros(value.as_i64().unwrap_or_default().max(0) as u64)
+                }
+                "Volume" => self.volume = value.as_f64().unwrap_or_default(),
+                "Rate" => self.rate = value.as_f64().unwrap_or(1.0),
+                "CanControl" => self.can_control = value.as_i64().unwrap_or_default() != 0,
+                "CanPlay" => self.can_play = value.as_i64().unwrap_or_default() != 0,
+                "CanPause" => self.can_pause = value.as_i64().unwrap_or_default() != 0,
+                "CanGoNext" => self.can_go_next = value.as_i64().unwrap_or_default() != 0,
+                "CanGoPrevious" => {
+                    self.can_go_previous = value.as_i64().unwrap_or_default() != 0
+                }
+                "CanSeek" => self.can_seek = value.as_i64().unwrap_or_default() != 0,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum PlayerAction {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    /// seek relative to the current position, in seconds
+    Seek(f64),
+    SetPosition(Duration),
+    SetVolume(f64),
+    Raise,
+}
+
+/// A request to a media player. `player` is the bus name of the target, the
+/// active player is used if it is `None`.
+#[derive(Message, Debug, Clone, Reflect)]
+pub struct PlayerRequest {
+    pub player: Option<String>,
+    pub action: PlayerAction,
+}
+
+impl PlayerRequest {
+    pub fn new(player: impl Into<String>, action: PlayerAction) -> Self {
+        Self {
+            player: Some(player.into()),
+            action,
+        }
+    }
+
+    pub fn active(action: PlayerAction) -> Self {
+        Self {
+            player: None,
+            action,
+        }
+    }
+}
+
+enum Request {
+    PlayerUpdated(MediaPlayer),
+    PlayerRemoved(String),
+}
+enum Response {
+    Request(String, PlayerAction, String),
+}
+
+struct PlayerWorker {
+    conn: Arc<SyncConnection>,
+    tokio: tokio::runtime::Handle,
+    tx: Sender<Request>,
+    /// well-known name to unique name
+    players: Mutex<IndexMap<String, String>>,
+}
+
+impl PlayerWorker {
+    fn name_of_owner(&self, owner: &str) -> Option<String> {
+        self.players
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, o)| *o == owner)
+            .map(|(name, _)| name.clone())
+    }
+
+    async fn add_player(&self, name: String, owner: String) -> Result<()> {
+        self.players
+            .lock()
+            .unwrap()
+            .insert(name.clone(), owner.clone());
+        self.refresh_player(name).await
+    }
+
+    fn remove_player(&self, name: &str) {
+        if self.players.lock().unwrap().shift_remove(name).is_some() {
+            let _ = self.tx.try_send(Request::PlayerRemoved(name.to_string()));
+        }
+    }
+
+    async fn refresh_player(&self, name: String) -> Result<()> {
+        let proxy = Proxy::new(&*name, MPRIS_DBUS_PATH, DBUS_TIMEOUT, self.conn.clone());
+        let mut player = MediaPlayer {
+            name: name.clone(),
+            rate: 1.0,
+            identity: proxy
+                .get(MPRIS_DBUS_INTERFACE, "Identity")
+                .await
+                .unwrap_or_default(),
+            desktop_entry: proxy
+                .get(MPRIS_DBUS_INTERFACE, "DesktopEntry")
+                .await
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let properties = proxy.get_all(MPRIS_PLAYER_DBUS_INTERFACE).await?;
+        player.update_player_properties(&properties);
+        self.tx.send(Request::PlayerUpdated(player)).await?;
+        Ok(())
+    }
+
+    fn spawn_refresh(self: &Arc<Self>, name: String) {
+        let this = self.clone();
+        self.tokio.spawn(async move {
+            if let Err(e) = this.refresh_player(name).await {
+                debug!("failed to refresh media player: {e}");
+            }
+        });
+    }
+
+    async fn poll_position(&self) {
+        let names: Vec<String> = self.players.lock().unwrap().keys().cloned().collect();
+        for name in names {
+            let proxy = Proxy::new(&*name, MPRIS_DBUS_PATH, DBUS_TIMEOUT, self.conn.clone());
+            if let Ok(status) = proxy
+                .get::<String>(MPRIS_PLAYER_DBUS_INTERFACE, "PlaybackStatus")
+                .await
+            {
+                if PlaybackStatus::from(&*status) == PlaybackStatus::Playing {
+                    if let Err(e) = self.refresh_player(name).await {
+                        debug!("failed to refresh media player: {e}");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn execute(&self, name: &str, action: PlayerAction, track_id: &str) -> Result<()> {
+        let proxy = Proxy::new(name, MPRIS_DBUS_PATH, DBUS_TIMEOUT, self.conn.clone());
+        let method = match &action {
+            PlayerAction::Play => "Play",
+            PlayerAction::Pause => "Pause",
+            PlayerAction::PlayPause => "PlayPause",
+            PlayerAction::Stop => "Stop",
+            PlayerAction::Next => "Next",
+            PlayerAction::Previous => "Previous",
+            PlayerAction::Seek(offset) => {
+                let offset = (*offset * 1_000_000.0) as i64;
+                proxy
+                    .method_call::<(), _, _, _>(MPRIS_PLAYER_DBUS_INTERFACE, "Seek", (offset,))
+                    .await?;
+                return Ok(());
+            }
+            PlayerAction::SetPosition(position) => {
+                let track_id = Path::new(track_id).map_err(|e| anyhow!("{e}"))?;
+                proxy
+                    .method_call::<(), _, _, _>(
+                        MPRIS_PLAYER_DBUS_INTERFACE,
+                        "SetPosition",
+                        (track_id, position.as_micros() as i64),
+                    )
+                    .await?;
+                return Ok(());
+            }
+            PlayerAction::SetVolume(volume) => {
+                proxy
+                    .set(MPRIS_PLAYER_DBUS_INTERFACE, "Volume", volume.clamp(0.0, 1.0))
+                    .await?;
+                return Ok(());
+            }
+            PlayerAction::Raise => {
+                proxy
+                    .method_call::<(), _, _, _>(MPRIS_DBUS_INTERFACE, "Raise", ())
+                    .await?;
+                return Ok(());
+            }
+        };
+        proxy
+            .method_call::<(), _, _, _>(MPRIS_PLAYER_DBUS_INTERFACE, method, ())
+            .await?;
+        Ok(())
+    }
 }
 
-impl PlayerController{
-    pub fn play(&mut self) {
+async fn create_dbus_connection(
+    tokio: tokio::runtime::Handle,
+    settings: DBusSettings,
+    mut rx: Receiver<Response>,
+    tx: Sender<Request>,
+) -> Result<()> {
+    let (tokio_handle, conn) = settings.connect_sync()?;
+    let _handle = tokio.spawn(async {
+        let err = tokio_handle.await;
+        panic!("Lost connection to D-Bus: {}", err);
+    });
+
+    let worker = Arc::new(PlayerWorker {
+        conn: conn.clone(),
+        tokio: tokio.clone(),
+        tx,
+        players: Default::default(),
+    });
+
+    let worker2 = worker.clone();
+    let name_owner_changed = conn
+        .add_match(MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged"))
+        .await?
+        .cb(move |_, (name, _old, new): (String, String, String)| {
+            if name.starts_with(MPRIS_DBUS_PREFIX) {
+                if new.is_empty() {
+                    worker2.remove_player(&name);
+                } else {
+                    let worker = worker2.clone();
+                    worker2.tokio.spawn(async move {
+                        if let Err(e) = worker.add_player(name, new).await {
+                            debug!("failed to add media player: {e}");
+                        }
+                    });
+                }
+            }
+            true
+        });
+
+    let worker3 = worker.clone();
+    let properties_changed = conn
+        .add_match(
+            MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+                .with_path(MPRIS_DBUS_PATH),
+        )
+        .await?
+        .msg_cb(move |msg| {
+            if let Some(name) = msg.sender().and_then(|s| worker3.name_of_owner(&s)) {
+                worker3.spawn_refresh(name);
+            }
+            true
+        });
+
+    let worker4 = worker.clone();
+    let seeked = conn
+        .add_match(MatchRule::new_signal(MPRIS_PLAYER_DBUS_INTERFACE, "Seeked"))
+        .await?
+        .msg_cb(move |msg| {
+            if let Some(name) = msg.sender().and_then(|s| worker4.name_of_owner(&s)) {
+                worker4.spawn_refresh(name);
+            }
+            true
+        });
+
+    let dbus_proxy = Proxy::new(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        DBUS_TIMEOUT,
+        conn.clone(),
+    );
+    let (names,): (Vec<String>,) = dbus_proxy
+        .method_call("org.freedesktop.DBus", "ListNames", ())
+        .await?;
+    for name in names.into_iter().filter(|n| n.starts_with(MPRIS_DBUS_PREFIX)) {
+        let owner: Result<(String,), _> = dbus_proxy
+            .method_call("org.freedesktop.DBus", "GetNameOwner", (&*name,))
+            .await;
+        if let Ok((owner,)) = owner {
+            if let Err(e) = worker.add_player(name, owner).await {
+                debug!("failed to add media player: {e}");
+            }
+        }
+    }
+
+    let mut interval = tokio::time::interval(POSITION_POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            response = rx.recv() => {
+                let Some(Response::Request(name, action, track_id)) = response else {
+                    break;
+                };
+                if let Err(e) = worker.execute(&name, action, &track_id).await {
+                    warn!("failed to send request to media player {name}: {e}");
+                }
+            }
+            _ = interval.tick() => {
+                worker.poll_position().await;
+            }
+        }
+    }
+
+    drop(name_owner_changed);
+    drop(properties_changed);
+    drop(seeked);
+    Ok(())
+}
+
+structstruck::strike! {
+    #[strikethrough[derive(Debug)]]
+    #[derive(Resource)]
+    pub struct PlayerController {
+        rx: Receiver<Request>,
+        tx: Sender<Response>,
+        pub players: IndexMap<String, MediaPlayer>,
+        /// the player controlled by media keys and shown in the panel
+        pub active: Option<String>,
+    }
+}
+
+impl PlayerController {
+    pub fn active_player(&self) -> Option<&MediaPlayer> {
+        self.active.as_ref().and_then(|name| self.players.get(name))
+    }
+
+    /// Prefer the player that is playing, otherwise keep the current one.
+    fn update_active(&mut self) {
+        let playing = self
+            .players
+            .values()
+            .find(|p| p.status == PlaybackStatus::Playing)
+            .map(|p| p.name.clone());
+        let active_is_playing = self
+            .active_player()
+            .is_some_and(|p| p.status == PlaybackStatus::Playing);
+        if !active_is_playing && playing.is_some() {
+            self.active = playing;
+        } else if self.active_player().is_none() {
+            self.active = self.players.keys().next().cloned();
+        }
+    }
+}
+
+impl FromWorld for PlayerController {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<DBusSettings>().cloned().unwrap_or_default();
+        let tokio = world.non_send_resource::<TokioRuntime>();
+        let (request_tx, request_rx) = channel(64);
+        let (response_tx, response_rx) = channel(64);
+        let handle = tokio.handle().clone();
+        tokio.spawn(async {
+            match create_dbus_connection(handle, settings, response_rx, request_tx).await {
+                Ok(()) => {
+                    info!("mpris client exit");
+                }
+                Err(e) => {
+                    error!("mpris client exit with an error: {e}");
+                }
+            }
+        });
+        Self {
+            players: Default::default(),
+            active: None,
+            rx: request_rx,
+            tx: response_tx,
+        }
+    }
+}
+
+pub fn update_player_controller(
+    mut events: MessageReader<PlayerRequest>,
+    mut player_controller: ResMut<PlayerController>,
+) {
+    let mut changed = false;
+    while let Ok(request) = player_controller.bypass_change_detection().rx.try_recv() {
+        let controller = player_controller.bypass_change_detection();
+        match request {
+            Request::PlayerUpdated(player) => {
+                if controller.players.get(&player.name) != Some(&player) {
+                    controller.players.insert(player.name.clone(), player);
+                    changed = true;
+                }
+            }
+            Request::PlayerRemoved(name) => {
+                controller.players.shift_remove(&name);
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        player_controller.update_active();
+    }
+    for event in events.read() {
+        let Some(player) = event
+            .player
+            .as_ref()
+            .or(player_controller.active.as_ref())
+            .and_then(|name| player_controller.players.get(name))
+        else {
+            continue;
+        };
+        let response = Response::Request(
+            player.name.clone(),
+            event.action.clone(),
+            player.metadata.track_id.clone(),
+        );
+        if player_controller.tx.try_send(response).is_err() {
+            warn!("failed to send player request: {event:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    use dbus::{arg::Variant, channel::MatchingReceiver};
+    use dbus_crossroads::Crossroads;
+
+    use super::*;
+    use crate::controller::dbus::test::PrivateSessionBus;
+
+    fn mock_metadata() -> PropMap {
+        PropMap::from([
+            (
+                "mpris:trackid".to_string(),
+                Variant(Box::new(Path::from("/track/1")) as Box<dyn RefArg>),
+            ),
+            (
+                "xesam:title".to_string(),
+                Variant(Box::new("Song".to_string()) as Box<dyn RefArg>),
+            ),
+            (
+                "xesam:artist".to_string(),
+                Variant(Box::new(vec!["A".to_string(), "B".to_string()]) as Box<dyn RefArg>),
+            ),
+            (
+                "mpris:length".to_string(),
+                Variant(Box::new(2_000_000i64) as Box<dyn RefArg>),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = PlayerMetadata::from_prop_map(&mock_metadata());
+        assert_eq!(metadata.track_id, "/track/1");
+        assert_eq!(metadata.title, "Song");
+        assert_eq!(metadata.artist(), "A, B");
+        assert_eq!(metadata.length, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_mpris_client_with_mock_player() {
+        let bus = PrivateSessionBus::spawn();
+        let player_conn = bus.connect();
+        player_conn
+            .request_name("org.mpris.MediaPlayer2.mock", false, true, false)
+            .unwrap();
+        let (call_tx, call_rx) = mpsc::channel();
+        let mut cr = Crossroads::new();
+        let root = cr.register(MPRIS_DBUS_INTERFACE, |b| {
+            b.property("Identity").get(|_, _| Ok("Mock".to_string()));
+        });
+        let player = cr.register(MPRIS_PLAYER_DBUS_INTERFACE, move |b| {
+            b.property("PlaybackStatus")
+                .get(|_, _| Ok("Playing".to_string()));
+            b.property("Metadata").get(|_, _| Ok(mock_metadata()));
+            b.property("Position").get(|_, _| Ok(1_000_000i64));
+            b.property("CanGoNext").get(|_, _| Ok(true));
+            b.method("Next", (), (), move |_, _, _: ()| {
+                call_tx.send("Next").unwrap();
+                Ok(())
+            });
+        });
+        cr.insert(MPRIS_DBUS_PATH, &[root, player], ());
+        player_conn.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |msg, conn| {
+                cr.handle_message(msg, conn).unwrap();
+                true
+            }),
+        );
+        let handle = std::thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                player_conn.process(Duration::from_millis(50)).unwrap();
+            }
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (request_tx, mut request_rx) = channel(64);
+        let (response_tx, response_rx) = channel(64);
+        runtime.spawn(create_dbus_connection(
+            runtime.handle().clone(),
+            bus.settings(),
+            response_rx,
+            request_tx,
+        ));
+
+        let Some(Request::PlayerUpdated(player)) = request_rx.blocking_recv() else {
+            panic!("media player not received");
+        };
+        assert_eq!(player.name, "org.mpris.MediaPlayer2.mock");
+        assert_eq!(player.identity, "Mock");
+        assert_eq!(player.status, PlaybackStatus::Playing);
+        assert_eq!(player.metadata.title, "Song");
+        assert_eq!(player.progress(), 0.5);
+        assert!(player.can_go_next);
+
+        response_tx
+            .blocking_send(Response::Request(
+                player.name.clone(),
+                PlayerAction::Next,
+                player.metadata.track_id.clone(),
+            ))
+            .unwrap();
+        assert_eq!(call_rx.recv_timeout(Duration::from_secs(3)), Ok("Next"));
+        handle.join().unwrap();
     }
 }
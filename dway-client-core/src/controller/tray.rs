@@ -0,0 +1,920 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use dbus::{
+    arg::{RefArg, Variant},
+    channel::{MatchingReceiver, Sender as _},
+    message::MatchRule,
+    nonblock::{
+        stdintf::org_freedesktop_dbus::{Properties, RequestNameReply},
+        Proxy, SyncConnection,
+    },
+    Message, Path,
+};
+use dbus_crossroads::Crossroads;
+use dway_util::tokio::TokioRuntime;
+use indexmap::{IndexMap, IndexSet};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use super::dbus::DBusSettings;
+use crate::prelude::*;
+
+pub const WATCHER_DBUS_DEST: &str = "org.kde.StatusNotifierWatcher";
+pub const WATCHER_DBUS_PATH: &str = "/StatusNotifierWatcher";
+pub const WATCHER_DBUS_INTERFACE: &str = "org.kde.StatusNotifierWatcher";
+pub const ITEM_DBUS_INTERFACE: &str = "org.kde.StatusNotifierItem";
+pub const ITEM_DEFAULT_PATH: &str = "/StatusNotifierItem";
+pub const DBUSMENU_DBUS_INTERFACE: &str = "com.canonical.dbusmenu";
+
+const DBUS_TIMEOUT: Duration = Duration::from_secs(2);
+const ITEM_SIGNALS: &[&str] = &[
+    "NewTitle",
+    "NewIcon",
+    "NewAttentionIcon",
+    "NewOverlayIcon",
+    "NewToolTip",
+    "NewStatus",
+];
+
+/// An item is identified by the unique bus name of its owner and its object path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Default)]
+pub struct TrayItemId {
+    pub service: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TrayItemStatus {
+    Passive,
+    #[default]
+    Active,
+    NeedsAttention,
+}
+
+impl From<&str> for TrayItemStatus {
+    fn from(value: &str) -> Self {
+        match value {
+            "Passive" => Self::Passive,
+            "NeedsAttention" => Self::NeedsAttention,
+            _ => Self::Active,
+        }
+    }
+}
+
+/// One entry of `IconPixmap`, stored as ARGB32 in network byte order.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct TrayPixmap {
+    pub width: u32,
+    pub height: u32,
+    pub argb: Vec<u8>,
+}
+
+impl TrayPixmap {
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.argb
+            .chunks_exact(4)
+            .flat_map(|p| [p[1], p[2], p[3], p[0]])
+            .collect()
+    }
+
+    /// Pick the smallest pixmap that is not smaller than `size`.
+    pub fn best_for_size(pixmaps: &[TrayPixmap], size: u32) -> Option<&TrayPixmap> {
+        pixmaps
+            .iter()
+            .filter(|p| p.width >= size)
+            .min_by_key(|p| p.width)
+            .or_else(|| pixmaps.iter().max_by_key(|p| p.width))
+    }
+}
+
+type RawPixmaps = Vec<(i32, i32, Vec<u8>)>;
+
+fn convert_pixmaps(raw: RawPixmaps) -> Vec<TrayPixmap> {
+    raw.into_iter()
+        .filter(|(w, h, data)| *w > 0 && *h > 0 && data.len() == (*w * *h * 4) as usize)
+        .map(|(width, height, argb)| TrayPixmap {
+            width: width as u32,
+            height: height as u32,
+            argb,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct TrayTooltip {
+    pub icon_name: String,
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct TrayItem {
+    pub id: TrayItemId,
+    pub item_id: String,
+    pub title: String,
+    pub category: String,
+    pub status: TrayItemStatus,
+    pub icon_name: String,
+    pub icon_theme_path: String,
+    pub icon_pixmap: Vec<TrayPixmap>,
+    pub attention_icon_name: String,
+    pub attention_icon_pixmap: Vec<TrayPixmap>,
+    pub tooltip: TrayTooltip,
+    pub item_is_menu: bool,
+    pub menu_path: Option<String>,
+    pub menu: Option<TrayMenuItem>,
+}
+
+impl TrayItem {
+    /// The icon name that should be shown for the current status.
+    pub fn current_icon_name(&self) -> &str {
+        if self.status == TrayItemStatus::NeedsAttention && !self.attention_icon_name.is_empty() {
+            &self.attention_icon_name
+        } else {
+            &self.icon_name
+        }
+    }
+
+    pub fn current_icon_pixmap(&self) -> &[TrayPixmap] {
+        if self.status == TrayItemStatus::NeedsAttention && !self.attention_icon_pixmap.is_empty()
+        {
+            &self.attention_icon_pixmap
+        } else {
+            &self.icon_pixmap
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TrayMenuToggle {
+    #[default]
+    None,
+    Checkmark(bool),
+    Radio(bool),
+}
+
+/// A node of a `com.canonical.dbusmenu` layout.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Reflect)]
+pub struct TrayMenuItem {
+    pub id: i32,
+    pub label: String,
+    pub enabled: bool,
+    pub visible: bool,
+    pub separator: bool,
+    pub icon_name: String,
+    pub toggle: TrayMenuToggle,
+    pub children: Vec<TrayMenuItem>,
+}
+
+impl TrayMenuItem {
+    fn parse(arg: &dyn RefArg) -> Option<Self> {
+        let mut fields = arg.as_iter()?;
+        let mut first = fields.next()?;
+        // children of a layout are wrapped in variants
+        if first.as_i64().is_none() {
+            fields = first.as_iter()?;
+            first = fields.next()?;
+        }
+        let id = first.as_i64()? as i32;
+        let properties = fields.next()?;
+        let children = fields.next();
+
+        let mut item = TrayMenuItem {
+            id,
+            enabled: true,
+            visible: true,
+            ..Default::default()
+        };
+        let mut toggle_type = String::new();
+        let mut toggle_state = 0;
+        let mut properties = properties.as_iter()?;
+        while let (Some(key), Some(value)) = (properties.next(), properties.next()) {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            match key {
+                "label" => {
+                    item.label = value.as_str().unwrap_or_default().replace('_', "");
+                }
+                "enabled" => item.enabled = value.as_i64().unwrap_or(1) != 0,
+                "visible" => item.visible = value.as_i64().unwrap_or(1) != 0,
+                "type" => item.separator = value.as_str() == Some("separator"),
+                "icon-name" => item.icon_name = value.as_str().unwrap_or_default().to_string(),
+                "toggle-type" => toggle_type = value.as_str().unwrap_or_default().to_string(),
+                "toggle-state" => toggle_state = value.as_i64().unwrap_or_default(),
+                _ => {}
+            }
+        }
+        item.toggle = match &*toggle_type {
+            "checkmark" => TrayMenuToggle::Checkmark(toggle_state == 1),
+            "radio" => TrayMenuToggle::Radio(toggle_state == 1),
+            _ => TrayMenuToggle::None,
+        };
+        if let Some(children) = children.and_then(|c| c.as_iter()) {
+            item.children = children.filter_map(TrayMenuItem::parse).collect();
+        }
+        Some(item)
+    }
+
+    pub fn find(&self, id: i32) -> Option<&TrayMenuItem> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|c| c.find(id))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum TrayScrollOrientation {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Message, Debug, Clone, Reflect)]
+pub enum TrayRequest {
+    /// left click, the position is in global coordinates
+    Activate { item: TrayItemId, position: IVec2 },
+    /// middle click
+    SecondaryActivate { item: TrayItemId, position: IVec2 },
+    /// ask the item to show its own menu, used when it does not export a dbusmenu
+    ContextMenu { item: TrayItemId, position: IVec2 },
+    Scroll {
+        item: TrayItemId,
+        delta: i32,
+        orientation: TrayScrollOrientation,
+    },
+    /// a menu entry was clicked
+    MenuClicked { item: TrayItemId, menu_id: i32 },
+    /// a menu is about to be opened, the item may update the layout
+    MenuAboutToShow { item: TrayItemId, menu_id: i32 },
+}
+
+impl TrayRequest {
+    pub fn item(&self) -> &TrayItemId {
+        match self {
+            TrayRequest::Activate { item, .. }
+            | TrayRequest::SecondaryActivate { item, .. }
+            | TrayRequest::ContextMenu { item, .. }
+            | TrayRequest::Scroll { item, .. }
+            | TrayRequest::MenuClicked { item, .. }
+            | TrayRequest::MenuAboutToShow { item, .. } => item,
+        }
+    }
+}
+
+enum Request {
+    ItemUpdated(TrayItem),
+    ItemRemoved(TrayItemId),
+}
+enum Response {
+    Request(TrayRequest, Option<String>),
+}
+
+struct TrayWorker {
+    conn: Arc<SyncConnection>,
+    tokio: tokio::runtime::Handle,
+    tx: Sender<Request>,
+    /// registered services in the format used by `RegisteredStatusNotifierItems`
+    registered: Mutex<IndexSet<String>>,
+    items: Mutex<IndexMap<TrayItemId, Option<String>>>,
+}
+
+impl TrayWorker {
+    fn parse_service(service: &str, sender: Option<&str>) -> Option<(String, String)> {
+        if service.starts_with('/') {
+            Some((sender?.to_string(), service.to_string()))
+        } else {
+            match service.split_once('/') {
+                Some((name, path)) => Some((name.to_string(), format!("/{path}"))),
+                None => Some((service.to_string(), ITEM_DEFAULT_PATH.to_string())),
+            }
+        }
+    }
+
+    async fn name_owner(&self, name: &str) -> Result<String> {
+        if name.starts_with(':') {
+            return Ok(name.to_string());
+        }
+        let proxy = Proxy::new(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            DBUS_TIMEOUT,
+            self.conn.clone(),
+        );
+        let (owner,): (String,) = proxy
+            .method_call("org.freedesktop.DBus", "GetNameOwner", (name,))
+            .await?;
+        Ok(owner)
+    }
+
+    async fn register_item(self: Arc<Self>, service: String, sender: Option<String>) -> Result<()> {
+        let Some((name, path)) = Self::parse_service(&service, sender.as_deref()) else {
+            bail!("invalid status notifier item: {service}");
+        };
+        let owner = self.name_owner(&name).await?;
+        let id = TrayItemId {
+            service: owner,
+            path: path.clone(),
+        };
+        let registered = format!("{name}{path}");
+        self.registered.lock().unwrap().insert(registered.clone());
+        self.items.lock().unwrap().insert(id.clone(), None);
+        let _ = self.conn.send(
+            Message::signal(
+                &WATCHER_DBUS_PATH.into(),
+                &WATCHER_DBUS_INTERFACE.into(),
+                &"StatusNotifierItemRegistered".into(),
+            )
+            .append1(registered),
+        );
+        self.refresh_item(id).await
+    }
+
+    fn remove_service(&self, name: &str) {
+        let removed: Vec<TrayItemId> = {
+            let mut items = self.items.lock().unwrap();
+            let removed = items.keys().filter(|id| id.service == name).cloned().collect();
+            items.retain(|id, _| id.service != name);
+            removed
+        };
+        let unregistered: Vec<String> = {
+            let mut registered = self.registered.lock().unwrap();
+            let unregistered = registered
+                .iter()
+                .filter(|s| s.starts_with(name) && s[name.len()..].starts_with('/'))
+                .cloned()
+                .collect();
+            registered.retain(|s| !(s.starts_with(name) && s[name.len()..].starts_with('/')));
+            unregistered
+        };
+        for service in unregistered {
+            let _ = self.conn.send(
+                Message::signal(
+                    &WATCHER_DBUS_PATH.into(),
+                    &WATCHER_DBUS_INTERFACE.into(),
+                    &"StatusNotifierItemUnregistered".into(),
+                )
+                .append1(service),
+            );
+        }
+        for id in removed {
+            let _ = self.tx.try_send(Request::ItemRemoved(id));
+        }
+    }
+
+    async fn get_property<T: for<'b> dbus::arg::Get<'b> + 'static>(
+        proxy: &Proxy<'_, Arc<SyncConnection>>,
+        name: &str,
+    ) -> Option<T> {
+        proxy.get(ITEM_DBUS_INTERFACE, name).await.ok()
+    }
+
+    async fn refresh_item(&self, id: TrayItemId) -> Result<()> {
+        let proxy = Proxy::new(&*id.service, &*id.path, DBUS_TIMEOUT, self.conn.clone());
+        let menu_path: Option<Path<'static>> = Self::get_property(&proxy, "Menu").await;
+        let menu_path = menu_path.map(|p| p.to_string()).filter(|p| p != "/");
+        let tooltip: Option<(String, RawPixmaps, String, String)> =
+            Self::get_property(&proxy, "ToolTip").await;
+        let mut item = TrayItem {
+            id: id.clone(),
+            item_id: Self::get_property(&proxy, "Id").await.unwrap_or_default(),
+            title: Self::get_property(&proxy, "Title").await.unwrap_or_default(),
+            category: Self::get_property(&proxy, "Category").await.unwrap_or_default(),
+            status: Self::get_property::<String>(&proxy, "Status")
+                .await
+                .as_deref()
+                .map(TrayItemStatus::from)
+                .unwrap_or_default(),
+            icon_name: Self::get_property(&proxy, "IconName").await.unwrap_or_default(),
+            icon_theme_path: Self::get_property(&proxy, "IconThemePath")
+                .await
+                .unwrap_or_default(),
+            icon_pixmap: convert_pixmaps(
+                Self::get_property(&proxy, "IconPixmap").await.unwrap_or_default(),
+            ),
+            attention_icon_name: Self::get_property(&proxy, "AttentionIconName")
+                .await
+                .unwrap_or_default(),
+            attention_icon_pixmap: convert_pixmaps(
+                Self::get_property(&proxy, "AttentionIconPixmap")
+                    .await
+                    .unwrap_or_default(),
+            ),
+            tooltip: tooltip
+                .map(|(icon_name, _, title, description)| TrayTooltip {
+                    icon_name,
+                    title,
+                    description,
+                })
+                .unwrap_or_default(),
+            item_is_menu: Self::get_property(&proxy, "ItemIsMenu").await.unwrap_or_default(),
+            menu_path: menu_path.clone(),
+            menu: None,
+        };
+        if let Some(menu_path) = &menu_path {
+            match self.get_menu_layout(&id.service, menu_path).await {
+                Ok(menu) => item.menu = Some(menu),
+                Err(e) => debug!("failed to get the menu of tray item {id:?}: {e}"),
+            }
+        }
+        self.items
+            .lock()
+            .unwrap()
+            .insert(id.clone(), menu_path.clone());
+        self.tx.send(Request::ItemUpdated(item)).await?;
+        Ok(())
+    }
+
+    async fn get_menu_layout(&self, service: &str, path: &str) -> Result<TrayMenuItem> {
+        let proxy = Proxy::new(service, path, DBUS_TIMEOUT, self.conn.clone());
+        let (_revision, layout): (u32, (i32, dbus::arg::PropMap, Vec<Variant<Box<dyn RefArg>>>)) =
+            proxy
+                .method_call(
+                    DBUSMENU_DBUS_INTERFACE,
+                    "GetLayout",
+                    (0i32, -1i32, Vec::<String>::new()),
+                )
+                .await?;
+        let (id, properties, children) = layout;
+        let mut root = TrayMenuItem {
+            id,
+            enabled: true,
+            visible: true,
+            label: dbus::arg::prop_cast::<String>(&properties, "label")
+                .cloned()
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        root.children = children
+            .iter()
+            .filter_map(|c| TrayMenuItem::parse(&c.0))
+            .collect();
+        Ok(root)
+    }
+
+    fn item_by_menu(&self, service: &str, path: &str) -> Option<TrayItemId> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(id, menu)| id.service == service && menu.as_deref() == Some(path))
+            .map(|(id, _)| id.clone())
+    }
+
+    fn spawn_refresh(self: &Arc<Self>, id: TrayItemId) {
+        let this = self.clone();
+        self.tokio.spawn(async move {
+            if let Err(e) = this.refresh_item(id).await {
+                debug!("failed to refresh tray item: {e}");
+            }
+        });
+    }
+
+    async fn execute(&self, request: TrayRequest, menu_path: Option<String>) -> Result<()> {
+        let item = request.item();
+        let proxy = Proxy::new(&*item.service, &*item.path, DBUS_TIMEOUT, self.conn.clone());
+        match &request {
+            TrayRequest::Activate { position, .. } => {
+                proxy
+                    .method_call(ITEM_DBUS_INTERFACE, "Activate", (position.x, position.y))
+                    .await?
+            }
+            TrayRequest::SecondaryActivate { position, .. } => {
+                proxy
+                    .method_call(
+                        ITEM_DBUS_INTERFACE,
+                        "SecondaryActivate",
+                        (position.x, position.y),
+                    )
+                    .await?
+            }
+            TrayRequest::ContextMenu { position, .. } => {
+                proxy
+                    .method_call(ITEM_DBUS_INTERFACE, "ContextMenu", (position.x, position.y))
+                    .await?
+            }
+            TrayRequest::Scroll {
+                delta, orientation, ..
+            } => {
+                let orientation = match orientation {
+                    TrayScrollOrientation::Vertical => "vertical",
+                    TrayScrollOrientation::Horizontal => "horizontal",
+                };
+                proxy
+                    .method_call(ITEM_DBUS_INTERFACE, "Scroll", (*delta, orientation))
+                    .await?
+            }
+            TrayRequest::MenuClicked { menu_id, .. } => {
+                let Some(menu_path) = menu_path else {
+                    bail!("tray item {item:?} has no menu");
+                };
+                let menu = Proxy::new(&*item.service, menu_path, DBUS_TIMEOUT, self.conn.clone());
+                let timestamp = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs() as u32)
+                    .unwrap_or_default();
+                menu.method_call(
+                    DBUSMENU_DBUS_INTERFACE,
+                    "Event",
+                    (
+                        *menu_id,
+                        "clicked",
+                        Variant(Box::new(0i32) as Box<dyn RefArg>),
+                        timestamp,
+                    ),
+                )
+                .await?
+            }
+            TrayRequest::MenuAboutToShow { menu_id, .. } => {
+                let Some(menu_path) = menu_path else {
+                    bail!("tray item {item:?} has no menu");
+                };
+                let menu = Proxy::new(&*item.service, menu_path, DBUS_TIMEOUT, self.conn.clone());
+                let (_need_update,): (bool,) = menu
+                    .method_call(DBUSMENU_DBUS_INTERFACE, "AboutToShow", (*menu_id,))
+                    .await?;
+            }
+        };
+        Ok(())
+    }
+}
+
+fn register_watcher(worker: Arc<TrayWorker>, cr: &mut Crossroads) {
+    let worker2 = worker.clone();
+    let worker3 = worker.clone();
+    let token = cr.register(WATCHER_DBUS_INTERFACE, |b| {
+        b.method(
+            "RegisterStatusNotifierItem",
+            ("service",),
+            (),
+            move |ctx, _, (service,): (String,)| {
+                let sender = ctx.message().sender().map(|s| s.to_string());
+                let worker = worker2.clone();
+                worker2.tokio.spawn(async move {
+                    if let Err(e) = worker.register_item(service, sender).await {
+                        warn!("failed to register status notifier item: {e}");
+                    }
+                });
+                Ok(())
+            },
+        );
+        b.method(
+            "RegisterStatusNotifierHost",
+            ("service",),
+            (),
+            |_, _, (_service,): (String,)| Ok(()),
+        );
+        b.property("RegisteredStatusNotifierItems").get(move |_, _| {
+            Ok(worker3
+                .registered
+                .lock()
+                .unwrap()
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>())
+        });
+        b.property("IsStatusNotifierHostRegistered").get(|_, _| Ok(true));
+        b.property("ProtocolVersion").get(|_, _| Ok(0i32));
+        b.signal::<(String,), _>("StatusNotifierItemRegistered", ("service",));
+        b.signal::<(String,), _>("StatusNotifierItemUnregistered", ("service",));
+        b.signal::<(), _>("StatusNotifierHostRegistered", ());
+    });
+    cr.insert(WATCHER_DBUS_PATH, &[token], ());
+}
+
+async fn create_dbus_connection(
+    tokio: tokio::runtime::Handle,
+    settings: DBusSettings,
+    mut rx: Receiver<Response>,
+    tx: Sender<Request>,
+) -> Result<()> {
+    let (tokio_handle, conn) = settings.connect_sync()?;
+    let _handle = tokio.spawn(async {
+        let err = tokio_handle.await;
+        panic!("Lost connection to D-Bus: {}", err);
+    });
+
+    let worker = Arc::new(TrayWorker {
+        conn: conn.clone(),
+        tokio: tokio.clone(),
+        tx,
+        registered: Default::default(),
+        items: Default::default(),
+    });
+
+    let is_watcher = matches!(
+        conn.request_name(WATCHER_DBUS_DEST, false, false, true).await,
+        Ok(RequestNameReply::PrimaryOwner)
+    );
+    let host_name = format!("org.kde.StatusNotifierHost-{}", std::process::id());
+    conn.request_name(&*host_name, false, true, false).await?;
+
+    let mut cr = Crossroads::new();
+    let tokio2 = tokio.clone();
+    cr.set_async_support(Some((
+        conn.clone(),
+        Box::new(move |x| {
+            tokio2.spawn(x);
+        }),
+    )));
+    if is_watcher {
+        register_watcher(worker.clone(), &mut cr);
+        let _ = conn.send(Message::signal(
+            &WATCHER_DBUS_PATH.into(),
+            &WATCHER_DBUS_INTERFACE.into(),
+            &"StatusNotifierHostRegistered".into(),
+        ));
+    }
+    conn.start_receive(
+        MatchRule::new_method_call(),
+        Box::new(move |msg, conn| {
+            if let Err(()) = cr.handle_message(msg, conn) {
+                warn!("failed to handle a dbus message");
+            }
+            true
+        }),
+    );
+
+    let worker2 = worker.clone();
+    let name_owner_changed = conn
+        .add_match(MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged"))
+        .await?
+        .cb(move |_, (name, _old, new): (String, String, String)| {
+            if new.is_empty() {
+                worker2.remove_service(&name);
+            }
+            true
+        });
+
+    let mut item_signals = vec![];
+    for signal in ITEM_SIGNALS {
+        let worker = worker.clone();
+        item_signals.push(
+            conn.add_match(MatchRule::new_signal(ITEM_DBUS_INTERFACE, *signal))
+                .await?
+                .msg_cb(move |msg| {
+                    if let (Some(sender), Some(path)) = (msg.sender(), msg.path()) {
+                        let id = TrayItemId {
+                            service: sender.to_string(),
+                            path: path.to_string(),
+                        };
+                        if worker.items.lock().unwrap().contains_key(&id) {
+                            worker.spawn_refresh(id);
+                        }
+                    }
+                    true
+                }),
+        );
+    }
+    for signal in ["LayoutUpdated", "ItemsPropertiesUpdated"] {
+        let worker = worker.clone();
+        item_signals.push(
+            conn.add_match(MatchRule::new_signal(DBUSMENU_DBUS_INTERFACE, signal))
+                .await?
+                .msg_cb(move |msg| {
+                    if let (Some(sender), Some(path)) = (msg.sender(), msg.path()) {
+                        if let Some(id) = worker.item_by_menu(&sender, &path) {
+                            worker.spawn_refresh(id);
+                        }
+                    }
+                    true
+                }),
+        );
+    }
+
+    if !is_watcher {
+        // another watcher owns the name, act as a host only
+        let worker3 = worker.clone();
+        item_signals.push(
+            conn.add_match(MatchRule::new_signal(
+                WATCHER_DBUS_INTERFACE,
+                "StatusNotifierItemRegistered",
+            ))
+            .await?
+            .cb(move |_, (service,): (String,)| {
+                let worker = worker3.clone();
+                worker3.tokio.spawn(async move {
+                    if let Err(e) = worker.register_item(service, None).await {
+                        warn!("failed to add status notifier item: {e}");
+                    }
+                });
+                true
+            }),
+        );
+        let watcher = Proxy::new(
+            WATCHER_DBUS_DEST,
+            WATCHER_DBUS_PATH,
+            DBUS_TIMEOUT,
+            conn.clone(),
+        );
+        watcher
+            .method_call::<(), _, _, _>(
+                WATCHER_DBUS_INTERFACE,
+                "RegisterStatusNotifierHost",
+                (&*host_name,),
+            )
+            .await?;
+        let items: Vec<String> = watcher
+            .get(WATCHER_DBUS_INTERFACE, "RegisteredStatusNotifierItems")
+            .await
+            .unwrap_or_default();
+        for service in items {
+            if let Err(e) = worker.clone().register_item(service, None).await {
+                warn!("failed to add status notifier item: {e}");
+            }
+        }
+    }
+
+    while let Some(response) = rx.recv().await {
+        match response {
+            Response::Request(request, menu_path) => {
+                if let Err(e) = worker.execute(request, menu_path).await {
+                    warn!("failed to send request to tray item: {e}");
+                }
+            }
+        }
+    }
+
+    drop(name_owner_changed);
+    drop(item_signals);
+    Ok(())
+}
+
+structstruck::strike! {
+    #[strikethrough[derive(Debug)]]
+    #[derive(Resource)]
+    pub struct TrayController {
+        rx: Receiver<Request>,
+        tx: Sender<Response>,
+        pub items: IndexMap<TrayItemId, TrayItem>,
+    }
+}
+
+impl std::ops::Deref for TrayController {
+    type Target = IndexMap<TrayItemId, TrayItem>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl FromWorld for TrayController {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<DBusSettings>().cloned().unwrap_or_default();
+        let tokio = world.non_send_resource::<TokioRuntime>();
+        let (request_tx, request_rx) = channel(64);
+        let (response_tx, response_rx) = channel(64);
+        let handle = tokio.handle().clone();
+        tokio.spawn(async {
+            match create_dbus_connection(handle, settings, response_rx, request_tx).await {
+                Ok(()) => {
+                    info!("status notifier host exit");
+                }
+                Err(e) => {
+                    error!("status notifier host exit with an error: {e}");
+                }
+            }
+        });
+        Self {
+            items: Default::default(),
+            rx: request_rx,
+            tx: response_tx,
+        }
+    }
+}
+
+pub fn update_tray_controller(
+    mut events: MessageReader<TrayRequest>,
+    mut tray_controller: ResMut<TrayController>,
+) {
+    while let Ok(request) = tray_controller.bypass_change_detection().rx.try_recv() {
+        match request {
+            Request::ItemUpdated(item) => {
+                if tray_controller.items.get(&item.id) != Some(&item) {
+                    tray_controller.items.insert(item.id.clone(), item);
+                }
+            }
+            Request::ItemRemoved(id) => {
+                tray_controller.items.shift_remove(&id);
+            }
+        }
+    }
+    for event in events.read() {
+        let menu_path = tray_controller
+            .items
+            .get(event.item())
+            .and_then(|item| item.menu_path.clone());
+        if tray_controller
+            .tx
+            .try_send(Response::Request(event.clone(), menu_path))
+            .is_err()
+        {
+            warn!("failed to send tray request: {event:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::controller::dbus::test::PrivateSessionBus;
+
+    #[test]
+    fn test_menu_layout() {
+        let child: Box<dyn RefArg> = Box::new((
+            2i32,
+            dbus::arg::PropMap::from([
+                ("label".to_string(), Variant(Box::new("_Quit".to_string()) as Box<dyn RefArg>)),
+                ("toggle-type".to_string(), Variant(Box::new("checkmark".to_string()) as Box<dyn RefArg>)),
+                ("toggle-state".to_string(), Variant(Box::new(1i32) as Box<dyn RefArg>)),
+            ]),
+            Vec::<Variant<Box<dyn RefArg>>>::new(),
+        ));
+        let item = TrayMenuItem::parse(&Variant(child)).unwrap();
+        assert_eq!(item.id, 2);
+        assert_eq!(item.label, "Quit");
+        assert_eq!(item.toggle, TrayMenuToggle::Checkmark(true));
+        assert!(item.enabled && item.visible && !item.separator);
+    }
+
+    #[test]
+    fn test_pixmap() {
+        let pixmaps = convert_pixmaps(vec![
+            (1, 1, vec![255, 1, 2, 3]),
+            (2, 2, vec![0; 16]),
+            (2, 2, vec![0; 3]),
+        ]);
+        assert_eq!(pixmaps.len(), 2);
+        assert_eq!(pixmaps[0].to_rgba(), vec![1, 2, 3, 255]);
+        assert_eq!(TrayPixmap::best_for_size(&pixmaps, 2).unwrap().width, 2);
+        assert_eq!(TrayPixmap::best_for_size(&pixmaps, 64).unwrap().width, 2);
+    }
+
+    #[test]
+    fn test_tray_watcher_with_mock_item() {
+        let bus = PrivateSessionBus::spawn();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (request_tx, mut request_rx) = channel(64);
+        let (_response_tx, response_rx) = channel(64);
+        runtime.spawn(create_dbus_connection(
+            runtime.handle().clone(),
+            bus.settings(),
+            response_rx,
+            request_tx,
+        ));
+
+        let item = bus.connect();
+        let mut cr = Crossroads::new();
+        let token = cr.register(ITEM_DBUS_INTERFACE, |b| {
+            b.property("Id").get(|_, _| Ok("mock".to_string()));
+            b.property("Title").get(|_, _| Ok("Mock Item".to_string()));
+            b.property("Status").get(|_, _| Ok("NeedsAttention".to_string()));
+            b.property("IconName").get(|_, _| Ok("mock-icon".to_string()));
+            b.property("AttentionIconName").get(|_, _| Ok("mock-attention".to_string()));
+            b.property("IconPixmap")
+                .get(|_, _| Ok(vec![(1i32, 1i32, vec![255u8, 1, 2, 3])]));
+        });
+        cr.insert(ITEM_DEFAULT_PATH, &[token], ());
+        item.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |msg, conn| {
+                cr.handle_message(msg, conn).unwrap();
+                true
+            }),
+        );
+        let item_name = item.unique_name().to_string();
+        let handle = std::thread::spawn(move || {
+            let watcher = item.with_proxy(WATCHER_DBUS_DEST, WATCHER_DBUS_PATH, DBUS_TIMEOUT);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while watcher
+                .method_call::<(), _, _, _>(
+                    WATCHER_DBUS_INTERFACE,
+                    "RegisterStatusNotifierItem",
+                    (ITEM_DEFAULT_PATH,),
+                )
+                .is_err()
+            {
+                assert!(Instant::now() < deadline);
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            while Instant::now() < deadline {
+                item.process(Duration::from_millis(50)).unwrap();
+            }
+        });
+
+        let Some(Request::ItemUpdated(tray_item)) = request_rx.blocking_recv() else {
+            panic!("tray item not received");
+        };
+        assert_eq!(tray_item.id.service, item_name);
+        assert_eq!(tray_item.id.path, ITEM_DEFAULT_PATH);
+        assert_eq!(tray_item.title, "Mock Item");
+        assert_eq!(tray_item.status, TrayItemStatus::NeedsAttention);
+        assert_eq!(tray_item.current_icon_name(), "mock-attention");
+        assert_eq!(tray_item.icon_pixmap[0].to_rgba(), vec![1, 2, 3, 255]);
+        handle.join().unwrap();
+    }
+}
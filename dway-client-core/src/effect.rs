@@ -0,0 +1,287 @@
+use bevy::prelude::*;
+use dway_server::xdg::{toplevel::DWayToplevel, DWayWindow};
+use smart_default::SmartDefault;
+
+use crate::{config::Config, desktop::FocusedWindow, DWayClientSystem};
+
+/// Inserted on the windows whose buffers are scanned out directly by an output.
+#[derive(Component, Reflect, Default, Debug)]
+pub struct DirectScanout;
+
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct WindowShadow {
+    pub color: Color,
+    pub offset: Vec2,
+    pub margin: Vec2,
+    pub radius: f32,
+}
+
+impl Default for WindowShadow {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK.with_alpha(0.35),
+            offset: Vec2::new(0.0, 4.0),
+            margin: Vec2::splat(4.0),
+            radius: 12.0,
+        }
+    }
+}
+
+/// The effects of windows without rules, usually set by the theme.
+#[derive(Resource, Clone, Debug, Reflect, SmartDefault)]
+pub struct WindowEffectSettings {
+    #[default(12.0)]
+    pub corner_radius: f32,
+    #[default(Some(WindowShadow::default()))]
+    pub shadow: Option<WindowShadow>,
+    pub blur: bool,
+    #[default(1.0)]
+    pub opacity: f32,
+    /// How much unfocused windows are darkened, from 0 to 1.
+    #[default(0.15)]
+    pub inactive_dim: f32,
+}
+
+/// Overrides of the effects of the matching windows.
+#[derive(Clone, Debug, Default, Reflect)]
+pub struct WindowEffectRule {
+    /// The app ids of the matching windows, every window matches if it is empty.
+    pub app_ids: Vec<String>,
+    pub rounded: Option<bool>,
+    pub shadow: Option<bool>,
+    pub blur: Option<bool>,
+    pub opacity: Option<f32>,
+    pub dim_inactive: Option<bool>,
+}
+
+impl WindowEffectRule {
+    pub fn matches(&self, toplevel: &DWayToplevel) -> bool {
+        self.app_ids.is_empty()
+            || toplevel
+                .app_id
+                .as_ref()
+                .is_some_and(|app_id| self.app_ids.contains(app_id))
+    }
+}
+
+/// The rules are applied in order, later rules override earlier ones.
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+pub struct WindowEffectRules(pub Vec<WindowEffectRule>);
+
+impl WindowEffectRules {
+    pub fn from_config(config: &Config) -> Self {
+        Self(
+            config
+                .rule
+                .iter()
+                .map(|rule| WindowEffectRule {
+                    app_ids: rule
+                        .patten
+                        .class
+                        .iter()
+                        .cloned()
+                        .chain(rule.patten.app.clone())
+                        .collect(),
+                    rounded: rule.properties.rounned_rect,
+                    blur: rule.properties.blur,
+                    // a window without opacity is drawn opaque
+                    opacity: (rule.properties.opacity == Some(false)).then_some(1.0),
+                    ..Default::default()
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Rebuild the rules when the [`Config`] is inserted or reloaded.
+pub fn load_window_effect_rules(config: Res<Config>, mut rules: ResMut<WindowEffectRules>) {
+    *rules = WindowEffectRules::from_config(&config);
+}
+
+/// The effects drawn by the compositor around and over a window.
+#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+pub struct WindowEffects {
+    pub corner_radius: f32,
+    pub shadow: Option<WindowShadow>,
+    /// Blur the content behind the translucent parts of the window.
+    pub blur: bool,
+    pub opacity: f32,
+    pub dim: f32,
+}
+
+impl Default for WindowEffects {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl WindowEffects {
+    /// No effect, used for fullscreen windows and windows scanned out directly.
+    pub fn none() -> Self {
+        Self {
+            corner_radius: 0.0,
+            shadow: None,
+            blur: false,
+            opacity: 1.0,
+            dim: 0.0,
+        }
+    }
+
+    pub fn resolve(
+        settings: &WindowEffectSettings,
+        rules: &WindowEffectRules,
+        toplevel: &DWayToplevel,
+        focused: bool,
+    ) -> Self {
+        let mut rounded = true;
+        let mut shadow = true;
+        let mut dim_inactive = true;
+        let mut effects = Self {
+            corner_radius: settings.corner_radius,
+            shadow: settings.shadow.clone(),
+            blur: settings.blur,
+            opacity: settings.opacity,
+            dim: 0.0,
+        };
+        for rule in rules.0.iter().filter(|rule| rule.matches(toplevel)) {
+            rounded = rule.rounded.unwrap_or(rounded);
+            shadow = rule.shadow.unwrap_or(shadow);
+            dim_inactive = rule.dim_inactive.unwrap_or(dim_inactive);
+            effects.blur = rule.blur.unwrap_or(effects.blur);
+            effects.opacity = rule.opacity.unwrap_or(effects.opacity);
+        }
+        if !rounded {
+            effects.corner_radius = 0.0;
+        }
+        if !shadow {
+            effects.shadow = None;
+        }
+        if dim_inactive && !focused {
+            effects.dim = settings.inactive_dim;
+        }
+        effects.opacity = effects.opacity.clamp(0.0, 1.0);
+        effects
+    }
+}
+
+pub fn update_window_effects(
+    window_query: Query<
+        (
+            Entity,
+            &DWayToplevel,
+            Has<DirectScanout>,
+            Option<&WindowEffects>,
+        ),
+        With<DWayWindow>,
+    >,
+    settings: Res<WindowEffectSettings>,
+    rules: Res<WindowEffectRules>,
+    focused_window: Res<FocusedWindow>,
+    mut commands: Commands,
+) {
+    for (entity, toplevel, direct_scanout, effects) in &window_query {
+        let new_effects = if toplevel.fullscreen || direct_scanout {
+            WindowEffects::none()
+        } else {
+            let focused = focused_window.window_entity == Some(entity);
+            WindowEffects::resolve(&settings, &rules, toplevel, focused)
+        };
+        if effects != Some(&new_effects) {
+            commands.entity(entity).insert(new_effects);
+        }
+    }
+}
+
+pub struct WindowEffectPlugin;
+impl Plugin for WindowEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WindowEffectSettings>()
+            .init_resource::<WindowEffectRules>()
+            .init_resource::<Config>()
+            .register_type::<WindowEffects>()
+            .register_type::<WindowEffectSettings>()
+            .register_type::<WindowEffectRules>()
+            .register_type::<DirectScanout>()
+            .add_systems(
+                PreUpdate,
+                (
+                    load_window_effect_rules.run_if(resource_changed::<Config>),
+                    update_window_effects,
+                )
+                    .chain()
+                    .after(DWayClientSystem::UpdateFocus)
+                    .in_set(DWayClientSystem::UpdateWindow),
+            );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_window_effects() {
+        let settings = WindowEffectSettings::default();
+        let toplevel = DWayToplevel {
+            app_id: Some("org.gnome.Terminal".to_string()),
+            ..Default::default()
+        };
+        let effects = WindowEffects::resolve(&settings, &default(), &toplevel, true);
+        assert_eq!(effects.corner_radius, settings.corner_radius);
+        assert_eq!(effects.dim, 0.0);
+        let effects = WindowEffects::resolve(&settings, &default(), &toplevel, false);
+        assert_eq!(effects.dim, settings.inactive_dim);
+
+        let rules = WindowEffectRules(vec![
+            WindowEffectRule {
+                opacity: Some(0.9),
+                ..Default::default()
+            },
+            WindowEffectRule {
+                app_ids: vec!["org.gnome.Terminal".to_string()],
+                rounded: Some(false),
+                blur: Some(true),
+                dim_inactive: Some(false),
+                ..Default::default()
+            },
+            WindowEffectRule {
+                app_ids: vec!["firefox".to_string()],
+                opacity: Some(1.0),
+                ..Default::default()
+            },
+        ]);
+        let effects = WindowEffects::resolve(&settings, &rules, &toplevel, false);
+        assert_eq!(effects.corner_radius, 0.0);
+        assert!(effects.blur);
+        assert_eq!(effects.opacity, 0.9);
+        assert_eq!(effects.dim, 0.0);
+        assert_eq!(effects.shadow, settings.shadow);
+    }
+
+    #[test]
+    fn test_window_effect_rules_from_config() {
+        let mut config = Config::default();
+        config.rule.push(default());
+        let rule = config.rule.last_mut().unwrap();
+        rule.patten.app = Some("org.gnome.Terminal".to_string());
+        rule.properties.blur = Some(true);
+
+        let rules = WindowEffectRules::from_config(&config);
+        assert_eq!(rules.0.len(), 1);
+        assert_eq!(rules.0[0].app_ids, vec!["org.gnome.Terminal".to_string()]);
+        assert_eq!(rules.0[0].blur, Some(true));
+        assert_eq!(rules.0[0].rounded, None);
+        assert_eq!(rules.0[0].opacity, None);
+
+        // the fields the rule doesn't set keep the settings
+        let settings = WindowEffectSettings::default();
+        let toplevel = DWayToplevel {
+            app_id: Some("org.gnome.Terminal".to_string()),
+            ..Default::default()
+        };
+        let effects = WindowEffects::resolve(&settings, &rules, &toplevel, true);
+        assert!(effects.blur);
+        assert_eq!(effects.corner_radius, settings.corner_radius);
+        assert_eq!(effects.opacity, settings.opacity);
+    }
+}
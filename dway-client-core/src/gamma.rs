@@ -0,0 +1,130 @@
+//! Emulation of the gamma tables of outputs with a post processing pass, for backends which
+//! cannot apply them, such as winit.
+
+use bevy::{
+    asset::{load_internal_asset, uuid_handle},
+    camera::NormalizedRenderTarget,
+    core_pipeline::core_2d::graph::{Core2d, Node2d},
+    ecs::{query::QueryItem, system::lifetimeless::Read},
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        render_graph::{RenderGraphExt as _, RenderLabel, ViewNodeRunner},
+        render_resource::ShaderType,
+        RenderApp,
+    },
+    window::PrimaryWindow,
+};
+use dway_util::{
+    gamma::{GammaLut, GammaSize, OutputGamma},
+    render::post_process::{PostProcessNode, PostProcessPlugin, PostProcessUniform},
+};
+
+use crate::prelude::*;
+
+/// The number of entries of the emulated gamma tables.
+pub const EMULATED_GAMMA_SIZE: usize = 256;
+
+const GAMMA_SHADER_HANDLE: Handle<Shader> = uuid_handle!("5b0c8a2e-6c3d-11f1-9a0e-3f6d2c1b7e45");
+
+/// The gamma table of the window a camera renders to.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct EmulatedGamma(pub GammaLut);
+
+#[derive(Component, Clone, ShaderType)]
+pub struct GammaUniform {
+    lut: [Vec4; EMULATED_GAMMA_SIZE],
+}
+
+impl ExtractComponent for EmulatedGamma {
+    type QueryData = Read<EmulatedGamma>;
+    type QueryFilter = With<Camera>;
+    type Out = GammaUniform;
+
+    fn extract_component(gamma: QueryItem<'_, '_, Self::QueryData>) -> Option<Self::Out> {
+        let lut = &gamma.0;
+        let mut uniform = GammaUniform {
+            lut: [Vec4::ONE; EMULATED_GAMMA_SIZE],
+        };
+        for (i, entry) in uniform.lut.iter_mut().enumerate() {
+            let x = i as f32 / (EMULATED_GAMMA_SIZE - 1) as f32;
+            *entry = Vec4::new(lut.sample(0, x), lut.sample(1, x), lut.sample(2, x), 1.0);
+        }
+        Some(uniform)
+    }
+}
+
+pub fn insert_emulated_gamma_size(
+    window_query: Query<Entity, (With<Window>, Without<GammaSize>)>,
+    mut commands: Commands,
+) {
+    for entity in &window_query {
+        commands
+            .entity(entity)
+            .insert(GammaSize(EMULATED_GAMMA_SIZE));
+    }
+}
+
+pub fn update_camera_gamma(
+    camera_query: Query<(Entity, &Camera, Option<&EmulatedGamma>)>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    gamma_query: Query<&OutputGamma>,
+    mut commands: Commands,
+) {
+    for (entity, camera, emulated) in &camera_query {
+        let window = match camera.target.normalize(primary_window.single().ok()) {
+            Some(NormalizedRenderTarget::Window(window)) => Some(window.entity()),
+            _ => None,
+        };
+        let gamma = window.and_then(|window| gamma_query.get(window).ok());
+        match gamma {
+            Some(gamma) if emulated.map(|e| &e.0) != Some(&gamma.0) => {
+                commands
+                    .entity(entity)
+                    .insert(EmulatedGamma(gamma.0.clone()));
+            }
+            None if emulated.is_some() => {
+                commands.entity(entity).remove::<EmulatedGamma>();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl PostProcessUniform for GammaUniform {
+    const LABEL: &'static str = "emulated_gamma";
+    const SHADER: Handle<Shader> = GAMMA_SHADER_HANDLE;
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct EmulatedGammaLabel;
+
+/// Apply [`OutputGamma`] of windows by the cameras rendering to them.
+pub struct GammaEmulationPlugin;
+impl Plugin for GammaEmulationPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, GAMMA_SHADER_HANDLE, "gamma.wgsl", Shader::from_wgsl);
+        app.add_plugins((
+            ExtractComponentPlugin::<EmulatedGamma>::default(),
+            PostProcessPlugin::<GammaUniform>::default(),
+        ))
+        .add_systems(PreUpdate, insert_emulated_gamma_size)
+        .add_systems(PostUpdate, update_camera_gamma);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<PostProcessNode<GammaUniform>>>(
+                Core2d,
+                EmulatedGammaLabel,
+            )
+            .add_render_graph_edges(
+                Core2d,
+                (
+                    Node2d::Tonemapping,
+                    EmulatedGammaLabel,
+                    Node2d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+}
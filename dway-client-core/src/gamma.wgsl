@@ -0,0 +1,37 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct Gamma {
+    lut: array<vec4<f32>, 256>,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var<uniform> gamma: Gamma;
+
+fn srgb_encode(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3(0.0031308));
+}
+
+fn srgb_decode(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return select(high, low, color <= vec3(0.04045));
+}
+
+// the entries of the lut around a channel value, interpolated
+fn lookup(value: f32) -> vec4<f32> {
+    let position = clamp(value, 0.0, 1.0) * 255.0;
+    let index = u32(floor(position));
+    let next = min(index + 1u, 255u);
+    return mix(gamma.lut[index], gamma.lut[next], fract(position));
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    let encoded = srgb_encode(clamp(color.rgb, vec3(0.0), vec3(1.0)));
+    let mapped = vec3(lookup(encoded.r).r, lookup(encoded.g).g, lookup(encoded.b).b);
+    return vec4(srgb_decode(mapped), color.a);
+}
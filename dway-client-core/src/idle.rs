@@ -0,0 +1,192 @@
+//! Powering the outputs off after a while without input.
+
+use std::time::Duration;
+
+use bevy::{
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButtonInput, MouseMotion, MouseWheel},
+        touch::TouchInput,
+    },
+    time::Real,
+};
+use dway_server::zwp::idle::IdleInhibitor;
+use dway_util::output::OutputPower;
+use smart_default::SmartDefault;
+
+use crate::prelude::*;
+
+#[derive(Resource, Clone, Debug, Reflect, SmartDefault)]
+pub struct IdleSettings {
+    /// The outputs are powered off after this long without input, never without it.
+    #[default(Some(Duration::from_secs(10 * 60)))]
+    pub blank_timeout: Option<Duration>,
+}
+
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+pub struct IdleState {
+    /// The time since the last input, it stays zero while a client inhibits idling.
+    pub idle_time: Duration,
+    /// The outputs were powered off by the timeout.
+    pub blanked: bool,
+    /// The outputs powered off by the timeout, the input only wakes these and leaves the outputs
+    /// powered off by clients off.
+    pub blanked_outputs: Vec<Entity>,
+}
+
+impl IdleState {
+    /// Advance the idle time, returns the power the outputs change to.
+    pub fn update(
+        &mut self,
+        delta: Duration,
+        input: bool,
+        inhibited: bool,
+        timeout: Option<Duration>,
+    ) -> Option<OutputPower> {
+        if input {
+            self.idle_time = Duration::ZERO;
+            return std::mem::replace(&mut self.blanked, false).then_some(OutputPower::On);
+        }
+        if inhibited {
+            self.idle_time = Duration::ZERO;
+            return None;
+        }
+        self.idle_time += delta;
+        if !self.blanked && timeout.is_some_and(|timeout| self.idle_time >= timeout) {
+            self.blanked = true;
+            return Some(OutputPower::Off);
+        }
+        None
+    }
+}
+
+pub fn update_idle(
+    mut keyboard_events: MessageReader<KeyboardInput>,
+    mut button_events: MessageReader<MouseButtonInput>,
+    mut motion_events: MessageReader<MouseMotion>,
+    mut wheel_events: MessageReader<MouseWheel>,
+    mut touch_events: MessageReader<TouchInput>,
+    inhibitor_query: Query<(), With<IdleInhibitor>>,
+    mut output_query: Query<(Entity, &mut OutputPower)>,
+    settings: Res<IdleSettings>,
+    mut state: ResMut<IdleState>,
+    time: Res<Time<Real>>,
+) {
+    let input_count = keyboard_events.read().count()
+        + button_events.read().count()
+        + motion_events.read().count()
+        + wheel_events.read().count()
+        + touch_events.read().count();
+    let Some(power) = state.update(
+        time.delta(),
+        input_count > 0,
+        !inhibitor_query.is_empty(),
+        settings.blank_timeout,
+    ) else {
+        return;
+    };
+    match power {
+        OutputPower::Off => {
+            info!(
+                "power off the outputs after {:?} without input",
+                state.idle_time
+            );
+            for (entity, mut output_power) in &mut output_query {
+                if output_power.is_on() {
+                    *output_power = OutputPower::Off;
+                    state.blanked_outputs.push(entity);
+                }
+            }
+        }
+        OutputPower::On => {
+            for entity in std::mem::take(&mut state.blanked_outputs) {
+                if let Ok((_, mut output_power)) = output_query.get_mut(entity) {
+                    output_power.set_if_neq(OutputPower::On);
+                }
+            }
+        }
+    }
+}
+
+pub struct IdlePlugin;
+impl Plugin for IdlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<IdleSettings>()
+            .init_resource::<IdleState>()
+            .register_type::<IdleSettings>()
+            .register_type::<IdleState>()
+            .add_systems(PreUpdate, update_idle.in_set(DWayClientSystem::Input));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_idle_state() {
+        let mut state = IdleState::default();
+        let timeout = Some(Duration::from_secs(10));
+        let second = Duration::from_secs(1);
+        assert_eq!(state.update(second, true, false, timeout), None);
+        for _ in 0..9 {
+            assert_eq!(state.update(second, false, false, timeout), None);
+        }
+        assert_eq!(
+            state.update(second, false, false, timeout),
+            Some(OutputPower::Off)
+        );
+        assert!(state.blanked);
+        assert_eq!(state.update(second, false, false, timeout), None);
+        assert_eq!(
+            state.update(second, true, false, timeout),
+            Some(OutputPower::On)
+        );
+        assert_eq!(state.idle_time, Duration::ZERO);
+
+        for _ in 0..20 {
+            assert_eq!(state.update(second, false, true, timeout), None);
+        }
+        assert_eq!(state.update(second, false, false, None), None);
+    }
+
+    #[test]
+    fn test_idle_wakes_blanked_outputs() {
+        let mut app = App::new();
+        app.add_message::<KeyboardInput>()
+            .add_message::<MouseButtonInput>()
+            .add_message::<MouseMotion>()
+            .add_message::<MouseWheel>()
+            .add_message::<TouchInput>()
+            .init_resource::<Time<Real>>()
+            .insert_resource(IdleSettings {
+                blank_timeout: Some(Duration::ZERO),
+            })
+            .init_resource::<IdleState>()
+            .add_systems(Update, update_idle);
+        let blanked = app.world_mut().spawn(OutputPower::On).id();
+        let client_off = app.world_mut().spawn(OutputPower::Off).id();
+
+        app.update();
+        assert_eq!(
+            app.world().resource::<IdleState>().blanked_outputs,
+            vec![blanked]
+        );
+        assert_eq!(
+            *app.world().get::<OutputPower>(blanked).unwrap(),
+            OutputPower::Off
+        );
+
+        app.world_mut()
+            .write_message(MouseMotion { delta: Vec2::ONE });
+        app.update();
+        assert_eq!(
+            *app.world().get::<OutputPower>(blanked).unwrap(),
+            OutputPower::On
+        );
+        assert_eq!(
+            *app.world().get::<OutputPower>(client_off).unwrap(),
+            OutputPower::Off
+        );
+    }
+}
@@ -12,6 +12,9 @@ pub mod compositor;
 pub mod config;
 pub mod controller;
 pub mod desktop;
+pub mod effect;
+pub mod gamma;
+pub mod idle;
 pub mod input;
 pub mod layout;
 pub mod model;
@@ -127,6 +130,8 @@ impl Plugin for DWayClientPlugin {
             input::DWayInputPlugin { debug: false },
             desktop::DWayDesktop,
             window::DWayWindowPlugin,
+            effect::WindowEffectPlugin,
+            idle::IdlePlugin,
             navigation::windowstack::WindowStackPlugin,
             layout::LayoutPlugin,
             screen::ScreenPlugin,
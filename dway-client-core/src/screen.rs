@@ -31,16 +31,21 @@ pub fn create_screen(
     mut event: MessageWriter<Insert<Screen>>,
 ) {
     for (entity, window, screen) in screen_query.iter() {
-        let WindowPosition::At(_window_position) = window.position else {
+        let WindowPosition::At(window_position) = window.position else {
             continue;
         };
         let rect = IRect::new(
-            0,
-            0,
+            window_position.x,
+            window_position.y,
             window.resolution.width() as i32,
             window.resolution.height() as i32,
         );
-        if screen.is_none() {
+        if screen.is_some() {
+            // the output is moved, scaled or rotated
+            commands
+                .entity(entity)
+                .insert((Geometry::new(rect), GlobalGeometry::new(rect)));
+        } else {
             commands.entity(entity).insert(ScreenBundle {
                 screen: Screen {
                     name: window.title.clone(),
@@ -96,6 +96,15 @@ pub struct PasteRequests(Vec<PasteRequest>);
 
 type Data = Vec<u8>;
 
+/// Mime types offered for plain text, preferred first.
+pub const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+    "TEXT",
+];
+
 structstruck::strike! {
     #[derive(Component, Clone, PartialEq)]
     #[require(PasteRequests)]
@@ -292,42 +301,9 @@ impl ClipboardManager {
                 Ok(())
             }
             Some(MimeTypeState::Pedding) => {
-                let mime_type = paste_request.mime_type.clone();
-
-                let mut poller = world.non_send_resource::<Poller>().handle();
-
-                match world
-                    .get_mut::<ClipboardSource>(record_entity)
-                    .as_deref_mut()
-                {
-                    Some(source) => {
-                        let source = source.clone();
-                        let mime_type = mime_type.clone();
-                        IoTaskPool::get()
-                            .spawn(async move {
-                                let r = read_clipboard(&mut poller, mime_type.clone(), &source);
-                                let data = match r {
-                                    Ok(o) => MimeTypeState::Ok(o),
-                                    Err(e) => {
-                                        error!("failed to read clipboard: {e:?}");
-                                        MimeTypeState::Error
-                                    }
-                                };
-                                debug!(entity=?record_entity,"read clipboard finish");
-                                let _ = sender.send(ClipboardTaskResult::ReadClipboard {
-                                    record_entity,
-                                    mime_type,
-                                    data,
-                                });
-                            })
-                            .detach();
-                    }
-                    _ => return Err(paste_request),
+                if !Self::fetch(world, record_entity, &paste_request.mime_type) {
+                    return Err(paste_request);
                 }
-
-                let mut record = world.get_mut::<ClipboardRecord>(record_entity).unwrap();
-                record.mime_types.insert(mime_type, MimeTypeState::Reading);
-
                 let mut paste_requests = world.get_mut::<PasteRequests>(record_entity).unwrap();
                 paste_requests.push(paste_request);
                 debug!("clipboard is pedding");
@@ -347,6 +323,65 @@ impl ClipboardManager {
         }
     }
 
+    /// Starts reading a mime type from the source of a record.
+    pub fn fetch(world: &mut World, record_entity: Entity, mime_type: &str) -> bool {
+        let sender = world.resource::<Self>().sender.clone();
+        let mut poller = world.non_send_resource::<Poller>().handle();
+
+        let Some(source) = world.get::<ClipboardSource>(record_entity).cloned() else {
+            return false;
+        };
+        let mime_type = mime_type.to_string();
+        {
+            let mime_type = mime_type.clone();
+            IoTaskPool::get()
+                .spawn(async move {
+                    let r = read_clipboard(&mut poller, mime_type.clone(), &source);
+                    let data = match r {
+                        Ok(o) => MimeTypeState::Ok(o),
+                        Err(e) => {
+                            error!("failed to read clipboard: {e:?}");
+                            MimeTypeState::Error
+                        }
+                    };
+                    debug!(entity=?record_entity,"read clipboard finish");
+                    let _ = sender.send(ClipboardTaskResult::ReadClipboard {
+                        record_entity,
+                        mime_type,
+                        data,
+                    });
+                })
+                .detach();
+        }
+
+        let mut record = world.get_mut::<ClipboardRecord>(record_entity).unwrap();
+        record.mime_types.insert(mime_type, MimeTypeState::Reading);
+        true
+    }
+
+    /// Returns the text of a record, or starts reading it from the source.
+    ///
+    /// [`ClipboardEvent::SourceMimeTypeReady`] is sent once the text can be read.
+    pub fn fetch_text(world: &mut World, record_entity: Entity) -> Option<String> {
+        if let Some(text) = Self::get_text(world, record_entity) {
+            return Some(text);
+        }
+        let record = world.get::<ClipboardRecord>(record_entity)?;
+        let mime_type = TEXT_MIME_TYPES
+            .iter()
+            .find(|m| record.mime_types.get(**m) == Some(&MimeTypeState::Pedding))?;
+        Self::fetch(world, record_entity, mime_type);
+        None
+    }
+
+    pub fn get_text(world: &World, record_entity: Entity) -> Option<String> {
+        let record = world.get::<ClipboardRecord>(record_entity)?;
+        TEXT_MIME_TYPES.iter().find_map(|m| match record.mime_types.get(*m) {
+            Some(MimeTypeState::Ok(data)) => Some(String::from_utf8_lossy(data).into_owned()),
+            _ => None,
+        })
+    }
+
     pub fn require_last_record(world: &mut World, mut paste_request: PasteRequest) {
         let records = world.resource::<Self>().records.clone();
         for record in records.into_iter().rev() {
@@ -379,17 +414,45 @@ impl ClipboardManager {
                 source,
             ))
             .id();
+        Self::push_record(world, entity);
+    }
+
+    /// Adds a record owned by the compositor, e.g. text copied in its own ui.
+    pub fn add_data(world: &mut World, data: HashMap<String, Data>) -> Entity {
+        let entity = world
+            .spawn(ClipboardRecord {
+                mime_types: data
+                    .into_iter()
+                    .map(|(key, data)| (key, MimeTypeState::Ok(data)))
+                    .collect(),
+            })
+            .id();
+        Self::push_record(world, entity);
+        entity
+    }
+
+    pub fn add_text(world: &mut World, text: &str) -> Entity {
+        let data = TEXT_MIME_TYPES
+            .iter()
+            .map(|m| (m.to_string(), text.as_bytes().to_vec()))
+            .collect();
+        Self::add_data(world, data)
+    }
 
+    fn push_record(world: &mut World, entity: Entity) {
         world.send_event(ClipboardEvent::SourceAdded(entity));
 
         let mut this = world.resource_mut::<Self>();
         this.records.push_back(entity);
 
-        if this.records.len() > this.count_limit {
-            if let Some(record) = this.records.pop_front() {
-                world.entity_mut(record).despawn();
-                world.send_event(ClipboardEvent::SourceDeleted(entity));
-            }
+        let removed = if this.records.len() > this.count_limit {
+            this.records.pop_front()
+        } else {
+            None
+        };
+        if let Some(record) = removed {
+            world.entity_mut(record).despawn();
+            world.send_event(ClipboardEvent::SourceDeleted(record));
         }
     }
 
@@ -62,6 +62,7 @@ impl Plugin for DWayServerPlugin {
             zxdg::outputmanager::XdgOutputManagerPlugin,
             zxdg::decoration::DecorationPlugin,
             zwlr::data_control::DataControlPlugin,
+            zwlr::output_management::OutputManagementPlugin,
             misc::gtk_primary_selection::GtkPrimarySelectionPlugin,
             input::seat::WlSeatPlugin,
             input::keyboard::WlKeyboardPlugin,
@@ -70,6 +71,10 @@ impl Plugin for DWayServerPlugin {
             wp::PrimarySelectionPlugin,
             x11::DWayXWaylandPlugin,
             zwp::DmaBufferPlugin,
+            zwlr::gamma_control::GammaControlPlugin,
+            zwlr::output_power::OutputPowerPlugin,
+            wp::color_management::ColorManagementPlugin,
+            zwp::idle::IdlePlugin,
             apps::DesktopEntriesPlugin,
         ));
         app.add_systems(Startup, init_display);
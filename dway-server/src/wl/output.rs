@@ -1,24 +1,31 @@
 use crate::{
     geometry::{Geometry, GlobalGeometry},
     prelude::*,
-    state::add_global_dispatch,
+    state::DWayServer,
     util::rect::IRect,
+    zxdg::outputmanager::ZxdgOutput,
 };
+use bevy::platform::collections::HashMap;
 use bevy_relationship::{graph_query, relationship};
+use dway_util::output::OutputHead;
 use wayland_server::protocol::wl_output::Mode;
 
 use super::surface::{ClientHasSurface, WlSurface};
 
+pub const WL_OUTPUT_VERSION: u32 = 4;
+
 #[derive(Component, Reflect, Debug, Clone)]
 #[reflect(Debug)]
 pub struct WlOutput {
     #[reflect(ignore, default = "unimplemented")]
     pub raw: wl_output::WlOutput,
+    /// The window entity of the output, which is the global data
+    pub output: Entity,
 }
 
 impl WlOutput {
-    pub fn new(raw: wl_output::WlOutput) -> Self {
-        Self { raw }
+    pub fn new(raw: wl_output::WlOutput, output: Entity) -> Self {
+        Self { raw, output }
     }
 }
 
@@ -31,16 +38,151 @@ pub struct WlOutputBundle {
 }
 
 impl WlOutputBundle {
-    pub fn new(resource: WlOutput) -> Self {
+    pub fn new(resource: WlOutput, rect: IRect) -> Self {
         Self {
             resource,
             surfaces: Default::default(),
-            geo: Geometry::new(IRect::new(0, 0, 1920, 1080)),
-            global: Default::default(),
+            geo: Geometry::new(rect),
+            global: GlobalGeometry::new(rect),
+        }
+    }
+}
+
+/// What the clients are told about an output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputInfo {
+    pub name: String,
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    /// The logical geometry in the compositor space
+    pub rect: IRect,
+    /// The physical size in millimeters
+    pub physical_size: IVec2,
+    pub mode_size: IVec2,
+    /// The refresh rate in mHz
+    pub refresh: u32,
+    pub transform: wl_output::Transform,
+    pub scale: f32,
+}
+
+impl Default for OutputInfo {
+    fn default() -> Self {
+        Self {
+            name: "WL-1".to_string(),
+            description: "dway output".to_string(),
+            make: "dway".to_string(),
+            model: "dway".to_string(),
+            rect: IRect::new(0, 0, 1920, 1080),
+            physical_size: IVec2::ZERO,
+            mode_size: IVec2::new(1920, 1080),
+            refresh: 60000,
+            transform: wl_output::Transform::Normal,
+            scale: 1.0,
+        }
+    }
+}
+
+impl OutputInfo {
+    /// Describe a window, the outputs of the tty backend also have an [`OutputHead`].
+    pub fn new(window: &Window, head: Option<&OutputHead>) -> Self {
+        let position = match window.position {
+            WindowPosition::At(position) => position,
+            _ => IVec2::ZERO,
+        };
+        let rect = IRect::from_pos_size(
+            position,
+            IVec2::new(
+                window.resolution.width() as i32,
+                window.resolution.height() as i32,
+            ),
+        );
+        let scale = window.scale_factor();
+        let Some(head) = head else {
+            let name = window.name.clone().unwrap_or_else(|| "WL-1".to_string());
+            return Self {
+                description: window.title.clone(),
+                name,
+                rect,
+                mode_size: IVec2::new(
+                    window.resolution.physical_width() as i32,
+                    window.resolution.physical_height() as i32,
+                ),
+                scale,
+                ..Default::default()
+            };
+        };
+        let identity = &head.identity;
+        let or_default = |value: &str| {
+            if value.is_empty() {
+                "dway".to_string()
+            } else {
+                value.to_string()
+            }
+        };
+        Self {
+            name: head.name().to_string(),
+            description: identity.description(),
+            make: or_default(&identity.vendor),
+            model: or_default(&identity.model),
+            rect,
+            physical_size: head.physical_size,
+            mode_size: head.state.mode.size(),
+            refresh: head.state.mode.refresh,
+            transform: wl_output::Transform::try_from(head.state.transform.to_wl())
+                .unwrap_or(wl_output::Transform::Normal),
+            scale,
+        }
+    }
+
+    /// Send the properties, the name can only be sent once.
+    pub fn send(&self, output: &wl_output::WlOutput, first: bool) {
+        output.geometry(
+            self.rect.pos().x,
+            self.rect.pos().y,
+            self.physical_size.x,
+            self.physical_size.y,
+            wl_output::Subpixel::Unknown,
+            self.make.clone(),
+            self.model.clone(),
+            self.transform,
+        );
+        output.mode(
+            Mode::Current,
+            self.mode_size.x,
+            self.mode_size.y,
+            self.refresh as i32,
+        );
+        if output.version() >= 2 {
+            output.scale(self.scale.ceil() as i32);
+        }
+        if output.version() >= 4 {
+            if first {
+                output.name(self.name.clone());
+            }
+            output.description(self.description.clone());
+        }
+    }
+
+    pub fn send_xdg(&self, xdg_output: &zxdg_output_v1::ZxdgOutputV1, first: bool) {
+        xdg_output.logical_position(self.rect.pos().x, self.rect.pos().y);
+        xdg_output.logical_size(self.rect.size().x, self.rect.size().y);
+        if xdg_output.version() >= 2 {
+            if first {
+                xdg_output.name(self.name.clone());
+            }
+            xdg_output.description(self.description.clone());
+        }
+        if xdg_output.version() < 3 {
+            xdg_output.done();
         }
     }
 }
 
+/// The `wl_output` globals of every window, keyed by the window entity.
+#[derive(Resource, Default, Debug)]
+pub struct WlOutputGlobals(pub HashMap<Entity, Vec<(Entity, GlobalId)>>);
+
 relationship!(ClientHasOutput=>EnteredOutputList-<ClientRef);
 relationship!(SurfaceInOutput => OutputList>-<SurfaceList);
 
@@ -49,16 +191,18 @@ pub struct OutputDelegate(pub GlobalId);
 delegate_dispatch!(DWay: [wl_output::WlOutput: Entity] => OutputDelegate);
 impl wayland_server::Dispatch<wl_output::WlOutput, bevy::prelude::Entity, DWay> for OutputDelegate {
     fn request(
-        _state: &mut DWay,
+        state: &mut DWay,
         _client: &wayland_server::Client,
-        _resource: &wl_output::WlOutput,
+        resource: &wl_output::WlOutput,
         request: <wl_output::WlOutput as wayland_server::Resource>::Request,
-        _data: &bevy::prelude::Entity,
+        data: &bevy::prelude::Entity,
         _dhandle: &wayland_server::DisplayHandle,
         _data_init: &mut wayland_server::DataInit<'_, DWay>,
     ) {
         match request {
-            wl_output::Request::Release => todo!(),
+            wl_output::Request::Release => {
+                state.despawn_object(*data, resource);
+            }
             _ => todo!(),
         }
     }
@@ -77,42 +221,99 @@ impl GlobalDispatch<wl_output::WlOutput, Entity> for DWay {
         _handle: &DisplayHandle,
         client: &wayland_server::Client,
         resource: wayland_server::New<wl_output::WlOutput>,
-        _global_data: &Entity,
+        global_data: &Entity,
         data_init: &mut wayland_server::DataInit<'_, Self>,
     ) {
+        let output_entity = *global_data;
+        let world = state.world();
+        let info = world
+            .get::<Window>(output_entity)
+            .map(|window| OutputInfo::new(window, world.get::<OutputHead>(output_entity)))
+            .unwrap_or_default();
         state.bind_spawn(client, resource, data_init, |output| {
-            output.geometry(
-                0,
-                0,
-                1920,
-                1080,
-                wl_output::Subpixel::VerticalRgb,
-                "dway".to_string(),
-                "dway".to_string(),
-                wl_output::Transform::Normal,
-            );
-            output.mode(Mode::Current, 1920, 1080, 60000);
-            if output.version() >= 4 {
-                output.name("WL-1".to_string());
-                output.description("dway output".to_string())
-            }
-
+            info.send(&output, true);
             if output.version() >= 2 {
-                output.scale(1);
                 output.done();
             }
-            WlOutputBundle::new(WlOutput::new(output))
+            WlOutputBundle::new(WlOutput::new(output, output_entity), info.rect)
         });
     }
 }
 
+/// Create a `wl_output` global for every window and remove it with the window.
+pub fn update_output_globals(
+    server_query: Query<(Entity, &DWayServer)>,
+    window_query: Query<Entity, With<Window>>,
+    mut removed_windows: RemovedComponents<Window>,
+    mut globals: ResMut<WlOutputGlobals>,
+) {
+    for window_entity in removed_windows.read() {
+        for (display_entity, global) in globals.0.remove(&window_entity).into_iter().flatten() {
+            if let Ok((_, server)) = server_query.get(display_entity) {
+                debug!(?window_entity, "remove wl_output global");
+                server.handle().remove_global::<DWay>(global);
+            }
+        }
+    }
+    for (display_entity, server) in &server_query {
+        for window_entity in &window_query {
+            let window_globals = globals.0.entry(window_entity).or_default();
+            if window_globals.iter().any(|(d, _)| *d == display_entity) {
+                continue;
+            }
+            debug!(?window_entity, ?display_entity, "create wl_output global");
+            let global = server
+                .handle()
+                .create_global::<DWay, wl_output::WlOutput, Entity>(
+                    WL_OUTPUT_VERSION,
+                    window_entity,
+                );
+            window_globals.push((display_entity, global));
+        }
+    }
+}
+
+/// Send the changes of the windows and the output heads to the bound outputs.
+pub fn update_outputs(
+    window_query: Query<(Ref<Window>, Option<Ref<OutputHead>>)>,
+    mut output_query: Query<(
+        &WlOutput,
+        Option<&ZxdgOutput>,
+        &mut Geometry,
+        &mut GlobalGeometry,
+    )>,
+) {
+    for (output, xdg_output, mut geometry, mut global_geometry) in &mut output_query {
+        let Ok((window, head)) = window_query.get(output.output) else {
+            continue;
+        };
+        if !window.is_changed() && !head.as_ref().is_some_and(|head| head.is_changed()) {
+            continue;
+        }
+        let info = OutputInfo::new(&window, head.as_deref());
+        info.send(&output.raw, false);
+        if let Some(xdg_output) = xdg_output {
+            info.send_xdg(&xdg_output.raw, false);
+        }
+        if output.raw.version() >= 2 {
+            output.raw.done();
+        }
+        geometry.geometry = info.rect;
+        global_geometry.geometry = info.rect;
+    }
+}
+
 pub struct WlOutputPlugin;
 impl Plugin for WlOutputPlugin {
     fn build(&self, app: &mut App) {
-        add_global_dispatch::<wl_output::WlOutput, 4>(app);
+        app.init_resource::<WlOutputGlobals>();
         app.add_systems(
             PreUpdate,
-            surface_enter_output.in_set(DWayServerSet::UpdateJoin),
+            (
+                update_output_globals.in_set(DWayServerSet::CreateGlobal),
+                update_outputs.in_set(DWayServerSet::UpdateGeometry),
+                surface_enter_output.in_set(DWayServerSet::UpdateJoin),
+            ),
         );
         app.register_type::<WlOutput>();
         app.register_relation::<ClientHasOutput>();
@@ -2,6 +2,7 @@ use std::borrow::Cow;
 
 use bevy::diagnostic::FrameCount;
 use bevy_relationship::relationship;
+use dway_util::color::ImageDescription;
 use wayland_server::backend::smallvec::SmallVec;
 use wgpu::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages};
 
@@ -33,6 +34,8 @@ pub struct WlSurfacePeddingState {
     pub window_geometry: Option<IRect>,
     #[reflect(ignore)]
     pub transform: Option<wl_output::Transform>,
+    /// Set by the color management surface, `Some(None)` unsets the image description
+    pub image_description: Option<Option<ImageDescription>>,
 }
 #[derive(Default, Reflect, Debug, Clone)]
 #[reflect(Debug)]
@@ -52,6 +55,8 @@ pub struct WlSurfaceCommitedState {
     /// not use yet
     #[reflect(ignore)]
     pub transform: Option<wl_output::Transform>,
+    /// The color space of the content, sRGB if there is none
+    pub image_description: Option<ImageDescription>,
 }
 
 #[derive(Component, Reflect, Debug, Clone)]
@@ -316,6 +321,9 @@ impl wayland_server::Dispatch<wl_surface::WlSurface, bevy::prelude::Entity, DWay
                         if let Some(transform) = surface.pending.transform.take() {
                             surface.commited.transform = Some(transform);
                         }
+                        if let Some(description) = surface.pending.image_description.take() {
+                            surface.commited.image_description = description;
+                        }
 
                         if let Some(wl_buffer) = surface.pending.wl_buffer.take() {
                             surface.commited.wl_buffer.as_ref().map(|b| {
@@ -0,0 +1,231 @@
+use dway_util::{
+    color::{ImageDescription, Luminances, Primaries, TransferFunction},
+    edid::Chromaticity,
+};
+use wayland_protocols::wp::color_management::v1::server::{
+    wp_image_description_creator_params_v1::*, wp_image_description_v1::Cause,
+};
+
+use super::{description::WpImageDescription, primaries_from_wl, transfer_function_from_wl};
+use crate::prelude::*;
+
+/// The parameters of a parametric image description which is being created.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct WpImageDescriptionCreatorParams {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: WpImageDescriptionCreatorParamsV1,
+    pub transfer_function: Option<TransferFunction>,
+    pub primaries: Option<Primaries>,
+    pub luminances: Option<Luminances>,
+    pub mastering_primaries: Option<Chromaticity>,
+    pub mastering_luminance: Option<Vec2>,
+    pub max_cll: Option<f32>,
+    pub max_fall: Option<f32>,
+}
+impl WpImageDescriptionCreatorParams {
+    pub fn new(raw: WpImageDescriptionCreatorParamsV1) -> Self {
+        Self {
+            raw,
+            transfer_function: None,
+            primaries: None,
+            luminances: None,
+            mastering_primaries: None,
+            mastering_luminance: None,
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+
+    fn image_description(&self) -> Option<ImageDescription> {
+        let transfer_function = self.transfer_function?;
+        Some(ImageDescription {
+            luminances: self
+                .luminances
+                .unwrap_or_else(|| transfer_function.default_luminances()),
+            mastering_primaries: self.mastering_primaries,
+            mastering_luminance: self.mastering_luminance,
+            max_cll: self.max_cll,
+            max_fall: self.max_fall,
+            ..ImageDescription::new(self.primaries?, transfer_function)
+        })
+    }
+}
+impl Drop for WpImageDescriptionCreatorParams {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn chromaticity(
+    r_x: i32,
+    r_y: i32,
+    g_x: i32,
+    g_y: i32,
+    b_x: i32,
+    b_y: i32,
+    w_x: i32,
+    w_y: i32,
+) -> Chromaticity {
+    let point = |x: i32, y: i32| Vec2::new(x as f32, y as f32) / 1_000_000.0;
+    Chromaticity {
+        red: point(r_x, r_y),
+        green: point(g_x, g_y),
+        blue: point(b_x, b_y),
+        white: point(w_x, w_y),
+    }
+}
+
+/// Set a parameter which may only be set once.
+fn set_once<T>(resource: &WpImageDescriptionCreatorParamsV1, slot: &mut Option<T>, value: T) {
+    if slot.is_some() {
+        resource.post_error(Error::AlreadySet, "the parameter is already set");
+        return;
+    }
+    *slot = Some(value);
+}
+
+impl Dispatch<WpImageDescriptionCreatorParamsV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        client: &wayland_server::Client,
+        resource: &WpImageDescriptionCreatorParamsV1,
+        request: <WpImageDescriptionCreatorParamsV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        if let Request::Create { image_description } = request {
+            let Some(params) = state.world().get::<WpImageDescriptionCreatorParams>(*data) else {
+                return;
+            };
+            let Some(description) = params.image_description() else {
+                resource.post_error(
+                    Error::IncompleteSet,
+                    "the transfer function and the primaries are required",
+                );
+                return;
+            };
+            // the protocol has no error for custom primaries which can not be converted
+            let valid = description.chromaticity().is_valid();
+            state.spawn_child_object(
+                DWay::client_entity(client),
+                image_description,
+                data_init,
+                |o| {
+                    if valid {
+                        WpImageDescription::new(o, description, false)
+                    } else {
+                        WpImageDescription::failed(o, Cause::Unsupported, "invalid primaries")
+                    }
+                },
+            );
+            state.despawn_object(*data, resource);
+            return;
+        }
+        let Some(mut params) = state.get_mut::<WpImageDescriptionCreatorParams>(*data) else {
+            return;
+        };
+        match request {
+            Request::SetTfNamed { tf } => {
+                let Some(tf) = tf.into_result().ok().and_then(transfer_function_from_wl) else {
+                    resource.post_error(Error::InvalidTf, "unsupported transfer function");
+                    return;
+                };
+                set_once(resource, &mut params.transfer_function, tf);
+            }
+            Request::SetTfPower { eexp } => {
+                if !(10000..=100000).contains(&eexp) {
+                    resource.post_error(Error::InvalidTf, "the exponent is out of range");
+                    return;
+                }
+                set_once(
+                    resource,
+                    &mut params.transfer_function,
+                    TransferFunction::Power(eexp as f32 / 10000.0),
+                );
+            }
+            Request::SetPrimariesNamed { primaries } => {
+                let Some(primaries) = primaries.into_result().ok().and_then(primaries_from_wl)
+                else {
+                    resource.post_error(Error::InvalidPrimariesNamed, "unknown primaries");
+                    return;
+                };
+                set_once(resource, &mut params.primaries, Primaries::Named(primaries));
+            }
+            Request::SetPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                let primaries = chromaticity(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+                set_once(resource, &mut params.primaries, Primaries::Custom(primaries));
+            }
+            Request::SetLuminances {
+                min_lum,
+                max_lum,
+                reference_lum,
+            } => {
+                let min = min_lum as f32 / 10000.0;
+                let (max, reference) = (max_lum as f32, reference_lum as f32);
+                if max <= min || reference <= min {
+                    resource.post_error(Error::InvalidLuminance, "invalid luminances");
+                    return;
+                }
+                set_once(
+                    resource,
+                    &mut params.luminances,
+                    Luminances::new(min, max, reference),
+                );
+            }
+            Request::SetMasteringDisplayPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                let primaries = chromaticity(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+                set_once(resource, &mut params.mastering_primaries, primaries);
+            }
+            Request::SetMasteringLuminance { min_lum, max_lum } => {
+                let luminance = Vec2::new(min_lum as f32 / 10000.0, max_lum as f32);
+                if luminance.y <= luminance.x {
+                    resource.post_error(Error::InvalidLuminance, "invalid mastering luminance");
+                    return;
+                }
+                set_once(resource, &mut params.mastering_luminance, luminance);
+            }
+            Request::SetMaxCll { max_cll } => {
+                set_once(resource, &mut params.max_cll, max_cll as f32);
+            }
+            Request::SetMaxFall { max_fall } => {
+                set_once(resource, &mut params.max_fall, max_fall as f32);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpImageDescriptionCreatorParamsV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
@@ -0,0 +1,174 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use dway_util::color::{ImageDescription, Primaries};
+use wayland_protocols::wp::color_management::v1::server::{
+    wp_image_description_info_v1::WpImageDescriptionInfoV1, wp_image_description_v1::*,
+};
+
+use super::{primaries_to_wl, transfer_function_to_wl};
+use crate::prelude::*;
+
+static NEXT_IDENTITY: AtomicU32 = AtomicU32::new(1);
+
+/// A new identity of an image description, identities are never reused.
+pub fn next_identity() -> u32 {
+    NEXT_IDENTITY.fetch_add(1, Ordering::Relaxed)
+}
+
+/// An image description object, a failed one has no description.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct WpImageDescription {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: WpImageDescriptionV1,
+    pub description: Option<ImageDescription>,
+    /// Whether the client may get the information of the description, only the descriptions
+    /// made by the compositor allow it
+    pub information: bool,
+}
+impl WpImageDescription {
+    /// A ready image description.
+    pub fn new(raw: WpImageDescriptionV1, description: ImageDescription, information: bool) -> Self {
+        raw.ready(next_identity());
+        Self {
+            raw,
+            description: Some(description),
+            information,
+        }
+    }
+
+    pub fn failed(raw: WpImageDescriptionV1, cause: Cause, msg: &str) -> Self {
+        raw.failed(cause, msg.to_string());
+        Self {
+            raw,
+            description: None,
+            information: false,
+        }
+    }
+}
+impl Drop for WpImageDescription {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+
+fn send_information(info: &WpImageDescriptionInfoV1, description: &ImageDescription) {
+    let coordinate = |v: f32| (v * 1_000_000.0).round() as i32;
+    let chromaticity = description.chromaticity();
+    info.primaries(
+        coordinate(chromaticity.red.x),
+        coordinate(chromaticity.red.y),
+        coordinate(chromaticity.green.x),
+        coordinate(chromaticity.green.y),
+        coordinate(chromaticity.blue.x),
+        coordinate(chromaticity.blue.y),
+        coordinate(chromaticity.white.x),
+        coordinate(chromaticity.white.y),
+    );
+    if let Primaries::Named(named) = description.primaries {
+        info.primaries_named(primaries_to_wl(named));
+    }
+    match transfer_function_to_wl(description.transfer_function) {
+        Some(tf) => info.tf_named(tf),
+        None => info.tf_power((description.transfer_function.exponent() * 10000.0).round() as u32),
+    }
+    let luminances = description.luminances;
+    info.luminances(
+        (luminances.min * 10000.0).round() as u32,
+        luminances.max.round() as u32,
+        luminances.reference.round() as u32,
+    );
+    let target = description.mastering_primaries.unwrap_or(chromaticity);
+    info.target_primaries(
+        coordinate(target.red.x),
+        coordinate(target.red.y),
+        coordinate(target.green.x),
+        coordinate(target.green.y),
+        coordinate(target.blue.x),
+        coordinate(target.blue.y),
+        coordinate(target.white.x),
+        coordinate(target.white.y),
+    );
+    let target_luminance = description
+        .mastering_luminance
+        .unwrap_or(Vec2::new(luminances.min, luminances.max));
+    info.target_luminance(
+        (target_luminance.x * 10000.0).round() as u32,
+        target_luminance.y.round() as u32,
+    );
+    if let Some(max_cll) = description.max_cll {
+        info.target_max_cll(max_cll.round() as u32);
+    }
+    if let Some(max_fall) = description.max_fall {
+        info.target_max_fall(max_fall.round() as u32);
+    }
+    info.done();
+}
+
+impl Dispatch<WpImageDescriptionV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &WpImageDescriptionV1,
+        request: <WpImageDescriptionV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::GetInformation { information } => {
+                let Some((description, allowed)) = state
+                    .world()
+                    .get::<WpImageDescription>(*data)
+                    .map(|d| (d.description.clone(), d.information))
+                else {
+                    return;
+                };
+                let Some(description) = description else {
+                    resource.post_error(Error::NotReady, "the image description failed");
+                    return;
+                };
+                if !allowed {
+                    resource.post_error(
+                        Error::NoInformation,
+                        "the image description does not allow get_information",
+                    );
+                    return;
+                }
+                let info = data_init.init(information, ());
+                send_information(&info, &description);
+            }
+            Request::Destroy => {
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpImageDescriptionV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
+impl Dispatch<WpImageDescriptionInfoV1, ()> for DWay {
+    fn request(
+        _state: &mut Self,
+        _client: &wayland_server::Client,
+        _resource: &WpImageDescriptionInfoV1,
+        _request: <WpImageDescriptionInfoV1 as WlResource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        todo!()
+    }
+}
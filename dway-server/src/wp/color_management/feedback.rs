@@ -0,0 +1,114 @@
+use dway_util::color::{ImageDescription, OutputImageDescription};
+use wayland_protocols::wp::color_management::v1::server::wp_color_management_surface_feedback_v1::*;
+
+use super::description::{next_identity, WpImageDescription};
+use crate::{
+    prelude::*,
+    wl::{
+        output::{OutputList, WlOutput},
+        surface::WlSurface,
+    },
+};
+
+/// The feedback of the image description preferred for a surface, which is the one of the first
+/// output the surface entered.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct WpColorManagementSurfaceFeedback {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: WpColorManagementSurfaceFeedbackV1,
+    pub surface: Entity,
+    pub preferred: ImageDescription,
+    pub identity: u32,
+}
+impl WpColorManagementSurfaceFeedback {
+    pub fn new(raw: WpColorManagementSurfaceFeedbackV1, surface: Entity) -> Self {
+        Self {
+            raw,
+            surface,
+            preferred: ImageDescription::srgb(),
+            identity: next_identity(),
+        }
+    }
+}
+impl Drop for WpColorManagementSurfaceFeedback {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<WpColorManagementSurfaceFeedbackV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        client: &wayland_server::Client,
+        resource: &WpColorManagementSurfaceFeedbackV1,
+        request: <WpColorManagementSurfaceFeedbackV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::GetPreferred { image_description }
+            | Request::GetPreferredParametric { image_description } => {
+                let Some((surface, preferred)) = state
+                    .world()
+                    .get::<WpColorManagementSurfaceFeedback>(*data)
+                    .map(|feedback| (feedback.surface, feedback.preferred.clone()))
+                else {
+                    return;
+                };
+                if state.world().get::<WlSurface>(surface).is_none() {
+                    resource.post_error(Error::Inert, "the surface was destroyed");
+                    return;
+                }
+                state.spawn_child_object(
+                    DWay::client_entity(client),
+                    image_description,
+                    data_init,
+                    |o| WpImageDescription::new(o, preferred, true),
+                );
+            }
+            Request::Destroy => {
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpColorManagementSurfaceFeedbackV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
+/// Notify the clients when the outputs of their surfaces change the preferred image description.
+pub fn update_preferred_image_descriptions(
+    mut feedback_query: Query<&mut WpColorManagementSurfaceFeedback>,
+    surface_query: Query<Option<&OutputList>, With<WlSurface>>,
+    wl_output_query: Query<&WlOutput>,
+    description_query: Query<&OutputImageDescription>,
+) {
+    for mut feedback in &mut feedback_query {
+        let Ok(output_list) = surface_query.get(feedback.surface) else {
+            continue;
+        };
+        let preferred = output_list
+            .and_then(|list| list.iter().next())
+            .and_then(|wl_output| wl_output_query.get(wl_output).ok())
+            .and_then(|wl_output| description_query.get(wl_output.output).ok())
+            .map(|description| description.0.clone())
+            .unwrap_or_default();
+        if preferred != feedback.preferred {
+            feedback.preferred = preferred;
+            feedback.identity = next_identity();
+            feedback.raw.preferred_changed(feedback.identity);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use dway_util::color::NamedPrimaries;
+use wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::*;
+
+use super::{
+    creator::WpImageDescriptionCreatorParams, feedback::WpColorManagementSurfaceFeedback,
+    output::WpColorManagementOutput, primaries_to_wl, surface::WpColorManagementSurface,
+    SUPPORTED_TRANSFER_FUNCTIONS,
+};
+use crate::{prelude::*, wl::output::WlOutput};
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct WpColorManager {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: WpColorManagerV1,
+}
+impl WpColorManager {
+    pub fn new(raw: WpColorManagerV1) -> Self {
+        raw.supported_intent(RenderIntent::Perceptual);
+        for feature in [
+            Feature::Parametric,
+            Feature::SetPrimaries,
+            Feature::SetTfPower,
+            Feature::SetLuminances,
+            Feature::SetMasteringDisplayPrimaries,
+        ] {
+            raw.supported_feature(feature);
+        }
+        for tf in SUPPORTED_TRANSFER_FUNCTIONS {
+            raw.supported_tf_named(tf);
+        }
+        for primaries in NamedPrimaries::ALL {
+            raw.supported_primaries_named(primaries_to_wl(primaries));
+        }
+        raw.done();
+        Self { raw }
+    }
+}
+impl Drop for WpColorManager {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<WpColorManagerV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &WpColorManagerV1,
+        request: <WpColorManagerV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::GetOutput { id, output } => {
+                let output_entity = state.with_component(&output, |o: &WlOutput| o.output);
+                let description = output_entity
+                    .map(|output| WpColorManagementOutput::image_description(state, output))
+                    .unwrap_or_default();
+                state.spawn_child_object(*data, id, data_init, |o| {
+                    WpColorManagementOutput::new(o, output_entity, description)
+                });
+            }
+            Request::GetSurface { id, surface } => {
+                let surface_entity = DWay::get_entity(&surface);
+                if WpColorManagementSurface::exists(state, surface_entity) {
+                    resource.post_error(
+                        Error::SurfaceExists,
+                        "the surface already has a color management surface",
+                    );
+                    return;
+                }
+                state.spawn_child_object(*data, id, data_init, |o| {
+                    WpColorManagementSurface::new(o, surface_entity)
+                });
+            }
+            Request::GetSurfaceFeedback { id, surface } => {
+                let surface_entity = DWay::get_entity(&surface);
+                state.spawn_child_object(*data, id, data_init, |o| {
+                    WpColorManagementSurfaceFeedback::new(o, surface_entity)
+                });
+            }
+            Request::CreateParametricCreator { obj } => {
+                state.spawn_child_object(
+                    *data,
+                    obj,
+                    data_init,
+                    WpImageDescriptionCreatorParams::new,
+                );
+            }
+            Request::CreateIccCreator { .. } => {
+                resource.post_error(Error::UnsupportedFeature, "icc profiles are not supported");
+            }
+            Request::CreateWindowsScrgb { .. } => {
+                resource.post_error(Error::UnsupportedFeature, "scRGB is not supported");
+            }
+            Request::Destroy => {
+                state.despawn_object_component::<WpColorManager>(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpColorManagerV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object_component::<WpColorManager>(*data, resource);
+    }
+}
+
+impl GlobalDispatch<WpColorManagerV1, Entity> for DWay {
+    fn bind(
+        state: &mut DWay,
+        _handle: &DisplayHandle,
+        client: &wayland_server::Client,
+        resource: wayland_server::New<WpColorManagerV1>,
+        _global_data: &bevy::prelude::Entity,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        state.bind(client, resource, data_init, WpColorManager::new);
+    }
+}
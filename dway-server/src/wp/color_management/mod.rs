@@ -0,0 +1,110 @@
+pub mod creator;
+pub mod description;
+pub mod feedback;
+pub mod manager;
+pub mod output;
+pub mod surface;
+
+use dway_util::color::{NamedPrimaries, TransferFunction};
+use wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::{
+    self, Primaries, WpColorManagerV1,
+};
+
+use crate::{prelude::*, state::add_global_dispatch};
+
+/// The transfer functions which can be converted in the composition shaders.
+pub const SUPPORTED_TRANSFER_FUNCTIONS: [wp_color_manager_v1::TransferFunction; 7] = [
+    wp_color_manager_v1::TransferFunction::Srgb,
+    wp_color_manager_v1::TransferFunction::Gamma22,
+    wp_color_manager_v1::TransferFunction::Gamma28,
+    wp_color_manager_v1::TransferFunction::Bt1886,
+    wp_color_manager_v1::TransferFunction::ExtLinear,
+    wp_color_manager_v1::TransferFunction::St2084Pq,
+    wp_color_manager_v1::TransferFunction::Hlg,
+];
+
+pub fn transfer_function_from_wl(
+    tf: wp_color_manager_v1::TransferFunction,
+) -> Option<TransferFunction> {
+    use wp_color_manager_v1::TransferFunction as Tf;
+    Some(match tf {
+        Tf::Srgb => TransferFunction::Srgb,
+        Tf::Gamma22 => TransferFunction::Gamma22,
+        Tf::Gamma28 => TransferFunction::Gamma28,
+        Tf::Bt1886 => TransferFunction::Bt1886,
+        Tf::ExtLinear => TransferFunction::ExtLinear,
+        Tf::St2084Pq => TransferFunction::St2084Pq,
+        Tf::Hlg => TransferFunction::Hlg,
+        _ => return None,
+    })
+}
+
+/// The named transfer function, power functions other than the named ones have none.
+pub fn transfer_function_to_wl(
+    tf: TransferFunction,
+) -> Option<wp_color_manager_v1::TransferFunction> {
+    use wp_color_manager_v1::TransferFunction as Tf;
+    Some(match tf {
+        TransferFunction::Srgb => Tf::Srgb,
+        TransferFunction::Gamma22 => Tf::Gamma22,
+        TransferFunction::Gamma28 => Tf::Gamma28,
+        TransferFunction::Bt1886 => Tf::Bt1886,
+        TransferFunction::ExtLinear => Tf::ExtLinear,
+        TransferFunction::St2084Pq => Tf::St2084Pq,
+        TransferFunction::Hlg => Tf::Hlg,
+        TransferFunction::Power(_) => return None,
+    })
+}
+
+pub fn primaries_from_wl(primaries: Primaries) -> Option<NamedPrimaries> {
+    Some(match primaries {
+        Primaries::Srgb => NamedPrimaries::Srgb,
+        Primaries::PalM => NamedPrimaries::PalM,
+        Primaries::Pal => NamedPrimaries::Pal,
+        Primaries::Ntsc => NamedPrimaries::Ntsc,
+        Primaries::GenericFilm => NamedPrimaries::GenericFilm,
+        Primaries::Bt2020 => NamedPrimaries::Bt2020,
+        Primaries::Cie1931Xyz => NamedPrimaries::Cie1931Xyz,
+        Primaries::DciP3 => NamedPrimaries::DciP3,
+        Primaries::DisplayP3 => NamedPrimaries::DisplayP3,
+        Primaries::AdobeRgb => NamedPrimaries::AdobeRgb,
+        _ => return None,
+    })
+}
+
+pub fn primaries_to_wl(primaries: NamedPrimaries) -> Primaries {
+    match primaries {
+        NamedPrimaries::Srgb => Primaries::Srgb,
+        NamedPrimaries::PalM => Primaries::PalM,
+        NamedPrimaries::Pal => Primaries::Pal,
+        NamedPrimaries::Ntsc => Primaries::Ntsc,
+        NamedPrimaries::GenericFilm => Primaries::GenericFilm,
+        NamedPrimaries::Bt2020 => Primaries::Bt2020,
+        NamedPrimaries::Cie1931Xyz => Primaries::Cie1931Xyz,
+        NamedPrimaries::DciP3 => Primaries::DciP3,
+        NamedPrimaries::DisplayP3 => Primaries::DisplayP3,
+        NamedPrimaries::AdobeRgb => Primaries::AdobeRgb,
+    }
+}
+
+pub struct ColorManagementPlugin;
+
+impl Plugin for ColorManagementPlugin {
+    fn build(&self, app: &mut App) {
+        add_global_dispatch::<WpColorManagerV1, 1>(app);
+        app.register_type::<manager::WpColorManager>();
+        app.register_type::<output::WpColorManagementOutput>();
+        app.register_type::<surface::WpColorManagementSurface>();
+        app.register_type::<feedback::WpColorManagementSurfaceFeedback>();
+        app.register_type::<creator::WpImageDescriptionCreatorParams>();
+        app.register_type::<description::WpImageDescription>();
+        app.add_systems(
+            PreUpdate,
+            (
+                output::update_output_image_descriptions,
+                feedback::update_preferred_image_descriptions,
+            )
+                .in_set(DWayServerSet::UpdateJoin),
+        );
+    }
+}
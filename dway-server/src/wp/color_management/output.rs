@@ -0,0 +1,115 @@
+use dway_util::color::{ImageDescription, OutputImageDescription};
+use wayland_protocols::wp::color_management::v1::server::{
+    wp_color_management_output_v1::*, wp_image_description_v1::Cause,
+};
+
+use super::description::WpImageDescription;
+use crate::prelude::*;
+
+/// The color management of an output, the output is none after it was removed.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct WpColorManagementOutput {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: WpColorManagementOutputV1,
+    pub output: Option<Entity>,
+    /// The image description last announced to the client
+    pub description: ImageDescription,
+}
+impl WpColorManagementOutput {
+    pub fn new(
+        raw: WpColorManagementOutputV1,
+        output: Option<Entity>,
+        description: ImageDescription,
+    ) -> Self {
+        Self {
+            raw,
+            output,
+            description,
+        }
+    }
+
+    /// The image description the output is driven with.
+    pub fn image_description(state: &DWay, output: Entity) -> ImageDescription {
+        state
+            .world()
+            .get::<OutputImageDescription>(output)
+            .map(|description| description.0.clone())
+            .unwrap_or_default()
+    }
+}
+impl Drop for WpColorManagementOutput {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<WpColorManagementOutputV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        client: &wayland_server::Client,
+        resource: &WpColorManagementOutputV1,
+        request: <WpColorManagementOutputV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::GetImageDescription { image_description } => {
+                let description = state
+                    .world()
+                    .get::<WpColorManagementOutput>(*data)
+                    .and_then(|output| output.output.map(|_| output.description.clone()));
+                state.spawn_child_object(
+                    DWay::client_entity(client),
+                    image_description,
+                    data_init,
+                    |o| match description {
+                        Some(description) => WpImageDescription::new(o, description, true),
+                        None => {
+                            WpImageDescription::failed(o, Cause::NoOutput, "the output was removed")
+                        }
+                    },
+                );
+            }
+            Request::Destroy => {
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpColorManagementOutputV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
+/// Notify the clients about the outputs whose image description changed.
+pub fn update_output_image_descriptions(
+    mut output_query: Query<&mut WpColorManagementOutput>,
+    description_query: Query<Option<&OutputImageDescription>>,
+) {
+    for mut output in &mut output_query {
+        let Some(entity) = output.output else {
+            continue;
+        };
+        let Ok(description) = description_query.get(entity) else {
+            debug!(output = ?entity, "the color managed output was removed");
+            output.output = None;
+            continue;
+        };
+        let description = description.map(|d| d.0.clone()).unwrap_or_default();
+        if description != output.description {
+            output.description = description;
+            output.raw.image_description_changed();
+        }
+    }
+}
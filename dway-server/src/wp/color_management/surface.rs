@@ -0,0 +1,120 @@
+use dway_util::color::ImageDescription;
+use wayland_protocols::wp::color_management::v1::server::{
+    wp_color_management_surface_v1::*, wp_color_manager_v1::RenderIntent,
+};
+
+use super::description::WpImageDescription;
+use crate::{prelude::*, wl::surface::WlSurface};
+
+/// The color management of a surface, it becomes inert when the surface is destroyed.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct WpColorManagementSurface {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: WpColorManagementSurfaceV1,
+    pub surface: Entity,
+}
+impl WpColorManagementSurface {
+    pub fn new(raw: WpColorManagementSurfaceV1, surface: Entity) -> Self {
+        Self { raw, surface }
+    }
+
+    pub fn exists(state: &mut DWay, surface: Entity) -> bool {
+        let world = state.world_mut();
+        world
+            .query::<&WpColorManagementSurface>()
+            .iter(world)
+            .any(|object| object.surface == surface)
+    }
+
+    /// Set the pending image description of the surface, applied on the next commit.
+    fn set_pending(
+        state: &mut DWay,
+        resource: &WpColorManagementSurfaceV1,
+        entity: Entity,
+        description: Option<ImageDescription>,
+    ) {
+        let Some(surface) = state
+            .world()
+            .get::<WpColorManagementSurface>(entity)
+            .map(|object| object.surface)
+        else {
+            return;
+        };
+        let Some(mut surface) = state.get_mut::<WlSurface>(surface) else {
+            resource.post_error(Error::Inert, "the surface was destroyed");
+            return;
+        };
+        surface.pending.image_description = Some(description);
+    }
+}
+impl Drop for WpColorManagementSurface {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<WpColorManagementSurfaceV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &WpColorManagementSurfaceV1,
+        request: <WpColorManagementSurfaceV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::SetImageDescription {
+                image_description,
+                render_intent,
+            } => {
+                if render_intent.into_result().ok() != Some(RenderIntent::Perceptual) {
+                    resource.post_error(Error::RenderIntent, "unsupported render intent");
+                    return;
+                }
+                let Some(description) = state
+                    .with_component(&image_description, |d: &WpImageDescription| {
+                        d.description.clone()
+                    })
+                    .flatten()
+                else {
+                    resource.post_error(
+                        Error::ImageDescription,
+                        "the image description is not ready",
+                    );
+                    return;
+                };
+                WpColorManagementSurface::set_pending(state, resource, *data, Some(description));
+            }
+            Request::UnsetImageDescription => {
+                WpColorManagementSurface::set_pending(state, resource, *data, None);
+            }
+            Request::Destroy => {
+                if let Some(surface) = state
+                    .world()
+                    .get::<WpColorManagementSurface>(*data)
+                    .map(|object| object.surface)
+                {
+                    if let Some(mut surface) = state.get_mut::<WlSurface>(surface) {
+                        surface.pending.image_description = Some(None);
+                    }
+                }
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &WpColorManagementSurfaceV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
@@ -5,7 +5,10 @@ use crate::{
     prelude::*,
     wp::data_device::{
         data_source::WlDataSource,
-        dnd::{DragAndDrop, DropFrom},
+        dnd::{
+            finish_drag, write_dnd_data, CompositorDndOffer, CompositorDragSource, DragAndDrop,
+            DropFrom,
+        },
     },
 };
 
@@ -61,6 +64,54 @@ impl Drop for WlDataOffer {
         trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
     }
 }
+/// Requests to the offers of a drag started by the compositor.
+fn compositor_dnd_request(
+    state: &mut DWay,
+    resource: &wl_data_offer::WlDataOffer,
+    source_entity: Entity,
+    request: wl_data_offer::Request,
+) {
+    let Some(mut source) = state.get_mut::<CompositorDragSource>(source_entity) else {
+        return;
+    };
+    match request {
+        wl_data_offer::Request::Accept { serial: _, mime_type } => {
+            source.accepted = mime_type.filter(|m| source.get(m).is_some());
+        }
+        wl_data_offer::Request::Receive { mime_type, fd } => {
+            if let Some(data) = source.get(&mime_type) {
+                write_dnd_data(data.clone(), fd);
+            }
+        }
+        wl_data_offer::Request::SetActions {
+            dnd_actions,
+            preferred_action,
+        } => {
+            let dnd_actions = dnd_actions.into_result().unwrap_or(DndAction::None);
+            let preferred_action = preferred_action.into_result().unwrap_or(DndAction::None);
+            let chosen_action =
+                DragAndDrop::choise_action(source.actions & dnd_actions, preferred_action);
+            if source.chosen_action != chosen_action {
+                source.chosen_action = chosen_action;
+                resource.action(chosen_action);
+            }
+        }
+        wl_data_offer::Request::Finish => {
+            finish_drag(state, source_entity);
+        }
+        wl_data_offer::Request::Destroy => {
+            // clients before version 3 can't finish a drop, they destroy the offer after reading
+            // the data
+            let finished = resource.version() < 3 && source.dropped;
+            state.destroy_object(resource);
+            if finished {
+                finish_drag(state, source_entity);
+            }
+        }
+        _ => {}
+    }
+}
+
 impl Dispatch<wl_data_offer::WlDataOffer, Entity> for DWay {
     fn request(
         state: &mut DWay,
@@ -75,6 +126,10 @@ impl Dispatch<wl_data_offer::WlDataOffer, Entity> for DWay {
             span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
         let _enter = span.enter();
         debug!("request {:?}", &request);
+        if let Some(source_entity) = state.get::<CompositorDndOffer>(*data).map(|o| o.source) {
+            compositor_dnd_request(state, resource, source_entity, request);
+            return;
+        }
         match request {
             wl_data_offer::Request::Accept { serial: _, mime_type } => {
                 let accepted = if let Some(source) = state
@@ -1,6 +1,15 @@
+use std::{io::Write, os::fd::OwnedFd, sync::Arc};
+
+use bevy::tasks::IoTaskPool;
 use wayland_server::protocol::wl_data_device_manager::DndAction;
 
-use crate::prelude::*;
+use crate::{
+    input::time,
+    prelude::*,
+    util::serial::next_serial,
+    wl::surface::WlSurface,
+    wp::data_device::{data_offer::WlDataOffer, WlDataDevice},
+};
 
 #[derive(Component, Reflect, Debug)]
 pub struct DragIcon;
@@ -34,3 +43,323 @@ impl DragAndDrop {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+struct CompositorDndTarget {
+    data_device: Entity,
+    offer: Entity,
+    surface: Entity,
+}
+
+/// A drag started by the compositor, e.g. from its own ui, offered to the client under the
+/// cursor.
+#[derive(Component, Debug)]
+pub struct CompositorDragSource {
+    pub data: Vec<(String, Arc<[u8]>)>,
+    pub actions: DndAction,
+    /// The mime type accepted by the client under the cursor.
+    pub accepted: Option<String>,
+    pub chosen_action: DndAction,
+    /// Set when the drop was sent to the client, until it finished reading the data.
+    pub dropped: bool,
+    target: Option<CompositorDndTarget>,
+}
+
+impl CompositorDragSource {
+    pub fn new(data: Vec<(String, Arc<[u8]>)>, actions: DndAction) -> Self {
+        Self {
+            data,
+            actions,
+            accepted: None,
+            chosen_action: DndAction::None,
+            dropped: false,
+            target: None,
+        }
+    }
+
+    pub fn get(&self, mime_type: &str) -> Option<&Arc<[u8]>> {
+        self.data
+            .iter()
+            .find(|(m, _)| m == mime_type)
+            .map(|(_, data)| data)
+    }
+}
+
+/// Added to the data offers of a [`CompositorDragSource`].
+#[derive(Component, Debug)]
+pub struct CompositorDndOffer {
+    pub source: Entity,
+}
+
+#[derive(Message, Debug, Clone)]
+pub enum CompositorDndRequest {
+    /// `position` is relative to the window geometry, like the position of pointer events.
+    Enter {
+        source: Entity,
+        surface: Entity,
+        position: Vec2,
+    },
+    Motion {
+        source: Entity,
+        position: Vec2,
+    },
+    Leave {
+        source: Entity,
+    },
+    Drop {
+        source: Entity,
+    },
+    /// The drag ended without a drop.
+    Cancel {
+        source: Entity,
+    },
+}
+
+/// The end of a [`CompositorDragSource`], the source entity is despawned.
+#[derive(Message, Debug, Clone)]
+pub enum CompositorDndEvent {
+    /// The client read the data of the drop.
+    Finished { source: Entity, action: DndAction },
+    /// The client rejected the drop or the drag was cancelled.
+    Cancelled { source: Entity },
+}
+
+pub fn write_dnd_data(data: Arc<[u8]>, fd: OwnedFd) {
+    IoTaskPool::get()
+        .spawn(async move {
+            let mut file = std::fs::File::from(fd);
+            if let Err(e) = file.write_all(&data) {
+                error!("failed to send drag and drop data: {e}");
+            }
+        })
+        .detach();
+}
+
+fn leave_target(
+    source: &mut CompositorDragSource,
+    device_query: &Query<(Entity, &WlDataDevice)>,
+    commands: &mut Commands,
+) {
+    let Some(target) = source.target.take() else {
+        return;
+    };
+    if let Ok((_, data_device)) = device_query.get(target.data_device) {
+        data_device.raw.leave();
+    }
+    commands.entity(target.offer).despawn();
+    source.accepted = None;
+    source.chosen_action = DndAction::None;
+}
+
+fn cancel_drag(
+    source_entity: Entity,
+    source: &mut CompositorDragSource,
+    device_query: &Query<(Entity, &WlDataDevice)>,
+    dnd_events: &mut MessageWriter<CompositorDndEvent>,
+    commands: &mut Commands,
+) {
+    leave_target(source, device_query, commands);
+    dnd_events.write(CompositorDndEvent::Cancelled {
+        source: source_entity,
+    });
+    commands.entity(source_entity).despawn();
+}
+
+/// Called when the client finished reading the data of a drop.
+pub fn finish_drag(state: &mut DWay, source_entity: Entity) {
+    let Some(action) = state
+        .get::<CompositorDragSource>(source_entity)
+        .filter(|source| source.dropped)
+        .map(|source| source.chosen_action)
+    else {
+        return;
+    };
+    state.send_event(CompositorDndEvent::Finished {
+        source: source_entity,
+        action,
+    });
+    state.despawn_tree(source_entity);
+}
+
+pub fn compositor_dnd_system(
+    mut events: MessageReader<CompositorDndRequest>,
+    mut source_query: Query<&mut CompositorDragSource>,
+    surface_query: Query<&WlSurface>,
+    device_query: Query<(Entity, &WlDataDevice)>,
+    mut dnd_events: MessageWriter<CompositorDndEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        match event {
+            CompositorDndRequest::Enter {
+                source: source_entity,
+                surface: surface_entity,
+                position,
+            } => {
+                let Ok(mut source) = source_query.get_mut(*source_entity) else {
+                    continue;
+                };
+                leave_target(&mut source, &device_query, &mut commands);
+
+                let Ok(surface) = surface_query.get(*surface_entity) else {
+                    continue;
+                };
+                let Some(client) = surface.raw.client() else {
+                    continue;
+                };
+                let Some((device_entity, data_device)) = device_query
+                    .iter()
+                    .find(|(_, d)| d.raw.client().map(|c| c.id()) == Some(client.id()))
+                else {
+                    debug!(surface=?surface_entity, "the client has no data device");
+                    continue;
+                };
+
+                let offer_entity = commands
+                    .spawn(CompositorDndOffer {
+                        source: *source_entity,
+                    })
+                    .id();
+                match WlDataOffer::create(
+                    &data_device.dhandle,
+                    &client,
+                    data_device.raw.version(),
+                    offer_entity,
+                ) {
+                    Ok(data_offer) => {
+                        data_device.raw.data_offer(&data_offer.raw);
+                        for (mime_type, _) in &source.data {
+                            data_offer.raw.offer(mime_type.clone());
+                        }
+                        if data_offer.raw.version() >= 3 {
+                            data_offer.raw.source_actions(source.actions);
+                        } else {
+                            // clients before version 3 never set the actions, their drops copy
+                            // the data
+                            source.chosen_action =
+                                DragAndDrop::choise_action(source.actions, DndAction::Copy);
+                        }
+                        let position =
+                            position.as_dvec2() - surface.image_rect().pos().as_dvec2();
+                        data_device.raw.enter(
+                            next_serial(),
+                            &surface.raw,
+                            position.x,
+                            position.y,
+                            Some(&data_offer.raw),
+                        );
+                        commands.entity(offer_entity).insert(data_offer);
+                        source.target = Some(CompositorDndTarget {
+                            data_device: device_entity,
+                            offer: offer_entity,
+                            surface: *surface_entity,
+                        });
+                    }
+                    Err(e) => {
+                        error!("failed to create WlDataOffer: {e}");
+                        commands.entity(offer_entity).despawn();
+                    }
+                }
+            }
+            CompositorDndRequest::Motion { source, position } => {
+                let Some(target) = source_query.get(*source).ok().and_then(|s| s.target) else {
+                    continue;
+                };
+                let (Ok((_, data_device)), Ok(surface)) = (
+                    device_query.get(target.data_device),
+                    surface_query.get(target.surface),
+                ) else {
+                    continue;
+                };
+                let position = position.as_dvec2() - surface.image_rect().pos().as_dvec2();
+                data_device.raw.motion(time(), position.x, position.y);
+            }
+            CompositorDndRequest::Leave { source } => {
+                if let Ok(mut source) = source_query.get_mut(*source) {
+                    leave_target(&mut source, &device_query, &mut commands);
+                }
+            }
+            CompositorDndRequest::Drop {
+                source: source_entity,
+            } => {
+                let Ok(mut source) = source_query.get_mut(*source_entity) else {
+                    continue;
+                };
+                let accepted = source.accepted.is_some() && !source.chosen_action.is_empty();
+                let Some((_, data_device)) = source
+                    .target
+                    .filter(|_| accepted)
+                    .and_then(|target| device_query.get(target.data_device).ok())
+                else {
+                    cancel_drag(
+                        *source_entity,
+                        &mut source,
+                        &device_query,
+                        &mut dnd_events,
+                        &mut commands,
+                    );
+                    continue;
+                };
+                data_device.raw.drop();
+                source.dropped = true;
+            }
+            CompositorDndRequest::Cancel {
+                source: source_entity,
+            } => {
+                if let Ok(mut source) = source_query.get_mut(*source_entity) {
+                    cancel_drag(
+                        *source_entity,
+                        &mut source,
+                        &device_query,
+                        &mut dnd_events,
+                        &mut commands,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_event::<CompositorDndRequest>()
+            .add_event::<CompositorDndEvent>()
+            .add_systems(Update, compositor_dnd_system);
+        app
+    }
+
+    fn drag_source(app: &mut App) -> Entity {
+        let data = vec![("text/plain".to_string(), Arc::from(&b"text"[..]))];
+        app.world_mut()
+            .spawn(CompositorDragSource::new(data, DndAction::Copy))
+            .id()
+    }
+
+    #[test]
+    fn test_rejected_drop() {
+        let mut app = setup();
+        let source = drag_source(&mut app);
+        let cancelled = drag_source(&mut app);
+        app.world_mut()
+            .write_message(CompositorDndRequest::Drop { source });
+        app.world_mut()
+            .write_message(CompositorDndRequest::Cancel { source: cancelled });
+        app.update();
+
+        assert!(app.world().get_entity(source).is_err());
+        assert!(app.world().get_entity(cancelled).is_err());
+        let events = app.world().resource::<Events<CompositorDndEvent>>();
+        let sources: Vec<_> = events
+            .iter_current_update_messages()
+            .map(|event| match event {
+                CompositorDndEvent::Cancelled { source } => *source,
+                CompositorDndEvent::Finished { .. } => panic!("unexpected event: {event:?}"),
+            })
+            .collect();
+        assert_eq!(sources, vec![source, cancelled]);
+    }
+}
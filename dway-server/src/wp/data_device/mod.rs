@@ -6,7 +6,9 @@ pub mod manager;
 use bevy::ecs::relationship::Relationship as _;
 use data_offer::WlDataOffer;
 use data_source::WlDataSource;
-use dnd::{DragAndDrop, DragIcon};
+use dnd::{
+    compositor_dnd_system, CompositorDndEvent, CompositorDndRequest, DragAndDrop, DragIcon,
+};
 
 use crate::{
     clipboard::{
@@ -155,5 +157,11 @@ impl Plugin for DataDevicePlugin {
                 .in_set(DWayServerSet::UpdateClipboard),
         );
         app.add_event::<ClipboardEvent>();
+        app.add_event::<CompositorDndRequest>();
+        app.add_event::<CompositorDndEvent>();
+        app.add_systems(
+            PreUpdate,
+            compositor_dnd_system.in_set(DWayServerSet::GrabInput),
+        );
     }
 }
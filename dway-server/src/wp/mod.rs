@@ -2,6 +2,7 @@ use wayland_protocols::wp::text_input::zv3::server::zwp_text_input_manager_v3;
 
 use crate::{prelude::*, state::add_global_dispatch};
 
+pub mod color_management;
 pub mod data_device;
 pub mod drmlease;
 pub mod primary_selection;
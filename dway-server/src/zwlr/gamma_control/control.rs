@@ -0,0 +1,132 @@
+use std::{fs::File, os::unix::fs::FileExt};
+
+use dway_util::gamma::{ClientGamma, GammaLut, GammaSize};
+use wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_v1::*;
+
+use crate::prelude::*;
+
+/// The exclusive gamma control of an output, a control which failed has no output.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct ZwlrGammaControl {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: ZwlrGammaControlV1,
+    pub output: Option<Entity>,
+    pub size: usize,
+}
+impl ZwlrGammaControl {
+    pub fn new(raw: ZwlrGammaControlV1, output: Option<Entity>, size: usize) -> Self {
+        Self { raw, output, size }
+    }
+
+    pub fn is_owned(state: &mut DWay, output: Entity) -> bool {
+        let world = state.world_mut();
+        world
+            .query::<&ZwlrGammaControl>()
+            .iter(world)
+            .any(|control| control.output == Some(output))
+    }
+
+    /// Give up the output and restore its gamma table.
+    pub fn release(state: &mut DWay, entity: Entity) {
+        let world = state.world_mut();
+        let Some(output) = world
+            .get_mut::<ZwlrGammaControl>(entity)
+            .and_then(|mut control| control.output.take())
+        else {
+            return;
+        };
+        if let Ok(mut output) = world.get_entity_mut(output) {
+            output.remove::<ClientGamma>();
+        }
+    }
+
+    fn fail(state: &mut DWay, entity: Entity) {
+        Self::release(state, entity);
+        if let Some(control) = state.world().get::<ZwlrGammaControl>(entity) {
+            control.raw.failed();
+        }
+    }
+}
+impl Drop for ZwlrGammaControl {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<ZwlrGammaControlV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &ZwlrGammaControlV1,
+        request: <ZwlrGammaControlV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::SetGamma { fd } => {
+                let Some((output, size)) = state
+                    .world()
+                    .get::<ZwlrGammaControl>(*data)
+                    .and_then(|control| Some((control.output?, control.size)))
+                else {
+                    return;
+                };
+                let mut buffer = vec![0; size * 3 * 2];
+                if let Err(e) = File::from(fd).read_exact_at(&mut buffer, 0) {
+                    warn!("failed to read gamma ramps: {e}");
+                    ZwlrGammaControl::fail(state, *data);
+                    return;
+                }
+                let Some(lut) = GammaLut::from_ne_bytes(&buffer, size) else {
+                    resource.post_error(Error::InvalidGamma, "invalid gamma ramps");
+                    return;
+                };
+                if let Ok(mut output) = state.get_entity_mut(output) {
+                    output.insert(ClientGamma(lut));
+                }
+            }
+            Request::Destroy => {
+                ZwlrGammaControl::release(state, *data);
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &ZwlrGammaControlV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        ZwlrGammaControl::release(state, *data);
+        state.despawn_object(*data, resource);
+    }
+}
+
+/// Fail the controls of outputs which were removed or whose gamma size changed.
+pub fn update_gamma_controls(
+    mut control_query: Query<&mut ZwlrGammaControl>,
+    output_query: Query<&GammaSize>,
+    mut commands: Commands,
+) {
+    for mut control in &mut control_query {
+        let Some(output) = control.output else {
+            continue;
+        };
+        if output_query.get(output).ok().map(|size| size.0) == Some(control.size) {
+            continue;
+        }
+        debug!(?output, "the gamma control of the output failed");
+        control.output = None;
+        control.raw.failed();
+        if let Ok(mut output) = commands.get_entity(output) {
+            output.remove::<ClientGamma>();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use dway_util::gamma::GammaSize;
+use wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_manager_v1::*;
+
+use crate::{prelude::*, wl::output::WlOutput, zwlr::gamma_control::control::ZwlrGammaControl};
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct ZwlrGammaControlManager {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: ZwlrGammaControlManagerV1,
+}
+impl ZwlrGammaControlManager {
+    pub fn new(raw: ZwlrGammaControlManagerV1) -> Self {
+        Self { raw }
+    }
+}
+impl Drop for ZwlrGammaControlManager {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<ZwlrGammaControlManagerV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &ZwlrGammaControlManagerV1,
+        request: <ZwlrGammaControlManagerV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::GetGammaControl { id, output } => {
+                let output_entity = state
+                    .with_component(&output, |o: &WlOutput| o.output)
+                    .filter(|output| !ZwlrGammaControl::is_owned(state, *output));
+                let size = output_entity
+                    .and_then(|output| state.world().get::<GammaSize>(output))
+                    .map(|size| size.0);
+                state.spawn_child_object(*data, id, data_init, |o| match (output_entity, size) {
+                    (Some(output), Some(size)) => {
+                        o.gamma_size(size as u32);
+                        ZwlrGammaControl::new(o, Some(output), size)
+                    }
+                    _ => {
+                        o.failed();
+                        ZwlrGammaControl::new(o, None, 0)
+                    }
+                });
+            }
+            Request::Destroy => {
+                state.despawn_object_component::<ZwlrGammaControlManager>(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &ZwlrGammaControlManagerV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object_component::<ZwlrGammaControlManager>(*data, resource);
+    }
+}
+
+impl GlobalDispatch<ZwlrGammaControlManagerV1, Entity> for DWay {
+    fn bind(
+        state: &mut DWay,
+        _handle: &DisplayHandle,
+        client: &wayland_server::Client,
+        resource: wayland_server::New<ZwlrGammaControlManagerV1>,
+        _global_data: &bevy::prelude::Entity,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        state.bind(client, resource, data_init, ZwlrGammaControlManager::new);
+    }
+}
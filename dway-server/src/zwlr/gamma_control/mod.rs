@@ -0,0 +1,20 @@
+pub mod control;
+pub mod manager;
+
+use wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1;
+
+use crate::{prelude::*, state::add_global_dispatch};
+
+pub struct GammaControlPlugin;
+
+impl Plugin for GammaControlPlugin {
+    fn build(&self, app: &mut App) {
+        add_global_dispatch::<ZwlrGammaControlManagerV1, 1>(app);
+        app.register_type::<manager::ZwlrGammaControlManager>();
+        app.register_type::<control::ZwlrGammaControl>();
+        app.add_systems(
+            PreUpdate,
+            control::update_gamma_controls.in_set(DWayServerSet::UpdateJoin),
+        );
+    }
+}
@@ -1 +1,4 @@
 pub mod data_control;
+pub mod gamma_control;
+pub mod output_management;
+pub mod output_power;
@@ -0,0 +1,338 @@
+use dway_util::output::{
+    OutputConfigRequest, OutputConfigResult, OutputMode, OutputState, OutputTransform, VrrPolicy,
+};
+use wayland_protocols_wlr::output_management::v1::server::{
+    zwlr_output_configuration_head_v1::{self, ZwlrOutputConfigurationHeadV1},
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_mode_v1::ZwlrOutputModeV1,
+};
+
+use super::manager::ZwlrOutputManager;
+use crate::prelude::*;
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct ZwlrOutputConfiguration {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: ZwlrOutputConfigurationV1,
+    pub serial: u32,
+    /// The outputs disabled by `disable_head`
+    pub disabled: Vec<(Entity, OutputState)>,
+    pub used: bool,
+}
+impl ZwlrOutputConfiguration {
+    pub fn new(raw: ZwlrOutputConfigurationV1, serial: u32) -> Self {
+        Self {
+            raw,
+            serial,
+            disabled: Vec::new(),
+            used: false,
+        }
+    }
+}
+impl Drop for ZwlrOutputConfiguration {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct ZwlrOutputConfigurationHead {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: ZwlrOutputConfigurationHeadV1,
+    pub output: Entity,
+    #[reflect(ignore)]
+    pub modes: Vec<(ZwlrOutputModeV1, OutputMode)>,
+    pub state: OutputState,
+}
+impl ZwlrOutputConfigurationHead {
+    pub fn new(
+        raw: ZwlrOutputConfigurationHeadV1,
+        output: Entity,
+        modes: Vec<(ZwlrOutputModeV1, OutputMode)>,
+        state: OutputState,
+    ) -> Self {
+        Self {
+            raw,
+            output,
+            modes,
+            state,
+        }
+    }
+}
+impl Drop for ZwlrOutputConfigurationHead {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+
+/// The head sent to the client, or `None` if the output is already gone.
+fn find_head(
+    state: &DWay,
+    client: &wayland_server::Client,
+    head: &ZwlrOutputHeadV1,
+) -> Option<(Vec<(ZwlrOutputModeV1, OutputMode)>, OutputState)> {
+    let manager = state
+        .world()
+        .get::<ZwlrOutputManager>(DWay::client_entity(client))?;
+    let head = manager.heads.get(&DWay::get_entity(head))?;
+    Some((head.modes.clone(), head.state.clone()))
+}
+
+fn configured_outputs(state: &DWay, entity: Entity) -> Vec<Entity> {
+    let world = state.world();
+    let configuration = world.get::<ZwlrOutputConfiguration>(entity);
+    world
+        .get::<Children>(entity)
+        .into_iter()
+        .flat_map(|children| children.iter())
+        .filter_map(|child| world.get::<ZwlrOutputConfigurationHead>(child))
+        .map(|head| head.output)
+        .chain(
+            configuration
+                .into_iter()
+                .flat_map(|c| c.disabled.iter().map(|(output, _)| *output)),
+        )
+        .collect()
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        client: &wayland_server::Client,
+        resource: &ZwlrOutputConfigurationV1,
+        request: <ZwlrOutputConfigurationV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            zwlr_output_configuration_v1::Request::EnableHead { id, head } => {
+                let output = DWay::get_entity(&head);
+                if configured_outputs(state, *data).contains(&output) {
+                    resource.post_error(
+                        zwlr_output_configuration_v1::Error::AlreadyConfiguredHead,
+                        "the head is already configured",
+                    );
+                    return;
+                }
+                let (modes, mut output_state) = find_head(state, client, &head).unwrap_or_default();
+                output_state.enabled = true;
+                state.spawn_child_object(*data, id, data_init, |o| {
+                    ZwlrOutputConfigurationHead::new(o, output, modes, output_state)
+                });
+            }
+            zwlr_output_configuration_v1::Request::DisableHead { head } => {
+                let output = DWay::get_entity(&head);
+                if configured_outputs(state, *data).contains(&output) {
+                    resource.post_error(
+                        zwlr_output_configuration_v1::Error::AlreadyConfiguredHead,
+                        "the head is already configured",
+                    );
+                    return;
+                }
+                let (_, mut output_state) = find_head(state, client, &head).unwrap_or_default();
+                output_state.enabled = false;
+                if let Some(mut configuration) =
+                    state.world_mut().get_mut::<ZwlrOutputConfiguration>(*data)
+                {
+                    configuration.disabled.push((output, output_state));
+                }
+            }
+            zwlr_output_configuration_v1::Request::Apply => {
+                submit_configuration(state, client, resource, *data, false);
+            }
+            zwlr_output_configuration_v1::Request::Test => {
+                submit_configuration(state, client, resource, *data, true);
+            }
+            zwlr_output_configuration_v1::Request::Destroy => {
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &ZwlrOutputConfigurationV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
+fn submit_configuration(
+    state: &mut DWay,
+    client: &wayland_server::Client,
+    resource: &ZwlrOutputConfigurationV1,
+    entity: Entity,
+    test_only: bool,
+) {
+    let world = state.world_mut();
+    let Some(mut configuration) = world.get_mut::<ZwlrOutputConfiguration>(entity) else {
+        return;
+    };
+    if configuration.used {
+        resource.post_error(
+            zwlr_output_configuration_v1::Error::AlreadyUsed,
+            "the configuration has already been applied or tested",
+        );
+        return;
+    }
+    configuration.used = true;
+    let serial = configuration.serial;
+    let mut states = configuration.disabled.clone();
+
+    let current_serial = world
+        .get::<ZwlrOutputManager>(DWay::client_entity(client))
+        .map(|manager| manager.serial);
+    if current_serial != Some(serial) {
+        debug!(
+            serial,
+            ?current_serial,
+            "the output configuration is outdated"
+        );
+        resource.cancelled();
+        return;
+    }
+
+    states.extend(
+        world
+            .get::<Children>(entity)
+            .into_iter()
+            .flat_map(|children| children.iter())
+            .filter_map(|child| world.get::<ZwlrOutputConfigurationHead>(child))
+            .map(|head| (head.output, head.state.clone())),
+    );
+    state.send_event(OutputConfigRequest {
+        states,
+        test_only,
+        sender: entity,
+    });
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &ZwlrOutputConfigurationHeadV1,
+        request: <ZwlrOutputConfigurationHeadV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        let Some(mut head) = state
+            .world_mut()
+            .get_mut::<ZwlrOutputConfigurationHead>(*data)
+        else {
+            return;
+        };
+        match request {
+            zwlr_output_configuration_head_v1::Request::SetMode { mode } => {
+                match head.modes.iter().find(|(raw, _)| raw == &mode) {
+                    Some((_, mode)) => head.state.mode = *mode,
+                    None => resource.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidMode,
+                        "the mode doesn't belong to the head",
+                    ),
+                }
+            }
+            zwlr_output_configuration_head_v1::Request::SetCustomMode {
+                width,
+                height,
+                refresh,
+            } => {
+                if width <= 0 || height <= 0 || refresh < 0 {
+                    resource.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidCustomMode,
+                        "invalid custom mode",
+                    );
+                    return;
+                }
+                head.state.mode = OutputMode::new(width, height, refresh as u32);
+            }
+            zwlr_output_configuration_head_v1::Request::SetPosition { x, y } => {
+                head.state.position = IVec2::new(x, y);
+            }
+            zwlr_output_configuration_head_v1::Request::SetTransform { transform } => {
+                match transform
+                    .into_result()
+                    .ok()
+                    .and_then(|t| OutputTransform::from_wl(t as u32))
+                {
+                    Some(transform) => head.state.transform = transform,
+                    None => resource.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidTransform,
+                        "invalid transform",
+                    ),
+                }
+            }
+            zwlr_output_configuration_head_v1::Request::SetScale { scale } => {
+                if !(scale > 0.0 && scale.is_finite()) {
+                    resource.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidScale,
+                        "invalid scale",
+                    );
+                    return;
+                }
+                head.state.scale = scale as f32;
+            }
+            zwlr_output_configuration_head_v1::Request::SetAdaptiveSync { state } => {
+                match state.into_result() {
+                    Ok(zwlr_output_head_v1::AdaptiveSyncState::Disabled) => {
+                        head.state.vrr = VrrPolicy::Never;
+                    }
+                    // the protocol has no fullscreen only state, keep it if it is configured
+                    Ok(zwlr_output_head_v1::AdaptiveSyncState::Enabled) => {
+                        if head.state.vrr == VrrPolicy::Never {
+                            head.state.vrr = VrrPolicy::Always;
+                        }
+                    }
+                    _ => resource.post_error(
+                        zwlr_output_configuration_head_v1::Error::InvalidAdaptiveSyncState,
+                        "invalid adaptive sync state",
+                    ),
+                }
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &ZwlrOutputConfigurationHeadV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
+pub fn send_configuration_result(
+    mut events: MessageReader<OutputConfigResult>,
+    configuration_query: Query<&ZwlrOutputConfiguration>,
+) {
+    for OutputConfigResult { sender, result } in events.read() {
+        let Ok(configuration) = configuration_query.get(*sender) else {
+            continue;
+        };
+        match result {
+            Ok(()) => configuration.raw.succeeded(),
+            Err(e) => {
+                debug!(entity = ?sender, "failed to apply output configuration: {e}");
+                configuration.raw.failed();
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use dway_util::output::{OutputHead, OutputMode, OutputState, VrrPolicy};
+use wayland_protocols_wlr::output_management::v1::server::{
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::ZwlrOutputManagerV1,
+    zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+};
+
+use super::manager::ZwlrOutputManager;
+use crate::prelude::*;
+
+/// A head sent to a client, the user data of the head and its modes is the entity of the output.
+#[derive(Debug)]
+pub struct ZwlrOutputHead {
+    pub raw: ZwlrOutputHeadV1,
+    pub modes: Vec<(ZwlrOutputModeV1, OutputMode)>,
+    pub state: OutputState,
+}
+
+impl ZwlrOutputHead {
+    pub fn create(
+        dh: &DisplayHandle,
+        manager: &ZwlrOutputManagerV1,
+        entity: Entity,
+        output: &OutputHead,
+    ) -> anyhow::Result<Self> {
+        let client = manager
+            .client()
+            .ok_or_else(|| anyhow!("the client of the output manager is gone"))?;
+        let version = manager.version();
+        let raw = client.create_resource::<ZwlrOutputHeadV1, Entity, DWay>(dh, version, entity)?;
+        manager.head(&raw);
+        raw.name(output.name().to_string());
+        raw.description(output.identity.description());
+        if output.physical_size != IVec2::ZERO {
+            raw.physical_size(output.physical_size.x, output.physical_size.y);
+        }
+
+        // the mode interface stays at version 3
+        let mode_version = version.min(3);
+        let mut modes = Vec::with_capacity(output.modes.len());
+        for (index, mode) in output.modes.iter().enumerate() {
+            let mode_raw = client
+                .create_resource::<ZwlrOutputModeV1, Entity, DWay>(dh, mode_version, entity)?;
+            raw.mode(&mode_raw);
+            mode_raw.size(mode.width, mode.height);
+            if mode.refresh > 0 {
+                mode_raw.refresh(mode.refresh as i32);
+            }
+            if output.preferred_mode == Some(index) {
+                mode_raw.preferred();
+            }
+            modes.push((mode_raw, *mode));
+        }
+
+        if version >= 2 && output.identity.has_edid() {
+            raw.make(output.identity.vendor.clone());
+            raw.model(output.identity.model.clone());
+            if !output.identity.serial.is_empty() {
+                raw.serial_number(output.identity.serial.clone());
+            }
+        }
+
+        let mut head = Self {
+            raw,
+            modes,
+            state: output.state.clone(),
+        };
+        head.send_state(&output.state, true);
+        Ok(head)
+    }
+
+    pub fn modes_match(&self, modes: &[OutputMode]) -> bool {
+        self.modes.len() == modes.len() && self.modes.iter().zip(modes).all(|((_, a), b)| a == b)
+    }
+
+    pub fn find_mode(&self, mode: &ZwlrOutputModeV1) -> Option<OutputMode> {
+        self.modes
+            .iter()
+            .find(|(raw, _)| raw == mode)
+            .map(|(_, mode)| *mode)
+    }
+
+    /// Send the changed properties, returns whether any event was sent.
+    pub fn send_state(&mut self, state: &OutputState, force: bool) -> bool {
+        let old = std::mem::replace(&mut self.state, state.clone());
+        let force = force || !old.enabled;
+        let mut changed = false;
+        if force || old.enabled != state.enabled {
+            self.raw.enabled(state.enabled as i32);
+            changed = true;
+        }
+        if !state.enabled {
+            return changed;
+        }
+        if force || old.mode != state.mode {
+            if let Some((mode, _)) = self.modes.iter().find(|(_, m)| m == &state.mode) {
+                self.raw.current_mode(mode);
+                changed = true;
+            }
+        }
+        if force || old.position != state.position {
+            self.raw.position(state.position.x, state.position.y);
+            changed = true;
+        }
+        if force || old.transform != state.transform {
+            if let Ok(transform) = wl_output::Transform::try_from(state.transform.to_wl()) {
+                self.raw.transform(transform);
+                changed = true;
+            }
+        }
+        if force || old.scale != state.scale {
+            self.raw.scale(state.scale as f64);
+            changed = true;
+        }
+        if self.raw.version() >= 4 && (force || old.vrr != state.vrr) {
+            self.raw.adaptive_sync(if state.vrr == VrrPolicy::Never {
+                zwlr_output_head_v1::AdaptiveSyncState::Disabled
+            } else {
+                zwlr_output_head_v1::AdaptiveSyncState::Enabled
+            });
+            changed = true;
+        }
+        changed
+    }
+
+    pub fn finish(&self) {
+        for (mode, _) in &self.modes {
+            mode.finished();
+        }
+        self.raw.finished();
+    }
+}
+
+impl Dispatch<ZwlrOutputHeadV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        client: &wayland_server::Client,
+        resource: &ZwlrOutputHeadV1,
+        request: <ZwlrOutputHeadV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            zwlr_output_head_v1::Request::Release => {
+                let manager_entity = DWay::client_entity(client);
+                if let Some(mut manager) = state
+                    .world_mut()
+                    .get_mut::<ZwlrOutputManager>(manager_entity)
+                {
+                    manager.heads.retain(|_, head| &head.raw != resource);
+                }
+            }
+            _ => todo!(),
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputModeV1, Entity> for DWay {
+    fn request(
+        _state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &ZwlrOutputModeV1,
+        request: <ZwlrOutputModeV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            zwlr_output_mode_v1::Request::Release => {}
+            _ => todo!(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::platform::collections::HashMap;
+use dway_util::output::OutputHead;
+use wayland_protocols_wlr::output_management::v1::server::zwlr_output_manager_v1::*;
+
+use super::{configuration::ZwlrOutputConfiguration, head::ZwlrOutputHead};
+use crate::prelude::*;
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct ZwlrOutputManager {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: ZwlrOutputManagerV1,
+    #[reflect(ignore, default = "unimplemented")]
+    pub dhandle: DisplayHandle,
+    /// The heads sent to the client, keyed by the entity of the output
+    #[reflect(ignore)]
+    pub heads: HashMap<Entity, ZwlrOutputHead>,
+    /// The serial of the last `done` event
+    pub serial: u32,
+}
+impl ZwlrOutputManager {
+    pub fn new(raw: ZwlrOutputManagerV1, dhandle: DisplayHandle) -> Self {
+        Self {
+            raw,
+            dhandle,
+            heads: Default::default(),
+            serial: 0,
+        }
+    }
+}
+impl Drop for ZwlrOutputManager {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<ZwlrOutputManagerV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &ZwlrOutputManagerV1,
+        request: <ZwlrOutputManagerV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::CreateConfiguration { id, serial } => {
+                state.spawn_child_object(*data, id, data_init, |o| {
+                    ZwlrOutputConfiguration::new(o, serial)
+                });
+            }
+            Request::Stop => {
+                resource.finished();
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &ZwlrOutputManagerV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object_component::<ZwlrOutputManager>(*data, resource);
+    }
+}
+
+impl GlobalDispatch<ZwlrOutputManagerV1, Entity> for DWay {
+    fn bind(
+        state: &mut DWay,
+        handle: &DisplayHandle,
+        client: &wayland_server::Client,
+        resource: wayland_server::New<ZwlrOutputManagerV1>,
+        _global_data: &bevy::prelude::Entity,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        state.bind(client, resource, data_init, |o| {
+            ZwlrOutputManager::new(o, handle.clone())
+        });
+    }
+}
+
+/// Send the heads to the new managers and the changes of the outputs to all managers.
+pub fn update_output_heads(
+    mut manager_query: Query<&mut ZwlrOutputManager>,
+    head_query: Query<(Entity, Ref<OutputHead>)>,
+    mut removed: RemovedComponents<OutputHead>,
+) {
+    let removed = removed.read().collect::<Vec<_>>();
+    let changed = !removed.is_empty() || head_query.iter().any(|(_, head)| head.is_changed());
+    for mut manager in &mut manager_query {
+        if !changed && !manager.is_added() {
+            continue;
+        }
+        let manager = &mut *manager;
+        let mut dirty = manager.serial == 0;
+        for entity in &removed {
+            if let Some(head) = manager.heads.remove(entity) {
+                head.finish();
+                dirty = true;
+            }
+        }
+        for (entity, output) in &head_query {
+            if let Some(head) = manager.heads.get_mut(&entity) {
+                if head.modes_match(&output.modes) {
+                    dirty |= head.send_state(&output.state, false);
+                    continue;
+                }
+                head.finish();
+            }
+            match ZwlrOutputHead::create(&manager.dhandle, &manager.raw, entity, &output) {
+                Ok(head) => {
+                    manager.heads.insert(entity, head);
+                }
+                Err(e) => {
+                    error!(?entity, "failed to create zwlr_output_head_v1: {e}");
+                    manager.heads.remove(&entity);
+                }
+            }
+            dirty = true;
+        }
+        if dirty {
+            manager.serial = manager.serial.wrapping_add(1).max(1);
+            manager.raw.done(manager.serial);
+        }
+    }
+}
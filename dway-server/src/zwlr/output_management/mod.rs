@@ -0,0 +1,29 @@
+pub mod configuration;
+pub mod head;
+pub mod manager;
+
+use dway_util::output::{OutputConfigRequest, OutputConfigResult};
+use wayland_protocols_wlr::output_management::v1::server::zwlr_output_manager_v1::ZwlrOutputManagerV1;
+
+use crate::{prelude::*, state::add_global_dispatch};
+
+pub struct OutputManagementPlugin;
+
+impl Plugin for OutputManagementPlugin {
+    fn build(&self, app: &mut App) {
+        add_global_dispatch::<ZwlrOutputManagerV1, 4>(app);
+        app.add_event::<OutputConfigRequest>();
+        app.add_event::<OutputConfigResult>();
+        app.register_type::<manager::ZwlrOutputManager>();
+        app.register_type::<configuration::ZwlrOutputConfiguration>();
+        app.register_type::<configuration::ZwlrOutputConfigurationHead>();
+        app.add_systems(
+            PreUpdate,
+            (
+                manager::update_output_heads,
+                configuration::send_configuration_result,
+            )
+                .in_set(DWayServerSet::UpdateJoin),
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use dway_util::output::OutputPower;
+use wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_manager_v1::*;
+
+use crate::{prelude::*, wl::output::WlOutput, zwlr::output_power::power::ZwlrOutputPower};
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct ZwlrOutputPowerManager {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: ZwlrOutputPowerManagerV1,
+}
+impl ZwlrOutputPowerManager {
+    pub fn new(raw: ZwlrOutputPowerManagerV1) -> Self {
+        Self { raw }
+    }
+}
+impl Drop for ZwlrOutputPowerManager {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<ZwlrOutputPowerManagerV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &ZwlrOutputPowerManagerV1,
+        request: <ZwlrOutputPowerManagerV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::GetOutputPower { id, output } => {
+                let output_entity = state
+                    .with_component(&output, |o: &WlOutput| o.output)
+                    .filter(|output| !ZwlrOutputPower::is_owned(state, *output));
+                let power = output_entity
+                    .and_then(|output| state.world().get::<OutputPower>(output))
+                    .copied();
+                state.spawn_child_object(*data, id, data_init, |o| match (output_entity, power) {
+                    (Some(output), Some(power)) => {
+                        o.mode(ZwlrOutputPower::to_wl(power));
+                        ZwlrOutputPower::new(o, Some(output))
+                    }
+                    _ => {
+                        o.failed();
+                        ZwlrOutputPower::new(o, None)
+                    }
+                });
+            }
+            Request::Destroy => {
+                state.despawn_object_component::<ZwlrOutputPowerManager>(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &ZwlrOutputPowerManagerV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object_component::<ZwlrOutputPowerManager>(*data, resource);
+    }
+}
+
+impl GlobalDispatch<ZwlrOutputPowerManagerV1, Entity> for DWay {
+    fn bind(
+        state: &mut DWay,
+        _handle: &DisplayHandle,
+        client: &wayland_server::Client,
+        resource: wayland_server::New<ZwlrOutputPowerManagerV1>,
+        _global_data: &bevy::prelude::Entity,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        state.bind(client, resource, data_init, ZwlrOutputPowerManager::new);
+    }
+}
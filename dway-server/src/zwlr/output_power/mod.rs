@@ -0,0 +1,20 @@
+pub mod manager;
+pub mod power;
+
+use wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1;
+
+use crate::{prelude::*, state::add_global_dispatch};
+
+pub struct OutputPowerPlugin;
+
+impl Plugin for OutputPowerPlugin {
+    fn build(&self, app: &mut App) {
+        add_global_dispatch::<ZwlrOutputPowerManagerV1, 1>(app);
+        app.register_type::<manager::ZwlrOutputPowerManager>();
+        app.register_type::<power::ZwlrOutputPower>();
+        app.add_systems(
+            PreUpdate,
+            power::update_output_power.in_set(DWayServerSet::UpdateJoin),
+        );
+    }
+}
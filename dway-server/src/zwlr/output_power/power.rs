@@ -0,0 +1,112 @@
+use dway_util::output::OutputPower;
+use wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_v1::*;
+
+use crate::prelude::*;
+
+/// The exclusive power mode control of an output, a control which failed has no output.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Debug)]
+pub struct ZwlrOutputPower {
+    #[reflect(ignore, default = "unimplemented")]
+    pub raw: ZwlrOutputPowerV1,
+    pub output: Option<Entity>,
+}
+impl ZwlrOutputPower {
+    pub fn new(raw: ZwlrOutputPowerV1, output: Option<Entity>) -> Self {
+        Self { raw, output }
+    }
+
+    pub fn is_owned(state: &mut DWay, output: Entity) -> bool {
+        let world = state.world_mut();
+        world
+            .query::<&ZwlrOutputPower>()
+            .iter(world)
+            .any(|control| control.output == Some(output))
+    }
+
+    pub fn to_wl(power: OutputPower) -> Mode {
+        match power {
+            OutputPower::On => Mode::On,
+            OutputPower::Off => Mode::Off,
+        }
+    }
+}
+impl Drop for ZwlrOutputPower {
+    fn drop(&mut self) {
+        trace!(entity = ?DWay::get_entity(&self.raw),resource = ?self.raw.id(),"drop wayland resource");
+    }
+}
+impl Dispatch<ZwlrOutputPowerV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &ZwlrOutputPowerV1,
+        request: <ZwlrOutputPowerV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span =
+            span!(Level::ERROR,"request",entity = ?data,resource = %WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            Request::SetMode { mode } => {
+                let power = match mode.into_result() {
+                    Ok(Mode::On) => OutputPower::On,
+                    Ok(Mode::Off) => OutputPower::Off,
+                    _ => {
+                        resource.post_error(Error::InvalidMode, "invalid power mode");
+                        return;
+                    }
+                };
+                let Some(output) = state
+                    .world()
+                    .get::<ZwlrOutputPower>(*data)
+                    .and_then(|control| control.output)
+                else {
+                    return;
+                };
+                if let Some(mut output_power) = state.world_mut().get_mut::<OutputPower>(output) {
+                    output_power.set_if_neq(power);
+                }
+            }
+            Request::Destroy => {
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut DWay,
+        _client: wayland_backend::server::ClientId,
+        resource: &ZwlrOutputPowerV1,
+        data: &bevy::prelude::Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
+/// Report the power mode changes of the outputs, and fail the controls of removed outputs.
+pub fn update_output_power(
+    mut control_query: Query<&mut ZwlrOutputPower>,
+    output_query: Query<Ref<OutputPower>>,
+) {
+    for mut control in &mut control_query {
+        let Some(output) = control.output else {
+            continue;
+        };
+        match output_query.get(output) {
+            Ok(power) if power.is_changed() => {
+                control.raw.mode(ZwlrOutputPower::to_wl(*power));
+            }
+            Ok(_) => {}
+            Err(_) => {
+                debug!(?output, "the power control of the output failed");
+                control.output = None;
+                control.raw.failed();
+            }
+        }
+    }
+}
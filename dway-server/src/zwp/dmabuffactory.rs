@@ -1,10 +1,11 @@
 use drm_fourcc::DrmFourcc;
+use dway_util::scanout::ScanoutFormats;
 
 use crate::{
     prelude::*,
     state::add_global_dispatch,
     zwp::{
-        dmabuffeedback::{init_feedback, DmabufFeedback},
+        dmabuffeedback::{init_feedback, update_surface_feedbacks, DmabufFeedback},
         dmabufparam::DmaBufferParams,
     },
 };
@@ -47,7 +48,7 @@ impl Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, Entity> for DWay {
             zwp_linux_dmabuf_v1::Request::GetDefaultFeedback { id } => {
                 let entity = state
                     .spawn((id, data_init, |o, world: &mut World| {
-                        init_feedback(world, &o);
+                        init_feedback(world, &o, false);
                         DmabufFeedback::new(o)
                     }))
                     .insert(ChildOf(*data))
@@ -59,7 +60,7 @@ impl Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, Entity> for DWay {
                     .insert(
                         DWay::get_entity(&surface),
                         (id, data_init, |o, world: &mut World| {
-                            init_feedback(world, &o);
+                            init_feedback(world, &o, true);
                             DmabufFeedback::new(o)
                         }),
                     )
@@ -111,6 +112,11 @@ impl Plugin for DWayDmaBufferFactoryPlugin {
         app.register_relation::<DmaBufferHasFeedback>();
         app.register_relation::<DmaBufferAttachSurface>();
         app.register_type::<ZwpDmaBufferFactory>();
+        app.init_resource::<ScanoutFormats>();
+        app.add_systems(
+            PreUpdate,
+            update_surface_feedbacks.in_set(DWayServerSet::UpdateJoin),
+        );
         add_global_dispatch::<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, 4>(app);
     }
 }
@@ -3,9 +3,12 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use dway_util::scanout::ScanoutFormats;
+
 use crate::{
     prelude::*,
     render::{drm::DmaBackend, DWayServerRenderClient},
+    wl::surface::WlSurface,
 };
 
 #[derive(Debug, Default)]
@@ -60,9 +63,29 @@ impl Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, Entity> fo
     }
 }
 
+fn send_tranche(
+    feedback: &zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1,
+    device: u64,
+    flags: zwp_linux_dmabuf_feedback_v1::TrancheFlags,
+    indices: &[usize],
+) {
+    feedback.tranche_target_device(device.to_ne_bytes().to_vec());
+    feedback.tranche_flags(flags);
+    feedback.tranche_formats(
+        indices
+            .iter()
+            .flat_map(|i| (*i as u16).to_ne_bytes())
+            .collect::<Vec<_>>(),
+    );
+    feedback.tranche_done();
+}
+
+/// Send the feedback, a scanout tranche goes first if `scanout` is given so that clients
+/// prefer the formats the display planes can show without composition.
 pub fn do_init_feedback(
     feedback: &zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1,
     dma_backend: &DmaBackend,
+    scanout: Option<&ScanoutFormats>,
 ) {
     feedback.main_device(
         dma_backend
@@ -77,21 +100,39 @@ pub fn do_init_feedback(
         dma_backend.format_table.1 as u32,
     );
 
+    if let Some(ScanoutFormats {
+        device: Some(device),
+        formats,
+    }) = scanout
+    {
+        let indices = dma_backend
+            .texture_formats
+            .iter()
+            .enumerate()
+            .filter(|(_, format)| formats.contains(format))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if !indices.is_empty() {
+            send_tranche(
+                feedback,
+                *device,
+                zwp_linux_dmabuf_feedback_v1::TrancheFlags::Scanout,
+                &indices,
+            );
+        }
+    }
+
     for tranche in dma_backend
         .preferred_tranches
         .iter()
         .chain(std::iter::once(&dma_backend.main_tranche))
     {
-        feedback.tranche_target_device(tranche.target_device.device.to_ne_bytes().to_vec());
-        feedback.tranche_flags(tranche.flags);
-        feedback.tranche_formats(
-            tranche
-                .indices
-                .iter()
-                .flat_map(|i| (*i as u16).to_ne_bytes())
-                .collect::<Vec<_>>(),
+        send_tranche(
+            feedback,
+            u64::from(tranche.target_device.device),
+            tranche.flags,
+            &tranche.indices,
         );
-        feedback.tranche_done();
     }
 
     feedback.done();
@@ -101,7 +142,11 @@ pub fn do_init_feedback(
 pub fn init_feedback(
     world: &mut World,
     feedback: &zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1,
+    surface: bool,
 ) {
+    let scanout = surface
+        .then(|| world.get_resource::<ScanoutFormats>())
+        .flatten();
     if let Some(drm_node) = &world
         .resource::<DWayServerRenderClient>()
         .drm_node
@@ -109,9 +154,29 @@ pub fn init_feedback(
         .ok()
     {
         if let Some(drm_node) = drm_node.as_ref() {
-            do_init_feedback(feedback, drm_node)
+            do_init_feedback(feedback, drm_node, scanout)
         }
     } else {
         warn!("failed to init dmabuf feedback");
     }
 }
+
+/// Resend the feedback of surfaces when the formats of the display planes change.
+pub fn update_surface_feedbacks(
+    scanout: Res<ScanoutFormats>,
+    feedback_query: Query<&DmabufFeedback, With<WlSurface>>,
+    render_client: Res<DWayServerRenderClient>,
+) {
+    if !scanout.is_changed() || scanout.is_added() {
+        return;
+    }
+    let Ok(guard) = render_client.drm_node.lock() else {
+        return;
+    };
+    let Some(dma_backend) = guard.as_ref() else {
+        return;
+    };
+    for feedback in &feedback_query {
+        do_init_feedback(&feedback.raw, dma_backend, Some(&scanout));
+    }
+}
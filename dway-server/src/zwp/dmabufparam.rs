@@ -1,6 +1,7 @@
-use std::{mem::take, os::fd::OwnedFd};
+use std::{mem::take, os::fd::OwnedFd, sync::Arc};
 
-use drm_fourcc::DrmModifier;
+use drm_fourcc::{DrmFormat, DrmFourcc, DrmModifier};
+use dway_util::scanout::{DmabufPlane, ScanoutBuffer};
 use wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_buffer_params_v1::Flags;
 
 use crate::{
@@ -41,6 +42,27 @@ impl DmaBufferParams {
         }
     }
 }
+
+/// Duplicate the planes of a buffer so that the backend can attach it to a display plane.
+pub fn scanout_buffer(size: IVec2, format: u32, planes: &[DmaBufferPlane]) -> Option<ScanoutBuffer> {
+    let code = DrmFourcc::try_from(format).ok()?;
+    let modifier = planes.first()?.modifier;
+    let planes = planes
+        .iter()
+        .map(|plane| {
+            Some(DmabufPlane {
+                fd: plane.fd.try_clone().ok()?,
+                offset: plane.offset,
+                stride: plane.stride,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(ScanoutBuffer {
+        size,
+        format: DrmFormat { code, modifier },
+        planes: Arc::new(planes),
+    })
+}
 impl Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, Entity> for DWay {
     fn request(
         state: &mut Self,
@@ -86,6 +108,7 @@ impl Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, Entity> for DW
                 let buffer_entity = state.spawn(ChildOf(DWay::client_entity(client))).id();
                 let mut planes = take(&mut state.get_mut::<DmaBufferParams>(*data).unwrap().planes);
                 planes.sort_by_key(|p| p.plane_idx);
+                let scanout = scanout_buffer(IVec2::new(width, height), format, &planes);
                 let render_client = state.resource::<DWayServerRenderClient>();
 
                 render_client
@@ -108,6 +131,9 @@ impl Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, Entity> for DW
                     format,
                     flags,
                 });
+                if let Some(scanout) = scanout {
+                    state.entity_mut(buffer_entity).insert(scanout);
+                }
             }
             zwp_linux_buffer_params_v1::Request::CreateImmed {
                 buffer_id,
@@ -119,6 +145,7 @@ impl Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, Entity> for DW
                 let buffer_entity = state.spawn(ChildOf(DWay::client_entity(client))).id();
                 let mut planes = take(&mut state.get_mut::<DmaBufferParams>(*data).unwrap().planes);
                 planes.sort_by_key(|p| p.plane_idx);
+                let scanout = scanout_buffer(IVec2::new(width, height), format, &planes);
                 let render_client = state.resource::<DWayServerRenderClient>();
 
                 let buffer = data_init.init(buffer_id, buffer_entity);
@@ -143,6 +170,9 @@ impl Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, Entity> for DW
                     format,
                     flags,
                 });
+                if let Some(scanout) = scanout {
+                    state.entity_mut(buffer_entity).insert(scanout);
+                }
             }
             _ => todo!(),
         }
@@ -21,16 +21,22 @@ impl wayland_server::Dispatch<zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManager
         request: <zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1 as WlResource>::Request,
         data: &Entity,
         _dhandle: &DisplayHandle,
-        _data_init: &mut wayland_server::DataInit<'_, Self>,
+        data_init: &mut wayland_server::DataInit<'_, Self>,
     ) {
         let span = span!(Level::ERROR, "request", entity=?data, resource=%WlResource::id(resource));
         let _enter = span.enter();
         debug!("request {:?}", &request);
         match request {
             zwp_idle_inhibit_manager_v1::Request::Destroy => {
-                state.destroy_object(resource);
+                state.despawn_object_component::<IdleInhibitManager>(*data, resource);
+            }
+            zwp_idle_inhibit_manager_v1::Request::CreateInhibitor { id, surface } => {
+                let surface_entity = DWay::get_entity(&surface);
+                state.spawn_child_object(surface_entity, id, data_init, |o| IdleInhibitor {
+                    raw: o,
+                    surface: surface_entity,
+                });
             }
-            zwp_idle_inhibit_manager_v1::Request::CreateInhibitor { id: _, surface: _ } => todo!(),
             _ => todo!(),
         }
     }
@@ -60,6 +66,44 @@ impl wayland_server::GlobalDispatch<zwp_idle_inhibit_manager_v1::ZwpIdleInhibitM
     }
 }
 
+/// Keeps the compositor from going idle while the surface exists, it is a child of the surface.
+#[derive(Component)]
+pub struct IdleInhibitor {
+    pub raw: zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+    pub surface: Entity,
+}
+
+impl wayland_server::Dispatch<zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1, Entity> for DWay {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        resource: &zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+        request: <zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1 as WlResource>::Request,
+        data: &Entity,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let span = span!(Level::ERROR, "request", entity=?data, resource=%WlResource::id(resource));
+        let _enter = span.enter();
+        debug!("request {:?}", &request);
+        match request {
+            zwp_idle_inhibitor_v1::Request::Destroy => {
+                state.despawn_object(*data, resource);
+            }
+            _ => todo!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_backend::server::ClientId,
+        resource: &zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+        data: &Entity,
+    ) {
+        state.despawn_object(*data, resource);
+    }
+}
+
 pub struct IdlePlugin;
 impl Plugin for IdlePlugin {
     fn build(&self, app: &mut App) {
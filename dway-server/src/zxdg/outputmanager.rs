@@ -1,36 +1,19 @@
 use crate::{
-    geometry::{Geometry, GlobalGeometry},
     prelude::*,
-    util::rect::IRect,
-    wl::output::SurfaceList,
+    state::add_global_dispatch,
+    wl::output::{OutputInfo, WlOutput},
 };
+use dway_util::output::OutputHead;
 
 #[derive(Component)]
 pub struct WlOutputManager {
     pub raw: zxdg_output_manager_v1::ZxdgOutputManagerV1,
 }
+/// Inserted on the entity of the `wl_output` it belongs to.
 #[derive(Component)]
 pub struct ZxdgOutput {
     pub raw: zxdg_output_v1::ZxdgOutputV1,
 }
-#[derive(Bundle)]
-pub struct ZxdgOutputBundle {
-    resource: ZxdgOutput,
-    surfaces: SurfaceList,
-    pub geo: Geometry,
-    pub global: GlobalGeometry,
-}
-
-impl ZxdgOutputBundle {
-    pub fn new(resource: ZxdgOutput) -> Self {
-        Self {
-            resource,
-            surfaces: Default::default(),
-            geo: Geometry::new(IRect::new(0, 0, 1920, 1080)),
-            global: Default::default(),
-        }
-    }
-}
 
 #[derive(Resource)]
 pub struct XdgOutputManagerDelegate(pub GlobalId);
@@ -45,23 +28,31 @@ impl
     fn request(
         state: &mut DWay,
         _client: &wayland_server::Client,
-        _resource: &zxdg_output_manager_v1::ZxdgOutputManagerV1,
+        resource: &zxdg_output_manager_v1::ZxdgOutputManagerV1,
         request: <zxdg_output_manager_v1::ZxdgOutputManagerV1 as wayland_server::Resource>::Request,
-        _data: &bevy::prelude::Entity,
+        data: &bevy::prelude::Entity,
         _dhandle: &wayland_server::DisplayHandle,
         data_init: &mut wayland_server::DataInit<'_, DWay>,
     ) {
         match request {
-            zxdg_output_manager_v1::Request::Destroy => todo!(),
+            zxdg_output_manager_v1::Request::Destroy => {
+                state.despawn_object_component::<WlOutputManager>(*data, resource);
+            }
             zxdg_output_manager_v1::Request::GetXdgOutput { id, output } => {
+                let output_entity = state.object_component::<WlOutput>(&output).output;
+                let world = state.world();
+                let info = world
+                    .get::<Window>(output_entity)
+                    .map(|window| OutputInfo::new(window, world.get::<OutputHead>(output_entity)))
+                    .unwrap_or_default();
                 state.insert(
                     DWay::get_entity(&output),
                     (id, data_init, |o: zxdg_output_v1::ZxdgOutputV1| {
-                        o.logical_position(0, 0);
-                        o.logical_size(1920, 1080);
-                        o.name("dway".to_string());
-                        o.done();
-                        ZxdgOutputBundle::new(ZxdgOutput { raw: o })
+                        info.send_xdg(&o, true);
+                        if o.version() >= 3 {
+                            output.done();
+                        }
+                        ZxdgOutput { raw: o }
                     }),
                 );
             }
@@ -71,17 +62,17 @@ impl
 }
 impl wayland_server::Dispatch<zxdg_output_v1::ZxdgOutputV1, Entity> for DWay {
     fn request(
-        _state: &mut Self,
+        state: &mut Self,
         _client: &wayland_server::Client,
-        _resource: &zxdg_output_v1::ZxdgOutputV1,
+        resource: &zxdg_output_v1::ZxdgOutputV1,
         request: <zxdg_output_v1::ZxdgOutputV1 as wayland_server::Resource>::Request,
-        _data: &Entity,
+        data: &Entity,
         _dhandle: &DisplayHandle,
         _data_init: &mut wayland_server::DataInit<'_, Self>,
     ) {
         match request {
-            <zxdg_output_v1::ZxdgOutputV1 as wayland_server::Resource>::Request::Destroy => {
-                todo!()
+            zxdg_output_v1::Request::Destroy => {
+                state.despawn_object_component::<ZxdgOutput>(*data, resource);
             }
             _ => {
                 todo!()
@@ -112,12 +103,7 @@ impl GlobalDispatch<zxdg_output_manager_v1::ZxdgOutputManagerV1, Entity> for DWa
 
 pub struct XdgOutputManagerPlugin;
 impl Plugin for XdgOutputManagerPlugin {
-    fn build(&self, _app: &mut App) {
-        // app.add_system(create_global_system_config::<
-        //     zxdg_output_manager_v1::ZxdgOutputManagerV1,
-        //     3,
-        // >());
-        // TODO 修复xwayland中的xdg_output大小问题
-        //
+    fn build(&self, app: &mut App) {
+        add_global_dispatch::<zxdg_output_manager_v1::ZxdgOutputManagerV1, 3>(app);
     }
 }
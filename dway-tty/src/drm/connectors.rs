@@ -1,8 +1,14 @@
 use anyhow::{bail, Result};
 use bevy::prelude::*;
-use drm::control::{connector, ModeTypeFlags};
+use drm::control::{connector, property, Mode, ModeFlags, ModeTypeFlags};
+use dway_util::{
+    edid::Edid,
+    output::{OutputHead, OutputIdentity, OutputMode, OutputState},
+};
 use getset::Getters;
 
+use super::DrmDeviceFd;
+
 #[derive(Component, Clone, Debug, Getters)]
 #[get = "pub"]
 pub struct Connector {
@@ -10,11 +16,17 @@ pub struct Connector {
     pub(crate) name: String,
     pub(crate) size: IVec2,
     pub(crate) mode: drm::control::Mode,
+    pub(crate) identity: OutputIdentity,
+    pub(crate) edid: Option<Edid>,
+    /// Whether the driver can drive the monitor with a variable refresh rate
+    pub(crate) vrr_capable: bool,
+    /// Whether the connector can send HDR metadata to a monitor which accepts PQ
+    pub(crate) hdr_capable: bool,
 }
 
 impl Connector {
     #[tracing::instrument(skip_all)]
-    pub fn new(info: connector::Info) -> Result<Self> {
+    pub fn new(drm: &DrmDeviceFd, info: connector::Info) -> Result<Self> {
         let modes = info.modes();
         if modes.is_empty() {
             bail!("no display mode");
@@ -26,17 +38,110 @@ impl Connector {
             .cloned()
             .unwrap_or_else(|| modes[0]);
 
-        let name = format!("{}-{}", info.interface().as_str(), info.interface_id());
+        let name = connector_name(&info);
         let size = info
             .size()
             .map(|(w, h)| IVec2::new(w as i32, h as i32))
             .unwrap_or_default();
 
+        let edid = drm
+            .try_with_prop(info.handle(), "EDID", |prop, value| {
+                Ok(match prop.value_type().convert_value(value) {
+                    property::Value::Blob(blob) if blob != 0 => Some(drm.get_property_blob(blob)?),
+                    _ => None,
+                })
+            })
+            .map_err(|e| warn!(connector = %name, "failed to read edid: {e}"))
+            .ok()
+            .flatten()
+            .flatten();
+        let edid = edid.and_then(|data| {
+            Edid::parse(&data)
+                .map_err(|e| warn!(connector = %name, "failed to parse edid: {e}"))
+                .ok()
+        });
+        // some projectors and tvs only report the size in the edid
+        let size = match &edid {
+            Some(edid) if size == IVec2::ZERO => edid.physical_size,
+            _ => size,
+        };
+        let identity = edid
+            .as_ref()
+            .map(|edid| edid.identity(&name))
+            .unwrap_or_else(|| OutputIdentity {
+                connector: name.clone(),
+                ..Default::default()
+            });
+
+        let vrr_capable = drm
+            .try_with_prop(info.handle(), "vrr_capable", |_, value| Ok(value != 0))
+            .map_err(|e| warn!(connector = %name, "failed to read vrr_capable: {e}"))
+            .ok()
+            .flatten()
+            .unwrap_or(false);
+        // the screens are composited in 8 bit sRGB and scanned out with 8 bits, encoding them with
+        // PQ would clip and band. HDR is not offered until they are composited in a float format
+        // and scanned out with 10 bits.
+        let hdr_capable = false;
+
         Ok(Self {
             info,
             name,
             size,
             mode,
+            identity,
+            edid,
+            vrr_capable,
+            hdr_capable,
         })
     }
+
+    /// The modes of the connector in the order reported by the driver.
+    pub fn output_modes(&self) -> Vec<OutputMode> {
+        self.info.modes().iter().map(output_mode).collect()
+    }
+
+    pub fn find_mode(&self, mode: &OutputMode) -> Option<Mode> {
+        self.info
+            .modes()
+            .iter()
+            .find(|m| &output_mode(m) == mode)
+            .cloned()
+    }
+
+    pub fn head(&self, state: OutputState) -> OutputHead {
+        OutputHead {
+            identity: self.identity.clone(),
+            physical_size: self.size,
+            modes: self.output_modes(),
+            preferred_mode: self.info.modes().iter().position(|m| m == &self.mode),
+            vrr_capable: self.vrr_capable,
+            vrr_range: self.edid.as_ref().and_then(|edid| edid.vrr),
+            hdr_capable: self.hdr_capable,
+            state,
+        }
+    }
+}
+
+pub fn connector_name(info: &connector::Info) -> String {
+    format!("{}-{}", info.interface().as_str(), info.interface_id())
+}
+
+/// Convert a drm mode, the refresh rate is computed from the pixel clock to keep the precision.
+pub fn output_mode(mode: &Mode) -> OutputMode {
+    let (width, height) = mode.size();
+    let (_, _, htotal) = mode.hsync();
+    let (_, _, vtotal) = mode.vsync();
+    let mut refresh = if htotal != 0 && vtotal != 0 {
+        (mode.clock() as u64 * 1_000_000 / (htotal as u64 * vtotal as u64)) as u32
+    } else {
+        mode.vrefresh() * 1000
+    };
+    if mode.flags().contains(ModeFlags::INTERLACE) {
+        refresh *= 2;
+    }
+    if mode.flags().contains(ModeFlags::DBLSCAN) {
+        refresh /= 2;
+    }
+    OutputMode::new(width as i32, height as i32, refresh)
 }
@@ -0,0 +1,248 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Result};
+use bevy::{ecs::relationship::Relationship as _, prelude::*};
+use drm::{
+    control::{framebuffer, Device as _},
+    Device as _, DriverCapability,
+};
+use drm_fourcc::DrmFourcc;
+use dway_util::{
+    color::{ImageDescription, OutputImageDescription},
+    cursor::{CursorImage, HardwareCursor, HardwareCursorState},
+    output::OutputTransform,
+};
+use gbm::{BufferObject, BufferObjectFlags};
+
+use super::{planes::PlaneConfig, surface::DrmSurface, DrmDevice};
+use crate::{gbm::GbmDevice, libinput::PointerState, window::relative_to_window};
+
+struct CursorBuffer {
+    buffer: BufferObject<()>,
+    framebuffer: framebuffer::Handle,
+}
+
+struct CursorPlaneInner {
+    buffers: Vec<CursorBuffer>,
+    /// The index of the buffer on the plane
+    current: usize,
+    /// The image in the current buffer and the size it was scaled to
+    image: Option<(Arc<CursorImage>, IVec2)>,
+    /// The position of the image on the output in pixels
+    position: Option<IVec2>,
+}
+
+/// The cursor plane of an output with the buffers the cursor images are uploaded to.
+#[derive(Component)]
+pub struct CursorPlane {
+    /// The size of the buffers, the largest image the plane takes
+    pub size: IVec2,
+    drm: DrmDevice,
+    inner: Mutex<CursorPlaneInner>,
+}
+
+impl Drop for CursorPlane {
+    fn drop(&mut self) {
+        let inner = self.inner.get_mut().unwrap();
+        for buffer in &inner.buffers {
+            let _ = self.drm.destroy_framebuffer(buffer.framebuffer);
+        }
+    }
+}
+
+impl CursorPlane {
+    pub fn new(drm: &DrmDevice, gbm: &GbmDevice, surface: &DrmSurface) -> Result<Self> {
+        {
+            let surface_guard = surface.inner.lock().unwrap();
+            let Some(plane) = &surface_guard.planes.cursor else {
+                bail!("no cursor plane");
+            };
+            if !plane.formats.iter().any(|f| f.code == DrmFourcc::Argb8888) {
+                bail!("the cursor plane does not support argb8888");
+            }
+        }
+        let width = drm
+            .get_driver_capability(DriverCapability::CursorWidth)
+            .unwrap_or(64);
+        let height = drm
+            .get_driver_capability(DriverCapability::CursorHeight)
+            .unwrap_or(64);
+
+        let gbm_guard = gbm.device.lock().unwrap();
+        let buffers = (0..2)
+            .map(|_| {
+                let buffer = gbm_guard.create_buffer_object::<()>(
+                    width as u32,
+                    height as u32,
+                    DrmFourcc::Argb8888,
+                    BufferObjectFlags::CURSOR | BufferObjectFlags::WRITE,
+                )?;
+                let framebuffer = drm.create_framebuffer(&buffer)?;
+                Ok(CursorBuffer {
+                    buffer,
+                    framebuffer,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            size: IVec2::new(width as i32, height as i32),
+            drm: drm.clone(),
+            inner: Mutex::new(CursorPlaneInner {
+                buffers,
+                current: 0,
+                image: None,
+                position: None,
+            }),
+        })
+    }
+}
+
+fn upload(buffer: &mut BufferObject<()>, image: &CursorImage) -> Result<()> {
+    let stride = buffer.stride() as usize;
+    let size = IVec2::new(buffer.width() as i32, buffer.height() as i32);
+    if image.size.x > size.x || image.size.y > size.y {
+        bail!("cursor image {} is larger than the plane", image.size);
+    }
+    let mut data = vec![0u8; stride * size.y as usize];
+    let row = image.size.x as usize * 4;
+    for y in 0..image.size.y as usize {
+        data[y * stride..y * stride + row].copy_from_slice(&image.data[y * row..(y + 1) * row]);
+    }
+    buffer
+        .write(&data)
+        .map_err(|e| anyhow!("failed to write cursor buffer: {e}"))?;
+    Ok(())
+}
+
+pub fn init_cursor_planes(
+    surface_query: Query<(Entity, &DrmSurface, &ChildOf), Changed<DrmSurface>>,
+    drm_query: Query<(&DrmDevice, &GbmDevice)>,
+    mut commands: Commands,
+) {
+    for (entity, surface, parent) in &surface_query {
+        let Ok((drm, gbm)) = drm_query.get(parent.get()) else {
+            continue;
+        };
+        match CursorPlane::new(drm, gbm, surface) {
+            Ok(cursor_plane) => {
+                commands.entity(entity).insert(cursor_plane);
+            }
+            Err(e) => {
+                debug!(?entity, "use software cursor: {e}");
+                commands.entity(entity).remove::<CursorPlane>();
+            }
+        }
+    }
+}
+
+fn update_cursor_plane(
+    cursor_plane: &CursorPlane,
+    surface: &DrmSurface,
+    drm: &DrmDevice,
+    image: Option<&Arc<CursorImage>>,
+    position: Option<Vec2>,
+    scale: f32,
+) -> Result<bool> {
+    let mut inner = cursor_plane.inner.lock().unwrap();
+    let (Some(image), Some(position)) = (image, position) else {
+        if inner.position.take().is_some() {
+            surface.commit_cursor(drm, None, false)?;
+        }
+        return Ok(false);
+    };
+
+    let size = (image.logical_size * scale).round().as_ivec2();
+    if size.x > cursor_plane.size.x || size.y > cursor_plane.size.y {
+        if inner.position.take().is_some() {
+            surface.commit_cursor(drm, None, false)?;
+        }
+        return Ok(false);
+    }
+    let image_changed = inner
+        .image
+        .as_ref()
+        .is_none_or(|(uploaded, uploaded_size)| {
+            !Arc::ptr_eq(uploaded, image) || *uploaded_size != size
+        });
+    let position = (position - image.hotspot * scale).round().as_ivec2();
+    if !image_changed && inner.position == Some(position) {
+        return Ok(true);
+    }
+
+    let mut index = inner.current;
+    if image_changed {
+        index = (inner.current + 1) % inner.buffers.len();
+        upload(&mut inner.buffers[index].buffer, &image.scaled(size))?;
+    }
+    let config = PlaneConfig {
+        src: Rect::from_corners(Vec2::ZERO, cursor_plane.size.as_vec2()),
+        dest: IRect::from_corners(position, position + cursor_plane.size).as_rect(),
+        transform: Default::default(),
+        framebuffer: inner.buffers[index].framebuffer,
+    };
+    surface.commit_cursor(drm, Some(config), image_changed)?;
+    inner.current = index;
+    inner.position = Some(position);
+    if image_changed {
+        inner.image = Some((image.clone(), size));
+    }
+    Ok(true)
+}
+
+/// Move the cursor plane right after the input is read, without waiting for the render.
+///
+/// The plane is not converted to the colors of the output, the compositor draws cursors whose
+/// image description differs.
+pub fn update_cursor_planes(
+    surface_query: Query<(
+        Entity,
+        &DrmSurface,
+        &CursorPlane,
+        &Window,
+        Option<&OutputImageDescription>,
+        &ChildOf,
+    )>,
+    drm_query: Query<&DrmDevice>,
+    pointer_state: Res<PointerState>,
+    hardware_cursor: Res<HardwareCursor>,
+    mut cursor_state: ResMut<HardwareCursorState>,
+    mut commands: Commands,
+) {
+    let mut active = false;
+    let srgb = ImageDescription::srgb();
+    for (entity, surface, cursor_plane, window, description, parent) in &surface_query {
+        let Ok(drm) = drm_query.get(parent.get()) else {
+            continue;
+        };
+        if drm.is_paused() {
+            continue;
+        }
+        let on_output = pointer_state.window == Some(entity)
+            && surface.inner.lock().unwrap().output_transform == OutputTransform::Normal;
+        let position = on_output
+            .then(|| relative_to_window(window, pointer_state.position))
+            .flatten()
+            .map(|position| position * window.scale_factor());
+        let image = hardware_cursor
+            .image
+            .as_ref()
+            .filter(|_| description.map_or(&srgb, |d| &d.0) == &hardware_cursor.image_description);
+        match update_cursor_plane(
+            cursor_plane,
+            surface,
+            drm,
+            image,
+            position,
+            window.scale_factor(),
+        ) {
+            Ok(shown) => active |= shown,
+            Err(e) => {
+                warn!(?entity, "fall back to software cursor: {e}");
+                let _ = surface.commit_cursor(drm, None, false);
+                commands.entity(entity).remove::<CursorPlane>();
+            }
+        }
+    }
+    cursor_state.set_if_neq(HardwareCursorState { active });
+}
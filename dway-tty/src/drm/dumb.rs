@@ -0,0 +1,85 @@
+use anyhow::Result;
+use bevy::prelude::*;
+use drm::{
+    buffer::Buffer,
+    control::{dumbbuffer::DumbBuffer, framebuffer, Device},
+};
+use drm_fourcc::DrmFourcc;
+use tracing::error;
+
+use super::{DrmDevice, DrmDeviceFd};
+
+/// A framebuffer in memory the cpu writes to, for outputs of a gpu which can't import the images
+/// of the render gpu.
+pub struct DumbFramebuffer {
+    drm: DrmDeviceFd,
+    buffer: DumbBuffer,
+    pub(crate) framebuffer: framebuffer::Handle,
+    pub(crate) size: IVec2,
+}
+
+impl DumbFramebuffer {
+    pub fn new(drm: &DrmDevice, size: IVec2) -> Result<Self> {
+        let buffer =
+            drm.create_dumb_buffer((size.x as u32, size.y as u32), DrmFourcc::Xrgb8888, 32)?;
+        let framebuffer = match drm.add_framebuffer(&buffer, 24, 32) {
+            Ok(framebuffer) => framebuffer,
+            Err(e) => {
+                let _ = drm.destroy_dumb_buffer(buffer);
+                return Err(e.into());
+            }
+        };
+        Ok(Self {
+            drm: drm.fd.clone(),
+            buffer,
+            framebuffer,
+            size,
+        })
+    }
+
+    /// Copy an image read back from the render gpu in RGBA8.
+    pub fn write_rgba(&mut self, data: &[u8]) -> Result<()> {
+        let pitch = self.buffer.pitch() as usize;
+        let size = self.size;
+        let mut mapping = self.drm.map_dumb_buffer(&mut self.buffer)?;
+        copy_rgba_to_xrgb8888(data, size, &mut mapping, pitch);
+        Ok(())
+    }
+}
+
+impl Drop for DumbFramebuffer {
+    fn drop(&mut self) {
+        if let Err(e) = self.drm.destroy_framebuffer(self.framebuffer) {
+            error!("failed to destroy drm framebuffer: {e}");
+        }
+        if let Err(e) = self.drm.destroy_dumb_buffer(self.buffer) {
+            error!("failed to destroy dumb buffer: {e}");
+        }
+    }
+}
+
+pub fn copy_rgba_to_xrgb8888(src: &[u8], size: IVec2, dst: &mut [u8], pitch: usize) {
+    let row = size.x as usize * 4;
+    for (src_row, dst_row) in src
+        .chunks_exact(row)
+        .zip(dst.chunks_mut(pitch))
+        .take(size.y as usize)
+    {
+        for (src, dst) in src_row.chunks_exact(4).zip(dst_row[..row].chunks_exact_mut(4)) {
+            dst.copy_from_slice(&[src[2], src[1], src[0], 0xff]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_copy_rgba_to_xrgb8888() {
+        let src = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut dst = [0u8; 16];
+        copy_rgba_to_xrgb8888(&src, IVec2::new(1, 2), &mut dst, 8);
+        assert_eq!(dst, [3, 2, 1, 255, 0, 0, 0, 0, 7, 6, 5, 255, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,79 @@
+//! Gamma lookup tables of the crtcs.
+
+use bevy::{prelude::*, window::RequestRedraw};
+use dway_util::{
+    gamma::{GammaLut, OutputGamma},
+    output::OutputHead,
+};
+
+use super::{surface::DrmSurface, DrmDevice};
+
+/// The data of a `GAMMA_LUT` blob, an array of `drm_color_lut` with the size of the crtc.
+pub fn color_lut_blob_data(lut: &GammaLut, size: usize) -> Vec<u8> {
+    let resampled;
+    let lut = if lut.len() == size {
+        lut
+    } else {
+        resampled = lut.resample(size);
+        &resampled
+    };
+    let mut data = Vec::with_capacity(size * 8);
+    for i in 0..size {
+        for value in [lut.red[i], lut.green[i], lut.blue[i], 0] {
+            data.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+    data
+}
+
+pub fn apply_output_gamma(
+    changed_query: Query<(&DrmSurface, Ref<OutputGamma>, &ChildOf, &OutputHead)>,
+    surface_query: Query<(&DrmSurface, &ChildOf, &OutputHead)>,
+    mut removed: RemovedComponents<OutputGamma>,
+    drm_query: Query<&DrmDevice>,
+    mut redraw: MessageWriter<RequestRedraw>,
+) {
+    let mut apply =
+        |surface: &DrmSurface, drm_entity: Entity, head: &OutputHead, lut: Option<&GammaLut>| {
+            let Ok(drm) = drm_query.get(drm_entity) else {
+                return;
+            };
+            if let Err(e) = surface.set_gamma(drm, lut) {
+                error!(output = head.name(), "failed to set gamma lut: {e}");
+                return;
+            }
+            redraw.write(RequestRedraw);
+        };
+    for (surface, gamma, child_of, head) in &changed_query {
+        if gamma.is_changed() {
+            apply(surface, child_of.parent(), head, Some(&gamma.0));
+        }
+    }
+    for entity in removed.read() {
+        if changed_query.contains(entity) {
+            continue;
+        }
+        if let Ok((surface, child_of, head)) = surface_query.get(entity) {
+            apply(surface, child_of.parent(), head, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_color_lut_blob_data() {
+        let lut = GammaLut::identity(2);
+        let data = color_lut_blob_data(&lut, 2);
+        assert_eq!(data.len(), 16);
+        let white = [u16::MAX, u16::MAX, u16::MAX, 0]
+            .map(u16::to_ne_bytes)
+            .concat();
+        assert_eq!(&data[8..16], &white[..]);
+        let data = color_lut_blob_data(&lut, 3);
+        assert_eq!(data.len(), 24);
+        assert_eq!(&data[8..10], &32768u16.to_ne_bytes());
+    }
+}
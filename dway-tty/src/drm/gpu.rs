@@ -0,0 +1,109 @@
+//! The gpus of the seat: the one the compositor renders with and the ones outputs are connected to.
+
+use std::{
+    borrow::Cow,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use bevy::{platform::collections::HashMap, prelude::*};
+use nix::libc;
+
+use super::{DrmDevice, DrmNode};
+use crate::seat::SeatState;
+
+/// The node of the gpu the renderer runs on, shared with the render world which reports it once
+/// the renderer is created.
+#[derive(Resource, Clone, Default, Debug)]
+pub struct RenderGpu(pub(crate) Arc<Mutex<Option<DrmNode>>>);
+
+impl RenderGpu {
+    pub fn get(&self) -> Option<DrmNode> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, node: DrmNode) {
+        *self.0.lock().unwrap() = Some(node);
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct GpuManager {
+    /// The drm device of the render gpu, outputs of other gpus show copies of its images
+    pub primary_gpu: Option<Entity>,
+    pub gpus: HashMap<libc::dev_t, Entity>,
+    /// The render node the primary gpu was chosen with
+    render_node: Option<libc::dev_t>,
+}
+
+/// Whether the udev device is a gpu of the seat, connectors are `card*-*` devices.
+pub fn is_seat_gpu(device: &udev::Device, seat: &SeatState) -> bool {
+    let sysname = device.sysname().to_string_lossy();
+    let is_card = sysname
+        .strip_prefix("card")
+        .is_some_and(|id| !id.is_empty() && id.bytes().all(|c| c.is_ascii_digit()));
+    is_card
+        && device
+            .property_value("ID_SEAT")
+            .map(|x| x.to_string_lossy())
+            .unwrap_or_else(|| Cow::from("seat0"))
+            == seat.name
+}
+
+/// Whether the firmware initialized the gpu, which drives the boot display.
+pub fn is_boot_vga(node: &DrmNode) -> bool {
+    udev::Device::from_devnum(udev::DeviceType::Character, node.device)
+        .ok()
+        .and_then(|device| device.parent_with_subsystem("pci").ok().flatten())
+        .and_then(|pci| pci.attribute_value("boot_vga").map(|v| v == "1"))
+        .unwrap_or(false)
+}
+
+/// Ask mesa to create the render device on the gpu of `path`.
+///
+/// It has to be called before the renderer is created, the gpu is told apart by the
+/// `ID_PATH_TAG` of udev which is what `DRI_PRIME` takes.
+pub fn select_render_gpu(path: &Path) -> Result<()> {
+    let node = DrmNode::new(path)?;
+    let device = udev::Device::from_devnum(udev::DeviceType::Character, node.device)?;
+    let tag = device
+        .property_value("ID_PATH_TAG")
+        .ok_or_else(|| anyhow!("the gpu {path:?} has no ID_PATH_TAG"))?;
+    info!("render on gpu {path:?} ({tag:?})");
+    std::env::set_var("DRI_PRIME", tag);
+    Ok(())
+}
+
+/// The primary gpu is the one the renderer runs on, or the boot gpu before it is known.
+pub fn update_primary_gpu(
+    drm_query: Query<(Entity, &DrmDevice)>,
+    added_query: Query<(), Added<DrmDevice>>,
+    render_gpu: Res<RenderGpu>,
+    mut gpu_manager: ResMut<GpuManager>,
+) {
+    let render_node = render_gpu.get();
+    let render_device = render_node.as_ref().map(|node| node.device);
+    let primary_exists = gpu_manager
+        .primary_gpu
+        .is_some_and(|entity| drm_query.contains(entity));
+    if added_query.is_empty() && primary_exists && gpu_manager.render_node == render_device {
+        return;
+    }
+    gpu_manager.bypass_change_detection().render_node = render_device;
+
+    let primary_gpu = render_node
+        .as_ref()
+        .and_then(|node| {
+            drm_query
+                .iter()
+                .find(|(_, drm)| drm.node().is_same_gpu(node))
+        })
+        .or_else(|| drm_query.iter().find(|(_, drm)| is_boot_vga(drm.node())))
+        .or_else(|| drm_query.iter().min_by_key(|(_, drm)| drm.node().device))
+        .map(|(entity, _)| entity);
+    if gpu_manager.primary_gpu != primary_gpu {
+        info!(?render_node, "primary gpu: {primary_gpu:?}");
+        gpu_manager.primary_gpu = primary_gpu;
+    }
+}
@@ -0,0 +1,139 @@
+//! HDR signaling of the connectors.
+
+use bevy::{prelude::*, window::RequestRedraw};
+use drm::control::property;
+use dway_util::{
+    color::{ImageDescription, OutputImageDescription, TransferFunction},
+    edid::Edid,
+    output::OutputHead,
+};
+
+use super::{surface::DrmSurface, DrmDevice};
+
+/// The size of `struct hdr_output_metadata`.
+const HDR_OUTPUT_METADATA_SIZE: usize = 32;
+const HDMI_STATIC_METADATA_TYPE1: u32 = 0;
+
+/// The data of a `HDR_OUTPUT_METADATA` blob, a `hdr_output_metadata` with the static metadata
+/// infoframe of CTA-861-G. The primaries are in the order red, green, blue.
+pub fn hdr_output_metadata_blob_data(description: &ImageDescription) -> Vec<u8> {
+    let eotf: u8 = match description.transfer_function {
+        TransferFunction::St2084Pq => 2,
+        TransferFunction::Hlg => 3,
+        _ => 0,
+    };
+    // in units of 0.00002
+    let coordinate = |v: f32| (v * 50000.0).round().clamp(0.0, 50000.0) as u16;
+    let luminance = |v: f32| v.round().clamp(0.0, u16::MAX as f32) as u16;
+    let primaries = description
+        .mastering_primaries
+        .unwrap_or_else(|| description.chromaticity());
+    let mastering_luminance = description.mastering_luminance.unwrap_or_default();
+
+    let mut data = Vec::with_capacity(HDR_OUTPUT_METADATA_SIZE);
+    data.extend_from_slice(&HDMI_STATIC_METADATA_TYPE1.to_ne_bytes());
+    data.push(eotf);
+    // the static metadata descriptor id
+    data.push(0);
+    for point in [
+        primaries.red,
+        primaries.green,
+        primaries.blue,
+        primaries.white,
+    ] {
+        data.extend_from_slice(&coordinate(point.x).to_ne_bytes());
+        data.extend_from_slice(&coordinate(point.y).to_ne_bytes());
+    }
+    for value in [
+        luminance(mastering_luminance.y),
+        // in units of 0.0001 cd/m²
+        luminance(mastering_luminance.x * 10000.0),
+        luminance(description.max_cll.unwrap_or(0.0)),
+        luminance(description.max_fall.unwrap_or(0.0)),
+    ] {
+        data.extend_from_slice(&value.to_ne_bytes());
+    }
+    data.resize(HDR_OUTPUT_METADATA_SIZE, 0);
+    data
+}
+
+/// The raw value of an entry of the `Colorspace` enum of a connector.
+pub fn colorspace_value(info: &property::Info, name: &str) -> Option<u64> {
+    let property::ValueType::Enum(values) = info.value_type() else {
+        return None;
+    };
+    let (_, entries) = values.values();
+    entries
+        .iter()
+        .find(|entry| entry.name().to_bytes() == name.as_bytes())
+        .map(|entry| entry.value())
+}
+
+/// Drive the outputs configured with HDR with BT.2100 PQ, and the others with sRGB.
+pub fn update_output_hdr(
+    output_query: Query<(
+        Entity,
+        &DrmSurface,
+        &OutputHead,
+        Option<&Edid>,
+        Option<&OutputImageDescription>,
+        &ChildOf,
+    )>,
+    drm_query: Query<&DrmDevice>,
+    mut redraw: MessageWriter<RequestRedraw>,
+    mut commands: Commands,
+) {
+    for (entity, surface, head, edid, current, child_of) in &output_query {
+        let hdr = head.hdr_capable && head.state.enabled && head.state.hdr;
+        let description = if hdr {
+            ImageDescription::bt2100_pq(edid.and_then(|edid| edid.hdr.as_ref()))
+        } else {
+            ImageDescription::srgb()
+        };
+        if current.map(|current| &current.0) == Some(&description) {
+            continue;
+        }
+        let Ok(drm) = drm_query.get(child_of.parent()) else {
+            continue;
+        };
+        if let Err(e) = surface.set_hdr(drm, hdr.then_some(&description)) {
+            error!(output = head.name(), "failed to set hdr metadata: {e}");
+            continue;
+        }
+        if current.is_some_and(|current| current.is_hdr()) != hdr {
+            info!(output = head.name(), "{} hdr", if hdr { "enable" } else { "disable" });
+        }
+        commands
+            .entity(entity)
+            .insert(OutputImageDescription(description));
+        redraw.write(RequestRedraw);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hdr_output_metadata_blob_data() {
+        let description = ImageDescription {
+            mastering_luminance: Some(Vec2::new(0.05, 400.0)),
+            max_cll: Some(400.0),
+            max_fall: Some(200.0),
+            ..ImageDescription::bt2100_pq(None)
+        };
+        let data = hdr_output_metadata_blob_data(&description);
+        assert_eq!(data.len(), HDR_OUTPUT_METADATA_SIZE);
+        assert_eq!(&data[0..4], &0u32.to_ne_bytes());
+        assert_eq!(data[4], 2);
+        let u16_at = |offset: usize| u16::from_ne_bytes([data[offset], data[offset + 1]]);
+        // the red and white primaries of BT.2020
+        assert_eq!((u16_at(6), u16_at(8)), (35400, 14600));
+        assert_eq!((u16_at(18), u16_at(20)), (15635, 16450));
+        assert_eq!(u16_at(22), 400);
+        assert_eq!(u16_at(24), 500);
+        assert_eq!((u16_at(26), u16_at(28)), (400, 200));
+
+        assert_eq!(hdr_output_metadata_blob_data(&ImageDescription::srgb())[4], 0);
+    }
+}
@@ -1,10 +1,18 @@
 pub mod camera;
 pub mod connectors;
+pub mod cursor;
+pub mod dumb;
+pub mod gamma;
+pub mod gpu;
+pub mod hdr;
+pub mod output;
 pub mod planes;
+pub mod power;
+pub mod scanout;
 pub mod surface;
+pub mod vrr;
 
 use std::{
-    borrow::Cow,
     collections::HashSet,
     io,
     os::fd::AsFd,
@@ -21,6 +29,7 @@ use bevy::{
         Render, RenderApp,
     },
     ui::{ui_focus_system, UiSystem},
+    window::RequestRedraw,
 };
 use double_map::DHashMap;
 use drm::{
@@ -32,29 +41,38 @@ use drm::{
 };
 use drm_ffi::drm_format_modifier_blob;
 use drm_fourcc::{DrmFormat, DrmFourcc, DrmModifier};
+use dway_util::{
+    color::OutputImageDescription,
+    edid::Edid,
+    gamma::GammaSize,
+    output::{
+        OutputConfigRequest, OutputConfigResult, OutputConfigs, OutputHead, OutputPower,
+        OutputState,
+    },
+    cursor::{HardwareCursor, HardwareCursorState},
+    scanout::{ScanoutFormats, ScanoutState},
+};
 use gbm::BufferObject;
 use nix::libc;
 use smallvec::SmallVec;
 use tracing::{span, Level};
 use udev::Enumerator;
 
-use self::{camera::DrmCamera, connectors::Connector};
+use self::{
+    camera::DrmCamera,
+    connectors::Connector,
+    gpu::{GpuManager, RenderGpu},
+};
 use crate::{
     drm::surface::DrmSurface,
     failure::DWayTTYError::*,
     gbm::GbmDevice,
     schedule::DWayTTYSet,
-    seat::{DeviceFd, SeatState},
+    seat::{DeviceFd, SeatState, SessionEvent},
     udev::{UDevEvent, UDevMonitor},
     window::create_window,
 };
 
-pub struct GpuManager {
-    pub udev: UDevMonitor,
-    pub primary_gpu: Entity,
-    pub gpus: HashMap<libc::dev_t, Entity>,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum DrmNodeType {
     Primary,
@@ -70,10 +88,17 @@ impl DrmNode {
     pub fn new(path: &Path) -> Result<Self> {
         let dev_stat =
             nix::sys::stat::stat(path).map_err(|e| anyhow!("failed to get file stat: {}", e))?;
-        let dev = dev_stat.st_rdev;
+        Self::from_device_id(dev_stat.st_rdev)
+    }
 
+    fn major_minor(dev: libc::dev_t) -> (libc::dev_t, libc::dev_t) {
         let major = ((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0x0000_0fff);
         let minor = ((dev >> 12) & 0xffff_ff00) | ((dev) & 0x0000_00ff);
+        (major, minor)
+    }
+
+    pub fn from_device_id(dev: libc::dev_t) -> Result<Self> {
+        let (major, minor) = Self::major_minor(dev);
 
         let path = format!("/sys/dev/char/{}:{}/device/drm", major, minor);
         nix::sys::stat::stat(path.as_str())
@@ -89,6 +114,20 @@ impl DrmNode {
             kind: ty,
         })
     }
+
+    /// The sysfs path of the gpu, shared by its primary and render nodes.
+    pub fn sysfs_device(&self) -> Option<PathBuf> {
+        let (major, minor) = Self::major_minor(self.device);
+        std::fs::canonicalize(format!("/sys/dev/char/{}:{}/device", major, minor)).ok()
+    }
+
+    /// Whether both nodes belong to the same gpu.
+    pub fn is_same_gpu(&self, other: &DrmNode) -> bool {
+        self.device == other.device
+            || self
+                .sysfs_device()
+                .is_some_and(|path| Some(path) == other.sysfs_device())
+    }
 }
 
 impl Drop for DrmDevice {
@@ -210,6 +249,55 @@ pub struct PropBackup {
     pub framebuffer: HashMap<framebuffer::Handle, PropertyValueSet>,
     pub plane: HashMap<plane::Handle, PropertyValueSet>,
 }
+impl PropBackup {
+    /// The properties which make up the displayed state, the others are left untouched on restore.
+    const RESTORED_PROPERTIES: [&'static str; 17] = [
+        "CRTC_ID", "MODE_ID", "ACTIVE", "VRR_ENABLED", "GAMMA_LUT", "HDR_OUTPUT_METADATA",
+        "Colorspace", "FB_ID", "SRC_X", "SRC_Y", "SRC_W", "SRC_H", "CRTC_X", "CRTC_Y", "CRTC_W",
+        "CRTC_H", "rotation",
+    ];
+
+    pub fn new(fd: &DrmDeviceFd) -> Result<Self> {
+        let res_handles = fd.resource_handles().map_err(ResourceHandlesError)?;
+        let planes = fd.plane_handles().map_err(PlanesHandlesError)?;
+        let mut backup = Self::default();
+        do_backup(fd, res_handles.connectors(), &mut backup.connector)?;
+        do_backup(fd, res_handles.crtcs(), &mut backup.crtc)?;
+        do_backup(fd, res_handles.framebuffers(), &mut backup.framebuffer)?;
+        do_backup(fd, &planes, &mut backup.plane)?;
+        Ok(backup)
+    }
+
+    fn add_to_request<H: ResourceHandle + std::hash::Hash + Eq>(
+        req: &mut AtomicModeReq,
+        backup: &HashMap<H, PropertyValueSet>,
+        props: &HashMap<(H, String), property::Handle>,
+    ) {
+        for (handle, values) in backup {
+            let (prop_handles, raw_values) = values.as_props_and_values();
+            for name in Self::RESTORED_PROPERTIES {
+                let Some(prop) = props.get(&(*handle, name.to_string())) else {
+                    continue;
+                };
+                if let Some(index) = prop_handles.iter().position(|p| p == prop) {
+                    req.add_property(*handle, *prop, property::Value::Unknown(raw_values[index]));
+                }
+            }
+        }
+    }
+
+    /// Commit the displayed state of the backup.
+    pub fn restore(&self, fd: &DrmDeviceFd, props: &PropMap) -> Result<()> {
+        let mut req = AtomicModeReq::new();
+        Self::add_to_request(&mut req, &self.connector, &props.connector);
+        Self::add_to_request(&mut req, &self.crtc, &props.crtc);
+        Self::add_to_request(&mut req, &self.plane, &props.plane);
+        fd.atomic_commit(AtomicCommitFlags::ALLOW_MODESET, req)
+            .map_err(AtomicCommitError)?;
+        Ok(())
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct PropMap {
     pub connector: HashMap<(connector::Handle, String), property::Handle>,
@@ -295,14 +383,9 @@ impl DrmDeviceState {
         {
             debug!("drm backuping in atomic mode");
             let planes = fd.plane_handles().map_err(PlanesHandlesError)?;
-            let mut backup = PropBackup::default();
+            let backup = PropBackup::new(fd)?;
             let mut props = PropMap::default();
 
-            do_backup(fd, res_handles.connectors(), &mut backup.connector)?;
-            do_backup(fd, res_handles.crtcs(), &mut backup.crtc)?;
-            do_backup(fd, res_handles.framebuffers(), &mut backup.framebuffer)?;
-            do_backup(fd, &planes, &mut backup.plane)?;
-
             do_dump_props(fd, res_handles.connectors(), &mut props.connector)?;
             do_dump_props(fd, res_handles.crtcs(), &mut props.crtc)?;
             do_dump_props(fd, &planes, &mut props.plane)?;
@@ -403,6 +486,15 @@ pub struct DrmDeviceInner {
 
     pub(crate) has_universal_planes: bool,
     pub(crate) connector_crtc_map: DHashMap<connector::Handle, crtc::Handle, ()>,
+
+    /// The black framebuffers shown by the crtcs of an output configuration until their surfaces
+    /// commit a frame
+    pub(crate) blank_framebuffers: HashMap<crtc::Handle, dumb::DumbFramebuffer>,
+
+    /// The session is paused, commits are skipped until it is resumed
+    pub(crate) paused: bool,
+    /// The state displayed when the session was paused
+    pub(crate) session_backup: Option<PropBackup>,
 }
 
 impl DrmDeviceInner {
@@ -445,6 +537,7 @@ impl DrmDeviceInner {
 pub struct DrmDevice {
     pub(crate) fd: DrmDeviceFd,
     pub(crate) path: PathBuf,
+    pub(crate) node: DrmNode,
     pub(crate) inner: Arc<Mutex<DrmDeviceInner>>,
 }
 
@@ -484,6 +577,7 @@ pub enum DrmConnectorEvent {
 impl DrmDevice {
     #[tracing::instrument(skip_all)]
     pub fn new(device: DeviceFd, path: PathBuf) -> Result<Self> {
+        let node = DrmNode::new(&path)?;
         let fd = DrmDeviceFd(device);
         let mut states = DrmDeviceState::new(&fd)?;
         states.reset(&fd)?;
@@ -503,12 +597,16 @@ impl DrmDevice {
         Ok(Self {
             fd,
             path,
+            node,
             inner: Arc::new(Mutex::new(DrmDeviceInner {
                 has_universal_planes,
                 privileged,
                 connectors: Default::default(),
                 states,
                 connector_crtc_map: Default::default(),
+                blank_framebuffers: Default::default(),
+                paused: false,
+                session_backup: None,
             })),
         })
     }
@@ -527,7 +625,7 @@ impl DrmDevice {
                     .ok()
             })
             .filter(|conn| conn.state() == connector::State::Connected)
-            .map(Connector::new)
+            .map(|conn| Connector::new(&self.fd, conn))
             .try_collect()?;
         Ok(connectors)
     }
@@ -548,6 +646,48 @@ impl DrmDevice {
         Ok(handle)
     }
 
+    pub fn node(&self) -> &DrmNode {
+        &self.node
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.lock().unwrap().paused
+    }
+
+    /// Remember the displayed state and release the device to the next session.
+    pub fn pause(&self) {
+        let mut guard = self.inner.lock().unwrap();
+        guard.paused = true;
+        if matches!(guard.states, DrmDeviceState::Atomic { .. }) {
+            guard.session_backup = PropBackup::new(&self.fd)
+                .map_err(|e| error!("failed to backup drm state: {e}"))
+                .ok();
+        }
+        if guard.privileged {
+            if let Err(e) = self.fd.release_master_lock() {
+                debug!("failed to release master lock: {e}");
+            }
+        }
+    }
+
+    /// Take the device back and restore the state displayed before the pause.
+    pub fn resume(&self) -> Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        if guard.privileged {
+            if let Err(e) = self.fd.acquire_master_lock() {
+                debug!("failed to acquire master lock: {e}");
+            }
+        }
+        guard.paused = false;
+        let backup = guard.session_backup.take();
+        // the planes may have been left on by the other session
+        guard.states.reset(&self.fd)?;
+        if let (Some(backup), DrmDeviceState::Atomic { props, .. }) = (backup, &guard.states) {
+            backup.restore(&self.fd, props)?;
+        }
+        Ok(())
+    }
+
     pub fn alloc_crtc(&self, connector: &connector::Info) -> Result<crtc::Handle> {
         if let Some(res_handles) = connector
             .current_encoder()
@@ -585,13 +725,7 @@ pub fn all_gpus(seat: &SeatState) -> io::Result<Vec<PathBuf>> {
     enumerator.match_sysname("card[0-9]*")?;
     Ok(enumerator
         .scan_devices()?
-        .filter(|device| {
-            device
-                .property_value("ID_SEAT")
-                .map(|x| x.to_string_lossy())
-                .unwrap_or_else(|| Cow::from("seat0"))
-                == seat.name
-        })
+        .filter(|device| gpu::is_seat_gpu(device, seat))
         .flat_map(|device| device.devnode().map(PathBuf::from))
         .collect())
 }
@@ -602,6 +736,8 @@ pub fn setup(
     mut seat: NonSendMut<SeatState>,
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
+    configs: Res<OutputConfigs>,
+    mut gpu_manager: ResMut<GpuManager>,
 ) {
     debug!(
         r"DRM Debugging method:
@@ -611,49 +747,105 @@ pub fn setup(
     "
     );
 
+    let mut heads = Vec::new();
     for gpu_path in all_gpus(&seat).unwrap() {
-        if let Err(e) = add_device(gpu_path, &mut udev, &mut seat, &mut commands, &mut images) {
-            error!("failed to add drm device: {e}");
+        match add_device(
+            gpu_path,
+            &mut udev,
+            &mut seat,
+            &mut commands,
+            &mut images,
+            &configs,
+            &mut gpu_manager,
+            &heads.iter().collect::<Vec<_>>(),
+        ) {
+            Ok((_, new_heads)) => heads.extend(new_heads),
+            Err(e) => error!("failed to add drm device: {e}"),
         }
     }
 }
 
+/// Spawn the output entity of a connector and light it up.
+///
+/// The entity is despawned again if the screen can't be connected.
 pub fn add_connector(
     conn: Connector,
+    state: OutputState,
     drm: &DrmDevice,
     drm_entity: Entity,
     images: &mut Assets<Image>,
     commands: &mut Commands,
-) {
+) -> Result<(Entity, OutputHead)> {
     trace!("conn: {:?}", conn);
     let mut entity_mut = commands.spawn_empty();
+    let entity = entity_mut.id();
 
     {
         let mut guard = drm.inner.lock().unwrap();
         guard
             .connectors
             .get_mut(&conn.info.handle())
-            .map(|v| v.0 = Some(entity_mut.id()));
+            .map(|v| v.0 = Some(entity));
+    }
+
+    let name = conn.name.clone();
+    let head = conn.head(state.clone());
+    entity_mut.insert((head.clone(), Name::new(name.clone()), ChildOf(drm_entity)));
+    if let Some(edid) = conn.edid.clone() {
+        entity_mut.insert(edid);
+    }
+    if !state.enabled {
+        entity_mut.insert(conn);
+        info!("monitor {:?} is disabled", name);
+        return Ok((entity, head));
     }
 
-    let Ok(surface) =
-        DrmSurface::new(drm, &conn, images).map_err(|e| error!("failed to connect screen: {e}"))
-    else {
-        return;
+    let surface = match DrmSurface::new(drm, &conn, &state, images) {
+        Ok(surface) => surface,
+        Err(e) => {
+            entity_mut.despawn();
+            return Err(e);
+        }
     };
     trace!("drm surface: {:?}", &surface);
 
-    let name = conn.name.clone();
-    let window = create_window(&conn, &surface);
-    entity_mut.insert((
-        window,
-        surface,
-        conn,
-        Name::new(name.clone()),
-        ChildOf(drm_entity),
-    ));
-    let entity = entity_mut.id();
+    let window = create_window(&conn, &surface, &state);
+    if let Some(size) = surface.gamma_size() {
+        entity_mut.insert(GammaSize(size));
+    }
+    entity_mut.insert((window, surface, conn, OutputPower::On));
     info!("init monitor {:?} at {entity:?}", name);
+    Ok((entity, head))
+}
+
+/// Add a connector, keeping it as a disabled output if the screen can't be connected.
+pub fn add_connector_or_disable(
+    conn: Connector,
+    state: OutputState,
+    drm: &DrmDevice,
+    drm_entity: Entity,
+    images: &mut Assets<Image>,
+    commands: &mut Commands,
+) -> (Entity, OutputHead) {
+    match add_connector(
+        conn.clone(),
+        state.clone(),
+        drm,
+        drm_entity,
+        images,
+        commands,
+    ) {
+        Ok(output) => output,
+        Err(e) => {
+            error!("failed to connect screen {}: {e}", conn.name);
+            let state = OutputState {
+                enabled: false,
+                ..state
+            };
+            add_connector(conn, state, drm, drm_entity, images, commands)
+                .expect("a disabled connector has no surface to connect")
+        }
+    }
 }
 
 pub fn add_device(
@@ -662,7 +854,10 @@ pub fn add_device(
     seat: &mut SeatState,
     commands: &mut Commands,
     images: &mut Assets<Image>,
-) -> Result<Entity> {
+    configs: &OutputConfigs,
+    gpu_manager: &mut GpuManager,
+    others: &[&OutputHead],
+) -> Result<(Entity, Vec<OutputHead>)> {
     let _span = span!(Level::ERROR,"init drm device",path=%gpu_path.to_string_lossy()).entered();
 
     debug!("open drm device");
@@ -673,10 +868,14 @@ pub fn add_device(
     let connectors = drm.connectors()?;
 
     let drm_entity = commands.spawn_empty().id();
+    let mut heads = Vec::new();
 
     {
-        for conn in connectors {
-            add_connector(conn, &drm, drm_entity, images, commands);
+        let states = output::resolve_connector_states(configs, &connectors, others);
+        for (conn, state) in connectors.into_iter().zip(states) {
+            let (_, head) =
+                add_connector_or_disable(conn, state, &drm, drm_entity, images, commands);
+            heads.push(head);
         }
         let res_handles = drm.fd.resource_handles().map_err(ResourceHandlesError)?;
         for crtc_handle in res_handles.crtcs() {
@@ -695,10 +894,11 @@ pub fn add_device(
 
     info!("gpu device {gpu_path:?} connected at {:?}", drm_entity);
     udev.device_entity_map.insert(gpu_path, drm_entity);
+    gpu_manager.gpus.insert(drm.node.device, drm_entity);
 
     commands.entity(drm_entity).insert((drm, gbm));
 
-    Ok(drm_entity)
+    Ok((drm_entity, heads))
 }
 
 #[tracing::instrument(skip_all)]
@@ -707,20 +907,45 @@ pub fn on_udev_event(
     mut seat: NonSendMut<SeatState>,
     mut commands: Commands,
     mut drm_query: Query<&mut DrmDevice>,
+    head_query: Query<&OutputHead>,
     mut images: ResMut<Assets<Image>>,
+    configs: Res<OutputConfigs>,
+    mut gpu_manager: ResMut<GpuManager>,
 ) {
+    let mut heads = head_query.iter().cloned().collect::<Vec<_>>();
     for event in udev.iter().cloned().collect::<Vec<_>>() {
+        let device = match &event {
+            UDevEvent::Added(device) | UDevEvent::Changed(device) | UDevEvent::Removed(device) => {
+                device
+            }
+        };
+        if !gpu::is_seat_gpu(device, &seat) {
+            continue;
+        }
+        // devices are keyed by their device node, as they are opened
+        let Some(gpu_path) = device.devnode().map(PathBuf::from) else {
+            continue;
+        };
         match event {
-            UDevEvent::Added(device) => {
-                let gpu_path = device.devpath().into();
-                if let Err(e) =
-                    add_device(gpu_path, &mut udev, &mut seat, &mut commands, &mut images)
-                {
-                    error!("failed to add drm device: {e}");
+            UDevEvent::Added(_) => {
+                if udev.device_entity_map.contains_key(&gpu_path) {
+                    continue;
+                }
+                match add_device(
+                    gpu_path,
+                    &mut udev,
+                    &mut seat,
+                    &mut commands,
+                    &mut images,
+                    &configs,
+                    &mut gpu_manager,
+                    &heads.iter().collect::<Vec<_>>(),
+                ) {
+                    Ok((_, new_heads)) => heads.extend(new_heads),
+                    Err(e) => error!("failed to add drm device: {e}"),
                 }
             }
-            UDevEvent::Changed(device) => {
-                let gpu_path: PathBuf = device.devpath().into();
+            UDevEvent::Changed(_) => {
                 let Some(drm_entity) = udev.device_entity_map.get(&gpu_path) else {
                     continue;
                 };
@@ -730,21 +955,37 @@ pub fn on_udev_event(
                 let Ok(events) = drm.connectors_change().map_err(|e| error!("{e}")) else {
                     continue;
                 };
-                let mut drm_guard = drm.inner.lock().unwrap();
                 for change in events {
                     match change {
                         DrmConnectorEvent::Added(info) => {
-                            let Ok(info) = Connector::new(info)
+                            let Ok(conn) = Connector::new(&drm.fd, info)
                                 .map_err(|e| error!("failed to connect screen: {e}"))
                             else {
                                 continue;
                             };
-                            add_connector(info, &drm, *drm_entity, &mut images, &mut commands);
+                            let state = output::resolve_connector_states(
+                                &configs,
+                                std::slice::from_ref(&conn),
+                                &heads.iter().collect::<Vec<_>>(),
+                            )
+                            .remove(0);
+                            let (_, head) = add_connector_or_disable(
+                                conn,
+                                state,
+                                &drm,
+                                *drm_entity,
+                                &mut images,
+                                &mut commands,
+                            );
+                            heads.push(head);
                         }
                         DrmConnectorEvent::Removed(info, entity) => {
+                            heads.retain(|head| head.name() != connectors::connector_name(&info));
                             if let Some(entity) = entity {
                                 commands.entity(entity).despawn();
-                                drm_guard
+                                drm.inner
+                                    .lock()
+                                    .unwrap()
                                     .connectors
                                     .get_mut(&info.handle())
                                     .map(|v| v.0 = None);
@@ -754,14 +995,40 @@ pub fn on_udev_event(
                     }
                 }
             }
-            UDevEvent::Removed(device) => {
-                if let Some(entity) = udev.device_entity_map.get(&PathBuf::from(device.devpath())) {
-                    if drm_query.get(*entity).is_ok() {
-                        commands.entity(*entity).despawn();
+            UDevEvent::Removed(_) => {
+                let Some(entity) = udev.device_entity_map.remove(&gpu_path) else {
+                    continue;
+                };
+                if let Ok(drm) = drm_query.get(entity) {
+                    info!("gpu device {gpu_path:?} removed");
+                    gpu_manager.gpus.remove(&drm.node.device);
+                    // the outputs of the gpu are despawned with it
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+    }
+}
+
+pub fn on_session_event(
+    mut session_events: MessageReader<SessionEvent>,
+    drm_query: Query<&DrmDevice>,
+    mut redraw: MessageWriter<RequestRedraw>,
+) {
+    for event in session_events.read() {
+        for drm in &drm_query {
+            match event {
+                SessionEvent::Paused => drm.pause(),
+                SessionEvent::Resumed => {
+                    if let Err(e) = drm.resume() {
+                        error!(path=?drm.path, "failed to restore drm state: {e}");
                     }
                 }
             }
         }
+        if *event == SessionEvent::Resumed {
+            redraw.write(RequestRedraw);
+        }
     }
 }
 
@@ -823,9 +1090,48 @@ pub fn recevie_drm_events(
 pub struct DrmPlugin;
 impl Plugin for DrmPlugin {
     fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<OutputConfigs>() {
+            app.insert_resource(output::load_output_configs());
+        }
         app.add_systems(PreStartup, setup)
             .add_plugins(ExtractComponentPlugin::<DrmDevice>::default())
+            .add_event::<OutputConfigRequest>()
+            .add_event::<OutputConfigResult>()
+            .init_resource::<GpuManager>()
+            .init_resource::<RenderGpu>()
+            .init_resource::<ScanoutFormats>()
+            .init_resource::<HardwareCursor>()
+            .init_resource::<HardwareCursorState>()
+            .init_resource::<vrr::FrameSchedule>()
+            .add_systems(
+                First,
+                (cursor::init_cursor_planes, cursor::update_cursor_planes)
+                    .chain()
+                    .after(DWayTTYSet::LibinputSystem)
+                    .after(DWayTTYSet::DrmSystem),
+            )
+            .add_systems(
+                Last,
+                (
+                    scanout::update_scanout_formats,
+                    scanout::update_scanout,
+                    vrr::update_vrr,
+                    gamma::apply_output_gamma,
+                    power::apply_output_power,
+                    hdr::update_output_hdr,
+                ),
+            )
             .add_systems(First, on_udev_event.in_set(DWayTTYSet::UdevSystem))
+            .add_systems(
+                First,
+                (
+                    on_session_event,
+                    gpu::update_primary_gpu,
+                    output::apply_output_config,
+                )
+                    .chain()
+                    .in_set(DWayTTYSet::DrmSystem),
+            )
             .add_systems(
                 PreUpdate,
                 (
@@ -834,7 +1140,13 @@ impl Plugin for DrmPlugin {
                 )
                     .in_set(UiSystem::Focus),
             )
-            .register_type::<DrmCamera>();
+            .register_type::<DrmCamera>()
+            .register_type::<OutputHead>()
+            .register_type::<OutputPower>()
+            .register_type::<OutputImageDescription>()
+            .register_type::<Edid>()
+            .register_type::<ScanoutState>()
+            .register_type::<HardwareCursorState>();
         app.sub_app_mut(RenderApp)
             .add_systems(
                 Render,
@@ -0,0 +1,755 @@
+use anyhow::{anyhow, bail, Result};
+use bevy::{platform::collections::HashMap, prelude::*};
+use drm::control::{
+    atomic::AtomicModeReq, connector, crtc, property, property::Value, AtomicCommitFlags, Device,
+};
+use dway_util::output::{
+    resolve_output_states, validate_output_states, OutputConfigError, OutputConfigRequest,
+    OutputConfigResult, OutputConfigs, OutputHead, OutputState, OutputTransform, VrrPolicy,
+};
+
+use super::{
+    add_connector, add_connector_or_disable,
+    connectors::{connector_name, output_mode, Connector},
+    dumb::DumbFramebuffer,
+    planes::Planes,
+    surface::DrmTransform,
+    DrmDevice, DrmDeviceState, PropMap,
+};
+use crate::failure::DWayTTYError::*;
+
+/// The part of a drm device that the output configuration needs, so that it can be tested
+/// without a gpu.
+pub trait OutputDevice {
+    /// Check that the device can drive its outputs with the states, nothing is changed.
+    fn test_outputs(&self, states: &[(&OutputHead, &OutputState)]) -> Result<()>;
+}
+
+/// Validate the states of all outputs, then test the outputs of every device.
+///
+/// The configuration is only applied after every device accepted it.
+pub fn test_output_states<D: OutputDevice>(
+    devices: &[(&D, Vec<(&OutputHead, &OutputState)>)],
+) -> Result<(), OutputConfigError> {
+    let all = devices
+        .iter()
+        .flat_map(|(_, states)| states.iter().copied())
+        .collect::<Vec<_>>();
+    validate_output_states(&all)?;
+    for (device, states) in devices {
+        device
+            .test_outputs(states)
+            .map_err(|e| OutputConfigError::Rejected(e.to_string()))?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputChange {
+    Unchanged,
+    /// Only the position, the scale or the mirrored output changed
+    Move,
+    Modeset,
+}
+
+pub fn output_change(old: &OutputState, new: &OutputState) -> OutputChange {
+    if old.needs_modeset(new) {
+        OutputChange::Modeset
+    } else if old != new {
+        OutputChange::Move
+    } else {
+        OutputChange::Unchanged
+    }
+}
+
+impl OutputDevice for DrmDevice {
+    fn test_outputs(&self, states: &[(&OutputHead, &OutputState)]) -> Result<()> {
+        self.commit_outputs(states, true)
+    }
+}
+
+impl DrmDevice {
+    /// Switch the crtcs and connectors of the outputs in one atomic commit, nothing is changed if
+    /// it fails.
+    ///
+    /// The crtcs of the outputs which need a modeset show a black framebuffer until their
+    /// surfaces commit a frame. Legacy devices can't change the outputs at once or test them, so
+    /// they are rejected.
+    pub fn commit_outputs(
+        &self,
+        states: &[(&OutputHead, &OutputState)],
+        test_only: bool,
+    ) -> Result<()> {
+        let props = match &self.inner.lock().unwrap().states {
+            DrmDeviceState::Atomic { props, .. } => props.clone(),
+            DrmDeviceState::Legacy { .. } => {
+                bail!(
+                    "{:?} has no atomic modesetting to configure outputs",
+                    self.path
+                )
+            }
+        };
+        let res_handles = self.resource_handles().map_err(ResourceHandlesError)?;
+        let connectors = res_handles
+            .connectors()
+            .iter()
+            .filter_map(|handle| self.get_connector(*handle, false).ok())
+            .map(|info| (connector_name(&info), info))
+            .collect::<HashMap<_, _>>();
+
+        let crtc_of = |info: &connector::Info| {
+            info.current_encoder()
+                .and_then(|e| self.get_encoder(e).ok())
+                .and_then(|e| e.crtc())
+        };
+        // the outputs without a modeset keep showing their frames on their crtcs
+        let unchanged =
+            |head: &OutputHead, state: &OutputState| !test_only && !head.state.needs_modeset(state);
+
+        let mut req = AtomicModeReq::new();
+        let mut used_crtcs = states
+            .iter()
+            .filter(|(head, state)| state.enabled && unchanged(head, state))
+            .filter_map(|(head, _)| crtc_of(connectors.get(head.name())?))
+            .collect::<Vec<_>>();
+        let mut routes = Vec::new();
+        let mut framebuffers = Vec::new();
+        let mut blobs = Vec::new();
+
+        let result = (|| -> Result<()> {
+            for (head, state) in states {
+                let info = connectors
+                    .get(head.name())
+                    .ok_or_else(|| anyhow!("connector {} not found", head.name()))?;
+                if unchanged(head, state) {
+                    continue;
+                }
+                let current_crtc = crtc_of(info);
+                if !state.enabled {
+                    req.add_property(
+                        info.handle(),
+                        connector_prop(&props, info.handle(), "CRTC_ID")?,
+                        Value::CRTC(None),
+                    );
+                    if let Some(crtc) = current_crtc {
+                        req.add_property(
+                            crtc,
+                            crtc_prop(&props, crtc, "ACTIVE")?,
+                            Value::Boolean(false),
+                        );
+                        req.add_property(
+                            crtc,
+                            crtc_prop(&props, crtc, "MODE_ID")?,
+                            Value::Unknown(0),
+                        );
+                        let plane = Planes::new(&crtc, self)?.primary.handle;
+                        if let Some(prop) = props.plane.get(&(plane, "CRTC_ID".to_string())) {
+                            req.add_property(plane, *prop, Value::CRTC(None));
+                        }
+                        if let Some(prop) = props.plane.get(&(plane, "FB_ID".to_string())) {
+                            req.add_property(plane, *prop, Value::Framebuffer(None));
+                        }
+                    }
+                    routes.push((info.handle(), None));
+                    continue;
+                }
+
+                let mode = info
+                    .modes()
+                    .iter()
+                    .find(|mode| output_mode(mode) == state.mode)
+                    .ok_or_else(|| anyhow!("{} has no mode {:?}", head.name(), state.mode))?;
+                let crtc = current_crtc
+                    .filter(|crtc| !used_crtcs.contains(crtc))
+                    .or_else(|| {
+                        info.encoders()
+                            .iter()
+                            .flat_map(|e| self.get_encoder(*e))
+                            .flat_map(|e| res_handles.filter_crtcs(e.possible_crtcs()))
+                            .find(|crtc| !used_crtcs.contains(crtc))
+                    })
+                    .ok_or_else(|| anyhow!("no available crtc for {}", head.name()))?;
+                used_crtcs.push(crtc);
+                routes.push((info.handle(), Some(crtc)));
+
+                let size = state.transform.apply(state.mode.size());
+                let framebuffer = DumbFramebuffer::new(self, size)?;
+                let framebuffer_handle = framebuffer.framebuffer;
+                framebuffers.push((crtc, framebuffer));
+                let blob = self.create_property_blob(mode)?;
+                if let Value::Blob(id) = blob {
+                    blobs.push(id);
+                }
+
+                req.add_property(
+                    info.handle(),
+                    connector_prop(&props, info.handle(), "CRTC_ID")?,
+                    Value::CRTC(Some(crtc)),
+                );
+                req.add_property(crtc, crtc_prop(&props, crtc, "MODE_ID")?, blob);
+                req.add_property(
+                    crtc,
+                    crtc_prop(&props, crtc, "ACTIVE")?,
+                    Value::Boolean(true),
+                );
+                match crtc_prop(&props, crtc, "VRR_ENABLED") {
+                    Ok(prop) => req.add_property(
+                        crtc,
+                        prop,
+                        Value::Boolean(state.vrr != VrrPolicy::Never),
+                    ),
+                    Err(_) if state.vrr != VrrPolicy::Never => {
+                        bail!("{} cannot enable variable refresh rate", head.name())
+                    }
+                    Err(_) => {}
+                }
+
+                let plane = Planes::new(&crtc, self)?.primary.handle;
+                let plane_prop = |name: &str| {
+                    props
+                        .plane
+                        .get(&(plane, name.to_string()))
+                        .copied()
+                        .ok_or_else(|| NoSuchProperty(name.to_string()))
+                };
+                let fixed = |v: i32| Value::UnsignedRange((v as u64) << 16);
+                req.add_property(plane, plane_prop("CRTC_ID")?, Value::CRTC(Some(crtc)));
+                req.add_property(
+                    plane,
+                    plane_prop("FB_ID")?,
+                    Value::Framebuffer(Some(framebuffer_handle)),
+                );
+                req.add_property(plane, plane_prop("SRC_X")?, fixed(0));
+                req.add_property(plane, plane_prop("SRC_Y")?, fixed(0));
+                req.add_property(plane, plane_prop("SRC_W")?, fixed(size.x));
+                req.add_property(plane, plane_prop("SRC_H")?, fixed(size.y));
+                req.add_property(plane, plane_prop("CRTC_X")?, Value::SignedRange(0));
+                req.add_property(plane, plane_prop("CRTC_Y")?, Value::SignedRange(0));
+                req.add_property(
+                    plane,
+                    plane_prop("CRTC_W")?,
+                    Value::UnsignedRange(state.mode.width as u64),
+                );
+                req.add_property(
+                    plane,
+                    plane_prop("CRTC_H")?,
+                    Value::UnsignedRange(state.mode.height as u64),
+                );
+                match plane_prop("rotation") {
+                    Ok(prop) => req.add_property(
+                        plane,
+                        prop,
+                        Value::Bitmask(DrmTransform::from(state.transform).bits() as u64),
+                    ),
+                    Err(_) if state.transform != OutputTransform::Normal => {
+                        bail!("{} cannot be transformed", head.name())
+                    }
+                    Err(_) => {}
+                }
+            }
+            let flags = if test_only {
+                AtomicCommitFlags::TEST_ONLY | AtomicCommitFlags::ALLOW_MODESET
+            } else {
+                AtomicCommitFlags::ALLOW_MODESET
+            };
+            self.atomic_commit(flags, req).map_err(AtomicCommitError)?;
+            Ok(())
+        })();
+
+        // the committed state keeps a reference to the mode blobs
+        for blob in blobs {
+            let _ = self.destroy_property_blob(blob);
+        }
+        if result.is_ok() && !test_only {
+            let mut guard = self.inner.lock().unwrap();
+            for (connector, crtc) in routes {
+                guard.connector_crtc_map.remove_key1(&connector);
+                if let Some(crtc) = crtc {
+                    guard.connector_crtc_map.insert(connector, crtc, ());
+                }
+            }
+            guard.blank_framebuffers.extend(framebuffers);
+        }
+        result
+    }
+}
+
+fn connector_prop(
+    props: &PropMap,
+    handle: connector::Handle,
+    name: &str,
+) -> Result<property::Handle> {
+    Ok(*props
+        .connector
+        .get(&(handle, name.to_string()))
+        .ok_or_else(|| NoSuchProperty(name.to_string()))?)
+}
+
+fn crtc_prop(props: &PropMap, handle: crtc::Handle, name: &str) -> Result<property::Handle> {
+    Ok(*props
+        .crtc
+        .get(&(handle, name.to_string()))
+        .ok_or_else(|| NoSuchProperty(name.to_string()))?)
+}
+
+impl DrmDevice {
+    /// Turn off the crtc driving the connector and release it.
+    pub fn disable_connector(&self, info: &connector::Info) -> Result<()> {
+        let Some(crtc) = info
+            .current_encoder()
+            .and_then(|e| self.get_encoder(e).ok())
+            .and_then(|e| e.crtc())
+        else {
+            return Ok(());
+        };
+        let props = {
+            let mut guard = self.inner.lock().unwrap();
+            guard.connector_crtc_map.remove_key1(&info.handle());
+            match &guard.states {
+                DrmDeviceState::Atomic { props, .. } => Some(props.clone()),
+                DrmDeviceState::Legacy { .. } => None,
+            }
+        };
+        let Some(props) = props else {
+            self.set_crtc(crtc, None, (0, 0), &[], None)
+                .map_err(SetCrtcStateError)?;
+            return Ok(());
+        };
+
+        let mut req = AtomicModeReq::new();
+        req.add_property(
+            info.handle(),
+            connector_prop(&props, info.handle(), "CRTC_ID")?,
+            Value::CRTC(None),
+        );
+        req.add_property(
+            crtc,
+            crtc_prop(&props, crtc, "ACTIVE")?,
+            Value::Boolean(false),
+        );
+        req.add_property(crtc, crtc_prop(&props, crtc, "MODE_ID")?, Value::Unknown(0));
+        let plane = Planes::new(&crtc, self)?.primary.handle;
+        if let Some(prop) = props.plane.get(&(plane, "CRTC_ID".to_string())) {
+            req.add_property(plane, *prop, Value::CRTC(None));
+        }
+        if let Some(prop) = props.plane.get(&(plane, "FB_ID".to_string())) {
+            req.add_property(plane, *prop, Value::Framebuffer(None));
+        }
+        self.atomic_commit(AtomicCommitFlags::ALLOW_MODESET, req)
+            .map_err(AtomicCommitError)?;
+        Ok(())
+    }
+}
+
+fn apply_request(
+    request: &OutputConfigRequest,
+    drm_query: &Query<(Entity, &DrmDevice)>,
+    output_query: &Query<(Entity, &Connector, &OutputHead, &ChildOf)>,
+    window_query: &mut Query<&mut Window>,
+    configs: &mut OutputConfigs,
+    images: &mut Assets<Image>,
+    commands: &mut Commands,
+) -> Result<(), OutputConfigError> {
+    let requested = request.states.iter().cloned().collect::<HashMap<_, _>>();
+    for entity in requested.keys() {
+        if !output_query.contains(*entity) {
+            return Err(OutputConfigError::UnknownOutput(format!("{entity:?}")));
+        }
+    }
+
+    let mut devices = HashMap::<Entity, Vec<_>>::default();
+    for (entity, connector, head, child_of) in output_query.iter() {
+        let state = requested.get(&entity).unwrap_or(&head.state);
+        devices
+            .entry(child_of.parent())
+            .or_default()
+            .push((entity, connector, head, state));
+    }
+    let tests = devices
+        .iter()
+        .filter_map(|(drm_entity, outputs)| {
+            let (_, drm) = drm_query.get(*drm_entity).ok()?;
+            Some((
+                drm,
+                outputs
+                    .iter()
+                    .map(|(_, _, head, state)| (*head, *state))
+                    .collect::<Vec<_>>(),
+            ))
+        })
+        .collect::<Vec<_>>();
+    test_output_states(&tests)?;
+    if request.test_only {
+        return Ok(());
+    }
+
+    // every device switches all its crtcs and connectors in one commit, the devices committed
+    // before a failing one are switched back
+    let mut committed = Vec::new();
+    for (drm, states) in &tests {
+        if let Err(e) = drm.commit_outputs(states, false) {
+            for (drm, states) in committed.into_iter().rev() {
+                restore_outputs(drm, states);
+            }
+            return Err(OutputConfigError::Rejected(e.to_string()));
+        }
+        committed.push((*drm, states));
+    }
+
+    let mut applied = Vec::new();
+    for (drm_entity, outputs) in devices.iter() {
+        let Ok((_, drm)) = drm_query.get(*drm_entity) else {
+            continue;
+        };
+        for (entity, connector, head, state) in outputs {
+            match set_output_state(
+                *entity,
+                connector,
+                head,
+                state,
+                drm,
+                *drm_entity,
+                window_query,
+                images,
+                commands,
+            ) {
+                Ok(entity) => applied.push((entity, *connector, *head, *state, drm, *drm_entity)),
+                Err(e) => {
+                    // only a modeset fails, it already despawned the output
+                    add_connector_or_disable(
+                        (*connector).clone(),
+                        head.state.clone(),
+                        drm,
+                        *drm_entity,
+                        images,
+                        commands,
+                    );
+                    for (entity, connector, head, state, drm, drm_entity) in
+                        applied.into_iter().rev()
+                    {
+                        restore_output_state(
+                            entity,
+                            connector,
+                            head,
+                            state,
+                            drm,
+                            drm_entity,
+                            window_query,
+                            images,
+                            commands,
+                        );
+                    }
+                    return Err(OutputConfigError::Rejected(format!(
+                        "{}: {e}",
+                        connector.name
+                    )));
+                }
+            }
+        }
+    }
+
+    for (_, _, head, state) in devices.values().flatten() {
+        configs.remember(&head.identity, state);
+    }
+    if let Err(e) = configs.save() {
+        error!("failed to save output configs: {e}");
+    }
+    Ok(())
+}
+
+/// Switch the outputs of a device from the requested states back to the states of their heads.
+fn restore_outputs(drm: &DrmDevice, states: &[(&OutputHead, &OutputState)]) {
+    let current = states
+        .iter()
+        .map(|(head, state)| OutputHead {
+            state: (*state).clone(),
+            ..(*head).clone()
+        })
+        .collect::<Vec<_>>();
+    let restore = current
+        .iter()
+        .zip(states)
+        .map(|(current, (head, _))| (current, &head.state))
+        .collect::<Vec<_>>();
+    if let Err(e) = drm.commit_outputs(&restore, false) {
+        error!("failed to restore the outputs of {:?}: {e}", drm.path);
+    }
+}
+
+/// Change the state of an output, returns the entity of the output afterwards.
+///
+/// A modeset respawns the output, the old entity is despawned even if the new state fails.
+fn set_output_state(
+    entity: Entity,
+    connector: &Connector,
+    head: &OutputHead,
+    state: &OutputState,
+    drm: &DrmDevice,
+    drm_entity: Entity,
+    window_query: &mut Query<&mut Window>,
+    images: &mut Assets<Image>,
+    commands: &mut Commands,
+) -> Result<Entity> {
+    match output_change(&head.state, state) {
+        OutputChange::Unchanged => Ok(entity),
+        OutputChange::Move => {
+            if let Ok(mut window) = window_query.get_mut(entity) {
+                window.position = WindowPosition::At(state.position);
+                window
+                    .resolution
+                    .set_scale_factor_override(Some(state.scale));
+            }
+            let mut head = head.clone();
+            head.state = state.clone();
+            commands.entity(entity).insert(head);
+            Ok(entity)
+        }
+        OutputChange::Modeset => {
+            // the connector may be on another crtc after the commit of the configuration
+            let mut connector = connector.clone();
+            if let Ok(info) = drm.get_connector(connector.info.handle(), false) {
+                connector.info = info;
+            }
+            if !state.enabled {
+                if let Err(e) = drm.disable_connector(&connector.info) {
+                    error!("failed to disable {}: {e}", connector.name);
+                }
+            }
+            commands.entity(entity).despawn();
+            let (entity, _) =
+                add_connector(connector, state.clone(), drm, drm_entity, images, commands)?;
+            Ok(entity)
+        }
+    }
+}
+
+/// Bring an output from `state` back to the state of its head, after a request failed.
+fn restore_output_state(
+    entity: Entity,
+    connector: &Connector,
+    head: &OutputHead,
+    state: &OutputState,
+    drm: &DrmDevice,
+    drm_entity: Entity,
+    window_query: &mut Query<&mut Window>,
+    images: &mut Assets<Image>,
+    commands: &mut Commands,
+) {
+    let mut current = head.clone();
+    current.state = state.clone();
+    if let Err(e) = set_output_state(
+        entity,
+        connector,
+        &current,
+        &head.state,
+        drm,
+        drm_entity,
+        window_query,
+        images,
+        commands,
+    ) {
+        error!("failed to restore the state of {}: {e}", connector.name);
+        let state = OutputState {
+            enabled: false,
+            ..head.state.clone()
+        };
+        add_connector_or_disable(connector.clone(), state, drm, drm_entity, images, commands);
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub fn apply_output_config(
+    mut requests: MessageReader<OutputConfigRequest>,
+    mut results: MessageWriter<OutputConfigResult>,
+    drm_query: Query<(Entity, &DrmDevice)>,
+    output_query: Query<(Entity, &Connector, &OutputHead, &ChildOf)>,
+    mut window_query: Query<&mut Window>,
+    mut configs: ResMut<OutputConfigs>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+) {
+    for request in requests.read() {
+        let result = apply_request(
+            request,
+            &drm_query,
+            &output_query,
+            &mut window_query,
+            &mut configs,
+            &mut images,
+            &mut commands,
+        );
+        match &result {
+            Ok(()) if !request.test_only => info!("output configuration applied"),
+            Ok(()) => {}
+            Err(e) => warn!("output configuration failed: {e}"),
+        }
+        results.write(OutputConfigResult {
+            sender: request.sender,
+            result: result.map_err(|e| e.to_string()),
+        });
+    }
+}
+
+/// Resolve the states of new connectors, placing them after the existing outputs.
+pub fn resolve_connector_states(
+    configs: &OutputConfigs,
+    connectors: &[Connector],
+    others: &[&OutputHead],
+) -> Vec<OutputState> {
+    let heads = connectors
+        .iter()
+        .map(|connector| connector.head(default()))
+        .collect::<Vec<_>>();
+    resolve_output_states(configs, &heads.iter().collect::<Vec<_>>(), others)
+}
+
+pub fn load_output_configs() -> OutputConfigs {
+    let Some(path) = OutputConfigs::default_path() else {
+        return OutputConfigs::default();
+    };
+    OutputConfigs::load(&path).unwrap_or_else(|e| {
+        error!("{e}");
+        OutputConfigs {
+            path: Some(path),
+            ..default()
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use anyhow::{bail, Result};
+    use bevy::prelude::*;
+    use dway_util::output::{
+        OutputConfigError, OutputHead, OutputIdentity, OutputMode, OutputState, OutputTransform,
+        VrrPolicy,
+    };
+
+    use super::{output_change, test_output_states, OutputChange, OutputDevice};
+
+    /// A device with a limited number of crtcs and no plane rotation.
+    struct MockDevice {
+        crtcs: usize,
+        tested: RefCell<Vec<Vec<String>>>,
+    }
+
+    impl MockDevice {
+        fn new(crtcs: usize) -> Self {
+            Self {
+                crtcs,
+                tested: Default::default(),
+            }
+        }
+    }
+
+    impl OutputDevice for MockDevice {
+        fn test_outputs(&self, states: &[(&OutputHead, &OutputState)]) -> Result<()> {
+            self.tested.borrow_mut().push(
+                states
+                    .iter()
+                    .map(|(head, _)| head.name().to_string())
+                    .collect(),
+            );
+            let enabled = states.iter().filter(|(_, state)| state.enabled).count();
+            if enabled > self.crtcs {
+                bail!("not enough crtcs");
+            }
+            if states
+                .iter()
+                .any(|(_, state)| state.transform != OutputTransform::Normal)
+            {
+                bail!("the primary plane cannot be rotated");
+            }
+            Ok(())
+        }
+    }
+
+    fn head(connector: &str) -> OutputHead {
+        OutputHead {
+            identity: OutputIdentity {
+                connector: connector.to_string(),
+                ..default()
+            },
+            physical_size: IVec2::new(300, 200),
+            modes: vec![OutputMode::new(1920, 1080, 60000)],
+            preferred_mode: Some(0),
+            vrr_capable: true,
+            vrr_range: None,
+            hdr_capable: false,
+            state: default(),
+        }
+    }
+
+    fn state(x: i32) -> OutputState {
+        OutputState {
+            mode: OutputMode::new(1920, 1080, 60000),
+            position: IVec2::new(x, 0),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn test_test_output_states() {
+        let (edp, dp, hdmi) = (head("eDP-1"), head("DP-1"), head("HDMI-A-1"));
+        let integrated = MockDevice::new(1);
+        let discrete = MockDevice::new(2);
+        let (left, middle, right) = (state(0), state(1920), state(3840));
+
+        test_output_states(&[
+            (&integrated, vec![(&edp, &left)]),
+            (&discrete, vec![(&dp, &middle), (&hdmi, &right)]),
+        ])
+        .unwrap();
+        assert_eq!(integrated.tested.borrow().len(), 1);
+        assert_eq!(discrete.tested.borrow()[0], vec!["DP-1", "HDMI-A-1"]);
+
+        let result = test_output_states(&[(&integrated, vec![(&edp, &left), (&dp, &middle)])]);
+        assert!(matches!(result, Err(OutputConfigError::Rejected(_))));
+
+        let rotated = OutputState {
+            transform: OutputTransform::Rotate90,
+            ..right.clone()
+        };
+        let result = test_output_states(&[(&discrete, vec![(&dp, &middle), (&hdmi, &rotated)])]);
+        assert!(matches!(result, Err(OutputConfigError::Rejected(_))));
+
+        let overlapping = state(100);
+        let result =
+            test_output_states(&[(&discrete, vec![(&dp, &middle), (&hdmi, &overlapping)])]);
+        assert!(matches!(result, Err(OutputConfigError::Overlapping(..))));
+        assert_eq!(
+            discrete.tested.borrow().len(),
+            2,
+            "invalid states are not sent to the device"
+        );
+    }
+
+    #[test]
+    fn test_output_change() {
+        let old = state(0);
+        assert_eq!(output_change(&old, &old), OutputChange::Unchanged);
+        assert_eq!(output_change(&old, &state(100)), OutputChange::Move);
+        let scaled = OutputState {
+            scale: 1.5,
+            ..old.clone()
+        };
+        assert_eq!(output_change(&old, &scaled), OutputChange::Move);
+        let vrr = OutputState {
+            vrr: VrrPolicy::Always,
+            ..old.clone()
+        };
+        assert_eq!(output_change(&old, &vrr), OutputChange::Move);
+        let disabled = OutputState {
+            enabled: false,
+            ..old.clone()
+        };
+        assert_eq!(output_change(&old, &disabled), OutputChange::Modeset);
+        let flipped = OutputState {
+            transform: OutputTransform::Flipped,
+            ..old.clone()
+        };
+        assert_eq!(output_change(&old, &flipped), OutputChange::Modeset);
+    }
+}
@@ -1,6 +1,9 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
 use bevy::prelude::Rect;
 use drm::control::{crtc, framebuffer, plane, property::Value, Device, PlaneType};
+use drm_fourcc::DrmFormat;
 
 use super::{surface::DrmTransform, DrmDevice};
 use crate::failure::DWayTTYError::*;
@@ -18,6 +21,7 @@ pub struct PlaneInfo {
     pub handle: plane::Handle,
     pub type_: PlaneType,
     pub zpos: Option<i32>,
+    pub formats: HashSet<DrmFormat>,
 }
 
 #[derive(Clone, Debug)]
@@ -61,6 +65,7 @@ impl Planes {
                     handle: plane_handle,
                     type_: plane_type,
                     zpos,
+                    formats: drm.formats(plane_handle).unwrap_or_default(),
                 };
                 match plane_type {
                     PlaneType::Overlay => {
@@ -0,0 +1,37 @@
+//! Power management of the outputs.
+
+use bevy::{prelude::*, window::RequestRedraw};
+use dway_util::output::{OutputHead, OutputPower};
+
+use super::{surface::DrmSurface, DrmDevice};
+
+pub fn apply_output_power(
+    mut output_query: Query<
+        (&DrmSurface, &mut OutputPower, &ChildOf, &OutputHead),
+        Changed<OutputPower>,
+    >,
+    drm_query: Query<&DrmDevice>,
+    mut redraw: MessageWriter<RequestRedraw>,
+) {
+    for (surface, mut power, child_of, head) in &mut output_query {
+        let Ok(drm) = drm_query.get(child_of.parent()) else {
+            continue;
+        };
+        if surface.power() == *power {
+            continue;
+        }
+        match surface.set_power(drm, *power) {
+            Ok(()) => {
+                info!(output = head.name(), "power {:?}", *power);
+                if power.is_on() {
+                    redraw.write(RequestRedraw);
+                }
+            }
+            Err(e) => {
+                error!(output = head.name(), "failed to set power: {e}");
+                // report the mode the output is still in
+                *power.bypass_change_detection() = surface.power();
+            }
+        }
+    }
+}
@@ -0,0 +1,480 @@
+use std::os::fd::AsFd;
+
+use anyhow::{anyhow, Result};
+use bevy::{ecs::relationship::Relationship as _, platform::collections::HashMap, prelude::*};
+use drm::{
+    buffer::{self, PlanarBuffer},
+    control::{framebuffer, plane, AtomicCommitFlags, Device, FbCmd2Flags},
+};
+use drm_fourcc::{DrmFourcc, DrmModifier};
+use dway_util::{
+    output::OutputTransform,
+    scanout::{ScanoutBuffer, ScanoutCandidate, ScanoutCandidates, ScanoutFormats, ScanoutState},
+};
+
+use super::{
+    gpu::GpuManager,
+    planes::{PlaneConfig, PlaneInfo, Planes},
+    surface::{create_request, DrmSurface, DrmTransform, SurfaceInner, SurfaceState},
+    DrmDevice, DrmDeviceState,
+};
+
+/// The client buffers attached to the planes of a surface.
+#[derive(Debug, Default)]
+pub struct ScanoutPlan {
+    /// The window on the primary plane
+    pub primary: Option<(Entity, PlaneConfig)>,
+    /// The windows on overlay planes
+    pub overlays: Vec<(Entity, plane::Handle, PlaneConfig)>,
+    /// The framebuffers imported from client buffers, keyed by the buffer entity
+    pub framebuffers: HashMap<Entity, framebuffer::Handle>,
+    /// The framebuffers of destroyed buffers waiting to be removed
+    pub retired: Vec<framebuffer::Handle>,
+}
+
+impl ScanoutPlan {
+    pub fn state(&self) -> ScanoutState {
+        ScanoutState {
+            primary: self.primary.as_ref().map(|(window, _)| *window),
+            overlays: self.overlays.iter().map(|(window, ..)| *window).collect(),
+        }
+    }
+
+    fn uses(&self, framebuffer: framebuffer::Handle) -> bool {
+        self.primary
+            .iter()
+            .map(|(_, config)| config)
+            .chain(self.overlays.iter().map(|(_, _, config)| config))
+            .any(|config| config.framebuffer == framebuffer)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaneAssignment {
+    /// The index in the candidate list
+    pub candidate: usize,
+    pub plane: plane::Handle,
+    pub primary: bool,
+}
+
+/// Assign the candidates, from top to bottom, to planes.
+///
+/// A candidate covering the whole output replaces the composited image on the primary plane,
+/// the others go to the overlay planes above it. It stops at the first candidate without a
+/// compatible plane since the candidates below would be drawn over the composited one.
+///
+/// The compositor only offers the fullscreen window of an output for now, an overlay plane shows
+/// it when the primary plane can't scan out its format.
+pub fn assign_planes(
+    candidates: &[ScanoutCandidate],
+    planes: &Planes,
+    output_size: IVec2,
+) -> Vec<PlaneAssignment> {
+    let mut overlays: Vec<&PlaneInfo> = planes
+        .overlay
+        .iter()
+        .filter(|plane| match (plane.zpos, planes.primary.zpos) {
+            (Some(zpos), Some(primary_zpos)) => zpos > primary_zpos,
+            _ => true,
+        })
+        .collect();
+    overlays.sort_by_key(|plane| std::cmp::Reverse(plane.zpos));
+
+    let mut assignments = Vec::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        let fullscreen = candidate.dest == IRect::from_corners(IVec2::ZERO, output_size);
+        if fullscreen
+            && candidate
+                .buffer
+                .is_supported_by(planes.primary.formats.iter())
+        {
+            assignments.push(PlaneAssignment {
+                candidate: index,
+                plane: planes.primary.handle,
+                primary: true,
+            });
+            break;
+        }
+        let Some(position) = overlays
+            .iter()
+            .position(|plane| candidate.buffer.is_supported_by(plane.formats.iter()))
+        else {
+            break;
+        };
+        // the planes above the chosen one would show the candidates below it on top
+        let plane = overlays[position];
+        overlays.drain(..=position);
+        assignments.push(PlaneAssignment {
+            candidate: index,
+            plane: plane.handle,
+            primary: false,
+        });
+    }
+    assignments
+}
+
+struct DmabufImport<'l> {
+    buffer: &'l ScanoutBuffer,
+    handles: [Option<buffer::Handle>; 4],
+}
+
+impl PlanarBuffer for DmabufImport<'_> {
+    fn size(&self) -> (u32, u32) {
+        (self.buffer.size.x as u32, self.buffer.size.y as u32)
+    }
+
+    fn format(&self) -> DrmFourcc {
+        self.buffer.format.code
+    }
+
+    fn modifier(&self) -> Option<DrmModifier> {
+        Some(self.buffer.format.modifier).filter(|modifier| *modifier != DrmModifier::Invalid)
+    }
+
+    fn pitches(&self) -> [u32; 4] {
+        let mut pitches = [0; 4];
+        for (pitch, plane) in pitches.iter_mut().zip(self.buffer.planes.iter()) {
+            *pitch = plane.stride;
+        }
+        pitches
+    }
+
+    fn handles(&self) -> [Option<buffer::Handle>; 4] {
+        self.handles
+    }
+
+    fn offsets(&self) -> [u32; 4] {
+        let mut offsets = [0; 4];
+        for (offset, plane) in offsets.iter_mut().zip(self.buffer.planes.iter()) {
+            *offset = plane.offset;
+        }
+        offsets
+    }
+}
+
+pub fn import_dmabuf(drm: &DrmDevice, buffer: &ScanoutBuffer) -> Result<framebuffer::Handle> {
+    if buffer.planes.is_empty() || buffer.planes.len() > 4 {
+        return Err(anyhow!("invalid plane count: {}", buffer.planes.len()));
+    }
+    let mut handles = [None; 4];
+    let result = (|| {
+        for (handle, plane) in handles.iter_mut().zip(buffer.planes.iter()) {
+            *handle = Some(drm.prime_fd_to_buffer(plane.fd.as_fd())?);
+        }
+        let flags = if buffer.format.modifier == DrmModifier::Invalid {
+            FbCmd2Flags::empty()
+        } else {
+            FbCmd2Flags::MODIFIERS
+        };
+        Ok(drm.add_planar_framebuffer(&DmabufImport { buffer, handles }, flags)?)
+    })();
+    // the framebuffer holds its own reference to the gem objects
+    let mut closed = Vec::new();
+    for handle in handles.into_iter().flatten() {
+        if !closed.contains(&handle) {
+            let _ = drm.close_buffer(handle);
+            closed.push(handle);
+        }
+    }
+    result
+}
+
+fn test_plan(surface: &SurfaceInner, drm: &DrmDevice) -> Result<()> {
+    let drm_guard = drm.inner.lock().unwrap();
+    let DrmDeviceState::Atomic { props, .. } = &drm_guard.states else {
+        return Err(anyhow!("legacy drm device"));
+    };
+    let req = create_request(
+        surface,
+        surface.connector,
+        &surface.plane_configs(surface.last_framebuffer),
+        props,
+    )?;
+    drm.atomic_commit(
+        AtomicCommitFlags::TEST_ONLY | AtomicCommitFlags::ALLOW_MODESET,
+        req,
+    )?;
+    Ok(())
+}
+
+fn update_plan(
+    surface: &mut SurfaceInner,
+    drm: &DrmDevice,
+    candidates: &[ScanoutCandidate],
+    buffer_query: &Query<(), With<ScanoutBuffer>>,
+) {
+    let ScanoutPlan {
+        primary,
+        overlays,
+        mut framebuffers,
+        retired,
+    } = std::mem::take(&mut surface.scanout);
+    let showing = ScanoutPlan {
+        primary,
+        overlays,
+        ..Default::default()
+    };
+    // the framebuffers of destroyed buffers are removed a frame later, when the render world
+    // has committed the new plan, since removing a framebuffer disables the planes showing it
+    let mut pending_retire = Vec::new();
+    for framebuffer in retired {
+        if showing.uses(framebuffer) {
+            pending_retire.push(framebuffer);
+        } else {
+            let _ = drm.destroy_framebuffer(framebuffer);
+        }
+    }
+    framebuffers.retain(|buffer_entity, framebuffer| {
+        let alive = buffer_query.contains(*buffer_entity);
+        if !alive {
+            pending_retire.push(*framebuffer);
+        }
+        alive
+    });
+
+    let supported = matches!(surface.state, SurfaceState::Atomic { .. })
+        && surface.output_transform == OutputTransform::Normal;
+    let mut assignments = if supported {
+        assign_planes(candidates, &surface.planes, surface.size())
+    } else {
+        vec![]
+    };
+
+    let mut plan = ScanoutPlan {
+        framebuffers,
+        retired: pending_retire,
+        ..Default::default()
+    };
+    let mut configs = Vec::new();
+    for assignment in &assignments {
+        let candidate = &candidates[assignment.candidate];
+        let framebuffer = match plan.framebuffers.get(&candidate.buffer_entity) {
+            Some(framebuffer) => *framebuffer,
+            None => match import_dmabuf(drm, &candidate.buffer) {
+                Ok(framebuffer) => {
+                    plan.framebuffers
+                        .insert(candidate.buffer_entity, framebuffer);
+                    framebuffer
+                }
+                Err(e) => {
+                    debug!("failed to import dmabuf of {:?}: {e}", candidate.window);
+                    break;
+                }
+            },
+        };
+        configs.push(PlaneConfig {
+            src: candidate.src,
+            dest: candidate.dest.as_rect(),
+            transform: DrmTransform::NORMAL,
+            framebuffer,
+        });
+    }
+    assignments.truncate(configs.len());
+
+    surface.scanout = plan;
+    while !assignments.is_empty() {
+        surface.scanout.primary = None;
+        surface.scanout.overlays.clear();
+        for (assignment, config) in assignments.iter().zip(configs.iter()) {
+            let window = candidates[assignment.candidate].window;
+            if assignment.primary {
+                surface.scanout.primary = Some((window, config.clone()));
+            } else {
+                surface
+                    .scanout
+                    .overlays
+                    .push((window, assignment.plane, config.clone()));
+            }
+        }
+        if surface.scanout.primary.is_none() && surface.last_framebuffer.is_none() {
+            // nothing has been composited to show below the overlays yet
+        } else {
+            match test_plan(surface, drm) {
+                Ok(()) => return,
+                Err(e) => debug!("test commit of scanout failed: {e}"),
+            }
+        }
+        assignments.pop();
+        configs.pop();
+    }
+    surface.scanout.primary = None;
+    surface.scanout.overlays.clear();
+}
+
+/// Attach the buffers of the scanout candidates to planes, or fall back to composition.
+pub fn update_scanout(
+    mut surface_query: Query<(
+        Entity,
+        &DrmSurface,
+        &ChildOf,
+        Option<&ScanoutCandidates>,
+        Option<&mut ScanoutState>,
+    )>,
+    drm_query: Query<&DrmDevice>,
+    buffer_query: Query<(), With<ScanoutBuffer>>,
+    gpu_manager: Res<GpuManager>,
+    mut commands: Commands,
+) {
+    for (entity, surface, parent, candidates, scanout_state) in surface_query.iter_mut() {
+        let Ok(drm) = drm_query.get(parent.get()) else {
+            continue;
+        };
+        if drm.is_paused() || !surface.power().is_on() {
+            continue;
+        }
+        // client buffers are allocated for the primary gpu, other gpus may not import them
+        let on_primary_gpu = gpu_manager.primary_gpu == Some(parent.get());
+        let state = {
+            let mut surface_guard = surface.inner.lock().unwrap();
+            let candidates = candidates
+                .filter(|_| on_primary_gpu)
+                .map(|c| &c.0[..])
+                .unwrap_or_default();
+            update_plan(&mut surface_guard, drm, candidates, &buffer_query);
+            surface_guard.scanout.state()
+        };
+        match scanout_state {
+            Some(mut scanout_state) => {
+                scanout_state.set_if_neq(state);
+            }
+            None => {
+                commands.entity(entity).insert(state);
+            }
+        }
+    }
+}
+
+/// Collect the formats which the planes of the outputs of the primary gpu can scan out.
+pub fn update_scanout_formats(
+    surface_query: Query<(&DrmSurface, &ChildOf)>,
+    changed_query: Query<(), Changed<DrmSurface>>,
+    mut removed: RemovedComponents<DrmSurface>,
+    drm_query: Query<&DrmDevice>,
+    gpu_manager: Res<GpuManager>,
+    mut scanout_formats: ResMut<ScanoutFormats>,
+) {
+    if changed_query.is_empty() && removed.read().count() == 0 && !gpu_manager.is_changed() {
+        return;
+    }
+    let mut device = None;
+    let mut formats = Vec::new();
+    for (surface, parent) in &surface_query {
+        if gpu_manager.primary_gpu != Some(parent.get()) {
+            continue;
+        }
+        let Ok(drm) = drm_query.get(parent.get()) else {
+            continue;
+        };
+        device = Some(drm.node().device);
+        let surface_guard = surface.inner.lock().unwrap();
+        let planes = &surface_guard.planes;
+        for plane in std::iter::once(&planes.primary).chain(planes.overlay.iter()) {
+            formats.extend(plane.formats.iter().copied());
+        }
+    }
+    formats.sort_by_key(|format| (format.code as u32, u64::from(format.modifier)));
+    formats.dedup();
+    scanout_formats.set_if_neq(ScanoutFormats {
+        device: device.map(|device| device as u64),
+        formats,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, sync::Arc};
+
+    use drm::control::PlaneType;
+    use drm_fourcc::DrmFormat;
+
+    use super::*;
+
+    fn plane(id: u32, type_: PlaneType, zpos: i32, formats: &[DrmFormat]) -> PlaneInfo {
+        PlaneInfo {
+            handle: drm::control::from_u32(id).unwrap(),
+            type_,
+            zpos: Some(zpos),
+            formats: formats.iter().copied().collect::<HashSet<_>>(),
+        }
+    }
+
+    fn candidate(world: &mut World, format: DrmFormat, dest: IRect) -> ScanoutCandidate {
+        ScanoutCandidate {
+            window: world.spawn_empty().id(),
+            buffer_entity: world.spawn_empty().id(),
+            buffer: ScanoutBuffer {
+                size: dest.size(),
+                format,
+                planes: Arc::new(vec![]),
+            },
+            src: Rect::from_corners(Vec2::ZERO, dest.size().as_vec2()),
+            dest,
+        }
+    }
+
+    #[test]
+    fn test_assign_planes() {
+        let mut world = World::new();
+        let world = &mut world;
+        let xrgb = DrmFormat {
+            code: DrmFourcc::Xrgb8888,
+            modifier: DrmModifier::Linear,
+        };
+        let nv12 = DrmFormat {
+            code: DrmFourcc::Nv12,
+            modifier: DrmModifier::Linear,
+        };
+        let planes = Planes {
+            primary: plane(1, PlaneType::Primary, 0, &[xrgb]),
+            cursor: None,
+            overlay: vec![
+                plane(2, PlaneType::Overlay, 1, &[xrgb, nv12]),
+                plane(3, PlaneType::Overlay, 2, &[xrgb]),
+            ],
+        };
+        let size = IVec2::new(1920, 1080);
+        let fullscreen = IRect::from_corners(IVec2::ZERO, size);
+        let small = IRect::new(100, 100, 740, 580);
+
+        let assignments = assign_planes(&[candidate(world, xrgb, fullscreen)], &planes, size);
+        assert_eq!(assignments.len(), 1);
+        assert!(assignments[0].primary);
+
+        // the primary plane can not show nv12, the overlay below can
+        let assignments = assign_planes(&[candidate(world, nv12, fullscreen)], &planes, size);
+        assert_eq!(assignments.len(), 1);
+        assert!(!assignments[0].primary);
+        assert_eq!(assignments[0].plane, planes.overlay[0].handle);
+
+        // the topmost candidate gets the highest overlay
+        let assignments = assign_planes(
+            &[candidate(world, xrgb, small), candidate(world, xrgb, fullscreen)],
+            &planes,
+            size,
+        );
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(assignments[0].plane, planes.overlay[1].handle);
+        assert!(assignments[1].primary);
+
+        // nothing below a composited candidate is assigned
+        let yuv = DrmFormat {
+            code: DrmFourcc::Yuyv,
+            modifier: DrmModifier::Linear,
+        };
+        let assignments = assign_planes(
+            &[candidate(world, yuv, small), candidate(world, xrgb, fullscreen)],
+            &planes,
+            size,
+        );
+        assert!(assignments.is_empty());
+
+        // the candidate below one on the lowest overlay can't take the overlay above it
+        let assignments = assign_planes(
+            &[candidate(world, nv12, small), candidate(world, xrgb, small)],
+            &planes,
+            size,
+        );
+        assert_eq!(assignments.len(), 1);
+        assert_eq!(assignments[0].plane, planes.overlay[0].handle);
+    }
+}
@@ -1,6 +1,8 @@
 use std::{
     collections::{LinkedList, VecDeque},
+    os::fd::AsFd,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use anyhow::{anyhow, bail, Result};
@@ -8,18 +10,29 @@ use bevy::prelude::*;
 use drm::{
     control::{
         atomic::AtomicModeReq,
-        connector, crtc, plane,
+        connector, crtc, framebuffer, plane,
         property::{self, Value},
         AtomicCommitFlags, Device, Mode, PageFlipEvent,
     },
     Device as drm_device, VblankWaitFlags,
 };
 use drm_fourcc::DrmFormat;
+use dway_util::{
+    color::ImageDescription,
+    gamma::GammaLut,
+    output::{OutputPower, OutputState, OutputTransform},
+};
 use measure_time::debug_time;
 use tracing::{span, Level};
 use wgpu::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages};
 
-use super::{connectors::Connector, planes::PlaneConfig, DrmDevice, DrmDeviceFd, PropMap};
+use super::{
+    connectors::Connector,
+    dumb::DumbFramebuffer,
+    gamma::color_lut_blob_data,
+    hdr::{colorspace_value, hdr_output_metadata_blob_data},
+    planes::PlaneConfig, scanout::ScanoutPlan, DrmDevice, DrmDeviceFd, PropMap,
+};
 use crate::{
     drm::{planes::Planes, DrmDeviceState},
     failure::DWayTTYError::*,
@@ -45,6 +58,22 @@ impl Default for DrmTransform {
     }
 }
 
+impl From<OutputTransform> for DrmTransform {
+    fn from(value: OutputTransform) -> Self {
+        let rotation = match value.rotation() {
+            90 => Self::ROTATE_90,
+            180 => Self::ROTATE_180,
+            270 => Self::ROTATE_270,
+            _ => Self::ROTATE_0,
+        };
+        if value.is_flipped() {
+            rotation | Self::REFLECT_X
+        } else {
+            rotation
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SurfaceState {
     Atomic { props: PropMap },
@@ -71,6 +100,7 @@ pub struct SurfaceInner {
     pub(crate) mode_blob: Value<'static>,
     pub(crate) planes: Planes,
     pub(crate) transform: DrmTransform,
+    pub(crate) output_transform: OutputTransform,
     pub(crate) formats: Vec<DrmFormat>,
     pub(crate) connector: connector::Handle,
 
@@ -78,6 +108,24 @@ pub struct SurfaceInner {
     pub(crate) commited: LinkedList<GbmBuffer>,
     pub(crate) showing: Option<GbmBuffer>,
     pub(crate) available: VecDeque<GbmBuffer>,
+    /// The composited framebuffer last commited
+    pub(crate) last_framebuffer: Option<framebuffer::Handle>,
+    pub(crate) scanout: ScanoutPlan,
+    /// The config of the cursor plane, carried by every commit
+    pub(crate) cursor: Option<PlaneConfig>,
+    /// The shortest interval between two commits while the output runs with a variable refresh
+    /// rate, commits are not aligned to vblanks then but paced by the runner
+    pub(crate) vrr: Option<Duration>,
+    /// The number of entries of the gamma lut of the crtc
+    pub(crate) gamma_size: Option<usize>,
+    /// The `GAMMA_LUT` blob, the crtc shows the colors unchanged without it
+    pub(crate) gamma: Option<u64>,
+    /// The crtc is inactive while the output is powered off, and nothing is commited
+    pub(crate) power: OutputPower,
+    /// The `HDR_OUTPUT_METADATA` blob of the connector, the monitor runs in SDR without it
+    pub(crate) hdr_metadata: Option<u64>,
+    /// The raw value of the `Colorspace` property of the connector
+    pub(crate) colorspace: u64,
 }
 
 impl SurfaceInner {
@@ -101,6 +149,46 @@ impl SurfaceInner {
         let size = self.mode.size();
         IVec2::new(size.0 as i32, size.1 as i32)
     }
+
+    /// The size of the rendered image, which is rotated by the primary plane.
+    pub fn image_size(&self) -> IVec2 {
+        self.output_transform.apply(self.size())
+    }
+
+    /// The configs of the primary and overlay planes, with the composited framebuffer on the
+    /// primary plane unless a client buffer is scanned out on it.
+    pub fn plane_configs(
+        &self,
+        composited: Option<framebuffer::Handle>,
+    ) -> Vec<(plane::Handle, Option<PlaneConfig>)> {
+        let primary = match &self.scanout.primary {
+            Some((_, config)) => Some(config.clone()),
+            None => composited.map(|framebuffer| PlaneConfig {
+                src: Rect::from_corners(Vec2::default(), self.image_size().as_vec2()),
+                dest: Rect::from_corners(Vec2::default(), self.size().as_vec2()),
+                transform: self.transform,
+                framebuffer,
+            }),
+        };
+        let mut planes = vec![(self.planes.primary.handle, primary)];
+        planes.extend(self.planes.overlay.iter().map(|overlay| {
+            let config = self
+                .scanout
+                .overlays
+                .iter()
+                .find(|(_, handle, _)| *handle == overlay.handle)
+                .map(|(_, _, config)| config.clone());
+            (overlay.handle, config)
+        }));
+        if let Some(cursor) = &self.planes.cursor {
+            planes.push((cursor.handle, self.cursor.clone()));
+        }
+        planes
+    }
+
+    pub fn is_direct_scanout(&self) -> bool {
+        self.scanout.primary.is_some()
+    }
 }
 
 #[derive(Component, Clone, Debug)]
@@ -110,7 +198,12 @@ pub struct DrmSurface {
 }
 
 impl DrmSurface {
-    pub fn new(drm: &DrmDevice, connector: &Connector, images: &mut Assets<Image>) -> Result<Self> {
+    pub fn new(
+        drm: &DrmDevice,
+        connector: &Connector,
+        output_state: &OutputState,
+        images: &mut Assets<Image>,
+    ) -> Result<Self> {
         let crtc = drm.alloc_crtc(&connector.info)?;
         let mut planes = Planes::new(&crtc, drm)?;
         let plane_info = drm.get_plane(planes.primary.handle)?;
@@ -127,14 +220,21 @@ impl DrmSurface {
             );
         }
 
-        let crtc_data = drm.fd.get_crtc(crtc)?;
-        let mode = crtc_data.mode().unwrap_or(connector.mode);
+        let mode = connector
+            .find_mode(&output_state.mode)
+            .unwrap_or(connector.mode);
+        let output_transform = output_state.transform;
 
         let state = SurfaceState::new(drm)?;
         let size = mode.size();
-        let image = images.add(create_image(IVec2::new(size.0 as i32, size.1 as i32)));
+        let image = images.add(create_image(
+            output_transform.apply(IVec2::new(size.0 as i32, size.1 as i32)),
+        ));
         let mode_blob = drm.create_property_blob(&mode)?;
         let formats = drm.formats(plane_info.handle())?;
+        let gamma_size = drm
+            .try_with_prop(crtc, "GAMMA_LUT_SIZE", |_, value| Ok(value as usize))?
+            .filter(|size| *size > 0);
 
         let driver = drm.get_driver()?;
         if driver
@@ -162,9 +262,19 @@ impl DrmSurface {
                 mode,
                 planes,
                 formats: formats.into_iter().collect(),
-                transform: DrmTransform::NORMAL,
+                transform: output_transform.into(),
+                output_transform,
                 mode_blob,
                 showing: None,
+                last_framebuffer: None,
+                scanout: Default::default(),
+                cursor: None,
+                vrr: None,
+                gamma_size,
+                gamma: None,
+                power: OutputPower::On,
+                hdr_metadata: None,
+                colorspace: 0,
                 connector: connector.info().handle(),
             })),
             image,
@@ -175,9 +285,193 @@ impl DrmSurface {
         self.inner.lock().unwrap().size()
     }
 
+    pub fn image_size(&self) -> IVec2 {
+        self.inner.lock().unwrap().image_size()
+    }
+
+    pub fn is_direct_scanout(&self) -> bool {
+        self.inner.lock().unwrap().is_direct_scanout()
+    }
+
+    pub fn vrr(&self) -> Option<Duration> {
+        self.inner.lock().unwrap().vrr
+    }
+
+    /// Enable variable refresh rate with the shortest interval between commits, it is applied
+    /// by the next commit.
+    pub fn set_vrr(&self, interval: Option<Duration>) {
+        self.inner.lock().unwrap().vrr = interval;
+    }
+
+    pub fn gamma_size(&self) -> Option<usize> {
+        self.inner.lock().unwrap().gamma_size
+    }
+
+    /// Replace the gamma lut of the crtc, it is applied by the next commit.
+    pub fn set_gamma(&self, drm: &DrmDevice, lut: Option<&GammaLut>) -> Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        let Some(size) = guard.gamma_size else {
+            bail!("the crtc has no gamma lut");
+        };
+        let blob = match lut {
+            Some(lut) => {
+                let mut data = color_lut_blob_data(lut, size);
+                let blob = drm_ffi::mode::create_property_blob(drm.as_fd(), &mut data)?;
+                Some(blob.blob_id as u64)
+            }
+            None => None,
+        };
+        if let Some(old) = std::mem::replace(&mut guard.gamma, blob) {
+            let _ = drm.destroy_property_blob(old);
+        }
+        Ok(())
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.inner.lock().unwrap().hdr_metadata.is_some()
+    }
+
+    /// Replace the HDR metadata and the colorimetry sent to the monitor, it is applied by the
+    /// next commit. The monitor goes back to SDR without a description.
+    pub fn set_hdr(&self, drm: &DrmDevice, description: Option<&ImageDescription>) -> Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        let colorspace_name = if description.is_some() {
+            "BT2020_RGB"
+        } else {
+            "Default"
+        };
+        let colorspace = drm
+            .try_with_prop(guard.connector, "Colorspace", |info, _| {
+                Ok(colorspace_value(&info, colorspace_name))
+            })?
+            .flatten()
+            .unwrap_or(0);
+        let blob = match description {
+            Some(description) => {
+                let mut data = hdr_output_metadata_blob_data(description);
+                let blob = drm_ffi::mode::create_property_blob(drm.as_fd(), &mut data)?;
+                Some(blob.blob_id as u64)
+            }
+            None => None,
+        };
+        if let Some(old) = std::mem::replace(&mut guard.hdr_metadata, blob) {
+            let _ = drm.destroy_property_blob(old);
+        }
+        guard.colorspace = colorspace;
+        Ok(())
+    }
+
+    pub fn power(&self) -> OutputPower {
+        self.inner.lock().unwrap().power
+    }
+
+    /// Turn the crtc off at once, or on with the next commit.
+    pub fn set_power(&self, drm: &DrmDevice, power: OutputPower) -> Result<()> {
+        let mut self_guard = self.inner.lock().unwrap();
+        if self_guard.power == power {
+            return Ok(());
+        }
+        let drm_guard = drm.inner.lock().unwrap();
+        if power == OutputPower::Off && !drm_guard.paused {
+            let DrmDeviceState::Atomic { props, .. } = &drm_guard.states else {
+                bail!("legacy drm device");
+            };
+            let mut req = AtomicModeReq::new();
+            req.add_property(
+                self_guard.crtc,
+                *props
+                    .crtc
+                    .get(&(self_guard.crtc, "ACTIVE".to_string()))
+                    .ok_or_else(|| NoSuchProperty("ACTIVE".to_string()))?,
+                Value::Boolean(false),
+            );
+            drm.atomic_commit(AtomicCommitFlags::ALLOW_MODESET, req)
+                .map_err(|e| anyhow!("failed to turn off the crtc: {e}"))?;
+        }
+        self_guard.power = power;
+        Ok(())
+    }
+
     pub fn commit_buffer(&self, drm: &DrmDevice, buffer: &GbmBuffer) -> Result<()> {
-        let self_guard = self.inner.lock().unwrap();
+        self.commit_planes(drm, Some(buffer.framebuffer))
+    }
+
+    /// Commit a composited image copied from another gpu.
+    pub fn commit_dumb_buffer(&self, drm: &DrmDevice, buffer: &DumbFramebuffer) -> Result<()> {
+        self.commit_planes(drm, Some(buffer.framebuffer))
+    }
+
+    /// Commit the client buffer scanned out on the primary plane, without a composited image.
+    pub fn commit_scanout(&self, drm: &DrmDevice) -> Result<()> {
+        self.commit_planes(drm, None)
+    }
+
+    /// Move or change the cursor plane without waiting for the next frame.
+    ///
+    /// When the commit is refused because a frame is still pending, the config is carried by
+    /// the commit of the next frame.
+    pub fn commit_cursor(
+        &self,
+        drm: &DrmDevice,
+        config: Option<PlaneConfig>,
+        test: bool,
+    ) -> Result<()> {
+        let mut self_guard = self.inner.lock().unwrap();
+        let Some(cursor_plane) = self_guard.planes.cursor.as_ref().map(|plane| plane.handle) else {
+            bail!("no cursor plane");
+        };
         let drm_guard = drm.inner.lock().unwrap();
+        if drm_guard.paused || !self_guard.power.is_on() {
+            // the config is restored with the state of the session, or carried by the commit
+            // which turns the crtc on
+            self_guard.cursor = config;
+            return Ok(());
+        }
+        let DrmDeviceState::Atomic { props, .. } = &drm_guard.states else {
+            bail!("legacy drm device");
+        };
+        let req = create_plane_request(&self_guard, &[(cursor_plane, config.clone())], props)?;
+        if test {
+            drm.atomic_commit(AtomicCommitFlags::TEST_ONLY, req.clone())
+                .map_err(|e| anyhow!("cursor plane refused the config: {e}"))?;
+        }
+        if let Err(e) = drm.atomic_commit(AtomicCommitFlags::NONBLOCK, req) {
+            debug!("defer the cursor update to the next frame: {e}");
+        }
+        drop(drm_guard);
+        self_guard.cursor = config;
+        Ok(())
+    }
+
+    fn commit_planes(
+        &self,
+        drm: &DrmDevice,
+        framebuffer: Option<framebuffer::Handle>,
+    ) -> Result<()> {
+        // with a variable refresh rate the monitor refreshes when the commit arrives, the runner
+        // delays the frames to keep them within the refresh rate range. The vblank is waited for
+        // without holding the locks.
+        let (crtc, vrr) = {
+            let self_guard = self.inner.lock().unwrap();
+            (self_guard.crtc, self_guard.vrr)
+        };
+        if vrr.is_none() && !drm.inner.lock().unwrap().paused {
+            debug_time!("wait_vblank");
+            if let Err(e) = drm.wait_vblank(
+                drm::VblankWaitTarget::Relative(1),
+                VblankWaitFlags::NEXT_ON_MISS,
+                u32::from(crtc) >> 27,
+                15,
+            ) {
+                error!("wait error: {e}");
+            };
+        }
+
+        let mut self_guard = self.inner.lock().unwrap();
+        let mut drm_guard = drm.inner.lock().unwrap();
+        if drm_guard.paused || !self_guard.power.is_on() {
+            return Ok(());
+        }
 
         match (&self_guard.state, &drm_guard.states) {
             (
@@ -186,35 +480,14 @@ impl DrmSurface {
                     props: drm_props, ..
                 },
             ) => {
-                let size = self_guard.size();
                 let req = create_request(
                     &self_guard,
                     self_guard.connector,
-                    &[(
-                        self_guard.planes.primary.handle,
-                        Some(PlaneConfig {
-                            src: Rect::from_corners(Vec2::default(), size.as_vec2()),
-                            dest: Rect::from_corners(Vec2::default(), size.as_vec2()),
-                            transform: self_guard.transform,
-                            framebuffer: buffer.framebuffer,
-                        }),
-                    )],
+                    &self_guard.plane_configs(framebuffer),
                     drm_props,
                 )?;
                 {
-                    debug_time!("wait_vblank");
-                    if let Err(e) = drm.wait_vblank(
-                        drm::VblankWaitTarget::Relative(1),
-                        VblankWaitFlags::NEXT_ON_MISS,
-                        u32::from(self_guard.crtc) >> 27,
-                        15,
-                    ) {
-                        error!("wait error: {e}");
-                    };
-                }
-                {
-                    let _span =
-                        info_span!("atomic_commit",framebuffer=?buffer.framebuffer).entered();
+                    let _span = info_span!("atomic_commit",?framebuffer).entered();
                     debug_time!("atomic_commit");
                     drm.atomic_commit(
                         AtomicCommitFlags::ALLOW_MODESET | AtomicCommitFlags::NONBLOCK,
@@ -224,11 +497,16 @@ impl DrmSurface {
 
                     debug!("commmit drm render buffer");
                 }
+                // the frame replaces the black framebuffer of an output configuration
+                drm_guard.blank_framebuffers.remove(&self_guard.crtc);
             }
             (SurfaceState::Legacy {}, DrmDeviceState::Legacy { .. }) => todo!(),
             (SurfaceState::Atomic { .. }, DrmDeviceState::Legacy { .. }) => unreachable!(),
             (SurfaceState::Legacy {}, DrmDeviceState::Atomic { .. }) => unreachable!(),
         }
+        if framebuffer.is_some() {
+            self_guard.last_framebuffer = framebuffer;
+        }
 
         Ok(())
     }
@@ -317,6 +595,55 @@ pub fn create_request(
         Boolean(true),
     );
 
+    if let Some(prop) = drm_props
+        .crtc
+        .get(&(surface.crtc, "VRR_ENABLED".to_string()))
+    {
+        req.add_property(surface.crtc, *prop, Boolean(surface.vrr.is_some()));
+    }
+
+    if let Some(prop) = drm_props
+        .crtc
+        .get(&(surface.crtc, "GAMMA_LUT".to_string()))
+    {
+        req.add_property(surface.crtc, *prop, Blob(surface.gamma.unwrap_or(0)));
+    }
+
+    if let Some(prop) = drm_props
+        .connector
+        .get(&(conn, "HDR_OUTPUT_METADATA".to_string()))
+    {
+        req.add_property(conn, *prop, Blob(surface.hdr_metadata.unwrap_or(0)));
+    }
+
+    if let Some(prop) = drm_props.connector.get(&(conn, "Colorspace".to_string())) {
+        req.add_property(conn, *prop, Unknown(surface.colorspace));
+    }
+
+    add_plane_properties(&mut req, surface, planes, drm_props)?;
+
+    Ok(req)
+}
+
+/// Create a request which only changes the given planes, without touching the mode.
+pub fn create_plane_request(
+    surface: &SurfaceInner,
+    planes: &[(plane::Handle, Option<PlaneConfig>)],
+    drm_props: &PropMap,
+) -> Result<AtomicModeReq> {
+    let mut req = AtomicModeReq::new();
+    add_plane_properties(&mut req, surface, planes, drm_props)?;
+    Ok(req)
+}
+
+fn add_plane_properties(
+    req: &mut AtomicModeReq,
+    surface: &SurfaceInner,
+    planes: &[(plane::Handle, Option<PlaneConfig>)],
+    drm_props: &PropMap,
+) -> Result<()> {
+    use property::Value::*;
+
     for (plane_handle, config) in planes {
         let plane_prop = |key: &str| {
             drm_props
@@ -415,7 +742,7 @@ pub fn create_request(
         }
     }
 
-    Ok(req)
+    Ok(())
 }
 
 pub fn print_drm_info(drm: &DrmDeviceFd) -> Result<()> {
@@ -491,7 +818,7 @@ mod test {
                         code: drm_fourcc::DrmFourcc::Rgba8888,
                         modifier: gbm::Modifier::Linear,
                     }];
-                    gbm.create_buffer(drm, surface_guard.size(), &formats, &formats)
+                    gbm.create_buffer(drm, surface_guard.image_size(), &formats, &formats)
                         .unwrap();
                 }
             },
@@ -0,0 +1,94 @@
+//! Variable refresh rate of the outputs.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use dway_util::{
+    edid::VrrRange,
+    output::{FullscreenContent, OutputHead, OutputMode, VrrActive},
+};
+
+use super::surface::DrmSurface;
+
+/// How the runner paces frames.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameSchedule {
+    /// While every output runs with a variable refresh rate, frames follow the commits of clients
+    /// and are at least this far apart instead of [`crate::DWayTTYSettings::frame_duration`], an
+    /// earlier commit is delayed to a deadline timer
+    pub vrr_interval: Option<Duration>,
+}
+
+/// The shortest interval between two frames, the mode is the highest refresh rate the monitor
+/// runs at even if the EDID reports a higher one.
+pub fn vrr_interval(range: Option<VrrRange>, mode: &OutputMode) -> Duration {
+    let mode_hz = mode.refresh as f64 / 1000.0;
+    let max_hz = match range.map(|range| range.max as f64).filter(|hz| *hz > 0.0) {
+        Some(range_hz) if mode_hz > 0.0 => range_hz.min(mode_hz),
+        Some(range_hz) => range_hz,
+        None => mode_hz,
+    };
+    if max_hz <= 0.0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(1.0 / max_hz)
+}
+
+pub fn update_vrr(
+    output_query: Query<(
+        Entity,
+        &DrmSurface,
+        &OutputHead,
+        Has<FullscreenContent>,
+        Has<VrrActive>,
+    )>,
+    mut schedule: ResMut<FrameSchedule>,
+    mut commands: Commands,
+) {
+    let mut all_active = true;
+    let mut interval = Duration::ZERO;
+    for (entity, surface, head, fullscreen, was_active) in &output_query {
+        let state = &head.state;
+        let active = head.vrr_capable && state.enabled && state.vrr.is_active(fullscreen);
+        if active {
+            let output_interval = vrr_interval(head.vrr_range, &state.mode);
+            interval = interval.max(output_interval);
+            if surface.vrr() != Some(output_interval) {
+                surface.set_vrr(Some(output_interval));
+            }
+        } else {
+            all_active = false;
+            if surface.vrr().is_some() {
+                surface.set_vrr(None);
+            }
+        }
+        if active && !was_active {
+            info!(output = head.name(), "enable variable refresh rate");
+            commands.entity(entity).insert(VrrActive);
+        } else if !active && was_active {
+            info!(output = head.name(), "disable variable refresh rate");
+            commands.entity(entity).remove::<VrrActive>();
+        }
+    }
+    let vrr_interval = (all_active && !output_query.is_empty()).then_some(interval);
+    schedule.set_if_neq(FrameSchedule { vrr_interval });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vrr_interval() {
+        let mode = OutputMode::new(2560, 1440, 144000);
+        let interval = |range| vrr_interval(range, &mode).as_micros();
+        assert_eq!(interval(None), 6944);
+        assert_eq!(interval(Some(VrrRange { min: 48, max: 144 })), 6944);
+        assert_eq!(interval(Some(VrrRange { min: 48, max: 100 })), 10000);
+        assert_eq!(interval(Some(VrrRange { min: 48, max: 240 })), 6944);
+        assert_eq!(
+            vrr_interval(None, &OutputMode::new(1920, 1080, 0)),
+            Duration::ZERO
+        );
+    }
+}
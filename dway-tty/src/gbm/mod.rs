@@ -101,4 +101,19 @@ impl GbmDevice {
             .ok_or_else(|| anyhow!("no supported format"))?;
         GbmBuffer::new(drm, buffer, size, format)
     }
+
+    /// Create a linear buffer for another gpu to draw to, which it imports as a dmabuf.
+    #[tracing::instrument(skip_all)]
+    pub fn create_shared_buffer(&self, drm: &DrmDevice, size: IVec2) -> Result<GbmBuffer> {
+        let guard = self.device.lock().unwrap();
+        let fourcc = SUPPORTED_FORMATS[0];
+        let buffer = guard.create_buffer_object_with_modifiers2(
+            size.x as u32,
+            size.y as u32,
+            fourcc,
+            [DrmModifier::Linear].into_iter(),
+            BufferObjectFlags::SCANOUT,
+        )?;
+        GbmBuffer::new(drm, buffer, size, fourcc)
+    }
 }
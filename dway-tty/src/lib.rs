@@ -10,10 +10,11 @@ use bevy::{
     prelude::*,
     window::RequestRedraw,
 };
-use drm::DrmPlugin;
+use drm::{vrr::FrameSchedule, DrmPlugin};
 use dway_util::eventloop::{EventLoopPlugin, EventLoopPluginMode, Poller, PollerRequest};
 use render::TtyRenderPlugin;
 use schedule::DWayTtySchedulePlugin;
+use seat::SeatState;
 use smart_default::SmartDefault;
 
 pub mod drm;
@@ -35,6 +36,9 @@ pub struct DWayTTYSettings {
     pub update_mode: EventLoopPluginMode,
     #[default(Duration::from_secs(0))]
     pub max_frame_duration: Duration,
+    /// How often the seat is polled while the session is paused, instead of the frame rate
+    #[default(Duration::from_millis(200))]
+    pub paused_poll_duration: Duration,
 }
 
 #[derive(Default)]
@@ -82,13 +86,32 @@ fn runner(mut app: App) -> AppExit {
 
     let rx = poller.take_recevier().unwrap();
 
+    let mut last_frame: Option<Instant> = None;
     for event in rx.iter() {
-        let start_time = Instant::now();
-
         for callback in event.commands {
             callback(app.world_mut());
         }
 
+        // with a variable refresh rate, frames follow the commits of clients. A commit arriving
+        // before the next frame is due waits for the deadline timer instead of flipping early.
+        let deadline = app
+            .world()
+            .get_resource::<FrameSchedule>()
+            .and_then(|schedule| schedule.vrr_interval)
+            .zip(last_frame)
+            .map(|(interval, last_frame)| last_frame + interval)
+            .filter(|deadline| Instant::now() < *deadline);
+        if let Some(deadline) = deadline {
+            poller.send(PollerRequest {
+                add_timer: Some(deadline),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let start_time = Instant::now();
+        last_frame = Some(start_time);
+
         app.update();
 
         let mut poller_request = PollerRequest::default();
@@ -101,12 +124,23 @@ fn runner(mut app: App) -> AppExit {
                 None
             };
         if let Some(frame) = app.world().get_resource::<DWayTTYSettings>() {
-            if redraw_events_reader
+            let redraw = redraw_events_reader
                 .read(app.world().resource())
                 .last()
-                .is_some()
-            {
-                poller_request.add_timer = Some(start_time + frame.frame_duration);
+                .is_some();
+            let paused = app
+                .world()
+                .get_non_send_resource::<SeatState>()
+                .is_some_and(|seat| !seat.is_session_active());
+            if paused {
+                poller_request.add_timer = Some(start_time + frame.paused_poll_duration);
+            } else if redraw {
+                let frame_duration = app
+                    .world()
+                    .get_resource::<FrameSchedule>()
+                    .and_then(|schedule| schedule.vrr_interval)
+                    .unwrap_or(frame.frame_duration);
+                poller_request.add_timer = Some(start_time + frame_duration);
             }
         }
         poller.send(poller_request.clone());
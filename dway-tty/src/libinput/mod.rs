@@ -10,7 +10,7 @@ use std::{
 use anyhow::Result;
 use bevy::{
     input::{
-        keyboard::{Key, KeyboardInput},
+        keyboard::{Key, KeyboardInput, NativeKey},
         mouse::{MouseButtonInput, MouseMotion, MouseWheel},
         ButtonState,
     }, math::DVec2, platform::collections::HashMap, prelude::*
@@ -26,7 +26,7 @@ use libseat::Seat;
 
 use crate::{
     libinput::convert::convert_keycode, schedule::DWayTTYSet,
-    seat::SeatState, window::relative_to_window,
+    seat::{SeatState, SessionEvent}, window::relative_to_window,
 };
 
 pub struct SeatLibinputInterface {
@@ -130,7 +130,11 @@ pub fn receive_events(
     keycode_state: Res<ButtonInput<KeyCode>>,
     mut lock_state: ResMut<KeyLockState>,
     mut pointer_state: ResMut<PointerState>,
+    mut seat: NonSendMut<SeatState>,
 ) {
+    if !seat.enable {
+        return;
+    }
     if let Err(e) = libinput.libinput.dispatch() {
         error!("libinput error: {e}");
     };
@@ -166,6 +170,14 @@ pub fn receive_events(
                         &mut lock_state,
                         &mut k.device(),
                     );
+                    if state == tablet_pad::KeyState::Pressed {
+                        if let Some(vt) = vt_switch_target(&keycode_state, key_code) {
+                            if let Err(e) = seat.switch_session(vt) {
+                                error!("failed to switch to vt {vt}: {e}");
+                            }
+                            continue;
+                        }
+                    }
                     let text = match &logical_key {
                         Key::Character(smol_str) => Some(smol_str.clone()),
                         _ => None,
@@ -268,6 +280,68 @@ pub fn receive_events(
     }
 }
 
+/// The vt to switch to for Ctrl+Alt+F1..F12.
+pub fn vt_switch_target(keycode_state: &ButtonInput<KeyCode>, key_code: KeyCode) -> Option<i32> {
+    if !keycode_state.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !keycode_state.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+    {
+        return None;
+    }
+    let vt = match key_code {
+        KeyCode::F1 => 1,
+        KeyCode::F2 => 2,
+        KeyCode::F3 => 3,
+        KeyCode::F4 => 4,
+        KeyCode::F5 => 5,
+        KeyCode::F6 => 6,
+        KeyCode::F7 => 7,
+        KeyCode::F8 => 8,
+        KeyCode::F9 => 9,
+        KeyCode::F10 => 10,
+        KeyCode::F11 => 11,
+        KeyCode::F12 => 12,
+        _ => return None,
+    };
+    Some(vt)
+}
+
+/// Release the input devices while the session is paused.
+///
+/// The keys held when switching away never see their release, so they are released here.
+pub fn on_session_event(
+    mut session_events: MessageReader<SessionEvent>,
+    mut libinput: NonSendMut<LibinputDevice>,
+    keycode_state: Res<ButtonInput<KeyCode>>,
+    windows: Query<Entity, With<Window>>,
+    mut keyboard_events: MessageWriter<KeyboardInput>,
+) {
+    for event in session_events.read() {
+        match event {
+            SessionEvent::Paused => {
+                libinput.libinput.suspend();
+                let Some(window) = windows.iter().next() else {
+                    continue;
+                };
+                for key_code in keycode_state.get_pressed() {
+                    keyboard_events.write(KeyboardInput {
+                        key_code: *key_code,
+                        logical_key: Key::Unidentified(NativeKey::Unidentified),
+                        state: ButtonState::Released,
+                        text: None,
+                        repeat: false,
+                        window,
+                    });
+                }
+            }
+            SessionEvent::Resumed => {
+                if libinput.libinput.resume().is_err() {
+                    error!("failed to resume libinput");
+                }
+            }
+        }
+    }
+}
+
 pub struct LibInputPlugin;
 impl Plugin for LibInputPlugin {
     fn build(&self, app: &mut App) {
@@ -277,7 +351,13 @@ impl Plugin for LibInputPlugin {
             let mut poller = cell.get_non_send_resource_mut::<Poller>().unwrap();
             LibinputDevice::new(&mut seat, &mut poller).unwrap()
         };
-        app.add_systems(First, receive_events.in_set(DWayTTYSet::LibinputSystem))
+        app.add_systems(
+            First,
+            (on_session_event, receive_events)
+                .chain()
+                .in_set(DWayTTYSet::LibinputSystem)
+                .after(DWayTTYSet::SeatSystem),
+        )
             .insert_non_send_resource(libinput)
             .init_resource::<ButtonInput<KeyCode>>()
             .init_resource::<KeyLockState>()
@@ -290,3 +370,20 @@ impl Plugin for LibInputPlugin {
             .add_event::<KeyboardInput>();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vt_switch_target() {
+        let mut keycode_state = ButtonInput::<KeyCode>::default();
+        assert_eq!(vt_switch_target(&keycode_state, KeyCode::F2), None);
+        keycode_state.press(KeyCode::ControlLeft);
+        assert_eq!(vt_switch_target(&keycode_state, KeyCode::F2), None);
+        keycode_state.press(KeyCode::AltRight);
+        assert_eq!(vt_switch_target(&keycode_state, KeyCode::F2), Some(2));
+        assert_eq!(vt_switch_target(&keycode_state, KeyCode::F12), Some(12));
+        assert_eq!(vt_switch_target(&keycode_state, KeyCode::KeyA), None);
+    }
+}
@@ -1,14 +1,19 @@
-use std::{os::fd::AsRawFd, ptr::null_mut};
+use std::{
+    ffi::{c_char, CStr},
+    os::fd::AsRawFd,
+    path::Path,
+    ptr::null_mut,
+};
 
 use anyhow::{anyhow, bail, Context, Result};
 use bevy::platform::collections::HashSet;
 use drm_fourcc::{DrmFormat, DrmFourcc, DrmModifier};
 use gbm::EGLImage;
 use glow::HasContext;
-use khronos_egl::{Boolean, EGLClientBuffer, EGLContext, EGLDisplay, Enum, Int};
+use khronos_egl::{Attrib, Boolean, EGLClientBuffer, EGLContext, EGLDisplay, Enum, Int};
 use measure_time::debug_time;
 use scopeguard::defer;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 use wgpu_hal::{
     api::Gles,
     gles::{Device, Texture, TextureInner},
@@ -25,7 +30,7 @@ pub fn call_gl<R>(gl: &glow::Context, f: impl FnOnce() -> R) -> Result<R, TtyRen
 
 use super::{TtyRender, TtyRenderError};
 use crate::{
-    drm::{surface::DrmSurface, DrmDevice},
+    drm::{dumb::DumbFramebuffer, surface::DrmSurface, DrmDevice, DrmNode},
     gbm::{buffer::GbmBuffer, GbmDevice},
 };
 pub type EGLInstance = khronos_egl::DynamicInstance<khronos_egl::EGL1_4>;
@@ -58,6 +63,10 @@ pub const DMA_BUF_PLANE3_MODIFIER_HI_EXT: u32 = 0x344A;
 
 pub const LINUX_DMA_BUF_EXT: u32 = 0x3270;
 
+pub const DEVICE_EXT: Int = 0x322C;
+pub const DRM_DEVICE_FILE_EXT: Int = 0x3233;
+pub const DRM_RENDER_NODE_FILE_EXT: Int = 0x3377;
+
 const PLANE_ATTR_NAMES: [(u32, u32, u32, u32, u32); 4] = [
     (
         DMA_BUF_PLANE0_FD_EXT,
@@ -89,19 +98,78 @@ const PLANE_ATTR_NAMES: [(u32, u32, u32, u32, u32); 4] = [
     ),
 ];
 
+pub enum SwapchainTarget {
+    /// A buffer of the display gpu the render gpu draws to
+    Buffer(GbmBuffer),
+    /// Buffers of another display gpu the image read back from the render gpu is copied to
+    Copy {
+        buffers: Vec<DumbFramebuffer>,
+        current: usize,
+    },
+}
+
 pub struct Swapchain {
     render_buffer: glow::Renderbuffer,
-    buffer: GbmBuffer,
+    target: SwapchainTarget,
 }
 
 pub struct Surface {
     frame_buffer: glow::Framebuffer,
     render_buffer: glow::Renderbuffer,
+    /// The pixels read back for a display gpu which can't import the image
+    pixels: Option<Vec<u8>>,
 }
 
 pub struct GlTtyRender {
     functions: GlesRenderFunctions,
     formats: Vec<DrmFormat>,
+    render_node: Option<DrmNode>,
+}
+
+impl GlTtyRender {
+    unsafe fn create_cross_device_swapchain(
+        &self,
+        gl: &glow::Context,
+        display: EGLDisplay,
+        drm: &DrmDevice,
+        gbm: &GbmDevice,
+        size: bevy::math::IVec2,
+    ) -> Result<Swapchain> {
+        let shared = gbm.create_shared_buffer(drm, size).and_then(|buffer| {
+            let render_buffer = do_create_renderbuffer(gl, &buffer, display, &self.functions)?;
+            Ok((render_buffer, buffer))
+        });
+        match shared {
+            Ok((render_buffer, buffer)) => {
+                debug!(path=?drm.path, "the render gpu draws to a buffer of the display gpu");
+                Ok(Swapchain {
+                    render_buffer,
+                    target: SwapchainTarget::Buffer(buffer),
+                })
+            }
+            Err(e) => {
+                warn!(path=?drm.path, "copy images to the display gpu with the cpu: {e}");
+                let buffers = (0..2)
+                    .map(|_| DumbFramebuffer::new(drm, size))
+                    .try_collect::<Vec<_>>()?;
+                let render_buffer = gl
+                    .create_renderbuffer()
+                    .map_err(|e| anyhow!("failed to create gl renderbuffer: {}", e))?;
+                gl.bind_renderbuffer(glow::RENDERBUFFER, Some(render_buffer));
+                call_gl(gl, || {
+                    gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, size.x, size.y)
+                })?;
+                gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+                Ok(Swapchain {
+                    render_buffer,
+                    target: SwapchainTarget::Copy {
+                        buffers,
+                        current: 0,
+                    },
+                })
+            }
+        }
+    }
 }
 
 impl TtyRender for GlTtyRender {
@@ -124,9 +192,25 @@ impl TtyRender for GlTtyRender {
             .ok_or_else(|| anyhow!("egl display is not valid"))?;
 
         let surface_guard = drm_surface.inner.lock().unwrap();
+        let cross_device = self
+            .render_node
+            .as_ref()
+            .is_some_and(|node| !node.is_same_gpu(drm.node()));
+        if cross_device {
+            let swapchain = self.create_cross_device_swapchain(
+                gl,
+                egl_display.as_ptr(),
+                drm,
+                gbm,
+                surface_guard.image_size(),
+            )?;
+            debug!("cross device swapchain created");
+            return Ok(swapchain);
+        }
+
         let buffer = gbm.create_buffer(
             drm,
-            surface_guard.size(),
+            surface_guard.image_size(),
             surface_guard.formats(),
             &self.formats,
         )?;
@@ -138,7 +222,7 @@ impl TtyRender for GlTtyRender {
 
         Ok(Swapchain {
             render_buffer,
-            buffer,
+            target: SwapchainTarget::Buffer(buffer),
         })
     }
 
@@ -160,6 +244,7 @@ impl TtyRender for GlTtyRender {
         Ok(Surface {
             frame_buffer,
             render_buffer: swapchain.render_buffer,
+            pixels: matches!(swapchain.target, SwapchainTarget::Copy { .. }).then(Vec::new),
         })
     }
 
@@ -229,6 +314,22 @@ impl TtyRender for GlTtyRender {
         })?;
         gl.enable(glow::FRAMEBUFFER_SRGB);
 
+        if let Some(pixels) = &mut surface.pixels {
+            pixels.resize((width * height * 4) as usize, 0);
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(surface.frame_buffer));
+            call_gl(gl, || {
+                gl.read_pixels(
+                    0,
+                    0,
+                    width,
+                    height,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    glow::PixelPackData::Slice(Some(pixels)),
+                )
+            })?;
+        }
+
         {
             debug_time!("wait gl operation");
             gl.flush();
@@ -252,21 +353,79 @@ impl TtyRender for GlTtyRender {
 
         let functions = GlesRenderFunctions::new(egl)?;
         let formats = get_formats(&functions, device)?;
+        let render_node = egl_context
+            .raw_display()
+            .ok_or_else(|| anyhow!("egl display is not valid"))
+            .and_then(|display| query_render_node(egl, display.as_ptr()))
+            .map_err(|e| warn!("failed to get the drm node of the render gpu: {e}"))
+            .ok();
+
+        Ok(Self {
+            functions,
+            formats,
+            render_node,
+        })
+    }
 
-        Ok(Self { functions, formats })
+    fn render_node(&self) -> Option<DrmNode> {
+        self.render_node.clone()
     }
 
     unsafe fn commit(
         &mut self,
         swapchain: &mut Self::Swapchain,
-        _surface: &mut Self::Surface,
+        surface: &mut Self::Surface,
         drm_surface: &DrmSurface,
         drm: &DrmDevice,
     ) -> Result<()> {
-        drm_surface.commit_buffer(drm, &swapchain.buffer)
+        match &mut swapchain.target {
+            SwapchainTarget::Buffer(buffer) => drm_surface.commit_buffer(drm, buffer),
+            SwapchainTarget::Copy { buffers, current } => {
+                let pixels = surface
+                    .pixels
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("the image is not read back"))?;
+                // the current buffer may still be scanned out
+                let index = (*current + 1) % buffers.len();
+                buffers[index].write_rgba(pixels)?;
+                drm_surface.commit_dumb_buffer(drm, &buffers[index])?;
+                *current = index;
+                Ok(())
+            }
+        }
     }
 }
 
+/// Find the drm node of the gpu the egl display runs on.
+pub fn query_render_node(egl: &EGLInstance, display: EGLDisplay) -> Result<DrmNode> {
+    let query_display_attrib_ext: extern "system" fn(EGLDisplay, Int, *mut Attrib) -> Boolean =
+        unsafe {
+            std::mem::transmute(
+                egl.get_proc_address("eglQueryDisplayAttribEXT")
+                    .ok_or_else(|| anyhow!("gl function eglQueryDisplayAttribEXT not exists"))?,
+            )
+        };
+    let query_device_string_ext: extern "system" fn(Attrib, Int) -> *const c_char = unsafe {
+        std::mem::transmute(
+            egl.get_proc_address("eglQueryDeviceStringEXT")
+                .ok_or_else(|| anyhow!("gl function eglQueryDeviceStringEXT not exists"))?,
+        )
+    };
+
+    let mut device: Attrib = 0;
+    call_egl_boolean(egl, || query_display_attrib_ext(display, DEVICE_EXT, &mut device))?;
+    if device == 0 {
+        bail!("the egl display has no device");
+    }
+    let path = [DRM_RENDER_NODE_FILE_EXT, DRM_DEVICE_FILE_EXT]
+        .into_iter()
+        .map(|name| query_device_string_ext(device, name))
+        .find(|path| !path.is_null())
+        .ok_or_else(|| anyhow!("the egl device has no drm node"))?;
+    let path = unsafe { CStr::from_ptr(path) }.to_str()?;
+    DrmNode::new(Path::new(path))
+}
+
 pub fn call_egl_boolean(
     egl: &EGLInstance,
     f: impl FnOnce() -> Boolean,
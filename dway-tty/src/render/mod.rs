@@ -24,7 +24,10 @@ use wgpu_hal::api::Gles;
 
 use self::gles::GlesRenderFunctions;
 use crate::{
-    drm::{connectors::Connector, surface::DrmSurface, DrmDevice, ExtractedDrmDevice},
+    drm::{
+        connectors::Connector, gpu::RenderGpu, surface::DrmSurface, DrmDevice, DrmNode,
+        ExtractedDrmDevice,
+    },
     gbm::GbmDevice,
 };
 
@@ -86,8 +89,15 @@ pub struct TtyRenderPlugin;
 impl Plugin for TtyRenderPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(SyncComponentPlugin::<DrmSurface>::default());
+        let render_gpu = app
+            .world()
+            .get_resource::<RenderGpu>()
+            .cloned()
+            .unwrap_or_default();
+        app.insert_resource(render_gpu.clone());
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
+                .insert_resource(render_gpu)
                 .init_resource::<TtyRenderState>()
                 .add_systems(ExtractSchedule, extract_drm_surfaces)
                 .add_systems(
@@ -115,6 +125,9 @@ pub trait TtyRender: Send + Sync + 'static {
     where
         Self: Sized;
 
+    /// The drm node of the gpu the renderer runs on.
+    fn render_node(&self) -> Option<DrmNode>;
+
     unsafe fn create_swapchain(
         &mut self,
         device: &<Self::Api as wgpu_hal::Api>::Device,
@@ -161,7 +174,11 @@ pub struct TtySwapchains<R: TtyRender> {
 impl<R: TtyRender> TtySwapchains<R> {
 }
 
-pub fn init_render(render_device: Res<RenderDevice>, mut commands: Commands) {
+pub fn init_render(
+    render_device: Res<RenderDevice>,
+    render_gpu: Res<RenderGpu>,
+    mut commands: Commands,
+) {
     let device = render_device.wgpu_device();
     unsafe {
         let finish = {
@@ -172,6 +189,10 @@ pub fn init_render(render_device: Res<RenderDevice>, mut commands: Commands) {
                         false
                     }
                     Ok(o) => {
+                        if let Some(node) = o.render_node() {
+                            info!(?node, "render gpu");
+                            render_gpu.set(node);
+                        }
                         commands.insert_resource(TtySwapchains {
                             swapchains: EntityHashMap::default(),
                             render: o,
@@ -204,6 +225,8 @@ pub fn commit_drm_surface<R: TtyRender>(
     let TtySwapchains::<R> {
         swapchains, render, ..
     } = &mut *state;
+    // the surfaces of reconfigured or removed outputs are gone
+    swapchains.retain(|entity, _| surface_query.contains(*entity));
 
     for (entity, extracted_drm_surface) in surface_query.iter() {
         let ExtractedDrmSurface {
@@ -217,6 +240,9 @@ pub fn commit_drm_surface<R: TtyRender>(
         };
         let _span =
             span!(Level::ERROR,"commit drm buffer",device=%drm.path.to_string_lossy()).entered();
+        if drm.is_paused() || !drm_surface.power().is_on() {
+            continue;
+        }
 
         let swapchain = match swapchains.entry(entity) {
             Entry::Occupied(occupied_entry) => occupied_entry.into_mut(),
@@ -240,6 +266,14 @@ pub fn commit_drm_surface<R: TtyRender>(
             }
         };
 
+        if drm_surface.is_direct_scanout() {
+            // a client buffer replaces the composited image
+            if let Err(e) = drm_surface.commit_scanout(drm) {
+                error!("failed to commit drm surface: {e}");
+            }
+            continue;
+        }
+
         if let Err(e) = (|| unsafe {
             let hal_device = render_device
                 .wgpu_device()
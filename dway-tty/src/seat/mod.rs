@@ -30,6 +30,13 @@ impl AsFd for DeviceFd {
     }
 }
 
+/// The session of the seat is paused when switching to another vt and resumed when switching back.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Paused,
+    Resumed,
+}
+
 #[derive(Getters)]
 #[get = "pub"]
 pub struct SeatState {
@@ -38,12 +45,19 @@ pub struct SeatState {
     pub(crate) enable: bool,
     pub(crate) devices: HashMap<RawFd, Entity>,
     pub(crate) queue: Arc<ArrayQueue<SeatEvent>>,
+    /// The session is disabled but the devices are not released yet
+    pub(crate) pending_disable: bool,
 }
 
 impl SeatState {
+    /// Whether the session is active, the devices can't be used while it is paused.
+    pub fn is_session_active(&self) -> bool {
+        self.enable
+    }
+
     #[tracing::instrument(skip_all)]
     pub fn new() -> Result<Self> {
-        let queue = Arc::new(ArrayQueue::<SeatEvent>::new(1));
+        let queue = Arc::new(ArrayQueue::<SeatEvent>::new(8));
         let tx = queue.clone();
         let mut seat = Seat::open(move |seat, event| {
             debug!(seat = seat.name(), "seat event: {event:?}");
@@ -61,23 +75,57 @@ impl SeatState {
             enable: active,
             devices: Default::default(),
             queue,
+            pending_disable: false,
         })
     }
 
+    pub fn switch_session(&mut self, vt: i32) -> Result<()> {
+        info!("switch to vt {vt}");
+        self.seat.lock().unwrap().switch_session(vt)?;
+        Ok(())
+    }
+
     pub fn open_device(&mut self, path: &PathBuf) -> Result<DeviceFd> {
         let device = self.seat.lock().unwrap().open_device(path)?;
         Ok(DeviceFd::new(device))
     }
 }
 
-pub fn process_seat_event(mut seat: NonSendMut<SeatState>) {
+pub fn process_seat_event(
+    mut seat: NonSendMut<SeatState>,
+    mut session_events: MessageWriter<SessionEvent>,
+) {
     if let Err(e) = seat.seat.lock().unwrap().dispatch(0) {
         error!("seat error: {e}");
     };
     while let Some(event) = seat.queue.clone().pop() {
         match event {
-            SeatEvent::Enable => seat.enable = true,
-            SeatEvent::Disable => seat.enable = false,
+            SeatEvent::Enable => {
+                if !seat.enable {
+                    info!("session resumed");
+                    seat.enable = true;
+                    seat.pending_disable = false;
+                    session_events.write(SessionEvent::Resumed);
+                }
+            }
+            SeatEvent::Disable => {
+                if seat.enable {
+                    info!("session paused");
+                    seat.enable = false;
+                    seat.pending_disable = true;
+                    session_events.write(SessionEvent::Paused);
+                }
+            }
+        }
+    }
+}
+
+/// Tell the seat the devices are released, after the drm and input devices handled the pause.
+pub fn acknowledge_seat_disable(mut seat: NonSendMut<SeatState>) {
+    if seat.pending_disable {
+        seat.pending_disable = false;
+        if let Err(e) = seat.seat.lock().unwrap().disable() {
+            error!("failed to disable seat: {e}");
         }
     }
 }
@@ -86,7 +134,14 @@ pub struct SeatPlugin;
 impl Plugin for SeatPlugin {
     fn build(&self, app: &mut App) {
         app.insert_non_send_resource(SeatState::new().unwrap())
-            .add_systems(First, process_seat_event.in_set(DWayTTYSet::SeatSystem));
+            .add_event::<SessionEvent>()
+            .add_systems(First, process_seat_event.in_set(DWayTTYSet::SeatSystem))
+            .add_systems(
+                First,
+                acknowledge_seat_disable
+                    .after(DWayTTYSet::DrmSystem)
+                    .after(DWayTTYSet::LibinputSystem),
+            );
     }
 }
 
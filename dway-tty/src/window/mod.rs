@@ -3,22 +3,26 @@ use bevy::{
     window::{PresentMode, WindowLevel, WindowMode, WindowResolution},
 };
 
+use dway_util::output::OutputState;
+
 use crate::drm::{connectors::Connector, surface::DrmSurface};
 
-pub fn create_window(conn: &Connector, surface: &DrmSurface) -> Window {
-    let size = surface.size();
+pub fn create_window(conn: &Connector, surface: &DrmSurface, state: &OutputState) -> Window {
+    let size = surface.image_size();
+    let logical_size = size.as_vec2() / state.scale;
     Window {
         present_mode: PresentMode::AutoVsync,
         mode: WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current),
-        position: WindowPosition::At(IVec2::default()),
-        resolution: WindowResolution::new(size.x as u32, size.y as u32),
+        position: WindowPosition::At(state.position),
+        resolution: WindowResolution::new(size.x as u32, size.y as u32)
+            .with_scale_factor_override(state.scale),
         title: conn.name.clone(),
         composite_alpha_mode: bevy::window::CompositeAlphaMode::Opaque,
         resize_constraints: WindowResizeConstraints {
-            min_width: size.x as f32,
-            min_height: size.y as f32,
-            max_width: size.x as f32,
-            max_height: size.y as f32,
+            min_width: logical_size.x,
+            min_height: logical_size.y,
+            max_width: logical_size.x,
+            max_height: logical_size.y,
         },
         resizable: false,
         decorations: false,
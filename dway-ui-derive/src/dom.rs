@@ -68,9 +68,7 @@ impl DomBundle {
         match &self {
             DomBundle::Expr { expr, .. } => {
                 let component_state = ParseCodeResult::from_expr(expr);
-                !component_state.use_state.is_empty()
-                    || !component_state.set_state.is_empty()
-                    || !component_state.use_prop.is_empty()
+                component_state.is_dynamic()
             }
             _ => false,
         }
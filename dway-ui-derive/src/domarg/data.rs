@@ -15,9 +15,7 @@ impl DomDecorator for InsertComponent {
     fn need_node_entity_field(&self) -> bool {
         if let Some(expr) = &self.expr {
             let component_state = ParseCodeResult::from_expr(expr);
-            !component_state.use_state.is_empty()
-                || !component_state.set_state.is_empty()
-                || !component_state.use_prop.is_empty()
+            component_state.is_dynamic()
         } else {
             false
         }
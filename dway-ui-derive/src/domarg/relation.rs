@@ -51,9 +51,7 @@ impl DomDecorator for Connect {
     }
     fn need_node_entity_field(&self) -> bool {
         let component_state = ParseCodeResult::from_expr(self.expr());
-        !component_state.use_state.is_empty()
-            || !component_state.set_state.is_empty()
-            || !component_state.use_prop.is_empty()
+        component_state.is_dynamic()
     }
 
     fn update_context(&self, context: &mut WidgetNodeContext) {
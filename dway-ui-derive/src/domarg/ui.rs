@@ -66,9 +66,7 @@ impl DomDecorator for Handle {
     }
     fn need_node_entity_field(&self) -> bool {
         let component_state = ParseCodeResult::from_expr(&self.expr);
-        !component_state.use_state.is_empty()
-            || !component_state.set_state.is_empty()
-            || !component_state.use_prop.is_empty()
+        component_state.is_dynamic()
     }
     fn get_component(&self, _context: &mut DomContext) -> Option<TokenStream> {
         let Self { expr, .. } = self;
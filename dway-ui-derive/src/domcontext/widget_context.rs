@@ -560,6 +560,11 @@ pub fn generate(decl: &WidgetDeclare) -> PluginBuilder {
     let mut plugin_builder = context.plugin_builder;
 
     let system_args = context.system_querys.values().cloned().collect::<Vec<_>>();
+    let theme_changed = if context.system_querys.contains_key("theme") {
+        quote!(theme.is_changed())
+    } else {
+        quote!(false)
+    };
     let world_query = context.world_query;
     let this_query = world_query.values().map(|(_, ty)| ty);
     let this_query_var = world_query.values().map(|(pat, _)| pat);
@@ -581,6 +586,7 @@ pub fn generate(decl: &WidgetDeclare) -> PluginBuilder {
             #(#system_args),*
         ) #where_clause {
             let commands = &mut __dway_ui_commands;
+            let __dway_theme_changed = #theme_changed;
             #(#before_foreach)*
             for (
                 this_entity,
@@ -14,6 +14,9 @@ pub struct ParseCodeResult {
     pub use_state: HashMap<String, Span>,
     pub use_prop: HashMap<String, Span>,
     pub set_state: HashMap<String, Span>,
+    /// the expression reads the `theme` resource and follows its changes, only
+    /// widgets declaring `@global(theme: Theme)` track them
+    pub use_theme: Option<Span>,
 }
 impl Visit<'_> for ParseCodeResult {
     fn visit_macro(&mut self, i: &'_ Macro) {
@@ -62,6 +65,9 @@ impl Visit<'_> for ParseCodeResult {
                 "prop" => {
                     self.use_prop(&i.method);
                 }
+                "theme" => {
+                    self.use_theme = Some(i.method.span());
+                }
                 "state" => {
                     self.use_state(&i.method);
                     let method_name = i.method.to_string();
@@ -89,8 +95,14 @@ impl ParseCodeResult {
     pub fn use_state(&mut self, ident: &Ident) {
         self.use_state.insert(ident.to_string(), ident.span());
     }
+    pub fn is_dynamic(&self) -> bool {
+        !self.use_state.is_empty()
+            || !self.set_state.is_empty()
+            || !self.use_prop.is_empty()
+            || self.use_theme.is_some()
+    }
     pub fn changed_bool(&self) -> BoolExpr {
-        if self.use_state.is_empty() && self.use_prop.is_empty() {
+        if self.use_state.is_empty() && self.use_prop.is_empty() && self.use_theme.is_none() {
             BoolExpr::False
         } else {
             let exprs = self
@@ -103,6 +115,10 @@ impl ParseCodeResult {
                 .chain(
                     (!self.use_prop.is_empty())
                         .then_some(quote!(__dway_prop_changed)),
+                )
+                .chain(
+                    self.use_theme
+                        .map(|span| quote_spanned!(span=>__dway_theme_changed)),
                 );
             BoolExpr::RuntimeValue(quote!(#(#exprs)||*))
         }
@@ -143,6 +159,13 @@ fn parse_expr_tokens(tokens: &TokenStream, output: &mut ParseCodeResult) {
                 ) if *base_ident == "prop" && dot_punct.as_char() == '.' => {
                     output.use_prop(member_ident);
                 }
+                (
+                    TokenTree::Ident(base_ident),
+                    TokenTree::Punct(dot_punct),
+                    TokenTree::Ident(member_ident),
+                ) if *base_ident == "theme" && dot_punct.as_char() == '.' => {
+                    output.use_theme = Some(member_ident.span());
+                }
                 _ => {}
             };
         })
@@ -44,18 +44,9 @@ fn setup(mut commands: Commands, theme: Res<Theme>) {
                     c.spawn((
                         ListViewBundle::default(),
                         TextViewFactory::new(text_font.clone(), Color::BLACK.into()),
-                        SimpleListViewModel(vec![
-                            "text view 1".to_string(),
-                            "text view 2".to_string(),
-                            "text view 3".to_string(),
-                            "text view 4".to_string(),
-                            "text view 5".to_string(),
-                            "text view 6".to_string(),
-                            "text view 7".to_string(),
-                            "text view 8".to_string(),
-                            "text view 9".to_string(),
-                            "text view 10".to_string(),
-                        ]),
+                        SimpleListViewModel(
+                            (1..=10000).map(|i| format!("text view {i}")).collect(),
+                        ),
                         ListViewLayout {
                             item_size: Vec2::new(256.0, 32.0),
                             ..Default::default()
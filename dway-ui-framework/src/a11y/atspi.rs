@@ -0,0 +1,102 @@
+use std::sync::{Arc, Mutex};
+
+use accesskit::{
+    Action, ActionData, ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler,
+    TreeUpdate,
+};
+use crossbeam_channel::{Receiver, Sender};
+
+use super::{UiAccessibilityRequest, UiAccessibilityTree};
+use crate::prelude::*;
+
+struct ActivationState {
+    tree: Arc<Mutex<Option<TreeUpdate>>>,
+}
+
+impl ActivationHandler for ActivationState {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        self.tree.lock().unwrap().clone()
+    }
+}
+
+struct ActionSender {
+    tx: Sender<ActionRequest>,
+}
+
+impl ActionHandler for ActionSender {
+    fn do_action(&mut self, request: ActionRequest) {
+        let _ = self.tx.send(request);
+    }
+}
+
+struct Deactivation;
+
+impl DeactivationHandler for Deactivation {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+/// Publishes the accessibility tree on the AT-SPI bus.
+pub struct AtspiAdapter {
+    adapter: accesskit_unix::Adapter,
+    tree: Arc<Mutex<Option<TreeUpdate>>>,
+    rx: Receiver<ActionRequest>,
+}
+
+impl Default for AtspiAdapter {
+    fn default() -> Self {
+        let tree = Arc::new(Mutex::new(None));
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut adapter = accesskit_unix::Adapter::new(
+            ActivationState { tree: tree.clone() },
+            ActionSender { tx },
+            Deactivation,
+        );
+        adapter.update_window_focus_state(true);
+        Self { adapter, tree, rx }
+    }
+}
+
+fn convert_request(request: ActionRequest) -> Option<UiAccessibilityRequest> {
+    let entity = UiAccessibilityTree::entity(request.target)?;
+    Some(match (request.action, request.data) {
+        (Action::Focus, _) => UiAccessibilityRequest::Focus(entity),
+        (Action::Click, _) => UiAccessibilityRequest::Activate(entity),
+        (Action::Increment, _) => UiAccessibilityRequest::Increment(entity),
+        (Action::Decrement, _) => UiAccessibilityRequest::Decrement(entity),
+        (Action::SetValue, Some(ActionData::NumericValue(value))) => {
+            UiAccessibilityRequest::SetValue(entity, value as f32)
+        }
+        (action, _) => {
+            debug!(?entity, "unsupported accessibility action: {action:?}");
+            return None;
+        }
+    })
+}
+
+pub fn update_atspi_adapter(
+    mut adapter: NonSendMut<AtspiAdapter>,
+    tree: Res<UiAccessibilityTree>,
+    mut requests: MessageWriter<UiAccessibilityRequest>,
+) {
+    let adapter = &mut *adapter;
+    if tree.is_changed() {
+        let update = tree.tree_update();
+        *adapter.tree.lock().unwrap() = Some(update.clone());
+        adapter.adapter.update_if_active(|| update);
+    }
+    for request in adapter.rx.try_iter() {
+        if let Some(request) = convert_request(request) {
+            requests.write(request);
+        }
+    }
+}
+
+pub struct AtspiPlugin;
+impl Plugin for AtspiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_non_send_resource::<AtspiAdapter>().add_systems(
+            PostUpdate,
+            update_atspi_adapter.after(super::update_accessibility_tree),
+        );
+    }
+}
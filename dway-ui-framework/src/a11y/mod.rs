@@ -0,0 +1,709 @@
+//! The accessibility tree of the widgets.
+//!
+//! The tree is rebuilt from the ui entities when they change. With the `atspi`
+//! feature it is exported to screen readers through AccessKit.
+
+#[cfg(feature = "atspi")]
+pub mod atspi;
+
+use accesskit::{
+    Action, Node as AccessNode, NodeId, Rect as AccessRect, Role, Toggled, Tree, TreeUpdate,
+};
+use bevy::ecs::system::SystemParam;
+
+use crate::{
+    mvvm::selection::KeyboardNavigation,
+    prelude::*,
+    theme::{StyleFlags, ThemeComponent},
+    widgets::{
+        combobox::{UiComboBox, UiComboBoxState},
+        inputbox::{UiInputBox, UiInputBoxKind, UiInputBoxState},
+        scroll::UiScroll,
+        slider::{UiSliderEventDispatcher, UiSliderEventKind},
+    },
+    UiFrameworkSystems,
+};
+
+pub const ROOT_NODE_ID: NodeId = NodeId(u64::MAX);
+
+/// Overrides what the accessibility tree reports for a node.
+///
+/// Nodes without this component are described by their widget components, plain
+/// `Text` becomes a label and other nodes are skipped.
+#[derive(Component, Debug, Clone, Default)]
+pub struct UiAccessible {
+    pub role: Option<Role>,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub focusable: bool,
+}
+
+impl UiAccessible {
+    pub fn new(role: Role) -> Self {
+        Self {
+            role: Some(role),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn focusable(mut self) -> Self {
+        self.focusable = true;
+        self
+    }
+}
+
+/// Hides a node and its descendants from the accessibility tree.
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+pub struct UiAccessibilityHidden;
+
+#[derive(Message, Debug, Clone, PartialEq, Reflect)]
+pub enum UiAccessibilityRequest {
+    Focus(Entity),
+    FocusNext,
+    FocusPrevious,
+    /// Click a button, toggle a checkbox or open a combobox.
+    Activate(Entity),
+    Increment(Entity),
+    Decrement(Entity),
+    SetValue(Entity, f32),
+}
+
+/// Presses a node for one frame and releases it, as if it was clicked.
+#[derive(Component, Debug, Default)]
+pub struct UiActivation {
+    pressed: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct UiAccessibilityTree {
+    pub nodes: Vec<(NodeId, AccessNode)>,
+    /// focusable widgets in tree order
+    pub focusable: Vec<Entity>,
+    pub focus: Option<Entity>,
+}
+
+impl UiAccessibilityTree {
+    pub fn node_id(entity: Entity) -> NodeId {
+        NodeId(entity.to_bits())
+    }
+
+    pub fn entity(node_id: NodeId) -> Option<Entity> {
+        (node_id != ROOT_NODE_ID)
+            .then(|| Entity::try_from_bits(node_id.0))
+            .flatten()
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&AccessNode> {
+        let id = Self::node_id(entity);
+        self.nodes.iter().find(|(i, _)| *i == id).map(|(_, n)| n)
+    }
+
+    pub fn tree_update(&self) -> TreeUpdate {
+        let mut tree = Tree::new(ROOT_NODE_ID);
+        tree.toolkit_name = Some("dway-ui-framework".to_string());
+        TreeUpdate {
+            nodes: self.nodes.clone(),
+            tree: Some(tree),
+            focus: self.focus.map(Self::node_id).unwrap_or(ROOT_NODE_ID),
+        }
+    }
+
+    fn step_focus(&self, current: Option<Entity>, forward: bool) -> Option<Entity> {
+        let len = self.focusable.len();
+        if len == 0 {
+            return None;
+        }
+        let index = current.and_then(|c| self.focusable.iter().position(|e| *e == c));
+        let next = match (index, forward) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        };
+        Some(self.focusable[next])
+    }
+}
+
+#[derive(SystemParam)]
+pub struct AccessibleNodeQuery<'w, 's> {
+    node_query: Query<
+        'w,
+        's,
+        (
+            &'static Node,
+            &'static ComputedNode,
+            &'static UiGlobalTransform,
+            Option<&'static Visibility>,
+            Option<&'static Children>,
+            Option<&'static UiAccessible>,
+            Has<UiAccessibilityHidden>,
+        ),
+    >,
+    text_query: Query<'w, 's, AnyOf<(&'static Text, &'static TextSpan)>>,
+    children_query: Query<'w, 's, &'static Children>,
+    button_query: Query<'w, 's, (), With<UiButton>>,
+    checkbox_query: Query<'w, 's, &'static UiCheckBoxState, With<UiCheckBox>>,
+    slider_query: Query<'w, 's, (&'static UiSlider, &'static UiSliderState)>,
+    combobox_query: Query<'w, 's, &'static UiComboBoxState, With<UiComboBox>>,
+    inputbox_query: Query<'w, 's, (&'static UiInputBox, &'static UiInputBoxState)>,
+    scroll_query: Query<'w, 's, (), With<UiScroll>>,
+}
+
+struct Described {
+    node: AccessNode,
+    /// the descendants are part of this node, like the label of a button
+    leaf: bool,
+    focusable: bool,
+}
+
+impl AccessibleNodeQuery<'_, '_> {
+    fn collect_text(&self, entity: Entity, output: &mut String) {
+        if let Ok((text, span)) = self.text_query.get(entity) {
+            for content in [text.map(|t| &t.0), span.map(|s| &s.0)].into_iter().flatten() {
+                if !content.trim().is_empty() {
+                    if !output.is_empty() {
+                        output.push(' ');
+                    }
+                    output.push_str(content.trim());
+                }
+            }
+        }
+        if let Ok(children) = self.children_query.get(entity) {
+            for child in children.iter() {
+                self.collect_text(child, output);
+            }
+        }
+    }
+
+    fn text_of(&self, entity: Entity) -> Option<String> {
+        let mut text = String::new();
+        self.collect_text(entity, &mut text);
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn describe(&self, entity: Entity, accessible: Option<&UiAccessible>) -> Option<Described> {
+        let mut described = if self.button_query.contains(entity) {
+            let mut node = AccessNode::new(Role::Button);
+            node.add_action(Action::Click);
+            Described {
+                node,
+                leaf: true,
+                focusable: true,
+            }
+        } else if let Ok(state) = self.checkbox_query.get(entity) {
+            let mut node = AccessNode::new(Role::CheckBox);
+            node.set_toggled(if state.value {
+                Toggled::True
+            } else {
+                Toggled::False
+            });
+            node.add_action(Action::Click);
+            Described {
+                node,
+                leaf: true,
+                focusable: true,
+            }
+        } else if let Ok((slider, state)) = self.slider_query.get(entity) {
+            let mut node = AccessNode::new(Role::Slider);
+            node.set_numeric_value(*state.value() as f64);
+            node.set_min_numeric_value(slider.min as f64);
+            node.set_max_numeric_value(slider.max as f64);
+            node.set_numeric_value_step(slider_step(slider) as f64);
+            node.add_action(Action::Increment);
+            node.add_action(Action::Decrement);
+            node.add_action(Action::SetValue);
+            Described {
+                node,
+                leaf: true,
+                focusable: true,
+            }
+        } else if let Ok(state) = self.combobox_query.get(entity) {
+            let mut node = AccessNode::new(Role::ComboBox);
+            node.set_expanded(*state.open());
+            node.add_action(Action::Click);
+            Described {
+                node,
+                leaf: false,
+                focusable: true,
+            }
+        } else if let Ok((inputbox, state)) = self.inputbox_query.get(entity) {
+            let role = match (&inputbox.kind, inputbox.multi_line) {
+                (UiInputBoxKind::Password, _) => Role::PasswordInput,
+                (_, true) => Role::MultilineTextInput,
+                (_, false) => Role::TextInput,
+            };
+            let mut node = AccessNode::new(role);
+            if !matches!(inputbox.kind, UiInputBoxKind::Password) {
+                node.set_value(state.data().as_str());
+            }
+            if !inputbox.placeholder.is_empty() {
+                node.set_placeholder(inputbox.placeholder.as_str());
+            }
+            if inputbox.readonly {
+                node.set_read_only();
+            }
+            Described {
+                node,
+                leaf: true,
+                focusable: true,
+            }
+        } else if self.scroll_query.contains(entity) {
+            Described {
+                node: AccessNode::new(Role::ScrollView),
+                leaf: false,
+                focusable: false,
+            }
+        } else if let Some(role) = accessible.and_then(|a| a.role) {
+            Described {
+                node: AccessNode::new(role),
+                leaf: false,
+                focusable: false,
+            }
+        } else if let Ok((Some(text), _)) = self.text_query.get(entity) {
+            if text.0.trim().is_empty() {
+                return None;
+            }
+            Described {
+                node: AccessNode::new(Role::Label),
+                leaf: true,
+                focusable: false,
+            }
+        } else {
+            return None;
+        };
+
+        if let Some(role) = accessible.and_then(|a| a.role) {
+            described.node.set_role(role);
+        }
+        let label = accessible
+            .and_then(|a| a.label.clone())
+            .or_else(|| described.leaf.then(|| self.text_of(entity)).flatten());
+        if let Some(label) = label {
+            described.node.set_label(label);
+        }
+        if let Some(description) = accessible.and_then(|a| a.description.clone()) {
+            described.node.set_description(description);
+        }
+        if accessible.map(|a| a.focusable).unwrap_or(false) {
+            described.focusable = true;
+        }
+        if described.focusable {
+            described.node.add_action(Action::Focus);
+        }
+        Some(described)
+    }
+
+    fn visit(
+        &self,
+        entity: Entity,
+        parent_children: &mut Vec<NodeId>,
+        tree: &mut UiAccessibilityTree,
+    ) {
+        let Ok((style, computed_node, transform, visibility, children, accessible, hidden)) =
+            self.node_query.get(entity)
+        else {
+            return;
+        };
+        if hidden || style.display == Display::None || visibility == Some(&Visibility::Hidden) {
+            return;
+        }
+
+        let Some(mut described) = self.describe(entity, accessible) else {
+            for child in children.iter().flat_map(|c| c.iter()) {
+                self.visit(child, parent_children, tree);
+            }
+            return;
+        };
+
+        let rect = get_node_rect(transform, computed_node);
+        described.node.set_bounds(AccessRect {
+            x0: rect.min.x as f64,
+            y0: rect.min.y as f64,
+            x1: rect.max.x as f64,
+            y1: rect.max.y as f64,
+        });
+        if described.focusable {
+            tree.focusable.push(entity);
+        }
+        if !described.leaf {
+            let mut node_children = vec![];
+            for child in children.iter().flat_map(|c| c.iter()) {
+                self.visit(child, &mut node_children, tree);
+            }
+            described.node.set_children(node_children);
+        }
+        let id = UiAccessibilityTree::node_id(entity);
+        parent_children.push(id);
+        tree.nodes.push((id, described.node));
+    }
+}
+
+fn slider_step(slider: &UiSlider) -> f32 {
+    (slider.max - slider.min) / 20.0
+}
+
+pub fn update_accessibility_tree(
+    changed_query: Query<
+        (),
+        Or<(
+            Changed<ComputedNode>,
+            Changed<UiGlobalTransform>,
+            Changed<Children>,
+            Changed<Visibility>,
+            Changed<Text>,
+            Changed<TextSpan>,
+            Changed<UiAccessible>,
+            Changed<UiCheckBoxState>,
+            Changed<UiSliderState>,
+            Changed<UiComboBoxState>,
+            Changed<UiInputBoxState>,
+        )>,
+    >,
+    mut removed: RemovedComponents<Node>,
+    root_query: Query<(Entity, Option<&ChildOf>), With<Node>>,
+    parent_query: Query<(), With<Node>>,
+    node_query: AccessibleNodeQuery,
+    focus_state: Res<UiFocusState>,
+    mut tree: ResMut<UiAccessibilityTree>,
+) {
+    let removed = removed.read().count() > 0;
+    if changed_query.is_empty() && !removed && !focus_state.is_changed() {
+        return;
+    }
+
+    let mut roots: Vec<Entity> = root_query
+        .iter()
+        .filter(|(_, parent)| parent.map(|p| !parent_query.contains(p.parent())).unwrap_or(true))
+        .map(|(entity, _)| entity)
+        .collect();
+    roots.sort();
+
+    let mut new_tree = UiAccessibilityTree::default();
+    let mut root_children = vec![];
+    for root in roots {
+        node_query.visit(root, &mut root_children, &mut new_tree);
+    }
+    let mut root = AccessNode::new(Role::Window);
+    root.set_label("dway");
+    root.set_children(root_children);
+    new_tree.nodes.push((ROOT_NODE_ID, root));
+    new_tree.focus = focus_state
+        .navigation_focus
+        .or(focus_state.input_focus)
+        .filter(|e| new_tree.get(*e).is_some());
+    *tree = new_tree;
+}
+
+pub fn keyboard_navigation_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    focus_state: Res<UiFocusState>,
+    edit_query: Query<(), Or<(With<UiInput>, With<UiSlider>, With<KeyboardNavigation>)>>,
+    slider_query: Query<(), With<UiSlider>>,
+    mut requests: MessageWriter<UiAccessibilityRequest>,
+) {
+    // the keys are typed into the text field or the client window holding the input focus
+    if focus_state.input_focus.is_some() {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let focus = focus_state.navigation_focus;
+    // text inputs, sliders and item views use the arrow keys by themselves
+    let editing = focus.map(|e| edit_query.contains(e)).unwrap_or(false);
+    let is_slider = focus.map(|e| slider_query.contains(e)).unwrap_or(false);
+
+    if keys.just_pressed(KeyCode::Tab) {
+        requests.write(if shift {
+            UiAccessibilityRequest::FocusPrevious
+        } else {
+            UiAccessibilityRequest::FocusNext
+        });
+        return;
+    }
+    if let (true, Some(focus)) = (is_slider, focus) {
+        if keys.just_pressed(KeyCode::ArrowRight) {
+            requests.write(UiAccessibilityRequest::Increment(focus));
+        } else if keys.just_pressed(KeyCode::ArrowLeft) {
+            requests.write(UiAccessibilityRequest::Decrement(focus));
+        }
+    }
+    if !editing {
+        if keys.any_just_pressed([KeyCode::ArrowDown, KeyCode::ArrowRight]) {
+            requests.write(UiAccessibilityRequest::FocusNext);
+        } else if keys.any_just_pressed([KeyCode::ArrowUp, KeyCode::ArrowLeft]) {
+            requests.write(UiAccessibilityRequest::FocusPrevious);
+        } else if let (true, Some(focus)) = (
+            keys.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space]),
+            focus,
+        ) {
+            requests.write(UiAccessibilityRequest::Activate(focus));
+        }
+    }
+}
+
+pub fn handle_accessibility_request(
+    mut requests: MessageReader<UiAccessibilityRequest>,
+    tree: Res<UiAccessibilityTree>,
+    mut focus_state: ResMut<UiFocusState>,
+    mut focus_event: MessageWriter<UiFocusEvent>,
+    input_query: Query<(), With<UiInput>>,
+    interaction_query: Query<(), With<Interaction>>,
+    mut theme_query: Query<&mut ThemeComponent>,
+    mut slider_query: Query<(&UiSlider, &mut UiSliderState, &UiSliderEventDispatcher)>,
+    mut combobox_query: Query<&mut UiComboBoxState>,
+    mut commands: Commands,
+) {
+    for request in requests.read() {
+        let focus = match request {
+            UiAccessibilityRequest::Focus(e) => Some(*e),
+            UiAccessibilityRequest::FocusNext => {
+                tree.step_focus(focus_state.navigation_focus, true)
+            }
+            UiAccessibilityRequest::FocusPrevious => {
+                tree.step_focus(focus_state.navigation_focus, false)
+            }
+            UiAccessibilityRequest::Activate(entity) => {
+                if let Ok(mut state) = combobox_query.get_mut(*entity) {
+                    let open = !*state.open();
+                    state.set_open(open);
+                } else if interaction_query.contains(*entity) {
+                    commands.entity(*entity).insert(UiActivation::default());
+                }
+                None
+            }
+            UiAccessibilityRequest::Increment(entity)
+            | UiAccessibilityRequest::Decrement(entity)
+            | UiAccessibilityRequest::SetValue(entity, _) => {
+                let Ok((slider, mut state, dispatcher)) = slider_query.get_mut(*entity) else {
+                    continue;
+                };
+                let value = match request {
+                    UiAccessibilityRequest::Increment(_) => state.value() + slider_step(slider),
+                    UiAccessibilityRequest::Decrement(_) => state.value() - slider_step(slider),
+                    UiAccessibilityRequest::SetValue(_, value) => *value,
+                    _ => unreachable!(),
+                }
+                .clamp(slider.min.min(slider.max), slider.max.max(slider.min));
+                state.set_value(value);
+                dispatcher.send(
+                    UiSliderEvent {
+                        value,
+                        kind: UiSliderEventKind::ValueChanged(value),
+                    },
+                    &mut commands,
+                );
+                None
+            }
+        };
+
+        let Some(focus) = focus else {
+            continue;
+        };
+        if focus_state.navigation_focus == Some(focus) {
+            continue;
+        }
+        if let Some(old) = focus_state.navigation_focus {
+            if let Ok(mut theme) = theme_query.get_mut(old) {
+                theme.set_flag(StyleFlags::FOCUSED, false);
+            }
+        }
+        if let Ok(mut theme) = theme_query.get_mut(focus) {
+            theme.set_flag(StyleFlags::FOCUSED, true);
+        }
+        if input_query.contains(focus) {
+            focus_event.write(UiFocusEvent::FocusEnterRequest(focus));
+        } else if let Some(input_focus) = focus_state.input_focus {
+            focus_event.write(UiFocusEvent::FocusLeaveRequest(input_focus));
+        }
+        focus_state.navigation_focus = Some(focus);
+    }
+}
+
+pub fn update_activation_system(
+    mut query: Query<(Entity, &mut UiActivation, &mut Interaction)>,
+    mut commands: Commands,
+) {
+    for (entity, mut activation, mut interaction) in &mut query {
+        if !activation.pressed {
+            *interaction = Interaction::Pressed;
+            activation.pressed = true;
+        } else {
+            *interaction = Interaction::Hovered;
+            commands.entity(entity).remove::<UiActivation>();
+        }
+    }
+}
+
+pub struct UiAccessibilityPlugin;
+impl Plugin for UiAccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiAccessibilityTree>()
+            .add_event::<UiAccessibilityRequest>()
+            .register_type::<UiAccessibilityHidden>()
+            .register_type::<UiAccessibilityRequest>()
+            .add_systems(
+                PreUpdate,
+                (
+                    (keyboard_navigation_system, handle_accessibility_request)
+                        .chain()
+                        .before(UiFrameworkSystems::InputSystems),
+                    update_activation_system
+                        .after(UiFrameworkSystems::InputSystems)
+                        .before(UiFrameworkSystems::WidgetInputSystems),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                update_accessibility_tree.after(bevy::ui::UiSystem::Layout),
+            );
+
+        #[cfg(feature = "atspi")]
+        app.add_plugins(atspi::AtspiPlugin);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<UiFocusState>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .add_event::<UiFocusEvent>()
+            .init_resource::<UiAccessibilityTree>()
+            .add_event::<UiAccessibilityRequest>()
+            .add_systems(
+                Update,
+                (
+                    keyboard_navigation_system,
+                    handle_accessibility_request,
+                    update_accessibility_tree,
+                )
+                    .chain(),
+            );
+        app
+    }
+
+    #[test]
+    fn test_accessibility_tree() {
+        let mut app = setup();
+        let world = app.world_mut();
+        let root = world.spawn(Node::default()).id();
+        let button = world
+            .spawn((UiButton::default(), ChildOf(root)))
+            .with_child(Text::new("Shut down"))
+            .id();
+        let checkbox = world
+            .spawn((
+                UiCheckBox::default(),
+                UiCheckBoxState::new(true),
+                UiAccessible::default().with_label("Do not disturb"),
+                ChildOf(root),
+            ))
+            .id();
+        let label = world.spawn((Text::new("Volume"), ChildOf(root))).id();
+        let slider = world
+            .spawn((
+                Node::default(),
+                UiSlider::default(),
+                UiSliderState {
+                    value: 0.5,
+                    ..default()
+                },
+                ChildOf(root),
+            ))
+            .id();
+        world.spawn((UiButton::default(), Visibility::Hidden, ChildOf(root)));
+        app.update();
+
+        let tree = app.world().resource::<UiAccessibilityTree>();
+        assert_eq!(tree.focusable, vec![button, checkbox, slider]);
+        let node = tree.get(button).unwrap();
+        assert_eq!(node.role(), Role::Button);
+        assert_eq!(node.label(), Some("Shut down"));
+        let node = tree.get(checkbox).unwrap();
+        assert_eq!(node.role(), Role::CheckBox);
+        assert_eq!(node.toggled(), Some(Toggled::True));
+        assert_eq!(node.label(), Some("Do not disturb"));
+        assert_eq!(tree.get(label).unwrap().role(), Role::Label);
+        let node = tree.get(slider).unwrap();
+        assert_eq!(node.numeric_value(), Some(0.5));
+        let (_, root_node) = tree.nodes.iter().find(|(id, _)| *id == ROOT_NODE_ID).unwrap();
+        assert_eq!(root_node.children().len(), 4);
+        assert!(tree.get(root).is_none());
+    }
+
+    #[test]
+    fn test_keyboard_navigation() {
+        let mut app = setup();
+        let world = app.world_mut();
+        let first = world.spawn(UiButton::default()).id();
+        let slider = world
+            .spawn((Node::default(), UiSlider::default(), UiSliderState::default()))
+            .id();
+        app.update();
+
+        let press = |app: &mut App, key: KeyCode| {
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.release_all();
+            keys.clear();
+            keys.press(key);
+            app.update();
+            app.world().resource::<UiFocusState>().navigation_focus
+        };
+
+        assert_eq!(press(&mut app, KeyCode::Tab), Some(first));
+        assert_eq!(press(&mut app, KeyCode::ArrowDown), Some(slider));
+        assert_eq!(press(&mut app, KeyCode::ArrowRight), Some(slider));
+        let value = *app.world().get::<UiSliderState>(slider).unwrap().value();
+        assert_eq!(value, 0.05);
+        assert_eq!(press(&mut app, KeyCode::Tab), Some(first));
+        assert_eq!(
+            app.world().resource::<UiAccessibilityTree>().focus,
+            Some(first)
+        );
+    }
+
+    #[test]
+    fn test_keyboard_navigation_with_input_focus() {
+        let mut app = setup();
+        let world = app.world_mut();
+        let button = world.spawn(UiButton::default()).id();
+        let window = world.spawn((Node::default(), UiInput::default())).id();
+        app.update();
+
+        let press = |app: &mut App, key: KeyCode| {
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.release_all();
+            keys.clear();
+            keys.press(key);
+            app.update();
+            app.world().resource::<UiFocusState>().navigation_focus
+        };
+
+        assert_eq!(press(&mut app, KeyCode::Tab), Some(button));
+        // a client window takes the keyboard, its keys must not move or activate the focus
+        app.world_mut().resource_mut::<UiFocusState>().input_focus = Some(window);
+        for key in [KeyCode::Tab, KeyCode::ArrowDown, KeyCode::Enter, KeyCode::Space] {
+            assert_eq!(press(&mut app, key), Some(button));
+        }
+        assert!(app.world().get::<UiActivation>(button).is_none());
+        let focus_events = app.world().resource::<Events<UiFocusEvent>>();
+        assert!(focus_events.is_empty());
+        assert_eq!(
+            app.world().resource::<UiFocusState>().input_focus,
+            Some(window)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use bevy::window::RequestRedraw;
+
+use super::{ease::AnimationEaseMethod, lens::AnimationLens, AnimationSettings, Interpolation};
+use crate::prelude::*;
+
+#[derive(Clone, Debug)]
+pub struct Keyframe<T> {
+    pub time: Duration,
+    pub value: T,
+    /// the easing of the segment that ends at this keyframe
+    pub ease: AnimationEaseMethod,
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyframeTrack<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolation + Clone> KeyframeTrack<T> {
+    pub fn new(start: T) -> Self {
+        Self {
+            keyframes: vec![Keyframe {
+                time: Duration::ZERO,
+                value: start,
+                ease: AnimationEaseMethod::Linear,
+            }],
+        }
+    }
+
+    /// Append a keyframe `duration` after the last one.
+    pub fn then(
+        mut self,
+        duration: Duration,
+        value: T,
+        ease: impl Into<AnimationEaseMethod>,
+    ) -> Self {
+        let time = self.duration() + duration;
+        self.keyframes.push(Keyframe {
+            time,
+            value,
+            ease: ease.into(),
+        });
+        self
+    }
+
+    pub fn with_keyframe(
+        mut self,
+        time: Duration,
+        value: T,
+        ease: impl Into<AnimationEaseMethod>,
+    ) -> Self {
+        let index = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes.insert(
+            index,
+            Keyframe {
+                time,
+                value,
+                ease: ease.into(),
+            },
+        );
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.keyframes.last().map(|k| k.time).unwrap_or_default()
+    }
+
+    pub fn sample(&self, time: Duration) -> T {
+        let index = self.keyframes.partition_point(|k| k.time <= time);
+        if index == 0 {
+            return self.keyframes[0].value.clone();
+        }
+        if index == self.keyframes.len() {
+            return self.keyframes[index - 1].value.clone();
+        }
+        let (prev, next) = (&self.keyframes[index - 1], &self.keyframes[index]);
+        let progress = (time - prev.time).as_secs_f32() / (next.time - prev.time).as_secs_f32();
+        prev.value
+            .interpolation(&next.value, next.ease.calc(progress.clamp(0.0, 1.0)))
+    }
+}
+
+/// Plays a [`KeyframeTrack`] on the component selected by the lens.
+#[derive(Component)]
+pub struct KeyframeAnimation<L: AnimationLens> {
+    pub lens: L,
+    pub track: KeyframeTrack<L::Value>,
+    pub repeat: bool,
+    clock: Duration,
+    finished: bool,
+}
+
+impl<L: AnimationLens> KeyframeAnimation<L> {
+    pub fn new(lens: L, track: KeyframeTrack<L::Value>) -> Self {
+        Self {
+            lens,
+            track,
+            repeat: false,
+            clock: Duration::ZERO,
+            finished: false,
+        }
+    }
+
+    pub fn with_repeat(mut self) -> Self {
+        self.repeat = true;
+        self
+    }
+
+    pub fn restart(&mut self) {
+        self.clock = Duration::ZERO;
+        self.finished = false;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+pub fn update_keyframe_animation_system<L: AnimationLens>(
+    mut query: Query<(&mut KeyframeAnimation<L>, &mut L::Component)>,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut redraw_request: MessageWriter<RequestRedraw>,
+) {
+    let mut play = false;
+    for (mut animation, mut component) in &mut query {
+        if animation.finished {
+            continue;
+        }
+        let duration = animation.track.duration();
+        let mut clock = animation.clock + time.delta();
+        if settings.reduce_motion || duration.is_zero() {
+            clock = duration;
+        } else if clock >= duration && animation.repeat {
+            clock = Duration::from_secs_f64(clock.as_secs_f64() % duration.as_secs_f64());
+        }
+        let value = animation.track.sample(clock);
+        animation.lens.set(&mut component, value);
+        animation.clock = clock;
+        animation.finished = clock >= duration && !animation.repeat;
+        play = true;
+    }
+    if play {
+        redraw_request.write(RequestRedraw);
+    }
+}
@@ -0,0 +1,129 @@
+use bevy_relationship::reexport::Mutable;
+
+use super::Interpolation;
+use crate::prelude::*;
+
+/// Reads and writes one animated value of a component.
+pub trait AnimationLens: Send + Sync + 'static {
+    type Component: Component<Mutability = Mutable>;
+    type Value: Interpolation + Clone + Send + Sync + 'static;
+
+    fn get(&self, component: &Self::Component) -> Self::Value;
+    fn set(&self, component: &mut Self::Component, value: Self::Value);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TranslationLens;
+
+impl AnimationLens for TranslationLens {
+    type Component = Transform;
+    type Value = Vec3;
+
+    fn get(&self, component: &Transform) -> Vec3 {
+        component.translation
+    }
+
+    fn set(&self, component: &mut Transform, value: Vec3) {
+        component.translation = value;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScaleLens;
+
+impl AnimationLens for ScaleLens {
+    type Component = Transform;
+    type Value = Vec3;
+
+    fn get(&self, component: &Transform) -> Vec3 {
+        component.scale
+    }
+
+    fn set(&self, component: &mut Transform, value: Vec3) {
+        component.scale = value;
+    }
+}
+
+fn px(value: Val) -> f32 {
+    match value {
+        Val::Px(v) => v,
+        _ => 0.0,
+    }
+}
+
+/// `left` and `top` of a node in pixels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodePositionLens;
+
+impl AnimationLens for NodePositionLens {
+    type Component = Node;
+    type Value = Vec2;
+
+    fn get(&self, component: &Node) -> Vec2 {
+        Vec2::new(px(component.left), px(component.top))
+    }
+
+    fn set(&self, component: &mut Node, value: Vec2) {
+        component.left = Val::Px(value.x);
+        component.top = Val::Px(value.y);
+    }
+}
+
+/// `width` and `height` of a node in pixels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeSizeLens;
+
+impl AnimationLens for NodeSizeLens {
+    type Component = Node;
+    type Value = Vec2;
+
+    fn get(&self, component: &Node) -> Vec2 {
+        Vec2::new(px(component.width), px(component.height))
+    }
+
+    fn set(&self, component: &mut Node, value: Vec2) {
+        component.width = Val::Px(value.x);
+        component.height = Val::Px(value.y);
+    }
+}
+
+/// The position and the size of a node in pixels, as `(left, top, width, height)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeRectLens;
+
+impl AnimationLens for NodeRectLens {
+    type Component = Node;
+    type Value = Vec4;
+
+    fn get(&self, component: &Node) -> Vec4 {
+        Vec4::new(
+            px(component.left),
+            px(component.top),
+            px(component.width),
+            px(component.height),
+        )
+    }
+
+    fn set(&self, component: &mut Node, value: Vec4) {
+        component.left = Val::Px(value.x);
+        component.top = Val::Px(value.y);
+        component.width = Val::Px(value.z);
+        component.height = Val::Px(value.w);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackgroundColorLens;
+
+impl AnimationLens for BackgroundColorLens {
+    type Component = BackgroundColor;
+    type Value = Color;
+
+    fn get(&self, component: &BackgroundColor) -> Color {
+        component.0
+    }
+
+    fn set(&self, component: &mut BackgroundColor, value: Color) {
+        component.0 = value;
+    }
+}
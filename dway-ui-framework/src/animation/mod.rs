@@ -1,6 +1,10 @@
 mod asset;
 pub mod ease;
+pub mod keyframe;
+pub mod lens;
 pub mod registry;
+pub mod spring;
+pub mod timeline;
 pub mod translation;
 pub mod ui;
 
@@ -10,7 +14,10 @@ use bevy::window::RequestRedraw;
 use ease::AnimationEaseMethod;
 pub use interpolation;
 use interpolation::{Ease, EaseFunction};
+use keyframe::update_keyframe_animation_system;
+use lens::AnimationLens;
 use registry::AnimationRegister;
+use spring::{update_spring_animation_system, SpringValue};
 
 use crate::{
     command::DestroyInterceptor,
@@ -40,6 +47,7 @@ make_interpolation!(f32);
 make_interpolation!(Vec2);
 make_interpolation!(Vec3);
 make_interpolation!(Vec4);
+make_interpolation!(Mat3);
 impl Interpolation for Color {
     fn interpolation(&self, other: &Self, v: f32) -> Self {
         LinearRgba::from_f32_array(Interpolation::interpolation(
@@ -95,6 +103,13 @@ impl Interpolation for LinearRgba {
     }
 }
 
+/// Global animation preferences.
+#[derive(Resource, Debug, Clone, Default, Reflect)]
+pub struct AnimationSettings {
+    /// collapse every animation to its end state
+    pub reduce_motion: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct AnimationEvent {
     pub value: f32,
@@ -227,6 +242,7 @@ impl Animation {
 pub fn update_animation_system(
     mut query: Query<(Entity, &mut Animation, &EventDispatcher<AnimationEvent>)>,
     time: Res<Time>,
+    settings: Res<AnimationSettings>,
     mut redraw_request: MessageWriter<RequestRedraw>,
     mut commands: Commands,
 ) {
@@ -236,7 +252,10 @@ pub fn update_animation_system(
             continue;
         }
         play = true;
-        let duration = animation.clock.duration + time.delta();
+        let mut duration = animation.clock.duration + time.delta();
+        if settings.reduce_motion {
+            duration = duration.max(animation.clock.total_duration + Duration::from_nanos(1));
+        }
         let mut ease_old = animation.ease.calc(
             animation.clock.duration.as_secs_f32() / animation.clock.total_duration.as_secs_f32(),
         );
@@ -287,15 +306,54 @@ impl<I: UiMaterial + Interpolation + Asset> Tween<I> {
     }
 }
 
+pub trait AnimationAppExt {
+    fn register_keyframe_lens<L: AnimationLens>(&mut self) -> &mut Self;
+    fn register_spring_lens<L: AnimationLens>(&mut self) -> &mut Self
+    where
+        L::Value: SpringValue;
+}
+
+impl AnimationAppExt for App {
+    fn register_keyframe_lens<L: AnimationLens>(&mut self) -> &mut Self {
+        self.add_systems(
+            PostUpdate,
+            update_keyframe_animation_system::<L>.in_set(UiFrameworkSystems::ApplyAnimation),
+        )
+    }
+
+    fn register_spring_lens<L: AnimationLens>(&mut self) -> &mut Self
+    where
+        L::Value: SpringValue,
+    {
+        self.add_systems(
+            PostUpdate,
+            update_spring_animation_system::<L>.in_set(UiFrameworkSystems::ApplyAnimation),
+        )
+    }
+}
+
 pub struct AnimationPlugin;
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             PostUpdate,
-            update_animation_system
+            (update_animation_system, timeline::update_timeline_system)
                 .in_set(UiFrameworkSystems::ApplyAnimation),
         )
         .init_resource::<AnimationRegister>()
+        .init_resource::<AnimationSettings>()
+        .register_type::<AnimationSettings>()
+        .register_keyframe_lens::<lens::TranslationLens>()
+        .register_keyframe_lens::<lens::ScaleLens>()
+        .register_keyframe_lens::<lens::NodePositionLens>()
+        .register_keyframe_lens::<lens::NodeSizeLens>()
+        .register_keyframe_lens::<lens::NodeRectLens>()
+        .register_keyframe_lens::<lens::BackgroundColorLens>()
+        .register_spring_lens::<lens::TranslationLens>()
+        .register_spring_lens::<lens::ScaleLens>()
+        .register_spring_lens::<lens::NodePositionLens>()
+        .register_spring_lens::<lens::NodeSizeLens>()
+        .register_spring_lens::<lens::NodeRectLens>()
         .register_component_as::<dyn EventReceiver<AnimationEvent>, translation::UiTranslationAnimation>()
         .register_component_as::<dyn EventReceiver<UiNodeAppearEvent>, translation::UiTranslationAnimation>()
         .register_component_as::<dyn EventReceiver<UiPopupEvent>, translation::UiTranslationAnimation>()
@@ -0,0 +1,239 @@
+use std::ops::{Add, Mul, Sub};
+
+use bevy::window::RequestRedraw;
+
+use super::{lens::AnimationLens, AnimationSettings};
+use crate::prelude::*;
+
+/// The integration step, smaller steps keep stiff springs stable at low frame rates.
+const SPRING_STEP: f32 = 1.0 / 240.0;
+
+pub trait SpringValue:
+    Copy
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<f32, Output = Self>
+{
+    const ZERO: Self;
+    fn norm(&self) -> f32;
+}
+
+macro_rules! make_spring_value {
+    ($t:ty, $zero:expr, $norm:expr) => {
+        impl SpringValue for $t {
+            const ZERO: Self = $zero;
+            fn norm(&self) -> f32 {
+                $norm(self)
+            }
+        }
+    };
+}
+
+make_spring_value!(f32, 0.0, |v: &f32| v.abs());
+make_spring_value!(Vec2, Vec2::ZERO, Vec2::length);
+make_spring_value!(Vec3, Vec3::ZERO, Vec3::length);
+make_spring_value!(Vec4, Vec4::ZERO, Vec4::length);
+
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct Spring {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+    /// the spring stops when both the distance and the speed are below it
+    pub epsilon: f32,
+}
+
+impl Default for Spring {
+    /// critically damped
+    fn default() -> Self {
+        Self::new(300.0, 2.0 * 300.0f32.sqrt())
+    }
+}
+
+impl Spring {
+    pub fn new(stiffness: f32, damping: f32) -> Self {
+        Self {
+            stiffness,
+            damping,
+            mass: 1.0,
+            epsilon: 0.01,
+        }
+    }
+
+    pub fn bouncy() -> Self {
+        Self::new(400.0, 20.0)
+    }
+
+    pub fn stiff() -> Self {
+        Self::new(1000.0, 2.0 * 1000.0f32.sqrt())
+    }
+
+    /// Advance `value` and `velocity` towards `target` by `delta` seconds.
+    pub fn step<V: SpringValue>(&self, value: &mut V, velocity: &mut V, target: V, delta: f32) {
+        let mut remain = delta;
+        while remain > 0.0 {
+            let dt = remain.min(SPRING_STEP);
+            let force = (target - *value) * self.stiffness - *velocity * self.damping;
+            *velocity = *velocity + force * (dt / self.mass);
+            *value = *value + *velocity * dt;
+            remain -= dt;
+        }
+    }
+
+    pub fn is_settled<V: SpringValue>(&self, value: V, velocity: V, target: V) -> bool {
+        (target - value).norm() < self.epsilon && velocity.norm() < self.epsilon
+    }
+}
+
+/// Moves the value selected by the lens towards [`SpringAnimation::target`].
+///
+/// Changing the target keeps the current velocity, so an animation can be retargeted
+/// in the middle of a gesture without a jump.
+#[derive(Component)]
+pub struct SpringAnimation<L: AnimationLens>
+where
+    L::Value: SpringValue,
+{
+    pub lens: L,
+    pub spring: Spring,
+    target: L::Value,
+    velocity: L::Value,
+    value: Option<L::Value>,
+    settled: bool,
+}
+
+impl<L: AnimationLens> SpringAnimation<L>
+where
+    L::Value: SpringValue,
+{
+    /// The animation starts from the current value of the component.
+    pub fn new(lens: L, spring: Spring, target: L::Value) -> Self {
+        Self {
+            lens,
+            spring,
+            target,
+            velocity: L::Value::ZERO,
+            value: None,
+            settled: false,
+        }
+    }
+
+    pub fn from_value(mut self, value: L::Value) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    pub fn target(&self) -> L::Value {
+        self.target
+    }
+
+    pub fn velocity(&self) -> L::Value {
+        self.velocity
+    }
+
+    pub fn retarget(&mut self, target: L::Value) {
+        self.target = target;
+        self.settled = false;
+    }
+
+    /// Move the value directly, like following a finger during a swipe. The
+    /// velocity is kept for the spring after release.
+    pub fn set_value(&mut self, value: L::Value, velocity: L::Value) {
+        self.value = Some(value);
+        self.velocity = velocity;
+        self.settled = false;
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    fn update(&mut self, delta: f32, reduce_motion: bool) -> L::Value {
+        let mut value = self.value.unwrap_or(self.target);
+        if reduce_motion {
+            value = self.target;
+            self.velocity = L::Value::ZERO;
+        } else {
+            self.spring
+                .step(&mut value, &mut self.velocity, self.target, delta);
+        }
+        if self.spring.is_settled(value, self.velocity, self.target) {
+            value = self.target;
+            self.velocity = L::Value::ZERO;
+            self.settled = true;
+        }
+        self.value = Some(value);
+        value
+    }
+}
+
+pub fn update_spring_animation_system<L: AnimationLens>(
+    mut query: Query<(&mut SpringAnimation<L>, &mut L::Component)>,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut redraw_request: MessageWriter<RequestRedraw>,
+) where
+    L::Value: SpringValue,
+{
+    let mut play = false;
+    for (mut animation, mut component) in &mut query {
+        if animation.settled {
+            continue;
+        }
+        if animation.value.is_none() {
+            animation.value = Some(animation.lens.get(&component));
+        }
+        let value = animation.update(time.delta_secs(), settings.reduce_motion);
+        animation.lens.set(&mut component, value);
+        play = true;
+    }
+    if play {
+        redraw_request.write(RequestRedraw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::lens::NodePositionLens;
+
+    fn run(animation: &mut SpringAnimation<NodePositionLens>, seconds: f32) -> Vec2 {
+        let mut value = Vec2::ZERO;
+        for _ in 0..(seconds * 60.0) as usize {
+            value = animation.update(1.0 / 60.0, false);
+        }
+        value
+    }
+
+    #[test]
+    fn test_spring_settles() {
+        let mut animation = SpringAnimation::new(NodePositionLens, Spring::default(), Vec2::X)
+            .from_value(Vec2::ZERO);
+        let value = run(&mut animation, 0.1);
+        assert!(value.x > 0.0 && value.x < 1.0);
+        run(&mut animation, 2.0);
+        assert!(animation.is_settled());
+        assert_eq!(animation.value, Some(Vec2::X));
+    }
+
+    #[test]
+    fn test_spring_retarget_keeps_velocity() {
+        let mut animation = SpringAnimation::new(NodePositionLens, Spring::default(), Vec2::X)
+            .from_value(Vec2::ZERO);
+        run(&mut animation, 0.1);
+        let velocity = animation.velocity();
+        assert!(velocity.x > 0.0);
+        animation.retarget(Vec2::new(1.0, 1.0));
+        assert_eq!(animation.velocity(), velocity);
+        let value = animation.update(1.0 / 60.0, false);
+        assert!(value.x > 0.0);
+
+        animation.retarget(Vec2::ZERO);
+        animation.update(1.0 / 60.0, true);
+        assert!(animation.is_settled());
+        assert_eq!(animation.value, Some(Vec2::ZERO));
+    }
+}
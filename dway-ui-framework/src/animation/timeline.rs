@@ -0,0 +1,277 @@
+use std::sync::Arc;
+
+use bevy::window::RequestRedraw;
+
+use super::{keyframe::KeyframeTrack, lens::AnimationLens, AnimationSettings, AnimationState};
+use crate::{event::EventDispatcher, prelude::*};
+
+/// A keyframe track applied to one entity by a [`Timeline`].
+pub trait TimelineClip: Send + Sync + 'static {
+    fn duration(&self) -> Duration;
+    fn apply(&self, world: &mut World, target: Entity, time: Duration);
+}
+
+struct LensClip<L: AnimationLens> {
+    lens: L,
+    track: KeyframeTrack<L::Value>,
+}
+
+impl<L: AnimationLens> TimelineClip for LensClip<L> {
+    fn duration(&self) -> Duration {
+        self.track.duration()
+    }
+
+    fn apply(&self, world: &mut World, target: Entity, time: Duration) {
+        if let Some(mut component) = world.get_mut::<L::Component>(target) {
+            self.lens.set(&mut component, self.track.sample(time));
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum TimelineNode {
+    Clip {
+        target: Entity,
+        clip: Arc<dyn TimelineClip>,
+    },
+    Delay(Duration),
+    /// run the children one after another
+    Sequence(Vec<TimelineNode>),
+    /// run the children at the same time
+    Parallel(Vec<TimelineNode>),
+}
+
+impl TimelineNode {
+    pub fn clip<L: AnimationLens>(target: Entity, lens: L, track: KeyframeTrack<L::Value>) -> Self {
+        Self::Clip {
+            target,
+            clip: Arc::new(LensClip { lens, track }),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            TimelineNode::Clip { clip, .. } => clip.duration(),
+            TimelineNode::Delay(d) => *d,
+            TimelineNode::Sequence(nodes) => nodes.iter().map(Self::duration).sum(),
+            TimelineNode::Parallel(nodes) => {
+                nodes.iter().map(Self::duration).max().unwrap_or_default()
+            }
+        }
+    }
+
+    /// Collect the clips active between `from` and `to`, with their local time at `to`.
+    fn collect_active(
+        &self,
+        start: Duration,
+        from: Option<Duration>,
+        to: Duration,
+        output: &mut Vec<(Entity, Arc<dyn TimelineClip>, Duration)>,
+    ) {
+        match self {
+            TimelineNode::Clip { target, clip } => {
+                let end = start + clip.duration();
+                let started = to >= start;
+                let finished_before = from.map(|from| from > end).unwrap_or(false);
+                if started && !finished_before {
+                    output.push((*target, clip.clone(), (to - start).min(clip.duration())));
+                }
+            }
+            TimelineNode::Delay(_) => {}
+            TimelineNode::Sequence(nodes) => {
+                let mut offset = start;
+                for node in nodes {
+                    node.collect_active(offset, from, to, output);
+                    offset += node.duration();
+                }
+            }
+            TimelineNode::Parallel(nodes) => {
+                for node in nodes {
+                    node.collect_active(start, from, to, output);
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for TimelineNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Clip { target, clip } => f
+                .debug_struct("Clip")
+                .field("target", target)
+                .field("duration", &clip.duration())
+                .finish(),
+            Self::Delay(d) => f.debug_tuple("Delay").field(d).finish(),
+            Self::Sequence(nodes) => f.debug_tuple("Sequence").field(nodes).finish(),
+            Self::Parallel(nodes) => f.debug_tuple("Parallel").field(nodes).finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimelineEvent {
+    Started,
+    Finished,
+}
+
+pub type TimelineEventDispatcher = EventDispatcher<TimelineEvent>;
+
+/// Drives keyframe tracks on several entities, grouped in sequences and parallel groups.
+#[derive(Component, Debug)]
+#[require(TimelineEventDispatcher)]
+pub struct Timeline {
+    pub root: TimelineNode,
+    pub state: AnimationState,
+    clock: Option<Duration>,
+}
+
+impl Timeline {
+    pub fn new(root: TimelineNode) -> Self {
+        Self {
+            root,
+            state: AnimationState::Play,
+            clock: None,
+        }
+    }
+
+    pub fn sequence(nodes: impl IntoIterator<Item = TimelineNode>) -> Self {
+        Self::new(TimelineNode::Sequence(nodes.into_iter().collect()))
+    }
+
+    pub fn parallel(nodes: impl IntoIterator<Item = TimelineNode>) -> Self {
+        Self::new(TimelineNode::Parallel(nodes.into_iter().collect()))
+    }
+
+    pub fn replay(&mut self) {
+        self.clock = None;
+        self.state = AnimationState::Play;
+    }
+
+    pub fn pause(&mut self) {
+        if self.state == AnimationState::Play {
+            self.state = AnimationState::Pause;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == AnimationState::Finished
+    }
+
+    /// Advance the clock and return the clips to apply.
+    fn advance(&mut self, delta: Duration) -> Vec<(Entity, Arc<dyn TimelineClip>, Duration)> {
+        let duration = self.root.duration();
+        let from = self.clock;
+        let to = from.map(|t| t + delta).unwrap_or_default().min(duration);
+        let mut clips = vec![];
+        self.root.collect_active(Duration::ZERO, from, to, &mut clips);
+        self.clock = Some(to);
+        if to >= duration {
+            self.state = AnimationState::Finished;
+        }
+        clips
+    }
+}
+
+pub fn update_timeline_system(
+    mut query: Query<(&mut Timeline, &TimelineEventDispatcher)>,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut redraw_request: MessageWriter<RequestRedraw>,
+    mut commands: Commands,
+) {
+    let mut play = false;
+    for (mut timeline, event_dispatcher) in &mut query {
+        if timeline.state != AnimationState::Play {
+            continue;
+        }
+        play = true;
+        if timeline.clock.is_none() {
+            event_dispatcher.send(TimelineEvent::Started, &mut commands);
+        }
+        let delta = if settings.reduce_motion {
+            timeline.root.duration()
+        } else {
+            time.delta()
+        };
+        let clips = timeline.advance(delta);
+        commands.queue(move |world: &mut World| {
+            for (target, clip, local_time) in clips {
+                clip.apply(world, target, local_time);
+            }
+        });
+        if timeline.is_finished() {
+            event_dispatcher.send(TimelineEvent::Finished, &mut commands);
+        }
+    }
+    if play {
+        redraw_request.write(RequestRedraw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use interpolation::EaseFunction;
+
+    use super::*;
+    use crate::animation::{ease::AnimationEaseMethod, lens::NodePositionLens};
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_keyframe_track() {
+        let track = KeyframeTrack::new(0.0f32)
+            .then(ms(100), 1.0, AnimationEaseMethod::Linear)
+            .then(ms(100), 3.0, EaseFunction::QuadraticIn);
+        assert_eq!(track.duration(), ms(200));
+        assert_eq!(track.sample(ms(50)), 0.5);
+        assert_eq!(track.sample(ms(150)), 1.5);
+        assert_eq!(track.sample(ms(500)), 3.0);
+    }
+
+    #[test]
+    fn test_timeline() {
+        let mut world = World::new();
+        let a = world.spawn(Node::default()).id();
+        let b = world.spawn(Node::default()).id();
+        let track = || {
+            KeyframeTrack::new(Vec2::ZERO).then(
+                ms(100),
+                Vec2::splat(100.0),
+                AnimationEaseMethod::Linear,
+            )
+        };
+        let mut timeline = Timeline::sequence([
+            TimelineNode::clip(a, NodePositionLens, track()),
+            TimelineNode::Delay(ms(50)),
+            TimelineNode::Parallel(vec![
+                TimelineNode::clip(b, NodePositionLens, track()),
+                TimelineNode::clip(a, NodePositionLens, track()),
+            ]),
+        ]);
+        assert_eq!(timeline.root.duration(), ms(250));
+
+        let mut step = |world: &mut World, delta: Duration| {
+            for (target, clip, local_time) in timeline.advance(delta) {
+                clip.apply(world, target, local_time);
+            }
+        };
+        let left = |world: &World, e: Entity| world.get::<Node>(e).unwrap().left;
+
+        step(&mut world, ms(0));
+        step(&mut world, ms(50));
+        assert_eq!(left(&world, a), Val::Px(50.0));
+        assert_eq!(left(&world, b), Val::Auto);
+        step(&mut world, ms(70));
+        assert_eq!(left(&world, a), Val::Px(100.0));
+        assert_eq!(left(&world, b), Val::Auto);
+        step(&mut world, ms(80));
+        assert_eq!(left(&world, a), Val::Px(50.0));
+        assert_eq!(left(&world, b), Val::Px(50.0));
+        step(&mut world, ms(500));
+        assert_eq!(left(&world, b), Val::Px(100.0));
+        assert!(timeline.is_finished());
+    }
+}
@@ -11,5 +11,6 @@ impl Plugin for UiAssetsPlugin {
         embedded_asset!(app, "assets", "fonts/SmileySans-Oblique.ttf.woff2");
         embedded_asset!(app, "assets", "fonts/FiraSans-Bold.ttf");
         embedded_asset!(app, "assets", "fonts/FiraMono-Medium.ttf");
+        embedded_asset!(app, "assets", "themes/default.theme.ron");
     }
 }
@@ -5,6 +5,7 @@ use bevy::{
         mouse::{MouseButtonInput, MouseWheel},
     },
     ui::RelativeCursorPosition,
+    window::Ime,
 };
 use bevy_relationship::reexport::SmallVec;
 
@@ -62,6 +63,8 @@ pub enum UiInputEvent {
     /// Mouse position relative to the node's top-left corner
     MouseMove(Vec2),
     KeyboardInput(KeyboardInput),
+    /// Input method events, sent to the node with keyboard focus
+    Ime(Ime),
     Wheel(MouseWheel),
     RawMouseButton(MouseButtonInput),
 }
@@ -77,6 +80,7 @@ impl UiInputEvent {
             UiInputEvent::KeyboardLeave => None,
             UiInputEvent::MouseMove(_) => None,
             UiInputEvent::KeyboardInput(_) => None,
+            UiInputEvent::Ime(_) => None,
             UiInputEvent::Wheel(_) => None,
             UiInputEvent::RawMouseButton(_) => None,
         }
@@ -131,6 +135,8 @@ pub enum UiFocusEvent {
 pub struct UiFocusState {
     pub mouse_focus: Option<Entity>,
     pub input_focus: Option<Entity>,
+    /// The widget selected by keyboard navigation or assistive technologies.
+    pub navigation_focus: Option<Entity>,
 }
 
 #[derive(QueryData)]
@@ -149,6 +155,7 @@ pub fn update_ui_input(
     mut query: Query<UiInputQuery>,
     mut commands: Commands,
     mut keyboard_event: MessageReader<KeyboardInput>,
+    mut ime_event: MessageReader<Ime>,
     mouse_button_state: Res<ButtonInput<MouseButton>>,
     mut ui_focus_event: MessageReader<UiFocusEvent>,
     mut ui_focus_state: ResMut<UiFocusState>,
@@ -249,6 +256,19 @@ pub fn update_ui_input(
             }
         }
     }
+    for ime in ime_event.read() {
+        for UiInputQueryItem {
+            entity: _,
+            ui_focus,
+            event_dispatcher,
+            ..
+        } in &mut query
+        {
+            if ui_focus.can_receive_keyboard_input() {
+                event_dispatcher.send(UiInputEvent::Ime(ime.clone()), &mut commands);
+            }
+        }
+    }
 
     let set_theme_focused = |theme: Option<Mut<ThemeComponent>>, value: bool| {
         if let Some(mut theme) = theme {
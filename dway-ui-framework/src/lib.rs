@@ -1,6 +1,7 @@
 #![feature(round_char_boundary)]
 #![feature(btree_cursors)]
 
+pub mod a11y;
 pub mod animation;
 pub mod assets;
 pub mod command;
@@ -67,6 +68,7 @@ impl Plugin for UiFrameworkPlugin {
             render::blur::BlurRenderPlugin,
             render::layer_manager::LayerManagerPlugin,
             render::ui_nodes::UiNodeRenderPlugin,
+            render::output_color::OutputColorPlugin,
             shader::ShaderFrameworkPlugin,
             mvvm::MvvmPlugin,
             animation::AnimationPlugin,
@@ -78,7 +80,10 @@ impl Plugin for UiFrameworkPlugin {
             widgets::scroll::UiScrollPlugin,
             widgets::combobox::UiComboBoxPlugin,
             widgets::inputbox::UiInputBoxPlugin,
+            widgets::dnd::UiDragAndDropPlugin,
+            widgets::menu::UiMenuPlugin,
             UiMeshMaterialPlugin::<Svg>::default(),
+            a11y::UiAccessibilityPlugin,
         ))
         .add_event::<event::DespawnLaterEvent>()
         .add_systems(
@@ -1,9 +1,8 @@
-use std::marker::PhantomData;
+use std::{collections::BTreeMap, marker::PhantomData, ops::Range};
 
 use bevy::{ecs::system::EntityCommands, platform::collections::HashMap};
-use slab_tree::Tree;
 
-use crate::prelude::*;
+use crate::{prelude::*, widgets::scroll::UiScrollState};
 
 pub trait ViewLayouter<Index> {
     fn contains(&self, index: Index) -> bool;
@@ -47,35 +46,366 @@ pub enum LayoutChange<Index> {
     },
 }
 
+#[derive(Clone, Debug, Reflect)]
 pub struct HeaderCell {
+    pub title: String,
     pub position: f32,
     pub size: f32,
+    pub min_size: f32,
     pub pin: bool,
     pub resizable: bool,
+    pub sortable: bool,
+}
+
+impl HeaderCell {
+    pub fn new(title: impl Into<String>, size: f32) -> Self {
+        Self {
+            title: title.into(),
+            position: 0.0,
+            size,
+            min_size: 16.0,
+            pin: false,
+            resizable: true,
+            sortable: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
 }
 
 pub struct HonHeader;
 pub struct VerHeader;
-pub struct Header<Direction> {
-    pub size: Vec<f32>,
+
+#[derive(Component)]
+pub struct Header<Direction: Send + Sync + 'static> {
+    pub cells: Vec<HeaderCell>,
+    pub sort: Option<(usize, SortOrder)>,
     phantom: PhantomData<Direction>,
 }
 
-pub struct ItemLayoutFromHeader<Direction> {
-    pub header_entity: Entity,
-    phantom: PhantomData<Direction>,
+impl<Direction: Send + Sync + 'static> Header<Direction> {
+    pub fn new(cells: impl IntoIterator<Item = HeaderCell>) -> Self {
+        let mut this = Self {
+            cells: cells.into_iter().collect(),
+            sort: None,
+            phantom: PhantomData,
+        };
+        this.update_position(0);
+        this
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn position(&self, index: usize) -> f32 {
+        self.cells.get(index).map(|c| c.position).unwrap_or(0.0)
+    }
+
+    pub fn size(&self, index: usize) -> f32 {
+        self.cells.get(index).map(|c| c.size).unwrap_or(0.0)
+    }
+
+    pub fn total_size(&self) -> f32 {
+        self.cells.last().map(|c| c.position + c.size).unwrap_or(0.0)
+    }
+
+    pub fn index_at(&self, position: f32) -> Option<usize> {
+        self.cells
+            .iter()
+            .position(|c| position >= c.position && position < c.position + c.size)
+    }
+
+    /// Resize a resizable cell and move the cells after it, returns false if nothing changed.
+    pub fn resize(&mut self, index: usize, size: f32) -> bool {
+        let Some(cell) = self.cells.get_mut(index) else {
+            return false;
+        };
+        let size = size.max(cell.min_size);
+        if !cell.resizable || cell.size == size {
+            return false;
+        }
+        cell.size = size;
+        self.update_position(index);
+        true
+    }
+
+    /// Sort by a sortable cell, sorting by the same cell again reverses the order.
+    pub fn toggle_sort(&mut self, index: usize) -> Option<(usize, SortOrder)> {
+        if !self.cells.get(index).map(|c| c.sortable).unwrap_or(false) {
+            return self.sort;
+        }
+        let order = match self.sort {
+            Some((column, SortOrder::Ascending)) if column == index => SortOrder::Descending,
+            _ => SortOrder::Ascending,
+        };
+        self.sort = Some((index, order));
+        self.sort
+    }
+
+    fn update_position(&mut self, from: usize) {
+        let mut position = from
+            .checked_sub(1)
+            .and_then(|i| self.cells.get(i))
+            .map(|c| c.position + c.size)
+            .unwrap_or(0.0);
+        for cell in self.cells.iter_mut().skip(from) {
+            cell.position = position;
+            position += cell.size;
+        }
+    }
 }
 
-structstruck::strike! {
-    pub struct TreeView<NodeId>{
-        pub item_size: Vec2,
-        pub view_area: ViewAreaLayout,
-        pub items: Tree<struct TreeViewItem<NodeId> {
-            id: NodeId,
-            layout: ItemLayout,
-            is_folded: bool,
-        }>,
+/// The offsets of rows with different sizes along one axis.
+///
+/// Rows without a measured size use `default_size`.
+#[derive(Clone, Reflect, Debug, Default)]
+pub struct RowLayout {
+    default_size: f32,
+    sizes: Vec<Option<f32>>,
+    offsets: Vec<f32>,
+}
+
+impl RowLayout {
+    pub fn new(default_size: f32) -> Self {
+        Self {
+            default_size,
+            sizes: vec![],
+            offsets: vec![0.0],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sizes.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    pub fn default_size(&self) -> f32 {
+        self.default_size
+    }
+
+    pub fn set_default_size(&mut self, size: f32) {
+        if self.default_size != size {
+            self.default_size = size;
+            self.update_offsets(0);
+        }
+    }
+
+    pub fn set_len(&mut self, len: usize) {
+        if len != self.len() {
+            let from = self.len().min(len);
+            self.sizes.resize(len, None);
+            self.update_offsets(from);
+        }
+    }
+
+    /// Replace all rows, `None` means the row is not measured yet.
+    pub fn set_sizes(&mut self, sizes: impl IntoIterator<Item = Option<f32>>) {
+        self.sizes = sizes.into_iter().collect();
+        self.update_offsets(0);
+    }
+
+    pub fn size(&self, index: usize) -> f32 {
+        self.sizes
+            .get(index)
+            .copied()
+            .flatten()
+            .unwrap_or(self.default_size)
+    }
+
+    /// Set the measured size of a row, returns false if nothing changed.
+    pub fn set_size(&mut self, index: usize, size: f32) -> bool {
+        let Some(slot) = self.sizes.get_mut(index) else {
+            return false;
+        };
+        if slot.map(|s| (s - size).abs() < 0.5).unwrap_or(false) {
+            return false;
+        }
+        *slot = Some(size);
+        self.update_offsets(index);
+        true
+    }
+
+    pub fn offset(&self, index: usize) -> f32 {
+        self.offsets.get(index).copied().unwrap_or_else(|| {
+            self.total_size() + index.saturating_sub(self.len()) as f32 * self.default_size
+        })
+    }
+
+    pub fn total_size(&self) -> f32 {
+        self.offsets.last().copied().unwrap_or(0.0)
+    }
+
+    /// The row at `position`, rows after the last one have the default size.
+    pub fn index_at(&self, position: f32) -> usize {
+        let total = self.total_size();
+        if position >= total {
+            let extra = if self.default_size > 0.0 {
+                ((position - total) / self.default_size).floor() as usize
+            } else {
+                0
+            };
+            return self.len() + extra;
+        }
+        self.offsets
+            .get(1..)
+            .map(|ends| ends.partition_point(|end| *end <= position))
+            .unwrap_or(0)
+    }
+
+    /// The rows intersecting `min..max`.
+    pub fn range(&self, min: f32, max: f32) -> Range<usize> {
+        let start = self.index_at(min.max(0.0));
+        let end = self.index_at(max.max(0.0)) + 1;
+        start..end.max(start)
+    }
+
+    fn update_offsets(&mut self, from: usize) {
+        self.offsets.truncate(from + 1);
+        if self.offsets.is_empty() {
+            self.offsets.push(0.0);
+        }
+        let mut offset = self.offsets[self.offsets.len() - 1];
+        for index in self.offsets.len() - 1..self.sizes.len() {
+            offset += self.size(index);
+            self.offsets.push(offset);
+        }
+    }
+}
+
+/// The item widgets of a virtualized view.
+///
+/// Only the items inside the viewport own a widget, widgets of items scrolled out
+/// of the viewport are hidden and reused for other items.
+#[derive(Debug)]
+pub struct ItemPool<Index> {
+    items: BTreeMap<Index, Entity>,
+    free: Vec<Entity>,
+}
+
+impl<Index> Default for ItemPool<Index> {
+    fn default() -> Self {
+        Self {
+            items: Default::default(),
+            free: Default::default(),
+        }
+    }
+}
+
+impl<Index: Ord + Clone> ItemPool<Index> {
+    pub fn get(&self, index: &Index) -> Option<Entity> {
+        self.items.get(index).copied()
+    }
+
+    pub fn indices(&self) -> Vec<Index> {
+        self.items.keys().cloned().collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Index, Entity)> {
+        self.items.iter().map(|(k, v)| (k, *v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Get the widget of an item, reuse a free widget or spawn one in the content of
+    /// the scroll view of `container`.
+    pub fn acquire(&mut self, mut container: EntityCommands, index: Index) -> Entity {
+        if let Some(entity) = self.items.get(&index) {
+            return *entity;
+        }
+        let entity = self.free.pop().unwrap_or_else(|| {
+            let entity = container
+                .commands()
+                .spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        ..Default::default()
+                    },
+                    Interaction::default(),
+                ))
+                .id();
+            container.queue(move |c: EntityWorldMut<'_>| {
+                if let Some(content_entity) = scroll_content(&c) {
+                    c.into_world_mut()
+                        .entity_mut(content_entity)
+                        .add_child(entity);
+                }
+            });
+            entity
+        });
+        self.items.insert(index, entity);
+        entity
+    }
+
+    /// Hide the widget of an item and keep it for reuse.
+    pub fn release(&mut self, mut commands: Commands, index: &Index) -> Option<Entity> {
+        let entity = self.items.remove(index)?;
+        commands.entity(entity).queue(|mut entity: EntityWorldMut| {
+            if let Some(mut node) = entity.get_mut::<Node>() {
+                node.display = Display::None;
+            }
+        });
+        self.free.push(entity);
+        Some(entity)
+    }
+}
+
+/// Move an item widget to `rect` in the content of the scroll view.
+///
+/// Without `fixed_size` the widget keeps its own height, so it can be measured.
+pub fn place_item(mut commands: EntityCommands, rect: Rect, fixed_size: bool) {
+    commands.queue(move |mut entity: EntityWorldMut| {
+        if let Some(mut node) = entity.get_mut::<Node>() {
+            node.display = Display::Flex;
+            node.left = Val::Px(rect.min.x);
+            node.top = Val::Px(rect.min.y);
+            node.width = Val::Px(rect.width());
+            node.height = if fixed_size {
+                Val::Px(rect.height())
+            } else {
+                Val::Auto
+            };
+        }
+    });
+}
+
+/// The content entity of a scroll view.
+pub fn scroll_content(entity: &EntityWorldMut) -> Option<Entity> {
+    entity.get::<UiScrollState>().and_then(|state| *state.content())
+}
+
+/// Resize the content of a scroll view.
+pub fn set_scroll_content_size(mut commands: EntityCommands, size: Vec2) {
+    commands.queue(move |c: EntityWorldMut<'_>| {
+        if let Some(mut style) = scroll_content(&c)
+            .and_then(|content_entity| c.into_world_mut().get_mut::<Node>(content_entity))
+        {
+            style.width = Val::Px(size.x);
+            style.height = Val::Px(size.y);
+        }
+    });
+}
+
+pub struct ItemLayoutFromHeader<Direction> {
+    pub header_entity: Entity,
+    phantom: PhantomData<Direction>,
 }
 
 structstruck::strike! {
@@ -95,3 +425,49 @@ structstruck::strike! {
         >,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_layout() {
+        let mut rows = RowLayout::new(10.0);
+        rows.set_len(100);
+        assert_eq!(rows.total_size(), 1000.0);
+        assert_eq!(rows.range(25.0, 55.0), 2..6);
+
+        assert!(rows.set_size(1, 30.0));
+        assert!(!rows.set_size(1, 30.0));
+        assert_eq!(rows.offset(2), 40.0);
+        assert_eq!(rows.index_at(35.0), 1);
+        assert_eq!(rows.index_at(45.0), 2);
+        assert_eq!(rows.total_size(), 1020.0);
+        assert_eq!(rows.index_at(1040.0), 102);
+
+        rows.set_len(2);
+        assert_eq!(rows.total_size(), 40.0);
+        rows.set_default_size(20.0);
+        assert_eq!(rows.total_size(), 50.0);
+    }
+
+    #[test]
+    fn test_header() {
+        let mut header = Header::<HonHeader>::new([
+            HeaderCell::new("name", 100.0),
+            HeaderCell::new("size", 50.0),
+            HeaderCell::new("date", 80.0),
+        ]);
+        assert_eq!(header.position(2), 150.0);
+        assert!(header.resize(0, 120.0));
+        assert_eq!(header.position(1), 120.0);
+        assert_eq!(header.position(2), 170.0);
+        assert_eq!(header.total_size(), 250.0);
+        header.resize(1, 0.0);
+        assert_eq!(header.size(1), 16.0);
+
+        assert_eq!(header.toggle_sort(1), Some((1, SortOrder::Ascending)));
+        assert_eq!(header.toggle_sort(1), Some((1, SortOrder::Descending)));
+        assert_eq!(header.toggle_sort(2), Some((2, SortOrder::Ascending)));
+    }
+}
@@ -5,9 +5,9 @@ use derive_builder::Builder;
 use derive_more::From;
 
 use super::{
-    layout::{ItemLayout, ViewAreaLayout, ViewLayouter},
+    layout::{place_item, ItemLayout, RowLayout, ViewAreaLayout, ViewLayouter},
+    selection::{ItemSelectionInfo, NavigationDirection, NavigationModel, SelectionState},
     ContainerViewFactory, ContainerViewModel, DataItem, EntityWorldRef, RangeModel, UpdateModel,
-    UpdateModelTrait,
 };
 use crate::{prelude::*, UiFrameworkSystems};
 
@@ -59,7 +59,8 @@ impl<Item: DataItem, T: ContainerViewFactory<usize, Item> + 'static> ListItemVie
 
 #[bevy_trait_query::queryable]
 pub trait ListViewTrait {
-    fn add(&mut self, commands: EntityCommands, item_index: usize, item_view_entity: Entity);
+    /// Get the widget of an item, widgets of removed items are reused.
+    fn acquire(&mut self, commands: EntityCommands, item_index: usize) -> Entity;
     fn remove(&mut self, commands: EntityCommands, item_index: usize);
     fn get_entity(&self, item_index: usize) -> Option<Entity>;
     fn set_size(&mut self, commands: EntityCommands, size: Vec2);
@@ -68,47 +69,23 @@ pub trait ListViewTrait {
 #[derive(Component, Default, Builder, Reflect, Clone)]
 pub struct ListViewLayout {
     pub item_size: Vec2,
+    /// Items in this distance around the viewport also get a widget.
+    pub overscan: f32,
+    /// Measure the height of the item widgets instead of using `item_size.y`.
+    pub variable_size: bool,
     pub items: BTreeMap<usize, ItemLayout>,
+    pub rows: RowLayout,
     pub view_area: ViewAreaLayout,
     pub range: Range<usize>,
 }
 
 impl ViewLayouter<usize> for ListViewLayout {
     fn add(&mut self, _entity: EntityCommands, index: usize) -> ItemLayout {
-        if let Some(layout) = self.items.get(&index) {
-            return layout.clone();
-        }
-        let lower = self
-            .items
-            .lower_bound(std::ops::Bound::Included(&index))
-            .peek_prev();
-        let upper = self
-            .items
-            .upper_bound(std::ops::Bound::Included(&index))
-            .peek_next();
-
-        let position = match (lower, upper) {
-            (None, None) => index as f32 * self.item_size.y,
-            (None, Some(upper)) => upper.1.rect.min.y - (upper.0 - index) as f32 * self.item_size.y,
-            (Some(lower), None) => {
-                lower.1.rect.max.y + (index - lower.0 - 1) as f32 * self.item_size.y
-            }
-            (Some(lower), Some(upper)) => {
-                if upper.0 - index >= index - lower.0 {
-                    upper.1.rect.min.y - (upper.0 - index) as f32 * self.item_size.y
-                } else {
-                    lower.1.rect.max.y + (index - lower.0 - 1) as f32 * self.item_size.y
-                }
-            }
+        let layout = ItemLayout {
+            rect: self.item_rect(index),
         };
-
-        self.items.insert(
-            index,
-            ItemLayout {
-                rect: Rect::new(0.0, position, self.item_size.x, position + self.item_size.y),
-            },
-        );
-        self.items[&index].clone()
+        self.items.insert(index, layout.clone());
+        layout
     }
 
     fn remove(&mut self, mut entiy: EntityCommands, index: usize) {
@@ -130,8 +107,9 @@ impl ViewLayouter<usize> for ListViewLayout {
 
     fn truncate(&mut self, _commands: Commands) -> Vec<usize> {
         let mut removed_items = vec![];
-        self.items.retain(|k, v| {
-            let r = v.rect.intersect(self.view_area.rect).is_empty();
+        let range = self.range.clone();
+        self.items.retain(|k, _| {
+            let r = range.contains(k);
             if !r {
                 removed_items.push(*k);
             }
@@ -151,8 +129,49 @@ impl ListViewLayout {
     }
 
     pub fn get_index_range(&self) -> Range<usize> {
-        (self.view_area.rect.min.y / self.item_size.y).floor() as usize
-            ..(self.view_area.rect.max.y / self.item_size.y).ceil() as usize + 1
+        let rect = self.view_area.rect;
+        self.rows
+            .range(rect.min.y - self.overscan, rect.max.y + self.overscan)
+    }
+
+    pub fn item_rect(&self, index: usize) -> Rect {
+        let top = self.rows.offset(index);
+        Rect::new(0.0, top, self.item_size.x, top + self.rows.size(index))
+    }
+
+    pub fn content_size(&self) -> Vec2 {
+        Vec2::new(self.item_size.x, self.rows.total_size())
+    }
+}
+
+impl NavigationModel<usize> for ListViewLayout {
+    fn navigate(&mut self, from: Option<&usize>, direction: NavigationDirection) -> Option<usize> {
+        let last = self.rows.len().checked_sub(1)?;
+        let Some(&from) = from else {
+            return Some(match direction {
+                NavigationDirection::Up | NavigationDirection::End => last,
+                _ => 0,
+            });
+        };
+        let page = self.view_area.rect.height();
+        Some(match direction {
+            NavigationDirection::Up => from.saturating_sub(1),
+            NavigationDirection::Down => from + 1,
+            NavigationDirection::PageUp => self.rows.index_at(self.rows.offset(from) - page),
+            NavigationDirection::PageDown => self.rows.index_at(self.rows.offset(from) + page),
+            NavigationDirection::Home => 0,
+            NavigationDirection::End => last,
+            NavigationDirection::Left | NavigationDirection::Right => return None,
+        }
+        .min(last))
+    }
+
+    fn range(&self, from: &usize, to: &usize) -> Vec<usize> {
+        (*from.min(to)..=*from.max(to)).collect()
+    }
+
+    fn item_rect(&self, index: &usize) -> Option<Rect> {
+        (*index < self.rows.len()).then(|| ListViewLayout::item_rect(self, *index))
     }
 }
 
@@ -160,7 +179,7 @@ pub fn list_bind_data<Item: DataItem>(
     mut set: ParamSet<(
         (
             &World,
-            Query<(Entity, &ListViewLayout, One<&dyn ListViewModel<Item>>)>,
+            Query<(Entity, Ref<ListViewLayout>, One<&dyn ListViewModel<Item>>)>,
         ),
         Query<(
             Entity,
@@ -173,50 +192,70 @@ pub fn list_bind_data<Item: DataItem>(
 ) {
     let (world, query) = set.p0();
     let mut update_list = vec![];
-    for (container_entity, _layout, model) in &query {
+    for (container_entity, layout, model) in &query {
         let entity_ref = EntityWorldRef::new(world, container_entity);
-        if model.update_from_world(entity_ref) {
-            update_list.push((
-                container_entity,
-                model.get_changed(entity_ref),
-                // model.get_changed_in_range(entity_ref, layout.range.clone()), // TODO
-            ));
+        let model_changed = model.update_from_world(entity_ref);
+        if !model_changed && !layout.is_changed() {
+            continue;
+        }
+        let len = model.len(entity_ref);
+        let range = layout.get_index_range();
+        let range = range.start.min(len)..range.end.min(len);
+        let mut items = model.get_changed_in_range(entity_ref, range.clone());
+        if !model_changed {
+            // only the items scrolled into the viewport need data
+            items.retain(|(index, _)| !layout.items.contains_key(index));
         }
+        update_list.push((container_entity, len, range, items));
     }
     let mut query = set.p1();
-    for (container_entity, update_model) in update_list {
+    for (container_entity, len, range, items) in update_list {
         let Ok((container_entity, mut layout, mut view, item_factory)) =
             query.get_mut(container_entity)
         else {
             continue;
         };
-        for (index, changed_item) in update_model.items {
-            let item_layout = layout.add(commands.entity(container_entity), index);
-            let entity = if let Some(item_entity) = view.get_entity(index) {
-                item_entity
-            } else {
-                let entity = commands
-                    .spawn(Node {
-                        top: Val::Px(item_layout.rect.min.y),
-                        height: Val::Px(item_layout.rect.height()),
-                        position_type: PositionType::Absolute,
-                        ..Default::default()
-                    })
-                    .id();
-                view.add(commands.entity(container_entity), index, entity);
-                entity
-            };
-            item_factory.create_item(&index, commands.entity(entity), changed_item);
+        let item_height = layout.item_size.y;
+        if layout.rows.default_size() != item_height {
+            layout.rows.set_default_size(item_height);
         }
-        if let Some(range) = update_model.range {
-            view.set_size(
-                commands.entity(container_entity),
-                Vec2::new(layout.item_size.x, layout.item_size.y * range.end as f32),
-            );
+        if layout.rows.len() != len {
+            layout.rows.set_len(len);
+        }
+        if layout.range != range {
+            layout.range = range;
         }
         for removed_index in layout.truncate(commands.reborrow()) {
             view.remove(commands.entity(container_entity), removed_index);
         }
+        for (index, changed_item) in items {
+            layout.add(commands.entity(container_entity), index);
+            let entity = view.acquire(commands.entity(container_entity), index);
+            commands.entity(entity).insert(ItemSelectionInfo {
+                index,
+                view: container_entity,
+                state: SelectionState::empty(),
+            });
+            item_factory.create_item(&index, commands.entity(entity), changed_item);
+        }
+        let ListViewLayout {
+            item_size,
+            variable_size,
+            items,
+            rows,
+            ..
+        } = layout.bypass_change_detection();
+        for (index, item_layout) in items.iter_mut() {
+            let top = rows.offset(*index);
+            item_layout.rect = Rect::new(0.0, top, item_size.x, top + rows.size(*index));
+            if let Some(entity) = view.get_entity(*index) {
+                place_item(commands.entity(entity), item_layout.rect, !*variable_size);
+            }
+        }
+        view.set_size(
+            commands.entity(container_entity),
+            Vec2::new(item_size.x, rows.total_size()),
+        );
     }
 }
 
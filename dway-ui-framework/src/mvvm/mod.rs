@@ -10,9 +10,16 @@ pub mod viewmodel;
 
 use std::{any::Any, hash::Hash, marker::PhantomData};
 
-use bevy::ecs::system::EntityCommands;
+use bevy::{ecs::system::EntityCommands, ui::UiSystem};
 use list::{ListItemViewFactory, ListViewLayout, ListViewTrait};
-use view::{list::ListView, TextViewFactory};
+use selection::{KeyboardNavigation, SelectionPlugin};
+use table::{TableItemViewFactory, TableViewLayout};
+use tree::TreeItemViewFactory;
+use view::{
+    list::ListView,
+    table::{update_table_header, TableView},
+    TextViewFactory,
+};
 
 use crate::{prelude::*, UiFrameworkSystems};
 
@@ -23,6 +30,7 @@ impl<T: Send + Sync + Any> DataItem for T {
 }
 
 pub trait IndexTrait: Send + Sync + Ord + Eq + Hash + 'static + Clone {}
+impl<T: Send + Sync + Ord + Eq + Hash + 'static + Clone> IndexTrait for T {}
 
 bitflags::bitflags! {
     pub struct ViewItemState: u8 {
@@ -151,11 +159,25 @@ impl Plugin for MvvmPlugin {
     fn build(&self, app: &mut App) {
         app.register_component_as::<dyn ListViewTrait, ListView>()
             .register_type::<ListView>()
+            .register_type::<KeyboardNavigation>()
+            .add_systems(
+                PreUpdate,
+                update_table_header.in_set(UiFrameworkSystems::WidgetInputSystems),
+            )
             .add_systems(
                 PostUpdate,
-                ListView::update_layout.in_set(UiFrameworkSystems::UpdateViewLayout),
+                (
+                    (ListView::update_layout, TableView::update_layout)
+                        .in_set(UiFrameworkSystems::UpdateViewLayout),
+                    TableView::update_header.after(UiFrameworkSystems::UpdateMVVM),
+                    (ListView::measure_items, TableView::measure_items).after(UiSystem::Layout),
+                ),
             )
             .register_type::<ListViewLayout>()
+            .add_plugins((
+                SelectionPlugin::<usize, ListViewLayout>::default(),
+                SelectionPlugin::<usize, TableViewLayout>::default(),
+            ))
             .add_plugins(ViewFactoryPlugin::<String, TextViewFactory>::default());
     }
 }
@@ -167,5 +189,7 @@ impl<Item: DataItem, Impl: Component + ViewFactory<Item>> Plugin for ViewFactory
     fn build(&self, app: &mut App) {
         app.register_component_as::<dyn ViewFactory<Item>, Impl>();
         app.register_component_as::<dyn ListItemViewFactory<Item>, Impl>();
+        app.register_component_as::<dyn TreeItemViewFactory<usize, Item>, Impl>();
+        app.register_component_as::<dyn TableItemViewFactory<Item>, Impl>();
     }
 }
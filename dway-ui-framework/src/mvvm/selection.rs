@@ -1,15 +1,17 @@
-use std::{
-    cmp::{max, min},
-    collections::BTreeSet,
-};
+use std::{collections::BTreeSet, marker::PhantomData};
 
-use bitflags::Flags;
-use petgraph::csr::IndexType;
+use bevy_relationship::reexport::Mutable;
 
 use super::IndexTrait;
-use crate::prelude::*;
+use crate::{
+    input::UiFocusState,
+    prelude::*,
+    widgets::scroll::UiScrollState,
+    UiFrameworkSystems,
+};
 
 bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub struct SelectionState: u8 {
         const Selected = 1;
         const Focused = 1 << 1;
@@ -17,6 +19,7 @@ bitflags::bitflags! {
     }
 }
 
+/// Added to the item widgets of a view, the index changes when the widget is reused.
 #[derive(Component)]
 pub struct ItemSelectionInfo<Index: IndexTrait> {
     pub index: Index,
@@ -24,13 +27,70 @@ pub struct ItemSelectionInfo<Index: IndexTrait> {
     pub state: SelectionState,
 }
 
+#[derive(Component)]
 pub struct FocusModel<Index: IndexTrait> {
-    pub focused: Option<(Index, Entity)>,
+    pub focused: Option<Index>,
+}
+
+impl<Index: IndexTrait> Default for FocusModel<Index> {
+    fn default() -> Self {
+        Self { focused: None }
+    }
 }
 
 #[derive(Component)]
 pub struct SelectionModel<Index: IndexTrait> {
-    pub items: BTreeSet<(Index, Entity)>,
+    pub items: BTreeSet<Index>,
+    /// where a range selection starts
+    pub anchor: Option<Index>,
+}
+
+impl<Index: IndexTrait> Default for SelectionModel<Index> {
+    fn default() -> Self {
+        Self {
+            items: Default::default(),
+            anchor: None,
+        }
+    }
+}
+
+impl<Index: IndexTrait> SelectionModel<Index> {
+    pub fn is_selected(&self, index: &Index) -> bool {
+        self.items.contains(index)
+    }
+
+    pub fn state(&self, focus: &FocusModel<Index>, index: &Index) -> SelectionState {
+        let mut state = SelectionState::empty();
+        state.set(SelectionState::Selected, self.is_selected(index));
+        state.set(SelectionState::Focused, focus.focused.as_ref() == Some(index));
+        state
+    }
+}
+
+/// Marks a view which uses the arrow keys to move between its items.
+#[derive(Component, Default, Reflect)]
+pub struct KeyboardNavigation;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NavigationDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+/// Implemented by the layouts of views to move the focus between items.
+pub trait NavigationModel<Index> {
+    /// The item next to `from`, or the first item if nothing is focused.
+    fn navigate(&mut self, from: Option<&Index>, direction: NavigationDirection) -> Option<Index>;
+    /// The items from `from` to `to` in the order of the view.
+    fn range(&self, from: &Index, to: &Index) -> Vec<Index>;
+    /// The rect of an item in the content of the scroll view.
+    fn item_rect(&self, index: &Index) -> Option<Rect>;
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -43,114 +103,276 @@ pub enum SelectMode {
 
 #[derive(Message)]
 pub struct SelectItemRequest<Index: IndexTrait> {
-    item_entity: Entity,
-    container_entity: Entity,
-    index: Index,
-    mode: SelectMode,
+    pub container_entity: Entity,
+    pub index: Index,
+    pub mode: SelectMode,
+}
+
+/// Sent when `Enter` is pressed on the focused item of a view.
+#[derive(Message, Debug, Clone)]
+pub struct ItemActivated<Index: IndexTrait> {
+    pub view: Entity,
+    pub index: Index,
 }
 
 #[derive(Component)]
 pub struct DontSelect;
 
-pub fn select_item_command(mut entity: EntityWorldMut) {
-    let (_ctrl, _shift) = entity.world_scope(|world| {
-        let keys = world.resource::<ButtonInput<KeyCode>>();
-        let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
-        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
-        (ctrl, shift)
-    });
+fn select_mode(keys: &ButtonInput<KeyCode>) -> SelectMode {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if ctrl {
+        SelectMode::Toggle
+    } else if shift {
+        SelectMode::AddByRange
+    } else {
+        SelectMode::Single
+    }
 }
 
 pub fn update_selection<Index: IndexTrait>(
     mut item_query: Query<
-        (Entity, &mut ItemSelectionInfo<Index>, &Interaction),
+        (&mut ItemSelectionInfo<Index>, &Interaction),
         (Without<DontSelect>, Changed<Interaction>),
     >,
     mut event_writer: MessageWriter<SelectItemRequest<Index>>,
     keys: Res<ButtonInput<KeyCode>>,
 ) {
-    for (entity, mut view_ref, interaction) in &mut item_query {
+    for (mut view_ref, interaction) in &mut item_query {
         let pressed = *interaction == Interaction::Pressed;
         let mouse_release = !pressed && view_ref.state.contains(SelectionState::Pressed);
         view_ref.state.set(SelectionState::Pressed, pressed);
 
-        let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
-        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
-        let mode = if ctrl {
-            SelectMode::Toggle
-        } else if shift {
-            SelectMode::AddByRange
-        } else {
-            SelectMode::Single
-        };
-
         if mouse_release {
             event_writer.write(SelectItemRequest {
-                item_entity: entity,
                 container_entity: view_ref.view,
                 index: view_ref.index.clone(),
-                mode,
+                mode: select_mode(&keys),
             });
         }
     }
 }
 
-pub fn do_select<Index: IndexTrait>(
-    mut item_query: Query<&mut ItemSelectionInfo<Index>>,
-    mut container: Query<&mut SelectionModel<Index>>,
+pub fn do_select<Index: IndexTrait, Layout: NavigationModel<Index> + Component>(
+    mut container: Query<(
+        &Layout,
+        &mut SelectionModel<Index>,
+        &mut FocusModel<Index>,
+    )>,
     mut event_reader: MessageReader<SelectItemRequest<Index>>,
+    mut focus_state: ResMut<UiFocusState>,
 ) {
     for event in event_reader.read() {
-        let mut set_selected = |_entity: Entity, selected: bool| {
-            let Ok(mut selection_info) = item_query.get_mut(event.item_entity) else {
-                return;
-            };
-            selection_info.state.set(SelectionState::Selected, selected);
-        };
-        let Ok(mut selection_model) = container.get_mut(event.container_entity) else {
+        let Ok((layout, mut selection_model, mut focus_model)) =
+            container.get_mut(event.container_entity)
+        else {
             continue;
         };
         let index = event.index.clone();
-        let item_entity = event.item_entity;
         match event.mode {
             SelectMode::Single => {
-                let items = BTreeSet::from_iter([(index, item_entity)]);
-                let removed_items = std::mem::replace(&mut selection_model.items, items);
-                for (_, removed_entity) in removed_items {
-                    set_selected(removed_entity, false);
-                }
-                set_selected(event.item_entity, true);
+                selection_model.items = BTreeSet::from_iter([index.clone()]);
+                selection_model.anchor = Some(index.clone());
             }
             SelectMode::AddByRange => {
-                let item = (index, item_entity);
-                let _min = selection_model
-                    .items
-                    .first()
-                    .map(|i| min(i, &item))
-                    .unwrap_or(&item);
-                let _max = selection_model
-                    .items
-                    .last()
-                    .map(|i| max(i, &item))
-                    .unwrap_or(&item);
-                todo!();
+                let anchor = selection_model
+                    .anchor
+                    .clone()
+                    .unwrap_or_else(|| index.clone());
+                selection_model.items = BTreeSet::from_iter(layout.range(&anchor, &index));
+                selection_model.anchor = Some(anchor);
             }
             SelectMode::Add => {
-                let added = selection_model.items.insert((index, item_entity));
-                if added {
-                    set_selected(event.item_entity, true);
-                }
+                selection_model.items.insert(index.clone());
+                selection_model.anchor = Some(index.clone());
             }
             SelectMode::Toggle => {
-                let item = (index, item_entity);
-                if selection_model.items.contains(&item) {
-                    selection_model.items.remove(&item);
-                    set_selected(event.item_entity, false);
-                } else {
-                    selection_model.items.insert(item);
-                    set_selected(event.item_entity, true);
+                if !selection_model.items.remove(&index) {
+                    selection_model.items.insert(index.clone());
                 }
+                selection_model.anchor = Some(index.clone());
+            }
+        }
+        if focus_model.focused.as_ref() != Some(&index) {
+            focus_model.focused = Some(index);
+        }
+        focus_state.navigation_focus = Some(event.container_entity);
+    }
+}
+
+/// Copy the selection and the focus of the views to their item widgets.
+pub fn sync_item_selection_state<Index: IndexTrait>(
+    mut item_query: Query<&mut ItemSelectionInfo<Index>>,
+    container_query: Query<
+        (Ref<SelectionModel<Index>>, Ref<FocusModel<Index>>),
+        With<SelectionModel<Index>>,
+    >,
+) {
+    for mut info in &mut item_query {
+        let Ok((selection, focus)) = container_query.get(info.view) else {
+            continue;
+        };
+        if !info.is_changed() && !selection.is_changed() && !focus.is_changed() {
+            continue;
+        }
+        let state =
+            selection.state(&focus, &info.index) | (info.state & SelectionState::Pressed);
+        if info.state != state {
+            info.state = state;
+        }
+    }
+}
+
+pub fn keyboard_navigation_system<
+    Index: IndexTrait,
+    Layout: NavigationModel<Index> + Component<Mutability = Mutable>,
+>(
+    keys: Res<ButtonInput<KeyCode>>,
+    focus_state: Res<UiFocusState>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Layout,
+            &mut FocusModel<Index>,
+            Option<&mut UiScrollState>,
+        ),
+        With<KeyboardNavigation>,
+    >,
+    mut select_request: MessageWriter<SelectItemRequest<Index>>,
+    mut activate_event: MessageWriter<ItemActivated<Index>>,
+    mut content_query: Query<&mut Node>,
+) {
+    let Some(focused_view) = focus_state.navigation_focus else {
+        return;
+    };
+    let Ok((entity, mut layout, mut focus, scroll)) = query.get_mut(focused_view) else {
+        return;
+    };
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    if let Some(focused) = &focus.focused {
+        if keys.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+            activate_event.write(ItemActivated {
+                view: entity,
+                index: focused.clone(),
+            });
+            return;
+        }
+        if keys.just_pressed(KeyCode::Space) {
+            select_request.write(SelectItemRequest {
+                container_entity: entity,
+                index: focused.clone(),
+                mode: if ctrl {
+                    SelectMode::Toggle
+                } else {
+                    SelectMode::Single
+                },
+            });
+            return;
+        }
+    }
+
+    let direction = [
+        (KeyCode::ArrowUp, NavigationDirection::Up),
+        (KeyCode::ArrowDown, NavigationDirection::Down),
+        (KeyCode::ArrowLeft, NavigationDirection::Left),
+        (KeyCode::ArrowRight, NavigationDirection::Right),
+        (KeyCode::PageUp, NavigationDirection::PageUp),
+        (KeyCode::PageDown, NavigationDirection::PageDown),
+        (KeyCode::Home, NavigationDirection::Home),
+        (KeyCode::End, NavigationDirection::End),
+    ]
+    .into_iter()
+    .find(|(key, _)| keys.just_pressed(*key))
+    .map(|(_, direction)| direction);
+    let Some(direction) = direction else {
+        return;
+    };
+    let Some(next) = layout.navigate(focus.focused.as_ref(), direction) else {
+        return;
+    };
+    if focus.focused.as_ref() == Some(&next) {
+        return;
+    }
+    focus.focused = Some(next.clone());
+    // moving with ctrl only moves the focus
+    if !ctrl {
+        select_request.write(SelectItemRequest {
+            container_entity: entity,
+            index: next.clone(),
+            mode: select_mode(&keys),
+        });
+    }
+
+    if let (Some(mut scroll), Some(rect)) = (scroll, layout.item_rect(&next)) {
+        let offset = *scroll.offset();
+        let size = *scroll.size();
+        let mut new_offset = offset;
+        if rect.min.y < offset.y {
+            new_offset.y = rect.min.y;
+        } else if rect.max.y > offset.y + size.y {
+            new_offset.y = rect.max.y - size.y;
+        }
+        if new_offset != offset {
+            if let Some(mut content) =
+                (*scroll.content()).and_then(|e| content_query.get_mut(e).ok())
+            {
+                scroll.scroll_to(&mut content, new_offset);
             }
         }
     }
 }
+
+/// Selection state of the item widgets of views indexed by `Index`.
+pub struct ItemSelectionPlugin<Index: IndexTrait>(PhantomData<Index>);
+
+impl<Index: IndexTrait> Default for ItemSelectionPlugin<Index> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Index: IndexTrait> Plugin for ItemSelectionPlugin<Index> {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SelectItemRequest<Index>>()
+            .add_message::<ItemActivated<Index>>()
+            .add_systems(
+                PreUpdate,
+                update_selection::<Index>.in_set(UiFrameworkSystems::WidgetInputSystems),
+            )
+            .add_systems(
+                PostUpdate,
+                sync_item_selection_state::<Index>.after(UiFrameworkSystems::UpdateMVVM),
+            );
+    }
+}
+
+/// Mouse and keyboard selection of the items of views using `Layout`.
+pub struct SelectionPlugin<Index: IndexTrait, Layout>(PhantomData<(Index, Layout)>);
+
+impl<Index: IndexTrait, Layout> Default for SelectionPlugin<Index, Layout> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<
+        Index: IndexTrait,
+        Layout: NavigationModel<Index> + Component<Mutability = Mutable> + Send + Sync + 'static,
+    > Plugin for SelectionPlugin<Index, Layout>
+{
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ItemSelectionPlugin<Index>>() {
+            app.add_plugins(ItemSelectionPlugin::<Index>::default());
+        }
+        app.add_systems(
+            PreUpdate,
+            (
+                keyboard_navigation_system::<Index, Layout>,
+                do_select::<Index, Layout>.after(update_selection::<Index>),
+            )
+                .chain()
+                .in_set(UiFrameworkSystems::WidgetInputSystems),
+        );
+    }
+}
@@ -1,9 +1,18 @@
-use std::{marker::PhantomData, ops::Range};
+use std::{collections::BTreeMap, marker::PhantomData, ops::Range};
 
 use bevy::ecs::system::EntityCommands;
 
-use super::{ContainerViewModel, DataItem, RangeModel};
-use crate::prelude::*;
+use super::{
+    layout::{
+        place_item, set_scroll_content_size, Header, HonHeader, ItemLayout, RowLayout,
+        SortOrder, ViewAreaLayout,
+    },
+    selection::{ItemSelectionInfo, NavigationDirection, NavigationModel, SelectionState},
+    view::table::TableView,
+    viewmodel::SimpleTableViewModel,
+    ContainerViewFactory, DataItem, EntityWorldRef, RangeModel,
+};
+use crate::{prelude::*, UiFrameworkSystems};
 
 pub struct TableRangeModel {
     pub min: [usize; 2],
@@ -40,24 +49,228 @@ pub struct TableUpdateModel<Item> {
 }
 
 #[bevy_trait_query::queryable]
-pub trait TableItemViewFactory<Item: DataItem>:
-    ContainerViewModel<[usize; 2], Item, UpdateModel = TableUpdateModel<Item>>
+pub trait TableItemViewFactory<Item: DataItem>: ContainerViewFactory<[usize; 2], Item> {}
+
+impl<Item: DataItem, T: ContainerViewFactory<[usize; 2], Item> + 'static> TableItemViewFactory<Item>
+    for T
 {
-    fn create_raw(&self, _index: usize, _commands: EntityCommands) {
-    }
 }
 
 #[bevy_trait_query::queryable]
 pub trait TableViewModel<Item: DataItem> {
-    fn len(&self) -> [usize; 2];
-    fn get(&self, index: [usize; 2]) -> Item;
-    fn update(&self, index: [usize; 2], item: Item) -> bool;
-    fn update_all(
-        &self,
-        range1: Range<usize>,
-        range2: Range<usize>,
-        items: &dyn Iterator<Item = Item>,
-    ) -> bool;
+    /// `[rows, columns]`
+    fn len(&self, entity: EntityWorldRef) -> [usize; 2];
+    fn is_changed(&self, entity: EntityWorldRef) -> bool;
+    fn get(&self, index: [usize; 2], entity: EntityWorldRef) -> Option<Item>;
+    fn set(&self, index: [usize; 2], commands: EntityCommands, item: Item);
+    /// Sort the rows by a column, models which can not be sorted ignore it.
+    fn sort(&self, _column: usize, _order: SortOrder, _commands: EntityCommands) {
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct TableViewLayout {
+    pub row_height: f32,
+    pub header_height: f32,
+    /// Rows in this distance around the viewport also get widgets.
+    pub overscan: f32,
+    /// Measure the height of the cell widgets instead of using `row_height`.
+    pub variable_size: bool,
+    pub items: BTreeMap<[usize; 2], ItemLayout>,
+    pub rows: RowLayout,
+    pub view_area: ViewAreaLayout,
+    pub range: Range<usize>,
+    applied_sort: Option<(usize, SortOrder)>,
+}
+
+impl TableViewLayout {
+    pub fn new(row_height: f32, header_height: f32) -> Self {
+        Self {
+            row_height,
+            header_height,
+            overscan: 0.0,
+            variable_size: false,
+            items: Default::default(),
+            rows: RowLayout::new(row_height),
+            view_area: Default::default(),
+            range: 0..0,
+            applied_sort: None,
+        }
+    }
+
+    pub fn get_index_range(&self) -> Range<usize> {
+        let rect = self.view_area.rect;
+        self.rows.range(
+            rect.min.y - self.header_height - self.overscan,
+            rect.max.y - self.header_height + self.overscan,
+        )
+    }
+
+    pub fn cell_rect(&self, header: &Header<HonHeader>, [row, column]: [usize; 2]) -> Rect {
+        let top = self.header_height + self.rows.offset(row);
+        let left = header.position(column);
+        Rect::new(
+            left,
+            top,
+            left + header.size(column),
+            top + self.rows.size(row),
+        )
+    }
+
+    fn row_rect(&self, row: usize) -> Rect {
+        let top = self.header_height + self.rows.offset(row);
+        Rect::new(
+            self.view_area.rect.min.x,
+            top,
+            self.view_area.rect.max.x,
+            top + self.rows.size(row),
+        )
+    }
+}
+
+impl NavigationModel<usize> for TableViewLayout {
+    fn navigate(&mut self, from: Option<&usize>, direction: NavigationDirection) -> Option<usize> {
+        let last = self.rows.len().checked_sub(1)?;
+        let Some(&from) = from else {
+            return Some(match direction {
+                NavigationDirection::Up | NavigationDirection::End => last,
+                _ => 0,
+            });
+        };
+        let page = self.view_area.rect.height() - self.header_height;
+        Some(match direction {
+            NavigationDirection::Up => from.saturating_sub(1),
+            NavigationDirection::Down => from + 1,
+            NavigationDirection::PageUp => self.rows.index_at(self.rows.offset(from) - page),
+            NavigationDirection::PageDown => self.rows.index_at(self.rows.offset(from) + page),
+            NavigationDirection::Home => 0,
+            NavigationDirection::End => last,
+            NavigationDirection::Left | NavigationDirection::Right => return None,
+        }
+        .min(last))
+    }
+
+    fn range(&self, from: &usize, to: &usize) -> Vec<usize> {
+        (*from.min(to)..=*from.max(to)).collect()
+    }
+
+    fn item_rect(&self, index: &usize) -> Option<Rect> {
+        // keep the row below the pinned header
+        (*index < self.rows.len()).then(|| {
+            let mut rect = self.row_rect(*index);
+            rect.min.y -= self.header_height;
+            rect
+        })
+    }
+}
+
+pub fn table_bind_data<Item: DataItem>(
+    mut set: ParamSet<(
+        (
+            &World,
+            Query<(
+                Entity,
+                Ref<TableViewLayout>,
+                Ref<Header<HonHeader>>,
+                One<&dyn TableViewModel<Item>>,
+            )>,
+        ),
+        Query<(
+            &mut TableViewLayout,
+            &Header<HonHeader>,
+            &mut TableView,
+            One<&dyn TableItemViewFactory<Item>>,
+        )>,
+    )>,
+    mut commands: Commands,
+) {
+    let (world, query) = set.p0();
+    let mut update_list = vec![];
+    for (container_entity, layout, header, model) in &query {
+        let entity_ref = EntityWorldRef::new(world, container_entity);
+        let model_changed = model.is_changed(entity_ref);
+        let sort_changed = header.sort != layout.applied_sort;
+        if sort_changed {
+            if let Some((column, order)) = header.sort {
+                model.sort(column, order, commands.entity(container_entity));
+            }
+        }
+        if !model_changed && !sort_changed && !layout.is_changed() && !header.is_changed() {
+            continue;
+        }
+        let [rows, columns] = model.len(entity_ref);
+        let range = layout.get_index_range();
+        let range = range.start.min(rows)..range.end.min(rows);
+        let mut items = vec![];
+        for row in range.clone() {
+            for column in 0..columns.min(header.len()) {
+                let index = [row, column];
+                // only the cells scrolled into the viewport need data
+                if !model_changed && layout.items.contains_key(&index) {
+                    continue;
+                }
+                if let Some(item) = model.get(index, entity_ref) {
+                    items.push((index, item));
+                }
+            }
+        }
+        update_list.push((container_entity, rows, range, header.sort, items));
+    }
+    let mut query = set.p1();
+    for (container_entity, len, range, sort, items) in update_list {
+        let Ok((mut layout, header, mut view, item_factory)) = query.get_mut(container_entity)
+        else {
+            continue;
+        };
+        if layout.applied_sort != sort {
+            layout.applied_sort = sort;
+        }
+        let row_height = layout.row_height;
+        if layout.rows.default_size() != row_height {
+            layout.rows.set_default_size(row_height);
+        }
+        if layout.rows.len() != len {
+            layout.rows.set_len(len);
+        }
+        if layout.range != range {
+            layout.range = range.clone();
+        }
+        let columns = header.len();
+        let layout = layout.bypass_change_detection();
+        layout.items.retain(|[row, column], _| {
+            let r = range.contains(row) && *column < columns;
+            if !r {
+                view.pool
+                    .release(commands.reborrow(), &[*row, *column]);
+            }
+            r
+        });
+        for (index, changed_item) in items {
+            layout.items.insert(index, ItemLayout::default());
+            let entity = view.pool.acquire(commands.entity(container_entity), index);
+            commands.entity(entity).insert(ItemSelectionInfo {
+                index: index[0],
+                view: container_entity,
+                state: SelectionState::empty(),
+            });
+            item_factory.create_item(&index, commands.entity(entity), changed_item);
+        }
+        let indices: Vec<_> = layout.items.keys().copied().collect();
+        for index in indices {
+            let rect = layout.cell_rect(header, index);
+            layout.items.insert(index, ItemLayout { rect });
+            if let Some(entity) = view.pool.get(&index) {
+                place_item(commands.entity(entity), rect, !layout.variable_size);
+            }
+        }
+        set_scroll_content_size(
+            commands.entity(container_entity),
+            Vec2::new(
+                header.total_size(),
+                layout.header_height + layout.rows.total_size(),
+            ),
+        );
+    }
 }
 
 #[derive(Component)]
@@ -78,3 +291,15 @@ Table<Item: DataItem>=>
 @use_state(phantim: PhantomData<Item>)
 <Node/>
 }
+
+#[derive(Default)]
+pub struct TableViewModelPlugin<Item: DataItem>(PhantomData<Item>);
+impl<Item: DataItem + Clone + PartialOrd> Plugin for TableViewModelPlugin<Item> {
+    fn build(&self, app: &mut App) {
+        app.register_component_as::<dyn TableViewModel<Item>, SimpleTableViewModel<Item>>()
+            .add_systems(
+                PostUpdate,
+                table_bind_data::<Item>.in_set(UiFrameworkSystems::UpdateMVVM),
+            );
+    }
+}
@@ -1,13 +1,21 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+    ops::Range,
+};
 
-use bevy::ecs::system::EntityCommands;
+use bevy::{ecs::system::EntityCommands, ui::UiSystem};
 
 use super::{
-    layout::{ItemLayout, ViewAreaLayout, ViewLayouter},
-    ContainerViewFactory, ContainerViewModel, DataItem, EntityWorldRef,
-    IndexTrait, RangeModel, UpdateModel, ViewFactory,
+    layout::{place_item, ItemLayout, RowLayout, ViewAreaLayout, ViewLayouter},
+    selection::{
+        ItemSelectionInfo, NavigationDirection, NavigationModel, SelectionPlugin, SelectionState,
+    },
+    view::tree::TreeView,
+    ContainerViewFactory, ContainerViewModel, DataItem, EntityWorldRef, IndexTrait, RangeModel,
+    UpdateModel, ViewFactory,
 };
-use crate::prelude::*;
+use crate::{prelude::*, UiFrameworkSystems};
 
 pub struct TreeRangeModel<NodeId> {
     pub items: BTreeSet<NodeId>,
@@ -56,97 +64,348 @@ impl<
 {
 }
 
+#[derive(Clone, Debug)]
 pub struct TreeItemLayout<NodeId> {
     pub layout: ItemLayout,
     pub parent: Option<NodeId>,
     pub children: Option<Vec<NodeId>>,
     pub level: usize,
-    pub sub_tree_size: Rect,
+    pub expanded: bool,
+    /// the row of the node, `None` if a parent is collapsed
+    pub row: Option<usize>,
+    /// the measured height of the item widget
+    pub size: Option<f32>,
+}
+
+impl<NodeId> TreeItemLayout<NodeId> {
+    pub fn has_children(&self) -> bool {
+        self.children.as_ref().map(|c| !c.is_empty()).unwrap_or(false)
+    }
+}
+
+/// The state of a node shown by an item widget of a tree view.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct TreeItemState {
+    pub level: usize,
+    pub expanded: bool,
+    pub has_children: bool,
+}
+
+/// The rows of a tree view after a rebuild.
+pub struct TreeRows<NodeId> {
+    items: BTreeMap<NodeId, TreeItemLayout<NodeId>>,
+    rows: Vec<NodeId>,
+    row_layout: RowLayout,
 }
 
 #[derive(Component)]
 pub struct TreeViewLayout<NodeId: IndexTrait> {
     pub expand_by_default: bool,
     pub item_size: Vec2,
+    /// the horizontal offset of each level
+    pub indent: f32,
+    /// Items in this distance around the viewport also get a widget.
+    pub overscan: f32,
+    /// Measure the height of the item widgets instead of using `item_size.y`.
+    pub variable_size: bool,
+    /// the nodes which are not hidden by a collapsed parent
     pub items: BTreeMap<NodeId, TreeItemLayout<NodeId>>,
+    pub rows: Vec<NodeId>,
+    pub row_layout: RowLayout,
     pub view_area: ViewAreaLayout,
-    pub used_area: Rect,
-    pub dirty_area: Rect,
+    pub range: Range<usize>,
+    bound: BTreeSet<NodeId>,
+    expand_state: BTreeMap<NodeId, bool>,
+    dirty: bool,
 }
 
 impl<NodeId: IndexTrait> TreeViewLayout<NodeId> {
+    pub fn new(item_size: Vec2) -> Self {
+        Self {
+            expand_by_default: false,
+            item_size,
+            indent: 16.0,
+            overscan: 0.0,
+            variable_size: false,
+            items: Default::default(),
+            rows: Default::default(),
+            row_layout: RowLayout::new(item_size.y),
+            view_area: Default::default(),
+            range: 0..0,
+            bound: Default::default(),
+            expand_state: Default::default(),
+            dirty: true,
+        }
+    }
+
     pub fn row_count(&self) -> usize {
         (self.view_area.rect.height() / self.item_size.y).ceil() as usize
     }
 
-    pub fn has_space(&self) -> bool {
-        self.used_area.max.y < self.view_area.rect.max.y
+    pub fn is_expanded(&self, node: &NodeId) -> bool {
+        self.expand_state
+            .get(node)
+            .copied()
+            .unwrap_or(self.expand_by_default)
+    }
+
+    pub fn set_expanded(&mut self, node: NodeId, expanded: bool) {
+        if self.is_expanded(&node) != expanded {
+            self.expand_state.insert(node, expanded);
+            self.dirty = true;
+        }
     }
 
-    fn add(&mut self, _entity: EntityCommands, _index: NodeId) -> ItemLayout {
-        todo!()
+    pub fn toggle(&mut self, node: NodeId) {
+        let expanded = self.is_expanded(&node);
+        self.set_expanded(node, !expanded);
+    }
+
+    /// Whether the rows need to be rebuilt from the model.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn get_row(&self, node: &NodeId) -> Option<usize> {
+        self.items.get(node).and_then(|i| i.row)
+    }
+
+    /// Flatten the expanded part of the tree into rows.
+    pub fn build_rows(
+        &self,
+        roots: Vec<NodeId>,
+        mut get_children: impl FnMut(&NodeId) -> Option<Vec<NodeId>>,
+    ) -> TreeRows<NodeId> {
+        let mut items = BTreeMap::new();
+        let mut rows = vec![];
+        let mut stack: Vec<_> = roots.into_iter().rev().map(|id| (id, None, 0)).collect();
+        while let Some((id, parent, level)) = stack.pop() {
+            let children = get_children(&id);
+            let expanded = self.is_expanded(&id);
+            if expanded {
+                for child in children.iter().flatten().rev() {
+                    stack.push((child.clone(), Some(id.clone()), level + 1));
+                }
+            }
+            let size = self.items.get(&id).and_then(|i| i.size);
+            items.insert(
+                id.clone(),
+                TreeItemLayout {
+                    layout: ItemLayout::default(),
+                    parent,
+                    children,
+                    level,
+                    expanded,
+                    row: Some(rows.len()),
+                    size,
+                },
+            );
+            rows.push(id);
+        }
+        let mut row_layout = RowLayout::new(self.item_size.y);
+        row_layout.set_sizes(rows.iter().map(|id| items[id].size));
+        TreeRows {
+            items,
+            rows,
+            row_layout,
+        }
+    }
+
+    pub fn set_rows(&mut self, rows: TreeRows<NodeId>) {
+        self.items = rows.items;
+        self.rows = rows.rows;
+        self.row_layout = rows.row_layout;
+        self.dirty = false;
+    }
+
+    pub fn get_index_range(&self) -> Range<usize> {
+        index_range(&self.row_layout, self.view_area.rect, self.overscan, self.rows.len())
+    }
+
+    /// The area of the widget of a node, shifted right by the indent of its level.
+    pub fn row_rect(&self, node: &NodeId) -> Option<Rect> {
+        let item = self.items.get(node)?;
+        let row = item.row?;
+        let top = self.row_layout.offset(row);
+        let left = item.level as f32 * self.indent;
+        Some(Rect::new(
+            left,
+            top,
+            left + self.item_size.x,
+            top + self.row_layout.size(row),
+        ))
+    }
+
+    /// The width of the rows with the indent of the deepest shown level.
+    pub fn content_width(&self) -> f32 {
+        let level = self
+            .rows
+            .iter()
+            .filter_map(|id| self.items.get(id))
+            .map(|item| item.level)
+            .max()
+            .unwrap_or_default();
+        level as f32 * self.indent + self.item_size.x
+    }
+
+    /// Set the measured height of the widget of a node.
+    pub fn set_item_size(&mut self, node: &NodeId, size: f32) {
+        if let Some(item) = self.items.get_mut(node) {
+            item.size = Some(size);
+            if let Some(row) = item.row {
+                self.row_layout.set_size(row, size);
+            }
+        }
     }
 }
 
+fn index_range(row_layout: &RowLayout, rect: Rect, overscan: f32, len: usize) -> Range<usize> {
+    let range = row_layout.range(rect.min.y - overscan, rect.max.y + overscan);
+    range.start.min(len)..range.end.min(len)
+}
+
 impl<NodeId: IndexTrait> ViewLayouter<NodeId> for TreeViewLayout<NodeId> {
-    fn contains(&self, _index: NodeId) -> bool {
-        todo!()
+    fn contains(&self, index: NodeId) -> bool {
+        self.bound.contains(&index)
     }
 
-    fn add(&mut self, _entity: EntityCommands, _index: NodeId) -> ItemLayout {
-        todo!()
+    fn add(&mut self, _entity: EntityCommands, index: NodeId) -> ItemLayout {
+        let layout = ItemLayout {
+            rect: self.row_rect(&index).unwrap_or_default(),
+        };
+        if let Some(item) = self.items.get_mut(&index) {
+            item.layout = layout.clone();
+        }
+        self.bound.insert(index);
+        layout
     }
 
-    fn remove(&mut self, _entity: EntityCommands, _index: NodeId) {
-        todo!()
+    fn remove(&mut self, mut entity: EntityCommands, index: NodeId) {
+        self.bound.remove(&index);
+        entity.despawn();
     }
 
-    fn get_item_layout(&self, _index: NodeId) -> Option<&ItemLayout> {
-        todo!()
+    fn get_item_layout(&self, index: NodeId) -> Option<&ItemLayout> {
+        self.items.get(&index).map(|i| &i.layout)
     }
 
-    fn get_item_layout_mut(&mut self, _index: NodeId) -> Option<&mut ItemLayout> {
-        todo!()
+    fn get_item_layout_mut(&mut self, index: NodeId) -> Option<&mut ItemLayout> {
+        self.items.get_mut(&index).map(|i| &mut i.layout)
     }
 
-    fn set_view_rect(&mut self, _rect: Rect) {
-        todo!()
+    fn set_view_rect(&mut self, rect: Rect) {
+        self.view_area.rect = rect;
     }
 
     fn truncate(&mut self, _commands: Commands) -> Vec<NodeId> {
-        todo!()
+        let mut removed_items = vec![];
+        let range = self.range.clone();
+        let items = &self.items;
+        self.bound.retain(|k| {
+            let r = items
+                .get(k)
+                .and_then(|i| i.row)
+                .map(|row| range.contains(&row))
+                .unwrap_or(false);
+            if !r {
+                removed_items.push(k.clone());
+            }
+            r
+        });
+        removed_items
+    }
+}
+
+impl<NodeId: IndexTrait> NavigationModel<NodeId> for TreeViewLayout<NodeId> {
+    fn navigate(
+        &mut self,
+        from: Option<&NodeId>,
+        direction: NavigationDirection,
+    ) -> Option<NodeId> {
+        let last = self.rows.len().checked_sub(1)?;
+        let Some((from, row)) = from.and_then(|f| Some((f.clone(), self.get_row(f)?))) else {
+            return Some(match direction {
+                NavigationDirection::Up | NavigationDirection::End => self.rows[last].clone(),
+                _ => self.rows[0].clone(),
+            });
+        };
+        let item = &self.items[&from];
+        let (expanded, has_children) = (item.expanded, item.has_children());
+        let parent = item.parent.clone();
+        let first_child = item.children.as_ref().and_then(|c| c.first()).cloned();
+        let page = self.view_area.rect.height();
+        let row = match direction {
+            NavigationDirection::Up => row.saturating_sub(1),
+            NavigationDirection::Down => row + 1,
+            NavigationDirection::PageUp => {
+                self.row_layout.index_at(self.row_layout.offset(row) - page)
+            }
+            NavigationDirection::PageDown => {
+                self.row_layout.index_at(self.row_layout.offset(row) + page)
+            }
+            NavigationDirection::Home => 0,
+            NavigationDirection::End => last,
+            NavigationDirection::Left => {
+                if expanded && has_children {
+                    self.set_expanded(from.clone(), false);
+                    return Some(from);
+                }
+                return parent;
+            }
+            NavigationDirection::Right => {
+                if !has_children {
+                    return None;
+                }
+                if !expanded {
+                    self.set_expanded(from.clone(), true);
+                    return Some(from);
+                }
+                return first_child;
+            }
+        };
+        Some(self.rows[row.min(last)].clone())
+    }
+
+    fn range(&self, from: &NodeId, to: &NodeId) -> Vec<NodeId> {
+        let (Some(from), Some(to)) = (self.get_row(from), self.get_row(to)) else {
+            return vec![to.clone()];
+        };
+        self.rows[from.min(to)..=from.max(to)].to_vec()
+    }
+
+    fn item_rect(&self, index: &NodeId) -> Option<Rect> {
+        self.row_rect(index)
     }
 }
 
 #[bevy_trait_query::queryable]
 pub trait TreeViewTrait<NodeId> {
-    fn add(&mut self, commands: EntityCommands, item_index: NodeId, item_view_entity: Entity);
+    /// Get the widget of a node, widgets of removed nodes are reused.
+    fn acquire(&mut self, commands: EntityCommands, item_index: NodeId) -> Entity;
     fn remove(&mut self, commands: EntityCommands, item_index: NodeId);
     fn get_entity(&self, item_index: &NodeId) -> Option<Entity>;
     fn set_size(&mut self, commands: EntityCommands, size: Vec2);
 }
 
-fn tree_node_bind_data<NodeId: IndexTrait, Item: DataItem>(
-    layout: &mut TreeViewLayout<NodeId>,
-    id: &NodeId,
-    model: &dyn TreeViewModel<NodeId, Item>,
-    entity_ref: EntityWorldRef<'_>,
-    mut entity_commands: EntityCommands<'_>,
+/// Expand or collapse a node of a tree view, `expanded: None` toggles it.
+#[derive(Message, Clone, Debug)]
+pub struct TreeExpandRequest<NodeId: IndexTrait> {
+    pub view: Entity,
+    pub node: NodeId,
+    pub expanded: Option<bool>,
+}
+
+pub fn handle_tree_expand_request<NodeId: IndexTrait>(
+    mut requests: MessageReader<TreeExpandRequest<NodeId>>,
+    mut query: Query<&mut TreeViewLayout<NodeId>>,
 ) {
-    let children = model.get_children(id, entity_ref);
-    let _item_layout = layout.add(entity_commands.reborrow(), id.clone());
-    if let Some(children) = &children {
-        for (child_index, _child) in children {
-            if !layout.has_space() {
-                break;
-            }
-            tree_node_bind_data(
-                layout,
-                child_index,
-                model,
-                entity_ref,
-                entity_commands.reborrow(),
-            );
+    for request in requests.read() {
+        let Ok(mut layout) = query.get_mut(request.view) else {
+            continue;
+        };
+        match request.expanded {
+            Some(expanded) => layout.set_expanded(request.node.clone(), expanded),
+            None => layout.toggle(request.node.clone()),
         }
     }
 }
@@ -157,7 +416,7 @@ pub fn tree_bind_data<NodeId: IndexTrait, Item: DataItem>(
             &World,
             Query<(
                 Entity,
-                &TreeViewLayout<NodeId>,
+                Ref<TreeViewLayout<NodeId>>,
                 One<&dyn TreeViewModel<NodeId, Item>>,
             )>,
         ),
@@ -172,53 +431,177 @@ pub fn tree_bind_data<NodeId: IndexTrait, Item: DataItem>(
 ) {
     let (world, query) = set.p0();
     let mut update_list = vec![];
-    for (container_entity, _layout, model) in &query {
+    for (container_entity, layout, model) in &query {
         let entity_ref = EntityWorldRef::new(world, container_entity);
-        if model.update_from_world(entity_ref) {
-            let changed = model.get_changed(entity_ref);
-            update_list.push((
-                container_entity,
-                changed,
-                // model.get_changed_in_range(entity_ref, layout.range.clone()), // TODO
-            ));
+        let model_changed = model.update_from_world(entity_ref);
+        if !model_changed && !layout.is_changed() {
+            continue;
         }
+        let tree_rows = (model_changed || layout.is_dirty()).then(|| {
+            let roots = model
+                .get_root(entity_ref)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            layout.build_rows(roots, |id| model.get_children_index(id, entity_ref))
+        });
+        let (rows, row_layout) = tree_rows
+            .as_ref()
+            .map(|r| (&r.rows, &r.row_layout))
+            .unwrap_or((&layout.rows, &layout.row_layout));
+        let range = index_range(row_layout, layout.view_area.rect, layout.overscan, rows.len());
+        let items: Vec<_> = rows[range.clone()]
+            .iter()
+            // only the nodes scrolled into the viewport need data
+            .filter(|id| model_changed || !layout.bound.contains(*id))
+            .filter_map(|id| Some((id.clone(), model.get(id, entity_ref)?)))
+            .collect();
+        update_list.push((container_entity, tree_rows, range, items));
     }
     let mut query = set.p1();
-    for (container_entity, update_model) in update_list {
+    for (container_entity, tree_rows, range, items) in update_list {
         let Ok((container_entity, mut layout, mut view, item_factory)) =
             query.get_mut(container_entity)
         else {
             continue;
         };
-        for (index, changed_item) in update_model.items {
-            let item_layout = layout.add(commands.entity(container_entity), index.clone());
-            let _entity = if let Some(item_entity) = view.get_entity(&index) {
-                item_entity
-            } else {
-                let entity = commands
-                    .spawn(Node {
-                        top: Val::Px(item_layout.rect.min.y),
-                        height: Val::Px(item_layout.rect.height()),
-                        position_type: PositionType::Absolute,
-                        ..Default::default()
-                    })
-                    .id();
-                item_factory.create_item(&index, commands.entity(entity), changed_item);
-                view.add(commands.entity(container_entity), index, entity);
-                entity
-            };
+        if let Some(tree_rows) = tree_rows {
+            layout.set_rows(tree_rows);
         }
-        if let Some(range) = update_model.range {
-            view.set_size(
-                commands.entity(container_entity),
-                Vec2::new(
-                    layout.item_size.x,
-                    layout.item_size.y * range.items.len() as f32,
-                ),
-            );
+        if layout.range != range {
+            layout.range = range;
         }
         for removed_index in layout.truncate(commands.reborrow()) {
             view.remove(commands.entity(container_entity), removed_index);
         }
+        for (index, changed_item) in items {
+            layout.add(commands.entity(container_entity), index.clone());
+            let entity = view.acquire(commands.entity(container_entity), index.clone());
+            commands.entity(entity).insert(ItemSelectionInfo {
+                index: index.clone(),
+                view: container_entity,
+                state: SelectionState::empty(),
+            });
+            item_factory.create_item(&index, commands.entity(entity), changed_item);
+        }
+        let layout = layout.bypass_change_detection();
+        for index in layout.bound.clone() {
+            let Some(rect) = layout.row_rect(&index) else {
+                continue;
+            };
+            let item = layout.items.get_mut(&index).unwrap();
+            item.layout.rect = rect;
+            let state = TreeItemState {
+                level: item.level,
+                expanded: item.expanded,
+                has_children: item.has_children(),
+            };
+            if let Some(entity) = view.get_entity(&index) {
+                place_item(commands.entity(entity), rect, !layout.variable_size);
+                commands.entity(entity).insert(state);
+            }
+        }
+        view.set_size(
+            commands.entity(container_entity),
+            Vec2::new(layout.content_width(), layout.row_layout.total_size()),
+        );
+    }
+}
+
+/// Expanding, collapsing and selection of the nodes of tree views.
+pub struct TreeViewPlugin<NodeId: IndexTrait>(PhantomData<NodeId>);
+
+impl<NodeId: IndexTrait> Default for TreeViewPlugin<NodeId> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<NodeId: IndexTrait> Plugin for TreeViewPlugin<NodeId> {
+    fn build(&self, app: &mut App) {
+        app.register_component_as::<dyn TreeViewTrait<NodeId>, TreeView<NodeId>>()
+            .add_message::<TreeExpandRequest<NodeId>>()
+            .add_plugins(SelectionPlugin::<NodeId, TreeViewLayout<NodeId>>::default())
+            .add_systems(
+                PreUpdate,
+                handle_tree_expand_request::<NodeId>
+                    .in_set(UiFrameworkSystems::WidgetInputSystems),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    TreeView::<NodeId>::update_layout
+                        .in_set(UiFrameworkSystems::UpdateViewLayout),
+                    TreeView::<NodeId>::measure_items.after(UiSystem::Layout),
+                ),
+            );
+    }
+}
+
+pub struct TreeViewModelPlugin<NodeId: IndexTrait, Item: DataItem>(PhantomData<(NodeId, Item)>);
+
+impl<NodeId: IndexTrait, Item: DataItem> Default for TreeViewModelPlugin<NodeId, Item> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<NodeId: IndexTrait, Item: DataItem> Plugin for TreeViewModelPlugin<NodeId, Item> {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<TreeViewPlugin<NodeId>>() {
+            app.add_plugins(TreeViewPlugin::<NodeId>::default());
+        }
+        app.add_systems(
+            PostUpdate,
+            tree_bind_data::<NodeId, Item>.in_set(UiFrameworkSystems::UpdateMVVM),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(layout: &mut TreeViewLayout<u32>) {
+        // 1 -> (2 -> (4, 5), 3)
+        let rows = layout.build_rows(vec![1], |id| match id {
+            1 => Some(vec![2, 3]),
+            2 => Some(vec![4, 5]),
+            _ => None,
+        });
+        layout.set_rows(rows);
+    }
+
+    #[test]
+    fn test_tree_expand() {
+        let mut layout = TreeViewLayout::<u32>::new(Vec2::new(100.0, 10.0));
+        build(&mut layout);
+        assert_eq!(layout.rows, vec![1]);
+
+        assert_eq!(layout.navigate(Some(&1), NavigationDirection::Right), Some(1));
+        assert!(layout.is_dirty());
+        build(&mut layout);
+        assert_eq!(layout.rows, vec![1, 2, 3]);
+        assert_eq!(layout.navigate(Some(&1), NavigationDirection::Right), Some(2));
+
+        layout.set_expanded(2, true);
+        build(&mut layout);
+        assert_eq!(layout.rows, vec![1, 2, 4, 5, 3]);
+        assert_eq!(layout.items[&5].level, 2);
+        assert_eq!(layout.row_rect(&5), Some(Rect::new(32.0, 30.0, 132.0, 40.0)));
+        assert_eq!(layout.content_width(), 132.0);
+        assert_eq!(layout.navigate(Some(&5), NavigationDirection::Down), Some(3));
+        assert_eq!(layout.navigate(Some(&5), NavigationDirection::Left), Some(2));
+        assert_eq!(layout.range(&3, &2), vec![2, 4, 5, 3]);
+
+        layout.set_item_size(&2, 30.0);
+        assert_eq!(layout.row_rect(&4).unwrap().min.y, 40.0);
+
+        assert_eq!(layout.navigate(Some(&1), NavigationDirection::Left), Some(1));
+        build(&mut layout);
+        assert_eq!(layout.rows, vec![1]);
+        layout.toggle(1);
+        build(&mut layout);
+        assert_eq!(layout.rows, vec![1, 2, 4, 5, 3]);
     }
 }
@@ -1,11 +1,12 @@
-use std::collections::BTreeMap;
-
-use bevy::{ecs::system::EntityCommands, reflect::Map};
+use accesskit::Role;
+use bevy::ecs::system::EntityCommands;
 
 use crate::{
+    a11y::UiAccessible,
     mvvm::{
-        layout::ViewLayouter,
+        layout::{set_scroll_content_size, ItemPool, ViewLayouter},
         list::{ListViewLayout, ListViewTrait},
+        selection::{FocusModel, KeyboardNavigation, SelectionModel},
     },
     prelude::*,
     widgets::scroll::{UiScroll, UiScrollState},
@@ -13,7 +14,8 @@ use crate::{
 
 #[derive(Component, Default, Reflect)]
 pub struct ListView {
-    pub items: BTreeMap<usize, Entity>,
+    #[reflect(ignore)]
+    pub pool: ItemPool<usize>,
 }
 
 impl ListView {
@@ -44,6 +46,28 @@ impl ListView {
             }
         }
     }
+
+    /// Read the height of the item widgets of lists with `variable_size`, every item in the pool
+    /// is measured since items reused for another index may keep their computed size.
+    pub fn measure_items(
+        mut query: Query<(&Self, &mut ListViewLayout)>,
+        item_query: Query<&ComputedNode>,
+    ) {
+        for (this, mut layout) in &mut query {
+            if !layout.variable_size {
+                continue;
+            }
+            for (index, entity) in this.pool.iter() {
+                let Ok(computed_node) = item_query.get(entity) else {
+                    continue;
+                };
+                let height = computed_node.size().y;
+                if height > 0.0 && (layout.rows.size(*index) - height).abs() >= 0.5 {
+                    layout.rows.set_size(*index, height);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Bundle)]
@@ -51,6 +75,10 @@ pub struct ListViewBundle {
     pub list_view: ListView,
     pub scroll: UiScroll,
     pub node: Node,
+    pub selection: SelectionModel<usize>,
+    pub focus: FocusModel<usize>,
+    pub keyboard_navigation: KeyboardNavigation,
+    pub accessible: UiAccessible,
 }
 
 impl Default for ListViewBundle {
@@ -63,44 +91,28 @@ impl Default for ListViewBundle {
                 vertical: true,
                 create_viewport: true,
             },
+            selection: Default::default(),
+            focus: Default::default(),
+            keyboard_navigation: KeyboardNavigation,
+            accessible: UiAccessible::new(Role::List).focusable(),
         }
     }
 }
 
 impl ListViewTrait for ListView {
-    fn add(&mut self, mut commands: EntityCommands, item_index: usize, item_view_entity: Entity) {
-        self.items.insert(item_index, item_view_entity);
-        commands.queue(move |c: EntityWorldMut<'_>| {
-            if let Some(content_entity) =
-                c.get::<UiScrollState>().and_then(|state| *state.content())
-            {
-                c.into_world_mut()
-                    .entity_mut(content_entity)
-                    .add_child(item_view_entity);
-            }
-        });
+    fn acquire(&mut self, commands: EntityCommands, item_index: usize) -> Entity {
+        self.pool.acquire(commands, item_index)
     }
 
     fn remove(&mut self, mut commands: EntityCommands, item_index: usize) {
-        if let Some(entity) = self.items.remove(&item_index) {
-            commands.commands().entity(entity).despawn();
-        }
+        self.pool.release(commands.commands(), &item_index);
     }
 
     fn get_entity(&self, item_index: usize) -> Option<Entity> {
-        self.items.get(&item_index).cloned()
+        self.pool.get(&item_index)
     }
 
-    fn set_size(&mut self, mut commands: EntityCommands, size: Vec2) {
-        commands.queue(move |c: EntityWorldMut<'_>| {
-            if let Some(mut style) = c
-                .get::<UiScrollState>()
-                .and_then(|state| *state.content())
-                .and_then(|content_entity| c.into_world_mut().get_mut::<Node>(content_entity))
-            {
-                style.width = Val::Px(size.x);
-                style.height = Val::Px(size.y);
-            }
-        });
+    fn set_size(&mut self, commands: EntityCommands, size: Vec2) {
+        set_scroll_content_size(commands, size);
     }
 }
@@ -1,4 +1,6 @@
 pub mod list;
+pub mod table;
+pub mod tree;
 use bevy::ecs::system::EntityCommands;
 
 use super::ViewFactory;
@@ -0,0 +1,235 @@
+use accesskit::Role;
+use bevy::ui::RelativeCursorPosition;
+
+use crate::{
+    a11y::UiAccessible,
+    mvvm::{
+        layout::{Header, HonHeader, ItemPool, SortOrder},
+        selection::{FocusModel, KeyboardNavigation, SelectionModel},
+        table::TableViewLayout,
+    },
+    prelude::*,
+    widgets::scroll::{UiScroll, UiScrollState},
+};
+
+/// The width of the area at the right edge of a header cell which resizes the column.
+pub const RESIZE_HANDLE_SIZE: f32 = 6.0;
+
+#[derive(Component, Default)]
+pub struct TableView {
+    pub pool: ItemPool<[usize; 2]>,
+    pub header_cells: Vec<Entity>,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TableHeaderCell {
+    pub view: Entity,
+    pub column: usize,
+}
+
+impl TableView {
+    pub fn update_layout(
+        mut query: Query<(&mut TableViewLayout, Ref<UiScrollState>), With<Self>>,
+    ) {
+        for (mut layout, scroll_state) in &mut query {
+            if scroll_state.is_changed() {
+                layout.view_area.rect = Rect::from_corners(
+                    scroll_state.offset,
+                    scroll_state.offset + scroll_state.size,
+                );
+            }
+        }
+    }
+
+    /// Create the header cells and keep them at the top of the viewport.
+    pub fn update_header(
+        mut query: Query<(
+            Entity,
+            &mut Self,
+            Ref<TableViewLayout>,
+            Ref<Header<HonHeader>>,
+            Ref<UiScrollState>,
+        )>,
+        theme: Res<Theme>,
+        mut commands: Commands,
+    ) {
+        for (entity, mut this, layout, header, scroll_state) in &mut query {
+            if !header.is_changed() && !scroll_state.is_changed() && !layout.is_changed() {
+                continue;
+            }
+            let Some(content) = *scroll_state.content() else {
+                continue;
+            };
+            while this.header_cells.len() > header.len() {
+                if let Some(cell) = this.header_cells.pop() {
+                    commands.entity(cell).despawn();
+                }
+            }
+            while this.header_cells.len() < header.len() {
+                let cell = commands
+                    .spawn((
+                        TableHeaderCell {
+                            view: entity,
+                            column: this.header_cells.len(),
+                        },
+                        Node {
+                            position_type: PositionType::Absolute,
+                            align_items: AlignItems::Center,
+                            ..style!("px-4")
+                        },
+                        Text::default(),
+                        theme.default_text_style(),
+                        BackgroundColor(theme.color("panel")),
+                        ZIndex(1),
+                        Interaction::default(),
+                        RelativeCursorPosition::default(),
+                        ChildOf(content),
+                    ))
+                    .id();
+                this.header_cells.push(cell);
+            }
+            for (column, (cell, &entity)) in header.cells.iter().zip(&this.header_cells).enumerate()
+            {
+                let arrow = match header.sort {
+                    Some((c, SortOrder::Ascending)) if c == column => " ▲",
+                    Some((c, SortOrder::Descending)) if c == column => " ▼",
+                    _ => "",
+                };
+                let rect = Rect::new(
+                    cell.position,
+                    scroll_state.offset.y,
+                    cell.position + cell.size,
+                    scroll_state.offset.y + layout.header_height,
+                );
+                let title = format!("{}{arrow}", cell.title);
+                commands.entity(entity).queue(move |mut entity: EntityWorldMut| {
+                    if let Some(mut node) = entity.get_mut::<Node>() {
+                        node.left = Val::Px(rect.min.x);
+                        node.top = Val::Px(rect.min.y);
+                        node.width = Val::Px(rect.width());
+                        node.height = Val::Px(rect.height());
+                    }
+                    if let Some(mut text) = entity.get_mut::<Text>() {
+                        if text.0 != title {
+                            text.0 = title;
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    /// Read the height of the cells of tables with `variable_size`, a row is as
+    /// high as its highest cell.
+    pub fn measure_items(
+        mut query: Query<(&Self, &mut TableViewLayout)>,
+        item_query: Query<&ComputedNode>,
+    ) {
+        for (this, mut layout) in &mut query {
+            if !layout.variable_size {
+                continue;
+            }
+            let mut row_heights = std::collections::BTreeMap::<usize, f32>::new();
+            for ([row, _], entity) in this.pool.iter() {
+                let Ok(computed_node) = item_query.get(entity) else {
+                    continue;
+                };
+                let height = row_heights.entry(*row).or_default();
+                *height = height.max(computed_node.size().y);
+            }
+            for (row, height) in row_heights {
+                if height > 0.0 && (layout.rows.size(row) - height).abs() >= 0.5 {
+                    layout.rows.set_size(row, height);
+                }
+            }
+        }
+    }
+}
+
+/// Click a header cell to sort by its column, drag its right edge to resize it.
+pub fn update_table_header(
+    cell_query: Query<(
+        Entity,
+        &TableHeaderCell,
+        &Interaction,
+        &RelativeCursorPosition,
+        &ComputedNode,
+    )>,
+    mut header_query: Query<&mut Header<HonHeader>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut drag: Local<Option<(Entity, Option<f32>)>>,
+) {
+    let local_x = |relative: &RelativeCursorPosition, node: &ComputedNode| {
+        relative.normalized.map(|v| (v.x + 0.5) * node.size().x)
+    };
+    if mouse.just_pressed(MouseButton::Left) {
+        *drag = cell_query
+            .iter()
+            .find(|(_, _, interaction, ..)| **interaction == Interaction::Pressed)
+            .and_then(|(entity, cell, _, relative, node)| {
+                let x = local_x(relative, node)?;
+                let header = header_query.get(cell.view).ok()?;
+                let resizable = header.cells.get(cell.column)?.resizable;
+                // remember the distance to the edge so the edge follows the cursor
+                let resize = (resizable && x > node.size().x - RESIZE_HANDLE_SIZE)
+                    .then(|| header.size(cell.column) - x);
+                Some((entity, resize))
+            });
+    }
+    let Some((entity, resize)) = *drag else {
+        return;
+    };
+    let Ok((_, cell, _, relative, node)) = cell_query.get(entity) else {
+        *drag = None;
+        return;
+    };
+    let Ok(mut header) = header_query.get_mut(cell.view) else {
+        *drag = None;
+        return;
+    };
+    if let Some(offset) = resize {
+        if let Some(x) = local_x(relative, node) {
+            if (header.size(cell.column) - (x + offset)).abs() >= 1.0 {
+                header.resize(cell.column, x + offset);
+            }
+        }
+    } else if mouse.just_released(MouseButton::Left) && relative.cursor_over() {
+        header.toggle_sort(cell.column);
+    }
+    if mouse.just_released(MouseButton::Left) {
+        *drag = None;
+    }
+}
+
+#[derive(Bundle)]
+pub struct TableViewBundle {
+    pub table_view: TableView,
+    pub layout: TableViewLayout,
+    pub header: Header<HonHeader>,
+    pub scroll: UiScroll,
+    pub node: Node,
+    pub selection: SelectionModel<usize>,
+    pub focus: FocusModel<usize>,
+    pub keyboard_navigation: KeyboardNavigation,
+    pub accessible: UiAccessible,
+}
+
+impl TableViewBundle {
+    pub fn new(layout: TableViewLayout, header: Header<HonHeader>) -> Self {
+        Self {
+            table_view: Default::default(),
+            layout,
+            header,
+            node: style!("full"),
+            scroll: UiScroll {
+                horizontal: true,
+                vertical: true,
+                create_viewport: true,
+            },
+            selection: Default::default(),
+            focus: Default::default(),
+            keyboard_navigation: KeyboardNavigation,
+            accessible: UiAccessible::new(Role::Table).focusable(),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use accesskit::Role;
+use bevy::ecs::system::EntityCommands;
+
+use crate::{
+    a11y::UiAccessible,
+    mvvm::{
+        layout::{set_scroll_content_size, ItemPool, ViewLayouter},
+        selection::{FocusModel, KeyboardNavigation, SelectionModel},
+        tree::{TreeViewLayout, TreeViewTrait},
+        IndexTrait,
+    },
+    prelude::*,
+    widgets::scroll::{UiScroll, UiScrollState},
+};
+
+#[derive(Component)]
+pub struct TreeView<NodeId: IndexTrait> {
+    pub pool: ItemPool<NodeId>,
+}
+
+impl<NodeId: IndexTrait> Default for TreeView<NodeId> {
+    fn default() -> Self {
+        Self {
+            pool: Default::default(),
+        }
+    }
+}
+
+impl<NodeId: IndexTrait> TreeView<NodeId> {
+    pub fn update_layout(
+        mut query: Query<(&mut TreeViewLayout<NodeId>, Ref<UiScrollState>), With<Self>>,
+    ) {
+        for (mut layout, scroll_state) in &mut query {
+            if scroll_state.is_changed() {
+                let rect = Rect::from_corners(
+                    scroll_state.offset,
+                    scroll_state.offset + scroll_state.size,
+                );
+                layout.set_view_rect(rect);
+            }
+        }
+    }
+
+    /// Read the height of the item widgets of trees with `variable_size`, every item in the pool
+    /// is measured since items reused for another node may keep their computed size.
+    pub fn measure_items(
+        mut query: Query<(&Self, &mut TreeViewLayout<NodeId>)>,
+        item_query: Query<&ComputedNode>,
+    ) {
+        for (this, mut layout) in &mut query {
+            if !layout.variable_size {
+                continue;
+            }
+            for (index, entity) in this.pool.iter() {
+                let Ok(computed_node) = item_query.get(entity) else {
+                    continue;
+                };
+                let height = computed_node.size().y;
+                let old_height = layout
+                    .get_row(index)
+                    .map(|row| layout.row_layout.size(row));
+                if height > 0.0 && old_height.is_some_and(|h| (h - height).abs() >= 0.5) {
+                    layout.set_item_size(index, height);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct TreeViewBundle<NodeId: IndexTrait> {
+    pub tree_view: TreeView<NodeId>,
+    pub layout: TreeViewLayout<NodeId>,
+    pub scroll: UiScroll,
+    pub node: Node,
+    pub selection: SelectionModel<NodeId>,
+    pub focus: FocusModel<NodeId>,
+    pub keyboard_navigation: KeyboardNavigation,
+    pub accessible: UiAccessible,
+}
+
+impl<NodeId: IndexTrait> TreeViewBundle<NodeId> {
+    pub fn new(layout: TreeViewLayout<NodeId>) -> Self {
+        Self {
+            tree_view: Default::default(),
+            layout,
+            node: style!("full"),
+            scroll: UiScroll {
+                horizontal: false,
+                vertical: true,
+                create_viewport: true,
+            },
+            selection: Default::default(),
+            focus: Default::default(),
+            keyboard_navigation: KeyboardNavigation,
+            accessible: UiAccessible::new(Role::Tree).focusable(),
+        }
+    }
+}
+
+impl<NodeId: IndexTrait> TreeViewTrait<NodeId> for TreeView<NodeId> {
+    fn acquire(&mut self, commands: EntityCommands, item_index: NodeId) -> Entity {
+        self.pool.acquire(commands, item_index)
+    }
+
+    fn remove(&mut self, mut commands: EntityCommands, item_index: NodeId) {
+        self.pool.release(commands.commands(), &item_index);
+    }
+
+    fn get_entity(&self, item_index: &NodeId) -> Option<Entity> {
+        self.pool.get(item_index)
+    }
+
+    fn set_size(&mut self, commands: EntityCommands, size: Vec2) {
+        set_scroll_content_size(commands, size);
+    }
+}
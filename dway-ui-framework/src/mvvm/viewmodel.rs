@@ -1,9 +1,11 @@
-use std::{marker::PhantomData, ops::Range};
+use std::{cmp::Ordering, marker::PhantomData, ops::Range};
 
 use super::{
+    layout::SortOrder,
     list::{ListRangeModel, ListViewModel},
-    ContainerViewModel, DataItem, EntityCommands, EntityWorldRef,
-    UpdateModel, ViewModel,
+    table::TableViewModel,
+    tree::{TreeRangeModel, TreeViewModel},
+    ContainerViewModel, DataItem, EntityCommands, EntityWorldRef, UpdateModel, ViewModel,
 };
 use crate::prelude::*;
 
@@ -103,11 +105,168 @@ impl<Item: DataItem + Clone> ContainerViewModel<usize, Item> for SimpleListViewM
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct SimpleTreeNode<Item> {
+    pub item: Item,
+    pub children: Vec<usize>,
+}
+
+impl<Item> SimpleTreeNode<Item> {
+    pub fn new(item: Item, children: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            item,
+            children: children.into_iter().collect(),
+        }
+    }
+}
+
+/// A tree stored in a vector, the id of a node is its index.
+#[derive(Component)]
+pub struct SimpleTreeViewModel<Item: DataItem> {
+    pub nodes: Vec<SimpleTreeNode<Item>>,
+    pub roots: Vec<usize>,
+}
+
+impl<Item: DataItem + Clone> ContainerViewModel<usize, Item> for SimpleTreeViewModel<Item> {
+    type UpdateModel = UpdateModel<usize, Item, TreeRangeModel<usize>>;
+
+    fn update_from_world(&self, entity: EntityWorldRef) -> bool {
+        entity.get().get_ref::<Self>().unwrap().is_changed()
+    }
+
+    fn get(&self, key: &usize, _entity: EntityWorldRef) -> Option<Item> {
+        self.nodes.get(*key).map(|n| n.item.clone())
+    }
+
+    fn get_changed(&self, entity: EntityWorldRef) -> Self::UpdateModel {
+        let changed = self.update_from_world(entity);
+        UpdateModel {
+            items: if changed {
+                self.nodes
+                    .iter()
+                    .map(|n| n.item.clone())
+                    .enumerate()
+                    .collect()
+            } else {
+                vec![]
+            },
+            range: changed.then(|| TreeRangeModel {
+                items: (0..self.nodes.len()).collect(),
+            }),
+        }
+    }
+
+    fn set(&self, key: &usize, mut commands: EntityCommands, item: Item) {
+        let key = *key;
+        commands.queue(move |mut c: EntityWorldMut| {
+            if let Some(p) = c.get_mut::<Self>().unwrap().nodes.get_mut(key) {
+                p.item = item;
+            };
+        });
+    }
+
+    fn set_batch(
+        &self,
+        mut commands: EntityCommands,
+        items: &mut dyn Iterator<Item = (usize, Item)>,
+    ) {
+        let items = Vec::from_iter(items);
+        commands.queue(|mut c: EntityWorldMut| {
+            let mut this = c.get_mut::<Self>().unwrap();
+            for (index, item) in items {
+                if let Some(p) = this.nodes.get_mut(index) {
+                    p.item = item;
+                }
+            }
+        });
+    }
+}
+
+impl<Item: DataItem + Clone> TreeViewModel<usize, Item> for SimpleTreeViewModel<Item> {
+    fn get_root(&self, _entity: EntityWorldRef) -> Vec<(usize, Item)> {
+        self.roots
+            .iter()
+            .filter_map(|&id| Some((id, self.nodes.get(id)?.item.clone())))
+            .collect()
+    }
+
+    fn get_root_by_index(&self, _entity: EntityWorldRef) -> Option<(usize, Item)> {
+        let id = *self.roots.first()?;
+        Some((id, self.nodes.get(id)?.item.clone()))
+    }
+
+    fn get_children(&self, node: &usize, _entity: EntityWorldRef) -> Option<Vec<(usize, Item)>> {
+        let node = self.nodes.get(*node)?;
+        Some(
+            node.children
+                .iter()
+                .filter_map(|&id| Some((id, self.nodes.get(id)?.item.clone())))
+                .collect(),
+        )
+    }
+
+    fn get_children_index(&self, node: &usize, _entity: EntityWorldRef) -> Option<Vec<usize>> {
+        self.nodes.get(*node).map(|n| n.children.clone())
+    }
+}
+
+/// A table stored as a vector of rows, sorting compares the items of a column.
+#[derive(Component)]
+pub struct SimpleTableViewModel<Item: DataItem> {
+    pub rows: Vec<Vec<Item>>,
+    pub columns: usize,
+}
+
+impl<Item: DataItem + Clone + PartialOrd> TableViewModel<Item> for SimpleTableViewModel<Item> {
+    fn len(&self, _entity: EntityWorldRef) -> [usize; 2] {
+        [self.rows.len(), self.columns]
+    }
+
+    fn is_changed(&self, entity: EntityWorldRef) -> bool {
+        entity.get().get_ref::<Self>().unwrap().is_changed()
+    }
+
+    fn get(&self, [row, column]: [usize; 2], _entity: EntityWorldRef) -> Option<Item> {
+        self.rows.get(row)?.get(column).cloned()
+    }
+
+    fn set(&self, [row, column]: [usize; 2], mut commands: EntityCommands, item: Item) {
+        commands.queue(move |mut c: EntityWorldMut| {
+            if let Some(p) = c
+                .get_mut::<Self>()
+                .unwrap()
+                .rows
+                .get_mut(row)
+                .and_then(|r| r.get_mut(column))
+            {
+                *p = item;
+            };
+        });
+    }
+
+    fn sort(&self, column: usize, order: SortOrder, mut commands: EntityCommands) {
+        commands.queue(move |mut c: EntityWorldMut| {
+            let mut this = c.get_mut::<Self>().unwrap();
+            this.rows.sort_by(|a, b| {
+                let ordering = a
+                    .get(column)
+                    .partial_cmp(&b.get(column))
+                    .unwrap_or(Ordering::Equal);
+                match order {
+                    SortOrder::Ascending => ordering,
+                    SortOrder::Descending => ordering.reverse(),
+                }
+            });
+        });
+    }
+}
+
 #[derive(Default)]
 pub struct ViewModelPlugin<Item: DataItem>(PhantomData<Item>);
 impl<Item: DataItem + Clone> Plugin for ViewModelPlugin<Item> {
     fn build(&self, app: &mut App) {
         app.register_component_as::<dyn ViewModel<Item>, SimpleItemViewModel<Item>>();
         app.register_component_as::<dyn ListViewModel<Item>, SimpleListViewModel<Item>>();
+        app.register_component_as::<dyn TreeViewModel<usize, Item>, SimpleTreeViewModel<Item>>();
     }
 }
@@ -19,7 +19,8 @@ pub(crate) use crate as dway_ui_framework;
 pub use crate::{
     animation::{
         translation::UiTranslationAnimation, ui::AnimationTargetNodeState, Animation,
-        AssetAnimationPlugin, AssetTweenExt, Interpolation, Tween,
+        AnimationAppExt, AnimationSettings, AssetAnimationPlugin, AssetTweenExt, Interpolation,
+        Tween,
     },
     event::{
         make_callback, CallbackRegisterAppExt, CallbackTypeRegister, EventDispatcher, UiEvent,
@@ -27,9 +28,18 @@ pub use crate::{
     input::*,
     mvvm::{
         container::{ItemCell, ItemCellPlugin},
+        layout::{Header, HeaderCell, HonHeader, SortOrder},
         list::{ListViewLayout, ListViewModelPlugin},
-        view::{list::ListViewBundle, TextViewFactory},
-        viewmodel::{SimpleItemViewModel, SimpleListViewModel, ViewModelPlugin},
+        selection::{FocusModel, ItemActivated, ItemSelectionInfo, SelectionModel},
+        table::{TableViewLayout, TableViewModelPlugin},
+        tree::{TreeExpandRequest, TreeItemState, TreeViewLayout, TreeViewModelPlugin},
+        view::{
+            list::ListViewBundle, table::TableViewBundle, tree::TreeViewBundle, TextViewFactory,
+        },
+        viewmodel::{
+            SimpleItemViewModel, SimpleListViewModel, SimpleTableViewModel, SimpleTreeNode,
+            SimpleTreeViewModel, ViewModelPlugin,
+        },
     },
     render::mesh::UiMesh,
     shader::{ShaderAsset, ShaderPlugin, ShapeRender, Transformed},
@@ -42,6 +52,14 @@ pub use crate::{
     widgets::{
         button::{UiButton, UiButtonEvent, UiButtonEventDispatcher, UiButtonEventKind},
         checkbox::{UiCheckBox, UiCheckBoxEvent, UiCheckBoxState},
+        dnd::{
+            DndActions, DragImage, DragPayload, UiDragSource, UiDragSourceEvent, UiDropEvent,
+            UiDropTarget, UiDropTargetState,
+        },
+        menu::{
+            open_menu, MenuIcon, MenuItem, MenuItemKind, MenuPlacement, UiMenu, UiMenuBar,
+            UiMenuEvent, UiMenuEventDispatcher,
+        },
         popup::*,
         shader::*,
         shape::UiShape,
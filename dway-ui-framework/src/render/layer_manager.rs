@@ -72,6 +72,22 @@ impl RenderToLayer {
 #[derive(Component, Reflect, Default)]
 pub struct LayerRenderArea;
 
+/// A node rendered by a lower layer which samples the background of the layer
+/// `kind` with [`FillWithLayer`], so the background is computed in its area too.
+///
+/// The background is read before the lower layer is rendered, it contains the
+/// content of the previous frame below the node.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug)]
+pub struct LayerBackdrop {
+    pub kind: LayerKind,
+}
+
+impl LayerBackdrop {
+    pub fn new(kind: LayerKind) -> Self {
+        Self { kind }
+    }
+}
+
 fn on_insert_layer(mut world: DeferredWorld, context: HookContext) {
     let Some(layer) = world.get::<RenderToLayer>(context.entity) else {
         warn!(
@@ -119,6 +135,17 @@ structstruck::strike! {
         }> )
 }
 
+impl SetWindowTarget {
+    /// Render to `layer`, but handle the input of the ui nodes like the camera renders to
+    /// `window_target`.
+    pub fn new(window_target: RenderTarget, layer: RenderTarget) -> Self {
+        Self(Some(BackupRenderTargetInner {
+            window_target,
+            layer,
+        }))
+    }
+}
+
 #[derive(Component, Reflect, Debug)]
 pub struct LayerCamera {
     layer_manager: Entity,
@@ -320,7 +347,7 @@ structstruck::strike! {
     #[derive(Debug, Reflect, Clone)]
     pub(crate) struct BlurLayer{
         pub(crate) blur_method:
-            #[derive(Clone, Copy, Reflect, Debug, PartialEq)]
+            #[derive(Clone, Copy, Reflect, Debug, PartialEq, serde::Deserialize)]
             pub enum BlurMethod {
                 Kawase{ layer: usize, radius: f32 },
                 Dual{ layer: usize, radius: f32 },
@@ -588,6 +615,17 @@ impl LayerManager {
         }
     }
 
+    pub fn blur_method(&self) -> BlurMethod {
+        self.blur_layer.blur_method
+    }
+
+    pub fn set_blur_method(&mut self, blur_method: BlurMethod) {
+        if self.blur_layer.blur_method != blur_method {
+            self.blur_layer.blur_method = blur_method;
+            self.blur_layer.update_shader();
+        }
+    }
+
     pub fn get_camera(&self, kind: LayerKind) -> Entity {
         match kind {
             LayerKind::Normal => self.base_layer.camera,
@@ -677,7 +715,15 @@ pub fn update_layers(
         &ComputedUiTargetCamera,
         Ref<LayerRenderArea>,
     )>,
+    backdrop_query: Query<(
+        &InheritedVisibility,
+        Ref<ComputedNode>,
+        Ref<UiGlobalTransform>,
+        &ComputedUiTargetCamera,
+        Ref<LayerBackdrop>,
+    )>,
     mut removed_area: RemovedComponents<LayerRenderArea>,
+    mut removed_backdrop: RemovedComponents<LayerBackdrop>,
     primary_window: Query<Entity, With<PrimaryWindow>>,
     mut images: ResMut<Assets<Image>>,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -716,12 +762,43 @@ pub fn update_layers(
         layer_usage.rects.push(rect);
     }
 
+    for (visibility, computed_node, global_transform, node_camera, backdrop) in &backdrop_query {
+        if !**visibility {
+            continue;
+        }
+        let Some(layer_camera) = node_camera
+            .get()
+            .and_then(|camera| layer_manager_query.get(camera).ok())
+            .map(|(_, layer_manager)| layer_manager.get_camera(backdrop.kind))
+        else {
+            continue;
+        };
+        let layer_usage = layer_rects.entry(layer_camera).or_default();
+        let rect = get_node_rect(&global_transform, &computed_node);
+        if backdrop.is_changed() || computed_node.is_changed() || global_transform.is_changed() {
+            layer_usage.changed = true;
+        }
+        if (rect.width() <= 0.0) || (rect.height() <= 0.0) {
+            continue;
+        }
+        layer_usage.rects.push(rect);
+    }
+
     for removed_entity in removed_area.read() {
         let layer_usage = layer_rects.entry(removed_entity).or_default();
         layer_usage.changed = true;
     }
+    let backdrop_removed = removed_backdrop.read().count() > 0;
 
     for (entity, mut layer_manager) in &mut layer_manager_query {
+        if backdrop_removed {
+            for kind in [LayerKind::Blur, LayerKind::Canvas] {
+                layer_rects
+                    .entry(layer_manager.get_camera(kind))
+                    .or_default()
+                    .changed = true;
+            }
+        }
         let mut image_size_changed = false;
         {
             let (camera, _, _) = camera_query.camera.get(entity).unwrap();
@@ -910,6 +987,7 @@ impl Plugin for LayerManagerPlugin {
         app.register_type::<LayerManager>()
             .register_type::<LayerCamera>()
             .register_type::<RenderToLayer>()
+            .register_type::<LayerBackdrop>()
             .add_systems(
                 PreUpdate,
                 (
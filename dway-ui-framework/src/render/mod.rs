@@ -3,6 +3,7 @@ use crate::prelude::*;
 pub mod blur;
 pub mod layer_manager;
 pub mod mesh;
+pub mod output_color;
 pub mod ui_nodes;
 
 #[derive(Default, Clone, Component, Debug, Reflect, PartialEq, Deref, DerefMut)]
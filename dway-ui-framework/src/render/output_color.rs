@@ -0,0 +1,88 @@
+//! Conversion of the composited image of a camera to the image description of its output, in a
+//! pass after the UI, so that everything drawn by the camera is converted.
+
+use bevy::{
+    asset::{load_internal_asset, uuid_handle},
+    core_pipeline::core_2d::graph::{Core2d, Node2d},
+    ecs::{query::QueryItem, system::lifetimeless::Read},
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        render_graph::{RenderGraphExt as _, RenderLabel, ViewNodeRunner},
+        render_resource::ShaderType,
+        RenderApp,
+    },
+    ui_render::graph::NodeUi,
+};
+use dway_util::{
+    color::ColorTransform,
+    render::post_process::{PostProcessNode, PostProcessPlugin, PostProcessUniform},
+};
+
+use crate::prelude::*;
+
+const OUTPUT_COLOR_SHADER_HANDLE: Handle<Shader> =
+    uuid_handle!("0c01ecc8-cb51-11f1-8a77-02fc00000001");
+
+/// The conversion from the colors a camera composites in to the ones of the output it renders to.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct OutputColorTransform(pub ColorTransform);
+
+#[derive(Component, Clone, ShaderType)]
+pub struct OutputColorUniform {
+    matrix: Mat3,
+    /// The kinds and the exponents of the source and the target transfer functions
+    transfer: Vec4,
+}
+
+impl ExtractComponent for OutputColorTransform {
+    type QueryData = Read<OutputColorTransform>;
+    type QueryFilter = With<Camera>;
+    type Out = OutputColorUniform;
+
+    fn extract_component(transform: QueryItem<'_, '_, Self::QueryData>) -> Option<Self::Out> {
+        let source = transform.0.source.shader_params();
+        let target = transform.0.target.shader_params();
+        Some(OutputColorUniform {
+            matrix: transform.0.matrix,
+            transfer: Vec4::new(source.x, source.y, target.x, target.y),
+        })
+    }
+}
+
+impl PostProcessUniform for OutputColorUniform {
+    const LABEL: &'static str = "output_color";
+    const SHADER: Handle<Shader> = OUTPUT_COLOR_SHADER_HANDLE;
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct OutputColorLabel;
+
+/// Apply [`OutputColorTransform`] of cameras after the UI is drawn.
+pub struct OutputColorPlugin;
+impl Plugin for OutputColorPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            OUTPUT_COLOR_SHADER_HANDLE,
+            "output_color.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins((
+            ExtractComponentPlugin::<OutputColorTransform>::default(),
+            PostProcessPlugin::<OutputColorUniform>::default(),
+        ));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<PostProcessNode<OutputColorUniform>>>(
+                Core2d,
+                OutputColorLabel,
+            )
+            .add_render_graph_edges(
+                Core2d,
+                (NodeUi::UiPass, OutputColorLabel, Node2d::Upscaling),
+            );
+    }
+}
@@ -0,0 +1,654 @@
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    render::render_resource::{
+        encase::internal::{BufferMut, Writer},
+        AsBindGroupError,
+    },
+    tasks::ConditionalSendFuture,
+};
+use thiserror::Error;
+
+use super::{
+    effect::Effect, fill::Fill, shape::Shape, BindGroupBuilder, BindGroupLayoutBuilder,
+    BuildBindGroup, Expr, ShaderBuilder, ShaderVariables, UniformLayout,
+};
+use crate::prelude::*;
+
+pub const SHADER_SNIPPET_FILE_EXTENSION: &str = "ui.wgsl";
+
+/// A function implemented in a [`ShaderSnippet`].
+///
+/// The function receives the position and the size of the shape followed by
+/// the uniforms in the order of `UNIFORMS`, which must also be the order they
+/// are written by [`BuildBindGroup::write_uniform`]. Its signature depends on
+/// the wrapper it is used with:
+/// - [`CustomShape`]: `fn(pos: vec2<f32>, size: vec2<f32>, ...) -> f32`, the signed distance
+/// - [`CustomFill`]: `fn(pos: vec2<f32>, size: vec2<f32>, ...) -> vec4<f32>`
+/// - [`CustomEffect`]: `fn(color: vec4<f32>, d: f32, pos: vec2<f32>, size: vec2<f32>, ...) -> vec4<f32>`,
+///   where `color` is the color below the effect and `d` the distance to the shape
+pub trait CustomWgsl: BuildBindGroup {
+    /// The asset path of the `*.ui.wgsl` file.
+    const SNIPPET: &'static str;
+    /// The `#define_import_path` of the snippet.
+    const IMPORT_PATH: &'static str;
+    const FUNCTION: &'static str;
+    /// The names and the wgsl types of the uniforms passed to the function.
+    const UNIFORMS: &'static [(&'static str, &'static str)] = &[];
+}
+
+fn call_custom<P: CustomWgsl>(
+    builder: &mut ShaderBuilder,
+    leading_args: &[&str],
+    var: &ShaderVariables,
+) -> Expr {
+    let ShaderVariables { pos, size } = var;
+    builder.import_from_snippet(
+        P::SNIPPET,
+        P::IMPORT_PATH,
+        P::FUNCTION,
+        leading_args.len() + 2 + P::UNIFORMS.len(),
+    );
+    let args = leading_args
+        .iter()
+        .map(|a| a.to_string())
+        .chain([pos.clone(), size.clone()])
+        .chain(
+            P::UNIFORMS
+                .iter()
+                .map(|(name, ty)| builder.get_uniform(name, "", ty)),
+        )
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{}({args})", P::FUNCTION)
+}
+
+macro_rules! impl_custom_wrapper {
+    ($name:ident) => {
+        impl<P: CustomWgsl> $name<P> {
+            pub fn new(inner: P) -> Self {
+                Self(inner)
+            }
+        }
+
+        impl<P: CustomWgsl + Interpolation> Interpolation for $name<P> {
+            fn interpolation(&self, other: &Self, v: f32) -> Self {
+                Self(self.0.interpolation(&other.0, v))
+            }
+        }
+
+        impl<P: CustomWgsl> BuildBindGroup for $name<P> {
+            fn bind_group_layout_entries(builder: &mut BindGroupLayoutBuilder) {
+                P::bind_group_layout_entries(builder);
+            }
+
+            fn unprepared_bind_group(
+                &self,
+                builder: &mut BindGroupBuilder,
+            ) -> Result<(), AsBindGroupError> {
+                self.0.unprepared_bind_group(builder)
+            }
+
+            fn update_layout(&self, layout: &mut UniformLayout) {
+                self.0.update_layout(layout);
+            }
+
+            fn write_uniform<B: BufferMut>(
+                &self,
+                layout: &mut UniformLayout,
+                writer: &mut Writer<B>,
+            ) {
+                self.0.write_uniform(layout, writer);
+            }
+        }
+    };
+}
+
+/// A [`Shape`] whose signed distance is computed by a [`CustomWgsl`] function.
+#[derive(Clone, Default, Debug)]
+pub struct CustomShape<P: CustomWgsl>(pub P);
+impl_custom_wrapper!(CustomShape);
+
+impl<P: CustomWgsl> Shape for CustomShape<P> {
+    fn register_uniforms(_builder: &mut ShaderBuilder) {
+    }
+
+    fn to_wgsl(builder: &mut ShaderBuilder, var: &ShaderVariables) -> Expr {
+        call_custom::<P>(builder, &[], var)
+    }
+
+    fn to_gradient_wgsl(builder: &mut ShaderBuilder, var: &ShaderVariables) -> Expr {
+        // central differences, custom shapes don't provide an analytic gradient
+        let ShaderVariables { pos, size } = var;
+        let sample = |builder: &mut ShaderBuilder, offset: &str| {
+            let var = ShaderVariables {
+                pos: format!("({pos} + {offset})"),
+                size: size.clone(),
+            };
+            call_custom::<P>(builder, &[], &var)
+        };
+        let right = sample(builder, "vec2(0.5, 0.0)");
+        let left = sample(builder, "vec2(-0.5, 0.0)");
+        let bottom = sample(builder, "vec2(0.0, 0.5)");
+        let top = sample(builder, "vec2(0.0, -0.5)");
+        format!("vec2({right} - {left}, {bottom} - {top})")
+    }
+}
+
+/// A [`Fill`] whose color is computed by a [`CustomWgsl`] function.
+#[derive(Clone, Default, Debug)]
+pub struct CustomFill<P: CustomWgsl>(pub P);
+impl_custom_wrapper!(CustomFill);
+
+impl<P: CustomWgsl> Fill for CustomFill<P> {
+    fn to_wgsl(builder: &mut ShaderBuilder, var: &ShaderVariables) -> Expr {
+        call_custom::<P>(builder, &[], var)
+    }
+}
+
+/// An [`Effect`] which maps the color below it with a [`CustomWgsl`] function.
+#[derive(Clone, Default, Debug)]
+pub struct CustomEffect<P: CustomWgsl>(pub P);
+impl_custom_wrapper!(CustomEffect);
+
+impl<P: CustomWgsl> Effect for CustomEffect<P> {
+    fn to_wgsl<S: Shape>(_shape_ns: &str, builder: &mut ShaderBuilder, var: &ShaderVariables) {
+        let expr = call_custom::<P>(builder, &["out", "shape_d"], var);
+        builder.fragment_inner += &format!(
+            "
+                out = {expr};
+            "
+        );
+    }
+}
+
+/// A snippet function which a material requires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnippetRequirement {
+    pub snippet: &'static str,
+    pub import_path: &'static str,
+    pub function: &'static str,
+    pub params: usize,
+    /// The type name of the material, used in error messages.
+    pub material: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnippetFunction {
+    pub name: String,
+    pub params: usize,
+    pub line: usize,
+}
+
+/// A `*.ui.wgsl` file providing the functions of [`CustomWgsl`] parts.
+///
+/// The file is checked when it is loaded and loaded again when it changes, the
+/// materials importing it are recompiled by the pipeline cache.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct ShaderSnippet {
+    pub import_path: String,
+    pub functions: Vec<SnippetFunction>,
+    pub shader: Handle<Shader>,
+}
+
+impl ShaderSnippet {
+    pub fn function(&self, name: &str) -> Option<&SnippetFunction> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    pub fn check(&self, requirement: &SnippetRequirement) -> Result<(), ShaderSnippetMismatch> {
+        if self.import_path != requirement.import_path {
+            return Err(ShaderSnippetMismatch::ImportPath {
+                expected: requirement.import_path,
+                found: self.import_path.clone(),
+            });
+        }
+        let Some(function) = self.function(requirement.function) else {
+            return Err(ShaderSnippetMismatch::MissingFunction {
+                name: requirement.function,
+            });
+        };
+        if function.params != requirement.params {
+            return Err(ShaderSnippetMismatch::Params {
+                name: requirement.function,
+                line: function.line,
+                expected: requirement.params,
+                found: function.params,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ShaderSnippetError {
+    #[error("could not read shader snippet: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("shader snippet is not valid utf-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("missing `#define_import_path`")]
+    MissingImportPath,
+    #[error("line {line}: block comment is never closed")]
+    UnclosedComment { line: usize },
+    #[error("line {line}: unexpected `{delimiter}`")]
+    UnexpectedDelimiter { delimiter: char, line: usize },
+    #[error("line {line}: `{delimiter}` is never closed")]
+    UnclosedDelimiter { delimiter: char, line: usize },
+    #[error("line {line}: expected a function name and parameters after `fn`")]
+    InvalidFunction { line: usize },
+    #[error("line {line}: function `{name}` is defined more than once")]
+    DuplicateFunction { name: String, line: usize },
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ShaderSnippetMismatch {
+    #[error("expected import path `{expected}`, found `{found}`")]
+    ImportPath {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("function `{name}` is not defined")]
+    MissingFunction { name: &'static str },
+    #[error("line {line}: function `{name}` should take {expected} parameters, found {found}")]
+    Params {
+        name: &'static str,
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+/// Replace the comments with spaces, keeping the offsets and the line numbers.
+fn strip_comments(source: &str) -> Result<String, ShaderSnippetError> {
+    let bytes = source.as_bytes();
+    let mut output = bytes.to_vec();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                output[i] = b' ';
+                i += 1;
+            }
+        } else if bytes[i..].starts_with(b"/*") {
+            // block comments nest in wgsl
+            let start = i;
+            let mut depth = 0;
+            loop {
+                if i >= bytes.len() {
+                    return Err(ShaderSnippetError::UnclosedComment {
+                        line: line_of(source, start),
+                    });
+                }
+                if bytes[i..].starts_with(b"/*") {
+                    depth += 1;
+                    output[i..i + 2].fill(b' ');
+                    i += 2;
+                } else if bytes[i..].starts_with(b"*/") {
+                    depth -= 1;
+                    output[i..i + 2].fill(b' ');
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    if bytes[i] != b'\n' {
+                        output[i] = b' ';
+                    }
+                    i += 1;
+                }
+            }
+        } else {
+            i += 1;
+        }
+    }
+    // every byte of a multi byte character in a comment is replaced, so this is lossless
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+fn check_delimiters(source: &str) -> Result<(), ShaderSnippetError> {
+    let mut stack = vec![];
+    for (offset, c) in source.char_indices() {
+        match c {
+            '(' | '[' | '{' => stack.push((c, offset)),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if stack.pop().map(|(open, _)| open) != Some(expected) {
+                    return Err(ShaderSnippetError::UnexpectedDelimiter {
+                        delimiter: c,
+                        line: line_of(source, offset),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some((delimiter, offset)) = stack.pop() {
+        return Err(ShaderSnippetError::UnclosedDelimiter {
+            delimiter,
+            line: line_of(source, offset),
+        });
+    }
+    Ok(())
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Count the parameters of the parameter list starting after `(`.
+fn count_params(params: &str) -> usize {
+    let mut depth = 0;
+    let mut count = 0;
+    let mut empty = true;
+    for c in params.chars() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            ')' | '>' | ']' if depth > 0 => depth -= 1,
+            ')' => break,
+            ',' if depth == 0 => {
+                if !empty {
+                    count += 1;
+                }
+                empty = true;
+                continue;
+            }
+            _ => {}
+        }
+        if !c.is_whitespace() {
+            empty = false;
+        }
+    }
+    if !empty {
+        count += 1;
+    }
+    count
+}
+
+fn parse_functions(source: &str) -> Result<Vec<SnippetFunction>, ShaderSnippetError> {
+    let mut functions: Vec<SnippetFunction> = vec![];
+    for (offset, _) in source.match_indices("fn") {
+        let before = source[..offset].chars().next_back();
+        let after = source[offset + 2..].chars().next();
+        if before.is_some_and(is_ident_char) || !after.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let line = line_of(source, offset);
+        let rest = source[offset + 2..].trim_start();
+        let name_len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(name_len);
+        let Some(params) = rest.trim_start().strip_prefix('(') else {
+            return Err(ShaderSnippetError::InvalidFunction { line });
+        };
+        if name.is_empty() {
+            return Err(ShaderSnippetError::InvalidFunction { line });
+        }
+        if functions.iter().any(|f| f.name == name) {
+            return Err(ShaderSnippetError::DuplicateFunction {
+                name: name.to_string(),
+                line,
+            });
+        }
+        functions.push(SnippetFunction {
+            name: name.to_string(),
+            params: count_params(params),
+            line,
+        });
+    }
+    Ok(functions)
+}
+
+/// Parse the import path and the functions of a snippet.
+pub fn parse_snippet(
+    source: &str,
+) -> Result<(String, Vec<SnippetFunction>), ShaderSnippetError> {
+    let source = strip_comments(source)?;
+    let import_path = source
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("#define_import_path"))
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .ok_or(ShaderSnippetError::MissingImportPath)?;
+    check_delimiters(&source)?;
+    let functions = parse_functions(&source)?;
+    Ok((import_path, functions))
+}
+
+#[derive(Default)]
+pub struct ShaderSnippetLoader;
+
+impl AssetLoader for ShaderSnippetLoader {
+    type Asset = ShaderSnippet;
+    type Error = ShaderSnippetError;
+    type Settings = ();
+
+    fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext,
+    ) -> impl ConditionalSendFuture<Output = Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            let source = String::from_utf8(bytes)?;
+            let (import_path, functions) = parse_snippet(&source)?;
+            let path = load_context.asset_path().to_string();
+            let shader = load_context
+                .add_labeled_asset("shader".to_string(), Shader::from_wgsl(source, path));
+            Ok(ShaderSnippet {
+                import_path,
+                functions,
+                shader,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[SHADER_SNIPPET_FILE_EXTENSION]
+    }
+}
+
+/// The snippets required by the registered materials.
+#[derive(Resource, Default)]
+pub struct ShaderSnippets {
+    requirements: Vec<SnippetRequirement>,
+    handles: HashMap<&'static str, Handle<ShaderSnippet>>,
+}
+
+impl ShaderSnippets {
+    pub fn add(&mut self, requirement: SnippetRequirement) {
+        if !self.requirements.contains(&requirement) {
+            self.requirements.push(requirement);
+        }
+    }
+
+    pub fn requirements(&self) -> &[SnippetRequirement] {
+        &self.requirements
+    }
+
+    pub fn get(&self, snippet: &str) -> Option<&Handle<ShaderSnippet>> {
+        self.handles.get(snippet)
+    }
+}
+
+pub fn load_shader_snippets(mut snippets: ResMut<ShaderSnippets>, asset_server: Res<AssetServer>) {
+    let snippets = &mut *snippets;
+    for requirement in &snippets.requirements {
+        snippets
+            .handles
+            .entry(requirement.snippet)
+            .or_insert_with(|| asset_server.load(requirement.snippet));
+    }
+}
+
+/// Check the snippets against the materials using them when they are loaded
+/// or modified.
+pub fn validate_shader_snippets(
+    mut events: MessageReader<AssetEvent<ShaderSnippet>>,
+    snippets: Res<ShaderSnippets>,
+    assets: Res<Assets<ShaderSnippet>>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        let Some(asset) = assets.get(*id) else {
+            continue;
+        };
+        for (path, handle) in &snippets.handles {
+            if handle.id() != *id {
+                continue;
+            }
+            let mut valid = true;
+            for requirement in snippets.requirements.iter().filter(|r| r.snippet == *path) {
+                if let Err(e) = asset.check(requirement) {
+                    error!(
+                        snippet = *path,
+                        material = requirement.material,
+                        "invalid shader snippet: {e}"
+                    );
+                    valid = false;
+                }
+            }
+            if valid {
+                debug!(snippet = *path, "shader snippet loaded");
+            }
+        }
+    }
+}
+
+pub struct ShaderSnippetPlugin;
+impl Plugin for ShaderSnippetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShaderSnippets>()
+            .init_asset::<ShaderSnippet>()
+            .init_asset_loader::<ShaderSnippetLoader>()
+            .add_systems(
+                PreUpdate,
+                (
+                    load_shader_snippets.run_if(resource_changed::<ShaderSnippets>),
+                    validate_shader_snippets,
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = "
+#define_import_path my_app::wave
+
+// fn commented(a: f32) -> f32 { return a; }
+fn wave(pos: vec2<f32>, size: vec2<f32>, amplitude: f32, phase: array<f32, 2>) -> f32 {
+    /* nested /* comment */ ( */
+    return sin(pos.x + phase[0]) * amplitude - pos.y;
+}
+
+fn tint(
+    color: vec4<f32>,
+    d: f32,
+    pos: vec2<f32>,
+    size: vec2<f32>,
+) -> vec4<f32> {
+    return color;
+}
+";
+
+    #[test]
+    fn test_parse_snippet() {
+        let (import_path, functions) = parse_snippet(SOURCE).unwrap();
+        assert_eq!(import_path, "my_app::wave");
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].name, "wave");
+        assert_eq!(functions[0].params, 4);
+        assert_eq!(functions[0].line, 5);
+        assert_eq!(functions[1].name, "tint");
+        assert_eq!(functions[1].params, 4);
+
+        assert!(matches!(
+            parse_snippet("fn a() {}"),
+            Err(ShaderSnippetError::MissingImportPath)
+        ));
+        assert!(matches!(
+            parse_snippet("#define_import_path a\nfn a() {\n    return (1;\n}"),
+            Err(ShaderSnippetError::UnexpectedDelimiter {
+                delimiter: '}',
+                line: 4
+            })
+        ));
+        assert!(matches!(
+            parse_snippet("#define_import_path a\nfn a() {\n"),
+            Err(ShaderSnippetError::UnclosedDelimiter {
+                delimiter: '{',
+                line: 2
+            })
+        ));
+        assert!(matches!(
+            parse_snippet("#define_import_path a\n/* fn a() {}"),
+            Err(ShaderSnippetError::UnclosedComment { line: 2 })
+        ));
+        assert!(matches!(
+            parse_snippet("#define_import_path a\nfn a() {}\nfn a(x: f32) {}"),
+            Err(ShaderSnippetError::DuplicateFunction { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_snippet() {
+        let (import_path, functions) = parse_snippet(SOURCE).unwrap();
+        let snippet = ShaderSnippet {
+            import_path,
+            functions,
+            shader: Handle::default(),
+        };
+        let requirement = SnippetRequirement {
+            snippet: "wave.ui.wgsl",
+            import_path: "my_app::wave",
+            function: "wave",
+            params: 4,
+            material: "Wave",
+        };
+        assert_eq!(snippet.check(&requirement), Ok(()));
+        assert_eq!(
+            snippet.check(&SnippetRequirement {
+                params: 3,
+                ..requirement.clone()
+            }),
+            Err(ShaderSnippetMismatch::Params {
+                name: "wave",
+                line: 5,
+                expected: 3,
+                found: 4
+            })
+        );
+        assert_eq!(
+            snippet.check(&SnippetRequirement {
+                function: "ripple",
+                ..requirement.clone()
+            }),
+            Err(ShaderSnippetMismatch::MissingFunction { name: "ripple" })
+        );
+        assert_eq!(
+            snippet.check(&SnippetRequirement {
+                import_path: "my_app::other",
+                ..requirement
+            }),
+            Err(ShaderSnippetMismatch::ImportPath {
+                expected: "my_app::other",
+                found: "my_app::wave".to_string()
+            })
+        );
+    }
+}
@@ -4,6 +4,7 @@ use bevy::render::render_resource::{
     encase::internal::{BufferMut, Writer},
     AsBindGroupError,
 };
+use dway_util::color::ColorTransform;
 
 use super::{BuildBindGroup, Expr, ShaderBuilder};
 use crate::{prelude::*, shader::ShaderVariables};
@@ -309,3 +310,109 @@ impl<F: Fill> BuildBindGroup for AddColor<F> {
         self.inner.unprepared_bind_group(builder)
     }
 }
+
+#[derive(Clone, Debug, Interpolation)]
+pub struct Opacity<F: Fill> {
+    pub inner: F,
+    pub opacity: f32,
+}
+
+impl<F: Fill> Opacity<F> {
+    pub fn new(inner: F, opacity: f32) -> Self {
+        Self { inner, opacity }
+    }
+}
+
+impl<F: Fill> Fill for Opacity<F> {
+    fn to_wgsl(builder: &mut ShaderBuilder, var: &ShaderVariables) -> Expr {
+        let inner = F::to_wgsl(builder, var);
+        let uniform_opacity = builder.get_uniform("opacity", "", "f32");
+        format!("({inner} * vec4(1.0, 1.0, 1.0, {uniform_opacity}))")
+    }
+}
+impl<F: Fill> BuildBindGroup for Opacity<F> {
+    fn update_layout(&self, layout: &mut super::UniformLayout) {
+        self.inner.update_layout(layout);
+        layout.update_layout(&self.opacity);
+    }
+
+    fn write_uniform<B: BufferMut>(
+        &self,
+        layout: &mut super::UniformLayout,
+        writer: &mut Writer<B>,
+    ) {
+        self.inner.write_uniform(layout, writer);
+        layout.write_uniform(&self.opacity, writer);
+    }
+
+    fn bind_group_layout_entries(builder: &mut super::BindGroupLayoutBuilder) {
+        F::bind_group_layout_entries(builder);
+    }
+
+    fn unprepared_bind_group(
+        &self,
+        builder: &mut super::BindGroupBuilder,
+    ) -> Result<(), AsBindGroupError> {
+        self.inner.unprepared_bind_group(builder)
+    }
+}
+
+/// Convert the colors of the inner fill between image descriptions, see
+/// [`dway_util::color::ColorTransform`].
+#[derive(Clone, Default, Debug, Interpolation)]
+pub struct ConvertColor<F: Fill> {
+    pub inner: F,
+    pub matrix: Mat3,
+    /// The kinds and the exponents of the source and the target transfer functions
+    pub transfer: Vec4,
+}
+
+impl<F: Fill> ConvertColor<F> {
+    pub fn new(inner: F, transform: ColorTransform) -> Self {
+        let source = transform.source.shader_params();
+        let target = transform.target.shader_params();
+        Self {
+            inner,
+            matrix: transform.matrix,
+            transfer: Vec4::new(source.x, source.y, target.x, target.y),
+        }
+    }
+}
+
+impl<F: Fill> Fill for ConvertColor<F> {
+    fn to_wgsl(builder: &mut ShaderBuilder, var: &ShaderVariables) -> Expr {
+        let inner = F::to_wgsl(builder, var);
+        let uniform_matrix = builder.get_uniform("matrix", "", "mat3x3<f32>");
+        let uniform_transfer = builder.get_uniform("transfer", "", "vec4<f32>");
+        builder.import_from_builtin("convert_color");
+        format!("convert_color({inner}, {uniform_matrix}, {uniform_transfer})")
+    }
+}
+impl<F: Fill> BuildBindGroup for ConvertColor<F> {
+    fn update_layout(&self, layout: &mut super::UniformLayout) {
+        self.inner.update_layout(layout);
+        layout.update_layout(&self.matrix);
+        layout.update_layout(&self.transfer);
+    }
+
+    fn write_uniform<B: BufferMut>(
+        &self,
+        layout: &mut super::UniformLayout,
+        writer: &mut Writer<B>,
+    ) {
+        self.inner.write_uniform(layout, writer);
+        layout.write_uniform(&self.matrix, writer);
+        layout.write_uniform(&self.transfer, writer);
+    }
+
+    fn bind_group_layout_entries(builder: &mut super::BindGroupLayoutBuilder) {
+        F::bind_group_layout_entries(builder);
+    }
+
+    fn unprepared_bind_group(
+        &self,
+        builder: &mut super::BindGroupBuilder,
+    ) -> Result<(), AsBindGroupError> {
+        self.inner.unprepared_bind_group(builder)
+    }
+}
@@ -160,3 +160,57 @@ fn color_wheel(pos: vec2<f32>) -> vec4<f32> {
     let rgb = hsl2rgb(vec3( angle / ( 2.0*PI ), 1.0, 0.5 ));
     return vec4(rgb, 1.0);
 }
+
+// the transfer functions of dway_util::color, `transfer` is the kind and the exponent of
+// TransferFunction::shader_params
+fn color_eotf(v: f32, transfer: vec2<f32>) -> f32 {
+    let kind = u32(transfer.x);
+    if kind == 0u {
+        return v;
+    } else if kind == 1u {
+        let c = max(v, 0.0);
+        return select(pow((c + 0.055) / 1.055, 2.4), c / 12.92, c <= 0.04045);
+    } else if kind == 3u {
+        let p = pow(clamp(v, 0.0, 1.0), 1.0 / 78.84375);
+        return pow(max(p - 0.8359375, 0.0) / (18.8515625 - 18.6875 * p), 1.0 / 0.1593017578125);
+    } else if kind == 4u {
+        let c = clamp(v, 0.0, 1.0);
+        return select((exp((c - 0.55991073) / 0.17883277) + 0.28466892) / 12.0, c * c / 3.0, c <= 0.5);
+    }
+    return pow(max(v, 0.0), transfer.y);
+}
+
+fn color_inverse_eotf(l: f32, transfer: vec2<f32>) -> f32 {
+    let kind = u32(transfer.x);
+    if kind == 0u {
+        return l;
+    } else if kind == 1u {
+        let c = max(l, 0.0);
+        return select(1.055 * pow(c, 1.0 / 2.4) - 0.055, c * 12.92, c <= 0.0031308);
+    } else if kind == 3u {
+        let p = pow(clamp(l, 0.0, 1.0), 0.1593017578125);
+        return pow((0.8359375 + 18.8515625 * p) / (1.0 + 18.6875 * p), 78.84375);
+    } else if kind == 4u {
+        let c = clamp(l, 0.0, 1.0);
+        return select(0.17883277 * log(max(12.0 * c - 0.28466892, 1e-6)) + 0.55991073, sqrt(3.0 * c), c <= 1.0 / 12.0);
+    }
+    return pow(max(l, 0.0), 1.0 / transfer.y);
+}
+
+fn color_eotf3(v: vec3<f32>, transfer: vec2<f32>) -> vec3<f32> {
+    return vec3(color_eotf(v.r, transfer), color_eotf(v.g, transfer), color_eotf(v.b, transfer));
+}
+
+fn color_inverse_eotf3(l: vec3<f32>, transfer: vec2<f32>) -> vec3<f32> {
+    return vec3(color_inverse_eotf(l.r, transfer), color_inverse_eotf(l.g, transfer), color_inverse_eotf(l.b, transfer));
+}
+
+// convert a color sampled from an sRGB texture and written to an sRGB target, the encoded values
+// are converted like ColorTransform::apply, `transfer` holds the source and the target transfer
+fn convert_color(color: vec4<f32>, matrix: mat3x3<f32>, transfer: vec4<f32>) -> vec4<f32> {
+    let srgb = vec2(1.0, 1.0);
+    let encoded = color_inverse_eotf3(color.rgb, srgb);
+    let linear = clamp(matrix * color_eotf3(encoded, transfer.xy), vec3(0.0), vec3(1.0));
+    let converted = color_inverse_eotf3(linear, transfer.zw);
+    return vec4(color_eotf3(converted, srgb), color.a);
+}
@@ -1,3 +1,4 @@
+pub mod custom;
 pub mod effect;
 pub mod fill;
 pub mod shape;
@@ -33,7 +34,12 @@ use bevy::{
 };
 use dway_ui_derive::Interpolation;
 
-use self::{effect::Effect, shape::Shape, transform::Transform};
+use self::{
+    custom::{ShaderSnippetPlugin, ShaderSnippets, SnippetRequirement},
+    effect::Effect,
+    shape::Shape,
+    transform::Transform,
+};
 use crate::{prelude::*, render::ui_nodes::UiMaterialPlugin};
 
 type Ident = String;
@@ -55,6 +61,7 @@ pub struct ShaderBuilder {
     binding: Vec<(String, String, String)>,
     vertex_fields: Vec<(String, String, String)>,
     prefixes: Vec<String>,
+    snippets: Vec<SnippetRequirement>,
 }
 
 impl ShaderBuilder {
@@ -93,6 +100,32 @@ impl ShaderBuilder {
             .insert(format!("dway_ui_framework::shader::framework::{import}"));
     }
 
+    /// Import `function` from the shader snippet at the asset path `snippet`,
+    /// the snippet is loaded and checked by [`ShaderSnippetPlugin`].
+    pub fn import_from_snippet(
+        &mut self,
+        snippet: &'static str,
+        import_path: &'static str,
+        function: &'static str,
+        params: usize,
+    ) {
+        self.imports.insert(format!("{import_path}::{function}"));
+        let requirement = SnippetRequirement {
+            snippet,
+            import_path,
+            function,
+            params,
+            material: "",
+        };
+        if !self.snippets.contains(&requirement) {
+            self.snippets.push(requirement);
+        }
+    }
+
+    pub fn snippets(&self) -> &[SnippetRequirement] {
+        &self.snippets
+    }
+
     pub fn add_var(&mut self, name: &str, value: Expr) -> (Ident, Stat) {
         let name = if let Some(prefix) = self.prefixes.last() {
             format!("{prefix}_{name}")
@@ -657,14 +690,18 @@ impl<T: Material> ShaderAsset<T> {
         Self { render }
     }
 
-    pub fn to_wgsl() -> String {
+    pub fn builder() -> ShaderBuilder {
         let mut builder = ShaderBuilder::default();
         let vars = ShaderVariables {
             pos: "rect_position".to_string(),
             size: "rect_size".to_string(),
         };
         T::to_wgsl(&mut builder, &vars);
-        builder.build()
+        builder
+    }
+
+    pub fn to_wgsl() -> String {
+        Self::builder().build()
     }
 
     fn id() -> String {
@@ -820,11 +857,21 @@ impl<T: Material> Default for ShaderPlugin<T> {
 impl<T: Material> Plugin for ShaderPlugin<T> {
     fn build(&self, app: &mut App) {
         if !app.is_plugin_added::<UiMaterialPlugin<ShaderAsset<T>>>() {
-            let embedded = app.world_mut().resource_mut::<EmbeddedAssetRegistry>();
             let path: PathBuf = ShaderAsset::<T>::raw_path().into();
-            let wgsl = ShaderAsset::<T>::to_wgsl();
+            let builder = ShaderAsset::<T>::builder();
+            let wgsl = builder.build();
             trace!("add shader: {path:?}\n{wgsl}");
+            let embedded = app.world_mut().resource_mut::<EmbeddedAssetRegistry>();
             embedded.insert_asset(std::path::PathBuf::new(), &path, wgsl.into_bytes());
+            if !builder.snippets().is_empty() {
+                let mut snippets = app.world_mut().get_resource_or_init::<ShaderSnippets>();
+                for requirement in builder.snippets() {
+                    snippets.add(SnippetRequirement {
+                        material: type_name::<T>(),
+                        ..requirement.clone()
+                    });
+                }
+            }
             if !app.is_plugin_added::<UiMaterialPlugin<ShaderAsset<T>>>() {
                 app.add_plugins(UiMaterialPlugin::<ShaderAsset<T>>::default());
             }
@@ -842,5 +889,6 @@ pub struct ShaderFrameworkPlugin;
 impl Plugin for ShaderFrameworkPlugin {
     fn build(&self, app: &mut App) {
         load_internal_asset!(app, FRAMEWORK_HANDLE, "framework.wgsl", Shader::from_wgsl);
+        app.add_plugins(ShaderSnippetPlugin);
     }
 }
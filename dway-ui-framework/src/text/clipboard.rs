@@ -0,0 +1,75 @@
+use crate::prelude::*;
+
+/// The text clipboard used by the editors.
+///
+/// A compositor can mirror it with the system clipboard by watching for changes, editors only
+/// mark it as changed when they copy text, see [`UiClipboard::edit`].
+#[derive(Resource, Debug, Clone, Default, Reflect)]
+pub struct UiClipboard {
+    text: String,
+    /// the number of texts set, to tell whether an edit copied text
+    serial: u64,
+}
+
+impl UiClipboard {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.serial = self.serial.wrapping_add(1);
+    }
+
+    /// Run an edit which may copy text into the clipboard, the resource is only marked as changed
+    /// if it did.
+    pub fn edit<R>(clipboard: &mut ResMut<Self>, edit: impl FnOnce(&mut Self) -> R) -> R {
+        let serial = clipboard.serial;
+        let result = edit(clipboard.bypass_change_detection());
+        if clipboard.serial != serial {
+            clipboard.set_changed();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, input::keyboard::Key};
+
+    use super::*;
+    use crate::text::editor::{TextEditContext, TextEditState};
+
+    fn type_key(world: &mut World, text: &'static str, key: Key, ctrl: bool) {
+        world
+            .run_system_once(move |mut clipboard: ResMut<UiClipboard>| {
+                let mut text = text.to_string();
+                let mut state = TextEditState::new(&text);
+                state.select_all(&text);
+                let context = TextEditContext {
+                    ctrl,
+                    ..Default::default()
+                };
+                UiClipboard::edit(&mut clipboard, |clipboard| {
+                    state.handle_key(&mut text, &key, context, clipboard)
+                });
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_typing_does_not_change_clipboard() {
+        let mut world = World::new();
+        world.init_resource::<UiClipboard>();
+        world.clear_trackers();
+
+        type_key(&mut world, "text", Key::Character("a".into()), false);
+        type_key(&mut world, "text", Key::Backspace, false);
+        type_key(&mut world, "text", Key::Character("v".into()), true);
+        assert!(!world.is_resource_changed::<UiClipboard>());
+
+        type_key(&mut world, "text", Key::Character("c".into()), true);
+        assert!(world.is_resource_changed::<UiClipboard>());
+        assert_eq!(world.resource::<UiClipboard>().text(), "text");
+    }
+}
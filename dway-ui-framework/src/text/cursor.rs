@@ -8,7 +8,7 @@ use bevy::{
 };
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::textarea::UiTextArea;
+use super::{editor::UiTextEditor, textarea::UiTextArea};
 use crate::{impl_event_receiver, prelude::*};
 
 #[derive(Component, SmartDefault, Reflect)]
@@ -145,6 +145,12 @@ impl UiTextCursor {
             .position(|(index, value)| index + value.len() > byte_index)
     }
 
+    pub fn glyph_count(&self, textarea: &UiTextArea) -> usize {
+        UnicodeSegmentation::graphemes(&*textarea.data, true)
+            .filter(|c| *c != "\n")
+            .count()
+    }
+
     pub fn set_glyph_index(&mut self, textarea: &UiTextArea, index: usize) {
         let byte_index = self.glyph_index_to_byte_index(textarea, index);
         self.byte_index = byte_index;
@@ -173,6 +179,7 @@ pub fn text_cursor_on_input_system(
         &mut UiInput,
         &RelativeCursorPosition,
         &ComputedNode,
+        Has<UiTextEditor>,
     )>,
     text_query: Query<&TextLayoutInfo>,
     mut input_focus_event: MessageWriter<UiFocusEvent>,
@@ -187,6 +194,7 @@ pub fn text_cursor_on_input_system(
         ui_input,
         relative_pos,
         computed_node,
+        has_editor,
     )) = query.get_mut(event.sender())
     else {
         return;
@@ -201,6 +209,10 @@ pub fn text_cursor_on_input_system(
             if !ui_input.can_receive_keyboard_input() {
                 input_focus_event.write(UiFocusEvent::FocusEnterRequest(entity));
             }
+            if has_editor {
+                // the editor moves the cursor itself
+                return;
+            }
 
             if let Some(mouse_position) = get_node_mouse_position(relative_pos, computed_node)
             {
@@ -209,7 +221,7 @@ pub fn text_cursor_on_input_system(
             }
         }
         UiInputEvent::MouseMove(_) => {
-            if *interaction == Interaction::Pressed {
+            if *interaction == Interaction::Pressed && !has_editor {
                 if let Some(mouse_position) =
                     get_node_mouse_position(relative_pos, computed_node)
                 {
@@ -219,7 +231,7 @@ pub fn text_cursor_on_input_system(
             }
         }
         UiInputEvent::KeyboardInput(key) => {
-            if key.state.is_pressed() {
+            if key.state.is_pressed() || has_editor {
                 return;
             }
             match key.logical_key {
@@ -1,134 +1,746 @@
-use bevy::input::keyboard::Key;
-use undo::History;
+use std::ops::Range;
+
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    text::TextLayoutInfo,
+    ui::RelativeCursorPosition,
+    window::Ime,
+};
+use unicode_segmentation::UnicodeSegmentation;
 
 use super::{
+    clipboard::UiClipboard,
     cursor::{UiTextCursor, UiTextCursorEvent},
     selection::UiTextSelection,
     textarea::UiTextArea,
 };
 use crate::{impl_event_receiver, prelude::*};
 
+#[derive(Debug, Clone)]
 pub enum UiTextEditorEvent {
     Changed(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UiInputCommand {
     Insert(usize, String),
     Delete(usize, String),
     Replace(usize, String, String),
 }
+
 impl UiInputCommand {
-    pub fn new_delete_char(textarea: &UiTextArea, cursor: &UiTextCursor) -> Self {
-        let char = textarea
-            .data
-            .char_indices()
-            .find_map(|(pos, c)| (pos == cursor.byte_index).then(|| c.to_string()));
-        Self::Delete(cursor.byte_index, char.unwrap_or_default())
+    pub fn apply(&self, text: &mut String) {
+        match self {
+            UiInputCommand::Insert(p, d) => {
+                text.insert_str(*p, d);
+            }
+            UiInputCommand::Delete(p, d) => {
+                text.replace_range(*p..*p + d.len(), "");
+            }
+            UiInputCommand::Replace(p, remove, insert) => {
+                text.replace_range(*p..*p + remove.len(), insert);
+            }
+        }
     }
 
-    pub fn apply(&self, textarea: &mut UiTextArea) {
+    pub fn revert(&self, text: &mut String) {
         match self {
             UiInputCommand::Insert(p, d) => {
-                textarea.data.insert_str(*p, d);
+                text.replace_range(*p..*p + d.len(), "");
             }
             UiInputCommand::Delete(p, d) => {
-                let split_off = textarea.data.split_off(*p);
-                textarea.data.push_str(split_off.split_at(d.len()).1);
+                text.insert_str(*p, d);
             }
             UiInputCommand::Replace(p, remove, insert) => {
-                let split_off = textarea.data.split_off(*p);
-                textarea.data.push_str(insert);
-                textarea.data.push_str(split_off.split_at(remove.len()).1);
+                text.replace_range(*p..*p + insert.len(), remove);
             }
         }
     }
 }
 
-#[derive(Component, SmartDefault)]
-#[require(UiTextCursor)]
-pub struct UiTextEditor {
-    pub histtory: History<UiInputCommand>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextMotion {
+    PreviousChar,
+    NextChar,
+    PreviousWord,
+    NextWord,
+    LineStart,
+    LineEnd,
+    Start,
+    End,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Insert,
+    Delete,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct EditGroup {
+    commands: Vec<UiInputCommand>,
+    kind: EditKind,
+    cursor_before: usize,
+    cursor_after: usize,
+}
+
+/// The text being composed by an input method, drawn underlined at the cursor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// The editing core shared by [`UiTextEditor`] and `UiInputBox`.
+///
+/// Positions are byte indices into the text, motions move over grapheme clusters.
+#[derive(Debug, Clone, Default)]
+pub struct TextEditState {
+    pub cursor: usize,
+    /// The other end of the selection.
+    pub anchor: Option<usize>,
+    pub preedit: Option<Preedit>,
+    /// The column kept by vertical motions across shorter lines.
+    goal_column: Option<usize>,
+    undo_stack: Vec<EditGroup>,
+    redo_stack: Vec<EditGroup>,
+    coalesce: bool,
 }
 
-impl UiTextEditor {
-    pub fn insert_text(
+fn previous_grapheme(text: &str, index: usize) -> usize {
+    text[..index]
+        .grapheme_indices(true)
+        .next_back()
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn next_grapheme(text: &str, index: usize) -> usize {
+    text[index..]
+        .graphemes(true)
+        .next()
+        .map(|g| index + g.len())
+        .unwrap_or(text.len())
+}
+
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+fn previous_word(text: &str, index: usize) -> usize {
+    text[..index]
+        .split_word_bound_indices()
+        .rev()
+        .find(|(_, w)| is_word(w))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn next_word(text: &str, index: usize) -> usize {
+    text[index..]
+        .split_word_bound_indices()
+        .find(|(_, w)| is_word(w))
+        .map(|(i, w)| index + i + w.len())
+        .unwrap_or(text.len())
+}
+
+fn line_start(text: &str, index: usize) -> usize {
+    text[..index].rfind('\n').map(|i| i + 1).unwrap_or(0)
+}
+
+fn line_end(text: &str, index: usize) -> usize {
+    text[index..].find('\n').map(|i| index + i).unwrap_or(text.len())
+}
+
+fn column(text: &str, index: usize) -> usize {
+    text[line_start(text, index)..index].graphemes(true).count()
+}
+
+fn at_column(text: &str, line_start: usize, column: usize) -> usize {
+    let end = line_end(text, line_start);
+    text[line_start..end]
+        .grapheme_indices(true)
+        .nth(column)
+        .map(|(i, _)| line_start + i)
+        .unwrap_or(end)
+}
+
+impl TextEditState {
+    pub fn new(text: &str) -> Self {
+        Self {
+            cursor: text.len(),
+            ..Default::default()
+        }
+    }
+
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        (anchor != self.cursor).then(|| anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    pub fn selected_text<'t>(&self, text: &'t str) -> Option<&'t str> {
+        self.selection().map(|range| &text[range])
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
+    }
+
+    pub fn select_all(&mut self, text: &str) {
+        self.anchor = Some(0);
+        self.cursor = text.len();
+        self.coalesce = false;
+    }
+
+    /// Keeps the state valid after the text was replaced from outside.
+    pub fn clamp(&mut self, text: &str) {
+        self.cursor = text.floor_char_boundary(self.cursor);
+        self.anchor = self.anchor.map(|a| text.floor_char_boundary(a));
+    }
+
+    pub fn target(&self, text: &str, motion: TextMotion) -> usize {
+        let cursor = self.cursor;
+        match motion {
+            TextMotion::PreviousChar => previous_grapheme(text, cursor),
+            TextMotion::NextChar => next_grapheme(text, cursor),
+            TextMotion::PreviousWord => previous_word(text, cursor),
+            TextMotion::NextWord => next_word(text, cursor),
+            TextMotion::LineStart => line_start(text, cursor),
+            TextMotion::LineEnd => line_end(text, cursor),
+            TextMotion::Start => 0,
+            TextMotion::End => text.len(),
+            TextMotion::Up => {
+                let start = line_start(text, cursor);
+                if start == 0 {
+                    return 0;
+                }
+                let column = self.goal_column.unwrap_or_else(|| column(text, cursor));
+                at_column(text, line_start(text, start - 1), column)
+            }
+            TextMotion::Down => {
+                let end = line_end(text, cursor);
+                if end == text.len() {
+                    return text.len();
+                }
+                let column = self.goal_column.unwrap_or_else(|| column(text, cursor));
+                at_column(text, end + 1, column)
+            }
+        }
+    }
+
+    pub fn move_cursor(&mut self, text: &str, motion: TextMotion, extend: bool) {
+        let goal_column = matches!(motion, TextMotion::Up | TextMotion::Down)
+            .then(|| self.goal_column.unwrap_or_else(|| column(text, self.cursor)));
+        let target = match (self.selection(), extend, motion) {
+            (Some(range), false, TextMotion::PreviousChar) => range.start,
+            (Some(range), false, TextMotion::NextChar) => range.end,
+            _ => self.target(text, motion),
+        };
+        self.move_to(target, extend);
+        self.goal_column = goal_column;
+    }
+
+    /// Moves the cursor to a byte index, e.g. one found from the text layout.
+    pub fn move_to(&mut self, index: usize, extend: bool) {
+        if extend {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = index;
+        self.goal_column = None;
+        self.coalesce = false;
+    }
+
+    fn push(&mut self, group: EditGroup) {
+        self.redo_stack.clear();
+        match self.undo_stack.last_mut() {
+            Some(last)
+                if self.coalesce
+                    && last.kind == group.kind
+                    && group.kind != EditKind::Other
+                    && last.cursor_after == group.cursor_before =>
+            {
+                last.commands.extend(group.commands);
+                last.cursor_after = group.cursor_after;
+            }
+            _ => self.undo_stack.push(group),
+        }
+        self.coalesce = self
+            .undo_stack
+            .last()
+            .is_some_and(|g| g.kind != EditKind::Other);
+    }
+
+    fn edit(
         &mut self,
-        textarea: &mut UiTextArea,
-        cursor: &mut UiTextCursor,
-        text: &str,
+        text: &mut String,
+        command: UiInputCommand,
+        kind: EditKind,
+        cursor: usize,
     ) {
-        UiInputCommand::Insert(cursor.byte_index, text.to_string()).apply(textarea);
-        let position = textarea.data.floor_char_boundary(
-            cursor.byte_index + text.len(),
-        );
-        cursor.byte_index = position;
+        let cursor_before = self.cursor;
+        command.apply(text);
+        self.cursor = cursor;
+        self.anchor = None;
+        self.goal_column = None;
+        self.push(EditGroup {
+            commands: vec![command],
+            kind,
+            cursor_before,
+            cursor_after: cursor,
+        });
+    }
+
+    /// Inserts text at the cursor, replacing the selection.
+    pub fn insert(&mut self, text: &mut String, value: &str) {
+        let (position, command) = match self.selection() {
+            Some(range) => (
+                range.start,
+                UiInputCommand::Replace(
+                    range.start,
+                    text[range.clone()].to_string(),
+                    value.to_string(),
+                ),
+            ),
+            None if value.is_empty() => return,
+            None => (
+                self.cursor,
+                UiInputCommand::Insert(self.cursor, value.to_string()),
+            ),
+        };
+        // the first letter of a new word starts a new undo step
+        if !value.starts_with(char::is_whitespace)
+            && text[..self.cursor].ends_with(char::is_whitespace)
+        {
+            self.coalesce = false;
+        }
+        let kind = if matches!(command, UiInputCommand::Insert(..))
+            && value.graphemes(true).count() == 1
+        {
+            EditKind::Insert
+        } else {
+            EditKind::Other
+        };
+        self.edit(text, command, kind, position + value.len());
+    }
+
+    /// Deletes the selection, or the text between the cursor and a motion target.
+    pub fn delete(&mut self, text: &mut String, motion: TextMotion) -> bool {
+        let (range, kind) = match self.selection() {
+            Some(range) => (range, EditKind::Other),
+            None => {
+                let target = self.target(text, motion);
+                (
+                    self.cursor.min(target)..self.cursor.max(target),
+                    if matches!(motion, TextMotion::PreviousChar | TextMotion::NextChar) {
+                        EditKind::Delete
+                    } else {
+                        EditKind::Other
+                    },
+                )
+            }
+        };
+        if range.is_empty() {
+            return false;
+        }
+        let command = UiInputCommand::Delete(range.start, text[range.clone()].to_string());
+        self.edit(text, command, kind, range.start);
+        true
+    }
+
+    pub fn copy(&self, text: &str) -> Option<String> {
+        self.selected_text(text).map(str::to_string)
+    }
+
+    pub fn cut(&mut self, text: &mut String) -> Option<String> {
+        let selected = self.copy(text)?;
+        self.delete(text, TextMotion::NextChar);
+        Some(selected)
+    }
+
+    pub fn undo(&mut self, text: &mut String) -> bool {
+        let Some(group) = self.undo_stack.pop() else {
+            return false;
+        };
+        for command in group.commands.iter().rev() {
+            command.revert(text);
+        }
+        self.cursor = group.cursor_before;
+        self.anchor = None;
+        self.coalesce = false;
+        self.redo_stack.push(group);
+        true
     }
 
-    pub fn backspace(&mut self, textarea: &mut UiTextArea, cursor: &mut UiTextCursor) {
-        if cursor.byte_index > 0 {
-            self.goto_previous_char(textarea, cursor);
-            self.delete_char(textarea, cursor);
+    pub fn redo(&mut self, text: &mut String) -> bool {
+        let Some(group) = self.redo_stack.pop() else {
+            return false;
+        };
+        for command in &group.commands {
+            command.apply(text);
         }
+        self.cursor = group.cursor_after;
+        self.anchor = None;
+        self.coalesce = false;
+        self.undo_stack.push(group);
+        true
     }
 
-    pub fn delete_char(&mut self, textarea: &mut UiTextArea, cursor: &mut UiTextCursor) {
-        UiInputCommand::new_delete_char(textarea, cursor).apply(textarea);
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
     }
 
-    pub fn goto_previous_char(&mut self, textarea: &UiTextArea, cursor: &mut UiTextCursor) {
-        cursor.byte_index = textarea
-            .data
-            .floor_char_boundary(cursor.byte_index.saturating_sub(1));
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
     }
 
-    pub fn goto_next_char(&mut self, textarea: &UiTextArea, cursor: &mut UiTextCursor) {
-        cursor.byte_index = textarea
-            .data
-            .floor_char_boundary(cursor.byte_index.saturating_add(1));
+    pub fn set_preedit(&mut self, preedit: Option<Preedit>) {
+        self.preedit = preedit.filter(|p| !p.text.is_empty());
+    }
+
+    /// The text with the preedit spliced in at the cursor.
+    pub fn display_text(&self, text: &str) -> String {
+        match &self.preedit {
+            Some(preedit) => {
+                let mut display = text.to_string();
+                display.insert_str(self.cursor, &preedit.text);
+                display
+            }
+            None => text.to_string(),
+        }
+    }
+
+    /// The byte range of the preedit in [`Self::display_text`].
+    pub fn preedit_range(&self) -> Option<Range<usize>> {
+        self.preedit
+            .as_ref()
+            .map(|p| self.cursor..self.cursor + p.text.len())
+    }
+
+    /// Applies the editing key bindings to a released key.
+    pub fn handle_key(
+        &mut self,
+        text: &mut String,
+        key: &Key,
+        context: TextEditContext,
+        clipboard: &mut UiClipboard,
+    ) -> TextEditResult {
+        let TextEditContext {
+            ctrl,
+            shift,
+            multi_line,
+            readonly,
+        } = context;
+        let len = text.len();
+        let cursor = self.cursor;
+        let mut changed = false;
+        match key {
+            Key::Character(s) if ctrl => match s.to_lowercase().as_str() {
+                "a" => self.select_all(text),
+                "c" => {
+                    if let Some(selected) = self.copy(text) {
+                        clipboard.set_text(selected);
+                    }
+                }
+                "x" if !readonly => {
+                    if let Some(selected) = self.cut(text) {
+                        clipboard.set_text(selected);
+                        changed = true;
+                    }
+                }
+                "v" if !readonly => {
+                    let pasted = if multi_line {
+                        clipboard.text().to_string()
+                    } else {
+                        clipboard.text().replace('\n', " ")
+                    };
+                    self.insert(text, &pasted);
+                    changed = !pasted.is_empty();
+                }
+                "z" if !readonly && shift => changed = self.redo(text),
+                "z" if !readonly => changed = self.undo(text),
+                "y" if !readonly => changed = self.redo(text),
+                _ => {}
+            },
+            Key::Character(s) if !readonly => {
+                self.insert(text, s);
+                changed = true;
+            }
+            Key::Space if !readonly => {
+                self.insert(text, " ");
+                changed = true;
+            }
+            Key::Enter if !readonly && multi_line => {
+                self.insert(text, "\n");
+                changed = true;
+            }
+            Key::Backspace if !readonly => {
+                let motion = if ctrl {
+                    TextMotion::PreviousWord
+                } else {
+                    TextMotion::PreviousChar
+                };
+                changed = self.delete(text, motion);
+            }
+            Key::Delete if !readonly => {
+                let motion = if ctrl {
+                    TextMotion::NextWord
+                } else {
+                    TextMotion::NextChar
+                };
+                changed = self.delete(text, motion);
+            }
+            Key::ArrowLeft => {
+                let motion = if ctrl {
+                    TextMotion::PreviousWord
+                } else {
+                    TextMotion::PreviousChar
+                };
+                self.move_cursor(text, motion, shift);
+            }
+            Key::ArrowRight => {
+                let motion = if ctrl {
+                    TextMotion::NextWord
+                } else {
+                    TextMotion::NextChar
+                };
+                self.move_cursor(text, motion, shift);
+            }
+            Key::ArrowUp if multi_line => self.move_cursor(text, TextMotion::Up, shift),
+            Key::ArrowDown if multi_line => self.move_cursor(text, TextMotion::Down, shift),
+            Key::Home => {
+                let motion = if ctrl || !multi_line {
+                    TextMotion::Start
+                } else {
+                    TextMotion::LineStart
+                };
+                self.move_cursor(text, motion, shift);
+            }
+            Key::End => {
+                let motion = if ctrl || !multi_line {
+                    TextMotion::End
+                } else {
+                    TextMotion::LineEnd
+                };
+                self.move_cursor(text, motion, shift);
+            }
+            _ => {
+                return TextEditResult::Ignored;
+            }
+        }
+        if changed || text.len() != len {
+            TextEditResult::Changed
+        } else if self.cursor != cursor {
+            TextEditResult::CursorMoved
+        } else {
+            TextEditResult::Handled
+        }
+    }
+
+    /// Applies an input method event.
+    pub fn handle_ime(&mut self, text: &mut String, ime: &Ime, readonly: bool) -> TextEditResult {
+        if readonly {
+            return TextEditResult::Ignored;
+        }
+        match ime {
+            Ime::Preedit { value, cursor, .. } => {
+                self.set_preedit(Some(Preedit {
+                    text: value.clone(),
+                    cursor: *cursor,
+                }));
+                TextEditResult::Handled
+            }
+            Ime::Commit { value, .. } => {
+                self.preedit = None;
+                self.insert(text, value);
+                TextEditResult::Changed
+            }
+            Ime::Disabled { .. } => {
+                self.preedit = None;
+                TextEditResult::Handled
+            }
+            _ => TextEditResult::Ignored,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextEditContext {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub multi_line: bool,
+    pub readonly: bool,
+}
+
+impl TextEditContext {
+    pub fn from_input(keys: &ButtonInput<KeyCode>) -> Self {
+        Self {
+            ctrl: keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]),
+            shift: keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEditResult {
+    Ignored,
+    Handled,
+    CursorMoved,
+    Changed,
+}
+
+#[derive(Component, SmartDefault)]
+#[require(UiTextCursor, UiTextSelection)]
+pub struct UiTextEditor {
+    pub state: TextEditState,
+    #[default(true)]
+    pub multi_line: bool,
+    pub readonly: bool,
+    #[default(Entity::PLACEHOLDER)]
+    pub preedit_entity: Entity,
+}
+
+fn sync_selection(
+    editor: &UiTextEditor,
+    textarea: &UiTextArea,
+    cursor: &mut UiTextCursor,
+    selection: &mut UiTextSelection,
+) {
+    if cursor.byte_index != editor.state.cursor {
+        cursor.byte_index = editor.state.cursor;
+    }
+    let (start, end) = match editor.state.selection() {
+        Some(range) => {
+            let glyph = |i| {
+                cursor
+                    .byte_index_to_glyph_index(textarea, i)
+                    .unwrap_or_else(|| cursor.glyph_count(textarea))
+            };
+            (glyph(range.start), glyph(range.end))
+        }
+        None => (0, 0),
+    };
+    if selection.glyph_start != start || selection.glyph_end != end {
+        selection.glyph_start = start;
+        selection.glyph_end = end;
     }
 }
 
 pub fn text_editor_on_event(
     event: UiEvent<UiInputEvent>,
     mut query: Query<(
-        Entity,
         &mut UiTextEditor,
         &mut UiTextSelection,
         &mut UiTextCursor,
         Option<&EventDispatcher<UiTextCursorEvent>>,
+        Option<&EventDispatcher<UiTextEditorEvent>>,
         &mut UiTextArea,
+        &Interaction,
+        &RelativeCursorPosition,
+        &ComputedNode,
     )>,
+    text_query: Query<&TextLayoutInfo>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut clipboard: ResMut<UiClipboard>,
+    mut input_focus_event: MessageWriter<UiFocusEvent>,
     mut commands: Commands,
 ) {
-    let Ok((_entity, mut editor, _selection, mut cursor, cursor_event, mut textarea)) =
-        query.get_mut(event.sender())
+    let Ok((
+        mut editor,
+        mut selection,
+        mut cursor,
+        cursor_event,
+        editor_event,
+        mut textarea,
+        interaction,
+        relative_pos,
+        computed_node,
+    )) = query.get_mut(event.sender())
     else {
         return;
     };
+    let text_layout = text_query.get(textarea.text_entity).ok();
 
-    if let UiInputEvent::KeyboardInput(keyboard_input) = &*event {
-        if keyboard_input.state.is_pressed() {
-            return;
-        }
-
-        match &keyboard_input.logical_key {
-            Key::Backspace => {
-                editor.backspace(&mut textarea, &mut cursor);
+    let result = match &*event {
+        UiInputEvent::MousePress(_) | UiInputEvent::MouseMove(_) => {
+            let pressed = matches!(&*event, UiInputEvent::MousePress(_));
+            if !pressed && *interaction != Interaction::Pressed {
+                return;
             }
-            Key::Delete => {
-                editor.delete_char(&mut textarea, &mut cursor);
-            }
-            Key::Enter => {
-                editor.insert_text(&mut textarea, &mut cursor, "\n");
+            let (Some(text_layout), Some(position)) = (
+                text_layout,
+                get_node_mouse_position(relative_pos, computed_node),
+            ) else {
+                return;
+            };
+            let glyph = cursor.position_to_glyph_index(position, text_layout);
+            let index = cursor.glyph_index_to_byte_index(&textarea, glyph);
+            // dragging extends the selection from where the press started
+            let extend = !pressed || keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+            editor.state.move_to(index, extend);
+            TextEditResult::CursorMoved
+        }
+        UiInputEvent::KeyboardInput(KeyboardInput {
+            logical_key, state, ..
+        }) => {
+            if state.is_pressed() {
+                return;
             }
-            Key::Space => {
-                editor.insert_text(&mut textarea, &mut cursor, " ");
+            if *logical_key == Key::Escape {
+                input_focus_event.write(UiFocusEvent::FocusLeaveRequest(event.sender()));
+                return;
             }
-            Key::Character(s) => {
-                editor.insert_text(&mut textarea, &mut cursor, s);
+            let context = TextEditContext {
+                multi_line: editor.multi_line,
+                readonly: editor.readonly,
+                ..TextEditContext::from_input(&keys)
+            };
+            let editor = &mut *editor;
+            match (logical_key, text_layout) {
+                (Key::ArrowUp | Key::ArrowDown, Some(text_layout)) if editor.multi_line => {
+                    // follow the layout to respect wrapped lines
+                    let direction = if *logical_key == Key::ArrowUp {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    let glyph = cursor.position_to_glyph_index(
+                        cursor.position + direction * cursor.line_height * Vec2::Y,
+                        text_layout,
+                    );
+                    let index = cursor.glyph_index_to_byte_index(&textarea, glyph);
+                    editor.state.move_to(index, context.shift);
+                    TextEditResult::CursorMoved
+                }
+                _ => UiClipboard::edit(&mut clipboard, |clipboard| {
+                    editor
+                        .state
+                        .handle_key(&mut textarea.data, logical_key, context, clipboard)
+                }),
             }
-            _ => {}
+        }
+        UiInputEvent::Ime(ime) => {
+            let readonly = editor.readonly;
+            editor.state.handle_ime(&mut textarea.data, ime, readonly)
+        }
+        _ => TextEditResult::Ignored,
+    };
+
+    if result == TextEditResult::Ignored {
+        return;
+    }
+    sync_selection(&editor, &textarea, &mut cursor, &mut selection);
+    if result == TextEditResult::Changed {
+        if let Some(editor_event) = editor_event {
+            editor_event.send(
+                UiTextEditorEvent::Changed(textarea.data.clone()),
+                &mut commands,
+            );
         }
     }
     if cursor.is_changed() {
@@ -144,3 +756,155 @@ pub fn text_editor_on_event(
 impl_event_receiver! {
     impl EventReceiver<UiInputEvent> for UiTextEditor => text_editor_on_event
 }
+
+/// Draws the input method preedit of editors and keeps it in the displayed text.
+pub fn update_text_editor_preedit_system(
+    mut query: Query<
+        (Entity, &mut UiTextEditor, &UiTextArea, &UiTextCursor),
+        Changed<UiTextEditor>,
+    >,
+    mut text_query: Query<(&mut Text, &TextLayoutInfo)>,
+    mut node_query: Query<&mut Node>,
+    mut commands: Commands,
+) {
+    for (entity, mut editor, textarea, cursor) in &mut query {
+        let Ok((mut text, text_layout)) = text_query.get_mut(textarea.text_entity) else {
+            continue;
+        };
+        let display = editor.state.display_text(&textarea.data);
+        if text.0 != display {
+            text.0 = display;
+        }
+
+        if editor.preedit_entity == Entity::PLACEHOLDER {
+            let preedit_entity = commands
+                .spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        height: Val::Px(2.0),
+                        ..Default::default()
+                    },
+                    BackgroundColor(cursor.corsor_color),
+                    ChildOf(entity),
+                ))
+                .id();
+            editor.bypass_change_detection().preedit_entity = preedit_entity;
+        }
+        let Ok(mut node) = node_query.get_mut(editor.preedit_entity) else {
+            continue;
+        };
+        match editor.state.preedit_range() {
+            Some(range) => {
+                let start = cursor.byte_index_to_glyph_index(textarea, range.start);
+                let count = editor
+                    .state
+                    .preedit
+                    .as_ref()
+                    .map(|p| p.text.graphemes(true).count())
+                    .unwrap_or_default();
+                let left = cursor.get_cursor_position_of_glyph(start, text_layout);
+                let right = cursor
+                    .get_cursor_position_of_glyph(start.map(|s| s + count), text_layout);
+                node.display = Display::Flex;
+                node.left = Val::Px(left.x);
+                node.top = Val::Px(left.y + cursor.line_height * 0.5 - 2.0);
+                node.width = Val::Px((right.x - left.x).max(0.0));
+            }
+            None => {
+                node.display = Display::None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_motions() {
+        let text = "hello wörld\n👍🏽 ok";
+        let mut state = TextEditState::default();
+        state.move_cursor(text, TextMotion::NextWord, false);
+        assert_eq!(state.cursor, 5);
+        state.move_cursor(text, TextMotion::NextWord, false);
+        assert_eq!(state.cursor, "hello wörld".len());
+        state.move_cursor(text, TextMotion::NextChar, false);
+        state.move_cursor(text, TextMotion::NextChar, false);
+        assert_eq!(state.cursor, "hello wörld\n👍🏽".len());
+        state.move_cursor(text, TextMotion::Up, false);
+        assert_eq!(state.cursor, 1);
+        state.move_cursor(text, TextMotion::LineEnd, true);
+        assert_eq!(state.selected_text(text), Some("ello wörld"));
+        state.move_cursor(text, TextMotion::PreviousWord, true);
+        assert_eq!(state.selected_text(text), Some("ello "));
+        state.move_cursor(text, TextMotion::PreviousChar, false);
+        assert_eq!(state.cursor, 1);
+        assert_eq!(state.selection(), None);
+    }
+
+    #[test]
+    fn test_undo_coalescing() {
+        let mut text = String::new();
+        let mut state = TextEditState::default();
+        for c in ["a", "b", " ", "c", "d"] {
+            state.insert(&mut text, c);
+        }
+        assert_eq!(text, "ab cd");
+        state.undo(&mut text);
+        assert_eq!(text, "ab ");
+        state.undo(&mut text);
+        assert_eq!(text, "");
+        state.redo(&mut text);
+        assert_eq!(text, "ab ");
+
+        state.delete(&mut text, TextMotion::PreviousChar);
+        state.delete(&mut text, TextMotion::PreviousChar);
+        assert_eq!(text, "a");
+        state.undo(&mut text);
+        assert_eq!(text, "ab ");
+        assert_eq!(state.cursor, 3);
+        assert!(state.can_redo());
+    }
+
+    #[test]
+    fn test_clipboard_and_preedit() {
+        let mut text = "copy paste".to_string();
+        let mut state = TextEditState::new(&text);
+        let mut clipboard = UiClipboard::default();
+        let context = TextEditContext {
+            ctrl: true,
+            ..Default::default()
+        };
+        state.move_cursor(&text, TextMotion::PreviousWord, true);
+        let result =
+            state.handle_key(&mut text, &Key::Character("x".into()), context, &mut clipboard);
+        assert_eq!(result, TextEditResult::Changed);
+        assert_eq!((text.as_str(), clipboard.text()), ("copy ", "paste"));
+        state.move_cursor(&text, TextMotion::Start, false);
+        state.handle_key(&mut text, &Key::Character("v".into()), context, &mut clipboard);
+        assert_eq!(text, "pastecopy ");
+
+        state.handle_ime(
+            &mut text,
+            &Ime::Preedit {
+                window: Entity::PLACEHOLDER,
+                value: "にほ".to_string(),
+                cursor: None,
+            },
+            false,
+        );
+        assert_eq!(state.display_text(&text), "pasteにほcopy ");
+        assert_eq!(state.preedit_range(), Some(5..5 + "にほ".len()));
+        state.handle_ime(
+            &mut text,
+            &Ime::Commit {
+                window: Entity::PLACEHOLDER,
+                value: "日本".to_string(),
+            },
+            false,
+        );
+        assert_eq!(text, "paste日本copy ");
+        assert_eq!(state.preedit, None);
+    }
+}
@@ -2,12 +2,14 @@ use bevy::input::ButtonState;
 use cursor::{
     text_cursor_on_input_system, update_text_cursor_layout_system, UiTextCursor, UiTextCursorEvent,
 };
-use editor::UiTextEditor;
+use clipboard::UiClipboard;
+use editor::{update_text_editor_preedit_system, UiTextEditor};
 use selection::{update_ui_text_selection_system, UiTextSelection};
 use textarea::{update_textarea, UiTextArea};
 
 use crate::{event::EventReceiver, prelude::*};
 
+pub mod clipboard;
 pub mod cursor;
 pub mod editor;
 pub mod selection;
@@ -47,11 +49,16 @@ impl Plugin for UiTextPlugin {
                 update_text_cursor_layout_system
                     .in_set(UiTextSystem::UpdateCursor)
                     .after(UiTextSystem::UpdateTextArea),
+                update_text_editor_preedit_system
+                    .in_set(UiTextSystem::UpdateCursor)
+                    .after(UiTextSystem::UpdateTextArea),
                 update_ui_text_selection_system
                     .in_set(UiTextSystem::UpdateSelectionArea)
                     .after(UiTextSystem::UpdateCursor),
             ),
         )
+        .init_resource::<UiClipboard>()
+        .register_type::<UiClipboard>()
         .register_type::<UiTextArea>()
         .register_type::<UiTextCursor>()
         .register_type::<UiTextSelection>()
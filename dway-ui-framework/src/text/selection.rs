@@ -10,6 +10,7 @@ use bevy_svg::prelude::{FillOptions, StrokeOptions};
 
 use super::{
     cursor::{UiTextCursor, UiTextCursorEvent},
+    editor::UiTextEditor,
     textarea::UiTextArea,
 };
 use crate::{
@@ -170,31 +171,38 @@ pub fn on_replace_selection(mut world: DeferredWorld, context: HookContext) {
 
 pub fn on_ui_input_event(
     event: UiEvent<UiTextCursorEvent>,
-    mut self_query: Query<(&mut UiTextSelection, &Interaction, &UiInput)>,
+    mut self_query: Query<
+        (&mut UiTextSelection, &UiTextCursor, &UiTextArea, &Interaction, &UiInput),
+        Without<UiTextEditor>,
+    >,
     keys: Res<ButtonInput<KeyCode>>,
 ) {
-    let Ok((mut selection, interaction, ui_input)) = self_query.get_mut(event.sender()) else {
+    let Ok((mut selection, cursor, textarea, interaction, ui_input)) =
+        self_query.get_mut(event.sender())
+    else {
         return;
     };
 
     match &*event {
         UiTextCursorEvent::ChangePosition { byte_index, .. } => {
-            let byte_index = *byte_index;
+            let glyph_index = cursor
+                .byte_index_to_glyph_index(textarea, *byte_index)
+                .unwrap_or_else(|| cursor.glyph_count(textarea));
             if *interaction == Interaction::Pressed {
                 let shift_pressed = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
                 if ui_input.pressed() && shift_pressed {
-                    if (byte_index < (selection.glyph_start + selection.glyph_end) / 2)
+                    if (glyph_index < (selection.glyph_start + selection.glyph_end) / 2)
                         ^ (selection.glyph_start < selection.glyph_end)
                     {
-                        selection.glyph_end = byte_index;
+                        selection.glyph_end = glyph_index;
                     } else {
-                        selection.glyph_start = byte_index;
+                        selection.glyph_start = glyph_index;
                     }
                 } else if ui_input.just_pressed() {
-                    selection.glyph_start = byte_index;
-                    selection.glyph_end = byte_index;
+                    selection.glyph_start = glyph_index;
+                    selection.glyph_end = glyph_index;
                 } else {
-                    selection.glyph_end = byte_index;
+                    selection.glyph_end = glyph_index;
                 }
             }
         }
@@ -0,0 +1,453 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    tasks::ConditionalSendFuture,
+};
+use thiserror::Error;
+
+use super::Theme;
+use crate::{
+    prelude::*,
+    render::layer_manager::{BlurMethod, LayerManager},
+};
+
+pub const THEME_FILE_EXTENSION: &str = "theme.ron";
+
+/// The color scheme preferred by the user, usually synchronized with the
+/// freedesktop `color-scheme` setting.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub enum ThemeIconSource {
+    Path(String),
+    Directory(String),
+}
+
+impl From<&ThemeIconSource> for super::ThemeIcon {
+    fn from(value: &ThemeIconSource) -> Self {
+        match value {
+            ThemeIconSource::Path(p) => Self::Path(Arc::from(&**p)),
+            ThemeIconSource::Directory(p) => Self::InDirectory(Arc::from(&**p)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct ThemeVariant {
+    pub text_color: Option<String>,
+    pub colors: HashMap<String, String>,
+    pub styles: HashMap<String, String>,
+    pub icons: HashMap<String, ThemeIconSource>,
+}
+
+/// A theme loaded from a `*.theme.ron` file.
+///
+/// Styles use the same syntax as the `style!` macro. The `light` and `dark`
+/// variants are applied on top of the base values according to [`ColorScheme`].
+#[derive(Asset, TypePath, Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct ThemeAsset {
+    pub font: Option<String>,
+    pub text_size: Option<f32>,
+    pub text_color: Option<String>,
+    pub blur: Option<BlurMethod>,
+    /// animation durations in milliseconds
+    pub animations: HashMap<String, u64>,
+    pub colors: HashMap<String, String>,
+    pub styles: HashMap<String, String>,
+    pub icons: HashMap<String, ThemeIconSource>,
+    pub light: Option<ThemeVariant>,
+    pub dark: Option<ThemeVariant>,
+}
+
+impl ThemeAsset {
+    pub fn variant(&self, color_scheme: ColorScheme) -> Option<&ThemeVariant> {
+        match color_scheme {
+            ColorScheme::Light => self.light.as_ref(),
+            ColorScheme::Dark => self.dark.as_ref(),
+        }
+    }
+
+    fn apply_variant(
+        theme: &mut Theme,
+        text_color: Option<&String>,
+        colors: &HashMap<String, String>,
+        styles: &HashMap<String, String>,
+        icons: &HashMap<String, ThemeIconSource>,
+    ) {
+        if let Some(color) = text_color {
+            match parse_color(color) {
+                Ok(color) => theme.default_text_color = color,
+                Err(e) => warn!("invalid text color in theme: {e}"),
+            }
+        }
+        for (name, color) in colors {
+            match parse_color(color) {
+                Ok(color) => {
+                    theme.color_map.insert(name.clone(), color);
+                }
+                Err(e) => warn!(%name, "invalid color in theme: {e}"),
+            }
+        }
+        for (name, style) in styles {
+            match parse_style(style) {
+                Ok(node) => {
+                    theme.style_map.insert(name.clone(), node);
+                }
+                Err(e) => warn!(%name, "invalid style in theme: {e}"),
+            }
+        }
+        for (name, icon) in icons {
+            theme.theme_icons.insert(Box::from(&**name), icon.into());
+        }
+    }
+
+    pub fn apply(&self, theme: &mut Theme, color_scheme: ColorScheme, asset_server: &AssetServer) {
+        theme.color_map = super::default_color_map();
+        theme.style_map = super::default_style_map();
+        theme.theme_icons.clear();
+        theme.default_text_color = Theme::default().default_text_color;
+        if let Some(font) = &self.font {
+            theme.default_font = asset_server.load(font.clone());
+        }
+        if let Some(text_size) = self.text_size {
+            theme.default_text_size = text_size;
+        }
+        if let Some(blur) = self.blur {
+            theme.blur_method = blur;
+        }
+        theme.animation_durations = self
+            .animations
+            .iter()
+            .map(|(name, ms)| (name.clone(), Duration::from_millis(*ms)))
+            .collect();
+        Self::apply_variant(
+            theme,
+            self.text_color.as_ref(),
+            &self.colors,
+            &self.styles,
+            &self.icons,
+        );
+        if let Some(variant) = self.variant(color_scheme) {
+            Self::apply_variant(
+                theme,
+                variant.text_color.as_ref(),
+                &variant.colors,
+                &variant.styles,
+                &variant.icons,
+            );
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ThemeAssetError {
+    #[error("could not read theme file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse theme file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ThemeValueError {
+    #[error("invalid color: {0:?}")]
+    Color(String),
+    #[error("invalid value: {0:?}")]
+    Value(String),
+    #[error("unknown style: {0:?}")]
+    UnknownStyle(String),
+}
+
+#[derive(Default)]
+pub struct ThemeAssetLoader;
+
+impl AssetLoader for ThemeAssetLoader {
+    type Asset = ThemeAsset;
+    type Error = ThemeAssetError;
+    type Settings = ();
+
+    fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext,
+    ) -> impl ConditionalSendFuture<Output = Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[THEME_FILE_EXTENSION]
+    }
+}
+
+pub fn parse_color(value: &str) -> Result<Color, ThemeValueError> {
+    Srgba::hex(value)
+        .map(Color::Srgba)
+        .map_err(|_| ThemeValueError::Color(value.to_string()))
+}
+
+fn parse_number(value: &str) -> Result<f32, ThemeValueError> {
+    value
+        .parse::<f32>()
+        .map_err(|_| ThemeValueError::Value(value.to_string()))
+}
+
+fn parse_val(value: &str) -> Result<Val, ThemeValueError> {
+    if value == "full" {
+        Ok(Val::Percent(100.0))
+    } else if value == "auto" {
+        Ok(Val::Auto)
+    } else if let Some(value) = value.strip_suffix('%') {
+        Ok(Val::Percent(parse_number(value)?))
+    } else {
+        Ok(Val::Px(parse_number(value)?))
+    }
+}
+
+macro_rules! parse_enum {
+    ($value:expr, $ty:ident { $($name:literal => $variant:ident),* $(,)? }) => {
+        match $value {
+            $($name => Ok($ty::$variant),)*
+            o => Err(ThemeValueError::Value(o.to_string())),
+        }
+    };
+}
+
+/// Parse a style at runtime, accepting the same syntax as the `style!` macro
+/// except embedded rust expressions and raw `field:value` pairs.
+pub fn parse_style(style: &str) -> Result<Node, ThemeValueError> {
+    let mut node = Node::default();
+    for component in style.split_whitespace() {
+        let p = |prefix: &str| component.strip_prefix(prefix);
+        match component {
+            "full" => {
+                node.width = Val::Percent(100.0);
+                node.height = Val::Percent(100.0);
+            }
+            "clip" => node.overflow = Overflow::clip(),
+            "clip-x" => node.overflow = Overflow::clip_x(),
+            "clip-y" => node.overflow = Overflow::clip_y(),
+            "absolute" => node.position_type = PositionType::Absolute,
+            "flex-row" => node.flex_direction = FlexDirection::Row,
+            "flex-row-rev" => node.flex_direction = FlexDirection::RowReverse,
+            "flex-col" => node.flex_direction = FlexDirection::Column,
+            "flex-col-rev" => node.flex_direction = FlexDirection::ColumnReverse,
+            "items-center" => node.align_items = AlignItems::Center,
+            "align-center" => node.align_self = AlignSelf::Center,
+            "justify-center" => node.justify_content = JustifyContent::Center,
+            _ => {
+                if let Some(v) = p("align-items:") {
+                    node.align_items = parse_enum!(v, AlignItems {
+                        "default" => Default, "start" => Start, "end" => End,
+                        "flex-start" => FlexStart, "flex-end" => FlexEnd, "center" => Center,
+                        "baseline" => Baseline, "stretch" => Stretch,
+                    })?;
+                } else if let Some(v) = p("justify-items:") {
+                    node.justify_items = parse_enum!(v, JustifyItems {
+                        "default" => Default, "start" => Start, "end" => End,
+                        "center" => Center, "baseline" => Baseline, "stretch" => Stretch,
+                    })?;
+                } else if let Some(v) = p("align-self:") {
+                    node.align_self = parse_enum!(v, AlignSelf {
+                        "auto" => Auto, "start" => Start, "end" => End,
+                        "flex-start" => FlexStart, "flex-end" => FlexEnd, "center" => Center,
+                        "baseline" => Baseline, "stretch" => Stretch,
+                    })?;
+                } else if let Some(v) = p("justify-self:") {
+                    node.justify_self = parse_enum!(v, JustifySelf {
+                        "auto" => Auto, "start" => Start, "end" => End,
+                        "center" => Center, "baseline" => Baseline, "stretch" => Stretch,
+                    })?;
+                } else if let Some(v) = p("align-content:") {
+                    node.align_content = parse_enum!(v, AlignContent {
+                        "default" => Default, "start" => Start, "end" => End,
+                        "flex-start" => FlexStart, "flex-end" => FlexEnd, "center" => Center,
+                        "stretch" => Stretch, "space-between" => SpaceBetween,
+                        "space-evenly" => SpaceEvenly, "space-around" => SpaceAround,
+                    })?;
+                } else if let Some(v) = p("justify-content:") {
+                    node.justify_content = parse_enum!(v, JustifyContent {
+                        "default" => Default, "start" => Start, "end" => End,
+                        "flex-start" => FlexStart, "flex-end" => FlexEnd, "center" => Center,
+                        "stretch" => Stretch, "space-between" => SpaceBetween,
+                        "space-evenly" => SpaceEvenly, "space-around" => SpaceAround,
+                    })?;
+                } else if let Some(v) = p("w-").or_else(|| p("widget-")) {
+                    node.width = parse_val(v)?;
+                } else if let Some(v) = p("h-").or_else(|| p("height-")) {
+                    node.height = parse_val(v)?;
+                } else if let Some(v) = p("min-w-") {
+                    node.min_width = parse_val(v)?;
+                } else if let Some(v) = p("min-h-") {
+                    node.min_height = parse_val(v)?;
+                } else if let Some(v) = p("max-w-") {
+                    node.max_width = parse_val(v)?;
+                } else if let Some(v) = p("max-h-") {
+                    node.max_height = parse_val(v)?;
+                } else if let Some(v) = p("m-").or_else(|| p("margin-")) {
+                    node.margin = UiRect::all(parse_val(v)?);
+                } else if let Some(v) = p("p-").or_else(|| p("padding-")) {
+                    node.padding = UiRect::all(parse_val(v)?);
+                } else if let Some(v) = p("left-") {
+                    node.left = parse_val(v)?;
+                } else if let Some(v) = p("right-") {
+                    node.right = parse_val(v)?;
+                } else if let Some(v) = p("top-") {
+                    node.top = parse_val(v)?;
+                } else if let Some(v) = p("bottom-") {
+                    node.bottom = parse_val(v)?;
+                } else if let Some(v) = p("w/h-").or_else(|| p("ratio-")) {
+                    node.aspect_ratio = Some(parse_number(v)?);
+                } else {
+                    return Err(ThemeValueError::UnknownStyle(component.to_string()));
+                }
+            }
+        }
+    }
+    Ok(node)
+}
+
+/// The theme file applied to [`Theme`]. Changes of the file are applied to
+/// running widgets when asset hot reloading is enabled.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct ThemeFile {
+    pub handle: Option<Handle<ThemeAsset>>,
+}
+
+impl ThemeFile {
+    pub fn new(handle: Handle<ThemeAsset>) -> Self {
+        Self {
+            handle: Some(handle),
+        }
+    }
+}
+
+pub fn apply_theme_file_system(
+    mut events: MessageReader<AssetEvent<ThemeAsset>>,
+    theme_file: Res<ThemeFile>,
+    color_scheme: Res<ColorScheme>,
+    assets: Res<Assets<ThemeAsset>>,
+    asset_server: Res<AssetServer>,
+    mut theme: ResMut<Theme>,
+) {
+    let Some(handle) = &theme_file.handle else {
+        events.clear();
+        return;
+    };
+    let file_changed = events.read().any(|event| match event {
+        AssetEvent::Added { id } | AssetEvent::Modified { id } => *id == handle.id(),
+        _ => false,
+    });
+    if !file_changed && !color_scheme.is_changed() && !theme_file.is_changed() {
+        return;
+    }
+    let Some(asset) = assets.get(handle) else {
+        return;
+    };
+    asset.apply(&mut theme, *color_scheme, &asset_server);
+    debug!(color_scheme = ?*color_scheme, "theme file applied");
+}
+
+pub fn update_blur_method_system(
+    theme: Res<Theme>,
+    mut layer_manager_query: Query<&mut LayerManager>,
+) {
+    for mut layer_manager in &mut layer_manager_query {
+        if (theme.is_changed() || layer_manager.is_added())
+            && layer_manager.blur_method() != theme.blur_method
+        {
+            layer_manager.set_blur_method(theme.blur_method);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        let node =
+            parse_style("flex-col w-full h-32 m-4 p-10% align-items:center ratio-2").unwrap();
+        assert_eq!(node.flex_direction, FlexDirection::Column);
+        assert_eq!(node.width, Val::Percent(100.0));
+        assert_eq!(node.height, Val::Px(32.0));
+        assert_eq!(node.margin, UiRect::all(Val::Px(4.0)));
+        assert_eq!(node.padding, UiRect::all(Val::Percent(10.0)));
+        assert_eq!(node.align_items, AlignItems::Center);
+        assert_eq!(node.aspect_ratio, Some(2.0));
+        assert_eq!(node.min_width, Val::Auto);
+
+        let node = parse_style("min-w-8 max-h-auto absolute").unwrap();
+        assert_eq!(node.min_width, Val::Px(8.0));
+        assert_eq!(node.max_height, Val::Auto);
+        assert_eq!(node.position_type, PositionType::Absolute);
+
+        assert_eq!(
+            parse_style("w-abc"),
+            Err(ThemeValueError::Value("abc".to_string()))
+        );
+        assert_eq!(
+            parse_style("unknown"),
+            Err(ThemeValueError::UnknownStyle("unknown".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_theme_variant() {
+        let asset: ThemeAsset = ron::de::from_str(
+            r##"(
+                text_size: Some(12.0),
+                blur: Some(Kawase(layer: 2, radius: 3.0)),
+                animations: { "popup": 300 },
+                colors: { "foreground": "#000000", "accent": "#6791C9" },
+                styles: { "popup": "m-8" },
+                icons: { "close": Directory("embedded://dway_ui/icons") },
+                dark: Some((
+                    text_color: Some("#ffffff"),
+                    colors: { "foreground": "#ffffff" },
+                )),
+            )"##,
+        )
+        .unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+        let asset_server = app.world().resource::<AssetServer>();
+
+        let mut theme = Theme::default();
+        asset.apply(&mut theme, ColorScheme::Light, asset_server);
+        assert_eq!(theme.default_text_size, 12.0);
+        assert_eq!(
+            theme.blur_method,
+            BlurMethod::Kawase {
+                layer: 2,
+                radius: 3.0
+            }
+        );
+        assert_eq!(theme.animation_duration("popup"), Duration::from_millis(300));
+        assert_eq!(theme.color("foreground"), Color::srgb(0.0, 0.0, 0.0));
+        assert_eq!(theme.color("accent"), parse_color("#6791C9").unwrap());
+        assert_eq!(theme.style_map["popup"].margin, UiRect::all(Val::Px(8.0)));
+        assert!(theme.theme_icons.contains_key("close"));
+        assert!(theme.color_map.contains_key("background"));
+
+        asset.apply(&mut theme, ColorScheme::Dark, asset_server);
+        assert_eq!(theme.color("foreground"), Color::srgb(1.0, 1.0, 1.0));
+        assert_eq!(theme.default_text_color, Color::srgb(1.0, 1.0, 1.0));
+
+        ThemeAsset::default().apply(&mut theme, ColorScheme::Light, asset_server);
+        assert!(!theme.theme_icons.contains_key("close"));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod adapter;
+pub mod asset;
 pub mod flat;
 
 use std::{any::TypeId, fmt::Debug, hash::Hash, sync::Arc};
@@ -19,6 +20,7 @@ use crate::{
     },
     event::{CallbackTypeRegister, EventDispatcher},
     prelude::*,
+    render::layer_manager::BlurMethod,
     shader::{
         effect::{InnerShadow, Shadow},
         fill::Fill,
@@ -86,6 +88,12 @@ pub struct Theme {
     pub style_map: HashMap<String, Node>,
     #[reflect(ignore)]
     pub icons: HashMap<Box<str>, ThemeIcon>,
+    /// The icons of the theme file, they override the registered icons and are replaced when
+    /// the theme is reloaded
+    #[reflect(ignore)]
+    pub theme_icons: HashMap<Box<str>, ThemeIcon>,
+    pub blur_method: BlurMethod,
+    pub animation_durations: HashMap<String, Duration>,
 
     #[reflect(ignore)]
     pub material_shadow: Shadow,
@@ -104,6 +112,9 @@ impl Default for Theme {
             color_map: Default::default(),
             style_map: Default::default(),
             icons: Default::default(),
+            theme_icons: Default::default(),
+            blur_method: BlurMethod::dual(),
+            animation_durations: Default::default(),
             material_shadow: Shadow::new(
                 color!("#888888"),
                 Vec2::new(0.0, 1.0),
@@ -125,10 +136,19 @@ impl Theme {
         self.color_map.get(color).cloned().unwrap_or(Color::NONE)
     }
 
+    pub fn animation_duration(&self, name: &str) -> Duration {
+        self.animation_durations
+            .get(name)
+            .or_else(|| self.animation_durations.get("default"))
+            .cloned()
+            .unwrap_or(Duration::from_millis(200))
+    }
+
     pub fn icon(&self, name: &str, asset_server: &AssetServer) -> Handle<Svg> {
         if let Some(icon) = self
-            .icons
+            .theme_icons
             .get(name)
+            .or_else(|| self.icons.get(name))
             .map(|icon| icon.get_svg(name, asset_server))
         {
             icon
@@ -218,6 +238,58 @@ pub fn apply_theme_system(
     }
 }
 
+pub fn default_color_map() -> HashMap<String, Color> {
+    HashMap::from([
+        ("foreground".to_string(), color!("#10171e")),
+        ("foreground1".to_string(), color!("#1b1d1e")),
+        ("foreground2".to_string(), color!("#2b2d2e")),
+        ("foreground:emphasis".to_string(), color!("#000000")),
+        ("background".to_string(), color!("#ffffff")),
+        ("background1".to_string(), color!("#D8DEE9")),
+        ("background2".to_string(), color!("#C8CED9")),
+        ("black".to_string(), color!("#1C252C")),
+        ("red".to_string(), color!("#DF5B61")),
+        ("green".to_string(), color!("#78B892")),
+        ("yellow".to_string(), color!("#e7c787")),
+        ("orange".to_string(), color!("#DE8F78")),
+        ("blue".to_string(), color!("#6791C9")),
+        ("purple".to_string(), color!("#BC83E3")),
+        ("magenta".to_string(), color!("#c678dd")),
+        ("cyan".to_string(), color!("#008080")),
+        ("sky".to_string(), color!("#67AFC1")),
+        ("white".to_string(), color!("#D9D7D6")),
+        ("gray".to_string(), color!("#484E5B")),
+        ("slider:bar".to_string(), color!("#C8CED9")),
+        ("slider:bar:highlight".to_string(), color!("#DE8F78")),
+        ("slider:handle".to_string(), color!("#6791C9")),
+        ("checkbox:bar".to_string(), color!("#6791C9")),
+        ("checkbox:bar:highlight".to_string(), color!("#DE8F78")),
+        ("checkbox:handle".to_string(), color!("#6791C9")),
+        ("inputbox:cursor".to_string(), color!("#6791C9")),
+        ("inputbox:placeholder".to_string(), color!("#C8CED9")),
+        ("inputbox:selection".to_string(), color!("#B4CBEA")),
+        ("inputbox:text".to_string(), color!("#10171e")),
+        ("panel".to_string(), Color::WHITE.with_alpha(0.5)),
+        ("panel:hover".to_string(), color!("#ffffff")),
+        ("panel:clicked".to_string(), color!("#D8DEE9")),
+        ("panel-popup".to_string(), Color::WHITE.with_alpha(0.5)),
+        ("panel-popup1".to_string(), Color::srgba(0.9, 0.9, 0.9, 0.5)),
+        ("panel-popup:hover".to_string(), color!("#ffffff")),
+        ("panel-popup:clicked".to_string(), color!("#D8DEE9")),
+        ("panel-foreground".to_string(), color!("#1b1d1e")),
+        ("scroll-bar".to_string(), color!("#6791C9").with_alpha(0.8)),
+        ("shadow".to_string(), color!("#888888").with_alpha(0.5)),
+        ("border".to_string(), color!("#6791C9")),
+        ("window:shadow".to_string(), Color::BLACK.with_alpha(0.35)),
+        ("window:dim".to_string(), Color::BLACK.with_alpha(0.15)),
+        (POPUP_BACKGROUND.to_string(), color!("#D8DEE9")),
+    ])
+}
+
+pub fn default_style_map() -> HashMap<String, Node> {
+    HashMap::from([("popup".to_string(), style!("m-4"))])
+}
+
 pub struct ThemePlugin;
 impl Plugin for ThemePlugin {
     fn build(&self, app: &mut App) {
@@ -225,48 +297,8 @@ impl Plugin for ThemePlugin {
         let theme = Theme {
             default_font: asset_server
                 .load("embedded://dway_ui_framework/fonts/SmileySans-Oblique.ttf"),
-            color_map: HashMap::from([
-                ("foreground".to_string(), color!("#10171e")),
-                ("foreground1".to_string(), color!("#1b1d1e")),
-                ("foreground2".to_string(), color!("#2b2d2e")),
-                ("background".to_string(), color!("#ffffff")),
-                ("background1".to_string(), color!("#D8DEE9")),
-                ("background2".to_string(), color!("#C8CED9")),
-                ("black".to_string(), color!("#1C252C")),
-                ("red".to_string(), color!("#DF5B61")),
-                ("green".to_string(), color!("#78B892")),
-                ("yellow".to_string(), color!("#e7c787")),
-                ("orange".to_string(), color!("#DE8F78")),
-                ("blue".to_string(), color!("#6791C9")),
-                ("purple".to_string(), color!("#BC83E3")),
-                ("magenta".to_string(), color!("#c678dd")),
-                ("cyan".to_string(), color!("#008080")),
-                ("sky".to_string(), color!("#67AFC1")),
-                ("white".to_string(), color!("#D9D7D6")),
-                ("gray".to_string(), color!("#484E5B")),
-                ("slider:bar".to_string(), color!("#C8CED9")),
-                ("slider:bar:highlight".to_string(), color!("#DE8F78")),
-                ("slider:handle".to_string(), color!("#6791C9")),
-                ("checkbox:bar".to_string(), color!("#6791C9")),
-                ("checkbox:bar:highlight".to_string(), color!("#DE8F78")),
-                ("checkbox:handle".to_string(), color!("#6791C9")),
-                ("inputbox:cursor".to_string(), color!("#6791C9")),
-                ("inputbox:placeholder".to_string(), color!("#C8CED9")),
-                ("inputbox:text".to_string(), color!("#10171e")),
-                ("panel".to_string(), Color::WHITE.with_alpha(0.5)),
-                ("panel:hover".to_string(), color!("#ffffff")),
-                ("panel:clicked".to_string(), color!("#D8DEE9")),
-                ("panel-popup".to_string(), Color::WHITE.with_alpha(0.5)),
-                ("panel-popup1".to_string(), Color::srgba(0.9, 0.9, 0.9, 0.5)),
-                ("panel-popup:hover".to_string(), color!("#ffffff")),
-                ("panel-popup:clicked".to_string(), color!("#D8DEE9")),
-                ("panel-foreground".to_string(), color!("#1b1d1e")),
-                ("scroll-bar".to_string(), color!("#6791C9").with_alpha(0.8)),
-                ("shadow".to_string(), color!("#888888").with_alpha(0.5)),
-                ("border".to_string(), color!("#6791C9")),
-                (POPUP_BACKGROUND.to_string(), color!("#D8DEE9")),
-            ]),
-            style_map: HashMap::from([("popup".to_string(), style!("m-4"))]),
+            color_map: default_color_map(),
+            style_map: default_style_map(),
             ..Default::default()
         };
         let systems = SystemMap {
@@ -274,12 +306,26 @@ impl Plugin for ThemePlugin {
         };
         app.insert_resource(theme)
             .insert_resource(systems)
+            .init_resource::<asset::ColorScheme>()
+            .init_asset::<asset::ThemeAsset>()
+            .init_asset_loader::<asset::ThemeAssetLoader>()
             .register_type::<ThemeComponent>()
+            .register_type::<asset::ColorScheme>()
+            .add_systems(PreUpdate, asset::apply_theme_file_system)
             .add_systems(
                 PostUpdate,
-                apply_theme_system.in_set(UiFrameworkSystems::UpdateTheme),
+                (
+                    apply_theme_system.in_set(UiFrameworkSystems::UpdateTheme),
+                    asset::update_blur_method_system.in_set(UiFrameworkSystems::UpdateTheme),
+                ),
             )
             .register_type::<Theme>();
+        let theme_file = asset::ThemeFile::new(
+            app.world()
+                .resource::<AssetServer>()
+                .load("embedded://dway_ui_framework/themes/default.theme.ron"),
+        );
+        app.insert_resource(theme_file);
     }
 }
 
@@ -0,0 +1,673 @@
+use std::{any::Any, fmt::Debug, sync::Arc};
+
+use bevy::ui::RelativeCursorPosition;
+
+use crate::{
+    event::EventDispatcher,
+    prelude::*,
+    theme::{StyleFlags, ThemeComponent},
+    UiFrameworkSystems,
+};
+
+pub const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
+pub const URI_LIST_MIME_TYPE: &str = "text/uri-list";
+
+bitflags::bitflags! {
+    /// What happens to the data after the drop, the bits match `wl_data_device_manager.dnd_action`.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    pub struct DndActions: u32 {
+        const COPY = 1;
+        const MOVE = 1 << 1;
+        const ASK = 1 << 2;
+    }
+}
+
+impl DndActions {
+    /// The action used when both sides support several actions.
+    pub fn preferred(self) -> Self {
+        [Self::COPY, Self::MOVE, Self::ASK]
+            .into_iter()
+            .find(|action| self.contains(*action))
+            .unwrap_or(Self::empty())
+    }
+}
+
+#[derive(Clone)]
+pub enum DragData {
+    Bytes(Arc<[u8]>),
+    /// A value which can only be received by the widgets of this program.
+    Value(Arc<dyn Any + Send + Sync>),
+}
+
+/// The data of a drag, offered in several mime types.
+#[derive(Clone, Default)]
+pub struct DragPayload {
+    data: Vec<(String, DragData)>,
+}
+
+impl Debug for DragPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DragPayload")
+            .field("mime_types", &self.mime_types().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl DragPayload {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_data(mut self, mime_type: impl Into<String>, data: DragData) -> Self {
+        let mime_type = mime_type.into();
+        self.data.retain(|(m, _)| m != &mime_type);
+        self.data.push((mime_type, data));
+        self
+    }
+
+    pub fn with_bytes(self, mime_type: impl Into<String>, bytes: impl Into<Arc<[u8]>>) -> Self {
+        self.with_data(mime_type, DragData::Bytes(bytes.into()))
+    }
+
+    pub fn with_value<T: Any + Send + Sync>(self, mime_type: impl Into<String>, value: T) -> Self {
+        self.with_data(mime_type, DragData::Value(Arc::new(value)))
+    }
+
+    pub fn with_text(self, text: &str) -> Self {
+        self.with_bytes(TEXT_MIME_TYPE, text.as_bytes())
+            .with_bytes("text/plain", text.as_bytes())
+    }
+
+    /// Offer a list of uris, e.g. `file:///home/user/image.png`.
+    pub fn with_uris<'a>(self, uris: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut uri_list = String::new();
+        for uri in uris {
+            uri_list.push_str(uri);
+            uri_list.push_str("\r\n");
+        }
+        self.with_bytes(URI_LIST_MIME_TYPE, uri_list.as_bytes())
+            .with_text(uri_list.trim_end())
+    }
+
+    pub fn mime_types(&self) -> impl Iterator<Item = &str> {
+        self.data.iter().map(|(m, _)| m.as_str())
+    }
+
+    pub fn has(&self, mime_type: &str) -> bool {
+        self.data.iter().any(|(m, _)| m == mime_type)
+    }
+
+    pub fn get(&self, mime_type: &str) -> Option<&DragData> {
+        self.data
+            .iter()
+            .find(|(m, _)| m == mime_type)
+            .map(|(_, data)| data)
+    }
+
+    pub fn value<T: Any>(&self, mime_type: &str) -> Option<&T> {
+        match self.get(mime_type)? {
+            DragData::Value(value) => value.downcast_ref(),
+            DragData::Bytes(_) => None,
+        }
+    }
+
+    pub fn bytes(&self, mime_type: &str) -> Option<&[u8]> {
+        match self.get(mime_type)? {
+            DragData::Bytes(bytes) => Some(bytes),
+            DragData::Value(_) => None,
+        }
+    }
+
+    pub fn text(&self) -> Option<String> {
+        [TEXT_MIME_TYPE, "text/plain"]
+            .into_iter()
+            .find_map(|m| self.bytes(m))
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// The data which can be sent to other programs.
+    pub fn export(&self) -> impl Iterator<Item = (&str, &Arc<[u8]>)> {
+        self.data.iter().filter_map(|(m, data)| match data {
+            DragData::Bytes(bytes) => Some((m.as_str(), bytes)),
+            DragData::Value(_) => None,
+        })
+    }
+}
+
+/// The node following the cursor during a drag.
+#[derive(Clone, Debug, Default)]
+pub enum DragImage {
+    /// A translucent block with the size of the source.
+    #[default]
+    Ghost,
+    Image(Handle<Image>, Vec2),
+    /// A node spawned by the user, it's moved with the cursor but not despawned.
+    Node(Entity),
+    None,
+}
+
+#[derive(Component, Clone, Debug, SmartDefault)]
+#[require(Node, Interaction, RelativeCursorPosition, UiDragSourceEventDispatcher)]
+pub struct UiDragSource {
+    pub payload: DragPayload,
+    #[default(DndActions::COPY)]
+    pub actions: DndActions,
+    pub image: DragImage,
+    /// How far the cursor moves before the drag starts.
+    #[default(4.0)]
+    pub threshold: f32,
+}
+
+impl UiDragSource {
+    pub fn new(payload: DragPayload) -> Self {
+        Self {
+            payload,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_actions(mut self, actions: DndActions) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    pub fn with_image(mut self, image: DragImage) -> Self {
+        self.image = image;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum UiDragSourceEvent {
+    Started,
+    /// The cursor moved onto another drop target.
+    TargetChanged(Option<Entity>),
+    Dropped { target: Entity, action: DndActions },
+    Cancelled,
+}
+
+pub type UiDragSourceEventDispatcher = EventDispatcher<UiDragSourceEvent>;
+
+#[derive(Component, Clone, Debug, SmartDefault)]
+#[require(Node, RelativeCursorPosition, UiDropTargetState, UiDropEventDispatcher)]
+pub struct UiDropTarget {
+    /// The accepted mime types in the order of preference, anything is accepted if it's empty.
+    pub mime_types: Vec<String>,
+    #[default(DndActions::all())]
+    pub actions: DndActions,
+}
+
+impl UiDropTarget {
+    pub fn new(mime_types: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            mime_types: mime_types.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// The mime type and the action of a drop on this target.
+    pub fn accept(&self, payload: &DragPayload, actions: DndActions) -> Option<(String, DndActions)> {
+        let action = (self.actions & actions).preferred();
+        if action.is_empty() {
+            return None;
+        }
+        let mime_type = if self.mime_types.is_empty() {
+            payload.mime_types().next()?.to_string()
+        } else {
+            self.mime_types.iter().find(|m| payload.has(m))?.clone()
+        };
+        Some((mime_type, action))
+    }
+}
+
+#[derive(Component, Clone, Debug, Default, Reflect)]
+pub struct UiDropTargetState {
+    pub hovered: bool,
+    /// Whether the drag over the target can be dropped.
+    pub accepted: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum UiDropEvent {
+    Enter {
+        source: Entity,
+        accepted: bool,
+    },
+    Leave {
+        source: Entity,
+    },
+    Drop {
+        source: Entity,
+        payload: DragPayload,
+        mime_type: String,
+        action: DndActions,
+    },
+}
+
+pub type UiDropEventDispatcher = EventDispatcher<UiDropEvent>;
+
+/// A node showing another program, drags over it are sent as [`ExternalDragEvent`].
+#[derive(Component, Clone, Debug, Reflect)]
+#[require(Node, RelativeCursorPosition)]
+pub struct UiExternalDropTarget {
+    pub target: Entity,
+}
+
+#[derive(Message, Clone, Debug)]
+pub enum ExternalDragEvent {
+    /// `position` is relative to the top left corner of the node.
+    Enter {
+        target: Entity,
+        position: Vec2,
+        payload: DragPayload,
+        actions: DndActions,
+    },
+    Motion {
+        target: Entity,
+        position: Vec2,
+    },
+    Leave {
+        target: Entity,
+    },
+    Drop {
+        target: Entity,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HoveredTarget {
+    Internal(Entity),
+    External { node: Entity, target: Entity },
+}
+
+#[derive(Debug)]
+pub struct ActiveDrag {
+    pub source: Entity,
+    pub payload: DragPayload,
+    pub actions: DndActions,
+    /// The cursor position relative to the top left corner of the drag image.
+    pub hotspot: Vec2,
+    pub image: Option<Entity>,
+    pub despawn_image: bool,
+    target: Option<HoveredTarget>,
+    position: Vec2,
+    /// The action chosen by the program under the cursor.
+    pub external_action: DndActions,
+}
+
+impl ActiveDrag {
+    pub fn target(&self) -> Option<Entity> {
+        self.target.map(|target| match target {
+            HoveredTarget::Internal(entity) => entity,
+            HoveredTarget::External { target, .. } => target,
+        })
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct UiDndState {
+    pending: Option<(Entity, Vec2)>,
+    pub drag: Option<ActiveDrag>,
+}
+
+impl UiDndState {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Called by the bridge of [`ExternalDragEvent`] when the program under the cursor
+    /// accepts or rejects the drag.
+    pub fn set_external_action(&mut self, action: DndActions) {
+        if let Some(drag) = &mut self.drag {
+            drag.external_action = action;
+        }
+    }
+}
+
+pub fn start_drag_system(
+    mut state: ResMut<UiDndState>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mouse_position: Res<MousePosition>,
+    source_query: Query<(
+        Entity,
+        &UiDragSource,
+        &Interaction,
+        &ComputedNode,
+        &UiGlobalTransform,
+        &ComputedUiTargetCamera,
+        &UiDragSourceEventDispatcher,
+    )>,
+    theme: Res<Theme>,
+    mut commands: Commands,
+) {
+    if state.drag.is_some() {
+        return;
+    }
+    let Some(cursor) = mouse_position.position else {
+        return;
+    };
+    if mouse.just_pressed(MouseButton::Left) {
+        state.pending = source_query
+            .iter()
+            .find(|(_, _, interaction, ..)| **interaction == Interaction::Pressed)
+            .map(|(entity, ..)| (entity, cursor));
+    }
+    if !mouse.pressed(MouseButton::Left) {
+        state.pending = None;
+        return;
+    }
+    let Some((entity, start)) = state.pending else {
+        return;
+    };
+    let Ok((entity, source, _, computed_node, transform, target_camera, event_dispatcher)) =
+        source_query.get(entity)
+    else {
+        state.pending = None;
+        return;
+    };
+    if cursor.distance(start) < source.threshold {
+        return;
+    }
+    state.pending = None;
+
+    let rect = get_node_rect(transform, computed_node);
+    let hotspot = start - rect.min;
+    let image_node = |size: Vec2| Node {
+        position_type: PositionType::Absolute,
+        left: Val::Px(cursor.x - hotspot.x),
+        top: Val::Px(cursor.y - hotspot.y),
+        width: Val::Px(size.x),
+        height: Val::Px(size.y),
+        ..default()
+    };
+    let (image, despawn_image) = match &source.image {
+        DragImage::Ghost => {
+            let image = commands.spawn((
+                image_node(rect.size()),
+                BackgroundColor(theme.color("panel").with_alpha(0.6)),
+            ));
+            (Some(image.id()), true)
+        }
+        DragImage::Image(handle, size) => {
+            let image = commands.spawn((image_node(*size), ImageNode::new(handle.clone())));
+            (Some(image.id()), true)
+        }
+        DragImage::Node(node) => (Some(*node), false),
+        DragImage::None => (None, false),
+    };
+    if let Some(image) = image.filter(|_| despawn_image) {
+        let mut image = commands.entity(image);
+        image.insert((FocusPolicy::Pass, GlobalZIndex(i32::MAX)));
+        if let Some(camera) = target_camera.get() {
+            image.insert(UiTargetCamera(camera));
+        }
+    }
+
+    event_dispatcher.send(UiDragSourceEvent::Started, &mut commands);
+    state.drag = Some(ActiveDrag {
+        source: entity,
+        payload: source.payload.clone(),
+        actions: source.actions,
+        hotspot,
+        image,
+        despawn_image,
+        target: None,
+        position: cursor,
+        external_action: DndActions::empty(),
+    });
+}
+
+/// Move the drag image, track the drop target under the cursor and drop when the button is
+/// released.
+pub fn update_drag_system(
+    mut state: ResMut<UiDndState>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_position: Res<MousePosition>,
+    mut target_query: Query<(
+        Entity,
+        &UiDropTarget,
+        &RelativeCursorPosition,
+        &ComputedNode,
+        &mut UiDropTargetState,
+        Option<&mut ThemeComponent>,
+        &UiDropEventDispatcher,
+    )>,
+    external_query: Query<(
+        Entity,
+        &UiExternalDropTarget,
+        &RelativeCursorPosition,
+        &ComputedNode,
+    )>,
+    source_query: Query<&UiDragSourceEventDispatcher>,
+    mut image_query: Query<&mut Node>,
+    mut external_events: MessageWriter<ExternalDragEvent>,
+    mut commands: Commands,
+) {
+    let Some(drag) = &mut state.drag else {
+        return;
+    };
+    let cursor = mouse_position.position.unwrap_or(drag.position);
+    let source_dispatcher = source_query.get(drag.source).ok();
+
+    if let Some(mut node) = drag.image.and_then(|e| image_query.get_mut(e).ok()) {
+        let position = cursor - drag.hotspot;
+        if node.left != Val::Px(position.x) || node.top != Val::Px(position.y) {
+            node.position_type = PositionType::Absolute;
+            node.left = Val::Px(position.x);
+            node.top = Val::Px(position.y);
+        }
+    }
+
+    let cancel = keys.just_pressed(KeyCode::Escape);
+    let release = !mouse.pressed(MouseButton::Left);
+
+    // the target drawn on top of the others receives the drag
+    let hovered = if cancel {
+        None
+    } else {
+        let internal = target_query
+            .iter()
+            .filter(|(entity, _, relative, ..)| *entity != drag.source && relative.cursor_over())
+            .map(|(entity, _, _, computed_node, ..)| {
+                (computed_node.stack_index(), HoveredTarget::Internal(entity))
+            });
+        let external = external_query
+            .iter()
+            .filter(|(_, _, relative, _)| relative.cursor_over())
+            .map(|(node, external, _, computed_node)| {
+                (
+                    computed_node.stack_index(),
+                    HoveredTarget::External {
+                        node,
+                        target: external.target,
+                    },
+                )
+            });
+        internal
+            .chain(external)
+            .max_by_key(|(stack_index, _)| *stack_index)
+            .map(|(_, target)| target)
+    };
+    let external_position = |node: Entity| {
+        external_query
+            .get(node)
+            .ok()
+            .and_then(|(_, _, relative, computed_node)| {
+                get_node_mouse_position(relative, computed_node)
+            })
+    };
+
+    if hovered != drag.target {
+        match drag.target.take() {
+            Some(HoveredTarget::Internal(entity)) => {
+                if let Ok((.., mut target_state, theme, event_dispatcher)) =
+                    target_query.get_mut(entity)
+                {
+                    *target_state = UiDropTargetState::default();
+                    if let Some(mut theme) = theme {
+                        theme.set_flag(StyleFlags::HIGHLIGHT, false);
+                    }
+                    event_dispatcher.send(
+                        UiDropEvent::Leave {
+                            source: drag.source,
+                        },
+                        &mut commands,
+                    );
+                }
+            }
+            Some(HoveredTarget::External { target, .. }) => {
+                external_events.write(ExternalDragEvent::Leave { target });
+                drag.external_action = DndActions::empty();
+            }
+            None => {}
+        }
+        match hovered {
+            Some(HoveredTarget::Internal(entity)) => {
+                if let Ok((_, target, _, _, mut target_state, theme, event_dispatcher)) =
+                    target_query.get_mut(entity)
+                {
+                    let accepted = target.accept(&drag.payload, drag.actions).is_some();
+                    *target_state = UiDropTargetState {
+                        hovered: true,
+                        accepted,
+                    };
+                    if let Some(mut theme) = theme {
+                        theme.set_flag(StyleFlags::HIGHLIGHT, accepted);
+                    }
+                    event_dispatcher.send(
+                        UiDropEvent::Enter {
+                            source: drag.source,
+                            accepted,
+                        },
+                        &mut commands,
+                    );
+                }
+            }
+            Some(HoveredTarget::External { node, target }) => {
+                external_events.write(ExternalDragEvent::Enter {
+                    target,
+                    position: external_position(node).unwrap_or_default(),
+                    payload: drag.payload.clone(),
+                    actions: drag.actions,
+                });
+            }
+            None => {}
+        }
+        drag.target = hovered;
+        if let Some(source_dispatcher) = source_dispatcher {
+            source_dispatcher.send(UiDragSourceEvent::TargetChanged(drag.target()), &mut commands);
+        }
+    } else if let Some(HoveredTarget::External { node, target }) = drag.target {
+        if cursor != drag.position {
+            if let Some(position) = external_position(node) {
+                external_events.write(ExternalDragEvent::Motion { target, position });
+            }
+        }
+    }
+    drag.position = cursor;
+
+    if !cancel && !release {
+        return;
+    }
+
+    let dropped = match drag.target {
+        Some(HoveredTarget::Internal(entity)) if !cancel => target_query
+            .get_mut(entity)
+            .ok()
+            .and_then(|(_, target, _, _, mut target_state, theme, event_dispatcher)| {
+                *target_state = UiDropTargetState::default();
+                if let Some(mut theme) = theme {
+                    theme.set_flag(StyleFlags::HIGHLIGHT, false);
+                }
+                let Some((mime_type, action)) = target.accept(&drag.payload, drag.actions) else {
+                    event_dispatcher.send(
+                        UiDropEvent::Leave {
+                            source: drag.source,
+                        },
+                        &mut commands,
+                    );
+                    return None;
+                };
+                event_dispatcher.send(
+                    UiDropEvent::Drop {
+                        source: drag.source,
+                        payload: drag.payload.clone(),
+                        mime_type,
+                        action,
+                    },
+                    &mut commands,
+                );
+                Some((entity, action))
+            }),
+        Some(HoveredTarget::External { target, .. }) if !cancel => {
+            external_events.write(ExternalDragEvent::Drop { target });
+            (!drag.external_action.is_empty()).then_some((target, drag.external_action))
+        }
+        Some(HoveredTarget::External { target, .. }) => {
+            external_events.write(ExternalDragEvent::Leave { target });
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(source_dispatcher) = source_dispatcher {
+        let event = match dropped {
+            Some((target, action)) => UiDragSourceEvent::Dropped { target, action },
+            None => UiDragSourceEvent::Cancelled,
+        };
+        source_dispatcher.send(event, &mut commands);
+    }
+    if let Some(image) = drag.image {
+        if drag.despawn_image {
+            commands.entity(image).despawn();
+        }
+    }
+    state.drag = None;
+}
+
+pub struct UiDragAndDropPlugin;
+impl Plugin for UiDragAndDropPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiDndState>()
+            .add_message::<ExternalDragEvent>()
+            .register_type::<UiDropTargetState>()
+            .register_type::<UiExternalDropTarget>()
+            .add_systems(
+                PreUpdate,
+                (start_drag_system, update_drag_system)
+                    .chain()
+                    .in_set(UiFrameworkSystems::WidgetInputSystems),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_target_accept() {
+        let payload = DragPayload::new()
+            .with_value("application/x-dway-app", 42u32)
+            .with_uris(["file:///tmp/a.txt"]);
+        assert_eq!(payload.value::<u32>("application/x-dway-app"), Some(&42));
+        assert_eq!(payload.text().as_deref(), Some("file:///tmp/a.txt"));
+        assert_eq!(
+            payload.export().map(|(m, _)| m).collect::<Vec<_>>(),
+            vec![URI_LIST_MIME_TYPE, TEXT_MIME_TYPE, "text/plain"]
+        );
+
+        let target = UiDropTarget::new(["text/plain", URI_LIST_MIME_TYPE]);
+        assert_eq!(
+            target.accept(&payload, DndActions::MOVE | DndActions::COPY),
+            Some(("text/plain".to_string(), DndActions::COPY))
+        );
+        let target = UiDropTarget {
+            actions: DndActions::MOVE,
+            ..UiDropTarget::new(["image/png"])
+        };
+        assert_eq!(target.accept(&payload, DndActions::MOVE), None);
+        assert_eq!(UiDropTarget::new(["text/plain"]).accept(&payload, DndActions::empty()), None);
+    }
+}
@@ -11,6 +11,10 @@ use unicode_segmentation::UnicodeSegmentation;
 use crate::{
     event::{EventReceiver, UiEvent},
     prelude::*,
+    text::{
+        clipboard::UiClipboard,
+        editor::{TextEditContext, TextEditResult, TextEditState},
+    },
     theme::{ThemeComponent, WidgetKind},
     widgets::shader::{rounded_rect, RoundedUiRectMaterial},
 };
@@ -22,47 +26,6 @@ pub enum UiInputboxEvent {
     CursorMoved,
 }
 
-#[derive(Debug)]
-pub enum UiInputCommand {
-    Insert(usize, String),
-    Delete(usize, String),
-    Replace(usize, String, String),
-}
-impl UiInputCommand {
-    pub fn new_delete(input_box_state: &UiInputBoxState) -> Self {
-        let cursor = input_box_state.cursor_byte_index();
-        let char = input_box_state
-            .data()
-            .char_indices()
-            .find_map(|(pos, c)| (pos == *cursor).then(|| c.to_string()));
-        Self::Delete(
-            *input_box_state.cursor_byte_index(),
-            char.unwrap_or_default(),
-        )
-    }
-
-    pub fn apply(&self, input_box_state: &mut UiInputBoxState) {
-        match self {
-            UiInputCommand::Insert(p, d) => {
-                input_box_state.data_mut().insert_str(*p, d);
-            }
-            UiInputCommand::Delete(p, d) => {
-                let split_off = input_box_state.data_mut().split_off(*p);
-                input_box_state
-                    .data_mut()
-                    .push_str(split_off.split_at(d.len()).1);
-            }
-            UiInputCommand::Replace(p, remove, insert) => {
-                let split_off = input_box_state.data_mut().split_off(*p);
-                input_box_state.data_mut().push_str(insert);
-                input_box_state
-                    .data_mut()
-                    .push_str(split_off.split_at(remove.len()).1);
-            }
-        }
-    }
-}
-
 structstruck::strike! {
     #[derive(Component, SmartDefault, Builder)]
     #[require(RelativeCursorPosition, Interaction, UiInputBoxEventDispatcher, UiInput)]
@@ -72,7 +35,7 @@ structstruck::strike! {
         pub placeholder: String,
         pub kind:
             #[derive(Default)]
-            enum UiInputBoxKind{
+            pub enum UiInputBoxKind{
                 #[default]
                 Normal,
                 Password,
@@ -98,6 +61,7 @@ pub fn move_cursor(
     inputbox: &UiInputBox,
     text_layout: &TextLayoutInfo,
     inputbox_state: &mut UiInputBoxState,
+    extend: bool,
 ) {
     let line_start = position.y - position.y % inputbox.text_size;
     let line_end = line_start + inputbox.line_height();
@@ -145,10 +109,31 @@ pub fn move_cursor(
                 .data()
                 .floor_char_boundary(byte_index.saturating_add(1));
         }
+        inputbox_state.editor_mut().move_to(byte_index, extend);
         inputbox_state.set_cursor_byte_index(byte_index);
     };
 }
 
+fn apply_edit(
+    inputbox_state: &mut UiInputBoxState,
+    editor: TextEditState,
+    data: String,
+    result: TextEditResult,
+    event_dispatcher: &UiInputBoxEventDispatcher,
+    commands: &mut Commands,
+) {
+    let cursor = editor.cursor;
+    *inputbox_state.editor_mut() = editor;
+    if result == TextEditResult::Changed {
+        inputbox_state.set_data(data);
+        event_dispatcher.send(UiInputboxEvent::Changed, commands);
+    }
+    if *inputbox_state.cursor_byte_index() != cursor {
+        inputbox_state.set_cursor_byte_index(cursor);
+        event_dispatcher.send(UiInputboxEvent::CursorMoved, commands);
+    }
+}
+
 fn on_input_event(
     event: UiEvent<UiInputEvent>,
     mut query: Query<(
@@ -162,6 +147,8 @@ fn on_input_event(
         &UiInputBoxEventDispatcher,
     )>,
     text_node_query: Query<(Ref<ComputedNode>, Ref<TextLayoutInfo>)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut clipboard: ResMut<UiClipboard>,
     mut input_focus_event: MessageWriter<UiFocusEvent>,
     mut commands: Commands,
 ) {
@@ -178,6 +165,7 @@ fn on_input_event(
     else {
         return;
     };
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
 
     match &*event {
         UiInputEvent::MousePress(_) => {
@@ -202,7 +190,13 @@ fn on_input_event(
                 if let Some(mouse_position) =
                     get_node_mouse_position(relative_pos, &computed_node)
                 {
-                    move_cursor(mouse_position, inputbox, &text_layout, &mut inputbox_state);
+                    move_cursor(
+                        mouse_position,
+                        inputbox,
+                        &text_layout,
+                        &mut inputbox_state,
+                        shift,
+                    );
                 }
             }
         }
@@ -218,7 +212,13 @@ fn on_input_event(
                 if let Some(mouse_position) =
                     get_node_mouse_position(relative_pos, &computed_node)
                 {
-                    move_cursor(mouse_position, inputbox, &text_layout, &mut inputbox_state);
+                    move_cursor(
+                        mouse_position,
+                        inputbox,
+                        &text_layout,
+                        &mut inputbox_state,
+                        true,
+                    );
                 }
             }
         }
@@ -233,108 +233,64 @@ fn on_input_event(
             };
 
             match &key.logical_key {
-                Key::Character(s) => {
-                    if !inputbox.readonly {
-                        UiInputCommand::Insert(*inputbox_state.cursor_byte_index(), s.to_string())
-                            .apply(&mut inputbox_state);
-                        let position = inputbox_state
-                            .data()
-                            .floor_char_boundary(inputbox_state.cursor_byte_index() + s.len());
-                        inputbox_state.set_cursor_byte_index(position);
-                        event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                        event_dispatcher.send(UiInputboxEvent::Changed, &mut commands);
-                    }
-                }
-                Key::Space => {
-                    if !inputbox.readonly {
-                        UiInputCommand::Insert(
-                            *inputbox_state.cursor_byte_index(),
-                            " ".to_string(),
-                        )
-                        .apply(&mut inputbox_state);
-                        let position = inputbox_state
-                            .data()
-                            .floor_char_boundary(inputbox_state.cursor_byte_index() + " ".len());
-                        inputbox_state.set_cursor_byte_index(position);
-                        event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                        event_dispatcher.send(UiInputboxEvent::Changed, &mut commands);
-                    }
-                }
-                Key::Enter => {
-                    if !inputbox.readonly && inputbox.multi_line {
-                        UiInputCommand::Insert(
-                            *inputbox_state.cursor_byte_index(),
-                            "\n".to_string(),
-                        )
-                        .apply(&mut inputbox_state);
-                        let position = inputbox_state
-                            .data()
-                            .floor_char_boundary(inputbox_state.cursor_byte_index() + "\n".len());
-                        inputbox_state.set_cursor_byte_index(position);
-                    }
-                    if !inputbox.readonly {
-                        event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                        event_dispatcher.send(UiInputboxEvent::Enter, &mut commands);
-                    }
-                }
-                Key::Backspace => {
-                    if !inputbox.readonly && *inputbox_state.cursor_byte_index() != 0 {
-                        *inputbox_state.cursor_byte_index_mut() =
-                            inputbox_state.data().floor_char_boundary(
-                                inputbox_state.cursor_byte_index().saturating_sub(1),
-                            );
-                        UiInputCommand::new_delete(&inputbox_state).apply(&mut inputbox_state);
-                        event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                        event_dispatcher.send(UiInputboxEvent::Changed, &mut commands);
-                    }
-                }
-                Key::Delete => {
-                    if !inputbox.readonly {
-                        UiInputCommand::new_delete(&inputbox_state).apply(&mut inputbox_state);
-                        event_dispatcher.send(UiInputboxEvent::Changed, &mut commands);
-                    }
-                }
-                Key::Home => {
-                    *inputbox_state.cursor_byte_index_mut() = 0;
-                    event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                }
-                Key::End => {
-                    *inputbox_state.cursor_byte_index_mut() = inputbox_state.data().len();
-                    event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                }
-                Key::ArrowRight => {
-                    *inputbox_state.cursor_byte_index_mut() = inputbox_state
-                        .data()
-                        .floor_char_boundary(inputbox_state.cursor_byte_index().saturating_add(1));
-                    event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                }
-                Key::ArrowLeft => {
-                    *inputbox_state.cursor_byte_index_mut() = inputbox_state
-                        .data()
-                        .ceil_char_boundary(inputbox_state.cursor_byte_index().saturating_sub(1));
-                    event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
-                }
-                Key::ArrowUp => {
-                    move_cursor(
-                        *inputbox_state.cursor_position() - inputbox.line_height() * Vec2::Y,
-                        inputbox,
-                        &text_layout,
-                        &mut inputbox_state,
-                    );
-                }
-                Key::ArrowDown => {
+                Key::ArrowUp | Key::ArrowDown if inputbox.multi_line => {
+                    let direction = if key.logical_key == Key::ArrowUp {
+                        -1.0
+                    } else {
+                        1.0
+                    };
                     move_cursor(
-                        *inputbox_state.cursor_position() + inputbox.line_height() * Vec2::Y,
+                        *inputbox_state.cursor_position()
+                            + direction * inputbox.line_height() * Vec2::Y,
                         inputbox,
                         &text_layout,
                         &mut inputbox_state,
+                        shift,
                     );
+                    event_dispatcher.send(UiInputboxEvent::CursorMoved, &mut commands);
+                    return;
                 }
                 Key::Escape => {
                     input_focus_event.write(UiFocusEvent::FocusLeaveRequest(entity));
+                    return;
                 }
                 _ => {}
+            }
+
+            let context = TextEditContext {
+                multi_line: inputbox.multi_line,
+                readonly: inputbox.readonly,
+                ..TextEditContext::from_input(&keys)
             };
+            let mut data = inputbox_state.data().clone();
+            let mut editor = std::mem::take(inputbox_state.editor_mut());
+            let result = UiClipboard::edit(&mut clipboard, |clipboard| {
+                editor.handle_key(&mut data, &key.logical_key, context, clipboard)
+            });
+            apply_edit(
+                &mut inputbox_state,
+                editor,
+                data,
+                result,
+                event_dispatcher,
+                &mut commands,
+            );
+            if key.logical_key == Key::Enter && !inputbox.readonly {
+                event_dispatcher.send(UiInputboxEvent::Enter, &mut commands);
+            }
+        }
+        UiInputEvent::Ime(ime) => {
+            let mut data = inputbox_state.data().clone();
+            let mut editor = std::mem::take(inputbox_state.editor_mut());
+            let result = editor.handle_ime(&mut data, ime, inputbox.readonly);
+            apply_edit(
+                &mut inputbox_state,
+                editor,
+                data,
+                result,
+                event_dispatcher,
+                &mut commands,
+            );
         }
         _ => (),
     }
@@ -366,7 +322,9 @@ UiInputBox=>
 @use_state(pub cursor_byte_index: usize)
 @use_state(pub show_cursor: bool = true)
 @use_state(pub cursor_position: Vec2)
-@use_state(#[reflect(ignore)] pub undo: undo::history::History<UiInputCommand>)
+@use_state(pub selection_rect: Option<Rect>)
+@use_state(pub preedit_rect: Option<Rect>)
+@use_state(#[reflect(ignore)] pub editor: TextEditState)
 @world_query(focus_policy: &mut FocusPolicy)
 @arg(text_node_query: Query<Ref<TextLayoutInfo>>)
 @before{{
@@ -377,16 +335,28 @@ UiInputBox=>
         state.set_cursor_position(Vec2::ZERO);
     }
 
+    if state.editor().cursor > state.data().len() {
+        let data = state.data().clone();
+        state.editor_mut().clamp(&data);
+    }
     if let Ok(text_layout) = text_node_query.get(widget.node_text_entity) {
         update_cursor(&prop, &mut state, text_layout);
     }
 }}
 <Node @id="text" @style="full"
-    Text=(Text::new(state.data()))
+    Text=(Text::new(state.editor().display_text(state.data())))
     TextFont=(theme.text_font(prop.text_size))
     TextColor=(theme.default_text_color())
     TextLayout=( TextLayout::new(Justify::Left, LineBreak::WordOrCharacter) )
 />
+<Node @style="absolute full" @if(state.selection_rect().is_some())>
+    <(rect_node(state.selection_rect().unwrap_or_default()))
+        @id="selection" @material(RoundedUiRectMaterial=>rounded_rect(theme.color("inputbox:selection"), 4.0)) />
+</Node>
+<Node @style="absolute full" @if(state.preedit_rect().is_some())>
+    <(rect_node(state.preedit_rect().unwrap_or_default()))
+        @id="preedit" @material(RoundedUiRectMaterial=>rounded_rect(theme.color("inputbox:cursor"), 1.0)) />
+</Node>
 <Node @style="absolute full" @if(*state.show_cursor())>
     <(Node{
         left: Val::Px(state.cursor_position().x),
@@ -397,32 +367,80 @@ UiInputBox=>
 </Node>
 }
 
-fn update_cursor(prop: &UiInputBox, state: &mut UiInputBoxState, text_layout: Ref<TextLayoutInfo>) {
-    if text_layout.is_changed() || state.cursor_byte_index_is_changed() {
-        let glyphs = &text_layout.glyphs;
-        let byte_index = *state.cursor_byte_index();
+fn rect_node(rect: Rect) -> Node {
+    Node {
+        position_type: PositionType::Absolute,
+        left: Val::Px(rect.min.x),
+        top: Val::Px(rect.min.y),
+        width: Val::Px(rect.width()),
+        height: Val::Px(rect.height()),
+        ..default()
+    }
+}
 
-        let gr_index = UnicodeSegmentation::grapheme_indices(&**state.data(), true)
-            .position(|(index, value)| index <= byte_index && index + value.len() > byte_index);
+fn glyph_position(
+    prop: &UiInputBox,
+    text: &str,
+    text_layout: &TextLayoutInfo,
+    byte_index: usize,
+) -> Vec2 {
+    let glyphs = &text_layout.glyphs;
+    let gr_index = UnicodeSegmentation::grapheme_indices(text, true)
+        .position(|(index, value)| index <= byte_index && index + value.len() > byte_index);
 
-        let position = if let Some(glyph) = gr_index.and_then(|i| glyphs.get(i)) {
-            Vec2::new(
-                glyph.position.x - 0.5 * glyph.size.x,
-                glyph.position.y - glyph.position.y % prop.line_height(),
-            )
-        } else if byte_index == state.data().len() {
-            glyphs
-                .last()
-                .map(|glyph| {
-                    Vec2::new(
-                        glyph.position.x + 0.5 * glyph.size.x,
-                        glyph.position.y - glyph.position.y % prop.line_height(),
-                    )
-                })
-                .unwrap_or_default()
+    if let Some(glyph) = gr_index.and_then(|i| glyphs.get(i)) {
+        Vec2::new(
+            glyph.position.x - 0.5 * glyph.size.x,
+            glyph.position.y - glyph.position.y % prop.line_height(),
+        )
+    } else if byte_index == text.len() {
+        glyphs
+            .last()
+            .map(|glyph| {
+                Vec2::new(
+                    glyph.position.x + 0.5 * glyph.size.x,
+                    glyph.position.y - glyph.position.y % prop.line_height(),
+                )
+            })
+            .unwrap_or_default()
+    } else {
+        Vec2::ZERO
+    }
+}
+
+fn update_cursor(prop: &UiInputBox, state: &mut UiInputBoxState, text_layout: Ref<TextLayoutInfo>) {
+    if !text_layout.is_changed()
+        && !state.cursor_byte_index_is_changed()
+        && !state.editor_is_changed()
+    {
+        return;
+    }
+    let line_height = prop.line_height();
+    let display = state.editor().display_text(state.data());
+    let position = glyph_position(prop, &display, &text_layout, *state.cursor_byte_index());
+    state.set_cursor_position(position);
+
+    let selection_rect = state.editor().selection().map(|range| {
+        let start = glyph_position(prop, &display, &text_layout, range.start);
+        let end = glyph_position(prop, &display, &text_layout, range.end);
+        if start.y == end.y {
+            Rect::new(start.x, start.y, end.x, end.y + line_height)
         } else {
-            Vec2::ZERO
-        };
-        state.set_cursor_position(position);
+            // a selection across lines covers the full width of those lines
+            let right = text_layout.size.x.max(start.x).max(end.x);
+            Rect::new(0.0, start.y, right, end.y + line_height)
+        }
+    });
+    if *state.selection_rect() != selection_rect {
+        state.set_selection_rect(selection_rect);
+    }
+
+    let preedit_rect = state.editor().preedit_range().map(|range| {
+        let start = glyph_position(prop, &display, &text_layout, range.start);
+        let end = glyph_position(prop, &display, &text_layout, range.end);
+        Rect::new(start.x, start.y + line_height - 2.0, end.x, start.y + line_height)
+    });
+    if *state.preedit_rect() != preedit_rect {
+        state.set_preedit_rect(preedit_rect);
     }
 }
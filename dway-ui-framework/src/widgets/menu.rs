@@ -0,0 +1,1437 @@
+//! Menus built from a tree of [`MenuItem`]s.
+//!
+//! A [`UiMenu`] holds the model and receives the [`UiMenuEvent`]s. It is shown
+//! as a popup by [`open_menu`], or as a row of its top level items with
+//! [`UiMenuBar`]. Submenus open on hover and the open menu is navigated with
+//! the arrow keys, `Enter` and `Escape`.
+
+use std::fmt::Display;
+
+use accesskit::Role;
+use bevy::ui::{RelativeCursorPosition, UiSystem};
+use bevy_svg::prelude::Svg;
+
+use crate::{
+    a11y::{UiAccessibilityHidden, UiAccessible},
+    mvvm::selection::KeyboardNavigation,
+    prelude::*,
+    UiFrameworkSystems,
+};
+
+/// How long the cursor stays on an item before its submenu opens, in seconds.
+pub const SUBMENU_DELAY: f32 = 0.2;
+pub const MENU_ZINDEX: i32 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub enum MenuItemKind {
+    #[default]
+    Action,
+    Check(bool),
+    /// Adjacent radio items form a group, checking one unchecks the others.
+    Radio(bool),
+    Separator,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MenuIcon {
+    Image(Handle<Image>),
+    Svg(Handle<Svg>),
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    pub struct AcceleratorModifiers: u8 {
+        const CTRL = 1;
+        const SHIFT = 1 << 1;
+        const ALT = 1 << 2;
+        const SUPER = 1 << 3;
+    }
+}
+
+/// A key combination which activates a menu item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Accelerator {
+    pub key: KeyCode,
+    pub modifiers: AcceleratorModifiers,
+}
+
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::KeyA),
+    ("B", KeyCode::KeyB),
+    ("C", KeyCode::KeyC),
+    ("D", KeyCode::KeyD),
+    ("E", KeyCode::KeyE),
+    ("F", KeyCode::KeyF),
+    ("G", KeyCode::KeyG),
+    ("H", KeyCode::KeyH),
+    ("I", KeyCode::KeyI),
+    ("J", KeyCode::KeyJ),
+    ("K", KeyCode::KeyK),
+    ("L", KeyCode::KeyL),
+    ("M", KeyCode::KeyM),
+    ("N", KeyCode::KeyN),
+    ("O", KeyCode::KeyO),
+    ("P", KeyCode::KeyP),
+    ("Q", KeyCode::KeyQ),
+    ("R", KeyCode::KeyR),
+    ("S", KeyCode::KeyS),
+    ("T", KeyCode::KeyT),
+    ("U", KeyCode::KeyU),
+    ("V", KeyCode::KeyV),
+    ("W", KeyCode::KeyW),
+    ("X", KeyCode::KeyX),
+    ("Y", KeyCode::KeyY),
+    ("Z", KeyCode::KeyZ),
+    ("0", KeyCode::Digit0),
+    ("1", KeyCode::Digit1),
+    ("2", KeyCode::Digit2),
+    ("3", KeyCode::Digit3),
+    ("4", KeyCode::Digit4),
+    ("5", KeyCode::Digit5),
+    ("6", KeyCode::Digit6),
+    ("7", KeyCode::Digit7),
+    ("8", KeyCode::Digit8),
+    ("9", KeyCode::Digit9),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("Enter", KeyCode::Enter),
+    ("Escape", KeyCode::Escape),
+    ("Space", KeyCode::Space),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::ArrowUp),
+    ("Down", KeyCode::ArrowDown),
+    ("Left", KeyCode::ArrowLeft),
+    ("Right", KeyCode::ArrowRight),
+    ("+", KeyCode::Equal),
+    ("-", KeyCode::Minus),
+    (",", KeyCode::Comma),
+    (".", KeyCode::Period),
+    ("/", KeyCode::Slash),
+];
+
+const MODIFIER_KEYS: &[(AcceleratorModifiers, [KeyCode; 2])] = &[
+    (
+        AcceleratorModifiers::CTRL,
+        [KeyCode::ControlLeft, KeyCode::ControlRight],
+    ),
+    (
+        AcceleratorModifiers::SHIFT,
+        [KeyCode::ShiftLeft, KeyCode::ShiftRight],
+    ),
+    (AcceleratorModifiers::ALT, [KeyCode::AltLeft, KeyCode::AltRight]),
+    (
+        AcceleratorModifiers::SUPER,
+        [KeyCode::SuperLeft, KeyCode::SuperRight],
+    ),
+];
+
+impl Accelerator {
+    pub fn new(modifiers: AcceleratorModifiers, key: KeyCode) -> Self {
+        Self { key, modifiers }
+    }
+
+    /// Parse a combination like `Ctrl+Shift+S`.
+    pub fn parse(s: &str) -> Option<Self> {
+        // the plus key is written as `Ctrl++`
+        let (s, plus) = match s.strip_suffix("++") {
+            Some(rest) => (rest, true),
+            None => (s, false),
+        };
+        let mut modifiers = AcceleratorModifiers::empty();
+        let mut key = plus.then_some(KeyCode::Equal);
+        for part in s.split('+').map(str::trim) {
+            match &*part.to_lowercase() {
+                "ctrl" | "control" => modifiers |= AcceleratorModifiers::CTRL,
+                "shift" => modifiers |= AcceleratorModifiers::SHIFT,
+                "alt" => modifiers |= AcceleratorModifiers::ALT,
+                "super" | "meta" | "logo" => modifiers |= AcceleratorModifiers::SUPER,
+                _ if key.is_none() => {
+                    key = KEY_NAMES
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(part))
+                        .map(|(_, key)| *key);
+                    key?;
+                }
+                _ => return None,
+            }
+        }
+        Some(Self::new(modifiers, key?))
+    }
+
+    pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>) -> bool {
+        keys.just_pressed(self.key)
+            && MODIFIER_KEYS
+                .iter()
+                .all(|(modifier, codes)| keys.any_pressed(*codes) == self.modifiers.contains(*modifier))
+    }
+}
+
+impl Display for Accelerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in [
+            (AcceleratorModifiers::CTRL, "Ctrl"),
+            (AcceleratorModifiers::SHIFT, "Shift"),
+            (AcceleratorModifiers::ALT, "Alt"),
+            (AcceleratorModifiers::SUPER, "Super"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match KEY_NAMES.iter().find(|(_, key)| *key == self.key) {
+            Some((name, _)) => write!(f, "{name}"),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+/// A node of a menu, the items with children are submenus.
+#[derive(Clone, Debug, PartialEq, SmartDefault)]
+pub struct MenuItem {
+    pub id: i64,
+    pub label: String,
+    pub kind: MenuItemKind,
+    pub icon: Option<MenuIcon>,
+    pub accelerator: Option<Accelerator>,
+    #[default(true)]
+    pub enabled: bool,
+    pub children: Vec<MenuItem>,
+}
+
+impl MenuItem {
+    pub fn action(id: i64, label: impl Into<String>) -> Self {
+        Self {
+            id,
+            label: label.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn check(id: i64, label: impl Into<String>, checked: bool) -> Self {
+        Self {
+            kind: MenuItemKind::Check(checked),
+            ..Self::action(id, label)
+        }
+    }
+
+    pub fn radio(id: i64, label: impl Into<String>, checked: bool) -> Self {
+        Self {
+            kind: MenuItemKind::Radio(checked),
+            ..Self::action(id, label)
+        }
+    }
+
+    pub fn separator() -> Self {
+        Self {
+            kind: MenuItemKind::Separator,
+            enabled: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn submenu(
+        id: i64,
+        label: impl Into<String>,
+        children: impl IntoIterator<Item = MenuItem>,
+    ) -> Self {
+        Self {
+            children: children.into_iter().collect(),
+            ..Self::action(id, label)
+        }
+    }
+
+    pub fn with_icon(mut self, icon: MenuIcon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Panics if `accelerator` can't be parsed by [`Accelerator::parse`].
+    pub fn with_accelerator(mut self, accelerator: &str) -> Self {
+        self.accelerator = Some(
+            Accelerator::parse(accelerator)
+                .unwrap_or_else(|| panic!("invalid accelerator: {accelerator:?}")),
+        );
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn is_submenu(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// Whether the item can be focused by the keyboard.
+    pub fn is_selectable(&self) -> bool {
+        self.enabled && self.kind != MenuItemKind::Separator
+    }
+
+    pub fn checked(&self) -> Option<bool> {
+        match self.kind {
+            MenuItemKind::Check(checked) | MenuItemKind::Radio(checked) => Some(checked),
+            _ => None,
+        }
+    }
+}
+
+/// The model of a menu, the entity receives the [`UiMenuEvent`]s.
+#[derive(Component, Clone, Debug, Default)]
+#[require(UiMenuEventDispatcher)]
+pub struct UiMenu {
+    pub items: Vec<MenuItem>,
+}
+
+impl UiMenu {
+    pub fn new(items: impl IntoIterator<Item = MenuItem>) -> Self {
+        Self {
+            items: items.into_iter().collect(),
+        }
+    }
+
+    pub fn get(&self, path: &[usize]) -> Option<&MenuItem> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.items.get(*first)?, |item, i| item.children.get(*i))
+    }
+
+    /// The items of the submenu at `path`, the top level items for an empty path.
+    pub fn children(&self, path: &[usize]) -> Option<&[MenuItem]> {
+        if path.is_empty() {
+            Some(&self.items)
+        } else {
+            self.get(path).map(|item| &*item.children)
+        }
+    }
+
+    fn children_mut(&mut self, path: &[usize]) -> Option<&mut Vec<MenuItem>> {
+        path.iter().try_fold(&mut self.items, |items, i| {
+            items.get_mut(*i).map(|item| &mut item.children)
+        })
+    }
+
+    /// The path of the first enabled item with the accelerator.
+    pub fn find_accelerator(&self, keys: &ButtonInput<KeyCode>) -> Option<Vec<usize>> {
+        fn find(
+            items: &[MenuItem],
+            keys: &ButtonInput<KeyCode>,
+            path: &mut Vec<usize>,
+        ) -> bool {
+            for (index, item) in items.iter().enumerate() {
+                if !item.enabled {
+                    continue;
+                }
+                path.push(index);
+                if (!item.is_submenu() && item.accelerator.is_some_and(|a| a.just_pressed(keys)))
+                    || find(&item.children, keys, path)
+                {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+        let mut path = vec![];
+        find(&self.items, keys, &mut path).then_some(path)
+    }
+
+    /// Toggle the item at `path` if it is checkable and return it.
+    pub fn activate(&mut self, path: &[usize]) -> Option<&MenuItem> {
+        let (index, parent) = path.split_last()?;
+        let siblings = self.children_mut(parent)?;
+        match siblings.get(*index)?.kind {
+            MenuItemKind::Check(checked) => {
+                siblings[*index].kind = MenuItemKind::Check(!checked);
+            }
+            MenuItemKind::Radio(_) => {
+                let start = siblings[..*index]
+                    .iter()
+                    .rposition(|item| !matches!(item.kind, MenuItemKind::Radio(_)))
+                    .map_or(0, |i| i + 1);
+                let end = siblings[*index..]
+                    .iter()
+                    .position(|item| !matches!(item.kind, MenuItemKind::Radio(_)))
+                    .map_or(siblings.len(), |i| index + i);
+                for (i, item) in siblings[start..end].iter_mut().enumerate() {
+                    item.kind = MenuItemKind::Radio(start + i == *index);
+                }
+            }
+            _ => {}
+        }
+        siblings.get(*index)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Reflect)]
+pub enum UiMenuEvent {
+    /// `checked` is the new state of checkable items.
+    Activated { id: i64, checked: Option<bool> },
+    Opened,
+    Closed,
+}
+
+pub type UiMenuEventDispatcher = EventDispatcher<UiMenuEvent>;
+
+/// Shows the top level items of the [`UiMenu`] of the entity in a row, their
+/// submenus open below them.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[require(UiMenu, Node, Interaction, RelativeCursorPosition)]
+pub struct UiMenuBar;
+
+/// Where a popup menu is opened, it is moved to stay inside the viewport.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum MenuPlacement {
+    /// At a point in the logical coordinates of the camera of the menu, such
+    /// as the cursor position.
+    Point(Vec2),
+    /// Below the node, or above it if there is no space.
+    Below(Entity),
+    /// At the right of the node, or at its left if there is no space.
+    Beside(Entity),
+}
+
+/// A list of the items of a menu: a menu bar, a popup menu or a submenu.
+#[derive(Component, Debug, Clone)]
+#[require(Node, RelativeCursorPosition, KeyboardNavigation)]
+pub struct UiMenuLevel {
+    pub menu: Entity,
+    /// The path of the submenu in the model, empty for the top level.
+    pub path: Vec<usize>,
+    pub parent: Option<Entity>,
+    pub horizontal: bool,
+    pub placement: Option<MenuPlacement>,
+    pub focused: Option<usize>,
+    /// The open submenu and the index of its item.
+    pub child: Option<(usize, Entity)>,
+    pub rows: Vec<Entity>,
+    hover: Option<(usize, f32)>,
+    placed: bool,
+}
+
+impl UiMenuLevel {
+    pub fn new(menu: Entity, path: Vec<usize>, parent: Option<Entity>) -> Self {
+        Self {
+            menu,
+            path,
+            parent,
+            horizontal: false,
+            placement: None,
+            focused: None,
+            child: None,
+            rows: vec![],
+            hover: None,
+            placed: false,
+        }
+    }
+}
+
+/// A popup menu which is not active yet.
+#[derive(Component)]
+struct PendingMenuPopup;
+
+/// A row of a [`UiMenuLevel`].
+#[derive(Component, Debug, Clone)]
+#[require(Node, Interaction)]
+pub struct UiMenuRow {
+    pub level: Entity,
+    pub index: usize,
+    pressed: bool,
+}
+
+/// The open levels of the active menu, from the outermost to the innermost.
+#[derive(Resource, Default, Debug)]
+pub struct UiMenuState {
+    pub active: Vec<Entity>,
+    focus_before_open: Option<Entity>,
+}
+
+impl UiMenuState {
+    pub fn is_open(&self) -> bool {
+        !self.active.is_empty()
+    }
+}
+
+fn menu_popup_bundle(level: UiMenuLevel, depth: usize) -> impl Bundle {
+    (
+        UiPopup::default().with_close_policy(PopupClosePolicy::None),
+        Node {
+            position_type: PositionType::Absolute,
+            min_width: Val::Px(160.0),
+            ..style!("flex-col p-4")
+        },
+        // hidden until it is placed
+        Visibility::Hidden,
+        GlobalZIndex(MENU_ZINDEX + depth as i32),
+        UiAccessible::new(Role::Menu),
+        level,
+    )
+}
+
+/// Open the [`UiMenu`] of `menu` as a popup, the open menu is closed.
+pub fn open_menu(commands: &mut Commands, menu: Entity, placement: MenuPlacement) -> Entity {
+    let mut level = UiMenuLevel::new(menu, vec![], None);
+    level.placement = Some(placement);
+    commands
+        .spawn((menu_popup_bundle(level, 0), PendingMenuPopup))
+        .id()
+}
+
+/// The position of a menu of `size` next to `anchor` inside `viewport`.
+pub fn place_menu(anchor: Rect, size: Vec2, viewport: Vec2, beside: bool) -> Vec2 {
+    let (mut position, flipped) = if beside {
+        (
+            Vec2::new(anchor.max.x, anchor.min.y),
+            Vec2::new(anchor.min.x - size.x, anchor.min.y),
+        )
+    } else {
+        (
+            Vec2::new(anchor.min.x, anchor.max.y),
+            Vec2::new(anchor.min.x, anchor.min.y - size.y),
+        )
+    };
+    // flip to the other side of the anchor on the main axis, shift on the other
+    if beside {
+        if position.x + size.x > viewport.x && flipped.x >= 0.0 {
+            position.x = flipped.x;
+        }
+        position.y = position.y.min(viewport.y - size.y);
+    } else {
+        if position.y + size.y > viewport.y && flipped.y >= 0.0 {
+            position.y = flipped.y;
+        }
+        position.x = position.x.min(viewport.x - size.x);
+    }
+    position.max(Vec2::ZERO)
+}
+
+fn first_selectable(items: &[MenuItem]) -> Option<usize> {
+    items.iter().position(MenuItem::is_selectable)
+}
+
+fn next_selectable(items: &[MenuItem], from: Option<usize>, forward: bool) -> Option<usize> {
+    let len = items.len();
+    if len == 0 {
+        return None;
+    }
+    let start = from.unwrap_or(if forward { len - 1 } else { 0 });
+    (1..=len)
+        .map(|step| {
+            if forward {
+                (start + step) % len
+            } else {
+                (start + len - step % len) % len
+            }
+        })
+        .find(|i| items[*i].is_selectable())
+}
+
+/// Close the levels opened from `after`, or all levels if it isn't open.
+fn close_levels(
+    state: &mut UiMenuState,
+    after: Option<Entity>,
+    levels: &mut Query<&mut UiMenuLevel>,
+    dispatchers: &Query<&UiMenuEventDispatcher>,
+    focus_state: &mut UiFocusState,
+    commands: &mut Commands,
+) {
+    let start = after
+        .and_then(|after| state.active.iter().position(|e| *e == after))
+        .map_or(0, |i| i + 1);
+    if start == 0 {
+        if let Some(dispatcher) = state
+            .active
+            .first()
+            .and_then(|e| levels.get(*e).ok())
+            .and_then(|level| dispatchers.get(level.menu).ok())
+        {
+            dispatcher.send(UiMenuEvent::Closed, commands);
+        }
+    }
+    for entity in state.active.drain(start..) {
+        let Ok(mut level) = levels.get_mut(entity) else {
+            continue;
+        };
+        level.child = None;
+        if level.horizontal {
+            level.focused = None;
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+    if let Some(mut level) = after.and_then(|e| levels.get_mut(e).ok()) {
+        level.child = None;
+    }
+    focus_state.navigation_focus = state.active.last().copied().or(state.focus_before_open);
+}
+
+/// Open the submenu of the item `index` of a level.
+#[allow(clippy::too_many_arguments)]
+fn open_submenu(
+    level_entity: Entity,
+    index: usize,
+    focus_first: bool,
+    state: &mut UiMenuState,
+    levels: &mut Query<&mut UiMenuLevel>,
+    menus: &Query<&mut UiMenu>,
+    dispatchers: &Query<&UiMenuEventDispatcher>,
+    focus_state: &mut UiFocusState,
+    commands: &mut Commands,
+) {
+    close_levels(state, Some(level_entity), levels, dispatchers, focus_state, commands);
+    let Ok(mut level) = levels.get_mut(level_entity) else {
+        return;
+    };
+    let Ok(menu) = menus.get(level.menu) else {
+        return;
+    };
+    let mut path = level.path.clone();
+    path.push(index);
+    level.focused = Some(index);
+    let Some(item) = menu.get(&path).filter(|item| item.is_submenu() && item.enabled) else {
+        return;
+    };
+    let Some(&row) = level.rows.get(index) else {
+        return;
+    };
+
+    let mut child = UiMenuLevel::new(level.menu, path, Some(level_entity));
+    child.placement = Some(if level.horizontal {
+        MenuPlacement::Below(row)
+    } else {
+        MenuPlacement::Beside(row)
+    });
+    child.focused = focus_first.then(|| first_selectable(&item.children)).flatten();
+    let depth = child.path.len();
+    let child_entity = commands.spawn(menu_popup_bundle(child, depth)).id();
+    level.child = Some((index, child_entity));
+
+    if state.active.is_empty() {
+        state.focus_before_open = focus_state.navigation_focus;
+        if let Ok(dispatcher) = dispatchers.get(level.menu) {
+            dispatcher.send(UiMenuEvent::Opened, commands);
+        }
+    }
+    if !state.active.contains(&level_entity) {
+        state.active = vec![level_entity];
+    }
+    state.active.push(child_entity);
+    focus_state.navigation_focus = Some(child_entity);
+}
+
+/// Activate the item at `path`: open its submenu or toggle it and close the menu.
+#[allow(clippy::too_many_arguments)]
+fn activate_item(
+    level_entity: Entity,
+    index: usize,
+    state: &mut UiMenuState,
+    levels: &mut Query<&mut UiMenuLevel>,
+    menus: &mut Query<&mut UiMenu>,
+    dispatchers: &Query<&UiMenuEventDispatcher>,
+    focus_state: &mut UiFocusState,
+    commands: &mut Commands,
+) {
+    let Ok(level) = levels.get(level_entity) else {
+        return;
+    };
+    let menu_entity = level.menu;
+    let mut path = level.path.clone();
+    path.push(index);
+    let Some(item) = menus.get(menu_entity).ok().and_then(|menu| menu.get(&path)) else {
+        return;
+    };
+    if !item.is_selectable() {
+        return;
+    }
+    if item.is_submenu() {
+        open_submenu(
+            level_entity,
+            index,
+            true,
+            state,
+            levels,
+            menus,
+            dispatchers,
+            focus_state,
+            commands,
+        );
+        return;
+    }
+    trigger_item(menu_entity, &path, menus, dispatchers, commands);
+    close_levels(state, None, levels, dispatchers, focus_state, commands);
+}
+
+fn trigger_item(
+    menu_entity: Entity,
+    path: &[usize],
+    menus: &mut Query<&mut UiMenu>,
+    dispatchers: &Query<&UiMenuEventDispatcher>,
+    commands: &mut Commands,
+) {
+    let Ok(mut menu) = menus.get_mut(menu_entity) else {
+        return;
+    };
+    let Some(item) = menu.activate(path) else {
+        return;
+    };
+    if let Ok(dispatcher) = dispatchers.get(menu_entity) {
+        dispatcher.send(
+            UiMenuEvent::Activated {
+                id: item.id,
+                checked: item.checked(),
+            },
+            commands,
+        );
+    }
+}
+
+/// Mouse, keyboard and accelerator input of the menus.
+#[allow(clippy::too_many_arguments)]
+pub fn menu_input_system(
+    mut state: ResMut<UiMenuState>,
+    mut focus_state: ResMut<UiFocusState>,
+    mut levels: Query<&mut UiMenuLevel>,
+    pending_query: Query<Entity, With<PendingMenuPopup>>,
+    mut row_query: Query<(&mut UiMenuRow, &Interaction), Changed<Interaction>>,
+    cursor_query: Query<&RelativeCursorPosition, With<UiMenuLevel>>,
+    mut menus: Query<&mut UiMenu>,
+    bar_query: Query<Entity, With<UiMenuBar>>,
+    dispatchers: Query<&UiMenuEventDispatcher>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_secs();
+    state.active.retain(|e| levels.contains(*e));
+
+    // a new popup menu replaces the open menu
+    for entity in &pending_query {
+        commands.entity(entity).remove::<PendingMenuPopup>();
+        close_levels(
+            &mut state,
+            None,
+            &mut levels,
+            &dispatchers,
+            &mut focus_state,
+            &mut commands,
+        );
+        let Ok(level) = levels.get(entity) else {
+            continue;
+        };
+        if let Ok(dispatcher) = dispatchers.get(level.menu) {
+            dispatcher.send(UiMenuEvent::Opened, &mut commands);
+        }
+        state.focus_before_open = focus_state.navigation_focus;
+        state.active = vec![entity];
+        focus_state.navigation_focus = Some(entity);
+    }
+
+    if mouse.any_just_pressed([MouseButton::Left, MouseButton::Middle, MouseButton::Right])
+        && state.is_open()
+        && !state
+            .active
+            .iter()
+            .any(|e| cursor_query.get(*e).is_ok_and(|c| c.cursor_over()))
+    {
+        close_levels(
+            &mut state,
+            None,
+            &mut levels,
+            &dispatchers,
+            &mut focus_state,
+            &mut commands,
+        );
+    }
+
+    for (mut row, interaction) in &mut row_query {
+        let (level_entity, index) = (row.level, row.index);
+        let released = row.pressed && *interaction == Interaction::Hovered;
+        row.pressed = *interaction == Interaction::Pressed;
+        let Ok(level) = levels.get(level_entity) else {
+            continue;
+        };
+        let horizontal = level.horizontal;
+        let child_index = level.child.map(|(i, _)| i);
+        let bar_active = horizontal && state.active.first() == Some(&level_entity);
+
+        match interaction {
+            Interaction::Pressed if horizontal && child_index == Some(index) => {
+                close_levels(
+                    &mut state,
+                    None,
+                    &mut levels,
+                    &dispatchers,
+                    &mut focus_state,
+                    &mut commands,
+                );
+            }
+            Interaction::Pressed if horizontal => {
+                activate_item(
+                    level_entity,
+                    index,
+                    &mut state,
+                    &mut levels,
+                    &mut menus,
+                    &dispatchers,
+                    &mut focus_state,
+                    &mut commands,
+                );
+            }
+            Interaction::Hovered if horizontal => {
+                // moving over the bar switches between the open menus
+                if bar_active && child_index.is_some_and(|i| i != index) {
+                    open_submenu(
+                        level_entity,
+                        index,
+                        false,
+                        &mut state,
+                        &mut levels,
+                        &menus,
+                        &dispatchers,
+                        &mut focus_state,
+                        &mut commands,
+                    );
+                }
+            }
+            Interaction::Hovered if released => {
+                activate_item(
+                    level_entity,
+                    index,
+                    &mut state,
+                    &mut levels,
+                    &mut menus,
+                    &dispatchers,
+                    &mut focus_state,
+                    &mut commands,
+                );
+            }
+            Interaction::Hovered | Interaction::Pressed => {
+                let mut path = level.path.clone();
+                path.push(index);
+                let selectable = menus
+                    .get(level.menu)
+                    .ok()
+                    .and_then(|menu| menu.get(&path))
+                    .is_some_and(MenuItem::is_selectable);
+                if let Ok(mut level) = levels.get_mut(level_entity) {
+                    if selectable && level.focused != Some(index) {
+                        level.focused = Some(index);
+                    }
+                    level.hover = Some((index, now));
+                }
+            }
+            Interaction::None => {
+                if let Ok(mut level) = levels.get_mut(level_entity) {
+                    if level.hover.is_some_and(|(i, _)| i == index) {
+                        level.hover = None;
+                    }
+                }
+            }
+        }
+    }
+
+    // open the submenu of the item under the cursor after a delay
+    for level_entity in state.active.clone() {
+        let Ok(mut level) = levels.get_mut(level_entity) else {
+            continue;
+        };
+        let Some((index, since)) = level.hover.filter(|(_, since)| now - since >= SUBMENU_DELAY)
+        else {
+            continue;
+        };
+        level.hover = None;
+        let child_index = level.child.map(|(i, _)| i);
+        if level.horizontal || child_index == Some(index) {
+            continue;
+        }
+        let mut path = level.path.clone();
+        path.push(index);
+        let is_submenu = menus
+            .get(level.menu)
+            .ok()
+            .and_then(|menu| menu.get(&path))
+            .is_some_and(|item| item.is_submenu() && item.enabled);
+        if is_submenu {
+            open_submenu(
+                level_entity,
+                index,
+                false,
+                &mut state,
+                &mut levels,
+                &menus,
+                &dispatchers,
+                &mut focus_state,
+                &mut commands,
+            );
+        } else if child_index.is_some() {
+            close_levels(
+                &mut state,
+                Some(level_entity),
+                &mut levels,
+                &dispatchers,
+                &mut focus_state,
+                &mut commands,
+            );
+        }
+    }
+
+    if keys.get_just_pressed().next().is_none() {
+        return;
+    }
+    // a closed menu leaves the keys to the widget or window holding the input focus
+    if !state.is_open() && focus_state.input_focus.is_some() {
+        return;
+    }
+    if state.is_open() {
+        menu_keyboard_input(
+            &keys,
+            &mut state,
+            &mut levels,
+            &mut menus,
+            &dispatchers,
+            &mut focus_state,
+            &mut commands,
+        );
+    }
+
+    // the accelerators of the menu bars work while nothing else holds the keyboard, the ones of a
+    // popup menu only while it is open
+    let open_menu = state
+        .active
+        .first()
+        .and_then(|e| levels.get(*e).ok())
+        .map(|level| level.menu);
+    let accelerator = bar_query.iter().chain(open_menu).find_map(|menu_entity| {
+        let path = menus.get(menu_entity).ok()?.find_accelerator(&keys)?;
+        Some((menu_entity, path))
+    });
+    if let Some((menu_entity, path)) = accelerator {
+        trigger_item(menu_entity, &path, &mut menus, &dispatchers, &mut commands);
+        close_levels(
+            &mut state,
+            None,
+            &mut levels,
+            &dispatchers,
+            &mut focus_state,
+            &mut commands,
+        );
+    }
+}
+
+/// Arrow keys, `Enter` and `Escape` in the innermost open level.
+fn menu_keyboard_input(
+    keys: &ButtonInput<KeyCode>,
+    state: &mut UiMenuState,
+    levels: &mut Query<&mut UiMenuLevel>,
+    menus: &mut Query<&mut UiMenu>,
+    dispatchers: &Query<&UiMenuEventDispatcher>,
+    focus_state: &mut UiFocusState,
+    commands: &mut Commands,
+) {
+    let Some(&level_entity) = state.active.last() else {
+        return;
+    };
+    let Ok(level) = levels.get(level_entity) else {
+        return;
+    };
+    let Some(items) = menus
+        .get(level.menu)
+        .ok()
+        .and_then(|menu| menu.children(&level.path))
+    else {
+        return;
+    };
+    let (focused, horizontal, parent, has_child) =
+        (level.focused, level.horizontal, level.parent, level.child.is_some());
+    let bar = state
+        .active
+        .first()
+        .copied()
+        .filter(|e| levels.get(*e).is_ok_and(|level| level.horizontal));
+    let focused_is_submenu = focused
+        .and_then(|i| items.get(i))
+        .is_some_and(|item| item.is_submenu() && item.enabled);
+    let (previous_key, next_key) = if horizontal {
+        (KeyCode::ArrowLeft, KeyCode::ArrowRight)
+    } else {
+        (KeyCode::ArrowUp, KeyCode::ArrowDown)
+    };
+
+    let new_focus = if keys.just_pressed(KeyCode::Escape) {
+        close_levels(state, parent, levels, dispatchers, focus_state, commands);
+        return;
+    } else if keys.just_pressed(next_key) {
+        next_selectable(items, focused, true)
+    } else if keys.just_pressed(previous_key) {
+        next_selectable(items, focused, false)
+    } else if keys.just_pressed(KeyCode::Home) {
+        first_selectable(items)
+    } else if keys.just_pressed(KeyCode::End) {
+        items.iter().rposition(MenuItem::is_selectable)
+    } else if keys.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space])
+        || (keys.just_pressed(KeyCode::ArrowRight) && focused_is_submenu)
+        || (horizontal && keys.just_pressed(KeyCode::ArrowDown))
+    {
+        if let Some(index) = focused {
+            activate_item(
+                level_entity,
+                index,
+                state,
+                levels,
+                menus,
+                dispatchers,
+                focus_state,
+                commands,
+            );
+        }
+        return;
+    } else if keys.any_just_pressed([KeyCode::ArrowLeft, KeyCode::ArrowRight]) {
+        let forward = keys.just_pressed(KeyCode::ArrowRight);
+        if !forward && parent.is_some() && parent != bar {
+            close_levels(state, parent, levels, dispatchers, focus_state, commands);
+        } else if let Some(bar) = bar {
+            // move to the menu next to the open one in the bar
+            let Ok(bar_level) = levels.get(bar) else {
+                return;
+            };
+            let from = bar_level.child.map(|(i, _)| i).or(bar_level.focused);
+            let next = menus
+                .get(bar_level.menu)
+                .ok()
+                .and_then(|menu| next_selectable(&menu.items, from, forward));
+            if let Some(next) = next {
+                open_submenu(
+                    bar,
+                    next,
+                    true,
+                    state,
+                    levels,
+                    menus,
+                    dispatchers,
+                    focus_state,
+                    commands,
+                );
+            }
+        }
+        return;
+    } else {
+        return;
+    };
+
+    let Some(index) = new_focus else {
+        return;
+    };
+    if let Ok(mut level) = levels.get_mut(level_entity) {
+        level.focused = Some(index);
+        level.hover = None;
+    }
+    // the bar keeps a menu open while moving between its items
+    if horizontal && has_child {
+        open_submenu(
+            level_entity,
+            index,
+            true,
+            state,
+            levels,
+            menus,
+            dispatchers,
+            focus_state,
+            commands,
+        );
+    } else if has_child {
+        close_levels(
+            state,
+            Some(level_entity),
+            levels,
+            dispatchers,
+            focus_state,
+            commands,
+        );
+    }
+}
+
+fn spawn_menu_row(
+    commands: &mut Commands,
+    level: Entity,
+    index: usize,
+    item: &MenuItem,
+    horizontal: bool,
+    theme: &Theme,
+) -> Entity {
+    let row = UiMenuRow {
+        level,
+        index,
+        pressed: false,
+    };
+    if item.kind == MenuItemKind::Separator {
+        let size = if horizontal {
+            Node {
+                width: Val::Px(1.0),
+                margin: UiRect::horizontal(Val::Px(4.0)),
+                ..style!("h-full")
+            }
+        } else {
+            Node {
+                height: Val::Px(1.0),
+                margin: UiRect::vertical(Val::Px(4.0)),
+                ..style!("w-full")
+            }
+        };
+        return commands
+            .spawn((
+                row,
+                size,
+                BackgroundColor(theme.color("gray")),
+                UiAccessibilityHidden,
+                ChildOf(level),
+            ))
+            .id();
+    }
+
+    let (font, mut text_color) = theme.default_text_style();
+    if !item.enabled {
+        text_color = TextColor(theme.color("gray"));
+    }
+    let role = match item.kind {
+        MenuItemKind::Check(_) => Role::MenuItemCheckBox,
+        MenuItemKind::Radio(_) => Role::MenuItemRadio,
+        _ => Role::MenuItem,
+    };
+    let mut row_commands = commands.spawn((
+        row,
+        Node {
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(8.0),
+            padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+            ..style!("flex-row")
+        },
+        BackgroundColor(Color::NONE),
+        UiAccessible::new(role).with_label(&item.label),
+        ChildOf(level),
+    ));
+    row_commands.with_children(|c| {
+        if !horizontal {
+            let mark = match item.kind {
+                MenuItemKind::Check(true) => "✓",
+                MenuItemKind::Radio(true) => "●",
+                _ => "",
+            };
+            c.spawn((Text::new(mark), font.clone(), text_color, style!("w-16")));
+        }
+        match &item.icon {
+            Some(MenuIcon::Image(image)) => {
+                c.spawn((ImageNode::new(image.clone()), style!("w-16 h-16")));
+            }
+            Some(MenuIcon::Svg(svg)) => {
+                c.spawn((UiSvg::new(svg.clone()), style!("w-16 h-16")));
+            }
+            None if !horizontal => {
+                c.spawn(style!("w-16 h-16"));
+            }
+            None => {}
+        }
+        c.spawn((
+            Text::new(&item.label),
+            font.clone(),
+            text_color,
+            Node {
+                flex_grow: 1.0,
+                ..default()
+            },
+        ));
+        if horizontal {
+            return;
+        }
+        if let Some(accelerator) = &item.accelerator {
+            c.spawn((
+                Text::new(accelerator.to_string()),
+                font.clone(),
+                TextColor(theme.color("gray")),
+                Node {
+                    margin: UiRect::left(Val::Px(16.0)),
+                    ..default()
+                },
+            ));
+        }
+        if item.is_submenu() {
+            c.spawn((Text::new("▸"), font.clone(), text_color));
+        }
+    });
+    row_commands.id()
+}
+
+pub fn init_menu_bar(
+    query: Query<Entity, Added<UiMenuBar>>,
+    mut commands: Commands,
+) {
+    for entity in &query {
+        let mut level = UiMenuLevel::new(entity, vec![], None);
+        level.horizontal = true;
+        commands.entity(entity).insert((
+            level,
+            UiAccessible::new(Role::MenuBar),
+        ));
+    }
+}
+
+/// Build the rows of the levels from the model and highlight the focused row.
+pub fn update_menu_levels(
+    mut level_query: Query<(Entity, &mut UiMenuLevel)>,
+    menu_query: Query<Ref<UiMenu>>,
+    mut row_query: Query<&mut BackgroundColor, With<UiMenuRow>>,
+    mut state: ResMut<UiMenuState>,
+    theme: Res<Theme>,
+    mut commands: Commands,
+) {
+    for (entity, mut level) in &mut level_query {
+        let Ok(menu) = menu_query.get(level.menu) else {
+            if !level.horizontal {
+                commands.entity(entity).despawn();
+            }
+            continue;
+        };
+        if level.is_added() || menu.is_changed() {
+            let Some(items) = menu.children(&level.path) else {
+                // the submenu was removed from the model
+                state.active.retain(|e| *e != entity);
+                commands.entity(entity).despawn();
+                continue;
+            };
+            for row in level.rows.drain(..) {
+                commands.entity(row).despawn();
+            }
+            let horizontal = level.horizontal;
+            level.rows = items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    spawn_menu_row(&mut commands, entity, index, item, horizontal, &theme)
+                })
+                .collect();
+            if level.focused.is_some_and(|i| i >= items.len()) {
+                level.focused = None;
+            }
+        }
+        if !level.is_changed() && !theme.is_changed() {
+            continue;
+        }
+        let highlighted = level.child.map(|(i, _)| i).or(level.focused);
+        for (index, row) in level.rows.iter().enumerate() {
+            let Ok(mut background) = row_query.get_mut(*row) else {
+                continue;
+            };
+            let color = if highlighted == Some(index) {
+                theme.color("panel-popup:hover")
+            } else {
+                Color::NONE
+            };
+            if background.0 != color {
+                background.0 = color;
+            }
+        }
+    }
+}
+
+/// Move the popup menus next to their anchors once their size is known.
+pub fn place_menu_system(
+    mut level_query: Query<(
+        Entity,
+        &mut UiMenuLevel,
+        Ref<ComputedNode>,
+        &ComputedUiTargetCamera,
+        &mut Node,
+        &mut Visibility,
+    )>,
+    anchor_query: Query<(&ComputedNode, &UiGlobalTransform, &ComputedUiTargetCamera), Without<UiMenuLevel>>,
+    camera_query: Query<&Camera>,
+    mut commands: Commands,
+) {
+    for (entity, mut level, computed_node, target_camera, mut node, mut visibility) in
+        &mut level_query
+    {
+        let Some(placement) = level.placement else {
+            continue;
+        };
+        if level.placed && !computed_node.is_changed() {
+            continue;
+        }
+        let (anchor, camera, beside) = match placement {
+            MenuPlacement::Point(point) => (
+                Rect::from_corners(point, point),
+                target_camera.get(),
+                false,
+            ),
+            MenuPlacement::Below(anchor) | MenuPlacement::Beside(anchor) => {
+                let Ok((anchor_node, anchor_transform, anchor_camera)) =
+                    anchor_query.get(anchor)
+                else {
+                    continue;
+                };
+                (
+                    get_node_rect(anchor_transform, anchor_node),
+                    anchor_camera.get(),
+                    matches!(placement, MenuPlacement::Beside(_)),
+                )
+            }
+        };
+        let Some(camera_entity) = camera else {
+            continue;
+        };
+        if target_camera.get() != Some(camera_entity) {
+            // placed after the popup is moved to the camera of the anchor
+            commands.entity(entity).insert(UiTargetCamera(camera_entity));
+            continue;
+        }
+        let Some(viewport) = camera_query
+            .get(camera_entity)
+            .ok()
+            .and_then(Camera::logical_viewport_size)
+        else {
+            continue;
+        };
+        let size = computed_node.size() * computed_node.inverse_scale_factor();
+        if size == Vec2::ZERO {
+            continue;
+        }
+        let position = place_menu(anchor, size, viewport, beside);
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+        if !level.placed {
+            level.placed = true;
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+pub struct UiMenuPlugin;
+impl Plugin for UiMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiMenuState>()
+            .register_type::<UiMenuBar>()
+            .add_systems(
+                PreUpdate,
+                menu_input_system.in_set(UiFrameworkSystems::WidgetInputSystems),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    (init_menu_bar, update_menu_levels)
+                        .chain()
+                        .in_set(UiFrameworkSystems::UpdateWidgets),
+                    place_menu_system.after(UiSystem::Layout),
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_model() {
+        let mut menu = UiMenu::new([
+            MenuItem::action(1, "Open").with_accelerator("Ctrl+O"),
+            MenuItem::separator(),
+            MenuItem::submenu(
+                2,
+                "View",
+                [
+                    MenuItem::check(3, "Sidebar", false),
+                    MenuItem::radio(4, "Small", true),
+                    MenuItem::radio(5, "Large", false),
+                ],
+            ),
+        ]);
+        assert_eq!(menu.get(&[2, 1]).map(|item| item.id), Some(4));
+        assert_eq!(menu.activate(&[2, 0]).and_then(MenuItem::checked), Some(true));
+        menu.activate(&[2, 2]);
+        let kinds: Vec<_> = menu.children(&[2]).unwrap().iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                MenuItemKind::Check(true),
+                MenuItemKind::Radio(false),
+                MenuItemKind::Radio(true),
+            ]
+        );
+        assert_eq!(next_selectable(&menu.items, Some(0), true), Some(2));
+        assert_eq!(next_selectable(&menu.items, Some(0), false), Some(2));
+
+        let accelerator = Accelerator::parse("ctrl+shift+s").unwrap();
+        assert_eq!(accelerator.to_string(), "Ctrl+Shift+S");
+        assert_eq!(Accelerator::parse("Ctrl++").map(|a| a.key), Some(KeyCode::Equal));
+        assert_eq!(Accelerator::parse("Ctrl+Foo"), None);
+    }
+
+    #[test]
+    fn test_menu_bar_accelerator() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<UiMenuState>()
+            .init_resource::<UiFocusState>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .add_systems(Update, menu_input_system);
+        let bar = app
+            .world_mut()
+            .spawn((
+                UiMenuBar,
+                UiMenu::new([MenuItem::check(1, "Sidebar", false).with_accelerator("F9")]),
+            ))
+            .id();
+        let window = app.world_mut().spawn(Node::default()).id();
+
+        let press = |app: &mut App, key: KeyCode| {
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.release_all();
+            keys.clear();
+            keys.press(key);
+            app.update();
+            app.world()
+                .get::<UiMenu>(bar)
+                .unwrap()
+                .get(&[0])
+                .and_then(MenuItem::checked)
+        };
+
+        assert_eq!(press(&mut app, KeyCode::F9), Some(true));
+        // a text field or client window holding the keyboard gets the keys instead
+        app.world_mut().resource_mut::<UiFocusState>().input_focus = Some(window);
+        assert_eq!(press(&mut app, KeyCode::F9), Some(true));
+        app.world_mut().resource_mut::<UiFocusState>().input_focus = None;
+        assert_eq!(press(&mut app, KeyCode::F9), Some(false));
+    }
+
+    #[test]
+    fn test_place_menu() {
+        let viewport = Vec2::new(800.0, 600.0);
+        let size = Vec2::new(200.0, 300.0);
+        let anchor = Rect::new(100.0, 10.0, 160.0, 40.0);
+        assert_eq!(place_menu(anchor, size, viewport, false), Vec2::new(100.0, 40.0));
+        // flipped above the anchor near the bottom edge
+        let anchor = Rect::new(100.0, 500.0, 160.0, 530.0);
+        assert_eq!(place_menu(anchor, size, viewport, false), Vec2::new(100.0, 200.0));
+        // flipped to the left of the anchor near the right edge
+        let anchor = Rect::new(650.0, 400.0, 750.0, 430.0);
+        assert_eq!(place_menu(anchor, size, viewport, true), Vec2::new(450.0, 300.0));
+    }
+}
@@ -3,8 +3,10 @@ pub mod button;
 pub mod canvas;
 pub mod checkbox;
 pub mod combobox;
+pub mod dnd;
 pub mod drag;
 pub mod inputbox;
+pub mod menu;
 pub mod popup;
 pub mod rightclick_popup;
 pub mod scroll;
@@ -15,6 +15,23 @@ pub struct UiScroll {
     pub create_viewport: bool,
 }
 
+impl UiScrollState {
+    /// Scroll the content to `offset` without the mouse wheel.
+    pub fn scroll_to(&mut self, content_style: &mut Node, offset: Vec2) {
+        let offset = offset.max(Vec2::ZERO);
+        let content_size = *self.size() / self.uv().size();
+        self.set_offset(offset);
+        if content_size.is_finite() {
+            self.set_uv(Rect::from_corners(
+                offset / content_size,
+                (offset + *self.size()) / content_size,
+            ));
+        }
+        content_style.left = Val::Px(-offset.x);
+        content_style.top = Val::Px(-offset.y);
+    }
+}
+
 dway_widget! {
 UiScroll=>
 @use_state(pub uv: Rect)
@@ -1,3 +1,6 @@
+// shared by several test targets, each uses a part of it
+#![allow(dead_code)]
+
 use std::{
     collections::HashMap,
     path::{absolute, PathBuf},
@@ -7,13 +10,18 @@ use std::{
 
 use bevy::{
     app::{AppExit, ScheduleRunnerPlugin}, camera::{ImageRenderTarget, RenderTarget}, core_pipeline::core_2d::graph::{Core2d, Node2d}, diagnostic::FrameCount, ecs::system::SystemId, math::FloatOrd, prelude::*, render::{
-        Render, RenderApp, RenderSet, extract_component::{ExtractComponent, ExtractComponentPlugin}, render_asset::RenderAssets, render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel}, render_resource::{
+        Render, RenderApp, RenderPlugin, RenderSet, settings::{Backends, RenderCreation, WgpuSettings}, extract_component::{ExtractComponent, ExtractComponentPlugin}, render_asset::RenderAssets, render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel}, render_resource::{
             Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
             MapMode, TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo,
             TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
         }, renderer::{RenderContext, RenderDevice, RenderQueue}, texture::GpuImage
-    }, window::{PresentMode, WindowResolution}, winit::WinitPlugin
+    }, input::{
+        keyboard::{Key, KeyboardInput, NativeKey, NativeKeyCode},
+        mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel},
+        ButtonState,
+    }, window::{PresentMode, WindowRef, WindowResolution}, winit::WinitPlugin
 };
+use dway_ui_framework::{animation::AnimationSettings, render::layer_manager::SetWindowTarget};
 use crossbeam_channel::{Receiver, Sender};
 use image::RgbaImage;
 
@@ -178,17 +186,93 @@ impl render_graph::Node for CopyImageNode {
     }
 }
 
+/// Frames to wait for the assets and the layout before running the input script.
+pub const WARMUP_FRAMES: u32 = 64;
+/// Frames after which the test suite fails if some unit tests are still pending.
+pub const TIMEOUT_FRAMES: u32 = 96;
+/// Set this environment variable to overwrite the golden images with the screenshots.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "DWAY_UPDATE_SNAPSHOTS";
+
 pub struct UnitTestParams {
     pub camera: Entity,
     pub window: Entity,
 }
 
+/// One step of the input script of a [`UnitTest`], a step is run each frame.
+#[derive(Clone, Debug)]
+pub enum InputAction {
+    /// Move the cursor to a position in the screenshot
+    MoveTo(Vec2),
+    Press(MouseButton),
+    Release(MouseButton),
+    Scroll(Vec2),
+    KeyPress(KeyCode),
+    KeyRelease(KeyCode),
+    /// Type some text into the widget with the keyboard focus
+    Type(String),
+    /// Do nothing for some frames
+    Wait(u32),
+}
+
+impl InputAction {
+    pub fn click(position: Vec2) -> Vec<Self> {
+        vec![
+            Self::MoveTo(position),
+            Self::Press(MouseButton::Left),
+            Self::Release(MouseButton::Left),
+        ]
+    }
+
+    pub fn key(key_code: KeyCode) -> Vec<Self> {
+        vec![Self::KeyPress(key_code), Self::KeyRelease(key_code)]
+    }
+
+    pub fn frames(&self) -> u32 {
+        match self {
+            Self::Wait(frames) => *frames,
+            _ => 1,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct UnitTest {
     pub name: String,
     pub image_path: PathBuf,
     pub image_size: Vec2,
     pub setup: SystemId<In<UnitTestParams>>,
+    /// The input sent to the window before taking the screenshot
+    pub script: Vec<InputAction>,
+}
+
+impl UnitTest {
+    pub fn new(
+        name: &str,
+        image_path: impl Into<PathBuf>,
+        image_size: Vec2,
+        setup: SystemId<In<UnitTestParams>>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            image_path: image_path.into(),
+            image_size,
+            setup,
+            script: vec![],
+        }
+    }
+
+    pub fn with_script(mut self, script: impl IntoIterator<Item = InputAction>) -> Self {
+        self.script.extend(script);
+        self
+    }
+}
+
+#[derive(Component)]
+struct InputScriptRunner {
+    window: Entity,
+    image: Handle<Image>,
+    step: usize,
+    wait: u32,
 }
 
 fn start_unit_test(
@@ -240,24 +324,40 @@ fn start_unit_test(
                 ..Default::default()
             })
             .id();
+        let image_target = RenderTarget::Image(ImageRenderTarget {
+            handle: image_handle.clone(),
+            scale_factor: FloatOrd(1.0),
+        });
         let camera_entity = commands
             .spawn((
                 Camera2d::default(),
                 Camera {
-                    target: RenderTarget::Image(ImageRenderTarget {
-                        handle: image_handle.clone(),
-                        scale_factor: FloatOrd(1.0),
-                    }),
+                    target: image_target.clone(),
                     clear_color: ClearColorConfig::Custom(Color::WHITE),
                     ..Default::default()
                 },
+                // bevy only handles the input of cameras rendering to a window
+                SetWindowTarget::new(
+                    RenderTarget::Window(WindowRef::Entity(window_entity)),
+                    image_target,
+                ),
             ))
             .id();
-        commands.entity(entity).insert(ImageCopier::new(
-            image_handle,
-            unit_test.image_size,
-            &render_device,
-        ));
+        if unit_test.script.is_empty() {
+            commands.entity(entity).insert(ImageCopier::new(
+                image_handle,
+                unit_test.image_size,
+                &render_device,
+            ));
+        } else {
+            test_suit.timeout += unit_test.script.iter().map(InputAction::frames).sum::<u32>();
+            commands.entity(entity).insert(InputScriptRunner {
+                window: window_entity,
+                image: image_handle,
+                step: 0,
+                wait: 0,
+            });
+        }
 
         let params = UnitTestParams {
             camera: camera_entity,
@@ -267,6 +367,135 @@ fn start_unit_test(
     }
 }
 
+fn logical_key(key_code: KeyCode) -> Key {
+    match key_code {
+        KeyCode::Enter | KeyCode::NumpadEnter => Key::Enter,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Space => Key::Space,
+        KeyCode::ArrowUp => Key::ArrowUp,
+        KeyCode::ArrowDown => Key::ArrowDown,
+        KeyCode::ArrowLeft => Key::ArrowLeft,
+        KeyCode::ArrowRight => Key::ArrowRight,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        _ => Key::Unidentified(NativeKey::Unidentified),
+    }
+}
+
+fn key_event(key_code: KeyCode, state: ButtonState, window: Entity) -> KeyboardInput {
+    let logical_key = logical_key(key_code);
+    let text = (state == ButtonState::Pressed && logical_key == Key::Space).then(|| " ".into());
+    KeyboardInput {
+        key_code,
+        logical_key,
+        state,
+        text,
+        repeat: false,
+        window,
+    }
+}
+
+fn run_input_script(
+    mut runner_query: Query<(Entity, &UnitTest, &mut InputScriptRunner)>,
+    mut window_query: Query<&mut Window>,
+    mut cursor_moved: MessageWriter<CursorMoved>,
+    mut mouse_button: MessageWriter<MouseButtonInput>,
+    mut mouse_wheel: MessageWriter<MouseWheel>,
+    mut keyboard: MessageWriter<KeyboardInput>,
+    mut commands: Commands,
+    frame: Res<FrameCount>,
+    render_device: Res<RenderDevice>,
+) {
+    if frame.0 < WARMUP_FRAMES {
+        return;
+    }
+    for (entity, unit_test, mut runner) in &mut runner_query {
+        if runner.wait > 0 {
+            runner.wait -= 1;
+            continue;
+        }
+        let window = runner.window;
+        let Some(action) = unit_test.script.get(runner.step) else {
+            // the script is finished, take the screenshot
+            commands
+                .entity(entity)
+                .remove::<InputScriptRunner>()
+                .insert(ImageCopier::new(
+                    runner.image.clone(),
+                    unit_test.image_size,
+                    &render_device,
+                ));
+            continue;
+        };
+        runner.step += 1;
+        match action {
+            InputAction::MoveTo(position) => {
+                if let Ok(mut window) = window_query.get_mut(window) {
+                    window.set_cursor_position(Some(*position));
+                }
+                cursor_moved.write(CursorMoved {
+                    window,
+                    position: *position,
+                    delta: None,
+                });
+            }
+            InputAction::Press(button) | InputAction::Release(button) => {
+                let state = if matches!(action, InputAction::Press(_)) {
+                    ButtonState::Pressed
+                } else {
+                    ButtonState::Released
+                };
+                mouse_button.write(MouseButtonInput {
+                    button: *button,
+                    state,
+                    window,
+                });
+            }
+            InputAction::Scroll(delta) => {
+                mouse_wheel.write(MouseWheel {
+                    unit: MouseScrollUnit::Pixel,
+                    x: delta.x,
+                    y: delta.y,
+                    window,
+                });
+            }
+            InputAction::KeyPress(key_code) => {
+                keyboard.write(key_event(*key_code, ButtonState::Pressed, window));
+            }
+            InputAction::KeyRelease(key_code) => {
+                keyboard.write(key_event(*key_code, ButtonState::Released, window));
+            }
+            InputAction::Type(text) => {
+                for c in text.chars() {
+                    let logical_key = if c == ' ' {
+                        Key::Space
+                    } else {
+                        Key::Character(c.to_string().into())
+                    };
+                    for state in [ButtonState::Pressed, ButtonState::Released] {
+                        keyboard.write(KeyboardInput {
+                            key_code: KeyCode::Unidentified(NativeKeyCode::Unidentified),
+                            logical_key: logical_key.clone(),
+                            state,
+                            text: (state == ButtonState::Pressed).then(|| c.to_string().into()),
+                            repeat: false,
+                            window,
+                        });
+                    }
+                }
+            }
+            InputAction::Wait(frames) => {
+                runner.wait = frames.saturating_sub(1);
+            }
+        }
+    }
+}
+
 fn receive_image_from_buffer(
     copier_qeruy: Query<&mut ExtractedImageCopier>,
     render_device: Res<RenderDevice>,
@@ -293,7 +522,7 @@ fn receive_image_from_buffer(
             vec,
         )
         .unwrap();
-        let result = if frame.0 < 64 || output_image.get_pixel(16, 16).0 != [255, 255, 255, 255] {
+        let result = if frame.0 < WARMUP_FRAMES || output_image.get_pixel(16, 16).0 != [255, 255, 255, 255] {
             None
         } else {
             Some(output_image)
@@ -325,6 +554,16 @@ fn wait_render(
 
         let dest = absolute(&unit_test.image_path).unwrap();
 
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            output_image.save(&dest).unwrap();
+            info!("unit test ({}): update {dest:?}", &unit_test.name);
+            test_suit
+                .unit_tests
+                .insert(unit_test.name.clone(), UnitTestState::Ok);
+            commands.entity(entity).despawn();
+            continue;
+        }
+
         let result = match image::open(&dest)
             .map(|image| image.into_rgba8())
             .map_err(|e| e.into())
@@ -342,10 +581,11 @@ fn wait_render(
             }
             Ok(None) => UnitTestState::Ok,
             Err(e) => {
-                output_image.save(&unit_test.image_path).unwrap();
+                let mut output_image_path = test_suit.tmp.to_owned();
+                output_image_path.push(format!("{}.png", &unit_test.name));
+                output_image.save(&output_image_path).unwrap();
                 UnitTestState::Err(anyhow::anyhow!(
-                    "failed to compare image: {e} \nexcept: {dest:?}\nscreenshot: {:?}",
-                    &unit_test.image_path
+                    "failed to compare image: {e} \nexcept: {dest:?}\nscreenshot: {output_image_path:?}"
                 ))
             }
         };
@@ -369,7 +609,7 @@ fn check_exit(
         .unit_tests
         .values()
         .all(|s| matches!(s, UnitTestState::Ok));
-    if finished || frame.0 > 96 {
+    if finished || frame.0 > test_suit.timeout {
         exit_event.write(if success {
             let _ = std::fs::remove_dir_all(&test_suit.tmp);
             AppExit::Success
@@ -400,7 +640,12 @@ impl Plugin for TestPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((ExtractComponentPlugin::<ImageCopier>::default(),))
             .insert_resource(ClearColor(Color::WHITE))
+            // screenshots are taken after the animations are finished
+            .insert_resource(AnimationSettings {
+                reduce_motion: true,
+            })
             .add_systems(Startup, start_unit_test)
+            .add_systems(First, run_input_script)
             .add_systems(Last, (wait_render, check_exit).chain());
 
         let render_app = app.sub_app_mut(RenderApp);
@@ -423,6 +668,16 @@ impl PluginGroup for TestPluginsSet {
     fn build(self) -> bevy::app::PluginGroupBuilder {
         let group = DefaultPlugins.build();
         group
+            // the software adapter renders the same pixels on every machine, the golden images
+            // are made with it
+            .set(RenderPlugin {
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    backends: Some(Backends::VULKAN),
+                    force_fallback_adapter: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
             .disable::<WinitPlugin>()
             .add(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f32(
                 1.0 / 60.0,
@@ -450,15 +705,22 @@ pub struct TestSuite {
     pub name: String,
     pub tmp: PathBuf,
     pub unit_tests: HashMap<String, UnitTestState>,
+    /// the frame after which the pending unit tests fail
+    pub timeout: u32,
 }
 
 impl TestSuite {
+    /// The screenshots and diff images of failed unit tests are kept under the `target`
+    /// directory, the golden images in the source tree are only written with
+    /// [`UPDATE_SNAPSHOTS_ENV`].
     pub fn new(name: &str) -> Self {
-        let tmp = tempdir::TempDir::new(name).unwrap();
+        let tmp = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+        std::fs::create_dir_all(&tmp).unwrap();
         Self {
             name: name.to_string(),
-            tmp: tmp.into_path(),
+            tmp,
             unit_tests: Default::default(),
+            timeout: TIMEOUT_FRAMES,
         }
     }
 }
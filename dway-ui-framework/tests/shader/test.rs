@@ -21,12 +21,12 @@ pub fn unit_test<M: shader::Material + Clone>(app: &mut App, name: &str, materia
         ));
     });
 
-    app.world_mut().spawn(UnitTest {
-        name: name.to_string(),
-        image_path: format!("tests/shader/{name}.png").into(),
-        image_size: Vec2::splat(384.0),
-        setup: systemid,
-    });
+    app.world_mut().spawn(UnitTest::new(
+        name,
+        format!("tests/shader/{name}.png"),
+        Vec2::splat(384.0),
+        systemid,
+    ));
 }
 
 pub fn shapes_unit_test(name: &str, effect: impl Effect + Clone) {
@@ -0,0 +1,153 @@
+//! Snapshot tests of the widgets, the golden images are in `tests/widgets/`.
+//!
+//! Run with `DWAY_UPDATE_SNAPSHOTS=1` to write the golden images after changing a widget, the
+//! screenshots and diff images of failed tests are written to `target/tmp/test_widgets/`.
+
+use bevy::prelude::*;
+use dway_ui_framework::{
+    prelude::*,
+    widgets::{inputbox::UiInputBox, text::UiTextBundle},
+};
+
+#[path = "../common.rs"]
+mod common;
+use common::*;
+
+const IMAGE_SIZE: Vec2 = Vec2::new(256.0, 128.0);
+const CENTER: Vec2 = Vec2::new(128.0, 64.0);
+
+/// Run one unit test in its own app, the mouse buttons and keys are shared by the whole app.
+pub fn widget_unit_test(
+    name: &str,
+    script: impl IntoIterator<Item = InputAction>,
+    spawn: impl Fn(&mut ChildSpawnerCommands, &Theme) + Send + Sync + 'static,
+) {
+    let mut app = App::new();
+    app.add_plugins(TestPluginsSet)
+        .insert_resource(TestSuite::new("test_widgets"));
+
+    let systemid = app.register_system(
+        move |params: In<UnitTestParams>, theme: Res<Theme>, mut commands: Commands| {
+            commands
+                .spawn((
+                    style!("full align-items:center justify-content:center"),
+                    UiTargetCamera(params.camera),
+                ))
+                .with_children(|parent| spawn(parent, &theme));
+        },
+    );
+    app.world_mut().spawn(
+        UnitTest::new(
+            name,
+            format!("tests/widgets/{name}.png"),
+            IMAGE_SIZE,
+            systemid,
+        )
+        .with_script(script),
+    );
+
+    let exit = app.run();
+    assert!(exit == AppExit::Success);
+}
+
+fn spawn_button(parent: &mut ChildSpawnerCommands, theme: &Theme) {
+    parent
+        .spawn((
+            UiButton::default(),
+            style!("w-96 h-32 align-items:center justify-content:center"),
+        ))
+        .with_children(|parent| {
+            parent.spawn(UiTextBundle::new("button", 16, theme));
+        });
+}
+
+fn spawn_checkbox(parent: &mut ChildSpawnerCommands, _theme: &Theme) {
+    parent.spawn((UiCheckBox::default(), Interaction::default(), style!("w-64 h-32")));
+}
+
+fn spawn_slider(parent: &mut ChildSpawnerCommands, _theme: &Theme) {
+    parent.spawn((UiSlider::default(), style!("w-128 h-32")));
+}
+
+fn spawn_inputbox(parent: &mut ChildSpawnerCommands, _theme: &Theme) {
+    parent.spawn((
+        UiInputBox {
+            placeholder: "input box...".into(),
+            ..Default::default()
+        },
+        style!("w-128 h-32"),
+    ));
+}
+
+#[test]
+pub fn test_widget_button() {
+    widget_unit_test("button", [], spawn_button);
+}
+
+#[test]
+pub fn test_widget_button_hovered() {
+    widget_unit_test(
+        "button_hovered",
+        [InputAction::MoveTo(CENTER), InputAction::Wait(4)],
+        spawn_button,
+    );
+}
+
+#[test]
+pub fn test_widget_button_pressed() {
+    widget_unit_test(
+        "button_pressed",
+        [
+            InputAction::MoveTo(CENTER),
+            InputAction::Press(MouseButton::Left),
+            InputAction::Wait(4),
+        ],
+        spawn_button,
+    );
+}
+
+#[test]
+pub fn test_widget_checkbox() {
+    widget_unit_test("checkbox", [], spawn_checkbox);
+}
+
+#[test]
+pub fn test_widget_checkbox_checked() {
+    widget_unit_test(
+        "checkbox_checked",
+        InputAction::click(CENTER)
+            .into_iter()
+            .chain([InputAction::MoveTo(Vec2::ZERO), InputAction::Wait(4)]),
+        spawn_checkbox,
+    );
+}
+
+#[test]
+pub fn test_widget_slider() {
+    widget_unit_test(
+        "slider",
+        InputAction::click(CENTER + Vec2::new(32.0, 0.0))
+            .into_iter()
+            .chain([InputAction::MoveTo(Vec2::ZERO), InputAction::Wait(4)]),
+        spawn_slider,
+    );
+}
+
+#[test]
+pub fn test_widget_inputbox() {
+    widget_unit_test("inputbox", [], spawn_inputbox);
+}
+
+#[test]
+pub fn test_widget_inputbox_typing() {
+    widget_unit_test(
+        "inputbox_typing",
+        InputAction::click(CENTER).into_iter().chain([
+            InputAction::Type("hello world".to_string()),
+            InputAction::Wait(1),
+        ])
+        .chain(InputAction::key(KeyCode::Backspace))
+        .chain([InputAction::Wait(4)]),
+        spawn_inputbox,
+    );
+}
@@ -18,6 +18,16 @@ impl Plugin for DWayAssetsPlugin {
         embedded_asset!(app, "assets", "icons/volume_off.svg");
         embedded_asset!(app, "assets", "icons/volume_on.svg");
         embedded_asset!(app, "assets", "icons/notifications.svg");
+        embedded_asset!(app, "assets", "icons/play.svg");
+        embedded_asset!(app, "assets", "icons/pause.svg");
+        embedded_asset!(app, "assets", "icons/skip_next.svg");
+        embedded_asset!(app, "assets", "icons/skip_previous.svg");
+        embedded_asset!(app, "assets", "icons/music_note.svg");
+        embedded_asset!(app, "assets", "icons/wifi.svg");
+        embedded_asset!(app, "assets", "icons/bluetooth.svg");
+        embedded_asset!(app, "assets", "icons/refresh.svg");
+        embedded_asset!(app, "assets", "icons/brightness.svg");
+        embedded_asset!(app, "assets", "icons/keyboard.svg");
 
         embedded_asset!(app, "assets", "cursors/cursor-default.png");
 
@@ -50,6 +60,16 @@ impl Plugin for DWayAssetsPlugin {
                 "volume_off",
                 "volume_on",
                 "notifications",
+                "play",
+                "pause",
+                "skip_next",
+                "skip_previous",
+                "music_note",
+                "wifi",
+                "bluetooth",
+                "refresh",
+                "brightness",
+                "keyboard",
             ],
         );
     }
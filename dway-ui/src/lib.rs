@@ -13,25 +13,42 @@ pub mod reexport {
 use bevy::{
     math::FloatOrd,
     camera::{ImageRenderTarget, RenderTarget},
+    ui::{ComputedUiTargetCamera, UiStack, UiSystems},
     window::WindowRef,
 };
 use bevy_svg::SvgPlugin;
 pub use bitflags::bitflags as __bitflags;
 use dway_client_core::{
+    compositor::update_scanout_candidates,
+    controller::appearance::AppearanceController,
     layout::{LayoutRect, LayoutStyle},
     screen::Screen,
     UiAttachData,
 };
 use dway_server::geometry::GlobalGeometry;
-use dway_tty::drm::{connectors::Connector, surface::DrmSurface};
-use dway_ui_framework::render::layer_manager::{LayerKind, LayerManager, RenderToLayer};
+use dway_tty::drm::{connectors::Connector, scanout::update_scanout, surface::DrmSurface};
+use dway_util::{
+    color::{ColorTransform, ImageDescription, OutputImageDescription},
+    output::OutputPower,
+    scanout::{ScanoutState, ScreenOverlay},
+};
+use dway_ui_framework::{
+    render::{
+        layer_manager::{LayerCamera, LayerKind, LayerManager, RenderToLayer},
+        output_color::OutputColorTransform,
+    },
+    theme::asset::{apply_theme_file_system, ColorScheme},
+};
 
 use crate::{
     panels::{dock::Dock, top_panel::Panel},
     prelude::*,
     widgets::{
         cursor::Cursor,
+        notifys::NotifyPopupList,
+        osd::Osd,
         screen::ScreenWindows,
+        window::WindowUI,
     },
 };
 
@@ -68,16 +85,30 @@ impl Plugin for DWayUiPlugin {
             widgets::cursor::CursorPlugin,
             widgets::windowtitle::WindowTitlePlugin,
             widgets::system_monitor::PanelSystemMonitorPlugin,
-            widgets::notifys::NotifyButtonPlugin,
             ScreenUIPlugin,
         ));
+        app.add_plugins((
+            widgets::notifys::NotifyButtonPlugin,
+            widgets::notifys::NotifyViewPlugin,
+            widgets::notifys::NotifyPopupListPlugin,
+            widgets::tray::TrayUIPlugin,
+            widgets::tray::TrayMenuPlugin,
+            widgets::windowmenu::WindowMenuPlugin,
+            widgets::player::MediaButtonPlugin,
+            widgets::osd::OsdPlugin,
+        ));
         app.add_plugins((
             popups::app_window_preview::AppWindowPreviewPopupPlugin,
             popups::launcher::LauncherUIPlugin,
             popups::volume_control::VolumeControlPlugin,
+            popups::vrr_control::VrrControlPlugin,
             popups::panel_settings::PanelSettingsPlugin,
             popups::workspace_window_preview::WorkspaceWindowPreviewPopupPlugin,
             popups::dock_launcher::DockLauncherUIPlugin,
+            popups::notify::NotifyCenterPlugin,
+            popups::player::MediaPlayerPopupPlugin,
+            popups::network::NetworkPopupPlugin,
+            popups::bluetooth::BluetoothPopupPlugin,
         ));
         app.add_plugins((
             panels::top_panel::PanelPlugin,
@@ -85,12 +116,41 @@ impl Plugin for DWayUiPlugin {
         ));
         app.add_observer(init_screen_ui);
         app.add_systems(Startup, setup);
+        app.add_systems(
+            PreUpdate,
+            sync_color_scheme.before(apply_theme_file_system),
+        );
+        app.add_systems(PreUpdate, update_screen_scale_factor);
+        app.add_systems(PostUpdate, update_screen_color_transform);
+        app.add_systems(
+            PostUpdate,
+            update_screen_overlay
+                .after(UiSystems::Stack)
+                .before(update_scanout_candidates),
+        );
+        app.add_systems(Last, update_screen_camera_active.after(update_scanout));
     }
 }
 
 fn setup(_commands: Commands) {
 }
 
+fn sync_color_scheme(
+    appearance_controller: Option<Res<AppearanceController>>,
+    mut color_scheme: ResMut<ColorScheme>,
+) {
+    let Some(appearance_controller) = appearance_controller else {
+        return;
+    };
+    if appearance_controller.is_changed() {
+        color_scheme.set_if_neq(if appearance_controller.color_scheme.is_dark() {
+            ColorScheme::Dark
+        } else {
+            ColorScheme::Light
+        });
+    }
+}
+
 #[derive(Component)]
 #[require(Name = Name::from("ScreenUI"))]
 pub struct ScreenUI {
@@ -126,6 +186,138 @@ ScreenUI=>
 </Node>
 }
 
+/// Keep the scale of the cameras rendering to outputs in sync with the output configuration.
+fn update_screen_scale_factor(
+    screen_query: Query<(&DrmSurface, &Window), (With<Screen>, Changed<Window>)>,
+    mut camera_query: Query<&mut Camera>,
+) {
+    for (drm_surface, window) in &screen_query {
+        let image = drm_surface.image();
+        for mut camera in &mut camera_query {
+            if let RenderTarget::Image(target) = &mut camera.target {
+                if target.handle == image && target.scale_factor.0 != window.scale_factor() {
+                    target.scale_factor = FloatOrd(window.scale_factor());
+                }
+            }
+        }
+    }
+}
+
+/// Convert the composited image to the image description of the output, on the camera which
+/// renders to the output now that layers may move the target between cameras.
+fn update_screen_color_transform(
+    screen_query: Query<(&DrmSurface, &OutputImageDescription), With<Screen>>,
+    camera_query: Query<(Entity, &Camera, Option<&OutputColorTransform>)>,
+    mut commands: Commands,
+) {
+    for (entity, camera, current) in &camera_query {
+        let description = match &camera.target {
+            RenderTarget::Image(target) => screen_query
+                .iter()
+                .find(|(drm_surface, _)| drm_surface.image() == target.handle)
+                .map(|(_, description)| description),
+            _ => None,
+        };
+        let transform = description
+            .map(|description| ColorTransform::new(&ImageDescription::compositing(), description))
+            .filter(|transform| !transform.is_identity());
+        match transform {
+            Some(transform) if current.map(|c| &c.0) != Some(&transform) => {
+                commands
+                    .entity(entity)
+                    .insert(OutputColorTransform(transform));
+            }
+            None if current.is_some() => {
+                commands.entity(entity).remove::<OutputColorTransform>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Mark the screens whose UI is drawn above the topmost window, direct scanout would hide it.
+fn update_screen_overlay(
+    screen_query: Query<(Entity, &DrmSurface, Has<ScreenOverlay>), With<Screen>>,
+    camera_query: Query<(Entity, &Camera)>,
+    layer_camera_query: Query<&LayerCamera>,
+    node_query: Query<(&ComputedNode, &InheritedVisibility, &ComputedUiTargetCamera)>,
+    window_ui_query: Query<(), With<WindowUI>>,
+    parent_query: Query<&ChildOf>,
+    ui_stack: Res<UiStack>,
+    mut commands: Commands,
+) {
+    for (screen_entity, drm_surface, marked) in &screen_query {
+        let image = drm_surface.image();
+        let Some((camera_entity, _)) = camera_query.iter().find(|(_, camera)| {
+            matches!(&camera.target, RenderTarget::Image(target) if target.handle == image)
+        }) else {
+            continue;
+        };
+        // the first visible node from the top decides, either a window or the UI above it
+        let overlay = ui_stack
+            .uinodes
+            .iter()
+            .rev()
+            .find_map(|&node| {
+                let (computed_node, visibility, node_camera) = node_query.get(node).ok()?;
+                let node_camera = node_camera.get()?;
+                let node_camera = layer_camera_query
+                    .get(node_camera)
+                    .map(|layer| layer.layer_manager())
+                    .unwrap_or(node_camera);
+                if node_camera != camera_entity || !visibility.get() || computed_node.is_empty() {
+                    return None;
+                }
+                let in_window = std::iter::once(node)
+                    .chain(parent_query.iter_ancestors(node))
+                    .any(|entity| window_ui_query.contains(entity));
+                Some(!in_window)
+            })
+            .unwrap_or(false);
+        if overlay && !marked {
+            commands.entity(screen_entity).insert(ScreenOverlay);
+        } else if !overlay && marked {
+            commands.entity(screen_entity).remove::<ScreenOverlay>();
+        }
+    }
+}
+
+/// Skip rendering outputs which are powered off or whose primary plane shows a client buffer.
+///
+/// Screens with UI above the windows are composited, even before the plane is released.
+fn update_screen_camera_active(
+    screen_query: Query<
+        (
+            &DrmSurface,
+            Option<&ScanoutState>,
+            Option<&OutputPower>,
+            Has<ScreenOverlay>,
+        ),
+        (
+            With<Screen>,
+            Or<(
+                Changed<ScanoutState>,
+                Changed<OutputPower>,
+                Added<ScreenOverlay>,
+            )>,
+        ),
+    >,
+    mut camera_query: Query<&mut Camera>,
+) {
+    for (drm_surface, scanout_state, power, overlay) in &screen_query {
+        let image = drm_surface.image();
+        let active = (overlay || scanout_state.is_none_or(|s| s.primary.is_none()))
+            && power.is_none_or(|p| p.is_on());
+        for mut camera in &mut camera_query {
+            if let RenderTarget::Image(target) = &camera.target {
+                if target.handle == image && camera.is_active != active {
+                    camera.is_active = active;
+                }
+            }
+        }
+    }
+}
+
 fn init_screen_ui(
     trigger: Trigger<OnAdd, Screen>,
     screen_query: Query<(&DrmSurface, &Connector)>,
@@ -136,11 +328,15 @@ fn init_screen_ui(
     let entity = trigger.target();
     let (name, target) = if let Ok((drm_surface, connector)) = screen_query.get(entity) {
         let image_handle = drm_surface.image();
+        let scale_factor = window_query
+            .get(entity)
+            .map(|window| window.scale_factor())
+            .unwrap_or(1.0);
         (
             connector.name().to_string(),
             RenderTarget::Image(ImageRenderTarget {
                 handle: image_handle,
-                scale_factor: FloatOrd(1.0),
+                scale_factor: FloatOrd(scale_factor),
             }),
         )
     } else if let Ok(window) = window_query.get(entity) {
@@ -210,6 +406,28 @@ fn init_screen_ui(
         ))
         .connect_to::<UiAttachData>(entity);
 
+    commands
+        .spawn((
+            Name::new("notify_popups"),
+            UiTargetCamera(camera),
+            NotifyPopupList,
+            style!("absolute top-42 right-4"),
+            RenderToLayer::new(camera, LayerKind::Blur),
+            zindex::POPUP,
+        ))
+        .connect_to::<UiAttachData>(entity);
+
+    commands
+        .spawn((
+            Name::new("osd"),
+            UiTargetCamera(camera),
+            Osd,
+            style!("absolute bottom-96 justify-self:center"),
+            RenderToLayer::new(camera, LayerKind::Blur),
+            zindex::POPUP,
+        ))
+        .connect_to::<UiAttachData>(entity);
+
     commands
         .spawn((
             Name::new("cursor"),
@@ -1,9 +1,9 @@
 use dway_ui_framework::theme::ThemeComponent;
 
 use crate::{
-    panels::PanelButtonBundle, popups::{launcher, panel_settings, volume_control}, prelude::*, widgets::{
-        clock::Clock, notifys::NotifyButton, system_monitor::PanelSystemMonitor,
-        windowtitle::WindowTitle, workspacelist::WorkspaceListUI,
+    panels::PanelButtonBundle, popups::{bluetooth, launcher, network, panel_settings, volume_control}, prelude::*, widgets::{
+        clock::Clock, notifys::NotifyButton, player::MediaButton, system_monitor::PanelSystemMonitor,
+        tray::TrayUI, windowtitle::WindowTitle, workspacelist::WorkspaceListUI,
     }
 };
 
@@ -38,9 +38,19 @@ Panel=>
         <WindowTitle/>
     </Node>
     <Node @style="absolute flex-row right-4 align-items:center" @id="right">
+        <MediaButton @id="media" @style="m-4"/>
+        <TrayUI @id="tray"/>
         <Clock/>
         <PanelSystemMonitor @id="system_monitor" @style="h-full"/>
         <NotifyButton @id="notify"/>
+        <(PanelButtonBundle::new(&theme,&mut assets!(RoundedUiRectMaterial)))
+            @on_event((callbacks.system(network::open_popup))->self)  @style="m-4">
+            <(UiSvg::new(theme.icon("wifi", &asset_server))) @style="w-24 h-24" @id="network"/>
+        </PanelButtonBundle>
+        <(PanelButtonBundle::new(&theme,&mut assets!(RoundedUiRectMaterial)))
+            @on_event((callbacks.system(bluetooth::open_popup))->self)  @style="m-4">
+            <(UiSvg::new(theme.icon("bluetooth", &asset_server))) @style="w-24 h-24" @id="bluetooth"/>
+        </PanelButtonBundle>
         <(PanelButtonBundle::new(&theme,&mut assets!(RoundedUiRectMaterial)))
             @on_event((callbacks.system(volume_control::open_popup))->self)  @style="m-4">
             <(UiSvg::new(theme.icon("volume_on", &asset_server))) @style="w-24 h-24" @id="volume"/>
@@ -0,0 +1,169 @@
+use dway_client_core::controller::bluetooth::{
+    BluetoothController, BluetoothDevice, BluetoothRequest,
+};
+use dway_ui_framework::widgets::{scroll::UiScroll, util::visibility};
+use widgets::text::UiTextBundle;
+
+use crate::{
+    panels::{PanelButtonBundle, PanelPopupBundle},
+    prelude::*,
+};
+
+fn device_status(device: &BluetoothDevice) -> String {
+    let status = if device.connected {
+        "connected"
+    } else if device.paired {
+        "paired"
+    } else {
+        "not paired"
+    };
+    match device.battery {
+        Some(battery) => format!("{status} · {battery}%"),
+        None => status.to_string(),
+    }
+}
+
+/// The bluetooth adapter switch and the known devices of BlueZ.
+#[derive(Component, Default)]
+pub struct BluetoothPopup;
+
+fn on_device(
+    event: UiEvent<UiButtonEvent>,
+    query: Query<&BluetoothPopupSubStateDevices>,
+    mut requests: MessageWriter<BluetoothRequest>,
+) {
+    let Ok(state) = query.get(event.receiver()) else {
+        return;
+    };
+    if event.kind == UiButtonEventKind::Released {
+        let device = state.device();
+        let path = device.path.clone();
+        requests.write(if device.connected {
+            BluetoothRequest::Disconnect { device: path }
+        } else if device.paired {
+            BluetoothRequest::Connect { device: path }
+        } else {
+            BluetoothRequest::Pair { device: path }
+        });
+    }
+}
+
+dway_widget! {
+BluetoothPopup=>
+@plugin{ app.register_callback(open_popup); }
+@add_callback{[UiEvent<UiButtonEvent>]on_device}
+@callback{[UiEvent<UiCheckBoxEvent>]
+    fn on_powered(
+        event: UiEvent<UiCheckBoxEvent>,
+        bluetooth_controller: Res<BluetoothController>,
+        mut requests: MessageWriter<BluetoothRequest>,
+    ) {
+        if let Some(adapter) = bluetooth_controller.adapter() {
+            requests.write(BluetoothRequest::SetPowered {
+                adapter: adapter.path.clone(),
+                powered: event.value,
+            });
+        }
+    }
+}
+@callback{[UiEvent<UiButtonEvent>]
+    fn on_discovery(
+        event: UiEvent<UiButtonEvent>,
+        bluetooth_controller: Res<BluetoothController>,
+        mut requests: MessageWriter<BluetoothRequest>,
+    ) {
+        if event.kind != UiButtonEventKind::Released {
+            return;
+        }
+        if let Some(adapter) = bluetooth_controller.adapter() {
+            let path = adapter.path.clone();
+            requests.write(if adapter.discovering {
+                BluetoothRequest::StopDiscovery { adapter: path }
+            } else {
+                BluetoothRequest::StartDiscovery { adapter: path }
+            });
+        }
+    }
+}
+@use_state(has_adapter: bool)
+@use_state(powered: bool)
+@use_state(discovering: bool)
+@use_state(adapter_name: String)
+@use_state(devices: Vec<BluetoothDevice>)
+@global(bluetooth_controller: BluetoothController -> {
+    let adapter = bluetooth_controller.adapter();
+    if *state.has_adapter() != adapter.is_some() {
+        state.set_has_adapter(adapter.is_some());
+    }
+    let powered = adapter.is_some_and(|a| a.powered);
+    if *state.powered() != powered {
+        state.set_powered(powered);
+    }
+    let discovering = adapter.is_some_and(|a| a.discovering);
+    if *state.discovering() != discovering {
+        state.set_discovering(discovering);
+    }
+    let adapter_name = adapter.map(|a| a.name.clone()).unwrap_or_default();
+    if state.adapter_name() != &adapter_name {
+        state.set_adapter_name(adapter_name);
+    }
+    if state.devices() != &bluetooth_controller.devices {
+        state.set_devices(bluetooth_controller.devices.clone());
+    }
+})
+@global(theme: Theme)
+@global(asset_server: AssetServer)
+@global(mut assets_rounded_ui_rect_material: Assets<RoundedUiRectMaterial>)
+<Node @style="flex-col p-4 min-w-320">
+    <Node @id="header" @style="p-4 flex-row align-items:center"
+        @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 16.0))
+    >
+        <(UiSvg::new(theme.icon("bluetooth", &asset_server))) @style="w-24 h-24 m-4"/>
+        <Node @style="flex-col flex_grow:1.0 m-4">
+            <(UiTextBundle::new("Bluetooth", 20, &theme))/>
+            <(UiTextBundle::new(state.adapter_name(), 14, &theme)) @id="adapter_name"/>
+        </Node>
+        <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+            @on_event(on_discovery) @style="w-32 h-32 m-4" @id="discovery_button"
+            Visibility=(visibility(*state.powered()))>
+            <(UiSvg::new(theme.icon("refresh", &asset_server))) @style="w-32 h-32"/>
+        </PanelButtonBundle>
+        <UiCheckBox @on_event(on_powered) @style="w-48 h-24 m-4" @id="powered"
+            Visibility=(visibility(*state.has_adapter()))
+            UiCheckBoxState=(UiCheckBoxState::new(*state.powered()))
+        />
+    </Node>
+    <UiScroll @style="max-h-400 m-4 w-full min-h-64" @id="device_scroll">
+        <Node @style="absolute flex-col w-full" @id="devices"
+            @for(device: BluetoothDevice in state.devices().iter().cloned() => {
+                state.set_device(device);
+            })>
+            <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+                @on_event(on_device) @style="flex-row align-items:center m-2 p-4"
+                @use_state(pub device: BluetoothDevice)>
+                <Node @style="flex-col flex_grow:1.0 m-2">
+                    <(UiTextBundle::new(&state.device().name, 16, &theme))/>
+                    <(UiTextBundle::new(&device_status(state.device()), 12, &theme))/>
+                </Node>
+                <Node @style="w-8 h-8 m-2"
+                    Visibility=(visibility(state.device().connected))
+                    @material(UiCircleMaterial=>circle_material(theme.color("blue")))
+                />
+            </PanelButtonBundle>
+        </Node>
+    </UiScroll>
+</Node>
+}
+
+pub fn open_popup(event: UiEvent<UiButtonEvent>, mut commands: Commands) {
+    if event.kind == UiButtonEventKind::Released {
+        commands
+            .spawn(PanelPopupBundle {
+                anchor_policy: AnchorPolicy::new(PopupAnlign::InnerEnd, PopupAnlign::None),
+                ..PanelPopupBundle::new(event.receiver(), style!("absolute top-42"))
+            })
+            .with_children(|c| {
+                c.spawn((BluetoothPopup, style!("h-auto w-auto")));
+            });
+    }
+}
@@ -1,7 +1,11 @@
 pub mod app_window_preview;
+pub mod bluetooth;
 pub mod launcher;
+pub mod network;
 pub mod notify;
 pub mod panel_settings;
+pub mod player;
 pub mod volume_control;
+pub mod vrr_control;
 pub mod workspace_window_preview;
 pub mod dock_launcher;
@@ -0,0 +1,193 @@
+use dway_client_core::controller::network::{
+    NetworkConnection, NetworkController, NetworkRequest, WifiAccessPoint,
+};
+use dway_ui_framework::widgets::{scroll::UiScroll, util::visibility};
+use widgets::text::UiTextBundle;
+
+use crate::{
+    panels::{PanelButtonBundle, PanelPopupBundle},
+    prelude::*,
+};
+
+/// Wi-Fi networks and vpn connections of NetworkManager.
+#[derive(Component, Default)]
+pub struct NetworkPopup;
+
+fn on_access_point(
+    event: UiEvent<UiButtonEvent>,
+    query: Query<&NetworkPopupSubStateAccessPoints>,
+    mut requests: MessageWriter<NetworkRequest>,
+) {
+    let Ok(state) = query.get(event.receiver()) else {
+        return;
+    };
+    if event.kind == UiButtonEventKind::Released {
+        requests.write(if *state.active() {
+            NetworkRequest::DisconnectDevice {
+                device: state.device().clone(),
+            }
+        } else {
+            NetworkRequest::ConnectWifi {
+                device: state.device().clone(),
+                access_point: state.access_point().path.clone(),
+                password: None,
+            }
+        });
+    }
+}
+
+fn on_vpn(
+    event: UiEvent<UiButtonEvent>,
+    query: Query<&NetworkPopupSubStateVpns>,
+    mut requests: MessageWriter<NetworkRequest>,
+) {
+    let Ok(state) = query.get(event.receiver()) else {
+        return;
+    };
+    if event.kind == UiButtonEventKind::Released {
+        requests.write(NetworkRequest::ToggleVpn {
+            connection: state.connection().path.clone(),
+        });
+    }
+}
+
+dway_widget! {
+NetworkPopup=>
+@plugin{ app.register_callback(open_popup); }
+@add_callback{[UiEvent<UiButtonEvent>]on_access_point}
+@add_callback{[UiEvent<UiButtonEvent>]on_vpn}
+@callback{[UiEvent<UiCheckBoxEvent>]
+    fn on_wireless_enabled(
+        event: UiEvent<UiCheckBoxEvent>,
+        mut requests: MessageWriter<NetworkRequest>,
+    ) {
+        requests.write(NetworkRequest::SetWirelessEnabled(event.value));
+    }
+}
+@callback{[UiEvent<UiButtonEvent>]
+    fn on_scan(
+        event: UiEvent<UiButtonEvent>,
+        network_controller: Res<NetworkController>,
+        mut requests: MessageWriter<NetworkRequest>,
+    ) {
+        if event.kind == UiButtonEventKind::Released {
+            if let Some(device) = network_controller.wifi_device() {
+                requests.write(NetworkRequest::RequestScan { device: device.path.clone() });
+            }
+        }
+    }
+}
+@use_state(wireless_enabled: bool)
+@use_state(primary: String)
+@use_state(device: String)
+@use_state(active_access_point: Option<String>)
+@use_state(access_points: Vec<WifiAccessPoint>)
+@use_state(vpns: Vec<(NetworkConnection, bool)>)
+@global(network_controller: NetworkController -> {
+    if *state.wireless_enabled() != network_controller.wireless_enabled {
+        state.set_wireless_enabled(network_controller.wireless_enabled);
+    }
+    let primary = network_controller.primary().map(|c| c.id.clone()).unwrap_or_default();
+    if state.primary() != &primary {
+        state.set_primary(primary);
+    }
+    let wifi = network_controller.wifi_device();
+    let device = wifi.map(|d| d.path.clone()).unwrap_or_default();
+    if state.device() != &device {
+        state.set_device(device);
+    }
+    let active_access_point = wifi.and_then(|d| d.active_access_point.clone());
+    if state.active_access_point() != &active_access_point {
+        state.set_active_access_point(active_access_point);
+    }
+    let access_points = wifi.map(|d| d.access_points.clone()).unwrap_or_default();
+    if state.access_points() != &access_points {
+        state.set_access_points(access_points);
+    }
+    let vpns: Vec<_> = network_controller.vpns()
+        .map(|c| (c.clone(), network_controller.active_of(&c.path).is_some()))
+        .collect();
+    if state.vpns() != &vpns {
+        state.set_vpns(vpns);
+    }
+})
+@global(theme: Theme)
+@global(asset_server: AssetServer)
+@global(mut assets_rounded_ui_rect_material: Assets<RoundedUiRectMaterial>)
+<Node @style="flex-col p-4 min-w-320">
+    <Node @id="header" @style="p-4 flex-row align-items:center"
+        @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 16.0))
+    >
+        <(UiSvg::new(theme.icon("wifi", &asset_server))) @style="w-24 h-24 m-4"/>
+        <Node @style="flex-col flex_grow:1.0 m-4">
+            <(UiTextBundle::new("Wi-Fi", 20, &theme))/>
+            <(UiTextBundle::new(state.primary(), 14, &theme)) @id="primary"/>
+        </Node>
+        <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+            @on_event(on_scan) @style="w-32 h-32 m-4" @id="scan_button">
+            <(UiSvg::new(theme.icon("refresh", &asset_server))) @style="w-32 h-32"/>
+        </PanelButtonBundle>
+        <UiCheckBox @on_event(on_wireless_enabled) @style="w-48 h-24 m-4" @id="wireless_enabled"
+            UiCheckBoxState=(UiCheckBoxState::new(*state.wireless_enabled()))
+        />
+    </Node>
+    <UiScroll @style="max-h-400 m-4 w-full min-h-64" @id="access_point_scroll">
+        <Node @style="absolute flex-col w-full" @id="access_points"
+            @for((access_point, device, active): (WifiAccessPoint, String, bool) in state.access_points().iter().cloned()
+                .map(|ap| {
+                    let active = Some(&ap.path) == state.active_access_point().as_ref();
+                    (ap, state.device().clone(), active)
+                }) => {
+                state.set_access_point(access_point);
+                state.set_device(device);
+                state.set_active(active);
+            })>
+            <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+                @on_event(on_access_point) @style="flex-row align-items:center m-2 p-4"
+                @use_state(pub access_point: WifiAccessPoint)
+                @use_state(pub device: String)
+                @use_state(pub active: bool)>
+                <(UiTextBundle::new(&state.access_point().ssid, 16, &theme)) @style="flex_grow:1.0 m-2"/>
+                <(UiSvg::new(theme.icon("lock", &asset_server))) @style="w-16 h-16 m-2"
+                    Visibility=(visibility(state.access_point().secured))/>
+                <(UiTextBundle::new(&format!("{}%", state.access_point().strength), 14, &theme)) @style="m-2"/>
+                <Node @style="w-8 h-8 m-2"
+                    Visibility=(visibility(*state.active()))
+                    @material(UiCircleMaterial=>circle_material(theme.color("blue")))
+                />
+            </PanelButtonBundle>
+        </Node>
+    </UiScroll>
+    <Node @style="flex-col m-4" @id="vpns"
+        Visibility=(visibility(!state.vpns().is_empty()))
+        @for((connection, active): (NetworkConnection, bool) in state.vpns().iter().cloned() => {
+            state.set_connection(connection);
+            state.set_active(active);
+        })>
+        <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+            @on_event(on_vpn) @style="flex-row align-items:center m-2 p-4"
+            @use_state(pub connection: NetworkConnection)
+            @use_state(pub active: bool)>
+            <(UiSvg::new(theme.icon("lock", &asset_server))) @style="w-16 h-16 m-2"/>
+            <(UiTextBundle::new(&state.connection().id, 16, &theme)) @style="flex_grow:1.0 m-2"/>
+            <Node @style="w-8 h-8 m-2"
+                Visibility=(visibility(*state.active()))
+                @material(UiCircleMaterial=>circle_material(theme.color("blue")))
+            />
+        </PanelButtonBundle>
+    </Node>
+</Node>
+}
+
+pub fn open_popup(event: UiEvent<UiButtonEvent>, mut commands: Commands) {
+    if event.kind == UiButtonEventKind::Released {
+        commands
+            .spawn(PanelPopupBundle {
+                anchor_policy: AnchorPolicy::new(PopupAnlign::InnerEnd, PopupAnlign::None),
+                ..PanelPopupBundle::new(event.receiver(), style!("absolute top-42"))
+            })
+            .with_children(|c| {
+                c.spawn((NetworkPopup, style!("h-auto w-auto")));
+            });
+    }
+}
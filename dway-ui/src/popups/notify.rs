@@ -0,0 +1,102 @@
+use std::time::SystemTime;
+
+use dway_client_core::controller::notify::{
+    NotifyController, NotifyHistory, NotifyRequest, NotifySettings,
+};
+use dway_ui_framework::widgets::scroll::UiScroll;
+use widgets::text::UiTextBundle;
+
+use crate::{
+    panels::{PanelButtonBundle, PanelPopupBundle},
+    prelude::*,
+    widgets::notifys::NotifyView,
+};
+
+/// The notification history center with the do-not-disturb switch.
+#[derive(Component, Default)]
+pub struct NotifyCenter;
+
+dway_widget! {
+NotifyCenter=>
+@callback{[UiEvent<UiCheckBoxEvent>]
+    fn on_do_not_disturb(
+        event: UiEvent<UiCheckBoxEvent>,
+        mut notify_sender: MessageWriter<NotifyRequest>,
+    ) {
+        notify_sender.write(NotifyRequest::SetDoNotDisturb(event.value));
+    }
+}
+@callback{[UiEvent<UiButtonEvent>]
+    fn on_clear(
+        event: UiEvent<UiButtonEvent>,
+        mut notify_sender: MessageWriter<NotifyRequest>,
+    ) {
+        if event.kind == UiButtonEventKind::Released {
+            notify_sender.write(NotifyRequest::ClearHistory);
+        }
+    }
+}
+@use_state(notifys: Vec<NotifyHistory>)
+@use_state(revisions: Vec<(u32, SystemTime, bool)>)
+@use_state(do_not_disturb: bool)
+@global(notify_controller: NotifyController -> {
+    let revisions: Vec<_> = notify_controller.values().rev().map(|n|n.revision()).collect();
+    if state.revisions() != &revisions {
+        state.set_revisions(revisions);
+        state.set_notifys(notify_controller.values().rev().cloned().collect());
+    }
+})
+@global(notify_settings: NotifySettings -> {
+    if *state.do_not_disturb() != notify_settings.do_not_disturb {
+        state.set_do_not_disturb(notify_settings.do_not_disturb);
+    }
+})
+@global(theme: Theme)
+@global(asset_server: AssetServer)
+@global(mut assets_rounded_ui_rect_material: Assets<RoundedUiRectMaterial>)
+<Node @style="flex-col p-4 min-w-400">
+    <Node @id="header" @style="p-4 flex-row align-items:center"
+        @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 16.0))
+    >
+        <(UiTextBundle::new("Notifications", 24, &theme)) @style="flex_grow:1.0 m-4"/>
+        <(UiTextBundle::new("Do not disturb", 16, &theme)) @style="m-4"/>
+        <UiCheckBox @on_event(on_do_not_disturb) @style="w-48 h-24 m-4" @id="do_not_disturb"
+            UiCheckBoxState=(UiCheckBoxState::new(*state.do_not_disturb()))
+        />
+        <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+            @on_event(on_clear->this_entity)
+            @style="w-32 h-32" @id="clear_button">
+            <(UiSvg::new(theme.icon("close", &asset_server))) @style="w-32 h-32"/>
+        </PanelButtonBundle>
+    </Node>
+    <UiScroll @style="max-h-600 m-4 w-full min-h-64" @id="history_scroll">
+        <Node @style="absolute flex-col w-full" @id="history"
+            @for(notify: NotifyHistory in state.notifys().iter().cloned() => {
+                state.set_notify(notify);
+            })>
+            <(NotifyView{ notify: state.notify().clone(), with_close_button: true })
+                @use_state(pub notify: NotifyHistory) />
+        </Node>
+    </UiScroll>
+</Node>
+}
+
+pub fn open_popup(
+    event: UiEvent<UiButtonEvent>,
+    mut commands: Commands,
+    mut notify_controller: ResMut<NotifyController>,
+) {
+    if event.kind == UiButtonEventKind::Released {
+        for notify in notify_controller.notifys.values_mut() {
+            notify.read = true;
+        }
+        commands
+            .spawn(PanelPopupBundle {
+                anchor_policy: AnchorPolicy::new(PopupAnlign::InnerEnd, PopupAnlign::None),
+                ..PanelPopupBundle::new(event.receiver(), style!("absolute top-42"))
+            })
+            .with_children(|c| {
+                c.spawn((NotifyCenter, style!("h-auto w-auto")));
+            });
+    }
+}
@@ -1,6 +1,7 @@
 use dway_client_core::controller::systemcontroller::SystemControllRequest;
+use widgets::text::UiTextBundle;
 
-use super::volume_control::VolumeControl;
+use super::{volume_control::VolumeControl, vrr_control::VrrControl};
 use crate::{
     panels::{PanelButtonBundle, PanelPopupBundle},
     prelude::*,
@@ -34,11 +35,31 @@ PanelSettings=>
         }
     }
 }
+@callback{[UiEvent<UiCheckBoxEvent>]
+    fn on_reduce_motion(event: UiEvent<UiCheckBoxEvent>, mut animation_settings: ResMut<AnimationSettings>) {
+        animation_settings.reduce_motion = event.value;
+    }
+}
+@use_state(reduce_motion: bool)
+@global(animation_settings: AnimationSettings -> {
+    if *state.reduce_motion() != animation_settings.reduce_motion {
+        state.set_reduce_motion(animation_settings.reduce_motion);
+    }
+})
 @global(theme:Theme)
 @global(asset_server: AssetServer)
 @global(mut assets_rounded_ui_rect_material: Assets<RoundedUiRectMaterial>)
 <Node @style="flex-col">
     <VolumeControl/>
+    <VrrControl/>
+    <Node @id="motion" @style="p-4 m-4 flex-row align-items:center"
+        @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 16.0))
+    >
+        <(UiTextBundle::new("Reduce motion", 16, &theme)) @style="flex_grow:1.0 m-4"/>
+        <UiCheckBox @on_event(on_reduce_motion) @style="w-48 h-24 m-4" @id="reduce_motion"
+            UiCheckBoxState=(UiCheckBoxState::new(*state.reduce_motion()))
+        />
+    </Node>
     <Node @id="bottom_bar" @style="p-4 justify-content:space-evenly"
         @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 16.0))
     >
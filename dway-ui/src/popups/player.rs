@@ -0,0 +1,255 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    process::Command,
+    time::Duration,
+};
+
+use bevy::{
+    image::{ImageFormatSetting, ImageLoaderSettings},
+    tasks::{block_on, futures_lite::future::poll_once, IoTaskPool, Task},
+};
+use dway_client_core::controller::player::{
+    MediaPlayer, PlaybackStatus, PlayerAction, PlayerController, PlayerRequest,
+};
+use dway_ui_framework::widgets::util::visibility;
+use widgets::text::UiTextBundle;
+
+use crate::{
+    panels::{PanelButtonBundle, PanelPopupBundle},
+    prelude::*,
+};
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// The album art of the players by url. `file://` urls are loaded directly, `http(s)://` urls are
+/// downloaded with `curl` first. Any other url, or a failed download, shows the placeholder.
+#[derive(Resource, Default)]
+pub struct PlayerArtCache {
+    arts: HashMap<String, Handle<Image>>,
+    downloads: HashMap<String, Task<Option<PathBuf>>>,
+}
+
+impl PlayerArtCache {
+    pub fn art(&self, url: &str) -> Handle<Image> {
+        self.arts.get(url).cloned().unwrap_or_default()
+    }
+}
+
+fn download_art(url: &str) -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("dway-player-art");
+    std::fs::create_dir_all(&dir).ok()?;
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let path = dir.join(format!("{:016x}", hasher.finish()));
+    let status = Command::new("curl")
+        .args(["--silent", "--fail", "--location", "--max-time", "10", "--output"])
+        .arg(&path)
+        .arg(url)
+        .status();
+    match status {
+        Ok(status) if status.success() => Some(path),
+        Ok(status) => {
+            warn!(%url, "failed to download the album art: curl exited with {status}");
+            None
+        }
+        Err(e) => {
+            warn!(%url, "failed to download the album art: {e}");
+            None
+        }
+    }
+}
+
+fn update_player_art(
+    player_controller: Res<PlayerController>,
+    mut art_cache: ResMut<PlayerArtCache>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut changed = false;
+    let cache = art_cache.bypass_change_detection();
+    if player_controller.is_changed() {
+        let urls: HashSet<&str> = player_controller
+            .players
+            .values()
+            .map(|player| &*player.metadata.art_url)
+            .filter(|url| !url.is_empty())
+            .collect();
+        cache.arts.retain(|url, _| urls.contains(&**url));
+        cache.downloads.retain(|url, _| urls.contains(&**url));
+        for url in urls {
+            if cache.arts.contains_key(url) || cache.downloads.contains_key(url) {
+                continue;
+            }
+            if let Some(path) = url.strip_prefix("file://") {
+                cache
+                    .arts
+                    .insert(url.to_string(), asset_server.load(path.to_string()));
+                changed = true;
+            } else if url.starts_with("http://") || url.starts_with("https://") {
+                let download_url = url.to_string();
+                let task = IoTaskPool::get().spawn(async move { download_art(&download_url) });
+                cache.downloads.insert(url.to_string(), task);
+            } else {
+                debug!(%url, "unsupported album art url");
+                cache.arts.insert(url.to_string(), default());
+            }
+        }
+    }
+
+    let mut finished = vec![];
+    for (url, task) in cache.downloads.iter_mut() {
+        if let Some(path) = block_on(poll_once(task)) {
+            finished.push((url.clone(), path));
+        }
+    }
+    for (url, path) in finished {
+        cache.downloads.remove(&url);
+        // the downloaded file has no extension, the format is guessed from its content
+        let art = path
+            .map(|path| {
+                asset_server.load_with_settings(path, |settings: &mut ImageLoaderSettings| {
+                    settings.format = ImageFormatSetting::Guess;
+                })
+            })
+            .unwrap_or_default();
+        cache.arts.insert(url, art);
+        changed = true;
+    }
+    if changed {
+        art_cache.set_changed();
+    }
+}
+
+/// The controls of the active media player with its album art.
+#[derive(Component, Default)]
+pub struct MediaPlayerPopup;
+
+dway_widget! {
+MediaPlayerPopup=>
+@callback{[UiEvent<UiButtonEvent>]
+    fn on_previous(event: UiEvent<UiButtonEvent>, mut requests: MessageWriter<PlayerRequest>) {
+        if event.kind == UiButtonEventKind::Released {
+            requests.write(PlayerRequest::active(PlayerAction::Previous));
+        }
+    }
+}
+@callback{[UiEvent<UiButtonEvent>]
+    fn on_play_pause(event: UiEvent<UiButtonEvent>, mut requests: MessageWriter<PlayerRequest>) {
+        if event.kind == UiButtonEventKind::Released {
+            requests.write(PlayerRequest::active(PlayerAction::PlayPause));
+        }
+    }
+}
+@callback{[UiEvent<UiButtonEvent>]
+    fn on_next(event: UiEvent<UiButtonEvent>, mut requests: MessageWriter<PlayerRequest>) {
+        if event.kind == UiButtonEventKind::Released {
+            requests.write(PlayerRequest::active(PlayerAction::Next));
+        }
+    }
+}
+@callback{[UiEvent<UiSliderEvent>]
+    fn on_seek(
+        event: UiEvent<UiSliderEvent>,
+        player_controller: Res<PlayerController>,
+        mut requests: MessageWriter<PlayerRequest>,
+    ) {
+        if let Some(length) = player_controller.active_player().and_then(|p| p.metadata.length) {
+            requests.write(PlayerRequest::active(PlayerAction::SetPosition(length.mul_f32(event.value))));
+        }
+    }
+}
+@callback{[UiEvent<UiSliderEvent>]
+    fn on_volume(event: UiEvent<UiSliderEvent>, mut requests: MessageWriter<PlayerRequest>) {
+        requests.write(PlayerRequest::active(PlayerAction::SetVolume(event.value as f64)));
+    }
+}
+@plugin{
+    app.init_resource::<PlayerArtCache>();
+    app.add_systems(Update, update_player_art);
+}
+@use_state(player: MediaPlayer)
+@use_state(art: Handle<Image>)
+@global(theme: Theme)
+@global(asset_server: AssetServer)
+@global(player_controller: PlayerController -> {
+    let player = player_controller.active_player().cloned().unwrap_or_default();
+    if state.player() != &player {
+        let art = art_cache.art(&player.metadata.art_url);
+        if state.art() != &art {
+            state.set_art(art);
+        }
+        state.set_player(player);
+    }
+})
+@global(art_cache: PlayerArtCache -> {
+    let art = art_cache.art(&state.player().metadata.art_url);
+    if state.art() != &art {
+        state.set_art(art);
+    }
+})
+@global(mut assets_rounded_ui_rect_material: Assets<RoundedUiRectMaterial>)
+<Node @style="flex-col p-8 w-320"
+    @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 16.0))>
+    <Node @style="flex-row align-items:center">
+        <Node @style="w-96 h-96 m-4" ImageNode=(state.art().clone().into())
+            Visibility=(visibility(state.art() != &Handle::default())) @id="art"/>
+        <(UiSvg::new(theme.icon("music_note", &asset_server))) @style="w-96 h-96 m-4"
+            Visibility=(visibility(state.art() == &Handle::default())) @id="art_placeholder"/>
+        <Node @style="flex-col m-4">
+            <(UiTextBundle::new(&state.player().metadata.title, 20, &theme)) @id="title"/>
+            <(UiTextBundle::new(&state.player().metadata.artist(), 16, &theme)) @id="artist"/>
+            <(UiTextBundle::new(&state.player().metadata.album, 16, &theme)) @id="album"/>
+            <(UiTextBundle::new(&state.player().identity, 12, &theme)) @id="identity"/>
+        </Node>
+    </Node>
+    <UiSlider @on_event(on_seek) @id="progress" @style="m-4 h-16 w-full"
+        UiSliderState=(UiSliderState{value: state.player().progress(),..default()})
+    />
+    <Node @style="flex-row justify-content:space-between m-4">
+        <(UiTextBundle::new(&format_duration(state.player().position), 12, &theme)) @id="position"/>
+        <(UiTextBundle::new(&format_duration(state.player().metadata.length.unwrap_or_default()), 12, &theme)) @id="length"/>
+    </Node>
+    <Node @style="flex-row justify-content:center align-items:center">
+        <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+            @on_event(on_previous) @style="w-32 h-32 m-4" @id="previous">
+            <(UiSvg::new(theme.icon("skip_previous", &asset_server))) @style="w-32 h-32"/>
+        </PanelButtonBundle>
+        <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+            @on_event(on_play_pause) @style="w-40 h-40 m-4" @id="play_pause">
+            <(UiSvg::new(theme.icon(
+                if state.player().status == PlaybackStatus::Playing { "pause" } else { "play" },
+                &asset_server))) @style="w-40 h-40"/>
+        </PanelButtonBundle>
+        <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+            @on_event(on_next) @style="w-32 h-32 m-4" @id="next">
+            <(UiSvg::new(theme.icon("skip_next", &asset_server))) @style="w-32 h-32"/>
+        </PanelButtonBundle>
+    </Node>
+    <Node @style="flex-row align-items:center m-4">
+        <(UiSvg::new(theme.icon("volume_on", &asset_server))) @style="w-24 h-24"/>
+        <UiSlider @on_event(on_volume) @id="volume" @style="m-4 h-16 w-full"
+            UiSliderState=(UiSliderState{value: state.player().volume as f32,..default()})
+        />
+    </Node>
+</Node>
+}
+
+pub fn open_popup(event: UiEvent<UiButtonEvent>, mut commands: Commands) {
+    if event.kind == UiButtonEventKind::Released {
+        commands
+            .spawn(PanelPopupBundle {
+                anchor_policy: AnchorPolicy::new(PopupAnlign::InnerEnd, PopupAnlign::None),
+                ..PanelPopupBundle::new(event.receiver(), style!("absolute top-42"))
+            })
+            .with_children(|c| {
+                c.spawn((MediaPlayerPopup, style!("h-auto w-auto")));
+            });
+    }
+}
@@ -0,0 +1,89 @@
+use dway_ui_framework::widgets::util::visibility;
+use dway_util::output::{OutputConfigRequest, OutputHead, VrrActive, VrrPolicy};
+use widgets::text::UiTextBundle;
+
+use crate::{panels::PanelButtonBundle, prelude::*};
+
+fn policy_name(policy: VrrPolicy) -> &'static str {
+    match policy {
+        VrrPolicy::Never => "VRR off",
+        VrrPolicy::Always => "VRR on",
+        VrrPolicy::Fullscreen => "VRR fullscreen",
+    }
+}
+
+fn next_policy(policy: VrrPolicy) -> VrrPolicy {
+    match policy {
+        VrrPolicy::Never => VrrPolicy::Fullscreen,
+        VrrPolicy::Fullscreen => VrrPolicy::Always,
+        VrrPolicy::Always => VrrPolicy::Never,
+    }
+}
+
+/// The variable refresh rate of the outputs which support it, a click switches to the next policy.
+#[derive(Component, Default)]
+pub struct VrrControl;
+
+fn on_output(
+    event: UiEvent<UiButtonEvent>,
+    query: Query<&VrrControlSubStateOutputs>,
+    head_query: Query<&OutputHead>,
+    mut requests: MessageWriter<OutputConfigRequest>,
+) {
+    let Ok(state) = query.get(event.receiver()) else {
+        return;
+    };
+    if event.kind != UiButtonEventKind::Released {
+        return;
+    }
+    let Ok(head) = head_query.get(*state.output()) else {
+        return;
+    };
+    let mut output_state = head.state.clone();
+    output_state.vrr = next_policy(output_state.vrr);
+    requests.write(OutputConfigRequest {
+        states: vec![(*state.output(), output_state)],
+        test_only: false,
+        sender: event.receiver(),
+    });
+}
+
+dway_widget! {
+VrrControl=>
+@add_callback{[UiEvent<UiButtonEvent>]on_output}
+@use_state(outputs: Vec<(Entity, String, VrrPolicy, bool)>)
+@arg(head_query: Query<(Entity, &OutputHead, Has<VrrActive>)> => {
+    let mut outputs: Vec<_> = head_query.iter()
+        .filter(|(_, head, _)| head.vrr_capable && head.state.enabled)
+        .map(|(entity, head, active)| (entity, head.name().to_string(), head.state.vrr, active))
+        .collect();
+    outputs.sort_by(|a, b| a.1.cmp(&b.1));
+    if state.outputs() != &outputs {
+        state.set_outputs(outputs);
+    }
+})
+@global(theme: Theme)
+@global(mut assets_rounded_ui_rect_material: Assets<RoundedUiRectMaterial>)
+<Node @style="flex-col m-4" @id="outputs"
+    Visibility=(visibility(!state.outputs().is_empty()))
+    @for((output, name, policy, active): (Entity, String, VrrPolicy, bool) in state.outputs().iter().cloned() => {
+        state.set_output(output);
+        state.set_name(name);
+        state.set_policy(policy);
+        state.set_active(active);
+    })>
+    <( PanelButtonBundle::new(&theme,&mut assets_rounded_ui_rect_material) )
+        @on_event(on_output) @style="flex-row align-items:center m-2 p-4"
+        @use_state(pub output: Entity = Entity::PLACEHOLDER)
+        @use_state(pub name: String)
+        @use_state(pub policy: VrrPolicy)
+        @use_state(pub active: bool)>
+        <(UiTextBundle::new(state.name(), 16, &theme)) @style="flex_grow:1.0 m-2"/>
+        <(UiTextBundle::new(policy_name(*state.policy()), 14, &theme)) @style="m-2"/>
+        <Node @style="w-8 h-8 m-2"
+            Visibility=(visibility(*state.active()))
+            @material(UiCircleMaterial=>circle_material(theme.color("blue")))
+        />
+    </PanelButtonBundle>
+</Node>
+}
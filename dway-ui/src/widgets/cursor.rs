@@ -1,11 +1,16 @@
 use dway_client_core::desktop::{CursorOnScreen, CursorOnWindow};
 use dway_server::{
-    geometry::GlobalGeometry,
+    geometry::{Geometry, GlobalGeometry},
     input::{pointer::WlPointer, seat::SeatHasPointer},
     util::rect::IRect,
-    wl::surface::{ClientHasSurface, WlSurface},
+    wl::{
+        buffer::WlShmBuffer,
+        surface::{ClientHasSurface, WlSurface},
+    },
 };
-use std::result::Result;
+use dway_util::cursor::{CursorImage, HardwareCursor, HardwareCursorState};
+use bevy::render::render_resource::TextureFormat;
+use std::{result::Result, sync::Arc};
 
 use crate::prelude::*;
 
@@ -27,7 +32,7 @@ impl Cursor {
 graph_query! { CursorQuery=>[
     surface=<Entity,With<WlSurface>>,
     client=Entity,
-    pointer=<(&'static WlSurface, &'static GlobalGeometry),With<WlPointer>>,
+    pointer=<(Entity, &'static WlSurface, &'static Geometry, &'static GlobalGeometry),With<WlPointer>>,
 ]=>{
     pointer=surface<-[ClientHasSurface]-client-[SeatHasPointer]->pointer
 }}
@@ -53,32 +58,121 @@ pub fn update_cursor_state(
         }
         if prop.is_changed() || surface_changed || focus_screen.is_changed() {
             let cursor_data = state.surface_entity().and_then(|surface| {
-                graph.for_each_pointer_from(surface, |_, _, &(surface, global_geometry)| {
-                    ControlFlow::Return((
-                        surface.image.clone(),
-                        surface.image_rect(),
-                        global_geometry.geometry,
-                    ))
-                })
+                graph.for_each_pointer_from(
+                    surface,
+                    |_, _, &(cursor_surface_entity, surface, geometry, global_geometry)| {
+                        ControlFlow::Return((
+                            cursor_surface_entity,
+                            surface.image.clone(),
+                            surface.image_rect(),
+                            geometry.pos(),
+                            global_geometry.geometry,
+                        ))
+                    },
+                )
             });
-            if let Some((image, image_rect, geo)) = cursor_data {
+            if let Some((cursor_surface, image, image_rect, offset, geo)) = cursor_data {
+                state.set_cursor_surface(Some(cursor_surface));
                 state.set_cursor_image(image);
                 state.set_cursor_geo(IRect::from_pos_size(
                     geo.pos() + image_rect.pos(),
                     image_rect.size(),
                 ));
+                state.set_cursor_hotspot(-(offset + image_rect.pos()));
             } else {
+                state.set_cursor_surface(None);
                 state.set_cursor_image(prop.default_cursor.clone());
                 state.set_cursor_geo(IRect::from_pos_size(*pos, prop.default_size.as_ivec2()));
+                state.set_cursor_hotspot(IVec2::ZERO);
             }
         }
     }
 }
 
+fn read_cursor_image(
+    state: &CursorState,
+    prop: &Cursor,
+    surface_query: &Query<Ref<WlSurface>>,
+    shm_query: &Query<&WlShmBuffer>,
+    images: &Assets<Image>,
+) -> Option<CursorImage> {
+    let logical_size = state.cursor_geo().size().as_vec2();
+    let hotspot = state.cursor_hotspot().as_vec2();
+    if let Some(cursor_surface) = state.cursor_surface() {
+        let surface = surface_query.get(*cursor_surface).ok()?;
+        let buffer = shm_query.get(surface.commited.buffer?).ok()?;
+        let opaque = match buffer.format {
+            wl_shm::Format::Argb8888 => false,
+            wl_shm::Format::Xrgb8888 => true,
+            _ => return None,
+        };
+        let pool = buffer.pool.read().unwrap();
+        let data = unsafe { pool.as_slice(buffer) }.ok()?;
+        CursorImage::from_argb8888(
+            buffer.size,
+            buffer.stride as usize,
+            data,
+            opaque,
+            logical_size,
+            hotspot,
+        )
+    } else {
+        let image = images.get(&prop.default_cursor)?;
+        if image.texture_descriptor.format != TextureFormat::Rgba8UnormSrgb {
+            return None;
+        }
+        let size = image.size().as_ivec2();
+        CursorImage::from_rgba(size, image.data.as_ref()?, logical_size, hotspot)
+    }
+}
+
+/// Hand the cursor image to the cursor planes and hide the widget while they show it.
+pub fn update_hardware_cursor(
+    mut widget_query: Query<(Ref<Cursor>, Ref<CursorState>, &mut Visibility)>,
+    surface_query: Query<Ref<WlSurface>>,
+    shm_query: Query<&WlShmBuffer>,
+    images: Res<Assets<Image>>,
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    mut hardware_cursor: ResMut<HardwareCursor>,
+    hardware_cursor_state: Res<HardwareCursorState>,
+) {
+    let image_loaded = image_events.read().count() > 0;
+    let Some((prop, state, _)) = widget_query.iter().next() else {
+        return;
+    };
+    let surface = (*state.cursor_surface()).and_then(|entity| surface_query.get(entity).ok());
+    let surface_changed = surface.as_ref().is_some_and(|surface| surface.is_changed());
+    if state.is_changed() || prop.is_changed() || surface_changed || image_loaded {
+        let image = read_cursor_image(&state, &prop, &surface_query, &shm_query, &images);
+        hardware_cursor.image = image.map(Arc::new);
+        hardware_cursor.image_description = surface
+            .and_then(|surface| surface.commited.image_description.clone())
+            .unwrap_or_default();
+    }
+
+    let visibility = if hardware_cursor_state.active {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    for (_, _, mut widget_visibility) in &mut widget_query {
+        widget_visibility.set_if_neq(visibility);
+    }
+}
+
 dway_widget! {
 Cursor=>
-@plugin{app.add_systems(Update, update_cursor_state.in_set(CursorSystems::Render).before(cursor_render));}
+@plugin{
+    app.init_resource::<HardwareCursor>();
+    app.init_resource::<HardwareCursorState>();
+    app.add_systems(Update, (
+        update_cursor_state.in_set(CursorSystems::Render).before(cursor_render),
+        update_hardware_cursor.after(update_cursor_state),
+    ));
+}
 @use_state(pub surface_entity: Option<Entity>)
+@use_state(pub cursor_surface: Option<Entity>)
+@use_state(pub cursor_hotspot: IVec2)
 @use_state(pub cursor_geo: IRect)
 @use_state(pub cursor_image: Handle<Image>)
 @state_component(#[derive(Debug)])
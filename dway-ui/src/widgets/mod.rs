@@ -4,9 +4,12 @@ pub mod cursor;
 pub mod icon;
 pub mod logger;
 pub mod notifys;
+pub mod osd;
+pub mod player;
 pub mod popupwindow;
 pub mod screen;
 pub mod system_monitor;
+pub mod tray;
 pub mod window;
 pub mod windowlist;
 pub mod windowmenu;
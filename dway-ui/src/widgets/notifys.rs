@@ -1,70 +1,254 @@
+use std::time::SystemTime;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
 use bevy_svg::prelude::Svg;
-use dway_client_core::controller::notify::{NotifyAction, NotifyHistory, NotifyRequest};
+use dway_client_core::controller::notify::{
+    NotifyAction, NotifyBodySpan, NotifyController, NotifyHistory, NotifyImage, NotifyRequest,
+    NotifyUrgency,
+};
+use dway_server::apps::icon::LinuxIcon;
 use dway_ui_framework::widgets::util::visibility;
 use widgets::text::UiTextBundle;
 
-use crate::prelude::*;
+use crate::{popups::notify, prelude::*, widgets::icon::UiIcon};
 
 #[derive(Component, Default)]
 pub struct NotifyButton;
 
 dway_widget! {
 NotifyButton=>
-@callback{[UiEvent<UiButtonEvent>]
-    fn open_notify_list(
-        event: UiEvent<UiButtonEvent>,
-        mut query: Query<&mut NotifyButtonState>,
-        _notify_sender: MessageWriter<NotifyRequest>
-    ) {
-        let Ok(_state) = query.get_mut(event.receiver()) else {return};
-        if event.kind == UiButtonEventKind::Released{
-        }
+@plugin{ app.register_callback(notify::open_popup); }
+@use_state(unread_count: usize)
+@global(notify_controller: NotifyController -> {
+    let unread_count = notify_controller.unread_count();
+    if *state.unread_count() != unread_count {
+        state.set_unread_count(unread_count);
     }
-}
-@use_state(notify_count: usize)
+})
 @global(theme:Theme)
+@global(callbacks: CallbackTypeRegister)
 @global(asset_server: AssetServer)
-<UiButton NoTheme @on_event(open_notify_list)  
+<UiButton NoTheme @on_event((callbacks.system(notify::open_popup))->self)
     @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 8.0))
 >
     <(UiSvg::new(theme.icon("notifications", &asset_server))) @style="w-24 h-24" @id="icon"/>
+    <Node @style="absolute top-0 right-0 w-8 h-8" @id="unread_mark"
+        Visibility=(visibility(*state.unread_count() > 0))
+        @material(UiCircleMaterial=>circle_material(theme.color("red")))
+    />
 </UiButton>
 }
 
+/// The styled body text of a notification, rebuilt by [`update_notify_body`].
+#[derive(Component, Default)]
+#[require(Node)]
+pub struct NotifyBody {
+    pub spans: Vec<NotifyBodySpan>,
+    pub font_size: f32,
+}
+
+pub fn update_notify_body(
+    query: Query<(Entity, &NotifyBody), Changed<NotifyBody>>,
+    theme: Res<Theme>,
+    mut commands: Commands,
+) {
+    for (entity, body) in query.iter() {
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.despawn_related::<Children>();
+        let font = theme.text_font(body.font_size);
+        for span in &body.spans {
+            let color = if span.link.is_some() {
+                theme.color("blue")
+            } else if span.bold {
+                theme.color("foreground:emphasis")
+            } else if span.italic {
+                theme.color("foreground2")
+            } else {
+                theme.color("foreground")
+            };
+            let underline = if span.underline {
+                (
+                    Node {
+                        border: UiRect::bottom(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor::all(color),
+                )
+            } else {
+                default()
+            };
+            entity_commands.with_child((
+                Text::new(&span.text),
+                font.clone(),
+                TextColor(color),
+                underline,
+            ));
+        }
+    }
+}
+
 #[derive(Component, Default)]
 pub struct NotifyView {
     pub notify: NotifyHistory,
     pub with_close_button: bool,
 }
 
+fn on_action(
+    event: UiEvent<UiButtonEvent>,
+    query: Query<&NotifyViewSubStateActions>,
+    mut notify_sender: MessageWriter<NotifyRequest>,
+) {
+    let Ok(state) = query.get(event.receiver()) else {
+        return;
+    };
+    if event.kind == UiButtonEventKind::Released {
+        notify_sender.write(NotifyRequest::InvokeAction {
+            id: *state.notify_id(),
+            action: state.action().clone(),
+        });
+    }
+}
+
+fn on_close(
+    event: UiEvent<UiButtonEvent>,
+    query: Query<&NotifyView>,
+    mut notify_sender: MessageWriter<NotifyRequest>,
+) {
+    let Ok(view) = query.get(event.receiver()) else {
+        return;
+    };
+    if event.kind == UiButtonEventKind::Released {
+        notify_sender.write(if view.notify.closed {
+            NotifyRequest::Remove(view.notify.id)
+        } else {
+            NotifyRequest::Dismiss(view.notify.id)
+        });
+    }
+}
+
+fn notify_image_handles(
+    image: Option<NotifyImage>,
+    images: &mut Assets<Image>,
+    asset_server: &AssetServer,
+) -> (Handle<Image>, Handle<Svg>, Handle<LinuxIcon>) {
+    match image {
+        Some(NotifyImage::Data {
+            width,
+            height,
+            rgba,
+        }) => {
+            let image = Image::new(
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                rgba,
+                TextureFormat::Rgba8UnormSrgb,
+                RenderAssetUsages::default(),
+            );
+            (images.add(image), default(), default())
+        }
+        Some(NotifyImage::Path(path)) if path.ends_with(".svg") => {
+            (default(), asset_server.load(path), default())
+        }
+        Some(NotifyImage::Path(path)) => (asset_server.load(path), default(), default()),
+        Some(NotifyImage::Icon(name)) => (
+            default(),
+            default(),
+            asset_server.load(format!("linuxicon://{name}/48x48")),
+        ),
+        None => default(),
+    }
+}
+
 dway_widget!{
 NotifyView=>
+@add_callback{[UiEvent<UiButtonEvent>]on_action}
+@add_callback{[UiEvent<UiButtonEvent>]on_close}
+@plugin{
+    app.add_systems(PostUpdate, update_notify_body.before(UiFrameworkSystems::UpdateWidgets));
+}
 @global(theme: Theme)
 @global(asset_server: AssetServer)
+@global(mut images: Assets<Image>)
 @use_state(image: Handle<Image>)
 @use_state(svg: Handle<Svg>)
-<Node @style="flex-col">
-    <Node>
-        <(UiSvg::new(theme.icon("close", &asset_server))) @style="w-24 h-24" />
-        <( UiTextBundle::new(&prop.notify.data.summary, 24, &theme) )/>
-        <( UiTextBundle::new(&prop.notify.data.app_name, 24, &theme) ) @style="right-0"/>
+@use_state(icon: Handle<LinuxIcon>)
+@use_state(image_key: Option<(u32, SystemTime)>)
+@before{
+    // the image data is added as an asset once per notification, a replaced notification has a
+    // new receive time
+    let image_key = Some((prop.notify.id, prop.notify.time));
+    if state.image_key() != &image_key {
+        let (image, svg, icon) = notify_image_handles(prop.notify.data.image(), &mut images, &asset_server);
+        state.set_image(image);
+        state.set_svg(svg);
+        state.set_icon(icon);
+        state.set_image_key(image_key);
+    }
+}
+<Node @style="flex-col p-4 m-4 min-w-320 max-w-480"
+    @material(RoundedUiRectMaterial=>rounded_rect(
+        if prop.notify.data.urgency() == NotifyUrgency::Critical {theme.color("red")} else {theme.color("panel-popup1")}, 8.0))>
+    <Node @style="flex-row align-items:center">
+        <( UiTextBundle::new(&prop.notify.data.app_name, 16, &theme) ) @style="flex_grow:1.0"/>
+        <UiButton NoTheme @style="w-20 h-20" @id="close_button" @on_event(on_close->this_entity)
+            Visibility=(visibility(prop.with_close_button))>
+            <(UiSvg::new(theme.icon("close", &asset_server))) @style="w-20 h-20" />
+        </UiButton>
     </Node>
-    <Node @style="right-0" >
-        <( UiTextBundle::new(&prop.notify.data.body, 24, &theme) )/>
-        <Node UiSvg=(state.svg().clone().into())
+    <Node @style="flex-row align-items:center">
+        <Node @style="w-48 h-48 m-4" UiSvg=(state.svg().clone().into())
             Visibility=(visibility(state.svg()!=&Handle::default())) />
-        <Node ImageNode=(state.image().clone().into())
+        <Node @style="w-48 h-48 m-4" ImageNode=(state.image().clone().into())
             Visibility=(visibility(state.image()!=&Handle::default()))/>
+        <(UiIcon::from(state.icon().clone())) @style="w-48 h-48 m-4"
+            Visibility=(visibility(state.icon()!=&Handle::default()))/>
+        <Node @style="flex-col">
+            <( UiTextBundle::new(&prop.notify.data.summary, 20, &theme) )/>
+            <Node @style="flex-row flex_wrap:FlexWrap::Wrap" NotifyBody=(NotifyBody{ spans: prop.notify.data.body_spans(), font_size: 16.0 }) />
+        </Node>
     </Node>
-    <Node @id="actions" 
-        Visibility=(visibility(prop.notify.data.actions.is_empty()))
-    @for(action: NotifyAction in prop.notify.data.actions.iter().cloned() => {
+    <Node @id="actions" @style="flex-row justify-content:flex-end"
+        Visibility=(visibility(!prop.notify.data.actions.is_empty()))
+    @for((notify_id, action): (u32, NotifyAction) in prop.notify.data.actions.iter().cloned().map(|a|(prop.notify.id, a)) => {
+        state.set_notify_id(notify_id);
         state.set_action(action.name.clone());
         state.set_text(action.text.clone());
     })>
-        <UiButton @use_state(action: String) @use_state(text: String) >
-            <( UiTextBundle::new(state.text(), 24, &theme) )/>
+        <UiButton @style="m-2 p-4" @on_event(on_action)
+            @use_state(pub notify_id: u32) @use_state(pub action: String) @use_state(pub text: String) >
+            <( UiTextBundle::new(state.text(), 16, &theme) )/>
         </UiButton>
     </Node>
 </Node>
 }
+
+/// The stack of notification popups shown in the corner of a screen.
+#[derive(Component, Default)]
+pub struct NotifyPopupList;
+
+dway_widget! {
+NotifyPopupList=>
+@use_state(notifys: Vec<NotifyHistory>)
+@use_state(revisions: Vec<(u32, SystemTime, bool)>)
+@global(notify_controller: NotifyController -> {
+    let revisions: Vec<_> = notify_controller.popups().map(|n|n.revision()).collect();
+    if state.revisions() != &revisions {
+        state.set_revisions(revisions);
+        state.set_notifys(notify_controller.popups().rev().cloned().collect());
+    }
+})
+<Node @style="flex-col" @id="list"
+    @for(notify: NotifyHistory in state.notifys().iter().cloned() => {
+        state.set_notify(notify);
+    })>
+    <(NotifyView{ notify: state.notify().clone(), with_close_button: true })
+        @use_state(pub notify: NotifyHistory) />
+</Node>
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use dway_client_core::controller::brightness::BacklightKind;
+use dway_ui_framework::widgets::util::visibility;
+
+use crate::prelude::*;
+
+/// How long the overlay stays after the last change.
+const OSD_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Shows a level on the on-screen display, e.g. after a volume or brightness key.
+#[derive(Message, Debug, Clone)]
+pub struct OsdRequest {
+    pub icon: String,
+    pub value: f32,
+}
+
+impl OsdRequest {
+    pub fn volume(value: f32, mute: bool) -> Self {
+        Self {
+            icon: if mute || value == 0.0 { "volume_off" } else { "volume_on" }.to_string(),
+            value: if mute { 0.0 } else { value },
+        }
+    }
+
+    pub fn brightness(kind: BacklightKind, value: f32) -> Self {
+        Self {
+            icon: match kind {
+                BacklightKind::Keyboard => "keyboard",
+                _ => "brightness",
+            }
+            .to_string(),
+            value,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct OsdState {
+    pub icon: String,
+    pub value: f32,
+    pub visible: bool,
+}
+
+fn update_osd(
+    mut requests: MessageReader<OsdRequest>,
+    mut osd: ResMut<OsdState>,
+    time: Res<Time>,
+    mut hide_timer: Local<Option<Timer>>,
+) {
+    if let Some(request) = requests.read().last() {
+        *osd = OsdState {
+            icon: request.icon.clone(),
+            value: request.value.clamp(0.0, 1.0),
+            visible: true,
+        };
+        *hide_timer = Some(Timer::new(OSD_TIMEOUT, TimerMode::Once));
+    } else if let Some(timer) = hide_timer.as_mut() {
+        if timer.tick(time.delta()).just_finished() {
+            osd.visible = false;
+            *hide_timer = None;
+        }
+    }
+}
+
+/// The on-screen display of volume and brightness changes.
+#[derive(Component, Default)]
+pub struct Osd;
+
+dway_widget! {
+Osd=>
+@plugin{
+    app.add_event::<OsdRequest>()
+        .init_resource::<OsdState>()
+        .add_systems(Update, update_osd);
+}
+@use_state(icon: String)
+@use_state(value: f32)
+@use_state(visible: bool)
+@global(osd: OsdState -> {
+    if state.icon() != &osd.icon {
+        state.set_icon(osd.icon.clone());
+    }
+    if *state.value() != osd.value {
+        state.set_value(osd.value);
+    }
+    if *state.visible() != osd.visible {
+        state.set_visible(osd.visible);
+    }
+})
+@global(theme: Theme)
+@global(asset_server: AssetServer)
+<Node @style="flex-row align-items:center p-8"
+    Visibility=(visibility(*state.visible()))
+    @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 16.0))>
+    <(UiSvg::new(theme.icon(state.icon(), &asset_server))) @style="w-32 h-32 m-4" @id="icon"/>
+    <Node @style="w-192 h-8 m-4" @id="bar"
+        @material(RoundedUiRectMaterial=>rounded_rect(theme.color("gray"), 4.0))>
+        <(Node{ width: Val::Percent(*state.value() * 100.0), height: Val::Percent(100.0), ..default() })
+            @id="level" @material(RoundedUiRectMaterial=>rounded_rect(theme.color("blue"), 4.0))/>
+    </Node>
+</Node>
+}
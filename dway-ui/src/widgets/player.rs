@@ -0,0 +1,43 @@
+use dway_client_core::controller::player::{PlaybackStatus, PlayerController};
+use dway_ui_framework::widgets::util::visibility;
+use widgets::text::UiTextBundle;
+
+use crate::{popups::player, prelude::*};
+
+/// The panel button of the active media player, shows the playing track.
+#[derive(Component, Default)]
+pub struct MediaButton;
+
+dway_widget! {
+MediaButton=>
+@plugin{ app.register_callback(player::open_popup); }
+@use_state(has_player: bool)
+@use_state(playing: bool)
+@use_state(title: String)
+@global(player_controller: PlayerController -> {
+    let player = player_controller.active_player();
+    if *state.has_player() != player.is_some() {
+        state.set_has_player(player.is_some());
+    }
+    let playing = player.is_some_and(|p| p.status == PlaybackStatus::Playing);
+    if *state.playing() != playing {
+        state.set_playing(playing);
+    }
+    let title = player.map(|p| p.metadata.title.clone()).unwrap_or_default();
+    if state.title() != &title {
+        state.set_title(title);
+    }
+})
+@global(theme:Theme)
+@global(callbacks: CallbackTypeRegister)
+@global(asset_server: AssetServer)
+<UiButton NoTheme @on_event((callbacks.system(player::open_popup))->self)
+    @style="flex-row align-items:center p-4 max-w-256"
+    Visibility=(visibility(*state.has_player()))
+    @material(RoundedUiRectMaterial=>rounded_rect(theme.color("panel-popup1"), 8.0))
+>
+    <(UiSvg::new(theme.icon(if *state.playing() { "music_note" } else { "pause" }, &asset_server)))
+        @style="w-20 h-20" @id="icon"/>
+    <(UiTextBundle::new(state.title(), 16, &theme)) @style="m-4" @id="title"/>
+</UiButton>
+}
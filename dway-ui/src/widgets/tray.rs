@@ -0,0 +1,253 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    input::mouse::MouseScrollUnit,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use dway_client_core::controller::tray::{
+    TrayController, TrayItem, TrayItemId, TrayMenuItem, TrayMenuToggle, TrayPixmap,
+    TrayRequest, TrayScrollOrientation,
+};
+use dway_server::{apps::icon::LinuxIcon, geometry::GlobalGeometry};
+use dway_ui_framework::widgets::util::visibility;
+
+use crate::{panels::top_panel::Panel, prelude::*, widgets::icon::UiIcon};
+
+const TRAY_ICON_SIZE: u32 = 24;
+
+/// The status notifier items shown in the panel.
+#[derive(Component, Default)]
+pub struct TrayUI;
+
+/// The position of a tray item in global coordinates. The UI of a screen is laid out in the
+/// physical pixels of its output, from the origin of the screen.
+fn item_position(
+    entity: Entity,
+    node_query: &Query<(&UiGlobalTransform, &ComputedNode)>,
+    parent_query: &Query<&ChildOf>,
+    panel_query: &Query<&Panel>,
+    screen_query: &Query<&GlobalGeometry>,
+) -> IVec2 {
+    let Ok((transform, computed_node)) = node_query.get(entity) else {
+        return IVec2::ZERO;
+    };
+    let position = transform.translation * computed_node.inverse_scale_factor();
+    let screen_position = parent_query
+        .iter_ancestors(entity)
+        .find_map(|entity| panel_query.get(entity).ok())
+        .and_then(|panel| screen_query.get(panel.screen).ok())
+        .map(|geometry| geometry.pos())
+        .unwrap_or_default();
+    screen_position + position.as_ivec2()
+}
+
+fn on_tray_item_input(
+    event: UiEvent<UiInputEvent>,
+    query: Query<&TrayUISubStateItems>,
+    node_query: Query<(&UiGlobalTransform, &ComputedNode)>,
+    parent_query: Query<&ChildOf>,
+    panel_query: Query<&Panel>,
+    screen_query: Query<&GlobalGeometry>,
+    mut commands: Commands,
+    mut requests: MessageWriter<TrayRequest>,
+) {
+    let Ok(state) = query.get(event.receiver()) else {
+        return;
+    };
+    let item = state.item();
+    let position = item_position(
+        event.receiver(),
+        &node_query,
+        &parent_query,
+        &panel_query,
+        &screen_query,
+    );
+    match &*event {
+        UiInputEvent::MouseRelease(MouseButton::Left) if !item.item_is_menu => {
+            requests.write(TrayRequest::Activate {
+                item: item.id.clone(),
+                position,
+            });
+        }
+        UiInputEvent::MouseRelease(MouseButton::Left | MouseButton::Right) => {
+            if let Some(menu) = &item.menu {
+                requests.write(TrayRequest::MenuAboutToShow {
+                    item: item.id.clone(),
+                    menu_id: menu.id,
+                });
+                open_tray_menu(event.receiver(), item.id.clone(), menu, &mut commands);
+            } else {
+                requests.write(TrayRequest::ContextMenu {
+                    item: item.id.clone(),
+                    position,
+                });
+            }
+        }
+        UiInputEvent::MouseRelease(MouseButton::Middle) => {
+            requests.write(TrayRequest::SecondaryActivate {
+                item: item.id.clone(),
+                position,
+            });
+        }
+        UiInputEvent::Wheel(wheel) => {
+            let scale = match wheel.unit {
+                MouseScrollUnit::Line => 1.0,
+                MouseScrollUnit::Pixel => 1.0 / 16.0,
+            };
+            let (delta, orientation) = if wheel.y != 0.0 {
+                (wheel.y, TrayScrollOrientation::Vertical)
+            } else {
+                (wheel.x, TrayScrollOrientation::Horizontal)
+            };
+            let delta = (delta * scale).round() as i32;
+            if delta != 0 {
+                requests.write(TrayRequest::Scroll {
+                    item: item.id.clone(),
+                    delta,
+                    orientation,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+fn tray_pixmap_handle(item: &TrayItem, images: &mut Assets<Image>) -> Handle<Image> {
+    let Some(pixmap) = TrayPixmap::best_for_size(item.current_icon_pixmap(), TRAY_ICON_SIZE)
+    else {
+        return default();
+    };
+    images.add(Image::new(
+        Extent3d {
+            width: pixmap.width,
+            height: pixmap.height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixmap.to_rgba(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ))
+}
+
+dway_widget! {
+TrayUI=>
+@add_callback{[UiEvent<UiInputEvent>]on_tray_item_input}
+@use_state(items: Vec<TrayItem>)
+@global(tray_controller: TrayController -> {
+    if state.items().len() != tray_controller.len()
+        || state.items().iter().zip(tray_controller.values()).any(|(a, b)| a != b)
+    {
+        state.set_items(tray_controller.values().cloned().collect());
+    }
+})
+@global(asset_server: AssetServer)
+@global(mut images: Assets<Image>)
+<Node @style="flex-row align-items:center" @id="items"
+    @for(item: TrayItem in state.items().iter().cloned() => {
+        state.set_pixmap(tray_pixmap_handle(&item, &mut images));
+        state.set_icon(if item.current_icon_name().is_empty() {
+            default()
+        } else {
+            asset_server.load(format!("linuxicon://{}/{TRAY_ICON_SIZE}x{TRAY_ICON_SIZE}", item.current_icon_name()))
+        });
+        state.set_item(item);
+    })>
+    <UiInput @style="w-24 h-24 m-4" @on_event(on_tray_item_input)
+        @use_state(pub item: TrayItem)
+        @use_state(pub icon: Handle<LinuxIcon>)
+        @use_state(pub pixmap: Handle<Image>)>
+        <(UiIcon::from(state.icon().clone())) @style="w-24 h-24"
+            Visibility=(visibility(state.pixmap()==&Handle::default()))/>
+        <Node @style="absolute w-24 h-24" ImageNode=(state.pixmap().clone().into())
+            Visibility=(visibility(state.pixmap()!=&Handle::default()))/>
+    </UiInput>
+</Node>
+}
+
+fn tray_menu_items(menu: &TrayMenuItem) -> Vec<MenuItem> {
+    menu.children
+        .iter()
+        .filter(|c| c.visible)
+        .map(|child| MenuItem {
+            id: child.id as i64,
+            label: child.label.clone(),
+            kind: match child.toggle {
+                _ if child.separator => MenuItemKind::Separator,
+                TrayMenuToggle::Checkmark(checked) => MenuItemKind::Check(checked),
+                TrayMenuToggle::Radio(checked) => MenuItemKind::Radio(checked),
+                TrayMenuToggle::None => MenuItemKind::Action,
+            },
+            enabled: child.enabled && !child.separator,
+            children: tray_menu_items(child),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// The dbusmenu of a tray item.
+#[derive(Component)]
+#[require(UiMenu)]
+pub struct TrayMenu {
+    pub item: TrayItemId,
+}
+
+fn on_tray_menu_event(
+    event: On<UiEvent<UiMenuEvent>>,
+    menu_query: Query<&TrayMenu>,
+    mut requests: MessageWriter<TrayRequest>,
+    mut commands: Commands,
+) {
+    let Ok(menu) = menu_query.get(event.receiver()) else {
+        return;
+    };
+    match event.event() {
+        UiMenuEvent::Activated { id, .. } => {
+            requests.write(TrayRequest::MenuClicked {
+                item: menu.item.clone(),
+                menu_id: *id as i32,
+            });
+        }
+        UiMenuEvent::Closed => {
+            commands.entity(event.receiver()).despawn();
+        }
+        UiMenuEvent::Opened => {}
+    }
+}
+
+pub fn open_tray_menu(anchor: Entity, item: TrayItemId, menu: &TrayMenuItem, commands: &mut Commands) {
+    let menu_entity = commands
+        .spawn((TrayMenu { item }, UiMenu::new(tray_menu_items(menu))))
+        .observe(on_tray_menu_event)
+        .id();
+    open_menu(commands, menu_entity, MenuPlacement::Below(anchor));
+}
+
+/// Follow the layout changes of the dbusmenus and close the menus of removed items.
+pub fn update_tray_menus(
+    tray_controller: Res<TrayController>,
+    mut menu_query: Query<(Entity, &TrayMenu, &mut UiMenu)>,
+    mut commands: Commands,
+) {
+    if !tray_controller.is_changed() {
+        return;
+    }
+    for (entity, tray_menu, mut menu) in &mut menu_query {
+        let Some(item) = tray_controller.get(&tray_menu.item) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if let Some(layout) = &item.menu {
+            let items = tray_menu_items(layout);
+            if menu.items != items {
+                menu.items = items;
+            }
+        }
+    }
+}
+
+pub struct TrayMenuPlugin;
+impl Plugin for TrayMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_tray_menus);
+    }
+}
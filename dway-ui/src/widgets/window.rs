@@ -1,5 +1,6 @@
 use bevy::ui::RelativeCursorPosition;
 use dway_client_core::{
+    effect::{WindowEffectSettings, WindowEffects, WindowShadow},
     input::{GrabRequestKind, SurfaceInputEvent},
     navigation::windowstack::{WindowIndex, WindowStack}, UiAttachData,
 };
@@ -7,9 +8,23 @@ use dway_server::{
     geometry::{Geometry, GlobalGeometry},
     util::rect::IRect,
     wl::surface::WlSurface,
+    wp::data_device::dnd::{CompositorDndRequest, CompositorDragSource},
     xdg::{toplevel::DWayToplevel, DWayWindow, PopupList},
 };
-use dway_ui_framework::widgets::drag::{UiDrag, UiDragEvent};
+use dway_ui_framework::{
+    render::layer_manager::{FillWithLayer, LayerBackdrop, LayerCamera, LayerKind, LayerManager},
+    shader::{
+        effect::Shadow,
+        fill::{AddColor, ConvertColor, FillColor, FillImage, Opacity},
+        shape::RoundedRect,
+    },
+    widgets::{
+        dnd::{DndActions, ExternalDragEvent, UiDndState, UiExternalDropTarget},
+        drag::{UiDrag, UiDragEvent},
+    },
+};
+use dway_server::prelude::wl_data_device_manager::DndAction;
+use dway_util::color::{ColorTransform, ImageDescription};
 
 use super::popupwindow::PopupUI;
 use crate::{prelude::*, util::irect_to_style};
@@ -84,6 +99,79 @@ pub fn on_window_ui_input(
     }
 }
 
+/// Offer the drags of the ui to the clients of the windows under the cursor.
+pub fn send_drag_to_window(
+    mut events: MessageReader<ExternalDragEvent>,
+    mut dnd_state: ResMut<UiDndState>,
+    source_query: Query<&CompositorDragSource>,
+    mut requests: MessageWriter<CompositorDndRequest>,
+    mut commands: Commands,
+    mut current: Local<Option<Entity>>,
+) {
+    for event in events.read() {
+        match event {
+            ExternalDragEvent::Enter {
+                target,
+                position,
+                payload,
+                actions,
+            } => {
+                let source = *current.get_or_insert_with(|| {
+                    let data = payload
+                        .export()
+                        .map(|(mime_type, data)| (mime_type.to_string(), data.clone()))
+                        .collect();
+                    commands
+                        .spawn(CompositorDragSource::new(
+                            data,
+                            DndAction::from_bits_truncate(actions.bits()),
+                        ))
+                        .id()
+                });
+                requests.write(CompositorDndRequest::Enter {
+                    source,
+                    surface: *target,
+                    position: *position,
+                });
+            }
+            ExternalDragEvent::Motion { position, .. } => {
+                if let Some(source) = *current {
+                    requests.write(CompositorDndRequest::Motion {
+                        source,
+                        position: *position,
+                    });
+                }
+            }
+            ExternalDragEvent::Leave { .. } => {
+                if let Some(source) = *current {
+                    requests.write(CompositorDndRequest::Leave { source });
+                }
+            }
+            ExternalDragEvent::Drop { .. } => {
+                if let Some(source) = current.take() {
+                    // the server despawns the source after the client read the data
+                    requests.write(CompositorDndRequest::Drop { source });
+                }
+            }
+        }
+    }
+
+    if let Some(source) = *current {
+        if let Ok(source) = source_query.get(source) {
+            let action = if source.accepted.is_some() {
+                DndActions::from_bits_truncate(source.chosen_action.bits())
+            } else {
+                DndActions::empty()
+            };
+            dnd_state.set_external_action(action);
+        }
+        if !dnd_state.is_dragging() {
+            requests.write(CompositorDndRequest::Cancel { source });
+            *current = None;
+        }
+    }
+}
+
 pub fn create_raw_window_material(
     image_rect: IRect,
     image: Handle<Image>,
@@ -99,6 +187,115 @@ pub fn create_raw_window_material(
     )
 }
 
+pub type WindowSurfaceMaterial =
+    ShaderAsset<ShapeRender<RoundedRect, (Opacity<AddColor<ConvertColor<FillImage>>>, Shadow)>>;
+pub type WindowDecorationMaterial =
+    ShaderAsset<ShapeRender<RoundedRect, (Opacity<FillColor>, Shadow)>>;
+pub type WindowBackdropMaterial = ShaderAsset<ShapeRender<RoundedRect, FillWithLayer>>;
+
+fn window_shadow(shadow: Option<&WindowShadow>) -> Shadow {
+    match shadow {
+        Some(shadow) => Shadow::new(shadow.color, shadow.offset, shadow.margin, shadow.radius),
+        None => Shadow::new(Color::NONE, Vec2::ZERO, Vec2::ZERO, 1.0),
+    }
+}
+
+/// The surface of a window clipped to its geometry `rect`, the shadow is drawn
+/// by the decoration if the window has one. The colors are converted from the
+/// image description of the surface to the one screens are composited in.
+pub fn window_surface_material(
+    effects: &WindowEffects,
+    image_rect: IRect,
+    rect: IRect,
+    image: Handle<Image>,
+    decorated: bool,
+    color_transform: ColorTransform,
+) -> WindowSurfaceMaterial {
+    let shadow = if decorated {
+        None
+    } else {
+        effects.shadow.as_ref()
+    };
+    RoundedRect::new(effects.corner_radius)
+        .with_effect((
+            Opacity::new(
+                AddColor::new(
+                    ConvertColor::new(
+                        FillImage::new(
+                            (image_rect.min - rect.min).as_vec2() / rect.size().as_vec2(),
+                            image_rect.size().as_vec2() / rect.size().as_vec2(),
+                            image,
+                        ),
+                        color_transform,
+                    ),
+                    Color::BLACK.with_alpha(effects.dim),
+                ),
+                effects.opacity,
+            ),
+            window_shadow(shadow),
+        ))
+        .into()
+}
+
+pub fn window_decoration_material(effects: &WindowEffects) -> WindowDecorationMaterial {
+    RoundedRect::new(effects.corner_radius + DECORATION_MARGIN)
+        .with_effect((
+            Opacity::new(FillColor::new(color!("#333333")), effects.opacity),
+            window_shadow(effects.shadow.as_ref()),
+        ))
+        .into()
+}
+
+/// The blurred content behind a translucent window.
+#[derive(Component, Reflect, Default, Debug)]
+#[require(LayerBackdrop=LayerBackdrop::new(LayerKind::Blur))]
+pub struct WindowBackdrop {
+    pub corner_radius: f32,
+}
+
+pub fn update_window_backdrop(
+    mut query: Query<
+        (
+            &ComputedUiTargetCamera,
+            &WindowBackdrop,
+            &mut MaterialNode<WindowBackdropMaterial>,
+        ),
+        Or<(Changed<ComputedUiTargetCamera>, Changed<WindowBackdrop>)>,
+    >,
+    layer_manager_query: Query<&LayerManager>,
+    layer_camera_query: Query<&LayerCamera>,
+    mut assets: ResMut<Assets<WindowBackdropMaterial>>,
+) {
+    for (node_target, backdrop, mut material) in &mut query {
+        let Some(layer) = node_target
+            .get()
+            .and_then(|camera| layer_manager_query.get(camera).ok())
+            .and_then(|manager| layer_camera_query.get(manager.get_camera(LayerKind::Blur)).ok())
+        else {
+            continue;
+        };
+        *material = assets
+            .add(RoundedRect::new(backdrop.corner_radius).with_effect(FillWithLayer::new(
+                layer.ui_background().clone(),
+                layer.background_size,
+            )))
+            .into();
+    }
+}
+
+/// Take the default window effects from the theme.
+pub fn apply_theme_to_window_effects(
+    theme: Res<Theme>,
+    mut settings: ResMut<WindowEffectSettings>,
+) {
+    let shadow_color = theme.color("window:shadow");
+    settings.shadow = (shadow_color.alpha() > 0.0).then(|| WindowShadow {
+        color: shadow_color,
+        ..settings.shadow.clone().unwrap_or_default()
+    });
+    settings.inactive_dim = theme.color("window:dim").alpha();
+}
+
 #[derive(Component, Reflect, Debug)]
 #[require(GlobalZIndex)]
 pub struct WindowUI {
@@ -123,6 +320,17 @@ WindowUI=>
     app.register_type::<WindowUI>();
     app.register_type::<WindowUIState>();
     app.configure_sets(PreUpdate, DWayClientSystem::Input.after(UiFrameworkSystems::InputSystems));
+    app.add_systems(PreUpdate, send_drag_to_window.after(UiFrameworkSystems::WidgetInputSystems));
+    app.register_type::<WindowBackdrop>();
+    app.add_plugins((
+        WindowSurfaceMaterial::plugin(),
+        WindowDecorationMaterial::plugin(),
+        WindowBackdropMaterial::plugin(),
+    ));
+    app.add_systems(PreUpdate, apply_theme_to_window_effects
+        .run_if(resource_changed::<Theme>)
+        .before(DWayClientSystem::UpdateWindow));
+    app.add_systems(Last, update_window_backdrop.in_set(UiFrameworkSystems::UpdateLayersMaterial));
 }
 @callback{ [UiEvent<UiButtonEvent>]
     fn on_close_button_event(
@@ -192,9 +400,11 @@ WindowUI=>
 @use_state(pub decorated:bool)
 @use_state(pub image:Handle<Image>)
 @use_state(pub popup_list:Vec<Entity>)
+@use_state(pub effects:WindowEffects)
+@use_state(pub color_transform:ColorTransform)
 @global(theme: Theme)
 @world_query(z_index: &mut GlobalZIndex)
-@query(window_query:(rect,surface, toplevel, index, popups)<-Query<(Ref<GlobalGeometry>, Ref<WlSurface>, Ref<DWayToplevel>, Ref<WindowIndex>, Option<Ref<PopupList>>), With<DWayWindow>>[prop.window_entity]->{
+@query(window_query:(rect,surface, toplevel, index, popups, effects)<-Query<(Ref<GlobalGeometry>, Ref<WlSurface>, Ref<DWayToplevel>, Ref<WindowIndex>, Option<Ref<PopupList>>, Option<Ref<WindowEffects>>), With<DWayWindow>>[prop.window_entity]->{
     let init = !widget.inited || prop.is_changed();
     if init {
         commands.queue(ConnectCommand::<UiAttachData>::new(this_entity, prop.window_entity));
@@ -214,6 +424,13 @@ WindowUI=>
         }
     }
     if init || surface.is_changed(){ *state.image_mut() = surface.image.clone(); }
+    if init || surface.is_changed() {
+        let source = surface.commited.image_description.clone().unwrap_or_default();
+        let color_transform = ColorTransform::new(&source, &ImageDescription::compositing());
+        if state.color_transform() != &color_transform {
+            *state.color_transform_mut() = color_transform;
+        }
+    }
     if init || index.is_changed() {
         let z = WINDEOW_BASE_ZINDEX + WINDEOW_MAX_STEP * (window_stack.list.len() - index.global) as i32;
         *z_index = GlobalZIndex(z);
@@ -223,31 +440,38 @@ WindowUI=>
             state.set_popup_list(popups.iter().collect());
         }
     }
+    if let Some(effects) = effects{
+        if (init || effects.is_changed()) && state.effects() != &*effects {
+            *state.effects_mut() = effects.clone();
+        }
+    }
 })
 <UiInput @id="content"
     Node=(irect_to_style(*state.rect()))
+    BorderRadius=(BorderRadius::all(Val::Px(state.effects().corner_radius)))
     ZIndex=(ZIndex(4))
     RelativeCursorPosition
+    UiExternalDropTarget=(UiExternalDropTarget{ target: prop.window_entity })
     FocusPolicy=(FocusPolicy::Block)
     @on_event(on_window_ui_input)
 />
-<(irect_to_style(*state.bbox_rect())) @if(!*state.decorated()) @id="without_decorated">
-    <(ImageNode::from(state.image().clone())) @id="image" @style="full" />
-</Node>
-<(irect_to_style(*state.rect())) @if(*state.decorated())
-     @id="with_decorated">
-    <MaterialNode::<RoundedUiRectMaterial> @id="decorated_box"
+<(irect_to_style(*state.rect())) @id="frame">
+    <MaterialNode::<WindowDecorationMaterial> @id="decorated_box" @if(*state.decorated())
         ZIndex=(ZIndex(0))
         UiDrag @on_event(on_decorated_mouse_event)
         @style="absolute left-{-DECORATION_MARGIN} right-{-DECORATION_MARGIN} bottom-{-DECORATION_MARGIN} top-{-DECORATION_HEIGHT}"
-        @handle(RoundedUiRectMaterial=>rounded_rect(color!("#333333"), 16.0)) />
-    <MaterialNode::<RoundedUiImageMaterial> @id="surface" @style="absolute full"
-    @handle(RoundedUiImageMaterial=>rounded_ui_image(
-        14.0,
-        ( state.bbox_rect().min-state.rect().min ).as_vec2() / state.rect().size().as_vec2(),
-        state.bbox_rect().size().as_vec2() / state.rect().size().as_vec2(),
-        state.image().clone())) />
-    <Node @id="title_bar"
+        @handle(WindowDecorationMaterial=>window_decoration_material(state.effects())) />
+    <MaterialNode::<WindowBackdropMaterial> @id="backdrop" @if(state.effects().blur) @style="absolute full"
+        WindowBackdrop=(WindowBackdrop{ corner_radius: state.effects().corner_radius }) />
+    <MaterialNode::<WindowSurfaceMaterial> @id="surface" @style="absolute full"
+    @handle(WindowSurfaceMaterial=>window_surface_material(
+        state.effects(),
+        *state.bbox_rect(),
+        *state.rect(),
+        state.image().clone(),
+        *state.decorated(),
+        *state.color_transform())) />
+    <Node @id="title_bar" @if(*state.decorated())
         UiDrag=(UiDrag{ auto_move: false,..Default::default() }) @on_event(on_title_bar_mouse_event)
         @style="absolute left-0 right-0 top-{-DECORATION_HEIGHT} height-{DECORATION_HEIGHT}" >
         <Node @id="close" @style="m-2 w-20 h-20"
@@ -1,9 +1,23 @@
-use widgets::text::UiTextBundle;
-
 pub use crate::prelude::*;
 
+const MINIMIZE: i64 = 1;
+const MAXIMIZE: i64 = 2;
+const FULLSCREEN: i64 = 3;
+const CLOSE: i64 = 4;
+
+pub fn window_menu_items() -> Vec<MenuItem> {
+    vec![
+        MenuItem::action(MINIMIZE, "minimize"),
+        MenuItem::action(MAXIMIZE, "maximize"),
+        MenuItem::action(FULLSCREEN, "fullscreen"),
+        MenuItem::separator(),
+        MenuItem::action(CLOSE, "close"),
+    ]
+}
+
+/// The menu of the actions of a window.
 #[derive(Component, Reflect)]
-#[require(UiPopup)]
+#[require(UiMenu=UiMenu::new(window_menu_items()))]
 pub struct WindowMenu {
     pub window_entity: Entity,
 }
@@ -16,68 +30,51 @@ impl Default for WindowMenu {
     }
 }
 
-dway_widget! {
-WindowMenu=>
-@plugin{
-    app.register_callback(open_popup);
-    app.register_type::<WindowMenu>();
-}
-@callback{ [UiEvent<UiButtonEvent>]
-    fn on_close_button_event(
-        event: UiEvent<UiButtonEvent>,
-        mut events: MessageWriter<WindowAction>,
-    ) {
-        if event.kind == UiButtonEventKind::Released{
-            events.write(WindowAction::Close(event.receiver()));
-        }
-    }
-}
-@callback{ [UiEvent<UiButtonEvent>]
-    fn on_maximize_button_event(
-        event: UiEvent<UiButtonEvent>,
-        mut events: MessageWriter<WindowAction>,
-    ) {
-        if event.kind == UiButtonEventKind::Released{
-            events.write(WindowAction::Maximize(event.receiver()));
+fn on_window_menu_event(
+    event: On<UiEvent<UiMenuEvent>>,
+    query: Query<&WindowMenu>,
+    mut events: MessageWriter<WindowAction>,
+    mut commands: Commands,
+) {
+    let Ok(menu) = query.get(event.receiver()) else {
+        return;
+    };
+    let window = menu.window_entity;
+    match event.event() {
+        UiMenuEvent::Activated { id, .. } => {
+            let action = match *id {
+                MINIMIZE => WindowAction::Minimize(window),
+                MAXIMIZE => WindowAction::Maximize(window),
+                FULLSCREEN => WindowAction::Fullscreen(window),
+                CLOSE => WindowAction::Close(window),
+                _ => return,
+            };
+            events.write(action);
         }
-    }
-}
-@callback{ [UiEvent<UiButtonEvent>]
-    fn on_minimize_button_event(
-        event: UiEvent<UiButtonEvent>,
-        mut events: MessageWriter<WindowAction>,
-    ) {
-        if event.kind == UiButtonEventKind::Released{
-            events.write(WindowAction::Minimize(event.receiver()));
+        UiMenuEvent::Closed => {
+            commands.entity(event.receiver()).despawn();
         }
+        UiMenuEvent::Opened => {}
     }
 }
-@global(theme: Theme)
-<Node @style="flex-col">
-    <UiButton NoTheme @on_event(on_close_button_event->prop.window_entity) >
-        <(UiTextBundle::new("close", 32, &theme))/>
-    </UiButton>
-    <UiButton NoTheme @on_event(on_maximize_button_event->prop.window_entity) >
-        <(UiTextBundle::new("maximize", 32, &theme))/>
-    </UiButton>
-    <UiButton NoTheme @on_event(on_minimize_button_event->prop.window_entity) >
-        <(UiTextBundle::new("minimize", 32, &theme))/>
-    </UiButton>
-</Node>
-}
 
+/// Open the menu of the window `receiver` below the button `sender`.
 pub fn open_popup(event: UiEvent<UiButtonEvent>, mut commands: Commands) {
     if event.kind == UiButtonEventKind::Released {
-        let style = style!("absolute justify-items:center top-36 align-self:end p-8");
-        commands
-            .spawn((
-                UiPopup::default(),
-                UiTranslationAnimation::default(),
-                AnimationTargetNodeState(style.clone()),
-                ChildOf(event.receiver()),
-            ))
-            .with_children(|c| {
-                c.spawn((WindowMenu::default(), style!("h-auto w-auto")));
-            });
+        let menu = commands
+            .spawn(WindowMenu {
+                window_entity: event.receiver(),
+            })
+            .observe(on_window_menu_event)
+            .id();
+        open_menu(&mut commands, menu, MenuPlacement::Below(event.sender()));
+    }
+}
+
+pub struct WindowMenuPlugin;
+impl Plugin for WindowMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_callback(open_popup);
+        app.register_type::<WindowMenu>();
     }
 }
@@ -0,0 +1,525 @@
+//! Color spaces of surfaces and outputs and the conversion between them, the composition shaders
+//! follow the reference implementation of this module.
+
+use bevy::prelude::*;
+
+use crate::edid::{Chromaticity, HdrStaticMetadata};
+
+const D65: Vec2 = Vec2::new(0.3127, 0.3290);
+const ILLUMINANT_C: Vec2 = Vec2::new(0.310, 0.316);
+
+/// The luminance in cd/m² of the largest value encoded with PQ.
+pub const PQ_PEAK_LUMINANCE: f32 = 10000.0;
+
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+const HLG_A: f32 = 0.17883277;
+const HLG_B: f32 = 0.28466892;
+const HLG_C: f32 = 0.55991073;
+
+/// The Bradford cone response matrix used for chromatic adaptation.
+const BRADFORD: Mat3 = Mat3::from_cols_array(&[
+    0.8951, -0.7502, 0.0389, 0.2664, 1.7135, -0.0685, -0.1614, 0.0367, 1.0296,
+]);
+
+impl Chromaticity {
+    const fn new(red: [f32; 2], green: [f32; 2], blue: [f32; 2], white: Vec2) -> Self {
+        Self {
+            red: Vec2::from_array(red),
+            green: Vec2::from_array(green),
+            blue: Vec2::from_array(blue),
+            white,
+        }
+    }
+
+    /// Whether the color space can be converted, the white point must have a luminance and the
+    /// primaries must not lie on a line.
+    pub fn is_valid(&self) -> bool {
+        let column = |xy: Vec2| Vec3::new(xy.x, xy.y, 1.0);
+        let primaries = Mat3::from_cols(column(self.red), column(self.green), column(self.blue));
+        self.white.y > 0.0 && primaries.determinant().abs() > f32::EPSILON
+    }
+
+    /// The matrix from linear RGB to CIE 1931 XYZ, the white point has a luminance of 1.
+    pub fn rgb_to_xyz(&self) -> Mat3 {
+        let column = |xy: Vec2| Vec3::new(xy.x, xy.y, 1.0 - xy.x - xy.y);
+        let primaries = Mat3::from_cols(column(self.red), column(self.green), column(self.blue));
+        let scale = primaries.inverse() * white_xyz(self.white);
+        primaries * Mat3::from_diagonal(scale)
+    }
+
+    pub fn xyz_to_rgb(&self) -> Mat3 {
+        self.rgb_to_xyz().inverse()
+    }
+}
+
+fn white_xyz(white: Vec2) -> Vec3 {
+    Vec3::new(white.x / white.y, 1.0, (1.0 - white.x - white.y) / white.y)
+}
+
+/// The Bradford adaptation of XYZ colors seen under the white point `from` to `to`.
+pub fn chromatic_adaptation(from: Vec2, to: Vec2) -> Mat3 {
+    if from == to {
+        return Mat3::IDENTITY;
+    }
+    let from_cone = BRADFORD * white_xyz(from);
+    let to_cone = BRADFORD * white_xyz(to);
+    BRADFORD.inverse() * Mat3::from_diagonal(to_cone / from_cone) * BRADFORD
+}
+
+/// The matrix from linear RGB with the primaries `from` to linear RGB with the primaries `to`.
+pub fn conversion_matrix(from: &Chromaticity, to: &Chromaticity) -> Mat3 {
+    if from == to {
+        return Mat3::IDENTITY;
+    }
+    to.xyz_to_rgb() * chromatic_adaptation(from.white, to.white) * from.rgb_to_xyz()
+}
+
+/// The named primaries of the color management protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum NamedPrimaries {
+    /// BT.709, also used by sRGB
+    Srgb,
+    PalM,
+    Pal,
+    Ntsc,
+    GenericFilm,
+    /// BT.2020 and BT.2100
+    Bt2020,
+    Cie1931Xyz,
+    DciP3,
+    DisplayP3,
+    AdobeRgb,
+}
+
+impl NamedPrimaries {
+    pub const ALL: [Self; 10] = [
+        Self::Srgb,
+        Self::PalM,
+        Self::Pal,
+        Self::Ntsc,
+        Self::GenericFilm,
+        Self::Bt2020,
+        Self::Cie1931Xyz,
+        Self::DciP3,
+        Self::DisplayP3,
+        Self::AdobeRgb,
+    ];
+
+    pub const fn chromaticity(self) -> Chromaticity {
+        match self {
+            Self::Srgb => Chromaticity::new([0.64, 0.33], [0.30, 0.60], [0.15, 0.06], D65),
+            Self::PalM => {
+                Chromaticity::new([0.67, 0.33], [0.21, 0.71], [0.14, 0.08], ILLUMINANT_C)
+            }
+            Self::Pal => Chromaticity::new([0.64, 0.33], [0.29, 0.60], [0.15, 0.06], D65),
+            Self::Ntsc => Chromaticity::new([0.630, 0.340], [0.310, 0.595], [0.155, 0.070], D65),
+            Self::GenericFilm => Chromaticity::new(
+                [0.681, 0.319],
+                [0.243, 0.692],
+                [0.145, 0.049],
+                ILLUMINANT_C,
+            ),
+            Self::Bt2020 => Chromaticity::new([0.708, 0.292], [0.170, 0.797], [0.131, 0.046], D65),
+            Self::Cie1931Xyz => Chromaticity::new(
+                [1.0, 0.0],
+                [0.0, 1.0],
+                [0.0, 0.0],
+                Vec2::new(1.0 / 3.0, 1.0 / 3.0),
+            ),
+            Self::DciP3 => Chromaticity::new(
+                [0.680, 0.320],
+                [0.265, 0.690],
+                [0.150, 0.060],
+                Vec2::new(0.314, 0.351),
+            ),
+            Self::DisplayP3 => {
+                Chromaticity::new([0.680, 0.320], [0.265, 0.690], [0.150, 0.060], D65)
+            }
+            Self::AdobeRgb => Chromaticity::new([0.64, 0.33], [0.21, 0.71], [0.15, 0.06], D65),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum Primaries {
+    Named(NamedPrimaries),
+    Custom(Chromaticity),
+}
+
+impl Primaries {
+    pub fn chromaticity(&self) -> Chromaticity {
+        match self {
+            Self::Named(named) => named.chromaticity(),
+            Self::Custom(chromaticity) => *chromaticity,
+        }
+    }
+}
+
+/// The transfer functions from encoded values to linear light, in `0..=1` of the peak luminance.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum TransferFunction {
+    /// The piecewise function of IEC 61966-2-1
+    Srgb,
+    Gamma22,
+    Gamma28,
+    /// BT.1886 with a black level of zero
+    Bt1886,
+    ExtLinear,
+    /// SMPTE ST 2084, the linear value is relative to [`PQ_PEAK_LUMINANCE`]
+    St2084Pq,
+    /// The inverse OETF of BT.2100 HLG, the OOTF of the display is not applied
+    Hlg,
+    /// A pure power function with the exponent
+    Power(f32),
+}
+
+impl TransferFunction {
+    pub fn eotf(self, v: f32) -> f32 {
+        match self {
+            Self::Srgb => {
+                let v = v.max(0.0);
+                if v <= 0.04045 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            Self::ExtLinear => v,
+            Self::St2084Pq => {
+                let p = v.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+                ((p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p)).powf(1.0 / PQ_M1)
+            }
+            Self::Hlg => {
+                let v = v.clamp(0.0, 1.0);
+                if v <= 0.5 {
+                    v * v / 3.0
+                } else {
+                    (((v - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+                }
+            }
+            _ => v.max(0.0).powf(self.exponent()),
+        }
+    }
+
+    pub fn inverse_eotf(self, l: f32) -> f32 {
+        match self {
+            Self::Srgb => {
+                let l = l.max(0.0);
+                if l <= 0.0031308 {
+                    l * 12.92
+                } else {
+                    1.055 * l.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Self::ExtLinear => l,
+            Self::St2084Pq => {
+                let p = l.clamp(0.0, 1.0).powf(PQ_M1);
+                ((PQ_C1 + PQ_C2 * p) / (1.0 + PQ_C3 * p)).powf(PQ_M2)
+            }
+            Self::Hlg => {
+                let l = l.clamp(0.0, 1.0);
+                if l <= 1.0 / 12.0 {
+                    (3.0 * l).sqrt()
+                } else {
+                    HLG_A * (12.0 * l - HLG_B).ln() + HLG_C
+                }
+            }
+            _ => l.max(0.0).powf(1.0 / self.exponent()),
+        }
+    }
+
+    /// The exponent of the power functions.
+    pub fn exponent(self) -> f32 {
+        match self {
+            Self::Gamma22 => 2.2,
+            Self::Gamma28 => 2.8,
+            Self::Bt1886 => 2.4,
+            Self::Power(exponent) => exponent,
+            _ => 1.0,
+        }
+    }
+
+    /// The kind and the exponent passed to the shaders, the kind is 0 for linear, 1 for sRGB, 2
+    /// for power functions, 3 for PQ and 4 for HLG.
+    pub fn shader_params(self) -> Vec2 {
+        let kind = match self {
+            Self::ExtLinear => 0.0,
+            Self::Srgb => 1.0,
+            Self::St2084Pq => 3.0,
+            Self::Hlg => 4.0,
+            _ => 2.0,
+        };
+        Vec2::new(kind, self.exponent())
+    }
+
+    /// The luminances implied by the transfer function.
+    pub fn default_luminances(self) -> Luminances {
+        match self {
+            Self::St2084Pq => Luminances::new(0.005, PQ_PEAK_LUMINANCE, 203.0),
+            Self::Hlg => Luminances::new(0.005, 1000.0, 203.0),
+            Self::Bt1886 => Luminances::new(0.01, 100.0, 100.0),
+            _ => Luminances::new(0.2, 80.0, 80.0),
+        }
+    }
+
+    pub fn is_hdr(self) -> bool {
+        matches!(self, Self::St2084Pq | Self::Hlg)
+    }
+}
+
+/// The luminances of the primary color volume in cd/m².
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct Luminances {
+    pub min: f32,
+    pub max: f32,
+    /// The luminance of the reference white, colors are anchored on it across color spaces
+    pub reference: f32,
+}
+
+impl Luminances {
+    pub const fn new(min: f32, max: f32, reference: f32) -> Self {
+        Self { min, max, reference }
+    }
+}
+
+/// The color properties of the content of a surface or of an output.
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct ImageDescription {
+    pub primaries: Primaries,
+    pub transfer_function: TransferFunction,
+    pub luminances: Luminances,
+    /// The primaries of the mastering display, the content stays inside them
+    pub mastering_primaries: Option<Chromaticity>,
+    /// The minimum and maximum luminance of the mastering display
+    pub mastering_luminance: Option<Vec2>,
+    /// The maximum content light level
+    pub max_cll: Option<f32>,
+    /// The maximum frame-average light level
+    pub max_fall: Option<f32>,
+}
+
+impl Default for ImageDescription {
+    fn default() -> Self {
+        Self::srgb()
+    }
+}
+
+impl ImageDescription {
+    pub fn new(primaries: Primaries, transfer_function: TransferFunction) -> Self {
+        Self {
+            primaries,
+            transfer_function,
+            luminances: transfer_function.default_luminances(),
+            mastering_primaries: None,
+            mastering_luminance: None,
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+
+    /// The content of surfaces without an image description and of SDR outputs, an sRGB display
+    /// decodes with a power of 2.2.
+    pub fn srgb() -> Self {
+        Self::new(
+            Primaries::Named(NamedPrimaries::Srgb),
+            TransferFunction::Gamma22,
+        )
+    }
+
+    /// The image description screens are composited in, the UI draws in sRGB. The composited
+    /// image is converted to the image description of the output in a final pass.
+    pub fn compositing() -> Self {
+        Self::srgb()
+    }
+
+    /// BT.2100 with PQ for HDR outputs, the target luminances are taken from the HDR static
+    /// metadata of the monitor.
+    pub fn bt2100_pq(metadata: Option<&HdrStaticMetadata>) -> Self {
+        let mut description = Self::new(
+            Primaries::Named(NamedPrimaries::Bt2020),
+            TransferFunction::St2084Pq,
+        );
+        if let Some(metadata) = metadata {
+            description.mastering_primaries = Some(NamedPrimaries::Bt2020.chromaticity());
+            description.mastering_luminance = metadata
+                .max_luminance
+                .map(|max| Vec2::new(metadata.min_luminance.unwrap_or(0.0), max));
+            description.max_cll = metadata.max_luminance;
+            description.max_fall = metadata.max_frame_average_luminance;
+        }
+        description
+    }
+
+    pub fn chromaticity(&self) -> Chromaticity {
+        self.primaries.chromaticity()
+    }
+
+    /// The luminance of the linear value 1.
+    pub fn peak_luminance(&self) -> f32 {
+        match self.transfer_function {
+            TransferFunction::St2084Pq => PQ_PEAK_LUMINANCE,
+            _ => self.luminances.max,
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.transfer_function.is_hdr()
+    }
+}
+
+/// The image description an output is driven with, inserted on the output entity.
+#[derive(Component, Clone, Debug, PartialEq, Default, Reflect, Deref)]
+pub struct OutputImageDescription(pub ImageDescription);
+
+/// The conversion of encoded colors from a source image description to a target one.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct ColorTransform {
+    pub source: TransferFunction,
+    /// From linear source colors to linear target colors, scaled so that the reference whites
+    /// match
+    pub matrix: Mat3,
+    pub target: TransferFunction,
+}
+
+impl Default for ColorTransform {
+    fn default() -> Self {
+        Self {
+            source: TransferFunction::Gamma22,
+            matrix: Mat3::IDENTITY,
+            target: TransferFunction::Gamma22,
+        }
+    }
+}
+
+impl ColorTransform {
+    pub fn new(source: &ImageDescription, target: &ImageDescription) -> Self {
+        let scale = source.peak_luminance() / source.luminances.reference
+            * target.luminances.reference
+            / target.peak_luminance();
+        Self {
+            source: source.transfer_function,
+            matrix: conversion_matrix(&source.chromaticity(), &target.chromaticity()) * scale,
+            target: target.transfer_function,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.source == self.target && self.matrix.abs_diff_eq(Mat3::IDENTITY, 1e-5)
+    }
+
+    /// Convert an encoded color, the colors out of the target color volume are clipped.
+    pub fn apply(&self, encoded: Vec3) -> Vec3 {
+        let linear = encoded.map(|v| self.source.eotf(v));
+        let linear = (self.matrix * linear).clamp(Vec3::ZERO, Vec3::ONE);
+        linear.map(|l| self.target.inverse_eotf(l))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_vec3_eq(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 2e-3), "{a} != {b}");
+    }
+
+    #[test]
+    fn test_transfer_functions() {
+        let functions = [
+            TransferFunction::Srgb,
+            TransferFunction::Gamma22,
+            TransferFunction::Gamma28,
+            TransferFunction::Bt1886,
+            TransferFunction::ExtLinear,
+            TransferFunction::St2084Pq,
+            TransferFunction::Hlg,
+            TransferFunction::Power(1.8),
+        ];
+        for tf in functions {
+            assert!(tf.eotf(0.0).abs() < 1e-6, "{tf:?}");
+            assert!((tf.eotf(1.0) - 1.0).abs() < 1e-4, "{tf:?}");
+            for i in 0..=20 {
+                let v = i as f32 / 20.0;
+                assert!((tf.inverse_eotf(tf.eotf(v)) - v).abs() < 1e-3, "{tf:?} {v}");
+            }
+        }
+        assert!((TransferFunction::Srgb.eotf(0.5) - 0.2140).abs() < 1e-4);
+        // the reference white of BT.2408 and 100 cd/m²
+        let pq = TransferFunction::St2084Pq;
+        assert!((pq.inverse_eotf(203.0 / PQ_PEAK_LUMINANCE) - 0.5807).abs() < 1e-3);
+        assert!((pq.inverse_eotf(100.0 / PQ_PEAK_LUMINANCE) - 0.5081).abs() < 1e-3);
+        assert!((TransferFunction::Hlg.inverse_eotf(1.0 / 12.0) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_conversion_matrix() {
+        let srgb = NamedPrimaries::Srgb.chromaticity();
+        let bt2020 = NamedPrimaries::Bt2020.chromaticity();
+        assert_vec3_eq(srgb.rgb_to_xyz() * Vec3::X, Vec3::new(0.4124, 0.2126, 0.0193));
+        assert_vec3_eq(srgb.rgb_to_xyz() * Vec3::ONE, white_xyz(D65));
+
+        let to_bt2020 = conversion_matrix(&srgb, &bt2020);
+        assert_vec3_eq(to_bt2020 * Vec3::X, Vec3::new(0.6274, 0.0691, 0.0164));
+        assert_vec3_eq(to_bt2020 * Vec3::Y, Vec3::new(0.3293, 0.9195, 0.0880));
+        assert_vec3_eq(
+            conversion_matrix(&bt2020, &srgb) * to_bt2020 * Vec3::new(0.2, 0.5, 0.8),
+            Vec3::new(0.2, 0.5, 0.8),
+        );
+        assert_eq!(conversion_matrix(&srgb, &srgb), Mat3::IDENTITY);
+
+        // white stays white across white points
+        for primaries in NamedPrimaries::ALL {
+            let matrix = conversion_matrix(&primaries.chromaticity(), &srgb);
+            assert_vec3_eq(matrix * Vec3::ONE, Vec3::ONE);
+            assert!(primaries.chromaticity().is_valid());
+        }
+
+        let no_white = Chromaticity {
+            white: Vec2::new(0.3, 0.0),
+            ..srgb
+        };
+        assert!(!no_white.is_valid());
+        let colinear = Chromaticity {
+            blue: (srgb.red + srgb.green) / 2.0,
+            ..srgb
+        };
+        assert!(!colinear.is_valid());
+    }
+
+    #[test]
+    fn test_color_transform() {
+        let srgb = ImageDescription::srgb();
+        let hdr = ImageDescription::bt2100_pq(None);
+        let identity = ColorTransform::new(&srgb, &srgb);
+        assert!(identity.is_identity());
+        assert_eq!(identity, ColorTransform::default());
+        assert_vec3_eq(
+            identity.apply(Vec3::new(0.1, 0.5, 0.9)),
+            Vec3::new(0.1, 0.5, 0.9),
+        );
+
+        // the sdr white is shown at the reference white of the hdr output
+        let to_hdr = ColorTransform::new(&srgb, &hdr);
+        assert!(!to_hdr.is_identity());
+        assert_vec3_eq(to_hdr.apply(Vec3::ONE), Vec3::splat(0.5807));
+        assert_vec3_eq(to_hdr.apply(Vec3::ZERO), Vec3::ZERO);
+
+        // and back, the highlights above the reference white are clipped
+        let to_sdr = ColorTransform::new(&hdr, &srgb);
+        assert_vec3_eq(to_sdr.apply(Vec3::splat(0.5807)), Vec3::ONE);
+        assert_vec3_eq(to_sdr.apply(Vec3::splat(0.9)), Vec3::ONE);
+        assert_vec3_eq(
+            to_sdr.apply(to_hdr.apply(Vec3::new(0.2, 0.5, 0.8))),
+            Vec3::new(0.2, 0.5, 0.8),
+        );
+
+        // a saturated sRGB red is inside BT.2020
+        let red = to_hdr.apply(Vec3::X);
+        assert!(red.x > red.y && red.y > 0.0 && red.z > 0.0);
+    }
+}
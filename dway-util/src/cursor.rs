@@ -0,0 +1,158 @@
+//! The cursor image shown by the cursor plane of outputs.
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::color::ImageDescription;
+
+/// A cursor image in ARGB8888 with premultiplied alpha, the layout cursor planes take.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CursorImage {
+    /// The size in pixels
+    pub size: IVec2,
+    /// The pixels, `size.x * 4` bytes per row
+    pub data: Vec<u8>,
+    /// The size in logical pixels
+    pub logical_size: Vec2,
+    /// The position of the pointer in the image, in logical pixels
+    pub hotspot: Vec2,
+}
+
+impl CursorImage {
+    /// Convert an image in RGBA8 with straight alpha.
+    pub fn from_rgba(size: IVec2, data: &[u8], logical_size: Vec2, hotspot: Vec2) -> Option<Self> {
+        if size.x <= 0 || size.y <= 0 || data.len() < (size.x * size.y * 4) as usize {
+            return None;
+        }
+        let data = data[..(size.x * size.y * 4) as usize]
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                let premultiply = |c: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+                [premultiply(b), premultiply(g), premultiply(r), a]
+            })
+            .collect();
+        Some(Self {
+            size,
+            data,
+            logical_size,
+            hotspot,
+        })
+    }
+
+    /// Copy an image in the `argb8888` or `xrgb8888` layout of `wl_shm`, which is premultiplied.
+    pub fn from_argb8888(
+        size: IVec2,
+        stride: usize,
+        data: &[u8],
+        opaque: bool,
+        logical_size: Vec2,
+        hotspot: Vec2,
+    ) -> Option<Self> {
+        let row = size.x as usize * 4;
+        if size.x <= 0 || size.y <= 0 || stride < row || data.len() < stride * size.y as usize {
+            return None;
+        }
+        let mut pixels = Vec::with_capacity(row * size.y as usize);
+        for y in 0..size.y as usize {
+            pixels.extend_from_slice(&data[y * stride..y * stride + row]);
+        }
+        if opaque {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+        }
+        Some(Self {
+            size,
+            data: pixels,
+            logical_size,
+            hotspot,
+        })
+    }
+
+    /// Resize the image with the nearest pixels.
+    pub fn scaled(&self, size: IVec2) -> Self {
+        if size == self.size {
+            return self.clone();
+        }
+        let mut data = Vec::with_capacity((size.x * size.y * 4).max(0) as usize);
+        for y in 0..size.y {
+            let src_y = (y * self.size.y / size.y).min(self.size.y - 1);
+            for x in 0..size.x {
+                let src_x = (x * self.size.x / size.x).min(self.size.x - 1);
+                let offset = ((src_y * self.size.x + src_x) * 4) as usize;
+                data.extend_from_slice(&self.data[offset..offset + 4]);
+            }
+        }
+        Self {
+            size,
+            data,
+            logical_size: self.logical_size,
+            hotspot: self.hotspot,
+        }
+    }
+}
+
+/// The cursor image to show on cursor planes, set by the compositor.
+///
+/// It is `None` when the pixels of the cursor are not available on the cpu, then the cursor is
+/// drawn by the compositor.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct HardwareCursor {
+    pub image: Option<Arc<CursorImage>>,
+    /// The colors of the image, cursor planes only show it on outputs driven with them
+    pub image_description: ImageDescription,
+}
+
+/// Whether the cursor is shown by the cursor plane of the output under it, set by the backend.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct HardwareCursorState {
+    pub active: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_rgba() {
+        let data = [255, 0, 0, 255, 0, 255, 0, 128];
+        let image =
+            CursorImage::from_rgba(IVec2::new(2, 1), &data, Vec2::new(2.0, 1.0), Vec2::ZERO)
+                .unwrap();
+        assert_eq!(image.data, vec![0, 0, 255, 255, 0, 128, 0, 128]);
+        assert!(
+            CursorImage::from_rgba(IVec2::new(2, 2), &data, Vec2::ONE, Vec2::ZERO).is_none()
+        );
+    }
+
+    #[test]
+    fn test_from_argb8888() {
+        let data = [1, 2, 3, 4, 9, 9, 9, 9, 5, 6, 7, 8, 9, 9, 9, 9];
+        let image = CursorImage::from_argb8888(
+            IVec2::new(1, 2),
+            8,
+            &data,
+            true,
+            Vec2::new(1.0, 2.0),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        assert_eq!(image.data, vec![1, 2, 3, 255, 5, 6, 7, 255]);
+    }
+
+    #[test]
+    fn test_scaled() {
+        let image = CursorImage {
+            size: IVec2::new(2, 1),
+            data: vec![1, 1, 1, 1, 2, 2, 2, 2],
+            logical_size: Vec2::new(2.0, 1.0),
+            hotspot: Vec2::ZERO,
+        };
+        let scaled = image.scaled(IVec2::new(4, 2));
+        assert_eq!(scaled.size, IVec2::new(4, 2));
+        assert_eq!(
+            scaled.data.chunks_exact(4).map(|p| p[0]).collect::<Vec<_>>(),
+            vec![1, 1, 2, 2, 1, 1, 2, 2]
+        );
+    }
+}
@@ -0,0 +1,644 @@
+//! A parser of EDID blobs, the base block and the CTA-861 extension are supported.
+
+use bevy::prelude::*;
+
+use crate::output::{OutputIdentity, OutputMode};
+
+pub const EDID_BLOCK_SIZE: usize = 128;
+const EDID_HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const CTA_EXTENSION_TAG: u8 = 0x02;
+const AMD_OUI: u32 = 0x00001a;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum EdidError {
+    #[error("the edid has {0} bytes, less than a block")]
+    TooShort(usize),
+    #[error("invalid edid header")]
+    InvalidHeader,
+    #[error("invalid checksum of the base block")]
+    InvalidChecksum,
+}
+
+/// A mode listed by the monitor, the refresh rate is in mHz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Reflect)]
+pub struct EdidMode {
+    pub width: i32,
+    pub height: i32,
+    pub refresh: u32,
+    pub interlaced: bool,
+}
+
+impl EdidMode {
+    pub fn new(width: i32, height: i32, refresh: u32) -> Self {
+        Self {
+            width,
+            height,
+            refresh,
+            interlaced: false,
+        }
+    }
+
+    pub fn output_mode(&self) -> OutputMode {
+        OutputMode::new(self.width, self.height, self.refresh)
+    }
+}
+
+/// The CIE 1931 xy coordinates of the primaries and the white point.
+#[derive(Clone, Copy, Debug, PartialEq, Default, Reflect)]
+pub struct Chromaticity {
+    pub red: Vec2,
+    pub green: Vec2,
+    pub blue: Vec2,
+    pub white: Vec2,
+}
+
+/// The HDR static metadata data block of the CTA-861 extension.
+#[derive(Clone, Debug, PartialEq, Default, Reflect)]
+pub struct HdrStaticMetadata {
+    pub traditional_sdr: bool,
+    pub traditional_hdr: bool,
+    /// SMPTE ST 2084, also known as PQ
+    pub pq: bool,
+    pub hlg: bool,
+    /// The desired content luminance in cd/m²
+    pub max_luminance: Option<f32>,
+    pub max_frame_average_luminance: Option<f32>,
+    pub min_luminance: Option<f32>,
+}
+
+/// The colorimetry data block of the CTA-861 extension, the wide gamut signals the monitor
+/// accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub struct Colorimetry {
+    pub bt2020_rgb: bool,
+    pub bt2020_ycc: bool,
+    pub dci_p3: bool,
+}
+
+/// The range of the refresh rate in Hz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub struct VrrRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// The monitor information read from the EDID, inserted on the output entity.
+#[derive(Component, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct Edid {
+    pub version: u8,
+    pub revision: u8,
+    /// The three letters PNP id of the manufacturer
+    pub manufacturer: String,
+    pub product_code: u16,
+    pub serial_number: u32,
+    pub manufacture_year: Option<u16>,
+    pub name: Option<String>,
+    pub serial: Option<String>,
+    /// The physical size in millimeters
+    pub physical_size: IVec2,
+    pub chromaticity: Option<Chromaticity>,
+    pub modes: Vec<EdidMode>,
+    pub preferred_mode: Option<usize>,
+    pub hdr: Option<HdrStaticMetadata>,
+    pub colorimetry: Option<Colorimetry>,
+    pub vrr: Option<VrrRange>,
+}
+
+impl Edid {
+    /// Parse an EDID, the extension blocks with an invalid checksum are ignored.
+    pub fn parse(data: &[u8]) -> Result<Self, EdidError> {
+        if data.len() < EDID_BLOCK_SIZE {
+            return Err(EdidError::TooShort(data.len()));
+        }
+        if data[..8] != EDID_HEADER {
+            return Err(EdidError::InvalidHeader);
+        }
+        let base = &data[..EDID_BLOCK_SIZE];
+        if !checksum_valid(base) {
+            return Err(EdidError::InvalidChecksum);
+        }
+
+        let mut edid = Self {
+            version: base[18],
+            revision: base[19],
+            manufacturer: pnp_id(u16::from_be_bytes([base[8], base[9]])),
+            product_code: u16::from_le_bytes([base[10], base[11]]),
+            serial_number: u32::from_le_bytes([base[12], base[13], base[14], base[15]]),
+            manufacture_year: (base[17] != 0 && base[17] != 0xff).then(|| 1990 + base[17] as u16),
+            physical_size: IVec2::new(base[21] as i32, base[22] as i32) * 10,
+            chromaticity: parse_chromaticity(&base[25..35]),
+            ..Default::default()
+        };
+        let continuous_frequency = edid.is_at_least(1, 4) && base[24] & 0x01 != 0;
+        let mut range_limits = None;
+
+        for (index, descriptor) in base[54..126].chunks_exact(18).enumerate() {
+            if let Some((mode, image_size)) = parse_detailed_timing(descriptor) {
+                if index == 0 {
+                    edid.preferred_mode = Some(edid.modes.len());
+                    if image_size.x > 0 && image_size.y > 0 {
+                        edid.physical_size = image_size;
+                    }
+                }
+                edid.push_mode(mode);
+                continue;
+            }
+            match descriptor[3] {
+                0xfc => edid.name = descriptor_text(descriptor),
+                0xff => edid.serial = descriptor_text(descriptor),
+                0xfd => range_limits = parse_range_limits(descriptor),
+                _ => {}
+            }
+        }
+
+        for mode in established_timings(&base[35..38]) {
+            edid.push_mode(mode);
+        }
+        for timing in base[38..54].chunks_exact(2) {
+            if let Some(mode) = edid.parse_standard_timing(timing) {
+                edid.push_mode(mode);
+            }
+        }
+
+        let extensions = base[126] as usize;
+        for block in data[EDID_BLOCK_SIZE..]
+            .chunks_exact(EDID_BLOCK_SIZE)
+            .take(extensions)
+        {
+            if !checksum_valid(block) {
+                warn!("ignore an edid extension block with an invalid checksum");
+                continue;
+            }
+            if block[0] == CTA_EXTENSION_TAG {
+                edid.parse_cta_extension(block);
+            }
+        }
+
+        if edid.vrr.is_none() && continuous_frequency {
+            edid.vrr = range_limits.filter(|range| range.min < range.max);
+        }
+        Ok(edid)
+    }
+
+    pub fn is_at_least(&self, version: u8, revision: u8) -> bool {
+        (self.version, self.revision) >= (version, revision)
+    }
+
+    pub fn preferred(&self) -> Option<&EdidMode> {
+        self.preferred_mode.and_then(|i| self.modes.get(i))
+    }
+
+    /// The name of the manufacturer, or its PNP id if it is not well known.
+    pub fn vendor(&self) -> String {
+        pnp_vendor_name(&self.manufacturer)
+            .map(str::to_string)
+            .unwrap_or_else(|| self.manufacturer.clone())
+    }
+
+    /// The monitor name, or the product code if the monitor has no name.
+    pub fn model(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("0x{:04X}", self.product_code))
+    }
+
+    pub fn serial(&self) -> String {
+        match &self.serial {
+            Some(serial) => serial.clone(),
+            None if self.serial_number != 0 => self.serial_number.to_string(),
+            None => String::new(),
+        }
+    }
+
+    pub fn identity(&self, connector: &str) -> OutputIdentity {
+        OutputIdentity {
+            connector: connector.to_string(),
+            make: self.manufacturer.clone(),
+            vendor: self.vendor(),
+            model: self.model(),
+            serial: self.serial(),
+        }
+    }
+
+    fn push_mode(&mut self, mode: EdidMode) {
+        if !self.modes.contains(&mode) {
+            self.modes.push(mode);
+        }
+    }
+
+    fn parse_standard_timing(&self, timing: &[u8]) -> Option<EdidMode> {
+        if timing == [0x01, 0x01] || timing[0] == 0 {
+            return None;
+        }
+        let width = (timing[0] as i32 + 31) * 8;
+        let height = match timing[1] >> 6 {
+            0 if self.is_at_least(1, 3) => width * 10 / 16,
+            0 => width,
+            1 => width * 3 / 4,
+            2 => width * 4 / 5,
+            _ => width * 9 / 16,
+        };
+        let refresh = (timing[1] & 0x3f) as u32 + 60;
+        Some(EdidMode::new(width, height, refresh * 1000))
+    }
+
+    fn parse_cta_extension(&mut self, block: &[u8]) {
+        let dtd_offset = (block[2] as usize).min(127);
+        if dtd_offset >= 4 {
+            let mut offset = 4;
+            while offset < dtd_offset {
+                let tag = block[offset] >> 5;
+                let length = (block[offset] & 0x1f) as usize;
+                let Some(payload) = block.get(offset + 1..offset + 1 + length) else {
+                    break;
+                };
+                if offset + 1 + length > dtd_offset {
+                    break;
+                }
+                self.parse_cta_data_block(tag, payload);
+                offset += 1 + length;
+            }
+            for descriptor in block[dtd_offset..127].chunks_exact(18) {
+                let Some((mode, _)) = parse_detailed_timing(descriptor) else {
+                    break;
+                };
+                self.push_mode(mode);
+            }
+        }
+    }
+
+    fn parse_cta_data_block(&mut self, tag: u8, payload: &[u8]) {
+        match tag {
+            // video data block
+            2 => {
+                for svd in payload {
+                    let vic = if (129..=192).contains(svd) {
+                        svd & 0x7f
+                    } else {
+                        *svd
+                    };
+                    if let Some(mode) = vic_mode(vic) {
+                        self.push_mode(mode);
+                    }
+                }
+            }
+            // vendor specific data block
+            3 if payload.len() >= 7 => {
+                let oui = u32::from_le_bytes([payload[0], payload[1], payload[2], 0]);
+                if oui == AMD_OUI && payload[5] > 0 && payload[5] < payload[6] {
+                    self.vrr = Some(VrrRange {
+                        min: payload[5] as u32,
+                        max: payload[6] as u32,
+                    });
+                }
+            }
+            // extended tag: colorimetry data block
+            7 if payload.len() >= 3 && payload[0] == 5 => {
+                self.colorimetry = Some(Colorimetry {
+                    bt2020_rgb: payload[1] & 0x80 != 0,
+                    bt2020_ycc: payload[1] & 0x40 != 0,
+                    dci_p3: payload[2] & 0x80 != 0,
+                });
+            }
+            // extended tag: hdr static metadata data block
+            7 if payload.len() >= 3 && payload[0] == 6 => {
+                let luminance = |index: usize| payload.get(index).filter(|v| **v != 0);
+                let max_luminance = luminance(3).map(|cv| 50.0 * 2.0f32.powf(*cv as f32 / 32.0));
+                let max_frame_average_luminance =
+                    luminance(4).map(|cv| 50.0 * 2.0f32.powf(*cv as f32 / 32.0));
+                let min_luminance = luminance(5)
+                    .zip(max_luminance)
+                    .map(|(cv, max)| max * (*cv as f32 / 255.0).powi(2) / 100.0);
+                self.hdr = Some(HdrStaticMetadata {
+                    traditional_sdr: payload[1] & 0x01 != 0,
+                    traditional_hdr: payload[1] & 0x02 != 0,
+                    pq: payload[1] & 0x04 != 0,
+                    hlg: payload[1] & 0x08 != 0,
+                    max_luminance,
+                    max_frame_average_luminance,
+                    min_luminance,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn checksum_valid(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+fn pnp_id(value: u16) -> String {
+    [10, 5, 0]
+        .iter()
+        .map(|shift| (b'A' - 1 + ((value >> shift) & 0x1f) as u8) as char)
+        .collect()
+}
+
+/// The names of the common manufacturers, other ids are reported as is.
+pub fn pnp_vendor_name(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "ACI" => "Ancor Communications Inc",
+        "ACR" => "Acer Technologies",
+        "AOC" => "AOC",
+        "AUO" => "AU Optronics",
+        "BNQ" => "BenQ Corporation",
+        "BOE" => "BOE",
+        "CMN" => "Chimei Innolux Corporation",
+        "DEL" => "Dell Inc.",
+        "ENC" => "Eizo Nanao Corporation",
+        "GSM" => "LG Electronics",
+        "HWP" => "HP Inc.",
+        "IVM" => "Iiyama North America",
+        "LEN" => "Lenovo Group Limited",
+        "LGD" => "LG Display",
+        "MSI" => "Microstep",
+        "NEC" => "NEC Corporation",
+        "PHL" => "Philips Consumer Electronics Company",
+        "SAM" => "Samsung Electric Company",
+        "SDC" => "Samsung Display Corp",
+        "SHP" => "Sharp Corporation",
+        "SNY" => "Sony",
+        "VSC" => "ViewSonic Corporation",
+        _ => return None,
+    })
+}
+
+fn parse_chromaticity(bytes: &[u8]) -> Option<Chromaticity> {
+    let coordinate = |high: u8, low_byte: u8, shift: u8| {
+        (((high as u16) << 2) | ((low_byte >> shift) & 0x03) as u16) as f32 / 1024.0
+    };
+    let point = |x: usize, y: usize, low: usize, shift: u8| {
+        Vec2::new(
+            coordinate(bytes[x], bytes[low], shift + 2),
+            coordinate(bytes[y], bytes[low], shift),
+        )
+    };
+    let chromaticity = Chromaticity {
+        red: point(2, 3, 0, 4),
+        green: point(4, 5, 0, 0),
+        blue: point(6, 7, 1, 4),
+        white: point(8, 9, 1, 0),
+    };
+    (chromaticity.white != Vec2::ZERO).then_some(chromaticity)
+}
+
+/// Parse a detailed timing descriptor, returns the mode and the image size in millimeters.
+fn parse_detailed_timing(descriptor: &[u8]) -> Option<(EdidMode, IVec2)> {
+    let clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as u64;
+    if clock == 0 {
+        return None;
+    }
+    let high = |byte: u8, shift: u8| ((byte >> shift) & 0x0f) as i32;
+    let hactive = descriptor[2] as i32 | high(descriptor[4], 4) << 8;
+    let hblank = descriptor[3] as i32 | high(descriptor[4], 0) << 8;
+    let vactive = descriptor[5] as i32 | high(descriptor[7], 4) << 8;
+    let vblank = descriptor[6] as i32 | high(descriptor[7], 0) << 8;
+    let image_size = IVec2::new(
+        descriptor[12] as i32 | high(descriptor[14], 4) << 8,
+        descriptor[13] as i32 | high(descriptor[14], 0) << 8,
+    );
+    let interlaced = descriptor[17] & 0x80 != 0;
+
+    let total = ((hactive + hblank) * (vactive + vblank)) as u64;
+    if total == 0 {
+        return None;
+    }
+    // the pixel clock is in 10 kHz
+    let refresh = (clock * 10_000_000 / total) as u32;
+    let mode = EdidMode {
+        width: hactive,
+        height: if interlaced { vactive * 2 } else { vactive },
+        refresh,
+        interlaced,
+    };
+    Some((mode, image_size))
+}
+
+fn descriptor_text(descriptor: &[u8]) -> Option<String> {
+    let text = &descriptor[5..18];
+    let end = text.iter().position(|c| *c == b'\n').unwrap_or(text.len());
+    let text = String::from_utf8_lossy(&text[..end]).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn parse_range_limits(descriptor: &[u8]) -> Option<VrrRange> {
+    let flags = descriptor[4];
+    let min_offset = if flags & 0x03 == 0x03 { 255 } else { 0 };
+    let max_offset = if flags & 0x02 != 0 { 255 } else { 0 };
+    let min = descriptor[5] as u32 + min_offset;
+    let max = descriptor[6] as u32 + max_offset;
+    (min > 0 && max > 0).then_some(VrrRange { min, max })
+}
+
+fn established_timings(bytes: &[u8]) -> impl Iterator<Item = EdidMode> + '_ {
+    const TIMINGS: [(i32, i32, u32); 17] = [
+        (800, 600, 60),
+        (800, 600, 56),
+        (640, 480, 75),
+        (640, 480, 72),
+        (640, 480, 67),
+        (640, 480, 60),
+        (720, 400, 88),
+        (720, 400, 70),
+        (1280, 1024, 75),
+        (1024, 768, 75),
+        (1024, 768, 70),
+        (1024, 768, 60),
+        (1024, 768, 87),
+        (832, 624, 75),
+        (800, 600, 75),
+        (800, 600, 72),
+        (1152, 870, 75),
+    ];
+    let bits = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    TIMINGS
+        .iter()
+        .enumerate()
+        .filter(move |(index, _)| {
+            let bit = if *index < 16 { *index } else { 23 };
+            bits & (1 << bit) != 0
+        })
+        .map(|(index, (width, height, refresh))| EdidMode {
+            width: *width,
+            height: *height,
+            refresh: refresh * 1000,
+            // 1024x768@87 is interlaced
+            interlaced: index == 12,
+        })
+}
+
+/// The modes of the common video identification codes of CTA-861.
+fn vic_mode(vic: u8) -> Option<EdidMode> {
+    let (width, height, refresh) = match vic {
+        1 => (640, 480, 60),
+        2 | 3 => (720, 480, 60),
+        4 => (1280, 720, 60),
+        16 => (1920, 1080, 60),
+        17 | 18 => (720, 576, 50),
+        19 => (1280, 720, 50),
+        31 => (1920, 1080, 50),
+        32 => (1920, 1080, 24),
+        33 => (1920, 1080, 25),
+        34 => (1920, 1080, 30),
+        63 => (1920, 1080, 120),
+        64 => (1920, 1080, 100),
+        93 => (3840, 2160, 24),
+        94 => (3840, 2160, 25),
+        95 => (3840, 2160, 30),
+        96 => (3840, 2160, 50),
+        97 => (3840, 2160, 60),
+        _ => return None,
+    };
+    Some(EdidMode::new(width, height, refresh * 1000))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DELL_U2720Q: &[u8] = include_bytes!("testdata/dell-u2720q.bin");
+    const BENQ_GW2480: &[u8] = include_bytes!("testdata/benq-gw2480.bin");
+    const BOE_EDP: &[u8] = include_bytes!("testdata/boe-edp-panel.bin");
+    const LG_FREESYNC: &[u8] = include_bytes!("testdata/lg-27gl850-freesync.bin");
+    const SONY_TV: &[u8] = include_bytes!("testdata/sony-tv-interlaced.bin");
+
+    #[test]
+    fn test_parse_4k_hdr_monitor() {
+        let edid = Edid::parse(DELL_U2720Q).unwrap();
+        assert_eq!(edid.manufacturer, "DEL");
+        assert_eq!(edid.vendor(), "Dell Inc.");
+        assert_eq!(edid.model(), "DELL U2720Q");
+        // configurations match on the PNP id, the vendor name is only shown
+        let identity = edid.identity("DP-1");
+        assert_eq!(identity.make, "DEL");
+        assert_eq!(identity.vendor, "Dell Inc.");
+        assert_eq!(edid.serial(), "7RVK123");
+        assert_eq!((edid.version, edid.revision), (1, 4));
+        assert_eq!(edid.manufacture_year, Some(2021));
+        assert_eq!(edid.physical_size, IVec2::new(597, 336));
+        assert_eq!(edid.preferred(), Some(&EdidMode::new(3840, 2160, 59996)));
+        assert!(edid.modes.contains(&EdidMode::new(1920, 1080, 60000)));
+        assert!(edid.modes.contains(&EdidMode::new(3840, 2160, 30000)));
+        let hdr = edid.hdr.as_ref().unwrap();
+        assert!(hdr.traditional_sdr && hdr.pq && !hdr.hlg);
+        assert!((hdr.max_luminance.unwrap() - 400.0).abs() < 1.0);
+        assert!(hdr.min_luminance.unwrap() < 1.0);
+        assert_eq!(edid.vrr, None);
+        assert_eq!(edid.colorimetry, None);
+
+        let chromaticity = edid.chromaticity.unwrap();
+        assert!((chromaticity.white - Vec2::new(0.3125, 0.3291)).length() < 0.002);
+        assert!((chromaticity.red - Vec2::new(0.68, 0.32)).length() < 0.002);
+    }
+
+    #[test]
+    fn test_parse_colorimetry() {
+        let mut edid = Edid::parse(DELL_U2720Q).unwrap();
+        edid.parse_cta_data_block(7, &[5, 0xc0, 0x80]);
+        assert_eq!(
+            edid.colorimetry,
+            Some(Colorimetry {
+                bt2020_rgb: true,
+                bt2020_ycc: true,
+                dci_p3: true,
+            })
+        );
+        edid.parse_cta_data_block(7, &[5, 0x03, 0x00]);
+        assert!(!edid.colorimetry.unwrap().bt2020_rgb);
+    }
+
+    #[test]
+    fn test_parse_base_block_only() {
+        let edid = Edid::parse(BENQ_GW2480).unwrap();
+        assert_eq!(edid.vendor(), "BenQ Corporation");
+        assert_eq!(edid.model(), "BenQ GW2480");
+        assert_eq!(edid.serial(), "ET1234567");
+        assert_eq!((edid.version, edid.revision), (1, 3));
+        assert_eq!(edid.preferred(), Some(&EdidMode::new(1920, 1080, 60000)));
+        // established and standard timings
+        assert!(edid.modes.contains(&EdidMode::new(1024, 768, 60000)));
+        assert!(edid.modes.contains(&EdidMode::new(1680, 1050, 60000)));
+        assert!(edid.modes.contains(&EdidMode::new(1280, 1024, 75000)));
+        assert_eq!(edid.hdr, None);
+        // the range limits of a monitor without continuous frequency are not a vrr range
+        assert_eq!(edid.vrr, None);
+    }
+
+    #[test]
+    fn test_parse_laptop_panel() {
+        let edid = Edid::parse(BOE_EDP).unwrap();
+        assert_eq!(edid.manufacturer, "BOE");
+        assert_eq!(edid.name, None);
+        assert_eq!(edid.model(), "0x0AF9");
+        assert_eq!(edid.serial(), "");
+        assert_eq!(edid.physical_size, IVec2::new(309, 174));
+        assert_eq!(edid.modes.len(), 2);
+        assert_eq!(edid.preferred(), Some(&EdidMode::new(1920, 1080, 59993)));
+        // 1.4 with continuous frequency
+        assert_eq!(edid.vrr, Some(VrrRange { min: 48, max: 60 }));
+        assert_eq!(
+            edid.identity("eDP-1"),
+            OutputIdentity {
+                connector: "eDP-1".to_string(),
+                make: "BOE".to_string(),
+                vendor: "BOE".to_string(),
+                model: "0x0AF9".to_string(),
+                serial: "".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_freesync_monitor() {
+        let edid = Edid::parse(LG_FREESYNC).unwrap();
+        assert_eq!(edid.vendor(), "LG Electronics");
+        assert_eq!(edid.model(), "LG ULTRAGEAR");
+        assert_eq!(edid.serial(), "123456");
+        assert_eq!(edid.vrr, Some(VrrRange { min: 48, max: 144 }));
+        assert!(edid.modes.contains(&EdidMode::new(2560, 1440, 144005)));
+    }
+
+    #[test]
+    fn test_parse_interlaced_mode() {
+        let edid = Edid::parse(SONY_TV).unwrap();
+        assert_eq!(edid.vendor(), "Sony");
+        assert_eq!(edid.model(), "SONY TV");
+        let preferred = edid.preferred().unwrap();
+        assert!(preferred.interlaced);
+        assert_eq!((preferred.width, preferred.height), (1920, 1080));
+        assert_eq!(preferred.refresh / 1000, 60);
+        let hdr = edid.hdr.as_ref().unwrap();
+        assert!(hdr.hlg && hdr.pq);
+        assert_eq!(hdr.max_luminance, None);
+    }
+
+    #[test]
+    fn test_invalid_edid() {
+        assert_eq!(
+            Edid::parse(&DELL_U2720Q[..100]),
+            Err(EdidError::TooShort(100))
+        );
+
+        let mut data = BENQ_GW2480.to_vec();
+        data[0] = 0x01;
+        assert_eq!(Edid::parse(&data), Err(EdidError::InvalidHeader));
+
+        let mut data = BENQ_GW2480.to_vec();
+        data[20] ^= 0xff;
+        assert_eq!(Edid::parse(&data), Err(EdidError::InvalidChecksum));
+
+        // a broken extension is ignored
+        let mut data = DELL_U2720Q.to_vec();
+        data[200] ^= 0xff;
+        let edid = Edid::parse(&data).unwrap();
+        assert_eq!(edid.model(), "DELL U2720Q");
+        assert_eq!(edid.hdr, None);
+
+        // the extension count is larger than the data
+        let mut data = BENQ_GW2480.to_vec();
+        data[126] = 1;
+        data[127] = data[127].wrapping_sub(1);
+        assert!(Edid::parse(&data).is_ok());
+    }
+}
@@ -0,0 +1,171 @@
+//! Gamma lookup tables of outputs, set by gamma control clients or by the night light.
+
+use bevy::prelude::*;
+
+/// The color temperature in kelvin which leaves the colors unchanged.
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+
+/// A gamma lookup table, each ramp maps the encoded value of a channel to the value sent to the
+/// monitor.
+#[derive(Clone, Debug, PartialEq, Eq, Reflect)]
+pub struct GammaLut {
+    pub red: Vec<u16>,
+    pub green: Vec<u16>,
+    pub blue: Vec<u16>,
+}
+
+impl GammaLut {
+    pub fn identity(size: usize) -> Self {
+        Self::from_fn(size, |_, x| x)
+    }
+
+    /// Scale the channels by the white point of the color temperature.
+    pub fn from_temperature(size: usize, temperature: u32) -> Self {
+        let white = whitepoint(temperature);
+        Self::from_fn(size, |channel, x| x * white[channel])
+    }
+
+    /// Build the ramps from a function of the channel index and the value in `0..=1`.
+    pub fn from_fn(size: usize, f: impl Fn(usize, f32) -> f32) -> Self {
+        let ramp = |channel| {
+            (0..size)
+                .map(|i| {
+                    let x = if size > 1 {
+                        i as f32 / (size - 1) as f32
+                    } else {
+                        1.0
+                    };
+                    (f(channel, x).clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+                })
+                .collect()
+        };
+        Self {
+            red: ramp(0),
+            green: ramp(1),
+            blue: ramp(2),
+        }
+    }
+
+    /// Parse the successive red, green and blue ramps of native endian values.
+    pub fn from_ne_bytes(data: &[u8], size: usize) -> Option<Self> {
+        if size == 0 || data.len() != size * 3 * 2 {
+            return None;
+        }
+        let values = data
+            .chunks_exact(2)
+            .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+            .collect::<Vec<_>>();
+        Some(Self {
+            red: values[..size].to_vec(),
+            green: values[size..2 * size].to_vec(),
+            blue: values[2 * size..].to_vec(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.red.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.red.is_empty()
+    }
+
+    /// The value of the channel at `x` in `0..=1`, interpolated between the entries.
+    pub fn sample(&self, channel: usize, x: f32) -> f32 {
+        let ramp = [&self.red, &self.green, &self.blue][channel];
+        if ramp.is_empty() {
+            return x;
+        }
+        let position = x.clamp(0.0, 1.0) * (ramp.len() - 1) as f32;
+        let index = position.floor() as usize;
+        let next = (index + 1).min(ramp.len() - 1);
+        let t = position - index as f32;
+        (ramp[index] as f32 * (1.0 - t) + ramp[next] as f32 * t) / u16::MAX as f32
+    }
+
+    pub fn resample(&self, size: usize) -> Self {
+        Self::from_fn(size, |channel, x| self.sample(channel, x))
+    }
+}
+
+fn blackbody(temperature: u32) -> Vec3 {
+    // the fit of Tanner Helland, in hundreds of kelvin
+    let t = temperature.clamp(1000, 40000) as f32 / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_16 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    (Vec3::new(red, green, blue) / 255.0).clamp(Vec3::ZERO, Vec3::ONE)
+}
+
+/// The factors of the channels which shift white to the color temperature, the neutral
+/// temperature is white and the brightest channel is 1.
+pub fn whitepoint(temperature: u32) -> Vec3 {
+    let white = blackbody(temperature) / blackbody(NEUTRAL_TEMPERATURE);
+    white / white.max_element()
+}
+
+/// The number of entries of the gamma ramps, inserted on the output entity by the backend if it
+/// can apply gamma tables.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub struct GammaSize(pub usize);
+
+/// The gamma table of the client with exclusive gamma control of the output, inserted on the
+/// output entity by the server.
+#[derive(Component, Clone, Debug, PartialEq, Eq, Reflect)]
+pub struct ClientGamma(pub GammaLut);
+
+/// The gamma table the backend applies, outputs without it show the colors unchanged.
+#[derive(Component, Clone, Debug, PartialEq, Eq, Reflect)]
+pub struct OutputGamma(pub GammaLut);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gamma_lut() {
+        let identity = GammaLut::identity(3);
+        assert_eq!(identity.red, vec![0, 32768, 65535]);
+        assert!((identity.sample(0, 0.25) - 0.25).abs() < 1e-4);
+        let resampled = identity.resample(5);
+        assert_eq!(resampled.len(), 5);
+        assert!(resampled.green[1].abs_diff(16384) <= 1);
+        assert_eq!(GammaLut::from_temperature(3, NEUTRAL_TEMPERATURE), identity);
+
+        let data = [0u16, 1, 2, 3, 4, 5]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let lut = GammaLut::from_ne_bytes(&data, 2).unwrap();
+        assert_eq!(
+            (lut.red, lut.green, lut.blue),
+            (vec![0, 1], vec![2, 3], vec![4, 5])
+        );
+        assert!(GammaLut::from_ne_bytes(&data, 3).is_none());
+    }
+
+    #[test]
+    fn test_whitepoint() {
+        assert_eq!(whitepoint(NEUTRAL_TEMPERATURE), Vec3::ONE);
+        let warm = whitepoint(3000);
+        assert_eq!(warm.x, 1.0);
+        assert!(warm.y < 1.0 && warm.z < warm.y);
+        let cold = whitepoint(10000);
+        assert_eq!(cold.z, 1.0);
+        assert!(cold.x < 1.0);
+    }
+}
@@ -9,6 +9,12 @@ pub mod temporary;
 pub mod keys;
 pub mod tokio;
 pub mod formats;
+pub mod output;
+pub mod edid;
+pub mod gamma;
+pub mod color;
+pub mod cursor;
+pub mod scanout;
 mod typed_ecs;
 pub mod render;
 pub mod diagnostic;
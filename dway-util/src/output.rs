@@ -0,0 +1,860 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+use crate::edid::VrrRange;
+
+pub const OUTPUT_CONFIG_FILE: &str = "outputs.ron";
+
+/// A display mode, the refresh rate is in mHz like `wl_output`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Reflect, Serialize, Deserialize)]
+pub struct OutputMode {
+    pub width: i32,
+    pub height: i32,
+    /// 0 means any refresh rate when it is requested by a config
+    pub refresh: u32,
+}
+
+impl OutputMode {
+    pub fn new(width: i32, height: i32, refresh: u32) -> Self {
+        Self {
+            width,
+            height,
+            refresh,
+        }
+    }
+
+    pub fn size(&self) -> IVec2 {
+        IVec2::new(self.width, self.height)
+    }
+}
+
+/// The same values as the transform of `wl_output`, rotations are counter-clockwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Reflect, Serialize, Deserialize)]
+pub enum OutputTransform {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+impl OutputTransform {
+    pub const ALL: [Self; 8] = [
+        Self::Normal,
+        Self::Rotate90,
+        Self::Rotate180,
+        Self::Rotate270,
+        Self::Flipped,
+        Self::Flipped90,
+        Self::Flipped180,
+        Self::Flipped270,
+    ];
+
+    pub fn from_wl(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn to_wl(self) -> u32 {
+        self as u32
+    }
+
+    pub fn is_flipped(self) -> bool {
+        self as u32 >= 4
+    }
+
+    /// The counter-clockwise rotation in degrees.
+    pub fn rotation(self) -> u32 {
+        (self as u32 % 4) * 90
+    }
+
+    pub fn swaps_axes(self) -> bool {
+        self.rotation() % 180 == 90
+    }
+
+    /// The size of the content shown with a mode of `size`.
+    pub fn apply(self, size: IVec2) -> IVec2 {
+        if self.swaps_axes() {
+            IVec2::new(size.y, size.x)
+        } else {
+            size
+        }
+    }
+}
+
+/// What identifies a monitor: the connector it is plugged into and what its EDID tells.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default, Reflect)]
+pub struct OutputIdentity {
+    /// The name of the connector, like `HDMI-A-1`
+    pub connector: String,
+    /// The PNP id of the manufacturer, like `DEL`, the configurations match on it
+    pub make: String,
+    /// The name of the manufacturer shown to users and clients, the PNP id if it is not well
+    /// known
+    pub vendor: String,
+    pub model: String,
+    pub serial: String,
+}
+
+impl OutputIdentity {
+    pub fn has_edid(&self) -> bool {
+        !self.make.is_empty() || !self.model.is_empty()
+    }
+
+    pub fn description(&self) -> String {
+        if self.has_edid() {
+            format!("{} {} ({})", self.vendor, self.model, self.connector)
+        } else {
+            self.connector.clone()
+        }
+    }
+}
+
+/// Which outputs a config applies to, the fields that are `None` match every output.
+#[derive(Clone, Debug, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputMatch {
+    pub connector: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+}
+
+impl OutputMatch {
+    /// Match exactly the monitor, or the connector if the monitor has no EDID.
+    pub fn exact(identity: &OutputIdentity) -> Self {
+        let edid =
+            |value: &String| (identity.has_edid() && !value.is_empty()).then(|| value.clone());
+        Self {
+            connector: Some(identity.connector.clone()),
+            make: edid(&identity.make),
+            model: edid(&identity.model),
+            serial: edid(&identity.serial),
+        }
+    }
+
+    /// How well the output matches, `None` if it does not match.
+    ///
+    /// A monitor matched by its EDID still matches after it is plugged into another connector,
+    /// but with a lower score than on its original connector.
+    pub fn score(&self, identity: &OutputIdentity) -> Option<u32> {
+        let mut score = 0;
+        for (expect, value) in [
+            (&self.make, &identity.make),
+            (&self.model, &identity.model),
+            (&self.serial, &identity.serial),
+        ] {
+            if let Some(expect) = expect {
+                if expect != value {
+                    return None;
+                }
+                score += 2;
+            }
+        }
+        match &self.connector {
+            Some(connector) if connector == &identity.connector => score += 1,
+            Some(_) if score == 0 => return None,
+            _ => {}
+        }
+        Some(score)
+    }
+}
+
+/// When an output runs with a variable refresh rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Reflect, Serialize, Deserialize)]
+pub enum VrrPolicy {
+    #[default]
+    Never,
+    Always,
+    /// Only while a fullscreen window is on top, some monitors flicker with a desktop which
+    /// updates at an uneven rate
+    Fullscreen,
+}
+
+impl VrrPolicy {
+    pub fn is_active(self, fullscreen: bool) -> bool {
+        match self {
+            VrrPolicy::Never => false,
+            VrrPolicy::Always => true,
+            VrrPolicy::Fullscreen => fullscreen,
+        }
+    }
+}
+
+/// The configuration of the matching outputs, the fields that are `None` are chosen automatically.
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize, Deserialize, SmartDefault)]
+#[serde(default)]
+pub struct OutputConfig {
+    pub output: OutputMatch,
+    #[default(true)]
+    pub enabled: bool,
+    pub mode: Option<OutputMode>,
+    /// The position in the global logical coordinate space
+    pub position: Option<(i32, i32)>,
+    pub transform: OutputTransform,
+    #[default(1.0)]
+    pub scale: f32,
+    /// Show the same content as the output with this connector name
+    pub mirror: Option<String>,
+    pub vrr: VrrPolicy,
+    /// Drive the output with HDR signaling if the monitor supports it
+    pub hdr: bool,
+}
+
+impl OutputConfig {
+    pub fn from_state(identity: &OutputIdentity, state: &OutputState) -> Self {
+        Self {
+            output: OutputMatch::exact(identity),
+            enabled: state.enabled,
+            mode: Some(state.mode),
+            position: (state.mirror.is_none()).then_some((state.position.x, state.position.y)),
+            transform: state.transform,
+            scale: state.scale,
+            mirror: state.mirror.clone(),
+            vrr: state.vrr,
+            hdr: state.hdr,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum OutputConfigError {
+    #[error("failed to access the output config file {0:?}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("failed to parse the output config file {0:?}: {1}")]
+    Parse(PathBuf, ron::error::SpannedError),
+    #[error("failed to serialize output configs: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("output {0} does not support the mode {1:?}")]
+    UnsupportedMode(String, OutputMode),
+    #[error("invalid scale {1} of output {0}")]
+    InvalidScale(String, f32),
+    #[error("output {0} cannot mirror {1}")]
+    InvalidMirror(String, String),
+    #[error("output {0} does not support variable refresh rate")]
+    UnsupportedVrr(String),
+    #[error("output {0} does not support hdr")]
+    UnsupportedHdr(String),
+    #[error("output {0} overlaps output {1}")]
+    Overlapping(String, String),
+    #[error("unknown output {0}")]
+    UnknownOutput(String),
+    #[error("no output is enabled")]
+    NoEnabledOutput,
+    #[error("the device rejected the configuration: {0}")]
+    Rejected(String),
+}
+
+/// The output configs, persisted in `$XDG_CONFIG_HOME/dway/outputs.ron`.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfigs {
+    pub outputs: Vec<OutputConfig>,
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl OutputConfigs {
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("dway").join(OUTPUT_CONFIG_FILE))
+    }
+
+    /// Load the configs from `path`, a missing file means no config.
+    pub fn load(path: &Path) -> Result<Self, OutputConfigError> {
+        let mut configs = match fs::read_to_string(path) {
+            Ok(content) => ron::from_str::<Self>(&content)
+                .map_err(|e| OutputConfigError::Parse(path.to_owned(), e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(OutputConfigError::Io(path.to_owned(), e)),
+        };
+        configs.path = Some(path.to_owned());
+        Ok(configs)
+    }
+
+    pub fn save(&self) -> Result<(), OutputConfigError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| OutputConfigError::Io(dir.to_owned(), e))?;
+        }
+        fs::write(path, content).map_err(|e| OutputConfigError::Io(path.clone(), e))
+    }
+
+    /// The config that matches the output best, later configs win on ties.
+    pub fn find(&self, identity: &OutputIdentity) -> Option<&OutputConfig> {
+        self.outputs
+            .iter()
+            .filter_map(|config| config.output.score(identity).map(|score| (score, config)))
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, config)| config)
+    }
+
+    /// Remember the state of an output, replacing the config that matches it exactly.
+    pub fn remember(&mut self, identity: &OutputIdentity, state: &OutputState) {
+        let config = OutputConfig::from_state(identity, state);
+        if let Some(old) = self
+            .outputs
+            .iter_mut()
+            .find(|old| old.output == config.output)
+        {
+            *old = config;
+        } else {
+            self.outputs.push(config);
+        }
+    }
+}
+
+/// The applied state of an output.
+#[derive(Clone, Debug, PartialEq, Reflect, SmartDefault)]
+pub struct OutputState {
+    #[default(true)]
+    pub enabled: bool,
+    pub mode: OutputMode,
+    pub position: IVec2,
+    pub transform: OutputTransform,
+    #[default(1.0)]
+    pub scale: f32,
+    /// The connector name of the mirrored output
+    pub mirror: Option<String>,
+    pub vrr: VrrPolicy,
+    pub hdr: bool,
+}
+
+impl OutputState {
+    pub fn logical_size(&self) -> IVec2 {
+        (self.transform.apply(self.mode.size()).as_vec2() / self.scale)
+            .round()
+            .as_ivec2()
+    }
+
+    pub fn logical_rect(&self) -> IRect {
+        IRect::from_corners(self.position, self.position + self.logical_size())
+    }
+
+    /// Whether applying `other` needs a modeset rather than only moving the output.
+    pub fn needs_modeset(&self, other: &Self) -> bool {
+        self.enabled != other.enabled
+            || self.mode != other.mode
+            || self.transform != other.transform
+    }
+}
+
+/// A connected output and the modes it supports, inserted on the output entity by the backend.
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct OutputHead {
+    pub identity: OutputIdentity,
+    /// The physical size in millimeters
+    pub physical_size: IVec2,
+    pub modes: Vec<OutputMode>,
+    pub preferred_mode: Option<usize>,
+    /// Whether the connector and the monitor support variable refresh rate
+    pub vrr_capable: bool,
+    /// The refresh rate range of the monitor, read from the EDID
+    pub vrr_range: Option<VrrRange>,
+    /// Whether the connector can send HDR metadata and the monitor accepts PQ
+    pub hdr_capable: bool,
+    pub state: OutputState,
+}
+
+impl OutputHead {
+    pub fn name(&self) -> &str {
+        &self.identity.connector
+    }
+
+    pub fn preferred(&self) -> Option<OutputMode> {
+        self.preferred_mode.and_then(|i| self.modes.get(i)).copied()
+    }
+}
+
+/// Choose the mode closest to `requested`, or the preferred mode.
+pub fn select_mode(
+    modes: &[OutputMode],
+    preferred: Option<usize>,
+    requested: Option<OutputMode>,
+) -> Option<OutputMode> {
+    if let Some(requested) = requested {
+        let closest = modes
+            .iter()
+            .filter(|mode| mode.size() == requested.size())
+            .min_by_key(|mode| {
+                if requested.refresh == 0 {
+                    u32::MAX - mode.refresh
+                } else {
+                    mode.refresh.abs_diff(requested.refresh)
+                }
+            });
+        if let Some(mode) = closest {
+            return Some(*mode);
+        }
+    }
+    preferred
+        .and_then(|i| modes.get(i))
+        .or_else(|| {
+            modes
+                .iter()
+                .max_by_key(|mode| (mode.width * mode.height, mode.refresh))
+        })
+        .copied()
+}
+
+/// Resolve the states of new outputs from the configs.
+///
+/// Outputs without a configured position are placed from left to right after the outputs in
+/// `others` and the outputs with a position.
+pub fn resolve_output_states(
+    configs: &OutputConfigs,
+    heads: &[&OutputHead],
+    others: &[&OutputHead],
+) -> Vec<OutputState> {
+    let resolved = heads
+        .iter()
+        .map(|head| {
+            let config = configs.find(&head.identity).cloned().unwrap_or_default();
+            let state = OutputState {
+                enabled: config.enabled,
+                mode: select_mode(&head.modes, head.preferred_mode, config.mode)
+                    .unwrap_or_default(),
+                position: config
+                    .position
+                    .map(|(x, y)| IVec2::new(x, y))
+                    .unwrap_or_default(),
+                transform: config.transform,
+                scale: if config.scale > 0.0 {
+                    config.scale
+                } else {
+                    1.0
+                },
+                mirror: config.mirror.filter(|mirror| mirror != head.name()),
+                vrr: if head.vrr_capable {
+                    config.vrr
+                } else {
+                    VrrPolicy::Never
+                },
+                hdr: head.hdr_capable && config.hdr,
+            };
+            (state, config.position.is_some())
+        })
+        .collect::<Vec<_>>();
+
+    let placed = |state: &OutputState| state.enabled && state.mirror.is_none();
+    let mut right = others
+        .iter()
+        .map(|head| &head.state)
+        .chain(resolved.iter().filter(|(_, fixed)| *fixed).map(|(s, _)| s))
+        .filter(|state| placed(state))
+        .map(|state| state.logical_rect().max.x)
+        .max()
+        .unwrap_or(0);
+    let mut states = resolved
+        .into_iter()
+        .map(|(mut state, fixed)| {
+            if !fixed && placed(&state) {
+                state.position = IVec2::new(right, 0);
+                right += state.logical_size().x;
+            }
+            state
+        })
+        .collect::<Vec<_>>();
+
+    let sources = others
+        .iter()
+        .map(|head| (head.name(), head.state.clone()))
+        .chain(
+            heads
+                .iter()
+                .zip(states.iter())
+                .map(|(head, state)| (head.name(), state.clone())),
+        )
+        .collect::<Vec<_>>();
+    for state in states.iter_mut() {
+        let source = state.mirror.as_ref().and_then(|mirror| {
+            sources
+                .iter()
+                .find(|(name, source)| *name == mirror.as_str() && placed(source))
+        });
+        match source {
+            Some((_, source)) => state.position = source.position,
+            None => state.mirror = None,
+        }
+    }
+    states
+}
+
+/// Check that the states of outputs can be applied together, without asking the device.
+pub fn validate_output_states(
+    states: &[(&OutputHead, &OutputState)],
+) -> Result<(), OutputConfigError> {
+    for (head, state) in states {
+        if !state.enabled {
+            continue;
+        }
+        if !head.modes.contains(&state.mode) {
+            return Err(OutputConfigError::UnsupportedMode(
+                head.name().to_string(),
+                state.mode,
+            ));
+        }
+        if !state.scale.is_finite() || state.scale <= 0.0 {
+            return Err(OutputConfigError::InvalidScale(
+                head.name().to_string(),
+                state.scale,
+            ));
+        }
+        if state.vrr != VrrPolicy::Never && !head.vrr_capable {
+            return Err(OutputConfigError::UnsupportedVrr(head.name().to_string()));
+        }
+        if state.hdr && !head.hdr_capable {
+            return Err(OutputConfigError::UnsupportedHdr(head.name().to_string()));
+        }
+        if let Some(mirror) = &state.mirror {
+            let source = states.iter().find(|(source, _)| source.name() == mirror);
+            if !source.is_some_and(|(source, source_state)| {
+                source.name() != head.name()
+                    && source_state.enabled
+                    && source_state.mirror.is_none()
+            }) {
+                return Err(OutputConfigError::InvalidMirror(
+                    head.name().to_string(),
+                    mirror.clone(),
+                ));
+            }
+        }
+    }
+    let placed = states
+        .iter()
+        .filter(|(_, state)| state.enabled && state.mirror.is_none())
+        .collect::<Vec<_>>();
+    if placed.is_empty() {
+        return Err(OutputConfigError::NoEnabledOutput);
+    }
+    for (i, (head, state)) in placed.iter().enumerate() {
+        for (other_head, other_state) in &placed[i + 1..] {
+            if !state
+                .logical_rect()
+                .intersect(other_state.logical_rect())
+                .is_empty()
+            {
+                return Err(OutputConfigError::Overlapping(
+                    head.name().to_string(),
+                    other_head.name().to_string(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Inserted on the output entity by the compositor while a fullscreen window is on top.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+pub struct FullscreenContent;
+
+/// Inserted on the output entity by the backend while it runs with a variable refresh rate.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+pub struct VrrActive;
+
+/// The power mode of an output, inserted on the output entity by backends which can turn the
+/// monitor off. A powered off output keeps its configuration, but nothing is rendered to it.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum OutputPower {
+    #[default]
+    On,
+    Off,
+}
+
+impl OutputPower {
+    pub fn is_on(self) -> bool {
+        self == Self::On
+    }
+}
+
+/// Request to change the states of outputs, the outputs are the entities with [`OutputHead`].
+#[derive(Message, Clone, Debug)]
+pub struct OutputConfigRequest {
+    pub states: Vec<(Entity, OutputState)>,
+    pub test_only: bool,
+    /// Receives the [`OutputConfigResult`]
+    pub sender: Entity,
+}
+
+#[derive(Message, Clone, Debug)]
+pub struct OutputConfigResult {
+    pub sender: Entity,
+    pub result: Result<(), String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn head(connector: &str, make: &str, serial: &str) -> OutputHead {
+        OutputHead {
+            identity: OutputIdentity {
+                connector: connector.to_string(),
+                make: make.to_string(),
+                vendor: make.to_string(),
+                model: if make.is_empty() { "" } else { "Monitor" }.to_string(),
+                serial: serial.to_string(),
+            },
+            physical_size: IVec2::new(600, 340),
+            modes: vec![
+                OutputMode::new(1920, 1080, 60000),
+                OutputMode::new(1920, 1080, 144000),
+                OutputMode::new(2560, 1440, 60000),
+                OutputMode::new(1280, 720, 60000),
+            ],
+            preferred_mode: Some(0),
+            vrr_capable: false,
+            vrr_range: None,
+            hdr_capable: false,
+            state: OutputState::default(),
+        }
+    }
+
+    #[test]
+    fn test_select_mode() {
+        let head = head("DP-1", "", "");
+        let select = |requested| select_mode(&head.modes, head.preferred_mode, requested);
+        assert_eq!(select(None), Some(OutputMode::new(1920, 1080, 60000)));
+        assert_eq!(
+            select(Some(OutputMode::new(1920, 1080, 0))),
+            Some(OutputMode::new(1920, 1080, 144000))
+        );
+        assert_eq!(
+            select(Some(OutputMode::new(1920, 1080, 120000))),
+            Some(OutputMode::new(1920, 1080, 144000))
+        );
+        assert_eq!(
+            select(Some(OutputMode::new(800, 600, 60000))),
+            Some(OutputMode::new(1920, 1080, 60000))
+        );
+        assert_eq!(
+            select_mode(&head.modes, None, None),
+            Some(OutputMode::new(2560, 1440, 60000))
+        );
+    }
+
+    #[test]
+    fn test_match_identity() {
+        let monitor = head("DP-1", "DEL", "1234").identity;
+        let moved = OutputIdentity {
+            connector: "DP-2".to_string(),
+            ..monitor.clone()
+        };
+        let exact = OutputMatch::exact(&monitor);
+        assert_eq!(exact.score(&monitor), Some(7));
+        assert_eq!(exact.score(&moved), Some(6));
+        let other = OutputIdentity {
+            serial: "5678".to_string(),
+            ..monitor.clone()
+        };
+        assert_eq!(exact.score(&other), None);
+
+        let by_connector = OutputMatch {
+            connector: Some("DP-1".to_string()),
+            ..default()
+        };
+        assert_eq!(by_connector.score(&monitor), Some(1));
+        assert_eq!(by_connector.score(&moved), None);
+        assert_eq!(OutputMatch::default().score(&moved), Some(0));
+
+        let without_edid = head("eDP-1", "", "").identity;
+        assert_eq!(
+            OutputMatch::exact(&without_edid),
+            OutputMatch {
+                connector: Some("eDP-1".to_string()),
+                ..default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_states() {
+        let laptop = head("eDP-1", "", "");
+        let monitor = head("DP-1", "DEL", "1234");
+        let projector = head("HDMI-A-1", "EPS", "");
+
+        let mut configs = OutputConfigs::default();
+        configs.outputs.push(OutputConfig {
+            output: OutputMatch::exact(&monitor.identity),
+            mode: Some(OutputMode::new(2560, 1440, 0)),
+            transform: OutputTransform::Rotate90,
+            scale: 2.0,
+            ..default()
+        });
+        configs.outputs.push(OutputConfig {
+            output: OutputMatch::exact(&projector.identity),
+            mirror: Some("eDP-1".to_string()),
+            ..default()
+        });
+
+        let states = resolve_output_states(&configs, &[&laptop, &monitor, &projector], &[]);
+        assert_eq!(states[0].position, IVec2::ZERO);
+        assert_eq!(states[1].mode, OutputMode::new(2560, 1440, 60000));
+        assert_eq!(states[1].position, IVec2::new(1920, 0));
+        assert_eq!(states[1].logical_size(), IVec2::new(720, 1280));
+        assert_eq!(states[2].mirror.as_deref(), Some("eDP-1"));
+        assert_eq!(states[2].position, states[0].position);
+
+        let heads = [&laptop, &monitor, &projector];
+        let pairs = heads.iter().copied().zip(states.iter()).collect::<Vec<_>>();
+        validate_output_states(&pairs).unwrap();
+
+        let mut laptop = laptop.clone();
+        laptop.state = states[0].clone();
+        let plugged = resolve_output_states(&configs, &[&monitor], &[&laptop]);
+        assert_eq!(plugged[0].position, IVec2::new(1920, 0));
+
+        configs.remember(
+            &monitor.identity,
+            &OutputState {
+                position: IVec2::new(-720, 0),
+                ..plugged[0].clone()
+            },
+        );
+        assert_eq!(configs.outputs.len(), 2);
+        let moved = resolve_output_states(&configs, &[&monitor], &[&laptop]);
+        assert_eq!(moved[0].position, IVec2::new(-720, 0));
+    }
+
+    #[test]
+    fn test_validate_output_states() {
+        let laptop = head("eDP-1", "", "");
+        let monitor = head("DP-1", "DEL", "1234");
+        let state = OutputState {
+            mode: OutputMode::new(1920, 1080, 60000),
+            ..default()
+        };
+        let overlapping = OutputState {
+            position: IVec2::new(1000, 0),
+            ..state.clone()
+        };
+        assert!(matches!(
+            validate_output_states(&[(&laptop, &state), (&monitor, &overlapping)]),
+            Err(OutputConfigError::Overlapping(..))
+        ));
+        let disabled = OutputState {
+            enabled: false,
+            ..state.clone()
+        };
+        assert!(matches!(
+            validate_output_states(&[(&laptop, &disabled)]),
+            Err(OutputConfigError::NoEnabledOutput)
+        ));
+        let unsupported = OutputState {
+            mode: OutputMode::new(3840, 2160, 60000),
+            ..state.clone()
+        };
+        assert!(matches!(
+            validate_output_states(&[(&laptop, &unsupported)]),
+            Err(OutputConfigError::UnsupportedMode(..))
+        ));
+        let mirror = OutputState {
+            mirror: Some("DP-1".to_string()),
+            ..state.clone()
+        };
+        assert!(matches!(
+            validate_output_states(&[(&laptop, &mirror), (&monitor, &disabled)]),
+            Err(OutputConfigError::InvalidMirror(..))
+        ));
+        assert!(validate_output_states(&[(&laptop, &mirror), (&monitor, &overlapping)]).is_ok());
+    }
+
+    #[test]
+    fn test_vrr_policy() {
+        let laptop = head("eDP-1", "", "");
+        let monitor = OutputHead {
+            vrr_capable: true,
+            vrr_range: Some(VrrRange { min: 48, max: 144 }),
+            ..head("DP-1", "DEL", "1234")
+        };
+        let mut configs = OutputConfigs::default();
+        configs.outputs.push(OutputConfig {
+            vrr: VrrPolicy::Fullscreen,
+            ..default()
+        });
+        let states = resolve_output_states(&configs, &[&laptop, &monitor], &[]);
+        assert_eq!(states[0].vrr, VrrPolicy::Never);
+        assert_eq!(states[1].vrr, VrrPolicy::Fullscreen);
+        assert!(!states[1].needs_modeset(&OutputState {
+            vrr: VrrPolicy::Always,
+            ..states[1].clone()
+        }));
+
+        let always = OutputState {
+            vrr: VrrPolicy::Always,
+            ..states[0].clone()
+        };
+        assert!(matches!(
+            validate_output_states(&[(&laptop, &always), (&monitor, &states[1])]),
+            Err(OutputConfigError::UnsupportedVrr(..))
+        ));
+        assert!(validate_output_states(&[(&laptop, &states[0]), (&monitor, &states[1])]).is_ok());
+
+        assert!(!VrrPolicy::Never.is_active(true));
+        assert!(VrrPolicy::Always.is_active(false));
+        assert!(!VrrPolicy::Fullscreen.is_active(false));
+        assert!(VrrPolicy::Fullscreen.is_active(true));
+    }
+
+    #[test]
+    fn test_hdr_config() {
+        let laptop = head("eDP-1", "", "");
+        let monitor = OutputHead {
+            hdr_capable: true,
+            ..head("DP-1", "DEL", "1234")
+        };
+        let mut configs = OutputConfigs::default();
+        configs.outputs.push(OutputConfig {
+            hdr: true,
+            ..default()
+        });
+        let states = resolve_output_states(&configs, &[&laptop, &monitor], &[]);
+        assert!(!states[0].hdr);
+        assert!(states[1].hdr);
+
+        let hdr = OutputState {
+            hdr: true,
+            ..states[0].clone()
+        };
+        assert!(matches!(
+            validate_output_states(&[(&laptop, &hdr), (&monitor, &states[1])]),
+            Err(OutputConfigError::UnsupportedHdr(..))
+        ));
+        assert!(validate_output_states(&[(&laptop, &states[0]), (&monitor, &states[1])]).is_ok());
+    }
+
+    #[test]
+    fn test_output_configs_file() {
+        let dir = std::env::temp_dir().join(format!("dway-output-test-{}", std::process::id()));
+        let path = dir.join(OUTPUT_CONFIG_FILE);
+        let monitor = head("DP-1", "DEL", "1234");
+        let mut configs = OutputConfigs::load(&path).unwrap();
+        assert!(configs.outputs.is_empty());
+        configs.remember(
+            &monitor.identity,
+            &OutputState {
+                mode: OutputMode::new(1920, 1080, 144000),
+                transform: OutputTransform::Flipped270,
+                ..default()
+            },
+        );
+        configs.save().unwrap();
+        let loaded = OutputConfigs::load(&path).unwrap();
+        assert_eq!(loaded.outputs, configs.outputs);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
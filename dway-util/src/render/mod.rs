@@ -14,6 +14,7 @@ use wgpu::{
 };
 
 pub mod gles;
+pub mod post_process;
 pub mod vulkan;
 
 pub fn save_image(image: &RgbaImage, label: &str) {
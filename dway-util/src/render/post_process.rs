@@ -0,0 +1,216 @@
+//! Full screen passes which read the image of a camera and write it back through a fragment
+//! shader, such as the conversion to the colors of an output or the emulated gamma tables.
+
+use std::marker::PhantomData;
+
+use bevy::{
+    core_pipeline::FullscreenShader,
+    ecs::{query::QueryItem, system::lifetimeless::Read},
+    image::BevyDefault,
+    prelude::*,
+    render::{
+        extract_component::{ComponentUniforms, DynamicUniformIndex, UniformComponentPlugin},
+        render_graph::{NodeRunError, RenderGraphContext, ViewNode},
+        render_resource::{
+            binding_types::{sampler, texture_2d, uniform_buffer},
+            encase::internal::WriteInto,
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, FilterMode, FragmentState, Operations, PipelineCache,
+            RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor, Sampler,
+            SamplerBindingType, SamplerDescriptor, ShaderStages, ShaderType,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, TextureFormat,
+            TextureSampleType,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::{ExtractedView, ViewTarget},
+        Render, RenderApp, RenderStartup, RenderSystems,
+    },
+};
+
+/// The uniform of a post processing pass, extracted to the render world for each camera which
+/// runs the pass.
+pub trait PostProcessUniform: Component + ShaderType + WriteInto + Clone {
+    /// The label of the pipeline and the render pass
+    const LABEL: &'static str;
+    /// The fragment shader, it samples the texture of binding 0 with the sampler of binding 1 and
+    /// reads the uniform from binding 2
+    const SHADER: Handle<Shader>;
+}
+
+#[derive(Resource)]
+pub struct PostProcessPipeline<U> {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    fullscreen_shader: FullscreenShader,
+    marker: PhantomData<U>,
+}
+
+impl<U: PostProcessUniform> SpecializedRenderPipeline for PostProcessPipeline<U> {
+    type Key = TextureFormat;
+
+    fn specialize(&self, format: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some(U::LABEL.into()),
+            layout: vec![self.layout.clone()],
+            vertex: self.fullscreen_shader.to_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: U::SHADER,
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+                ..default()
+            }),
+            ..default()
+        }
+    }
+}
+
+fn init_post_process_pipeline<U: PostProcessUniform>(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    fullscreen_shader: Res<FullscreenShader>,
+) {
+    let layout = render_device.create_bind_group_layout(
+        Some(U::LABEL),
+        &BindGroupLayoutEntries::sequential(
+            ShaderStages::FRAGMENT,
+            (
+                texture_2d(TextureSampleType::Float { filterable: true }),
+                sampler(SamplerBindingType::Filtering),
+                uniform_buffer::<U>(true),
+            ),
+        ),
+    );
+    let sampler = render_device.create_sampler(&SamplerDescriptor {
+        min_filter: FilterMode::Nearest,
+        mag_filter: FilterMode::Nearest,
+        ..default()
+    });
+    commands.insert_resource(PostProcessPipeline::<U> {
+        layout,
+        sampler,
+        fullscreen_shader: fullscreen_shader.clone(),
+        marker: PhantomData,
+    });
+}
+
+#[derive(Component)]
+pub struct PostProcessPipelineId<U> {
+    id: CachedRenderPipelineId,
+    marker: PhantomData<U>,
+}
+
+fn prepare_post_process_pipelines<U: PostProcessUniform>(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<PostProcessPipeline<U>>>,
+    post_process_pipeline: Res<PostProcessPipeline<U>>,
+    views: Query<(Entity, &ExtractedView), With<U>>,
+) {
+    for (entity, view) in &views {
+        let format = if view.hdr {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+        let id = pipelines.specialize(&pipeline_cache, &post_process_pipeline, format);
+        commands.entity(entity).insert(PostProcessPipelineId::<U> {
+            id,
+            marker: PhantomData,
+        });
+    }
+}
+
+/// The render graph node of the pass, the users add it to the graph where the pass runs.
+pub struct PostProcessNode<U>(PhantomData<U>);
+
+impl<U> Default for PostProcessNode<U> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<U: PostProcessUniform> ViewNode for PostProcessNode<U> {
+    type ViewQuery = (
+        Read<ViewTarget>,
+        Read<PostProcessPipelineId<U>>,
+        Read<DynamicUniformIndex<U>>,
+    );
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (view_target, pipeline_id, uniform_index): QueryItem<'w, '_, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let post_process_pipeline = world.resource::<PostProcessPipeline<U>>();
+        let Some(pipeline) = pipeline_cache.get_render_pipeline(pipeline_id.id) else {
+            return Ok(());
+        };
+        let Some(uniforms) = world
+            .resource::<ComponentUniforms<U>>()
+            .uniforms()
+            .binding()
+        else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            Some(U::LABEL),
+            &post_process_pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &post_process_pipeline.sampler,
+                uniforms,
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some(U::LABEL),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                depth_slice: None,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[uniform_index.index()]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+/// Prepare the pipelines of the pass for the cameras with the uniform `U`. The uniform is
+/// extracted and the [`PostProcessNode`] is added to the render graph by the users.
+pub struct PostProcessPlugin<U>(PhantomData<U>);
+
+impl<U> Default for PostProcessPlugin<U> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<U: PostProcessUniform> Plugin for PostProcessPlugin<U> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(UniformComponentPlugin::<U>::default());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<SpecializedRenderPipelines<PostProcessPipeline<U>>>()
+            .add_systems(RenderStartup, init_post_process_pipeline::<U>)
+            .add_systems(
+                Render,
+                prepare_post_process_pipelines::<U>.in_set(RenderSystems::Prepare),
+            );
+    }
+}
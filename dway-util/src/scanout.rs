@@ -0,0 +1,121 @@
+//! Client buffers that outputs show without composition.
+
+use std::{os::fd::OwnedFd, sync::Arc};
+
+use bevy::prelude::*;
+use drm_fourcc::{DrmFormat, DrmModifier};
+
+/// A plane of a dmabuf, the fd is duplicated from the one sent by the client.
+#[derive(Debug)]
+pub struct DmabufPlane {
+    pub fd: OwnedFd,
+    pub offset: u32,
+    pub stride: u32,
+}
+
+/// A client dmabuf that may be attached to a drm plane, inserted on the buffer entity.
+#[derive(Component, Clone, Debug)]
+pub struct ScanoutBuffer {
+    pub size: IVec2,
+    pub format: DrmFormat,
+    pub planes: Arc<Vec<DmabufPlane>>,
+}
+
+impl ScanoutBuffer {
+    /// Whether a plane supporting `formats` can show the buffer.
+    pub fn is_supported_by<'a>(&self, mut formats: impl Iterator<Item = &'a DrmFormat>) -> bool {
+        formats.any(|format| {
+            format.code == self.format.code
+                && (format.modifier == self.format.modifier
+                    || (self.format.modifier == DrmModifier::Invalid
+                        && format.modifier == DrmModifier::Linear))
+        })
+    }
+}
+
+/// A surface that may bypass composition.
+#[derive(Clone, Debug)]
+pub struct ScanoutCandidate {
+    pub window: Entity,
+    pub buffer_entity: Entity,
+    pub buffer: ScanoutBuffer,
+    /// The source rectangle in buffer pixels
+    pub src: Rect,
+    /// The destination rectangle in output pixels
+    pub dest: IRect,
+}
+
+/// The candidates of an output from top to bottom, inserted on the output entity by the compositor.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ScanoutCandidates(pub Vec<ScanoutCandidate>);
+
+/// Marks an output whose compositor UI is drawn above the topmost window, such as panels, popups
+/// or the software cursor. Its windows are not offered for direct scanout, which would hide the UI.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+pub struct ScreenOverlay;
+
+/// The windows attached to planes, inserted on the output entity by the backend.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct ScanoutState {
+    /// The window shown on the primary plane instead of the composited image
+    pub primary: Option<Entity>,
+    /// The windows shown on overlay planes above the composited image
+    pub overlays: Vec<Entity>,
+}
+
+impl ScanoutState {
+    pub fn contains(&self, window: Entity) -> bool {
+        self.primary == Some(window) || self.overlays.contains(&window)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primary.is_none() && self.overlays.is_empty()
+    }
+}
+
+/// The formats the planes of the outputs can scan out, advertised in the dmabuf feedback.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanoutFormats {
+    /// The device number of the drm device
+    pub device: Option<u64>,
+    pub formats: Vec<DrmFormat>,
+}
+
+#[cfg(test)]
+mod test {
+    use drm_fourcc::DrmFourcc;
+
+    use super::*;
+
+    #[test]
+    fn test_scanout_buffer_supported() {
+        let buffer = ScanoutBuffer {
+            size: IVec2::new(1920, 1080),
+            format: DrmFormat {
+                code: DrmFourcc::Xrgb8888,
+                modifier: DrmModifier::Invalid,
+            },
+            planes: Default::default(),
+        };
+        let linear = DrmFormat {
+            code: DrmFourcc::Xrgb8888,
+            modifier: DrmModifier::Linear,
+        };
+        let argb = DrmFormat {
+            code: DrmFourcc::Argb8888,
+            modifier: DrmModifier::Linear,
+        };
+        assert!(buffer.is_supported_by([argb, linear].iter()));
+        assert!(!buffer.is_supported_by([argb].iter()));
+
+        let tiled = ScanoutBuffer {
+            format: DrmFormat {
+                code: DrmFourcc::Xrgb8888,
+                modifier: DrmModifier::I915_x_tiled,
+            },
+            ..buffer
+        };
+        assert!(!tiled.is_supported_by([linear].iter()));
+        assert!(tiled.is_supported_by([tiled.format].iter()));
+    }
+}
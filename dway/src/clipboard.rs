@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use dway_server::clipboard::{ClipboardEvent, ClipboardManager, ClipboardSource};
+use dway_ui_framework::text::clipboard::UiClipboard;
+
+/// Keeps the clipboard of the compositor ui in sync with the clipboard of the clients.
+pub fn sync_clipboard(
+    clipboard: Res<UiClipboard>,
+    mut events: MessageReader<ClipboardEvent>,
+    manager: Res<ClipboardManager>,
+    source_query: Query<(), With<ClipboardSource>>,
+    mut commands: Commands,
+) {
+    if clipboard.is_changed() && !clipboard.is_added() {
+        let text = clipboard.text().to_string();
+        commands.queue(move |world: &mut World| {
+            ClipboardManager::add_text(world, &text);
+        });
+    }
+
+    for event in events.read() {
+        let record_entity = match event {
+            ClipboardEvent::SourceAdded(record_entity) if source_query.contains(*record_entity) => {
+                *record_entity
+            }
+            ClipboardEvent::SourceMimeTypeReady { record_entity, .. }
+                if manager.records.back() == Some(record_entity) =>
+            {
+                *record_entity
+            }
+            _ => continue,
+        };
+        commands.queue(move |world: &mut World| {
+            if let Some(text) = ClipboardManager::fetch_text(world, record_entity) {
+                // not a change made in the ui, so it is not sent back to the clients
+                world
+                    .resource_mut::<UiClipboard>()
+                    .bypass_change_detection()
+                    .set_text(text);
+            }
+        });
+    }
+}
@@ -2,12 +2,20 @@ use std::f32::consts::PI;
 
 use bevy::{
     app::AppExit,
-    input::mouse::{MouseButtonInput, MouseMotion},
+    input::{
+        keyboard::NativeKeyCode,
+        mouse::{MouseButtonInput, MouseMotion},
+    },
     prelude::*,
     winit::WinitSettings,
 };
 use bevy_relationship::{graph_query2, ControlFlow};
 use dway_client_core::{
+    controller::{
+        brightness::{BacklightKind, BrightnessController, BrightnessRequest, BrightnessSettings},
+        player::{PlayerAction, PlayerRequest},
+        volume::VolumeController,
+    },
     desktop::{CursorOnScreen, CursorOnWindow, FocusedWindow},
     layout::tile::{TileLayoutKind, TileLayoutSet},
     navigation::windowstack::WindowStack,
@@ -19,6 +27,8 @@ use dway_server::{
     prelude::WindowAction,
     xdg::toplevel::DWayToplevel,
 };
+use dway_ui::widgets::osd::OsdRequest;
+use dway_util::keys::{KEY_BRIGHTNESSDOWN, KEY_BRIGHTNESSUP, KEY_KBDILLUMDOWN, KEY_KBDILLUMUP};
 
 graph_query2! {
 WmGraph=>
@@ -169,6 +179,71 @@ pub fn wm_keys(
     }
 }
 
+pub fn media_keys(
+    input: Res<ButtonInput<KeyCode>>,
+    mut player_request: MessageWriter<PlayerRequest>,
+) {
+    for (key, action) in [
+        (KeyCode::MediaPlayPause, PlayerAction::PlayPause),
+        (KeyCode::MediaStop, PlayerAction::Stop),
+        (KeyCode::MediaTrackNext, PlayerAction::Next),
+        (KeyCode::MediaTrackPrevious, PlayerAction::Previous),
+    ] {
+        if input.just_pressed(key) {
+            player_request.write(PlayerRequest::active(action));
+        }
+    }
+}
+
+pub fn volume_keys(
+    input: Res<ButtonInput<KeyCode>>,
+    mut volume_controller: NonSendMut<VolumeController>,
+    mut osd_request: MessageWriter<OsdRequest>,
+) {
+    let result = if input.just_pressed(KeyCode::AudioVolumeMute) {
+        let mute = !volume_controller.is_mute();
+        volume_controller.set_mute(mute)
+    } else if input.just_pressed(KeyCode::AudioVolumeUp) {
+        let volume = (volume_controller.volume() + 0.05).min(1.0);
+        volume_controller.set_volume(volume)
+    } else if input.just_pressed(KeyCode::AudioVolumeDown) {
+        let volume = (volume_controller.volume() - 0.05).max(0.0);
+        volume_controller.set_volume(volume)
+    } else {
+        return;
+    };
+    if let Err(e) = result {
+        error!("failed to change the volume: {e}");
+        return;
+    }
+    osd_request.write(OsdRequest::volume(
+        volume_controller.volume(),
+        volume_controller.is_mute(),
+    ));
+}
+
+pub fn brightness_keys(
+    input: Res<ButtonInput<KeyCode>>,
+    brightness_controller: Res<BrightnessController>,
+    settings: Res<BrightnessSettings>,
+    mut brightness_request: MessageWriter<BrightnessRequest>,
+    mut osd_request: MessageWriter<OsdRequest>,
+) {
+    for (key, kind, delta) in [
+        (KEY_BRIGHTNESSUP, BacklightKind::Display, settings.step),
+        (KEY_BRIGHTNESSDOWN, BacklightKind::Display, -settings.step),
+        (KEY_KBDILLUMUP, BacklightKind::Keyboard, settings.step),
+        (KEY_KBDILLUMDOWN, BacklightKind::Keyboard, -settings.step),
+    ] {
+        if input.just_pressed(KeyCode::Unidentified(NativeKeyCode::Xkb(key))) {
+            if let Some(value) = brightness_controller.increased(kind, delta) {
+                brightness_request.write(BrightnessRequest::Set { kind, value });
+                osd_request.write(OsdRequest::brightness(kind, value));
+            }
+        }
+    }
+}
+
 pub fn wm_mouse_action(
     keys: Res<ButtonInput<KeyCode>>,
     mut mouse_motion: MessageReader<MouseMotion>,
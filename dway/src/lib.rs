@@ -1,10 +1,11 @@
 #![feature(stmt_expr_attributes)]
+pub mod clipboard;
 pub mod debug;
 pub mod keys;
 pub mod opttions;
 pub mod spawn_app;
 
-use std::time::Duration;
+use std::{path::Path, time::Duration};
 
 use bevy::{
     app::PluginGroupBuilder,
@@ -32,7 +33,7 @@ use dway_client_core::{
 };
 use dway_server::apps::{icon::LinuxIconSourcePlugin, launchapp::RunCommandRequest};
 use dway_tty::{DWayTTYPlugin, DWayTTYSettings};
-use dway_ui_framework::diagnostics::UiDiagnosticsPlugin;
+use dway_ui_framework::{diagnostics::UiDiagnosticsPlugin, theme::asset::ThemeFile};
 use dway_util::{
     diagnostic::ChangedDiagnosticPlugin,
     logger::{log_layer, DWayLogPlugin},
@@ -92,6 +93,12 @@ pub fn init_app(app: &mut App, mut default_plugins: PluginGroupBuilder) {
     });
 
     let opts = DWayOption::parse();
+    // the renderer is created with the default plugins, the error is logged once the log plugin
+    // is added
+    let select_gpu_result = opts.gpu.clone().map(|gpu| {
+        let result = dway_tty::drm::gpu::select_render_gpu(Path::new(&gpu));
+        (gpu, result)
+    });
     app.insert_resource(opts.clone());
     app.insert_resource(ClearColor(Color::NONE));
     app.insert_resource(Time::<Fixed>::from_hz(20.0));
@@ -148,6 +155,9 @@ pub fn init_app(app: &mut App, mut default_plugins: PluginGroupBuilder) {
 
     app.insert_resource(Time::<Virtual>::from_max_delta(Duration::from_secs(1)))
         .add_plugins(default_plugins);
+    if let Some((gpu, Err(e))) = select_gpu_result {
+        error!("failed to select the render gpu {gpu:?}: {e}");
+    }
 
     let use_tty = std::env::var("DISPLAY").is_err() && std::env::var("WAYLAND_DISPLAY").is_err();
     app.insert_resource(DWayClientSetting {
@@ -181,6 +191,7 @@ pub fn init_app(app: &mut App, mut default_plugins: PluginGroupBuilder) {
         app.add_plugins((
             WinitPlugin::<WakeUp>::default(),
             dway_util::eventloop::EventLoopPlugin::default(),
+            dway_client_core::gamma::GammaEmulationPlugin,
             // bevy_framepace::FramepacePlugin,
         ));
     }
@@ -215,7 +226,18 @@ pub fn init_app(app: &mut App, mut default_plugins: PluginGroupBuilder) {
     ));
 
     app.add_systems(Startup, setup);
-    app.add_systems(Update, (wm_mouse_action, wm_keys, update));
+    app.add_systems(
+        Update,
+        (
+            wm_mouse_action,
+            wm_keys,
+            media_keys,
+            volume_keys,
+            brightness_keys,
+            clipboard::sync_clipboard,
+            update,
+        ),
+    );
     app.add_systems(Last, last);
 
     if cfg!(feature = "single_thread") {
@@ -265,8 +287,12 @@ pub fn setup(
     mut commands: Commands,
     mut app_model: ResMut<AppListModel>,
     opts: Res<DWayOption>,
+    asset_server: Res<AssetServer>,
     mut run_command_request_sender: MessageWriter<RunCommandRequest>,
 ) {
+    if let Some(theme) = &opts.theme {
+        commands.insert_resource(ThemeFile::new(asset_server.load(theme.clone())));
+    }
     commands
         .spawn((WorkspaceSet, Name::from("WorkspaceSet")))
         .with_children(|c| {
@@ -11,6 +11,12 @@ pub struct DWayOption {
     pub frame_rate: f32,
     #[arg(long, default_value_t = String::from("../dway/assets"))]
     pub assets: String,
+    /// a `*.theme.ron` file in the assets directory
+    #[arg(long)]
+    pub theme: Option<String>,
+    /// the drm device to render with on the tty, like `/dev/dri/card1`
+    #[arg(long)]
+    pub gpu: Option<String>,
     #[arg(short, long, allow_hyphen_values = true, num_args = 0..)]
     pub exec: Vec<String>,
 }